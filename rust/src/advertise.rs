@@ -0,0 +1,113 @@
+/// Tools advertised to each client
+///
+/// `tools/list` leaves out tools a client has no use for: on a host with
+/// no display `computer` can only fail, and `browser` needs node with
+/// Playwright's browsers downloaded. The host is probed once; config then
+/// hides or shows tools for every client and per client, keyed by the
+/// name the client sends in `clientInfo` at initialize. Hiding only
+/// shortens the list, calls to a hidden tool still run.
+
+use crate::config::AdvertiseConfig;
+use serde_json::Value;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Default)]
+pub struct Advertiser {
+    config: AdvertiseConfig,
+    /// Tools the host cannot serve, with why
+    unavailable: Vec<(&'static str, &'static str)>,
+}
+
+impl Advertiser {
+    pub fn new(config: AdvertiseConfig) -> Self {
+        let mut unavailable = Vec::new();
+        if config.auto {
+            if headless() {
+                unavailable.push(("computer", "no display"));
+            }
+            if !browser_installed() {
+                unavailable.push(("browser", "node or Playwright browsers not installed"));
+            }
+        }
+        Self::with_unavailable(config, unavailable)
+    }
+
+    fn with_unavailable(config: AdvertiseConfig, unavailable: Vec<(&'static str, &'static str)>) -> Self {
+        for (tool, reason) in &unavailable {
+            log::info!("Not advertising {}: {}", tool, reason);
+        }
+        Self { config, unavailable }
+    }
+
+    /// Tools to leave out of the list for a client, given the params it
+    /// sent to initialize (null when unknown). `canonical` maps configured
+    /// names, which may be aliases, to tool names.
+    pub fn hidden(&self, client: &Value, canonical: impl Fn(&str) -> String) -> HashSet<String> {
+        let mut hidden: HashSet<String> = self.unavailable.iter().map(|(tool, _)| tool.to_string()).collect();
+        let mut apply = |hide: &[String], show: &[String]| {
+            hidden.extend(hide.iter().map(|tool| canonical(tool)));
+            for tool in show {
+                hidden.remove(&canonical(tool));
+            }
+        };
+        apply(&self.config.hide, &self.config.show);
+        if let Some(name) = client["clientInfo"]["name"].as_str() {
+            let rules = self.config.clients.iter().filter(|(key, _)| key.eq_ignore_ascii_case(name));
+            for (_, rule) in rules {
+                apply(&rule.hide, &rule.show);
+            }
+        }
+        hidden
+    }
+}
+
+/// No display server to drive: Linux and the BSDs without X11 or Wayland
+fn headless() -> bool {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return false;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY"].iter().all(|var| std::env::var_os(var).is_none_or(|v| v.is_empty()))
+}
+
+/// node on PATH and a Playwright browser cache, where `playwright install` puts them
+fn browser_installed() -> bool {
+    let browsers = std::env::var_os("PLAYWRIGHT_BROWSERS_PATH")
+        .map(Into::into)
+        .or_else(|| dirs::cache_dir().map(|dir| dir.join("ms-playwright")));
+    on_path("node") && browsers.is_some_and(|dir| dir.is_dir())
+}
+
+fn on_path(program: &str) -> bool {
+    let Some(path) = std::env::var_os("PATH") else { return false };
+    let file = format!("{}{}", program, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&path).any(|dir| dir.join(&file).is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClientTools;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_hidden() {
+        let config = AdvertiseConfig {
+            hide: vec!["k8s".into()],
+            clients: HashMap::from([
+                ("Cursor".into(), ClientTools { show: vec!["computer".into()], hide: vec!["browser".into()] }),
+            ]),
+            ..Default::default()
+        };
+        let advertiser = Advertiser::with_unavailable(config, vec![("computer", "no display")]);
+        let anyone = advertiser.hidden(&Value::Null, str::to_string);
+        assert!(anyone.contains("computer") && anyone.contains("k8s"));
+        assert!(!anyone.contains("browser"));
+        let cursor = advertiser.hidden(&json!({ "clientInfo": { "name": "cursor", "version": "1.0" } }), str::to_string);
+        assert!(!cursor.contains("computer"));
+        assert!(cursor.contains("browser") && cursor.contains("k8s"));
+
+        let shown = AdvertiseConfig { show: vec!["computer".into()], ..Default::default() };
+        assert!(Advertiser::with_unavailable(shown, vec![("computer", "no display")]).hidden(&Value::Null, str::to_string).is_empty());
+    }
+}
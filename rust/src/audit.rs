@@ -0,0 +1,225 @@
+/// Append-only audit log of tool calls
+///
+/// Every call is written as one JSON line (time, session, tool, action,
+/// params with secrets masked and bulky text summarized, duration and
+/// outcome) to a file rotated like the server log. The `audit` tool reads
+/// it back, newest files last, filtered by session, tool or outcome.
+
+use crate::config::AuditConfig;
+use crate::error::ToolError;
+use crate::hooks::ToolCall;
+use crate::redaction::{self, Redactor, REDACTED};
+use crate::logging::{RollingFile, Rotation};
+use crate::ToolResult;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Longest string param written as is
+const MAX_PARAM_CHARS: usize = 512;
+
+/// Entries `query` returns unless told otherwise
+const DEFAULT_LIMIT: usize = 100;
+
+pub fn default_audit_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hanzo-mcp")
+        .join("audit")
+        .join("audit.jsonl")
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    max_files: usize,
+    file: Mutex<RollingFile>,
+    redactor: Option<Arc<Redactor>>,
+}
+
+impl AuditLog {
+    pub fn open(config: &AuditConfig) -> Result<Self> {
+        let path = config.file.clone().unwrap_or_else(default_audit_path);
+        let file = RollingFile::open(&path, config.max_size_mb * 1024 * 1024, Rotation::Never, config.max_files)?;
+        Ok(Self { path, max_files: config.max_files, file: Mutex::new(file), redactor: None })
+    }
+
+    /// Mask secrets in params and errors with `redactor` before writing
+    pub fn redacting(mut self, redactor: Option<Arc<Redactor>>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, call: &ToolCall, result: &ToolResult) {
+        let mut entry = json!({
+            "ts": Utc::now().to_rfc3339(),
+            "session": call.session,
+            "tool": call.tool,
+            "action": call.action,
+            "params": redact_params(&call.params),
+            "duration_ms": call.started.elapsed().as_millis() as u64,
+            "success": result.success
+        });
+        if let Some(error) = &result.error {
+            entry["error"] = json!(error);
+        }
+        if let Some(redactor) = &self.redactor {
+            redactor.redact_value(&mut entry);
+        }
+        if let Ok(mut file) = self.file.lock() {
+            if let Err(e) = file.write_line(&entry.to_string()) {
+                log::warn!("Cannot write audit log {}: {}", self.path.display(), e);
+            }
+        }
+    }
+
+    /// Log files oldest first
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = (1..=self.max_files)
+            .rev()
+            .map(|n| {
+                let mut name = self.path.clone().into_os_string();
+                name.push(format!(".{}", n));
+                PathBuf::from(name)
+            })
+            .filter(|p| p.exists())
+            .collect();
+        files.push(self.path.clone());
+        files
+    }
+
+    pub fn query(&self, params: &Value) -> Result<Value> {
+        let session = params["session"].as_str();
+        let tool = params["tool"].as_str();
+        let since = match params["since"].as_str() {
+            Some(since) => Some(DateTime::parse_from_rfc3339(since)
+                .map_err(|e| ToolError::invalid(format!("since must be RFC 3339: {}", e)))?
+                .with_timezone(&Utc)),
+            None => None,
+        };
+        let failed_only = params["failed"].as_bool().unwrap_or(false);
+        let limit = params["limit"].as_u64().map_or(DEFAULT_LIMIT, |n| n as usize);
+
+        let mut matched = Vec::new();
+        for path in self.files() {
+            let Ok(file) = std::fs::File::open(&path) else { continue };
+            for line in std::io::BufReader::new(file).lines().map_while(Result::ok) {
+                let Ok(entry) = serde_json::from_str::<Value>(&line) else { continue };
+                if session.is_some_and(|s| entry["session"] != s)
+                    || tool.is_some_and(|t| !names_call(t, &entry))
+                    || (failed_only && entry["success"] != false)
+                {
+                    continue;
+                }
+                if let Some(since) = since {
+                    let at = entry["ts"].as_str().and_then(|ts| DateTime::parse_from_rfc3339(ts).ok());
+                    if at.is_none_or(|at| at < since) {
+                        continue;
+                    }
+                }
+                matched.push(entry);
+            }
+        }
+
+        let total = matched.len();
+        let entries: Vec<Value> = matched.into_iter().skip(total.saturating_sub(limit)).collect();
+        Ok(json!({
+            "entries": entries,
+            "count": entries.len(),
+            "matched": total,
+            "truncated": total > entries.len(),
+            "file": self.path
+        }))
+    }
+
+    /// Sessions seen in the log with their call counts and time span
+    pub fn sessions(&self) -> Result<Value> {
+        let all = self.query(&json!({ "limit": u64::MAX }))?;
+        let mut sessions: Vec<Value> = Vec::new();
+        for entry in all["entries"].as_array().into_iter().flatten() {
+            let id = entry["session"].clone();
+            match sessions.iter_mut().find(|s| s["session"] == id) {
+                Some(session) => {
+                    session["calls"] = json!(session["calls"].as_u64().unwrap_or(0) + 1);
+                    if entry["success"] == false {
+                        session["failures"] = json!(session["failures"].as_u64().unwrap_or(0) + 1);
+                    }
+                    session["last"] = entry["ts"].clone();
+                }
+                None => sessions.push(json!({
+                    "session": id,
+                    "calls": 1,
+                    "failures": if entry["success"] == false { 1 } else { 0 },
+                    "first": entry["ts"],
+                    "last": entry["ts"]
+                })),
+            }
+        }
+        Ok(json!({ "sessions": sessions, "count": sessions.len() }))
+    }
+}
+
+/// Whether `key` (`tool` or `tool.action`) names the logged call
+fn names_call(key: &str, entry: &Value) -> bool {
+    match key.split_once('.') {
+        Some((tool, action)) => entry["tool"] == tool && entry["action"] == action,
+        None => entry["tool"] == key,
+    }
+}
+
+/// Params safe to write down: secret-looking keys masked and long text
+/// replaced by its size
+pub fn redact_params(params: &Value) -> Value {
+    match params {
+        Value::Object(map) => Value::Object(map.iter().map(|(key, value)| {
+            let value = if redaction::secret_name(key) && !value.is_null() { json!(REDACTED) } else { redact_params(value) };
+            (key.clone(), value)
+        }).collect()),
+        Value::Array(items) => Value::Array(items.iter().map(redact_params).collect()),
+        Value::String(text) if text.chars().count() > MAX_PARAM_CHARS => {
+            json!(format!("<{} bytes>", text.len()))
+        }
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = AuditLog::open(&AuditConfig {
+            enabled: true,
+            file: Some(dir.path().join("audit.jsonl")),
+            max_size_mb: 1,
+            max_files: 2,
+        }).unwrap();
+
+        let params = json!({ "action": "write", "content": "x".repeat(2000), "env": { "API_TOKEN": "abc" }, "password": "pw" });
+        log.record(&ToolCall::new("fs", params, Some("s1")), &ToolResult::ok(json!({})));
+        log.record(&ToolCall::new("exec", json!({ "action": "exec" }), Some("s2")), &ToolResult::err("boom"));
+        log.record(&ToolCall::new("fs", json!({ "action": "read" }), Some("s1")), &ToolResult::ok(json!({})));
+
+        let s1 = log.query(&json!({ "session": "s1" })).unwrap();
+        assert_eq!(s1["count"], 2);
+        let written = &s1["entries"][0]["params"];
+        assert_eq!(written["content"], "<2000 bytes>");
+        assert_eq!(written["env"]["API_TOKEN"], REDACTED);
+        assert_eq!(written["password"], REDACTED);
+
+        assert_eq!(log.query(&json!({ "failed": true })).unwrap()["entries"][0]["error"], "boom");
+        assert_eq!(log.query(&json!({ "tool": "fs.read" })).unwrap()["count"], 1);
+        let last = log.query(&json!({ "limit": 1 })).unwrap();
+        assert_eq!(last["entries"][0]["action"], "read");
+        assert_eq!(last["truncated"], true);
+        assert_eq!(log.sessions().unwrap()["sessions"][0]["calls"], 2);
+    }
+}
@@ -0,0 +1,94 @@
+/// Argument completion for `completion/complete`
+///
+/// Clients point at a tool with `{"type": "ref/tool", "name": ...}` and
+/// send the argument being typed. Suggestions come from live state: paths
+/// under the session's root for fs, open window titles for computer,
+/// process ids for exec, the memory ids the session can see and mode
+/// names. Arguments whose schema lists an `enum`, such as `action`,
+/// complete from it. Matching is by case-insensitive prefix.
+
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Most values one completion returns, as the MCP spec allows
+pub const MAX_VALUES: usize = 100;
+
+/// Directory entries read at most when completing a path
+const MAX_ENTRIES: usize = 5000;
+
+/// MCP completion result of the `candidates` starting with `prefix`
+pub fn complete(candidates: Vec<String>, prefix: &str) -> Value {
+    let lower = prefix.to_lowercase();
+    let mut values: Vec<String> = candidates.into_iter()
+        .filter(|c| c.to_lowercase().starts_with(&lower))
+        .collect();
+    values.sort();
+    values.dedup();
+    let total = values.len();
+    values.truncate(MAX_VALUES);
+    json!({
+        "completion": {
+            "values": values,
+            "total": total,
+            "hasMore": total > MAX_VALUES
+        }
+    })
+}
+
+/// Paths completing `typed`, relative to `base` unless absolute or `~`;
+/// directories end in `/` and hidden entries need a typed `.`
+pub fn paths(base: &Path, typed: &str) -> Vec<String> {
+    let (dir, name) = match typed.rfind('/') {
+        Some(at) => typed.split_at(at + 1),
+        None => ("", typed),
+    };
+    let expanded = shellexpand::tilde(if dir.is_empty() { "." } else { dir }).to_string();
+    let Ok(entries) = std::fs::read_dir(base.join(expanded)) else { return Vec::new() };
+    entries.flatten()
+        .take(MAX_ENTRIES)
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !file_name.starts_with(name) || (file_name.starts_with('.') && !name.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, file_name, slash))
+        })
+        .collect()
+}
+
+/// Values an argument's JSON schema enumerates
+pub fn enumerated(schema: &Value, argument: &str) -> Vec<String> {
+    let property = &schema["properties"][argument];
+    [&property["enum"], &property["items"]["enum"]].iter()
+        .filter_map(|values| values.as_array())
+        .flatten()
+        .filter_map(|value| value.as_str().map(str::to_string))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "").unwrap();
+        std::fs::write(dir.path().join("src/server.rs"), "").unwrap();
+        std::fs::write(dir.path().join(".env"), "").unwrap();
+
+        assert_eq!(paths(dir.path(), "s"), vec!["src/"]);
+        assert_eq!(paths(dir.path(), ""), vec!["src/"]);
+        assert_eq!(paths(dir.path(), "."), vec![".env"]);
+        assert_eq!(paths(dir.path(), "src/l"), vec!["src/lib.rs"]);
+        let absolute = format!("{}/src/se", dir.path().display());
+        assert_eq!(paths(Path::new("/"), &absolute), vec![format!("{}/src/server.rs", dir.path().display())]);
+
+        let result = complete((0..150).map(|n| format!("p{}", n)).collect(), "P");
+        assert_eq!(result["completion"]["values"].as_array().unwrap().len(), MAX_VALUES);
+        assert_eq!(result["completion"]["total"], 150);
+        assert_eq!(result["completion"]["hasMore"], true);
+    }
+}
@@ -1,12 +1,943 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
     pub tools: ToolsConfig,
     pub node: NodeConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Limits keyed by tool (`browser`) or tool and action (`fs.search`)
+    #[serde(default = "default_limits")]
+    pub limits: HashMap<String, ToolLimit>,
+    #[serde(default)]
+    pub timeouts: TimeoutConfig,
+    #[serde(default)]
+    pub journal: JournalConfig,
+    #[serde(default)]
+    pub fs: FsConfig,
+    #[serde(default)]
+    pub code: CodeConfig,
+    #[serde(default)]
+    pub pagination: PaginationConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// Refuse every call that could change files, processes or state
+    #[serde(default)]
+    pub read_only: bool,
+    /// Project roots besides the startup directory
+    #[serde(default)]
+    pub workspaces: Vec<WorkspaceRoot>,
+    /// Multi-step workflows the `workflow` tool runs, by name
+    #[serde(default)]
+    pub workflows: HashMap<String, Workflow>,
+    #[serde(default)]
+    pub schedule: ScheduleConfig,
+    #[serde(default)]
+    pub index: IndexConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub calendar: CalendarConfig,
+    #[serde(default)]
+    pub vector: VectorConfig,
+    #[serde(default)]
+    pub browser: BrowserConfig,
+    #[serde(default)]
+    pub naming: NamingConfig,
+    #[serde(default)]
+    pub advertise: AdvertiseConfig,
+    #[serde(default)]
+    pub exec: ExecConfig,
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub gateway: GatewayConfig,
+}
+
+/// Execution timeouts applied to every tool call by the registry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    /// Seconds a call may run unless overridden (0 disables)
+    pub default_secs: u64,
+    /// Overrides keyed by tool (`browser`) or tool and action (`exec.wait`)
+    pub tools: HashMap<String, u64>,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            default_secs: 300,
+            tools: HashMap::from([
+                // exec backgrounds long commands itself; wait may block up to an hour
+                ("exec".to_string(), 3660),
+                ("browser".to_string(), 120),
+                ("computer".to_string(), 60),
+                // a cold cargo check of a large workspace takes minutes
+                ("diagnostics".to_string(), 900),
+                ("test".to_string(), 1800),
+                ("lsp".to_string(), 120),
+            ]),
+        }
+    }
+}
+
+impl TimeoutConfig {
+    /// Timeout for a call, or None when disabled
+    pub fn for_call(&self, tool: &str, action: Option<&str>) -> Option<std::time::Duration> {
+        let secs = action
+            .and_then(|a| self.tools.get(&format!("{}.{}", tool, a)))
+            .or_else(|| self.tools.get(tool))
+            .copied()
+            .unwrap_or(self.default_secs);
+        (secs > 0).then(|| std::time::Duration::from_secs(secs))
+    }
+}
+
+/// Undo journal kept for fs write/edit/patch, per session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    /// Changes kept per session; the oldest are dropped first
+    pub max_entries: usize,
+    /// Bytes of pre- and post-images kept per session
+    pub max_bytes: usize,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            max_entries: 100,
+            max_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+/// Defaults for commands the exec tool runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecConfig {
+    /// Where commands given no cwd run
+    pub default_cwd: DefaultCwd,
+    /// Which of the server's environment variables commands inherit
+    pub env_inherit: EnvInherit,
+    /// Variables inherited under `allowlist`; a trailing `*` matches a
+    /// prefix (`LC_*`)
+    pub env_allow: Vec<String>,
+    /// Variables set for every command; a call's env overrides them
+    pub env: HashMap<String, String>,
+    /// Put toolchain directories missing from PATH in front of it: cargo,
+    /// nvm's default node, pyenv, rbenv and asdf shims, go, bun, deno,
+    /// volta, ~/.local/bin and Homebrew
+    pub augment_path: bool,
+    /// More directories put in front of PATH
+    pub path: Vec<PathBuf>,
+    /// Long-running commands exec's start action runs by name
+    pub services: HashMap<String, ServiceConfig>,
+}
+
+/// A declared service: dev server, database, watcher
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceConfig {
+    /// Shell string or argv array, as exec takes it
+    pub command: serde_json::Value,
+    pub cwd: Option<String>,
+    pub env: HashMap<String, String>,
+    /// Ready once this local port accepts connections
+    pub ready_port: Option<u16>,
+    /// Ready once a line of output matches this regex
+    pub ready_log: Option<String>,
+    /// never, on-failure or always; on-failure when unset
+    pub restart: Option<String>,
+    pub max_restarts: Option<u32>,
+}
+
+impl Default for ExecConfig {
+    fn default() -> Self {
+        Self {
+            default_cwd: DefaultCwd::Root,
+            env_inherit: EnvInherit::All,
+            env_allow: [
+                "HOME", "USER", "LOGNAME", "SHELL", "LANG", "LC_*", "TERM", "TMPDIR", "TZ", "SSH_AUTH_SOCK",
+                "SYSTEMROOT", "COMSPEC", "PATHEXT", "USERPROFILE", "APPDATA", "LOCALAPPDATA", "TEMP", "TMP",
+            ].iter().map(|v| v.to_string()).collect(),
+            env: HashMap::new(),
+            augment_path: true,
+            path: Vec::new(),
+            services: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultCwd {
+    /// The calling session's active project root
+    Root,
+    /// The server's own working directory
+    Server,
+}
+
+/// PATH is passed under every policy, or no program could be found
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvInherit {
+    All,
+    Allowlist,
+    None,
+}
+
+/// Guards for fs reads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FsConfig {
+    /// Largest file read, edited or searched whole; bigger files must be
+    /// read with head/tail or byte ranges
+    pub max_read_bytes: u64,
+}
+
+impl Default for FsConfig {
+    fn default() -> Self {
+        Self { max_read_bytes: 10 * 1024 * 1024 }
+    }
+}
+
+/// Splitting of oversized tool results into pages fetched by cursor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaginationConfig {
+    /// Serialized bytes above which a result is paged (0 disables)
+    pub max_result_bytes: usize,
+    /// Paged results kept for their cursors; the oldest are dropped first
+    pub max_cursors: usize,
+    /// Seconds a cursor stays valid
+    pub cursor_ttl_secs: u64,
+}
+
+impl Default for PaginationConfig {
+    fn default() -> Self {
+        Self {
+            max_result_bytes: 100_000,
+            max_cursors: 32,
+            cursor_ttl_secs: 900,
+        }
+    }
+}
+
+/// How tools are named to clients, for hosts that aggregate several MCP
+/// servers whose tool names would otherwise collide
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NamingConfig {
+    /// Namespace advertised before every tool name, e.g. `hanzo` for
+    /// `hanzo.fs`; calls are accepted with or without it
+    pub prefix: Option<String>,
+    /// Joins the prefix to the tool name; use `_` for clients that only
+    /// accept `[a-zA-Z0-9_-]` names
+    pub separator: String,
+    /// Other names tools answer to, alias to tool; by default `proc` for
+    /// exec and `ui` for computer
+    pub aliases: HashMap<String, String>,
+    /// Advertise aliases in `tools/list` as well
+    pub list_aliases: bool,
+}
+
+impl Default for NamingConfig {
+    fn default() -> Self {
+        Self {
+            prefix: None,
+            separator: ".".to_string(),
+            aliases: HashMap::from([
+                ("proc".to_string(), "exec".to_string()),
+                ("ui".to_string(), "computer".to_string()),
+            ]),
+            list_aliases: false,
+        }
+    }
+}
+
+/// Which tools `tools/list` shows each client. Hidden tools still run
+/// when called; they are only left out of the list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdvertiseConfig {
+    /// Hide tools this host cannot serve: computer without a display,
+    /// browser without node and Playwright browsers
+    pub auto: bool,
+    /// Tools hidden from every client
+    pub hide: Vec<String>,
+    /// Tools shown to every client, even when auto would hide them
+    pub show: Vec<String>,
+    /// Overrides for clients by the name they send in `clientInfo`,
+    /// matched case-insensitively; applied after the ones above
+    pub clients: HashMap<String, ClientTools>,
+}
+
+impl Default for AdvertiseConfig {
+    fn default() -> Self {
+        Self { auto: true, hide: Vec::new(), show: Vec::new(), clients: HashMap::new() }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientTools {
+    pub hide: Vec<String>,
+    pub show: Vec<String>,
+}
+
+/// Policies applied to every tool call; keys name a tool (`exec`) or a
+/// tool and action (`fs.write`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Calls refused outright
+    pub deny: Vec<String>,
+    /// When non-empty, the only calls allowed
+    pub allow: Vec<String>,
+    /// Regexes whose matches are masked in results
+    pub redact: Vec<String>,
+    /// Secrets masked in results and logs
+    pub secrets: SecretsConfig,
+    /// Append-only record of every call
+    pub audit: AuditConfig,
+    /// Shell commands and webhooks run before or after calls
+    pub commands: Vec<HookCommand>,
+}
+
+/// A project root and the policies that apply while it is active
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceRoot {
+    pub path: PathBuf,
+    /// Defaults to the directory's name
+    pub name: Option<String>,
+    /// When non-empty, the only calls allowed in this root
+    pub allow: Vec<String>,
+    /// Calls refused in this root
+    pub deny: Vec<String>,
+}
+
+/// Scheduled tool calls, kept across restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleConfig {
+    pub enabled: bool,
+    /// Where jobs and their run logs are kept; defaults to `schedule/`
+    /// under the hanzo-mcp data directory
+    pub dir: Option<PathBuf>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self { enabled: true, dir: None }
+    }
+}
+
+/// Background indexing of the workspace roots, so searches start warm
+///
+/// ```toml
+/// [index]
+/// enabled = true
+/// interval_secs = 30
+/// vector = true
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexConfig {
+    pub enabled: bool,
+    /// Seconds between passes; each pass re-reads only changed files
+    pub interval_secs: u64,
+    /// Also embed each definition into the vector store
+    pub vector: bool,
+    /// Vector collection definitions go to
+    pub collection: String,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self { enabled: false, interval_secs: 60, vector: false, collection: "code".to_string() }
+    }
+}
+
+/// Device profiles the browser's `emulate` offers besides the built-in
+/// ones (mobile, tablet, laptop, desktop, iphone_14, pixel_7, ipad_pro)
+///
+/// ```toml
+/// [browser.devices.kiosk]
+/// width = 1080
+/// height = 1920
+/// has_touch = true
+/// user_agent = "Mozilla/5.0 (X11; Linux x86_64) Kiosk/1.0"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrowserConfig {
+    /// Profiles by name; one named like a built-in device replaces it
+    pub devices: HashMap<String, DeviceProfile>,
+}
+
+/// What a browser context emulates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub width: u32,
+    pub height: u32,
+    #[serde(default = "default_scale_factor")]
+    pub device_scale_factor: f64,
+    /// The browser's own when unset
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// Honor the meta viewport tag and mobile layout
+    #[serde(default)]
+    pub is_mobile: bool,
+    #[serde(default)]
+    pub has_touch: bool,
+}
+
+fn default_scale_factor() -> f64 {
+    1.0
+}
+
+/// Other MCP servers whose tools this one re-exports
+///
+/// ```toml
+/// [proxy.servers.github]
+/// command = "npx"
+/// args = ["-y", "@modelcontextprotocol/server-github"]
+/// env = { GITHUB_PERSONAL_ACCESS_TOKEN = "${GITHUB_TOKEN}" }
+///
+/// [proxy.servers.docs]
+/// url = "https://docs.example.com/mcp"
+/// headers = { Authorization = "Bearer ${DOCS_TOKEN}" }
+/// prefix = "kb"
+/// tools = ["search_docs", "read_page"]
+/// ```
+///
+/// Tools are mounted as `<prefix>_<tool>`, the prefix defaulting to the
+/// server's name. Environment and header values expand `${VAR}`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub servers: HashMap<String, ProxyServer>,
+}
+
+/// A downstream MCP server: a command speaking over stdio, or a URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyServer {
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: HashMap<String, String>,
+    pub cwd: Option<PathBuf>,
+    /// Streamable HTTP endpoint, instead of a command
+    pub url: Option<String>,
+    pub headers: HashMap<String, String>,
+    /// Defaults to the server's name
+    pub prefix: Option<String>,
+    /// When non-empty, the only downstream tools mounted
+    pub tools: Vec<String>,
+    /// Seconds to wait for the server to start and for each answer
+    pub timeout_secs: u64,
+    pub enabled: bool,
+}
+
+impl Default for ProxyServer {
+    fn default() -> Self {
+        Self {
+            command: None,
+            args: Vec::new(),
+            env: HashMap::new(),
+            cwd: None,
+            url: None,
+            headers: HashMap::new(),
+            prefix: None,
+            tools: Vec::new(),
+            timeout_secs: 60,
+            enabled: true,
+        }
+    }
+}
+
+/// REST endpoints for the tools, served beside MCP on the same port
+///
+/// ```toml
+/// [gateway]
+/// enabled = true
+///
+/// [[gateway.keys]]
+/// name = "ci"
+/// key_env = "HANZO_CI_KEY"
+/// allow = ["test", "diagnostics", "git.status"]
+///
+/// [[gateway.keys]]
+/// name = "deploy-hook"
+/// key_env = "HANZO_DEPLOY_KEY"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GatewayConfig {
+    pub enabled: bool,
+    /// Keys a request may present; at least one is needed
+    pub keys: Vec<GatewayKey>,
+}
+
+/// An API key of the REST gateway and what it may call
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GatewayKey {
+    /// Names the key in the audit log, whose session is `gateway:<name>`
+    pub name: String,
+    pub key: Option<String>,
+    /// Environment variable holding the key, to keep it out of the file
+    pub key_env: Option<String>,
+    /// When non-empty, the only calls allowed (`tool` or `tool.action`)
+    pub allow: Vec<String>,
+}
+
+/// Object storage accounts the `storage` tool can use
+///
+/// ```toml
+/// [storage]
+/// default_profile = "minio"
+///
+/// [storage.profiles.minio]
+/// endpoint = "http://localhost:9000"
+/// access_key_id_env = "MINIO_ROOT_USER"
+/// secret_access_key_env = "MINIO_ROOT_PASSWORD"
+///
+/// [storage.profiles.gcs]
+/// endpoint = "https://storage.googleapis.com"
+/// region = "auto"
+/// access_key_id_env = "GCS_HMAC_KEY"
+/// secret_access_key_env = "GCS_HMAC_SECRET"
+/// ```
+///
+/// Without profiles, the AWS_* environment variables make up one named
+/// `default`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// Profile used when a call names none; the only one when there is one
+    pub default_profile: Option<String>,
+    pub profiles: HashMap<String, StorageProfile>,
+}
+
+impl StorageConfig {
+    /// The profile `name`, or the default one
+    pub fn profile(&self, name: Option<&str>) -> Option<(String, StorageProfile)> {
+        if self.profiles.is_empty() {
+            return matches!(name, None | Some("default")).then(|| ("default".to_string(), StorageProfile::default()));
+        }
+        let name = match name.or(self.default_profile.as_deref()) {
+            Some(name) => name.to_string(),
+            None if self.profiles.len() == 1 => self.profiles.keys().next()?.clone(),
+            None => return None,
+        };
+        self.profiles.get(&name).map(|profile| (name, profile.clone()))
+    }
+}
+
+/// An S3-compatible account: AWS S3, MinIO, R2, or GCS with HMAC keys.
+/// Keys left out fall back to the AWS_* environment variables.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageProfile {
+    /// Service URL; AWS_ENDPOINT_URL, else AWS S3 for the region, when
+    /// left out
+    pub endpoint: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: Option<String>,
+    pub secret_access_key: Option<String>,
+    pub session_token: Option<String>,
+    /// Environment variables holding the keys, to keep them out of the file
+    pub access_key_id_env: Option<String>,
+    pub secret_access_key_env: Option<String>,
+    /// Address buckets as `endpoint/bucket` rather than
+    /// `bucket.endpoint`; on by default for custom endpoints
+    pub path_style: Option<bool>,
+    /// Bucket used when a call names none
+    pub bucket: Option<String>,
+}
+
+impl StorageProfile {
+    pub fn endpoint(&self) -> Option<String> {
+        self.endpoint.clone().or_else(|| std::env::var("AWS_ENDPOINT_URL").ok())
+    }
+
+    pub fn region(&self) -> String {
+        self.region.clone()
+            .or_else(|| std::env::var("AWS_REGION").ok())
+            .or_else(|| std::env::var("AWS_DEFAULT_REGION").ok())
+            .unwrap_or_else(|| "us-east-1".to_string())
+    }
+
+    pub fn credentials(&self) -> Option<crate::tools::storage_s3::Credentials> {
+        let from = |value: &Option<String>, env: &Option<String>, fallback: &str| {
+            value.clone()
+                .or_else(|| env.as_ref().and_then(|name| std::env::var(name).ok()))
+                .or_else(|| std::env::var(fallback).ok())
+                .filter(|v| !v.is_empty())
+        };
+        Some(crate::tools::storage_s3::Credentials {
+            access_key_id: from(&self.access_key_id, &self.access_key_id_env, "AWS_ACCESS_KEY_ID")?,
+            secret_access_key: from(&self.secret_access_key, &self.secret_access_key_env, "AWS_SECRET_ACCESS_KEY")?,
+            session_token: from(&self.session_token, &None, "AWS_SESSION_TOKEN"),
+        })
+    }
+}
+
+/// Where the `notify` tool sends messages
+///
+/// ```toml
+/// [notify]
+/// default_channels = ["team", "me"]
+///
+/// [notify.channels.team]
+/// type = "slack"
+/// webhook_url_env = "SLACK_WEBHOOK_URL"
+///
+/// [notify.channels.me]
+/// type = "smtp"
+/// url = "smtps://smtp.example.com:465"
+/// from = "agent@example.com"
+/// to = ["me@example.com"]
+/// username = "agent@example.com"
+/// password_env = "SMTP_PASSWORD"
+/// ```
+///
+/// Without channels, messages go to a desktop notification.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    /// Channels used when a call names none; every channel when empty
+    pub default_channels: Vec<String>,
+    pub channels: HashMap<String, NotifyChannel>,
+}
+
+/// A destination for notifications. Secrets can be given directly or
+/// through the `_env` variant naming the variable that holds them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NotifyChannel {
+    /// Email through an SMTP server; `smtps://` for implicit TLS,
+    /// `smtp://` upgraded with STARTTLS unless `tls = false`
+    Smtp {
+        url: String,
+        from: String,
+        to: Vec<String>,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+        #[serde(default)]
+        password_env: Option<String>,
+        #[serde(default = "default_true")]
+        tls: bool,
+    },
+    /// Slack incoming webhook
+    Slack {
+        #[serde(default)]
+        webhook_url: Option<String>,
+        #[serde(default)]
+        webhook_url_env: Option<String>,
+    },
+    /// JSON POST of `{title, message, level, ts}` to any URL
+    Webhook {
+        #[serde(default)]
+        url: Option<String>,
+        #[serde(default)]
+        url_env: Option<String>,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// Notification on this machine's desktop
+    Desktop,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A value given directly or through the environment variable `env`
+pub fn secret(value: &Option<String>, env: &Option<String>) -> Option<String> {
+    value.clone()
+        .or_else(|| env.as_ref().and_then(|name| std::env::var(name).ok()))
+        .filter(|v| !v.is_empty())
+}
+
+/// Where the `calendar` tool keeps events and reminders
+///
+/// ```toml
+/// [calendar]
+/// backend = "ics"
+/// path = "~/Calendars/agent.ics"
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    /// `system` for Calendar and Reminders through EventKit (macOS only)
+    /// or `ics` for an iCalendar file; system on macOS, ics elsewhere
+    pub backend: Option<String>,
+    /// The iCalendar file; defaults to `calendar.ics` under the
+    /// hanzo-mcp data directory
+    pub path: Option<PathBuf>,
+}
+
+/// Where the vector store keeps collections and how it embeds text
+///
+/// ```toml
+/// [vector]
+/// path = "~/.hanzo/vectors"
+///
+/// [vector.embeddings]
+/// url = "https://api.openai.com/v1/embeddings"
+/// model = "text-embedding-3-small"
+/// api_key_env = "OPENAI_API_KEY"
+/// ```
+///
+/// Without `embeddings`, text is embedded locally by hashing its words,
+/// which finds shared vocabulary rather than meaning.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VectorConfig {
+    /// Directory of collection files; defaults to `vectors` under the
+    /// hanzo-mcp data directory
+    pub path: Option<PathBuf>,
+    pub embeddings: Option<EmbeddingConfig>,
+}
+
+/// An OpenAI-compatible embeddings endpoint (OpenAI, Ollama, vLLM, ...)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmbeddingConfig {
+    pub url: String,
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+    /// Environment variable holding the key, to keep it out of the file
+    pub api_key_env: Option<String>,
+    /// Ask models that support it for shorter vectors
+    pub dimensions: Option<usize>,
+}
+
+/// A named sequence of tool calls
+///
+/// ```toml
+/// [workflows.test_branch]
+/// description = "Check out a branch, run its tests and list the failures"
+/// inputs.branch = { description = "Branch to test" }
+///
+/// [[workflows.test_branch.steps]]
+/// tool = "git"
+/// args = { action = "checkout", branch = "{{inputs.branch}}" }
+///
+/// [[workflows.test_branch.steps]]
+/// id = "tests"
+/// tool = "test"
+/// args = { action = "run" }
+///
+/// [[workflows.test_branch.steps]]
+/// tool = "think"
+/// when = "{{steps.tests.result.data.failed}}"
+/// args = { thought = "Failing on {{inputs.branch}}: {{#each steps.tests.result.data.failures}}{{name}} {{/each}}" }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workflow {
+    pub description: Option<String>,
+    /// Values callers pass in, referred to as `inputs.<name>`
+    pub inputs: HashMap<String, WorkflowInput>,
+    pub steps: Vec<WorkflowStep>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkflowInput {
+    pub description: Option<String>,
+    /// Value used when the caller passes none; inputs without one are
+    /// required
+    pub default: Option<serde_json::Value>,
+}
+
+/// One tool call of a workflow. String args are Handlebars templates over
+/// `inputs`, `steps.<id>` (`success`, `skipped`, `result`, `error`) and
+/// `previous`; an arg that is a single `{{path}}` takes the value as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    /// Name later steps use for this step's outcome
+    #[serde(default)]
+    pub id: Option<String>,
+    pub tool: String,
+    #[serde(default = "empty_args")]
+    pub args: serde_json::Value,
+    /// Template; the step is skipped when it renders empty, `false`, `0`
+    /// or `null`
+    #[serde(default)]
+    pub when: Option<String>,
+    /// Carry on with later steps when this one fails
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+fn empty_args() -> serde_json::Value {
+    serde_json::Value::Object(Default::default())
+}
+
+/// Secret detection for redaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecretsConfig {
+    /// Mask well-known credential formats: API keys, access tokens,
+    /// private keys, JWTs
+    pub builtin: bool,
+    /// Mask values read from `.env` files and secret-named environment
+    /// variables of the server
+    pub env_values: bool,
+    /// Further named patterns; a `secret` capture group masks only itself
+    pub patterns: Vec<SecretPattern>,
+    /// Regexes of matches left visible, keyed by pattern name (`openai`,
+    /// `env`, a configured name, `custom` for `redact`, or `*` for all)
+    pub allow: HashMap<String, Vec<String>>,
+}
+
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            env_values: true,
+            patterns: Vec::new(),
+            allow: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretPattern {
+    pub name: String,
+    pub regex: String,
+}
+
+/// JSONL audit log of tool calls, rotated by size
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// Defaults to `audit/audit.jsonl` under the hanzo-mcp data directory
+    pub file: Option<PathBuf>,
+    pub max_size_mb: u64,
+    /// Rotated files kept besides the live one
+    pub max_files: usize,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            file: None,
+            max_size_mb: 50,
+            max_files: 10,
+        }
+    }
+}
+
+/// A user hook, given the call (and, after it, the result) as JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookCommand {
+    /// `pre` runs before the call and may refuse or rewrite it; `post`
+    /// runs after and may rewrite the result
+    pub phase: HookPhase,
+    /// Calls the hook applies to; empty means all
+    pub tools: Vec<String>,
+    /// Shell command reading the JSON on stdin
+    pub command: Option<String>,
+    /// URL the JSON is POSTed to
+    pub url: Option<String>,
+    /// Seconds before the hook is abandoned
+    pub timeout_secs: u64,
+    /// Refuse the call when a pre hook fails or times out, instead of
+    /// letting it through
+    pub fail_closed: bool,
+}
+
+impl Default for HookCommand {
+    fn default() -> Self {
+        Self {
+            phase: HookPhase::Pre,
+            tools: Vec::new(),
+            command: None,
+            url: None,
+            timeout_secs: 10,
+            fail_closed: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookPhase {
+    Pre,
+    Post,
+}
+
+/// External programs run by the code, diagnostics and test tools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CodeConfig {
+    /// Formatter commands keyed by language (`rust`) or file extension
+    /// (`tsx`); each reads source on stdin and writes it to stdout, with
+    /// `{path}` replaced by the file's path
+    pub formatters: HashMap<String, Vec<String>>,
+    /// Diagnostics commands replacing the defaults, keyed by linter
+    /// (`cargo`, `clippy`, `eslint`, `ruff`, `tsc`); output must stay in
+    /// the format the default produces
+    pub linters: HashMap<String, Vec<String>>,
+    /// Test commands replacing the defaults, keyed by framework (`cargo`,
+    /// `pytest`, `jest`); filters are appended, and output must stay in the
+    /// format the default produces
+    pub test_runners: HashMap<String, Vec<String>>,
+    /// Language server commands keyed by server family (`rust`, `python`,
+    /// `typescript`, `go`); each must speak LSP over stdio
+    pub language_servers: HashMap<String, Vec<String>>,
+}
+
+impl Default for CodeConfig {
+    fn default() -> Self {
+        Self {
+            formatters: crate::tools::code_format::default_formatters(),
+            linters: HashMap::new(),
+            test_runners: HashMap::new(),
+            language_servers: crate::tools::lsp_tool::default_servers(),
+        }
+    }
+}
+
+/// Concurrency cap and token-bucket rate limit for a tool
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ToolLimit {
+    /// Calls allowed to run at once
+    pub max_concurrent: Option<usize>,
+    /// Sustained calls per second
+    pub rate_per_second: Option<f64>,
+    /// Calls allowed in a burst; defaults to one second's worth
+    pub burst: Option<u32>,
+}
+
+pub fn default_limits() -> HashMap<String, ToolLimit> {
+    let concurrent = |n| ToolLimit { max_concurrent: Some(n), ..Default::default() };
+    HashMap::from([
+        ("browser".to_string(), concurrent(2)),
+        ("computer".to_string(), concurrent(1)),
+        ("search".to_string(), concurrent(8)),
+        ("fs.search".to_string(), concurrent(8)),
+    ])
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +945,9 @@ pub struct ServerConfig {
     pub host: String,
     pub port: u16,
     pub max_connections: usize,
+    /// Archive session-scoped memories on disconnect instead of dropping them
+    #[serde(default)]
+    pub archive_session_memory: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,6 +967,48 @@ pub struct NodeConfig {
     pub node_api_key: Option<String>,
 }
 
+/// Log output settings. Console logs always go to stderr, never stdout, so
+/// they cannot corrupt a stdio transport.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Default level: error, warn, info, debug, trace or off
+    pub level: String,
+    /// Per-module level overrides keyed by module path prefix,
+    /// e.g. `"hanzo_mcp::tools::exec_tool" = "debug"`
+    pub modules: HashMap<String, String>,
+    /// Log file; defaults to `<data dir>/hanzo-mcp/logs/hanzo-mcp.log`
+    pub file: Option<PathBuf>,
+    /// Write to the log file at all
+    pub file_enabled: bool,
+    /// Also log to stderr
+    pub console: bool,
+    /// File line format: json or text
+    pub format: String,
+    /// Rotate once the file exceeds this size (0 disables)
+    pub max_size_mb: u64,
+    /// Time-based rotation: never, hourly or daily
+    pub rotation: String,
+    /// Rotated files to keep
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            modules: HashMap::new(),
+            file: None,
+            file_enabled: true,
+            console: true,
+            format: "json".to_string(),
+            max_size_mb: 10,
+            rotation: "daily".to_string(),
+            max_files: 5,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -40,6 +1016,7 @@ impl Default for Config {
                 host: "127.0.0.1".to_string(),
                 port: 3333,
                 max_connections: 100,
+                archive_session_memory: false,
             },
             tools: ToolsConfig {
                 computer_control: true,
@@ -54,6 +1031,29 @@ impl Default for Config {
                 node_api_url: "http://localhost:9999".to_string(),
                 node_api_key: None,
             },
+            logging: LoggingConfig::default(),
+            limits: default_limits(),
+            timeouts: TimeoutConfig::default(),
+            journal: JournalConfig::default(),
+            fs: FsConfig::default(),
+            code: CodeConfig::default(),
+            pagination: PaginationConfig::default(),
+            hooks: HooksConfig::default(),
+            read_only: false,
+            workspaces: Vec::new(),
+            workflows: HashMap::new(),
+            schedule: ScheduleConfig::default(),
+            index: IndexConfig::default(),
+            storage: StorageConfig::default(),
+            notify: NotifyConfig::default(),
+            calendar: CalendarConfig::default(),
+            vector: VectorConfig::default(),
+            browser: BrowserConfig::default(),
+            naming: NamingConfig::default(),
+            advertise: AdvertiseConfig::default(),
+            exec: ExecConfig::default(),
+            proxy: ProxyConfig::default(),
+            gateway: GatewayConfig::default(),
         }
     }
 }
@@ -0,0 +1,157 @@
+/// Tool error taxonomy
+///
+/// Tools raise `ToolError` (through `anyhow`) for failures an agent can act
+/// on. The registry turns every failed call into a `ToolResult` whose
+/// content carries a stable `code` and a remediation `hint`, so clients can
+/// branch on the failure type instead of parsing messages.
+
+use serde_json::{json, Value};
+use std::io::ErrorKind;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ToolError {
+    /// A file, process, plan, memory or other named item does not exist
+    #[error("{0}")]
+    NotFound(String),
+    /// The OS or a policy refused access
+    #[error("{0}")]
+    PermissionDenied(String),
+    /// The call or something it waited on ran out of time
+    #[error("{0}")]
+    Timeout(String),
+    /// Arguments are missing, malformed or inconsistent
+    #[error("{0}")]
+    InvalidArgument(String),
+    /// The action, format or platform is not supported here
+    #[error("{0}")]
+    Unsupported(String),
+    /// The target changed since the caller last saw it
+    #[error("{0}")]
+    Conflict(String),
+    /// A command, helper process or remote service failed
+    #[error("{0}")]
+    External(String),
+}
+
+impl ToolError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound(message.into())
+    }
+
+    pub fn permission_denied(message: impl Into<String>) -> Self {
+        Self::PermissionDenied(message.into())
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::Timeout(message.into())
+    }
+
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::InvalidArgument(message.into())
+    }
+
+    pub fn unsupported(message: impl Into<String>) -> Self {
+        Self::Unsupported(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::Conflict(message.into())
+    }
+
+    pub fn external(message: impl Into<String>) -> Self {
+        Self::External(message.into())
+    }
+
+    /// Stable machine-readable code
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "not_found",
+            Self::PermissionDenied(_) => "permission_denied",
+            Self::Timeout(_) => "timeout",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::Unsupported(_) => "unsupported",
+            Self::Conflict(_) => "conflict",
+            Self::External(_) => "external",
+        }
+    }
+
+    /// What the caller can do about it
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "Check the path, id or name; list what exists first",
+            Self::PermissionDenied(_) => "Use a path or resource the server is allowed to access",
+            Self::Timeout(_) => "Retry with a smaller request, run it in the background, or raise the timeout in config",
+            Self::InvalidArgument(_) => "Fix the arguments; call the tool with action=help for usage",
+            Self::Unsupported(_) => "Use another action or format, or install the missing helper",
+            Self::Conflict(_) => "Re-read the target to see the other change, then retry against its current state",
+            Self::External(_) => "A command or service failed; read the message, fix the cause and retry",
+        }
+    }
+
+    /// `{"error": code, "message", "hint"}` for `ToolResult` content
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": self.code(),
+            "message": self.to_string(),
+            "hint": self.hint()
+        })
+    }
+
+    /// Categorize any error: a `ToolError` in the chain wins, then I/O error
+    /// kinds and argument deserialization; anything else is `External`
+    pub fn classify(err: &anyhow::Error) -> Self {
+        let message = format!("{:#}", err);
+        for cause in err.chain() {
+            if let Some(tool_error) = cause.downcast_ref::<ToolError>() {
+                return match tool_error {
+                    // Keep context added on top of the original message
+                    Self::NotFound(_) => Self::NotFound(message),
+                    Self::PermissionDenied(_) => Self::PermissionDenied(message),
+                    Self::Timeout(_) => Self::Timeout(message),
+                    Self::InvalidArgument(_) => Self::InvalidArgument(message),
+                    Self::Unsupported(_) => Self::Unsupported(message),
+                    Self::Conflict(_) => Self::Conflict(message),
+                    Self::External(_) => Self::External(message),
+                };
+            }
+        }
+        for cause in err.chain() {
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                match io.kind() {
+                    ErrorKind::NotFound => return Self::NotFound(message),
+                    ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => return Self::PermissionDenied(message),
+                    ErrorKind::TimedOut => return Self::Timeout(message),
+                    ErrorKind::InvalidInput | ErrorKind::InvalidData => return Self::InvalidArgument(message),
+                    ErrorKind::Unsupported => return Self::Unsupported(message),
+                    _ => {}
+                }
+            }
+            if cause.is::<serde_json::Error>() {
+                return Self::InvalidArgument(message);
+            }
+        }
+        Self::External(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_classify() {
+        let err = anyhow::Error::from(ToolError::not_found("Plan not found: x")).context("show failed");
+        let classified = ToolError::classify(&err);
+        assert_eq!(classified.code(), "not_found");
+        assert_eq!(classified.to_string(), "show failed: Plan not found: x");
+
+        let io = std::fs::read("/nonexistent/file").context("read /nonexistent/file").unwrap_err();
+        assert_eq!(ToolError::classify(&io).code(), "not_found");
+
+        let serde = serde_json::from_str::<Vec<u8>>("{").unwrap_err();
+        assert_eq!(ToolError::classify(&serde.into()).code(), "invalid_argument");
+
+        assert_eq!(ToolError::classify(&anyhow::anyhow!("boom")).to_json()["error"], "external");
+    }
+}
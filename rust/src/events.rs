@@ -0,0 +1,277 @@
+/// Events published by tools
+///
+/// Tools announce things that happen outside the call that caused them: a
+/// background process exiting, a file changing, a download finishing, a
+/// plan step being completed. Each event gets an increasing id and is kept
+/// in a short history. Clients can subscribe to kinds (`process.exited`,
+/// or a `process.*` prefix) and receive `notifications/hanzo/event` for
+/// them, or block in `events wait` until a matching event arrives.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+pub const PROCESS_EXITED: &str = "process.exited";
+pub const FILE_CHANGED: &str = "file.changed";
+pub const DOWNLOAD_FINISHED: &str = "download.finished";
+pub const PLAN_STEP_COMPLETED: &str = "plan.step_completed";
+
+/// Kinds tools publish
+pub const KINDS: &[&str] = &[PROCESS_EXITED, FILE_CHANGED, DOWNLOAD_FINISHED, PLAN_STEP_COMPLETED];
+
+/// MCP notification carrying a subscribed event
+pub const NOTIFICATION: &str = "notifications/hanzo/event";
+
+/// Field of a client notification naming the sessions it is for; the
+/// server delivers it to those alone and strips the field first
+pub const AUDIENCE: &str = "_sessions";
+
+/// Events kept for `recent` and for waits that start from an earlier id
+const HISTORY: usize = 500;
+
+/// Longest a `wait` may block
+const MAX_WAIT: Duration = Duration::from_secs(600);
+
+/// Wait used when the caller gives none
+const DEFAULT_WAIT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    pub id: u64,
+    pub ts: String,
+    pub kind: String,
+    pub data: Value,
+}
+
+impl Event {
+    /// Whether the event is of `kind` (exact, `prefix.*` or `*`) and its
+    /// data has every field of `filter`
+    fn matches(&self, kind: Option<&str>, filter: &Value) -> bool {
+        kind.is_none_or(|kind| kind_matches(kind, &self.kind))
+            && filter.as_object().is_none_or(|fields| fields.iter().all(|(key, value)| self.data[key] == *value))
+    }
+}
+
+fn kind_matches(pattern: &str, kind: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => kind.starts_with(prefix),
+        None => pattern == kind,
+    }
+}
+
+pub struct EventBus {
+    next: AtomicU64,
+    history: Mutex<VecDeque<Event>>,
+    live: broadcast::Sender<Event>,
+    client: RwLock<Option<broadcast::Sender<Value>>>,
+    /// Kind patterns each session subscribed to
+    subscriptions: Mutex<HashMap<String, Vec<String>>>,
+}
+
+static BUS: Lazy<EventBus> = Lazy::new(EventBus::new);
+
+/// The process-wide bus tools publish on
+pub fn bus() -> &'static EventBus {
+    &BUS
+}
+
+/// Publish an event on the process-wide bus
+pub fn publish(kind: &str, data: Value) {
+    BUS.publish(kind, data);
+}
+
+/// Send subscribed events to clients as MCP notifications on `sender`
+pub fn attach_client(sender: broadcast::Sender<Value>) {
+    *BUS.client.write().unwrap() = Some(sender);
+}
+
+impl EventBus {
+    fn new() -> Self {
+        let (live, _) = broadcast::channel(256);
+        Self {
+            next: AtomicU64::new(0),
+            history: Mutex::new(VecDeque::new()),
+            live,
+            client: RwLock::new(None),
+            subscriptions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn publish(&self, kind: &str, data: Value) -> Event {
+        let event = Event {
+            id: self.next.fetch_add(1, Ordering::SeqCst) + 1,
+            ts: Utc::now().to_rfc3339(),
+            kind: kind.to_string(),
+            data,
+        };
+        {
+            let mut history = self.history.lock().unwrap();
+            history.push_back(event.clone());
+            while history.len() > HISTORY {
+                history.pop_front();
+            }
+        }
+        let _ = self.live.send(event.clone());
+        let audience = self.audience(&event.kind);
+        if !audience.is_empty() {
+            if let Some(client) = self.client.read().unwrap().as_ref() {
+                let _ = client.send(json!({
+                    "jsonrpc": "2.0",
+                    "method": NOTIFICATION,
+                    "params": event,
+                    AUDIENCE: audience
+                }));
+            }
+        }
+        event
+    }
+
+    /// Sessions subscribed to events of `kind`
+    fn audience(&self, kind: &str) -> Vec<String> {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        let mut sessions: Vec<String> = subscriptions.iter()
+            .filter(|(_, patterns)| patterns.iter().any(|pattern| kind_matches(pattern, kind)))
+            .map(|(session, _)| session.clone())
+            .collect();
+        sessions.sort();
+        sessions
+    }
+
+    /// Deliver events of `kinds` to `session`'s client; returns its
+    /// subscriptions
+    pub fn subscribe(&self, session: Option<&str>, kinds: &[String]) -> Vec<String> {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let mine = subscriptions.entry(session.unwrap_or_default().to_string()).or_default();
+        for kind in kinds {
+            if !mine.contains(kind) {
+                mine.push(kind.clone());
+            }
+        }
+        mine.clone()
+    }
+
+    /// Stop delivering `kinds`, or every kind when empty
+    pub fn unsubscribe(&self, session: Option<&str>, kinds: &[String]) -> Vec<String> {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        let Some(mine) = subscriptions.get_mut(session.unwrap_or_default()) else { return Vec::new() };
+        mine.retain(|kind| !kinds.is_empty() && !kinds.contains(kind));
+        mine.clone()
+    }
+
+    pub fn end_session(&self, session: &str) {
+        self.subscriptions.lock().unwrap().remove(session);
+    }
+
+    /// Events after `since` matching `kind` and `filter`, oldest first
+    pub fn recent(&self, kind: Option<&str>, filter: &Value, since: u64, limit: usize) -> Vec<Event> {
+        let history = self.history.lock().unwrap();
+        let matched: Vec<&Event> = history.iter().filter(|e| e.id > since && e.matches(kind, filter)).collect();
+        matched[matched.len().saturating_sub(limit)..].iter().map(|e| (*e).clone()).collect()
+    }
+
+    /// Id of the latest event, to wait for anything after it
+    pub fn last_id(&self) -> u64 {
+        self.next.load(Ordering::SeqCst)
+    }
+
+    /// The first event after `since` matching `kind` and `filter`, waiting
+    /// up to `timeout` for one to be published
+    pub async fn wait(&self, kind: Option<&str>, filter: &Value, since: u64, timeout: Duration) -> Option<Event> {
+        // Listen before looking at the history so nothing slips in between
+        let mut live = self.live.subscribe();
+        if let Some(event) = self.recent(kind, filter, since, HISTORY).into_iter().next() {
+            return Some(event);
+        }
+        let wait = async {
+            loop {
+                match live.recv().await {
+                    Ok(event) if event.id > since && event.matches(kind, filter) => return Some(event),
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.ok().flatten()
+    }
+
+    /// Handle an `events` tool call
+    pub async fn execute(&self, params: &Value, session: Option<&str>) -> Result<Value> {
+        let kind = params["kind"].as_str();
+        let filter = &params["filter"];
+        let kinds: Vec<String> = match &params["kinds"] {
+            Value::Array(kinds) => kinds.iter().filter_map(|k| k.as_str().map(str::to_string)).collect(),
+            _ => kind.map(|k| vec![k.to_string()]).unwrap_or_default(),
+        };
+        match params["action"].as_str().unwrap_or("recent") {
+            "subscribe" => {
+                if kinds.is_empty() {
+                    return Err(ToolError::invalid("kinds required").into());
+                }
+                Ok(json!({ "subscriptions": self.subscribe(session, &kinds), "notification": NOTIFICATION }))
+            }
+            "unsubscribe" => Ok(json!({ "subscriptions": self.unsubscribe(session, &kinds) })),
+            "recent" | "list" => {
+                let limit = params["limit"].as_u64().map_or(50, |n| n as usize);
+                let events = self.recent(kind, filter, params["since_id"].as_u64().unwrap_or(0), limit);
+                Ok(json!({ "events": events, "count": events.len(), "last_id": self.last_id() }))
+            }
+            "wait" | "wait_for_event" => {
+                let timeout = params["timeout_ms"].as_u64().map_or(DEFAULT_WAIT, Duration::from_millis).min(MAX_WAIT);
+                let since = params["since_id"].as_u64().unwrap_or_else(|| self.last_id());
+                match self.wait(kind, filter, since, timeout).await {
+                    Some(event) => Ok(json!({ "event": event })),
+                    None => Err(ToolError::timeout(format!("No matching event within {}ms", timeout.as_millis())).into()),
+                }
+            }
+            "kinds" => Ok(json!({ "kinds": KINDS })),
+            other => Err(ToolError::invalid(format!("Unknown events action: {} (subscribe, unsubscribe, recent, wait, kinds)", other)).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_events() {
+        let bus = EventBus::new();
+        let (client, mut notifications) = broadcast::channel(16);
+        *bus.client.write().unwrap() = Some(client);
+
+        bus.publish(FILE_CHANGED, json!({ "path": "/a" }));
+        assert!(notifications.try_recv().is_err(), "nothing is sent before a subscription");
+        bus.subscribe(Some("s1"), &["process.*".to_string()]);
+        let exited = bus.publish(PROCESS_EXITED, json!({ "proc_id": "proc_1", "exit_code": 0 }));
+        let sent = notifications.try_recv().unwrap();
+        assert_eq!(sent["params"]["id"], exited.id);
+        assert_eq!(sent[AUDIENCE], json!(["s1"]), "only the subscribing session is addressed");
+
+        assert_eq!(bus.recent(Some("file.*"), &Value::Null, 0, 10).len(), 1);
+        let found = bus.wait(Some(PROCESS_EXITED), &json!({ "proc_id": "proc_1" }), 0, Duration::from_millis(10)).await;
+        assert_eq!(found.unwrap().id, exited.id);
+        let none = bus.wait(Some(PROCESS_EXITED), &json!({ "proc_id": "proc_2" }), 0, Duration::from_millis(10)).await;
+        assert!(none.is_none());
+
+        let since = bus.last_id();
+        let waiter = bus.wait(Some(PLAN_STEP_COMPLETED), &Value::Null, since, Duration::from_secs(5));
+        let publisher = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            bus.publish(PLAN_STEP_COMPLETED, json!({ "step": 1 }));
+        };
+        let (found, _) = tokio::join!(waiter, publisher);
+        assert_eq!(found.unwrap().data["step"], 1);
+
+        bus.end_session("s1");
+        bus.publish(PROCESS_EXITED, json!({}));
+        assert!(notifications.try_recv().is_err());
+    }
+}
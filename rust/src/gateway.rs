@@ -0,0 +1,255 @@
+/// REST gateway: the tools as plain HTTP endpoints
+///
+/// For systems that don't speak MCP, such as CI jobs and webhooks, the
+/// server can also answer `POST /tools/{name}` with the call's arguments
+/// as the JSON body, `GET /tools` with the tools there are and
+/// `GET /tools/{name}` with one's schema. Every request presents one of
+/// the configured API keys, as `Authorization: Bearer <key>` or in
+/// `X-API-Key`, and a key may be limited to some tools or actions. Calls
+/// take the registry's path in a session named after their key
+/// (`gateway:ci`), so limits, hooks, read-only mode and the audit log
+/// treat them like an agent's and the log says which key made each.
+
+use crate::config::GatewayConfig;
+use crate::error::ToolError;
+use crate::hooks::ToolCall;
+use crate::ToolRegistry;
+use anyhow::Result;
+use serde_json::{json, Value};
+
+/// Path the tool endpoints are under
+pub const TOOLS_PATH: &str = "/tools";
+
+pub struct Gateway {
+    keys: Vec<Key>,
+}
+
+struct Key {
+    name: String,
+    secret: String,
+    allow: Vec<String>,
+}
+
+impl Key {
+    fn permits(&self, tool: &str, params: &Value) -> bool {
+        self.allow.is_empty() || ToolCall::new(tool, params.clone(), None).matches(&self.allow)
+    }
+
+    /// Whether any call of `tool` is allowed, for listing
+    fn shows(&self, tool: &str) -> bool {
+        self.allow.is_empty() || self.allow.iter().any(|key| key == "*" || key.split('.').next() == Some(tool))
+    }
+
+    fn session(&self) -> String {
+        format!("gateway:{}", self.name)
+    }
+}
+
+/// An HTTP answer: status code and JSON body
+pub type Reply = (u16, Value);
+
+impl Gateway {
+    /// The gateway `config` describes; every key must resolve to a secret
+    pub fn new(config: &GatewayConfig) -> Result<Self> {
+        let mut keys: Vec<Key> = Vec::new();
+        for key in &config.keys {
+            let name = key.name.trim();
+            if name.is_empty() {
+                return Err(ToolError::invalid("Gateway keys need a name").into());
+            }
+            if keys.iter().any(|k| k.name == name) {
+                return Err(ToolError::conflict(format!("Gateway key {} is configured twice", name)).into());
+            }
+            let secret = match (&key.key, &key.key_env) {
+                (Some(secret), None) => secret.clone(),
+                (None, Some(var)) => std::env::var(var)
+                    .map_err(|_| ToolError::invalid(format!("Gateway key {}: {} is not set", name, var)))?,
+                _ => return Err(ToolError::invalid(format!("Gateway key {} needs one of key or key_env", name)).into()),
+            };
+            if secret.trim().is_empty() {
+                return Err(ToolError::invalid(format!("Gateway key {} is empty", name)).into());
+            }
+            keys.push(Key { name: name.to_string(), secret, allow: key.allow.clone() });
+        }
+        if keys.is_empty() {
+            return Err(ToolError::invalid("The REST gateway needs at least one key in [[gateway.keys]]").into());
+        }
+        Ok(Self { keys })
+    }
+
+    /// Whether `path` is one of the gateway's
+    pub fn serves(path: &str) -> bool {
+        path == TOOLS_PATH || path.strip_prefix(TOOLS_PATH).is_some_and(|rest| rest.starts_with('/'))
+    }
+
+    /// The key a request presented: a bearer token, else `X-API-Key`
+    pub fn credential<'a>(authorization: Option<&'a str>, api_key: Option<&'a str>) -> Option<&'a str> {
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer ").or_else(|| value.strip_prefix("bearer ")))
+            .or(api_key)
+            .map(str::trim)
+    }
+
+    fn key(&self, credential: Option<&str>) -> Option<&Key> {
+        credential.and_then(|c| self.keys.iter().find(|k| same(k.secret.as_bytes(), c.as_bytes())))
+    }
+
+    /// The 401 answer when `credential` is none of the keys; checked
+    /// before the body is read, so strangers can't make the server hold one
+    pub fn refuses(&self, credential: Option<&str>) -> Option<Reply> {
+        match self.key(credential) {
+            Some(_) => None,
+            None => Some(error(401, ToolError::permission_denied("Missing or unknown API key"))),
+        }
+    }
+
+    /// The 413 answer to a body over `limit` bytes
+    pub fn too_large(limit: usize) -> Reply {
+        error(413, ToolError::invalid(format!("The body is over the {} byte limit", limit)))
+    }
+
+    /// Answer a request to `path` made with `credential`
+    pub async fn route(&self, method: &str, path: &str, credential: Option<&str>, body: &[u8], tools: &ToolRegistry) -> Reply {
+        let Some(key) = self.key(credential) else {
+            return error(401, ToolError::permission_denied("Missing or unknown API key"));
+        };
+        let name = path.strip_prefix(TOOLS_PATH).unwrap_or_default().trim_matches('/');
+        match (method, name) {
+            ("GET", "") => {
+                let listed: Vec<Value> = tools.get_definitions().into_iter()
+                    .filter(|d| key.shows(tools.canonical(d["name"].as_str().unwrap_or_default())))
+                    .collect();
+                (200, json!({ "tools": listed }))
+            }
+            ("GET", name) => {
+                let definition = tools.get_definitions().into_iter().find(|d| d["name"] == name);
+                match definition {
+                    Some(definition) if key.shows(tools.canonical(name)) => (200, definition),
+                    _ => error(404, ToolError::not_found(format!("Unknown tool: {}", name))),
+                }
+            }
+            ("POST", "") => error(404, ToolError::not_found(format!("POST {}/<name> to call a tool", TOOLS_PATH))),
+            ("POST", name) if name.contains('/') => error(404, ToolError::not_found(format!("Unknown tool: {}", name))),
+            ("POST", name) => self.call(key, name, body, tools).await,
+            _ => error(405, ToolError::unsupported(format!("{} is not supported; GET lists tools, POST calls one", method))),
+        }
+    }
+
+    async fn call(&self, key: &Key, name: &str, body: &[u8], tools: &ToolRegistry) -> Reply {
+        let params = if body.iter().all(u8::is_ascii_whitespace) {
+            json!({})
+        } else {
+            match serde_json::from_slice::<Value>(body) {
+                Ok(params @ Value::Object(_)) => params,
+                Ok(_) => return error(400, ToolError::invalid("The body must be a JSON object of arguments")),
+                Err(e) => return error(400, ToolError::invalid(format!("The body is not JSON: {}", e))),
+            }
+        };
+        if !key.permits(tools.canonical(name), &params) {
+            return error(403, ToolError::permission_denied(format!("Key {} may not call {}", key.name, name)));
+        }
+        match tools.execute_in_session(name, params, Some(&key.session())).await {
+            Ok(result) => {
+                let code = if result.success { 200 } else { status(result.content["error"].as_str()) };
+                (code, json!(result))
+            }
+            Err(e) => {
+                let err = ToolError::classify(&e);
+                error(status(Some(err.code())), err)
+            }
+        }
+    }
+}
+
+/// Status for a failed call's error code
+fn status(code: Option<&str>) -> u16 {
+    match code {
+        Some("invalid_argument") => 400,
+        Some("permission_denied") => 403,
+        Some("not_found") => 404,
+        Some("conflict") => 409,
+        Some("unsupported") => 422,
+        Some("external") => 502,
+        Some("timeout") => 504,
+        _ => 500,
+    }
+}
+
+fn error(code: u16, err: ToolError) -> Reply {
+    (code, json!(crate::ToolResult::from_error(&err)))
+}
+
+/// Compare secrets in time independent of where they differ
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GatewayKey;
+
+    fn gateway() -> Gateway {
+        Gateway::new(&GatewayConfig {
+            enabled: true,
+            keys: vec![
+                GatewayKey { name: "admin".into(), key: Some("s3cret".into()), ..Default::default() },
+                GatewayKey { name: "ci".into(), key: Some("ci-key".into()), allow: vec!["think".into(), "fs.read".into()], ..Default::default() },
+            ],
+        }).unwrap()
+    }
+
+    #[test]
+    fn test_new_needs_keys() {
+        assert!(Gateway::new(&GatewayConfig { enabled: true, keys: vec![] }).is_err());
+        let unset = GatewayKey { name: "ci".into(), key_env: Some("HANZO_GATEWAY_TEST_UNSET".into()), ..Default::default() };
+        assert!(Gateway::new(&GatewayConfig { enabled: true, keys: vec![unset] }).is_err());
+        let twice = GatewayKey { name: "ci".into(), key: Some("k".into()), ..Default::default() };
+        assert!(Gateway::new(&GatewayConfig { enabled: true, keys: vec![twice.clone(), twice] }).is_err());
+    }
+
+    #[test]
+    fn test_paths_and_credentials() {
+        assert!(Gateway::serves("/tools") && Gateway::serves("/tools/fs"));
+        assert!(!Gateway::serves("/") && !Gateway::serves("/toolsx"));
+        assert_eq!(Gateway::credential(Some("Bearer abc"), None), Some("abc"));
+        assert_eq!(Gateway::credential(None, Some("abc")), Some("abc"));
+        assert_eq!(Gateway::credential(Some("Basic abc"), None), None);
+        assert!(same(b"abc", b"abc") && !same(b"abc", b"abd") && !same(b"abc", b"ab"));
+    }
+
+    #[tokio::test]
+    async fn test_route() {
+        let gateway = gateway();
+        let tools = ToolRegistry::new();
+
+        let (code, _) = gateway.route("GET", "/tools", None, b"", &tools).await;
+        assert_eq!(code, 401);
+        let (code, _) = gateway.route("GET", "/tools", Some("wrong"), b"", &tools).await;
+        assert_eq!(code, 401);
+        assert_eq!(gateway.refuses(Some("wrong")).map(|(code, _)| code), Some(401));
+        assert!(gateway.refuses(Some("ci-key")).is_none());
+
+        let (code, listed) = gateway.route("GET", "/tools", Some("ci-key"), b"", &tools).await;
+        assert_eq!(code, 200);
+        let names: Vec<&str> = listed["tools"].as_array().unwrap().iter().filter_map(|d| d["name"].as_str()).collect();
+        assert_eq!(names, ["fs", "think"]);
+        let (code, schema) = gateway.route("GET", "/tools/think", Some("ci-key"), b"", &tools).await;
+        assert_eq!((code, schema["name"].as_str()), (200, Some("think")));
+        assert_eq!(gateway.route("GET", "/tools/exec", Some("ci-key"), b"", &tools).await.0, 404);
+
+        let (code, called) = gateway.route("POST", "/tools/think", Some("ci-key"), br#"{"action": "help"}"#, &tools).await;
+        assert_eq!(code, 200, "{}", called);
+        assert_eq!(called["success"], true);
+        let (code, refused) = gateway.route("POST", "/tools/fs", Some("ci-key"), br#"{"action": "write", "path": "x"}"#, &tools).await;
+        assert_eq!((code, refused["content"]["error"].as_str()), (403, Some("permission_denied")));
+        assert!(gateway.keys[1].permits("fs", &json!({ "action": "READ" })));
+        assert!(!gateway.keys[1].permits("fs", &json!({ "action": "Write" })));
+
+        assert_eq!(gateway.route("POST", "/tools/think", Some("s3cret"), b"[1]", &tools).await.0, 400);
+        assert_eq!(gateway.route("POST", "/tools/think", Some("s3cret"), b"{", &tools).await.0, 400);
+        assert_eq!(gateway.route("POST", "/tools/nope", Some("s3cret"), b"", &tools).await.0, 404);
+        assert_eq!(gateway.route("POST", "/tools/fs", Some("s3cret"), br#"{"action": 3}"#, &tools).await.0, 400);
+        assert_eq!(gateway.route("DELETE", "/tools/fs", Some("s3cret"), b"", &tools).await.0, 405);
+    }
+}
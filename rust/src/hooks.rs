@@ -0,0 +1,453 @@
+/// Middleware run around every tool call
+///
+/// Hooks registered on the `ToolRegistry` see each call once its arguments
+/// are validated, and may refuse it or rewrite its params; once it has run
+/// they may rewrite its result. Permission checks, audit logging and
+/// redaction are built in and configured from `HooksConfig`, as are user
+/// hooks: shell commands and webhooks given the call as JSON. Secrets are
+/// masked by the `redaction` module.
+
+use crate::audit::AuditLog;
+use crate::config::{HookCommand, HookPhase, HooksConfig};
+use crate::error::ToolError;
+use crate::redaction::Redactor;
+use crate::ToolResult;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+pub use crate::redaction::REDACTED;
+
+/// A tool call as hooks see it
+#[derive(Debug, Clone)]
+pub struct ToolCall {
+    pub tool: String,
+    /// The action as the tool's own parser names it, so aliases and case
+    /// variants match the same keys
+    pub action: Option<String>,
+    pub params: Value,
+    pub session: Option<String>,
+    pub started: Instant,
+}
+
+impl ToolCall {
+    pub fn new(tool: &str, params: Value, session: Option<&str>) -> Self {
+        Self {
+            tool: tool.to_string(),
+            action: canonical(tool, params["action"].as_str()),
+            params,
+            session: session.map(str::to_string),
+            started: Instant::now(),
+        }
+    }
+
+    /// Whether one of `keys` names this call's tool or tool and action
+    pub fn matches(&self, keys: &[String]) -> bool {
+        keys.iter().any(|key| match key.split_once('.') {
+            Some((tool, action)) => tool == self.tool && self.action == canonical(tool, Some(action)),
+            None => key == "*" || *key == self.tool,
+        })
+    }
+
+    /// `tool` or `tool.action`
+    pub fn key(&self) -> String {
+        match &self.action {
+            Some(action) => format!("{}.{}", self.tool, action),
+            None => self.tool.clone(),
+        }
+    }
+}
+
+/// `action` as `tool`'s own parser names it, or as given for actions and
+/// tools the registry doesn't parse
+fn canonical(tool: &str, action: Option<&str>) -> Option<String> {
+    action.map(|action| crate::canonical_action(tool, action).unwrap_or_else(|| action.to_string()))
+}
+
+/// What a pre hook decided about a call
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Allow,
+    Deny(String),
+}
+
+#[async_trait::async_trait]
+pub trait Hook: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Refuse the call, or rewrite its params, before it runs
+    async fn before(&self, _call: &mut ToolCall) -> Decision {
+        Decision::Allow
+    }
+
+    /// Inspect or rewrite the result; runs for refused and failed calls too
+    async fn after(&self, _call: &ToolCall, _result: &mut ToolResult) {}
+}
+
+/// Hooks in the order they run
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<Arc<dyn Hook>>,
+    redactor: Option<Arc<Redactor>>,
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl Hooks {
+    /// Permission checks first, then user hooks, redaction and audit, so
+    /// the audit log sees results as the client will
+    pub fn from_config(config: &HooksConfig) -> Result<Self> {
+        let mut hooks = Self::default();
+        if !config.allow.is_empty() || !config.deny.is_empty() {
+            hooks.push(Arc::new(PermissionHook { allow: config.allow.clone(), deny: config.deny.clone() }));
+        }
+        for command in &config.commands {
+            hooks.push(Arc::new(CommandHook::new(command.clone())?));
+        }
+        let redactor = Arc::new(Redactor::from_config(config)?);
+        if !redactor.is_empty() || config.secrets.env_values {
+            hooks.redactor = Some(redactor.clone());
+            hooks.push(Arc::new(RedactionHook::new(redactor.clone())));
+        }
+        if config.audit.enabled {
+            let log = Arc::new(AuditLog::open(&config.audit)?.redacting(hooks.redactor.clone()));
+            hooks.audit = Some(log.clone());
+            hooks.push(Arc::new(AuditHook { log }));
+        }
+        Ok(hooks)
+    }
+
+    pub fn push(&mut self, hook: Arc<dyn Hook>) {
+        self.hooks.push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// The redactor masking results, for other writers to share
+    pub fn redactor(&self) -> Option<Arc<Redactor>> {
+        self.redactor.clone()
+    }
+
+    /// The audit log calls are written to, when auditing is on
+    pub fn audit_log(&self) -> Option<Arc<AuditLog>> {
+        self.audit.clone()
+    }
+
+    /// The first refusal, naming the hook that refused
+    pub async fn before(&self, call: &mut ToolCall) -> Decision {
+        for hook in &self.hooks {
+            if let Decision::Deny(reason) = hook.before(call).await {
+                return Decision::Deny(format!("{} refused by {} hook: {}", call.key(), hook.name(), reason));
+            }
+        }
+        Decision::Allow
+    }
+
+    pub async fn after(&self, call: &ToolCall, result: &mut ToolResult) {
+        for hook in &self.hooks {
+            hook.after(call, result).await;
+        }
+    }
+}
+
+/// Allow and deny lists of tools and actions
+pub struct PermissionHook {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl Hook for PermissionHook {
+    fn name(&self) -> &str {
+        "permission"
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Decision {
+        if call.matches(&self.deny) {
+            return Decision::Deny("denied by policy".to_string());
+        }
+        if !self.allow.is_empty() && !call.matches(&self.allow) {
+            return Decision::Deny("not in the allowed tools".to_string());
+        }
+        Decision::Allow
+    }
+}
+
+/// Masks secrets in results, learning the values of `.env` files it sees
+/// read on the way
+pub struct RedactionHook {
+    redactor: Arc<Redactor>,
+}
+
+impl RedactionHook {
+    pub fn new(redactor: Arc<Redactor>) -> Self {
+        Self { redactor }
+    }
+}
+
+#[async_trait::async_trait]
+impl Hook for RedactionHook {
+    fn name(&self) -> &str {
+        "redaction"
+    }
+
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) {
+        let paths = call.params["path"].as_str().into_iter()
+            .chain(call.params["paths"].as_array().into_iter().flatten().filter_map(Value::as_str));
+        for path in paths {
+            learn_strings(&self.redactor, path, &result.content);
+        }
+        self.redactor.redact_value(&mut result.content);
+        if let Some(redacted) = result.error.as_deref().and_then(|e| self.redactor.redact(e)) {
+            result.error = Some(redacted);
+        }
+    }
+}
+
+fn learn_strings(redactor: &Redactor, path: &str, value: &Value) {
+    match value {
+        Value::String(text) => redactor.learn(path, text),
+        Value::Array(items) => items.iter().for_each(|item| learn_strings(redactor, path, item)),
+        Value::Object(map) => map.values().for_each(|item| learn_strings(redactor, path, item)),
+        _ => {}
+    }
+}
+
+/// Writes every call with its outcome and duration to the audit log
+pub struct AuditHook {
+    log: Arc<AuditLog>,
+}
+
+#[async_trait::async_trait]
+impl Hook for AuditHook {
+    fn name(&self) -> &str {
+        "audit"
+    }
+
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) {
+        self.log.record(call, result);
+    }
+}
+
+/// A configured shell command or webhook
+///
+/// Pre hooks get `{phase, tool, action, params, session}` and refuse the
+/// call by exiting non-zero (stderr is the reason), answering
+/// `{"allow": false, "reason": ...}`, or returning non-2xx; answering
+/// `{"params": {...}}` rewrites the call. Post hooks also get `result` and
+/// may answer `{"content": ...}` to replace what the client receives.
+/// How a user hook answered
+enum Outcome {
+    /// Ran and allowed, possibly answering JSON
+    Answer(Option<Value>),
+    /// Deliberately refused
+    Refused(String),
+    /// Could not be run or did not finish
+    Failed(String),
+}
+
+pub struct CommandHook {
+    config: HookCommand,
+    client: reqwest::Client,
+}
+
+impl CommandHook {
+    pub fn new(config: HookCommand) -> Result<Self> {
+        if config.command.is_some() == config.url.is_some() {
+            return Err(ToolError::invalid("A hook needs exactly one of command or url").into());
+        }
+        Ok(Self { config, client: reqwest::Client::new() })
+    }
+
+    async fn run(&self, payload: &Value) -> Outcome {
+        let limit = Duration::from_secs(self.config.timeout_secs.max(1));
+        let refusal = |reason: &str, fallback: String| {
+            Outcome::Refused(if reason.trim().is_empty() { fallback } else { reason.trim().to_string() })
+        };
+        let body = match (&self.config.command, &self.config.url) {
+            (Some(command), _) => {
+                let mut child = match tokio::process::Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                {
+                    Ok(child) => child,
+                    Err(e) => return Outcome::Failed(format!("cannot run hook: {}", e)),
+                };
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                let _ = stdin.write_all(payload.to_string().as_bytes()).await;
+                drop(stdin);
+                let output = match tokio::time::timeout(limit, child.wait_with_output()).await {
+                    Ok(Ok(output)) => output,
+                    Ok(Err(e)) => return Outcome::Failed(e.to_string()),
+                    Err(_) => return Outcome::Failed("hook timed out".to_string()),
+                };
+                if !output.status.success() {
+                    return refusal(&String::from_utf8_lossy(&output.stderr), format!("hook exited with {}", output.status));
+                }
+                String::from_utf8_lossy(&output.stdout).into_owned()
+            }
+            (None, Some(url)) => {
+                let response = match self.client.post(url).timeout(limit).json(payload).send().await {
+                    Ok(response) => response,
+                    Err(e) => return Outcome::Failed(format!("hook request failed: {}", e)),
+                };
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                if !status.is_success() {
+                    return refusal(&text, format!("hook answered {}", status));
+                }
+                text
+            }
+            (None, None) => return Outcome::Answer(None),
+        };
+        Outcome::Answer(serde_json::from_str::<Value>(body.trim()).ok().filter(Value::is_object))
+    }
+}
+
+#[async_trait::async_trait]
+impl Hook for CommandHook {
+    fn name(&self) -> &str {
+        if self.config.url.is_some() { "webhook" } else { "command" }
+    }
+
+    async fn before(&self, call: &mut ToolCall) -> Decision {
+        if self.config.phase != HookPhase::Pre || (!self.config.tools.is_empty() && !call.matches(&self.config.tools)) {
+            return Decision::Allow;
+        }
+        let payload = json!({
+            "phase": "pre",
+            "tool": call.tool,
+            "action": call.action,
+            "params": call.params,
+            "session": call.session
+        });
+        match self.run(&payload).await {
+            Outcome::Answer(Some(answer)) if answer["allow"] == false => {
+                Decision::Deny(answer["reason"].as_str().unwrap_or("refused").to_string())
+            }
+            Outcome::Answer(Some(answer)) => {
+                if answer["params"].is_object() {
+                    call.params = answer["params"].clone();
+                    call.action = canonical(&call.tool, call.params["action"].as_str());
+                }
+                Decision::Allow
+            }
+            Outcome::Answer(None) => Decision::Allow,
+            Outcome::Refused(reason) => Decision::Deny(reason),
+            Outcome::Failed(reason) if self.config.fail_closed => Decision::Deny(reason),
+            Outcome::Failed(reason) => {
+                log::warn!("{} hook failed, allowing {}: {}", self.name(), call.key(), reason);
+                Decision::Allow
+            }
+        }
+    }
+
+    async fn after(&self, call: &ToolCall, result: &mut ToolResult) {
+        if self.config.phase != HookPhase::Post || (!self.config.tools.is_empty() && !call.matches(&self.config.tools)) {
+            return;
+        }
+        let payload = json!({
+            "phase": "post",
+            "tool": call.tool,
+            "action": call.action,
+            "params": call.params,
+            "session": call.session,
+            "result": { "success": result.success, "content": result.content, "error": result.error },
+            "duration_ms": call.started.elapsed().as_millis() as u64
+        });
+        match self.run(&payload).await {
+            Outcome::Answer(Some(answer)) => {
+                if let Some(content) = answer.get("content") {
+                    result.content = content.clone();
+                }
+            }
+            Outcome::Answer(None) => {}
+            Outcome::Refused(reason) | Outcome::Failed(reason) => {
+                log::warn!("{} post hook failed for {}: {}", self.name(), call.key(), reason);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AuditConfig;
+
+    /// Config writing no audit log
+    fn unaudited() -> HooksConfig {
+        HooksConfig { audit: AuditConfig { enabled: false, ..Default::default() }, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_builtin_hooks() {
+        let hooks = Hooks::from_config(&HooksConfig {
+            deny: vec!["fs.write".into()],
+            redact: vec![r"sk-[A-Za-z0-9]{8,}".into()],
+            ..unaudited()
+        }).unwrap();
+
+        let mut write = ToolCall::new("fs", json!({ "action": "write" }), None);
+        assert!(matches!(hooks.before(&mut write).await, Decision::Deny(reason) if reason.contains("fs.write")));
+        let mut read = ToolCall::new("fs", json!({ "action": "read" }), None);
+        assert_eq!(hooks.before(&mut read).await, Decision::Allow);
+        let mut shouted = ToolCall::new("fs", json!({ "action": "Write" }), None);
+        assert!(matches!(hooks.before(&mut shouted).await, Decision::Deny(_)));
+
+        let patches = Hooks::from_config(&HooksConfig { deny: vec!["fs.apply_patch".into()], ..unaudited() }).unwrap();
+        let mut patch = ToolCall::new("fs", json!({ "action": "patch" }), None);
+        assert_eq!(patch.key(), "fs.patch");
+        assert!(matches!(patches.before(&mut patch).await, Decision::Deny(_)));
+        let mut alias = ToolCall::new("fs", json!({ "action": "apply_patch" }), None);
+        assert!(matches!(patches.before(&mut alias).await, Decision::Deny(_)));
+
+        let mut result = ToolResult::ok(json!({ "data": { "content": ["key=sk-abcdef123456 ok"] } }));
+        hooks.after(&read, &mut result).await;
+        assert_eq!(result.content["data"]["content"][0], "key=[REDACTED] ok");
+
+        let only = Hooks::from_config(&HooksConfig { allow: vec!["fs".into()], ..unaudited() }).unwrap();
+        let mut exec = ToolCall::new("exec", json!({}), None);
+        assert!(matches!(only.before(&mut exec).await, Decision::Deny(_)));
+        assert!(Hooks::from_config(&HooksConfig { redact: vec!["(".into()], ..unaudited() }).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_command_hooks() {
+        let hooks = Hooks::from_config(&HooksConfig {
+            commands: vec![
+                HookCommand {
+                    command: Some(r#"grep -q '"rm' && { echo 'no deleting' >&2; exit 1; } || echo '{"params": {"action": "exec", "command": "echo rewritten"}}'"#.into()),
+                    tools: vec!["exec".into()],
+                    ..Default::default()
+                },
+                HookCommand {
+                    phase: HookPhase::Post,
+                    command: Some(r#"echo '{"content": {"replaced": true}}'"#.into()),
+                    tools: vec!["fs.read".into()],
+                    ..Default::default()
+                },
+            ],
+            ..unaudited()
+        }).unwrap();
+
+        let mut rm = ToolCall::new("exec", json!({ "action": "exec", "command": "rm -rf /" }), None);
+        assert_eq!(hooks.before(&mut rm).await, Decision::Deny("exec.exec refused by command hook: no deleting".into()));
+        let mut ls = ToolCall::new("exec", json!({ "action": "exec", "command": "ls" }), None);
+        assert_eq!(hooks.before(&mut ls).await, Decision::Allow);
+        assert_eq!(ls.params["command"], "echo rewritten");
+
+        let read = ToolCall::new("fs", json!({ "action": "read" }), None);
+        let mut result = ToolResult::ok(json!({ "content": "secret" }));
+        hooks.after(&read, &mut result).await;
+        assert_eq!(result.content, json!({ "replaced": true }));
+    }
+}
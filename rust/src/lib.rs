@@ -1,3 +1,5 @@
+#![recursion_limit = "256"]
+
 /// Hanzo MCP Server - Rust implementation (HIP-0300)
 ///
 /// Provides full tool parity with Python hanzo-mcp:
@@ -10,15 +12,69 @@
 /// - browser: Playwright-based browser automation
 /// - mode: Development modes
 /// - search: Unified code search
+/// - diagnostics: Linter and compiler findings
+/// - test: Test runs with structured results
+/// - task: Project-defined commands (cargo, npm scripts, make, just)
+/// - lsp: Language server hover, definitions, references, rename
+/// - repl: Persistent python, node and deno sessions
+/// - scratch: Large text passed between calls by handle
+/// - docker: Containers, images and compose projects
+/// - k8s: Kubernetes pods, deployments, logs, exec, apply and rollouts
+/// - storage: S3-compatible object storage (S3, GCS, MinIO)
+/// - notify: Email, Slack, webhook and desktop alerts to humans
+/// - calendar: Calendar events and reminders
+/// - image: Resize, crop, convert, annotate, diff and EXIF for images
+/// - vector: Persistent vector collections queried by similarity
+/// - sysinfo: Host OS, resources, runtimes and power state
+/// - stats: Per-tool execution metrics
+/// - page: Further pages of an oversized result
+/// - batch: Several tool calls in one request
+/// - audit: Review of the tool call audit log
+/// - workflow: Configured multi-step sequences of tool calls
+/// - schedule: Delayed, recurring and cron-timed tool calls
+/// - events: Subscriptions to and waits for events tools publish
+///
+/// Systems that don't speak MCP can call the same tools over plain HTTP
+/// (`POST /tools/fs`) with an API key; see `gateway`.
+///
+/// Tools of other MCP servers can be mounted beside these under a prefix
+/// (`github_create_issue`), reached through this server's permissions and
+/// audit log; see `proxy`.
+///
+/// Hosts aggregating several servers can namespace every tool (`hanzo.fs`)
+/// and give tools aliases (`proc` for exec); see `naming`. Each client is
+/// shown only the tools suited to it and the host; see `advertise`.
 
+pub mod advertise;
+pub mod audit;
+pub mod completion;
 pub mod config;
+pub mod error;
+pub mod events;
 pub mod ffi;
+pub mod gateway;
+pub mod hooks;
+pub mod limits;
+pub mod logging;
+pub mod metrics;
+pub mod naming;
+pub mod pagination;
+pub mod redaction;
+pub mod sampling;
+pub mod schedule;
+pub mod schema;
 pub mod server;
 pub mod protocol;
+pub mod proxy;
+pub mod read_only;
 pub mod tools;
+pub mod truncation;
+pub mod workflow;
 pub mod search;
 
 pub use config::Config;
+pub use error::ToolError;
+pub use metrics::Metrics;
 pub use server::MCPServer;
 pub use tools::{
     ExecTool, FsTool, PlanTool, ThinkTool, MemoryTool,
@@ -31,8 +87,22 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+
+/// Calls one batch may hold
+const MAX_BATCH_CALLS: usize = 100;
+
+/// Calls a parallel batch runs at once unless told otherwise
+const BATCH_CONCURRENCY: usize = 8;
+
+/// Tools the registry serves itself rather than through an `MCPTool`
+const REGISTRY_TOOLS: &[&str] = &["stats", "page", "audit", "batch", "workflow", "schedule", "events"];
+
+/// A downstream server and the tools mounted from it, each with whether
+/// the server says it is read-only
+type Mount = (Arc<proxy::Downstream>, Vec<(String, bool)>);
 
 /// MCP Tool trait that all tools must implement
 #[async_trait::async_trait]
@@ -46,16 +116,47 @@ pub trait MCPTool: Send + Sync {
     /// Get the tool's parameters schema
     fn parameters(&self) -> serde_json::Value;
 
+    /// Schema of the result's `structuredContent`; results of tools
+    /// without one are sent as text only
+    fn output_schema(&self) -> Option<serde_json::Value> {
+        None
+    }
+
     /// Execute the tool with given parameters
     async fn execute(&self, params: serde_json::Value) -> Result<ToolResult>;
+
+    /// Execute on behalf of a caller; tools with session-scoped state
+    /// override this, the rest ignore the context
+    async fn execute_with(&self, params: serde_json::Value, _context: &CallContext) -> Result<ToolResult> {
+        self.execute(params).await
+    }
+}
+
+/// Who a tool call is made for
+#[derive(Debug, Clone, Default)]
+pub struct CallContext {
+    /// MCP session that owns any session-scoped state the call creates
+    pub session: Option<String>,
+    /// Project root the session works in
+    pub project: Option<PathBuf>,
 }
 
 /// Result from tool execution
+///
+/// `content` is the tool's JSON output, sent to clients as text and, when it
+/// is an object and the tool declares an output schema, as
+/// `structuredContent`. `blocks` carries extra MCP content such as images
+/// and embedded resources so clients can render them natively.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToolResult {
     pub success: bool,
     pub content: serde_json::Value,
     pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<protocol::Content>,
+    /// Fields of `content` a block carries, left out of the text block
+    #[serde(skip)]
+    pub embedded: Vec<&'static str>,
 }
 
 impl ToolResult {
@@ -64,6 +165,8 @@ impl ToolResult {
             success: true,
             content,
             error: None,
+            blocks: Vec::new(),
+            embedded: Vec::new(),
         }
     }
 
@@ -72,238 +175,1196 @@ impl ToolResult {
             success: false,
             content: json!(null),
             error: Some(message.to_string()),
+            blocks: Vec::new(),
+            embedded: Vec::new(),
+        }
+    }
+
+    /// Failure with machine-readable details
+    pub fn failure(message: String, details: Value) -> Self {
+        Self {
+            success: false,
+            content: details,
+            error: Some(message),
+            blocks: Vec::new(),
+            embedded: Vec::new(),
+        }
+    }
+
+    /// Failure categorized by `ToolError`, with its code and hint as content
+    pub fn from_error(err: &ToolError) -> Self {
+        Self::failure(err.to_string(), err.to_json())
+    }
+
+    pub fn with_blocks(mut self, blocks: Vec<protocol::Content>) -> Self {
+        self.blocks.extend(blocks);
+        self
+    }
+
+    /// Add `block`, which carries the content's `field`, such as a read
+    /// file's text, so the text block need not repeat it
+    pub fn embedding(mut self, field: &'static str, block: protocol::Content) -> Self {
+        self.blocks.push(block);
+        self.embedded.push(field);
+        self
+    }
+
+    /// MCP `tools/call` result; `structured` when the tool declares an
+    /// output schema. A failure's details (error code, hint, retry delay,
+    /// validation errors) follow its message as a second text block.
+    pub fn to_call_result(&self, structured: bool) -> Value {
+        let text = if self.success {
+            match self.content.as_object() {
+                Some(object) if !self.embedded.is_empty() => {
+                    let shown: serde_json::Map<String, Value> = object.iter()
+                        .filter(|(key, _)| !self.embedded.contains(&key.as_str()))
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect();
+                    serde_json::to_string(&shown).unwrap_or_default()
+                }
+                _ => serde_json::to_string(&self.content).unwrap_or_default(),
+            }
+        } else {
+            self.error.clone().unwrap_or_else(|| "Unknown tool error".to_string())
+        };
+        let mut content = vec![json!(protocol::Content::text(text))];
+        if !self.success && self.content.as_object().is_some_and(|details| !details.is_empty()) {
+            content.push(json!(protocol::Content::text(self.content.to_string())));
+        }
+        content.extend(self.blocks.iter().map(|block| json!(block)));
+        let mut result = json!({
+            "content": content,
+            "isError": !self.success
+        });
+        if structured && self.content.is_object() {
+            result["structuredContent"] = self.content.clone();
         }
+        result
     }
 }
 
-/// Tool wrapper for unified execution
+/// Tool wrapper for unified execution: a built-in tool as an `MCPTool`
+/// (see `tools::builtin`)
 pub struct ToolWrapper<T> {
     pub tool: Arc<RwLock<T>>,
     pub name: String,
     pub description: String,
     pub schema: Value,
+    pub output: Option<Value>,
 }
 
 /// Tool registry for managing all available tools
 pub struct ToolRegistry {
-    tools: HashMap<String, Box<dyn MCPTool>>,
+    /// Built-in and registered tools in definition order
+    tools: Vec<Box<dyn MCPTool>>,
+    /// Names still served by the built-in tool, which the registry routes
+    /// by session and extends with reports
+    builtins: std::collections::HashSet<String>,
+    names: naming::Names,
+    advertiser: advertise::Advertiser,
     exec: Arc<RwLock<ExecTool>>,
+    /// Processes exec, task and docker start, kept when exec is reconfigured
+    processes: Arc<tools::exec_tool::ProcessManager>,
     fs: Arc<RwLock<FsTool>>,
     code: Arc<RwLock<CodeTool>>,
-    git: Arc<RwLock<GitTool>>,
-    fetch: Arc<RwLock<FetchTool>>,
-    workspace: Arc<RwLock<WorkspaceTool>>,
+    diagnostics: Arc<RwLock<tools::DiagnosticsTool>>,
+    test: Arc<RwLock<tools::TestTool>>,
+    lsp: Arc<RwLock<tools::LspTool>>,
+    scratch: Arc<RwLock<tools::ScratchTool>>,
     plan: Arc<RwLock<PlanTool>>,
     think: Arc<RwLock<ThinkTool>>,
     memory: Arc<RwLock<MemoryTool>>,
     computer: Arc<RwLock<ComputerTool>>,
     browser: Arc<RwLock<BrowserTool>>,
-    mode: Arc<RwLock<ModeTool>>,
-    tasks: Arc<RwLock<TasksTool>>,
-    hanzo: Arc<RwLock<HanzoTool>>,
+    storage: Arc<RwLock<tools::StorageTool>>,
+    notify: Arc<RwLock<tools::NotifyTool>>,
+    calendar: Arc<RwLock<tools::CalendarTool>>,
+    vector: Arc<RwLock<tools::VectorStoreTool>>,
+    notifications: broadcast::Sender<Value>,
+    metrics: Arc<Metrics>,
+    limiter: limits::Limiter,
+    timeouts: config::TimeoutConfig,
+    pages: pagination::Pager,
+    hooks: hooks::Hooks,
+    read_only: bool,
+    roots: Arc<tools::Roots>,
+    /// Plans of roots other than the startup one, made on first use
+    project_plans: std::sync::Mutex<HashMap<PathBuf, Arc<RwLock<PlanTool>>>>,
+    workflows: HashMap<String, config::Workflow>,
+    scheduler: Option<Arc<schedule::Scheduler>>,
+    /// Symbol tables the search tool reads and the indexer keeps warm
+    symbols: search::symbol_index::SymbolTables,
+    indexer: Option<Arc<search::indexer::Indexer>>,
+    /// Downstream servers whose tools are mounted
+    mounts: Vec<Mount>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
-        Self {
-            tools: HashMap::new(),
-            exec: Arc::new(RwLock::new(ExecTool::new())),
+        let plan = PlanTool::new();
+        let notifications = plan.notifier();
+        let exec = ExecTool::new();
+        let task = tools::TaskTool::new(exec.manager());
+        let docker = tools::DockerTool::new(exec.manager());
+        let roots = Arc::new(tools::Roots::new());
+        let mut registry = Self {
+            tools: Vec::new(),
+            builtins: std::collections::HashSet::new(),
+            names: naming::Names::default(),
+            advertiser: advertise::Advertiser::new(config::AdvertiseConfig::default()),
+            processes: exec.manager(),
+            exec: Arc::new(RwLock::new(exec)),
             fs: Arc::new(RwLock::new(FsTool::new())),
             code: Arc::new(RwLock::new(CodeTool::new())),
-            git: Arc::new(RwLock::new(GitTool::new())),
-            fetch: Arc::new(RwLock::new(FetchTool::new())),
-            workspace: Arc::new(RwLock::new(WorkspaceTool::new())),
-            plan: Arc::new(RwLock::new(PlanTool::new())),
+            diagnostics: Arc::new(RwLock::new(tools::DiagnosticsTool::new())),
+            test: Arc::new(RwLock::new(tools::TestTool::new())),
+            lsp: Arc::new(RwLock::new(tools::LspTool::new())),
+            scratch: Arc::new(RwLock::new(tools::ScratchTool::new())),
+            plan: Arc::new(RwLock::new(plan)),
             think: Arc::new(RwLock::new(ThinkTool::new())),
-            memory: Arc::new(RwLock::new(MemoryTool::new())),
+            memory: Arc::new(RwLock::new(MemoryTool::shared())),
             computer: Arc::new(RwLock::new(ComputerTool::new())),
-            browser: Arc::new(RwLock::new(BrowserTool::new())),
-            mode: Arc::new(RwLock::new(ModeTool::new())),
-            tasks: Arc::new(RwLock::new(TasksTool::new())),
-            hanzo: Arc::new(RwLock::new(HanzoTool::new())),
+            browser: Arc::new(RwLock::new(BrowserTool::with_roots(roots.clone()))),
+            storage: Arc::new(RwLock::new(tools::StorageTool::new())),
+            notify: Arc::new(RwLock::new(tools::NotifyTool::new())),
+            calendar: Arc::new(RwLock::new(tools::CalendarTool::new())),
+            vector: Arc::new(RwLock::new(tools::VectorStoreTool::new())),
+            notifications,
+            metrics: Arc::new(Metrics::new()),
+            limiter: limits::Limiter::new(config::default_limits()),
+            timeouts: config::TimeoutConfig::default(),
+            pages: pagination::Pager::new(config::PaginationConfig::default()),
+            hooks: hooks::Hooks::default(),
+            read_only: false,
+            roots,
+            project_plans: std::sync::Mutex::new(HashMap::new()),
+            workflows: HashMap::new(),
+            scheduler: None,
+            symbols: Default::default(),
+            indexer: None,
+            mounts: Vec::new(),
+        };
+        let builtins: Vec<Box<dyn MCPTool>> = vec![
+            Box::new(ToolWrapper::shared(registry.exec.clone())),
+            Box::new(ToolWrapper::shared(registry.fs.clone())),
+            Box::new(ToolWrapper::new(tools::SearchTool::with_roots(registry.roots.clone()).sharing(registry.symbols.clone()))),
+            Box::new(ToolWrapper::shared(registry.plan.clone())),
+            Box::new(ToolWrapper::shared(registry.think.clone())),
+            Box::new(ToolWrapper::shared(registry.memory.clone())),
+            Box::new(ToolWrapper::shared(registry.computer.clone())),
+            Box::new(ToolWrapper::shared(registry.browser.clone())),
+            Box::new(ToolWrapper::new(ModeTool::new())),
+            Box::new(ToolWrapper::shared(registry.code.clone())),
+            Box::new(ToolWrapper::shared(registry.diagnostics.clone())),
+            Box::new(ToolWrapper::shared(registry.test.clone())),
+            Box::new(ToolWrapper::new(task)),
+            Box::new(ToolWrapper::shared(registry.lsp.clone())),
+            Box::new(ToolWrapper::new(tools::ReplTool::new())),
+            Box::new(ToolWrapper::shared(registry.scratch.clone())),
+            Box::new(ToolWrapper::new(GitTool::new())),
+            Box::new(ToolWrapper::new(FetchTool::new())),
+            Box::new(ToolWrapper::new(docker)),
+            Box::new(ToolWrapper::new(tools::K8sTool::new())),
+            Box::new(ToolWrapper::new(WorkspaceTool::with_roots(registry.roots.clone()))),
+            Box::new(ToolWrapper::shared(registry.storage.clone())),
+            Box::new(ToolWrapper::shared(registry.notify.clone())),
+            Box::new(ToolWrapper::shared(registry.calendar.clone())),
+            Box::new(ToolWrapper::new(tools::ImageTool::new())),
+            Box::new(ToolWrapper::shared(registry.vector.clone())),
+            Box::new(ToolWrapper::new(tools::SysinfoTool::new())),
+            Box::new(ToolWrapper::new(TasksTool::new())),
+            Box::new(ToolWrapper::new(HanzoTool::new())),
+            Box::new(ToolWrapper::new(tools::IndexTool::new())),
+        ];
+        for tool in builtins {
+            registry.register_builtin(tool);
         }
+        registry.names = naming::Names::new(&config::NamingConfig::default(), &registry.list()).unwrap_or_default();
+        registry
     }
 
+    /// Receive MCP notifications emitted by built-in tools
+    pub fn subscribe_notifications(&self) -> broadcast::Receiver<Value> {
+        self.notifications.subscribe()
+    }
+
+    /// Channel for sending MCP notifications to connected clients
+    pub fn notifier(&self) -> broadcast::Sender<Value> {
+        self.notifications.clone()
+    }
+
+    /// Resources exposed by built-in tools
+    pub async fn list_resources(&self) -> Vec<Value> {
+        let mut resources = self.plan.read().await.resources().await;
+        resources.extend(self.scratch.read().await.resources().await);
+        resources
+    }
+
+    /// Read a resource by URI
+    pub async fn read_resource(&self, uri: &str) -> Result<Value> {
+        if uri.starts_with(tools::scratch_tool::SCRATCH_URI_PREFIX) {
+            return self.scratch.read().await.read_resource(uri).await;
+        }
+        self.plan.read().await.read_resource(uri).await
+    }
+
+    /// Release state owned by an MCP session that has disconnected
+    pub async fn end_session(&self, session_id: &str, archive: bool) -> Result<Value> {
+        self.fs.read().await.end_session(session_id);
+        self.roots.end_session(session_id);
+        events::bus().end_session(session_id);
+        self.browser.read().await.end_session(session_id).await;
+        if let Some(scheduler) = &self.scheduler {
+            scheduler.end_session(session_id);
+        }
+        self.memory.read().await.end_session(session_id, archive).await
+    }
+
+    /// Add a tool, replacing any tool of the same name, built-ins included
     pub fn register(&mut self, tool: Box<dyn MCPTool>) {
-        self.tools.insert(tool.name().to_string(), tool);
+        self.builtins.remove(tool.name());
+        self.insert(tool);
+    }
+
+    /// Take a tool out, e.g. to register a wrapper around it
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn MCPTool>> {
+        self.builtins.remove(name);
+        let index = self.tools.iter().position(|t| t.name() == name)?;
+        Some(self.tools.remove(index))
     }
 
     pub fn get(&self, name: &str) -> Option<&Box<dyn MCPTool>> {
-        self.tools.get(name)
+        self.tools.iter().find(|t| t.name() == name)
     }
 
     pub fn list(&self) -> Vec<String> {
-        let mut names: Vec<String> = self.tools.keys().cloned().collect();
-        // Add built-in tools (all 13 HIP-0300 canonical + search alias + browser extension)
-        names.extend(vec![
-            "exec".into(), "fs".into(), "code".into(), "git".into(),
-            "fetch".into(), "workspace".into(), "computer".into(),
-            "think".into(), "memory".into(), "hanzo".into(),
-            "plan".into(), "tasks".into(), "mode".into(),
-            "search".into(), "browser".into(),
-        ]);
+        let mut names: Vec<String> = self.tools.iter().map(|t| t.name().to_string()).collect();
+        names.extend(REGISTRY_TOOLS.iter().map(|name| name.to_string()));
         names.sort();
         names.dedup();
         names
     }
 
+    fn register_builtin(&mut self, tool: Box<dyn MCPTool>) {
+        self.builtins.insert(tool.name().to_string());
+        self.insert(tool);
+    }
+
+    fn insert(&mut self, tool: Box<dyn MCPTool>) {
+        match self.tools.iter_mut().find(|t| t.name() == tool.name()) {
+            Some(slot) => *slot = tool,
+            None => self.tools.push(tool),
+        }
+    }
+
     /// Execute a tool by name
     pub async fn execute(&self, name: &str, params: Value) -> Result<ToolResult> {
-        match name {
-            "exec" => {
-                let args: tools::ExecToolArgs = serde_json::from_value(params)?;
-                let result = self.exec.read().await.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+        self.execute_in_session(name, params, None).await
+    }
+
+    /// Execute a tool on behalf of an MCP session, which owns any
+    /// session-scoped state the tool creates
+    pub async fn execute_in_session(&self, name: &str, mut params: Value, session: Option<&str>) -> Result<ToolResult> {
+        let name = self.names.resolve(name);
+        if name == "stats" {
+            return self.stats(&params);
+        }
+        if name == "page" {
+            return self.page(&params, session);
+        }
+        if name == "audit" {
+            return self.audit(&params, session);
+        }
+        if name == "workflow" {
+            return self.workflow(&params, session).await;
+        }
+        if name == "schedule" {
+            return self.schedule(&params, session);
+        }
+        if name == "events" {
+            if let Some(invalid) = self.validate("events", &params) {
+                return Ok(invalid);
             }
-            "fs" => {
-                let args: tools::FsToolArgs = serde_json::from_value(params)?;
-                let result = self.fs.read().await.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+            return match events::bus().execute(&params, session).await {
+                Ok(content) => Ok(ToolResult::ok(content)),
+                Err(e) => Ok(ToolResult::from_error(&ToolError::classify(&e))),
+            };
+        }
+        if name == "batch" {
+            let mut result = self.batch(&params, session).await?;
+            if result.success {
+                result.content = self.pages.paginate(name, std::mem::take(&mut result.content), session);
             }
-            "search" => {
-                let mut args: tools::FsToolArgs = serde_json::from_value(params)?;
-                if args.action.is_empty() {
-                    args.action = "search".to_string();
+            return Ok(result);
+        }
+        // Keyed by the action the tool will run, so a limit on fs.search
+        // also holds for grep
+        let action = canonical_action(name, params["action"].as_str().unwrap_or_default())
+            .or_else(|| params["action"].as_str().map(str::to_string));
+        let _permit = match self.limiter.acquire(name, action.as_deref()) {
+            Ok(permit) => permit,
+            Err(exceeded) => return Ok(ToolResult::failure(exceeded.message(), exceeded.to_json())),
+        };
+        let timeout = self.timeouts.for_call(name, action.as_deref());
+        let op = self.metrics.start(name, action.as_deref());
+        let invalid = self.validate(name, &params);
+        let budget = truncation::take_budget(name, &mut params);
+        self.roots.resolve(name, &mut params, session);
+        let mut call = hooks::ToolCall::new(name, params, session);
+        let result = if let Some(invalid) = invalid {
+            Ok(invalid)
+        } else if self.read_only && !self.permits(name, &call.params) {
+            log::warn!("Refused {} in read-only mode", call.key());
+            Ok(ToolResult::from_error(&ToolError::permission_denied(format!("{} is disabled in read-only mode", call.key()))))
+        } else if let Some(reason) = self.roots.refuses(&call) {
+            log::warn!("Refused: {}", reason);
+            Ok(ToolResult::from_error(&ToolError::permission_denied(reason)))
+        } else if let hooks::Decision::Deny(reason) = self.hooks.before(&mut call).await {
+            log::warn!("Hook denied {}: {}", call.key(), reason);
+            Ok(ToolResult::from_error(&ToolError::permission_denied(reason)))
+        } else {
+            // Dropping a timed-out call drops its futures, which kills
+            // children spawned with kill_on_drop
+            match timeout {
+                Some(limit) => match tokio::time::timeout(limit, self.dispatch(name, call.params.clone(), session)).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        log::warn!("Tool {} timed out after {}s", name, limit.as_secs());
+                        let err = ToolError::timeout(format!("Tool {} timed out after {}s", name, limit.as_secs()));
+                        let mut result = ToolResult::from_error(&err);
+                        result.content["timeout_ms"] = json!(limit.as_millis() as u64);
+                        Ok(result)
+                    }
+                },
+                None => self.dispatch(name, call.params.clone(), session).await,
+            }
+        };
+        let mut result = result.unwrap_or_else(|e| ToolResult::from_error(&ToolError::classify(&e)));
+        self.hooks.after(&call, &mut result).await;
+        if result.success {
+            if let Some(budget) = budget {
+                result.content = truncation::fit(name, std::mem::take(&mut result.content), budget, &self.pages, session);
+            }
+            result.content = self.pages.paginate(name, std::mem::take(&mut result.content), session);
+        }
+        let bytes = if result.success {
+            result.content.to_string().len()
+        } else {
+            result.error.as_ref().map_or(0, String::len)
+        };
+        self.metrics.finish(op, result.success, bytes);
+        Ok(result)
+    }
+
+    /// Check arguments against the tool's input schema, returning the
+    /// failure to send back when they don't conform
+    fn validate(&self, name: &str, params: &Value) -> Option<ToolResult> {
+        let schema = match self.get(name) {
+            Some(tool) => tool.parameters(),
+            None => self.definitions().into_iter()
+                .find(|d| d["name"] == name)?
+                .get("inputSchema")?
+                .clone(),
+        };
+        let params = if params.is_null() { &json!({}) } else { params };
+        let errors = schema::validate_with(&schema, params, &|key, value| accepts_alias(name, key, value));
+        if errors.is_empty() {
+            return None;
+        }
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        let err = ToolError::invalid(format!("Invalid arguments for {}: {}", name, messages.join("; ")));
+        let mut result = ToolResult::from_error(&err);
+        result.content["errors"] = json!(errors);
+        Some(result)
+    }
+
+    /// Replace the per-tool rate limits and concurrency caps
+    pub fn set_limits(&mut self, limits: HashMap<String, config::ToolLimit>) {
+        self.limiter = limits::Limiter::new(limits);
+    }
+
+    /// Add a hook run around every call, after those already registered
+    pub fn register_hook(&mut self, hook: Arc<dyn hooks::Hook>) {
+        self.hooks.push(hook);
+    }
+
+    /// Replace all hooks with the built-in and user hooks in `config`
+    pub fn configure_hooks(&mut self, config: &config::HooksConfig) -> Result<()> {
+        self.hooks = hooks::Hooks::from_config(config)?;
+        Ok(())
+    }
+
+    /// Add the configured project roots
+    pub fn configure_workspaces(&mut self, workspaces: &[config::WorkspaceRoot]) -> Result<()> {
+        self.roots.configure(workspaces)
+    }
+
+    /// Replace the workflows the `workflow` tool runs
+    pub fn configure_workflows(&mut self, workflows: &HashMap<String, config::Workflow>) -> Result<()> {
+        for (name, workflow) in workflows {
+            workflow::check(name, workflow)?;
+        }
+        self.workflows = workflows.clone();
+        Ok(())
+    }
+
+    /// Keep scheduled calls in `config.dir`, or turn scheduling off
+    pub fn configure_schedule(&mut self, config: &config::ScheduleConfig) -> Result<()> {
+        self.scheduler = match config.enabled {
+            true => Some(Arc::new(schedule::Scheduler::open(config)?.redacting(self.redactor()))),
+            false => None,
+        };
+        Ok(())
+    }
+
+    /// Offer the configured device profiles to the browser's emulate
+    pub fn configure_browser(&mut self, browser: config::BrowserConfig) {
+        self.browser = Arc::new(RwLock::new(BrowserTool::with_roots(self.roots.clone()).configured(browser)));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.browser.clone())));
+    }
+
+    /// Mount the tools of the configured downstream MCP servers. A server
+    /// that cannot be reached, or a tool that would hide one already
+    /// here, is logged and left out.
+    pub async fn mount_proxies(&mut self, config: &config::ProxyConfig) {
+        let mut servers: Vec<_> = config.servers.iter().filter(|(_, server)| server.enabled).collect();
+        servers.sort_by_key(|(name, _)| name.as_str());
+        for (name, server) in servers {
+            let tools = match proxy::mount(name, server).await {
+                Ok(tools) => tools,
+                Err(e) => {
+                    log::warn!("Cannot mount MCP server {}: {}", name, e);
+                    continue;
+                }
+            };
+            let Some(downstream) = tools.first().map(|tool| tool.downstream().clone()) else {
+                log::warn!("MCP server {} has no tools to mount", name);
+                continue;
+            };
+            let taken: Vec<Value> = self.definitions().into_iter().map(|d| d["name"].clone()).collect();
+            let mut mounted = Vec::new();
+            for tool in tools {
+                if taken.contains(&json!(tool.name())) {
+                    log::warn!("Not mounting {} from {}: the name is taken", tool.name(), name);
+                    continue;
                 }
-                let result = self.fs.read().await.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+                mounted.push((tool.name().to_string(), tool.read_only()));
+                self.register(Box::new(tool));
             }
-            "plan" => {
-                let args: tools::PlanToolArgs = serde_json::from_value(params)?;
-                let result = self.plan.read().await.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+            log::info!("Mounted {} tools from MCP server {}", mounted.len(), name);
+            self.mounts.push((downstream, mounted));
+        }
+    }
+
+    /// Mounted downstream servers and the tools each gave
+    pub fn mounts(&self) -> Vec<Value> {
+        self.mounts.iter()
+            .map(|(downstream, tools)| {
+                let mut described = downstream.describe();
+                described["tools"] = json!(tools.iter().map(|(name, _)| name).collect::<Vec<_>>());
+                described
+            })
+            .collect()
+    }
+
+    /// Whether a call leaves everything as it found it; mounted tools
+    /// are taken at their server's word
+    fn permits(&self, name: &str, params: &Value) -> bool {
+        let mounted = self.mounts.iter().flat_map(|(_, tools)| tools).find(|(tool, _)| tool == name);
+        match mounted {
+            Some((_, read_only)) => *read_only,
+            None => read_only::permits(name, params),
+        }
+    }
+
+    /// Keep the search indexes of the roots warm in the background, or not;
+    /// the server starts the passes
+    pub fn configure_index(&mut self, config: &config::IndexConfig) {
+        self.indexer = config.enabled.then(|| Arc::new(search::indexer::Indexer::new(config.clone(), self.symbols.clone())));
+        let tool = self.indexer.clone().map_or_else(tools::IndexTool::new, tools::IndexTool::with_indexer);
+        self.register_builtin(Box::new(ToolWrapper::new(tool)));
+    }
+
+    /// Indexer the server runs, when indexing is on
+    pub fn indexer(&self) -> Option<Arc<search::indexer::Indexer>> {
+        self.indexer.clone()
+    }
+
+    /// Scheduler whose due calls the server runs
+    pub fn scheduler(&self) -> Option<Arc<schedule::Scheduler>> {
+        self.scheduler.clone()
+    }
+
+    /// Run one scheduled call in the session that added it and log its
+    /// outcome; a job whose session has ended is refused and dropped
+    pub async fn run_scheduled(&self, job: &schedule::Job) {
+        let Some(scheduler) = &self.scheduler else { return };
+        let started = chrono::Utc::now();
+        let orphaned = scheduler.orphaned(job);
+        let result = if orphaned {
+            log::warn!("Refused scheduled {}: its session has ended", job.id);
+            ToolResult::from_error(&ToolError::permission_denied("The session that scheduled this job has ended"))
+        } else {
+            let session = job.session.as_deref();
+            logging::in_session(session, self.execute_in_session(&job.tool, job.params.clone(), session)).await
+                .unwrap_or_else(|e| ToolResult::from_error(&ToolError::classify(&e)))
+        };
+        scheduler.finish(job, &result, started);
+        if orphaned {
+            let _ = scheduler.cancel(&job.id);
+        }
+    }
+
+    /// Project roots, shared with the workspace tool
+    pub fn roots(&self) -> Arc<tools::Roots> {
+        self.roots.clone()
+    }
+
+    /// Plan tool of the root `session` works in
+    fn plan_for(&self, session: Option<&str>) -> Arc<RwLock<PlanTool>> {
+        let root = self.roots.active(session);
+        if root.source == tools::RootSource::Startup {
+            return self.plan.clone();
+        }
+        let mut plans = self.project_plans.lock().unwrap();
+        plans.entry(root.path.clone())
+            .or_insert_with(|| Arc::new(RwLock::new(PlanTool::for_project(&root.path).with_notifier(self.notifications.clone()))))
+            .clone()
+    }
+
+    /// Refuse calls that could change files, processes or state
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The redactor masking secrets in results, when one is configured
+    pub fn redactor(&self) -> Option<Arc<redaction::Redactor>> {
+        self.hooks.redactor()
+    }
+
+    /// Replace the result paging limits; outstanding cursors are dropped
+    pub fn set_pagination(&mut self, pagination: config::PaginationConfig) {
+        self.pages = pagination::Pager::new(pagination);
+    }
+
+    /// Replace the per-tool execution timeouts
+    pub fn set_timeouts(&mut self, timeouts: config::TimeoutConfig) {
+        self.timeouts = timeouts;
+    }
+
+    /// Replace the defaults exec runs commands with; tracked processes
+    /// are kept
+    pub fn configure_exec(&mut self, exec: config::ExecConfig) {
+        self.exec = Arc::new(RwLock::new(ExecTool::with_config(exec).with_manager(self.processes.clone())));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.exec.clone())));
+    }
+
+    /// Replace the fs undo journal limits and read guards; recorded
+    /// changes are dropped
+    pub fn configure_fs(&mut self, journal: config::JournalConfig, fs: config::FsConfig) {
+        self.fs = Arc::new(RwLock::new(FsTool::with_config(journal, fs)));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.fs.clone())));
+    }
+
+    /// Replace the object storage profiles the storage tool uses
+    pub fn configure_storage(&mut self, storage: config::StorageConfig) {
+        self.storage = Arc::new(RwLock::new(tools::StorageTool::with_config(storage)));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.storage.clone())));
+    }
+
+    /// Replace the channels the notify tool sends through
+    pub fn configure_notify(&mut self, notify: config::NotifyConfig) {
+        self.notify = Arc::new(RwLock::new(tools::NotifyTool::with_config(notify)));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.notify.clone())));
+    }
+
+    /// Choose where the calendar tool keeps events and reminders
+    pub fn configure_calendar(&mut self, calendar: config::CalendarConfig) -> Result<()> {
+        self.calendar = Arc::new(RwLock::new(tools::CalendarTool::with_config(calendar)?));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.calendar.clone())));
+        Ok(())
+    }
+
+    /// Choose where the vector store keeps collections and how it embeds text
+    pub fn configure_vector(&mut self, vector: config::VectorConfig) {
+        self.vector = Arc::new(RwLock::new(tools::VectorStoreTool::with_config(vector)));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.vector.clone())));
+    }
+
+    /// Advertise tools under a prefix and answer to the configured aliases;
+    /// call after registering every tool the aliases name
+    pub fn configure_naming(&mut self, naming: &config::NamingConfig) -> Result<()> {
+        self.names = naming::Names::new(naming, &self.list())?;
+        Ok(())
+    }
+
+    /// Choose which tools `tools/list` shows each client
+    pub fn configure_advertise(&mut self, advertise: config::AdvertiseConfig) {
+        self.advertiser = advertise::Advertiser::new(advertise);
+    }
+
+    /// The vector store behind the `vector` tool, for other subsystems to share
+    pub async fn vector_store(&self) -> Arc<search::vector_store::VectorStore> {
+        self.vector.read().await.store()
+    }
+
+    /// Replace the programs the code, diagnostics and test tools run;
+    /// remembered test failures are dropped
+    pub fn configure_code(&mut self, code: config::CodeConfig) {
+        self.test = Arc::new(RwLock::new(tools::TestTool::with_config(code.clone())));
+        self.lsp = Arc::new(RwLock::new(tools::LspTool::with_config(code.clone())));
+        self.diagnostics = Arc::new(RwLock::new(tools::DiagnosticsTool::with_config(code.clone())));
+        self.code = Arc::new(RwLock::new(CodeTool::with_config(code)));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.code.clone())));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.diagnostics.clone())));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.test.clone())));
+        self.register_builtin(Box::new(ToolWrapper::shared(self.lsp.clone())));
+    }
+
+    /// Execution metrics accumulated since the registry was created
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    fn stats(&self, params: &Value) -> Result<ToolResult> {
+        if let Some(invalid) = self.validate("stats", params) {
+            return Ok(invalid);
+        }
+        let tool = params["tool"].as_str();
+        match params["action"].as_str().unwrap_or("show") {
+            "show" => Ok(ToolResult::ok(self.metrics.snapshot(tool))),
+            "reset" => {
+                self.metrics.reset();
+                Ok(ToolResult::ok(json!({ "reset": true })))
             }
-            "think" => {
-                let args: tools::ThinkToolArgs = serde_json::from_value(params)?;
-                let result = self.think.read().await.execute(args).await?;
-                Ok(ToolResult::ok(result))
+            other => Ok(ToolResult::from_error(&ToolError::invalid(format!("Unknown stats action: {} (show, reset)", other)))),
+        }
+    }
+
+    fn page(&self, params: &Value, session: Option<&str>) -> Result<ToolResult> {
+        if let Some(invalid) = self.validate("page", params) {
+            return Ok(invalid);
+        }
+        let cursor = params["cursor"].as_str().unwrap_or_default();
+        match self.pages.page(cursor, session) {
+            Ok(page) => Ok(ToolResult::ok(page)),
+            Err(e) => Ok(ToolResult::from_error(&ToolError::classify(&e))),
+        }
+    }
+
+    /// Markdown report of the session's plan, reasoning threads and recent
+    /// tool calls, written to `output` when given
+    async fn report(&self, params: &Value, session: Option<&str>, output: Option<&str>) -> Result<Value> {
+        let get = tools::PlanToolArgs { action: "get".to_string(), ..Default::default() };
+        let plan: Value = serde_json::from_str(&self.plan_for(session).read().await.execute(get).await?)?;
+        let export = tools::ThinkToolArgs {
+            action: Some("export".to_string()),
+            branch: params["branch"].as_str().map(str::to_string),
+            ..Default::default()
+        };
+        let journal = self.think.read().await.execute(export).await?;
+        let thoughts = journal["data"]["entries"].as_array().cloned().unwrap_or_default();
+        // Calls outside any MCP session are logged with a null session
+        let limit = params["limit"].as_u64().map_or(tools::report::DEFAULT_CALLS, |n| n as usize);
+        let calls = match self.hooks.audit_log() {
+            Some(log) => {
+                let all = log.query(&json!({ "session": session, "limit": u64::MAX }))?;
+                let mut calls: Vec<Value> = all["entries"].as_array().into_iter().flatten()
+                    .filter(|entry| entry["session"] == json!(session))
+                    .cloned()
+                    .collect();
+                calls.drain(..calls.len().saturating_sub(limit));
+                Some(calls)
             }
-            "memory" => {
-                let args: tools::MemoryToolArgs = serde_json::from_value(params)?;
-                let result = self.memory.read().await.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+            None => None,
+        };
+
+        let report = tools::report::Report {
+            title: params["title"].as_str(),
+            session,
+            plan: Some(&plan),
+            thoughts: Some(&thoughts),
+            calls: calls.as_deref(),
+        }.render();
+        Ok(match output {
+            Some(output) => {
+                let path = shellexpand::tilde(output).to_string();
+                tokio::fs::write(&path, &report).await?;
+                json!({ "path": path, "bytes": report.len() })
             }
-            "computer" => {
-                let args: tools::ComputerToolArgs = serde_json::from_value(params)?;
-                let mut computer = self.computer.write().await;
-                let result = computer.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+            None => json!({ "format": "markdown", "report": report }),
+        })
+    }
+
+    /// Review the audit log; session `current` means the caller's own
+    fn audit(&self, params: &Value, session: Option<&str>) -> Result<ToolResult> {
+        if let Some(invalid) = self.validate("audit", params) {
+            return Ok(invalid);
+        }
+        let Some(log) = self.hooks.audit_log() else {
+            return Ok(ToolResult::from_error(&ToolError::unsupported("Audit logging is disabled (hooks.audit.enabled)")));
+        };
+        let mut query = params.clone();
+        if query["session"] == "current" {
+            query["session"] = json!(session);
+        }
+        let result = match params["action"].as_str().unwrap_or("query") {
+            "query" => log.query(&query),
+            "sessions" => log.sessions(),
+            other => Err(ToolError::invalid(format!("Unknown audit action: {} (query, sessions)", other)).into()),
+        };
+        match result {
+            Ok(content) => Ok(ToolResult::ok(content)),
+            Err(e) => Ok(ToolResult::from_error(&ToolError::classify(&e))),
+        }
+    }
+
+    fn schedule(&self, params: &Value, session: Option<&str>) -> Result<ToolResult> {
+        if let Some(invalid) = self.validate("schedule", params) {
+            return Ok(invalid);
+        }
+        let Some(scheduler) = &self.scheduler else {
+            return Ok(ToolResult::from_error(&ToolError::unsupported("Scheduling is disabled (schedule.enabled)")));
+        };
+        let id = || params["id"].as_str().ok_or_else(|| anyhow::Error::from(ToolError::invalid("id required")));
+        let result = match params["action"].as_str().unwrap_or("list") {
+            "add" | "create" | "new" => scheduler.add(params, session).map(|job| json!({ "job": job, "scheduled": true })),
+            "list" => {
+                let jobs = scheduler.list();
+                Ok(json!({ "jobs": jobs, "count": jobs.len(), "dir": scheduler.dir() }))
             }
-            "browser" => {
-                let args: tools::BrowserToolArgs = serde_json::from_value(params)?;
-                let result = self.browser.read().await.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+            "show" | "get" => id().and_then(|id| scheduler.get(id)).map(|job| json!({ "job": job })),
+            "cancel" | "remove" | "delete" => id().and_then(|id| scheduler.cancel(id)).map(|job| json!({ "job": job, "cancelled": true })),
+            "logs" | "results" => id().and_then(|id| scheduler.logs(id, params["tail"].as_u64().map(|n| n as usize))),
+            other => Err(ToolError::invalid(format!("Unknown schedule action: {} (add, list, show, cancel, logs)", other)).into()),
+        };
+        match result {
+            Ok(content) => Ok(ToolResult::ok(content)),
+            Err(e) => Ok(ToolResult::from_error(&ToolError::classify(&e))),
+        }
+    }
+
+    /// Run the calls in `params.calls` in the requested mode, each through
+    /// the same limits, validation and timeouts as a lone call
+    async fn batch(&self, params: &Value, session: Option<&str>) -> Result<ToolResult> {
+        if let Some(invalid) = self.validate("batch", params) {
+            return Ok(invalid);
+        }
+        let calls = params["calls"].as_array().cloned().unwrap_or_default();
+        if calls.len() > MAX_BATCH_CALLS {
+            let err = ToolError::invalid(format!("A batch holds at most {} calls, got {}", MAX_BATCH_CALLS, calls.len()));
+            return Ok(ToolResult::from_error(&err));
+        }
+        let mode = params["mode"].as_str().unwrap_or("sequential");
+        let started = std::time::Instant::now();
+
+        let run = |index: usize, call: Value| async move {
+            let tool = call["tool"].as_str().unwrap_or_default().to_string();
+            let args = call.get("params").or_else(|| call.get("arguments")).cloned().unwrap_or_else(|| json!({}));
+            let call_started = std::time::Instant::now();
+            let result = if tool == "batch" {
+                ToolResult::from_error(&ToolError::invalid("Batches cannot be nested"))
+            } else {
+                Box::pin(self.execute_in_session(&tool, args, session)).await
+                    .unwrap_or_else(|e| ToolResult::from_error(&ToolError::classify(&e)))
+            };
+            let mut item = json!({
+                "index": index,
+                "tool": tool,
+                "success": result.success,
+                "duration_ms": call_started.elapsed().as_millis() as u64
+            });
+            if let Some(id) = call.get("id") {
+                item["id"] = id.clone();
             }
-            "mode" => {
-                let args: tools::ModeToolArgs = serde_json::from_value(params)?;
-                let result = self.mode.read().await.execute(args).await?;
-                Ok(ToolResult::ok(serde_json::from_str(&result)?))
+            if result.success {
+                item["result"] = result.content;
+            } else {
+                item["error"] = json!(result.error);
+                item["details"] = result.content;
             }
-            "code" => {
-                let args: tools::CodeToolArgs = serde_json::from_value(params)?;
-                let result = self.code.read().await.execute(args).await?;
-                Ok(ToolResult::ok(result))
+            item
+        };
+
+        let mut items = Vec::with_capacity(calls.len());
+        match mode {
+            "parallel" => {
+                use futures::stream::StreamExt;
+                let limit = params["max_concurrency"].as_u64().map_or(BATCH_CONCURRENCY, |n| n.max(1) as usize);
+                let runs = calls.into_iter().enumerate().map(|(index, call)| run(index, call));
+                items = futures::stream::iter(runs).buffered(limit).collect().await;
             }
-            "git" => {
-                let args: tools::GitToolArgs = serde_json::from_value(params)?;
-                let result = self.git.read().await.execute(args).await?;
-                Ok(ToolResult::ok(result))
+            _ => {
+                let stop_on_error = mode == "stop_on_error";
+                let mut failed = false;
+                for (index, call) in calls.into_iter().enumerate() {
+                    if failed {
+                        items.push(json!({ "index": index, "tool": call["tool"], "id": call.get("id"), "skipped": true }));
+                        continue;
+                    }
+                    let item = run(index, call).await;
+                    failed = stop_on_error && item["success"] == false;
+                    items.push(item);
+                }
             }
-            "fetch" => {
-                let args: tools::FetchToolArgs = serde_json::from_value(params)?;
-                let result = self.fetch.read().await.execute(args).await?;
-                Ok(ToolResult::ok(result))
+        }
+
+        let succeeded = items.iter().filter(|i| i["success"] == true).count();
+        let skipped = items.iter().filter(|i| i["skipped"] == true).count();
+        Ok(ToolResult::ok(json!({
+            "mode": mode,
+            "results": items,
+            "total": items.len(),
+            "succeeded": succeeded,
+            "failed": items.len() - succeeded - skipped,
+            "skipped": skipped,
+            "duration_ms": started.elapsed().as_millis() as u64
+        })))
+    }
+
+    /// List, show or run a workflow; steps run one by one through
+    /// `execute_in_session`, like lone calls
+    async fn workflow(&self, params: &Value, session: Option<&str>) -> Result<ToolResult> {
+        if let Some(invalid) = self.validate("workflow", params) {
+            return Ok(invalid);
+        }
+        let result = match params["action"].as_str().unwrap_or("run") {
+            "list" => {
+                let mut workflows: Vec<Value> = self.workflows.iter().map(|(name, w)| json!({
+                    "name": name,
+                    "description": w.description,
+                    "inputs": w.inputs.keys().collect::<Vec<_>>(),
+                    "steps": w.steps.len()
+                })).collect();
+                workflows.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+                Ok(json!({ "workflows": workflows, "count": workflows.len() }))
             }
-            "workspace" => {
-                let args: tools::WorkspaceToolArgs = serde_json::from_value(params)?;
-                let result = self.workspace.read().await.execute(args).await?;
-                Ok(ToolResult::ok(result))
+            "show" => self.find_workflow(params).map(|(name, workflow)| json!({ "name": name, "workflow": workflow })),
+            "run" => match self.find_workflow(params) {
+                Ok((name, workflow)) => self.run_workflow(&name, &workflow, &params["inputs"], session).await,
+                Err(e) => Err(e),
+            },
+            other => Err(ToolError::invalid(format!("Unknown workflow action: {} (list, show, run)", other)).into()),
+        };
+        match result {
+            Ok(content) => Ok(ToolResult::ok(content)),
+            Err(e) => Ok(ToolResult::from_error(&ToolError::classify(&e))),
+        }
+    }
+
+    /// The configured workflow `params.name`, or the inline `params.workflow`
+    fn find_workflow(&self, params: &Value) -> Result<(String, config::Workflow)> {
+        if let Some(inline) = params.get("workflow").filter(|w| !w.is_null()) {
+            let workflow: config::Workflow = serde_json::from_value(inline.clone())
+                .map_err(|e| ToolError::invalid(format!("Bad workflow: {}", e)))?;
+            workflow::check("inline", &workflow)?;
+            return Ok(("inline".to_string(), workflow));
+        }
+        let name = params["name"].as_str().ok_or_else(|| ToolError::invalid("name or workflow required"))?;
+        let workflow = self.workflows.get(name)
+            .ok_or_else(|| ToolError::not_found(format!("Unknown workflow: {}", name)))?;
+        Ok((name.to_string(), workflow.clone()))
+    }
+
+    async fn run_workflow(&self, name: &str, workflow: &config::Workflow, inputs: &Value, session: Option<&str>) -> Result<Value> {
+        let mut context = workflow::Context::new(workflow::inputs(name, workflow, inputs)?);
+        let started = std::time::Instant::now();
+        let mut items = Vec::with_capacity(workflow.steps.len());
+        let mut stopped = false;
+        for (index, step) in workflow.steps.iter().enumerate() {
+            let mut item = json!({ "index": index, "id": step.id, "tool": step.tool });
+            if stopped {
+                item["skipped"] = json!("an earlier step failed");
+                items.push(item);
+                continue;
             }
-            "tasks" => {
-                let args: tools::TasksToolArgs = serde_json::from_value(params)?;
-                let result = self.tasks.read().await.execute(args).await?;
-                Ok(ToolResult::ok(result))
+            let step_started = std::time::Instant::now();
+            let result = match context.admits(step) {
+                Ok(false) => {
+                    item["skipped"] = json!("condition not met");
+                    context.record(step, json!({ "success": false, "skipped": true }));
+                    items.push(item);
+                    continue;
+                }
+                Ok(true) => match context.args(step) {
+                    Ok(args) => {
+                        item["action"] = args["action"].clone();
+                        Box::pin(self.execute_in_session(&step.tool, args, session)).await
+                            .unwrap_or_else(|e| ToolResult::from_error(&ToolError::classify(&e)))
+                    }
+                    Err(e) => ToolResult::from_error(&ToolError::classify(&e)),
+                },
+                Err(e) => ToolResult::from_error(&ToolError::classify(&e)),
+            };
+            item["success"] = json!(result.success);
+            item["duration_ms"] = json!(step_started.elapsed().as_millis() as u64);
+            let mut outcome = json!({ "success": result.success, "skipped": false });
+            if result.success {
+                item["result"] = result.content.clone();
+                outcome["result"] = result.content;
+            } else {
+                item["error"] = json!(result.error);
+                item["details"] = result.content.clone();
+                outcome["error"] = json!(result.error);
+                stopped = !step.continue_on_error;
             }
-            "hanzo" => {
-                let args: tools::HanzoToolArgs = serde_json::from_value(params)?;
-                let result = self.hanzo.read().await.execute(args).await?;
-                Ok(ToolResult::ok(result))
+            context.record(step, outcome);
+            items.push(item);
+        }
+
+        let succeeded = items.iter().filter(|i| i["success"] == true).count();
+        let failed = items.iter().filter(|i| i["success"] == false).count();
+        Ok(json!({
+            "workflow": name,
+            "success": failed == 0,
+            "steps": items,
+            "total": items.len(),
+            "succeeded": succeeded,
+            "failed": failed,
+            "skipped": items.len() - succeeded - failed,
+            "duration_ms": started.elapsed().as_millis() as u64
+        }))
+    }
+
+    async fn dispatch(&self, name: &str, params: Value, session: Option<&str>) -> Result<ToolResult> {
+        let context = CallContext {
+            session: session.map(str::to_string),
+            project: Some(self.roots.active(session).path.clone()),
+        };
+        let name = match name {
+            "plan" if self.builtins.contains(name) => {
+                if params["action"].as_str().and_then(|a| a.parse().ok()) == Some(tools::plan_tool::PlanAction::Export) {
+                    let output = params["path"].as_str();
+                    return Ok(ToolResult::ok(self.report(&params, session, output).await?));
+                }
+                return <PlanTool as tools::BuiltinTool>::call(&self.plan_for(session), params, &context).await;
             }
-            _ => {
-                if let Some(tool) = self.tools.get(name) {
-                    tool.execute(params).await
-                } else {
-                    Ok(ToolResult::err(&format!("Unknown tool: {}", name)))
+            "think" if self.builtins.contains(name) => {
+                let markdown = matches!(params["format"].as_str().map(str::to_lowercase).as_deref(), Some("markdown" | "md"));
+                if markdown && params["action"].as_str().and_then(|a| a.parse().ok()) == Some(tools::think_tool::LlmAction::Export) {
+                    let data = self.report(&params, session, params["output"].as_str()).await?;
+                    return Ok(ToolResult::ok(json!({
+                        "ok": true,
+                        "data": data,
+                        "error": null,
+                        "meta": { "tool": "think", "action": "export" }
+                    })));
                 }
+                name
             }
+            _ => name,
+        };
+        match self.get(name) {
+            Some(tool) => tool.execute_with(params, &context).await,
+            None => Ok(ToolResult::from_error(&ToolError::not_found(format!("Unknown tool: {}", name)))),
+        }
+    }
+
+    /// Completions for a tool argument, the result of `completion/complete`
+    pub async fn complete(&self, params: &Value, session: Option<&str>) -> Result<Value> {
+        // No prompts or resource templates take arguments
+        if params["ref"]["type"] != "ref/tool" {
+            return Ok(completion::complete(Vec::new(), ""));
         }
+        let tool = params["ref"]["name"].as_str().ok_or_else(|| ToolError::invalid("Missing ref.name"))?;
+        let tool = self.names.resolve(tool);
+        let argument = params["argument"]["name"].as_str().ok_or_else(|| ToolError::invalid("Missing argument.name"))?;
+        let typed = params["argument"]["value"].as_str().unwrap_or("");
+        let candidates = match (tool, argument) {
+            ("fs" | "search", "path" | "paths" | "file_path" | "source" | "dest")
+            | ("code" | "diagnostics" | "test" | "task" | "git" | "workspace" | "lsp", "path") => {
+                completion::paths(&self.roots.active(session).path, typed)
+            }
+            ("computer" | "ui", "title") => self.computer.read().await.window_titles().await.unwrap_or_default(),
+            ("exec" | "proc", "proc_id") => self.exec.read().await.manager().list().await.into_keys().collect(),
+            ("memory", "id" | "ids" | "supersedes") => {
+                let project = self.roots.active(session).path.to_string_lossy().to_string();
+                let viewer = tools::memory_tool::Viewer { session, project: Some(&project) };
+                self.memory.read().await.ids(viewer).await
+            }
+            ("mode", "name") => tools::personality::api::list().into_iter().map(|p| p.name).collect(),
+            ("workflow", "name") => self.workflows.keys().cloned().collect(),
+            ("events", "kind" | "kinds") => events::KINDS.iter().map(|k| k.to_string()).collect(),
+            ("schedule", "id") => self.scheduler.iter().flat_map(|s| s.list()).map(|job| job.id).collect(),
+            _ => {
+                let definition = self.definitions().into_iter()
+                    .find(|d| d["name"] == tool)
+                    .ok_or_else(|| ToolError::not_found(format!("Unknown tool: {}", tool)))?;
+                completion::enumerated(&definition["inputSchema"], argument)
+            }
+        };
+        Ok(completion::complete(candidates, typed))
     }
 
-    /// Get tool definitions for MCP protocol
+    /// The canonical name of `name`, which may be namespaced or an alias
+    pub fn canonical<'a>(&'a self, name: &'a str) -> &'a str {
+        self.names.resolve(name)
+    }
+
+    /// Whether the tool `name` declares an output schema, so its results
+    /// go out as `structuredContent`
+    pub fn declares_output(&self, name: &str) -> bool {
+        let name = self.names.resolve(name);
+        name == "stats" || self.tools.iter().any(|tool| tool.name() == name && tool.output_schema().is_some())
+    }
+
+    /// Definitions as clients see them, under advertised names
     pub fn get_definitions(&self) -> Vec<Value> {
-        let mut definitions = vec![
-            json!({
-                "name": "exec",
-                "description": tools::ExecToolDefinition::new().description,
-                "inputSchema": tools::ExecToolDefinition::new().input_schema
-            }),
-            json!({
-                "name": "fs",
-                "description": tools::FsToolDefinition::new().description,
-                "inputSchema": tools::FsToolDefinition::new().input_schema
-            }),
+        self.names.definitions(self.definitions())
+    }
+
+    /// Definitions shown to a client, given the params it sent to
+    /// initialize (null when unknown)
+    pub fn definitions_for(&self, client: &Value) -> Vec<Value> {
+        let hidden = self.advertiser.hidden(client, |name| self.names.resolve(name).to_string());
+        let mut definitions = self.definitions();
+        definitions.retain(|d| !hidden.contains(d["name"].as_str().unwrap_or_default()));
+        self.names.definitions(definitions)
+    }
+
+    /// Definitions under canonical names
+    fn definitions(&self) -> Vec<Value> {
+        let mut definitions: Vec<Value> = self.tools.iter()
+            .map(|tool| {
+                let mut definition = json!({
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "inputSchema": tool.parameters()
+                });
+                if let Some(output) = tool.output_schema() {
+                    definition["outputSchema"] = output;
+                }
+                definition
+            })
+            .collect();
+        definitions.extend([
             json!({
-                "name": "search",
-                "description": "Search file contents (alias of fs with action=search)",
-                "inputSchema": tools::FsToolDefinition::new().input_schema
+                "name": "batch",
+                "description": "Run several tool calls in one request: sequential (in order), parallel (concurrently) or stop_on_error (in order, skipping the rest after a failure). Returns each call's result or error with its timing",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "calls": {
+                            "type": "array",
+                            "description": "Calls to run",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "tool": {"type": "string", "description": "Tool name"},
+                                    "params": {"type": "object", "description": "Tool arguments"},
+                                    "arguments": {"type": "object", "description": "Alias for params"},
+                                    "id": {"type": "string", "description": "Caller's label, echoed in the result"}
+                                },
+                                "required": ["tool"]
+                            }
+                        },
+                        "mode": {"type": "string", "enum": ["sequential", "parallel", "stop_on_error"], "default": "sequential"},
+                        "max_concurrency": {"type": "integer", "minimum": 1, "description": "For parallel: calls running at once", "default": BATCH_CONCURRENCY}
+                    },
+                    "required": ["calls"]
+                }
             }),
             json!({
-                "name": "plan",
-                "description": tools::PlanToolDefinition::new().description,
-                "inputSchema": tools::PlanToolDefinition::new().input_schema
+                "name": "workflow",
+                "description": "Run a configured multi-step workflow: tool calls in order with templated args ({{inputs.x}}, {{steps.<id>.result...}}) and when-conditions, stopping at the first failing step unless it continues on error. Returns each step's outcome",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "action": {"type": "string", "enum": ["run", "list", "show"], "default": "run"},
+                        "name": {"type": "string", "description": "Configured workflow to show or run"},
+                        "inputs": {"type": "object", "description": "Values for the workflow's inputs"},
+                        "workflow": {"type": "object", "description": "Workflow to run instead of a configured one: {inputs, steps: [{id, tool, args, when, continue_on_error}]}"}
+                    }
+                }
             }),
             json!({
-                "name": "think",
-                "description": tools::ThinkToolDefinition::new().description,
-                "inputSchema": tools::ThinkToolDefinition::new().input_schema
+                "name": "schedule",
+                "description": "Run a tool call later or on a schedule: once after a delay or at a time, every interval, or on a cron expression. Jobs survive restarts; each run's result is logged and read back with logs",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "action": {"type": "string", "enum": ["add", "list", "show", "cancel", "logs"], "default": "list"},
+                        "tool": {"type": "string", "description": "For add: tool to call"},
+                        "params": {"type": "object", "description": "For add: the call's arguments"},
+                        "name": {"type": "string", "description": "For add: label usable in place of the id"},
+                        "delay": {"type": "string", "description": "Run once after this long, e.g. 10m"},
+                        "at": {"type": "string", "description": "Run once at this RFC 3339 time"},
+                        "every": {"type": "string", "description": "Run every interval, e.g. 5m, 1h30m"},
+                        "cron": {"type": "string", "description": "Run on a five-field cron expression in local time, or @hourly, @daily, @weekly, @monthly"},
+                        "max_runs": {"type": "integer", "minimum": 1, "description": "Stop after this many runs"},
+                        "id": {"type": "string", "description": "Job id or name for show, cancel and logs"},
+                        "tail": {"type": "integer", "minimum": 1, "description": "For logs: most recent runs returned", "default": 20}
+                    }
+                }
             }),
             json!({
-                "name": "memory",
-                "description": tools::MemoryToolDefinition::new().description,
-                "inputSchema": tools::MemoryToolDefinition::new().input_schema
+                "name": "events",
+                "description": "Events tools publish (process.exited, file.changed, download.finished, plan.step_completed): subscribe to receive them as notifications/hanzo/event, list recent ones, or wait for the next match",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "action": {"type": "string", "enum": ["recent", "wait", "subscribe", "unsubscribe", "kinds"], "default": "recent"},
+                        "kind": {"type": "string", "description": "Event kind, a prefix like process.*, or *"},
+                        "kinds": {"type": "array", "items": {"type": "string"}, "description": "For subscribe and unsubscribe: kinds or prefixes; unsubscribe without kinds drops all"},
+                        "filter": {"type": "object", "description": "Fields the event's data must have, e.g. {\"proc_id\": \"proc_3\"}"},
+                        "since_id": {"type": "integer", "minimum": 0, "description": "Only events after this id; wait defaults to the latest"},
+                        "timeout_ms": {"type": "integer", "minimum": 0, "description": "For wait: how long to block", "default": 30000},
+                        "limit": {"type": "integer", "minimum": 1, "description": "For recent: most recent events returned", "default": 50}
+                    }
+                }
             }),
             json!({
-                "name": "computer",
-                "description": tools::ComputerToolDefinition::new().description,
-                "inputSchema": tools::ComputerToolDefinition::new().input_schema
+                "name": "page",
+                "description": "Fetch the next page of a result that was too large to return at once, using the cursor from its pagination field",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "cursor": {"type": "string", "description": "pagination.cursor from the previous page"}
+                    },
+                    "required": ["cursor"]
+                }
             }),
             json!({
-                "name": "browser",
-                "description": tools::BrowserToolDefinition::new().description,
-                "inputSchema": tools::BrowserToolDefinition::new().input_schema
+                "name": "audit",
+                "description": "Review the audit log of tool calls: what ran, with which (redacted) params, in which session, how long it took and whether it failed",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "action": {"type": "string", "enum": ["query", "sessions"], "default": "query"},
+                        "session": {"type": "string", "description": "Only calls from this session; \"current\" for the caller's"},
+                        "tool": {"type": "string", "description": "Only calls to this tool or tool.action"},
+                        "since": {"type": "string", "description": "Only calls at or after this RFC 3339 time"},
+                        "failed": {"type": "boolean", "description": "Only failed or refused calls", "default": false},
+                        "limit": {"type": "integer", "minimum": 1, "description": "Most recent entries returned", "default": 100}
+                    }
+                }
             }),
             json!({
-                "name": "mode",
-                "description": tools::ModeToolDefinition::new().description,
-                "inputSchema": tools::ModeToolDefinition::new().input_schema
+                "name": "stats",
+                "description": "Per-tool invocation counts, p50/p95 latency, failure rates, output bytes and running operations since server start",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "action": {"type": "string", "enum": ["show", "reset"], "default": "show"},
+                        "tool": {"type": "string", "description": "Only report this tool"}
+                    }
+                },
+                "outputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "since": {"type": "string"},
+                        "total_calls": {"type": "integer"},
+                        "tools": {"type": "object", "additionalProperties": {"type": "object"}},
+                        "running": {"type": "array", "items": {"type": "object"}},
+                        "reset": {"type": "boolean"}
+                    }
+                }
             }),
-            tools::CodeToolDefinition::schema(),
-            tools::GitToolDefinition::schema(),
-            tools::FetchToolDefinition::schema(),
-            tools::WorkspaceToolDefinition::schema(),
-            tools::TasksToolDefinition::schema(),
-            tools::HanzoToolDefinition::schema(),
-        ];
-
-        // Add custom registered tools
-        for tool in self.tools.values() {
-            definitions.push(json!({
-                "name": tool.name(),
-                "description": tool.description(),
-                "inputSchema": tool.parameters()
-            }));
-        }
-
+        ]);
         definitions
     }
 
@@ -334,6 +1395,116 @@ impl Default for ToolRegistry {
     }
 }
 
+/// The canonical name of `action` as a built-in tool's own parser reads
+/// it (`search` for fs `grep`), or None if the tool has no such action;
+/// an empty action is the tool's default where it has one
+pub(crate) fn canonical_action(tool: &str, action: &str) -> Option<String> {
+    use tools::*;
+
+    fn name<T: std::str::FromStr + serde::Serialize>(action: &str) -> Option<String> {
+        let parsed = action.parse::<T>().ok()?;
+        serde_json::to_value(parsed).ok()?.as_str().map(str::to_string)
+    }
+
+    match tool {
+        "fs" => name::<fs_tool::FsAction>(action),
+        "search" => name::<search_tool::SearchAction>(action),
+        "exec" => name::<exec_tool::ProcAction>(action),
+        "code" => name::<code_tool::CodeAction>(action),
+        "diagnostics" => name::<diagnostics_tool::DiagnosticsAction>(action),
+        "test" => name::<test_tool::TestAction>(action),
+        "task" => name::<task_tool::TaskAction>(action),
+        "lsp" => name::<lsp_tool::LspAction>(action),
+        "repl" => name::<repl_tool::ReplAction>(action),
+        "scratch" => name::<scratch_tool::ScratchAction>(action),
+        "git" => name::<git_tool::VcsAction>(action),
+        "fetch" => name::<fetch_tool::NetAction>(action),
+        "docker" => name::<docker_tool::DockerAction>(action),
+        "k8s" => name::<k8s_tool::K8sAction>(action),
+        "workspace" => name::<workspace_tool::WsAction>(action),
+        "plan" => name::<plan_tool::PlanAction>(action),
+        "think" => name::<think_tool::LlmAction>(action),
+        "memory" => name::<memory_tool::MemoryAction>(action),
+        "computer" => name::<computer_tool::UiAction>(action),
+        "browser" => name::<browser_tool::BrowserAction>(action),
+        "storage" => name::<storage_tool::StorageAction>(action),
+        "notify" => name::<notify_tool::NotifyAction>(action),
+        "calendar" => name::<calendar_tool::CalendarAction>(action),
+        "image" => name::<image_tool::ImageAction>(action),
+        "vector" => name::<vector_tool::VectorAction>(action),
+        "sysinfo" => name::<sysinfo_tool::SysAction>(action),
+        "tasks" => name::<tasks_tool::TodoAction>(action),
+        "mode" => (action == "switch").then(|| action.to_string()),
+        _ => None,
+    }
+}
+
+/// Whether a built-in tool's own parser takes `value` for the enum field
+/// `key`; schemas list canonical names only, not aliases such as `stat` for
+/// fs info or `done` for a completed plan step
+fn accepts_alias(tool: &str, key: &str, value: &Value) -> bool {
+    use tools::*;
+
+    fn parses<T: std::str::FromStr>(value: &str) -> bool {
+        value.parse::<T>().is_ok()
+    }
+
+    let Some(value) = value.as_str() else { return false };
+    match (tool, key) {
+        (_, "action") => canonical_action(tool, value).is_some(),
+        ("fs", "engine") => parses::<fs_template::Engine>(value),
+        ("exec", "mode") => parses::<exec_tool::ExecMode>(value),
+        ("test", "framework") => parses::<test_tool::Framework>(value),
+        ("repl", "language") => parses::<repl_tool::Language>(value),
+        ("k8s", "dry_run") => parses::<k8s_tool::DryRun>(value),
+        ("plan", "status") => parses::<plan_tool::StepStatus>(value),
+        ("think", "relation") => parses::<think_tool::ThoughtRelation>(value),
+        ("think", "format") => value.eq_ignore_ascii_case("md") || value.eq_ignore_ascii_case("markdown") || value.eq_ignore_ascii_case("json"),
+        ("memory", "scope") => parses::<memory_tool::MemoryScope>(value),
+        ("memory", "on_conflict") => parses::<memory_tool::ConflictPolicy>(value),
+        ("memory", "format") => parses::<memory_export::ExportFormat>(value),
+        ("computer", "format") => parses::<computer_tool::CaptureFormat>(value) || parses::<computer_tool::VideoFormat>(value),
+        ("storage", "direction") => parses::<storage_tool::SyncDirection>(value),
+        ("storage", "method") => value.eq_ignore_ascii_case("get") || value.eq_ignore_ascii_case("put"),
+        ("notify", "level") => parses::<notify_tool::Level>(value),
+        ("image", "fit") => parses::<image_tool::Fit>(value),
+        ("image", "format") => parses::<computer_tool::CaptureFormat>(value),
+        ("vector", "metric") => parses::<search::vector_store::Metric>(value),
+        _ => false,
+    }
+}
+
+/// Move base64 image data out of a computer or image result into an image block
+pub(crate) fn take_image(content: &mut Value) -> Option<protocol::Content> {
+    let object = content.as_object_mut()?;
+    let format = object.get("format")?.as_str()?.to_string();
+    let Some(Value::String(data)) = object.remove("base64") else { return None };
+    let mime = if format == "jpg" { "image/jpeg".to_string() } else { format!("image/{}", format) };
+    Some(protocol::Content::image(data, mime))
+}
+
+/// Embedded resource for an fs read, without the line-number gutter
+pub(crate) fn file_resource(content: &Value) -> Option<protocol::Content> {
+    let path = content["path"].as_str()?;
+    let text: Vec<&str> = content["content"].as_str()?
+        .lines()
+        .map(|line| line.split_once('\u{2192}').map_or(line, |(_, rest)| rest))
+        .collect();
+    let mime = match std::path::Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("json") => "application/json",
+        Some("md") => "text/markdown",
+        Some("html" | "htm") => "text/html",
+        Some("rs") => "text/x-rust",
+        Some("py") => "text/x-python",
+        Some("js" | "mjs" | "cjs") => "text/javascript",
+        Some("ts" | "tsx") => "text/typescript",
+        _ => "text/plain",
+    };
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let uri = url::Url::from_file_path(&path).ok()?;
+    Some(protocol::Content::resource_text(uri.to_string(), Some(mime.to_string()), text.join("\n")))
+}
+
 /// Get version information
 pub fn version() -> Value {
     json!({
@@ -371,6 +1542,93 @@ mod tests {
         assert!(definitions.len() >= 9);
     }
 
+    /// Uppercases the `text` of whatever it wraps
+    struct Shout(Box<dyn MCPTool>);
+
+    #[async_trait::async_trait]
+    impl MCPTool for Shout {
+        fn name(&self) -> &str {
+            self.0.name()
+        }
+
+        fn description(&self) -> &str {
+            "shouts"
+        }
+
+        fn parameters(&self) -> Value {
+            self.0.parameters()
+        }
+
+        async fn execute(&self, mut params: Value) -> Result<ToolResult> {
+            params["content"] = json!(params["content"].as_str().unwrap_or("").to_uppercase());
+            self.0.execute(params).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replace_builtin() {
+        let mut registry = ToolRegistry::new();
+        let scratch = registry.remove("scratch").unwrap();
+        registry.register(Box::new(Shout(scratch)));
+        let definitions = registry.get_definitions();
+        let scratch: Vec<&Value> = definitions.iter().filter(|d| d["name"] == "scratch").collect();
+        assert_eq!(scratch.len(), 1);
+        assert_eq!(scratch[0]["description"], "shouts");
+
+        let stored = registry.execute("scratch", json!({ "action": "store", "handle": "h", "content": "hi" })).await.unwrap();
+        assert!(stored.success);
+        let read = registry.execute("scratch", json!({ "action": "read", "handle": "h" })).await.unwrap();
+        assert!(read.content.to_string().contains("HI"));
+
+        registry.remove("search");
+        assert!(!registry.list().contains(&"search".to_string()));
+        assert!(registry.get_definitions().iter().all(|d| d["name"] != "search"));
+        let missing = registry.execute("search", json!({ "pattern": "x" })).await.unwrap();
+        assert_eq!(missing.content["error"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_naming() {
+        let mut registry = ToolRegistry::new();
+        assert!(registry.execute("proc", json!({ "action": "help" })).await.unwrap().success);
+        registry.configure_naming(&config::NamingConfig { prefix: Some("hanzo".into()), ..Default::default() }).unwrap();
+        let definitions = registry.get_definitions();
+        assert!(definitions.iter().all(|d| d["name"].as_str().unwrap().starts_with("hanzo.")));
+        assert!(definitions.iter().any(|d| d["name"] == "hanzo.search"));
+
+        assert!(registry.execute("hanzo.fs", json!({ "action": "help" })).await.unwrap().success);
+        assert!(registry.execute("hanzo.proc", json!({ "action": "help" })).await.unwrap().success);
+        let invalid = registry.execute("hanzo.fs", json!({ "action": 3 })).await.unwrap();
+        assert_eq!(invalid.content["error"], "invalid_argument");
+        let stats = registry.execute("hanzo.stats", json!({})).await.unwrap();
+        assert_eq!(stats.content["tools"]["fs"]["calls"], 2);
+        assert_eq!(stats.content["tools"]["exec"]["calls"], 2);
+
+        let aliases = config::NamingConfig { aliases: HashMap::from([("files".into(), "nope".into())]), ..Default::default() };
+        assert!(registry.configure_naming(&aliases).is_err());
+    }
+
+    #[test]
+    fn test_definitions_for() {
+        let mut registry = ToolRegistry::new();
+        registry.configure_advertise(config::AdvertiseConfig {
+            auto: false,
+            hide: vec!["ui".into()],
+            clients: HashMap::from([("ci".into(), config::ClientTools { hide: vec!["browser".into()], show: vec!["computer".into()] })]),
+            ..Default::default()
+        });
+        let names = |definitions: Vec<Value>| -> Vec<String> {
+            definitions.iter().map(|d| d["name"].as_str().unwrap().to_string()).collect()
+        };
+        let anyone = names(registry.definitions_for(&Value::Null));
+        assert!(!anyone.contains(&"computer".to_string()));
+        assert!(anyone.contains(&"browser".to_string()));
+        let ci = names(registry.definitions_for(&json!({ "clientInfo": { "name": "CI" } })));
+        assert!(ci.contains(&"computer".to_string()));
+        assert!(!ci.contains(&"browser".to_string()));
+        assert_eq!(registry.get_definitions().len(), anyone.len() + 1);
+    }
+
     #[tokio::test]
     async fn test_proc_execute() {
         let registry = ToolRegistry::new();
@@ -389,6 +1647,464 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_stats_tool() {
+        let registry = ToolRegistry::new();
+        registry.execute("fs", json!({ "action": "help" })).await.unwrap();
+        registry.execute("fs", json!({ "action": "nope" })).await.ok();
+        let result = registry.execute("stats", json!({ "tool": "fs" })).await.unwrap();
+        assert_eq!(result.content["tools"]["fs"]["calls"], 2);
+        assert_eq!(result.content["tools"]["fs"]["failures"], 1);
+        assert!(result.content["tools"].get("stats").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_result_pagination() {
+        let mut registry = ToolRegistry::new();
+        registry.set_pagination(config::PaginationConfig { max_result_bytes: 5000, ..Default::default() });
+        let text = "log line\n".repeat(2000);
+        registry.execute("scratch", json!({ "action": "store", "handle": "log", "content": text })).await.unwrap();
+
+        let first = registry.execute("scratch", json!({ "action": "get", "handle": "log" })).await.unwrap();
+        assert_eq!(first.content["pagination"]["field"], "/data/content");
+        let mut joined = first.content["data"]["content"].as_str().unwrap().to_string();
+        let mut cursor = first.content["pagination"]["cursor"].as_str().map(str::to_string);
+        let stranger = registry.execute_in_session("page", json!({ "cursor": cursor }), Some("s2")).await.unwrap();
+        assert_eq!(stranger.content["error"], "not_found");
+        while let Some(next) = cursor {
+            let page = registry.execute("page", json!({ "cursor": next })).await.unwrap();
+            joined.push_str(page.content["data"]["content"].as_str().unwrap());
+            cursor = page.content["pagination"]["cursor"].as_str().map(str::to_string);
+        }
+        assert_eq!(joined, text);
+        assert!(!registry.execute("page", json!({})).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_output_budget() {
+        let registry = ToolRegistry::new();
+        let result = registry.execute("exec", json!({ "action": "exec", "command": "seq 1 5000", "max_bytes": 2000 })).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.content["truncated"], true);
+        assert_eq!(result.content["truncation"]["strategy"], "head_tail");
+        assert!(result.content.to_string().len() <= 2000);
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let registry = ToolRegistry::new();
+        let calls = json!([
+            { "tool": "scratch", "params": { "action": "store", "handle": "a", "content": "one" }, "id": "store" },
+            { "tool": "scratch", "params": { "action": "get", "handle": "missing" } },
+            { "tool": "scratch", "params": { "action": "get", "handle": "a" } }
+        ]);
+
+        let result = registry.execute("batch", json!({ "calls": calls })).await.unwrap();
+        assert_eq!(result.content["succeeded"], 2);
+        assert_eq!(result.content["failed"], 1);
+        assert_eq!(result.content["results"][0]["id"], "store");
+        assert_eq!(result.content["results"][2]["result"]["data"]["content"], "one");
+
+        let result = registry.execute("batch", json!({ "calls": calls, "mode": "stop_on_error" })).await.unwrap();
+        assert_eq!(result.content["skipped"], 1);
+        assert_eq!(result.content["results"][2]["skipped"], true);
+
+        let result = registry.execute("batch", json!({ "calls": calls, "mode": "parallel" })).await.unwrap();
+        assert_eq!(result.content["results"][1]["index"], 1);
+        assert_eq!(result.content["failed"], 1);
+
+        let nested = registry.execute("batch", json!({ "calls": [{ "tool": "batch", "params": { "calls": [] } }] })).await.unwrap();
+        assert_eq!(nested.content["failed"], 1);
+        assert!(!registry.execute("batch", json!({ "mode": "parallel" })).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.configure_hooks(&config::HooksConfig {
+            deny: vec!["scratch.delete".into()],
+            redact: vec!["hunter2".into()],
+            audit: config::AuditConfig { file: Some(dir.path().join("audit.jsonl")), ..Default::default() },
+            ..Default::default()
+        }).unwrap();
+        registry.execute("scratch", json!({ "action": "store", "handle": "pw", "content": "password=hunter2" })).await.unwrap();
+        let read = registry.execute("scratch", json!({ "action": "get", "handle": "pw" })).await.unwrap();
+        assert_eq!(read.content["data"]["content"], "password=[REDACTED]");
+        let denied = registry.execute("scratch", json!({ "action": "delete", "handle": "pw" })).await.unwrap();
+        assert!(!denied.success);
+        assert_eq!(denied.content["error"], "permission_denied");
+
+        // Values read from a .env file are masked wherever they show up later
+        let env = dir.path().join(".env");
+        std::fs::write(&env, "SERVICE_URL=https://internal.example/s3cr3t-path\n").unwrap();
+        let read = registry.execute("fs", json!({ "action": "read", "path": env })).await.unwrap();
+        assert!(!read.content.to_string().contains("s3cr3t"), "{}", read.content);
+        let echoed = registry.execute("scratch", json!({ "action": "store", "handle": "url", "content": "https://internal.example/s3cr3t-path" })).await.unwrap();
+        assert!(echoed.success);
+        let read = registry.execute("scratch", json!({ "action": "get", "handle": "url" })).await.unwrap();
+        assert_eq!(read.content["data"]["content"], "[REDACTED]");
+
+        assert!(registry.list().iter().any(|name| name == "audit"));
+        let audit = registry.execute_in_session("audit", json!({ "tool": "scratch" }), Some("s1")).await.unwrap();
+        assert_eq!(audit.content["count"], 5);
+        assert_eq!(audit.content["entries"][2]["success"], false);
+        assert_eq!(audit.content["entries"][0]["session"], Value::Null);
+        assert_eq!(audit.content["entries"][0]["params"]["content"], "password=[REDACTED]");
+        assert_eq!(audit.content["entries"][3]["params"]["content"], "[REDACTED]");
+        let sessions = registry.execute("audit", json!({ "action": "sessions" })).await.unwrap();
+        assert_eq!(sessions.content["sessions"][0]["calls"], 6);
+    }
+
+    #[tokio::test]
+    async fn test_export_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.configure_hooks(&config::HooksConfig {
+            audit: config::AuditConfig { file: Some(dir.path().join("audit.jsonl")), ..Default::default() },
+            ..Default::default()
+        }).unwrap();
+        registry.execute_in_session("scratch", json!({ "action": "store", "handle": "r", "content": "x" }), Some("r1")).await.unwrap();
+        registry.execute_in_session("scratch", json!({ "action": "get", "handle": "missing" }), Some("r1")).await.unwrap();
+        registry.execute_in_session("scratch", json!({ "action": "list" }), Some("r2")).await.unwrap();
+
+        let think = registry.execute_in_session("think", json!({ "action": "export", "format": "markdown", "limit": 1 }), Some("r1")).await.unwrap();
+        let report = think.content["data"]["report"].as_str().unwrap();
+        assert!(report.contains("for session `r1`"));
+        assert!(report.contains("## Plan") && report.contains("## Reasoning"));
+        assert!(report.contains("1 call, 1 failed"), "{}", report);
+        assert!(report.contains("`scratch.get`") && !report.contains("`scratch.list`"));
+
+        let path = dir.path().join("report.md");
+        let plan = registry.execute_in_session("plan", json!({ "action": "export", "title": "Run", "path": path }), Some("r1")).await.unwrap();
+        assert!(plan.success, "{:?}", plan.error);
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with("# Run\n"));
+        assert!(written.contains("`scratch.store`"));
+    }
+
+    #[tokio::test]
+    async fn test_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, "hello").unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.set_read_only(true);
+        assert!(registry.execute("fs", json!({ "action": "read", "path": file })).await.unwrap().success);
+        let write = registry.execute("fs", json!({ "action": "write", "path": file, "content": "bye" })).await.unwrap();
+        assert_eq!(write.content["error"], "permission_denied");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "hello");
+        let exec = registry.execute("exec", json!({ "action": "exec", "command": "touch x" })).await.unwrap();
+        assert!(!exec.success);
+        let batch = registry.execute("batch", json!({ "calls": [{ "tool": "fs", "params": { "action": "write", "path": file, "content": "bye" } }] })).await.unwrap();
+        assert!(batch.content["results"][0]["error"].as_str().unwrap().contains("read-only"));
+    }
+
+    #[tokio::test]
+    async fn test_workflow() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("out.txt");
+        let config: config::Config = toml::from_str(&format!(r#"
+            [server]
+            host = "127.0.0.1"
+            port = 3333
+            max_connections = 1
+            [tools]
+            computer_control = false
+            blockchain = false
+            vector_store = false
+            file_system = true
+            web_search = false
+            code_execution = true
+            [node]
+            connect_to_hanzo_node = false
+            node_api_url = ""
+
+            [workflows.save]
+            inputs.text = {{ description = "What to write" }}
+            inputs.path = {{ default = "{}" }}
+
+            [[workflows.save.steps]]
+            id = "write"
+            tool = "fs"
+            args = {{ action = "write", path = "{{{{inputs.path}}}}", content = "{{{{inputs.text}}}}" }}
+
+            [[workflows.save.steps]]
+            id = "missing"
+            tool = "fs"
+            args = {{ action = "read", path = "{{{{inputs.path}}}}.nope" }}
+            continue_on_error = true
+
+            [[workflows.save.steps]]
+            tool = "fs"
+            when = "{{{{not steps.missing.success}}}}"
+            args = {{ action = "read", path = "{{{{inputs.path}}}}" }}
+
+            [[workflows.save.steps]]
+            tool = "fs"
+            when = "{{{{steps.missing.success}}}}"
+            args = {{ action = "read", path = "/" }}
+        "#, file.display())).unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.configure_workflows(&config.workflows).unwrap();
+
+        let listed = registry.execute("workflow", json!({ "action": "list" })).await.unwrap();
+        assert_eq!(listed.content["workflows"][0]["name"], "save");
+        let missing = registry.execute("workflow", json!({ "name": "save" })).await.unwrap();
+        assert!(!missing.success);
+
+        let run = registry.execute("workflow", json!({ "name": "save", "inputs": { "text": "hello" } })).await.unwrap();
+        assert!(run.success, "{}", run.content);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "hello");
+        let steps = &run.content["steps"];
+        assert_eq!(steps[1]["success"], false);
+        assert!(steps[2]["result"].to_string().contains("hello"), "{}", steps[2]);
+        assert_eq!(steps[3]["skipped"], "condition not met");
+        assert_eq!(run.content["success"], false);
+        assert_eq!(run.content["failed"], 1);
+
+        // Inline workflows stop at the first failure unless told otherwise
+        let inline = json!({ "workflow": { "steps": [
+            { "tool": "fs", "args": { "action": "read", "path": "/nonexistent/x" } },
+            { "tool": "fs", "args": { "action": "read", "path": file } }
+        ] } });
+        let stopped = registry.execute("workflow", inline).await.unwrap();
+        assert_eq!(stopped.content["steps"][1]["skipped"], "an earlier step failed");
+        let nested = json!({ "workflow": { "steps": [{ "tool": "workflow" }] } });
+        assert!(!registry.execute("workflow", nested).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_schedule() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut registry = ToolRegistry::new();
+        assert!(!registry.execute("schedule", json!({})).await.unwrap().success);
+        registry.configure_schedule(&config::ScheduleConfig { enabled: true, dir: Some(dir.path().to_path_buf()) }).unwrap();
+
+        let params = json!({ "action": "add", "tool": "scratch", "params": { "action": "list" }, "every": "1m" });
+        let added = registry.execute("schedule", params).await.unwrap();
+        assert!(added.success, "{}", added.content);
+        let id = added.content["job"]["id"].as_str().unwrap().to_string();
+        assert!(!registry.execute("schedule", json!({ "action": "add", "tool": "fs", "every": "soon" })).await.unwrap().success);
+
+        let scheduler = registry.scheduler().unwrap();
+        for job in scheduler.due(chrono::Utc::now() + chrono::Duration::minutes(2)) {
+            registry.run_scheduled(&job).await;
+        }
+        let logs = registry.execute("schedule", json!({ "action": "logs", "id": id })).await.unwrap();
+        assert_eq!(logs.content["runs"][0]["success"], true, "{}", logs.content);
+        assert_eq!(logs.content["job"]["runs"], 1);
+
+        assert!(registry.execute("schedule", json!({ "action": "cancel", "id": id })).await.unwrap().success);
+        assert_eq!(registry.execute("schedule", json!({ "action": "list" })).await.unwrap().content["count"], 0);
+
+        // A session's jobs run as that session and end with it
+        let roots = dir.path().canonicalize().unwrap();
+        let uri = url::Url::from_directory_path(&roots).unwrap();
+        registry.roots().set_client_roots(Some("s1"), &[json!({ "uri": uri.as_str() })]).unwrap();
+        let outside = json!({ "action": "add", "tool": "fs", "params": { "action": "info", "path": "/etc/hostname" }, "every": "1m" });
+        let added = registry.execute_in_session("schedule", outside, Some("s1")).await.unwrap();
+        let id = added.content["job"]["id"].as_str().unwrap().to_string();
+        for job in scheduler.due(chrono::Utc::now() + chrono::Duration::minutes(2)) {
+            registry.run_scheduled(&job).await;
+        }
+        let logs = registry.execute("schedule", json!({ "action": "logs", "id": id })).await.unwrap();
+        assert_eq!(logs.content["runs"][0]["details"]["error"], "permission_denied", "{}", logs.content);
+        registry.end_session("s1", false).await.unwrap();
+        assert_eq!(registry.execute("schedule", json!({ "action": "list" })).await.unwrap().content["count"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("watched.txt");
+        let registry = ToolRegistry::new();
+        let since = events::bus().last_id();
+        let write = registry.execute("fs", json!({ "action": "write", "path": file, "content": "x" })).await.unwrap();
+        assert!(write.success, "{}", write.content);
+
+        let filter = json!({ "path": file });
+        let waited = registry.execute("events", json!({ "action": "wait", "kind": "file.*", "filter": filter, "since_id": since, "timeout_ms": 1000 })).await.unwrap();
+        assert!(waited.success, "{}", waited.content);
+        assert_eq!(waited.content["event"]["kind"], events::FILE_CHANGED);
+        let recent = registry.execute("events", json!({ "kind": events::FILE_CHANGED, "filter": filter })).await.unwrap();
+        assert_eq!(recent.content["count"], 1);
+
+        let none = registry.execute("events", json!({ "action": "wait", "kind": "process.exited", "filter": { "proc_id": "none" }, "timeout_ms": 10 })).await.unwrap();
+        assert_eq!(none.content["error"], "timeout");
+        assert!(!registry.execute("events", json!({ "action": "subscribe" })).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_completion() {
+        let registry = ToolRegistry::new();
+        let complete = |tool: &str, argument: &str, value: &str| json!({
+            "ref": { "type": "ref/tool", "name": tool },
+            "argument": { "name": argument, "value": value }
+        });
+
+        let actions = registry.complete(&complete("fs", "action", "rea"), None).await.unwrap();
+        assert_eq!(actions["completion"]["values"], json!(["read", "read_lines", "readlink", "realpath"]));
+        let paths = registry.complete(&complete("fs", "path", "src/lib"), None).await.unwrap();
+        assert_eq!(paths["completion"]["values"], json!(["src/lib.rs"]));
+
+        let created = registry.execute_in_session("memory", json!({ "action": "create", "statement": "x", "scope": "session" }), Some("s1")).await.unwrap();
+        let id = created.content["ids"][0].as_str().unwrap().to_string();
+        let mine = registry.complete(&complete("memory", "id", ""), Some("s1")).await.unwrap();
+        assert!(mine["completion"]["values"].as_array().unwrap().contains(&json!(id)));
+        let theirs = registry.complete(&complete("memory", "id", ""), Some("s2")).await.unwrap();
+        assert!(!theirs["completion"]["values"].as_array().unwrap().contains(&json!(id)));
+
+        assert!(registry.complete(&complete("nope", "x", ""), None).await.is_err());
+        let prompt = json!({ "ref": { "type": "ref/prompt", "name": "p" }, "argument": { "name": "a", "value": "" } });
+        assert_eq!(registry.complete(&prompt, None).await.unwrap()["completion"]["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_workspaces() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "in the other root").unwrap();
+        let mut registry = ToolRegistry::new();
+        registry.configure_workspaces(&[config::WorkspaceRoot {
+            path: dir.path().to_path_buf(),
+            name: Some("other".into()),
+            deny: vec!["exec".into()],
+            ..Default::default()
+        }]).unwrap();
+
+        let roots = registry.execute("workspace", json!({ "action": "roots" })).await.unwrap();
+        assert_eq!(roots.content["data"]["count"], 2);
+        let remember = json!({ "action": "create", "statement": "startup project fact" });
+        assert!(registry.execute_in_session("memory", remember, Some("s1")).await.unwrap().success);
+
+        let switched = registry.execute_in_session("workspace", json!({ "action": "switch", "name": "other" }), Some("s1")).await.unwrap();
+        assert!(switched.success, "{}", switched.content);
+        let read = registry.execute_in_session("fs", json!({ "action": "read", "path": "notes.txt" }), Some("s1")).await.unwrap();
+        assert!(read.content.to_string().contains("in the other root"), "{}", read.content);
+        let exec = registry.execute_in_session("exec", json!({ "command": "true" }), Some("s1")).await.unwrap();
+        assert_eq!(exec.content["error"], "permission_denied");
+
+        // Project memories stay with their project; other sessions keep the startup root
+        let recall = json!({ "action": "recall", "query": "startup project" });
+        let other = registry.execute_in_session("memory", recall.clone(), Some("s1")).await.unwrap();
+        assert_eq!(other.content["count"], 0);
+        let startup = registry.execute_in_session("memory", recall, Some("s2")).await.unwrap();
+        assert_eq!(startup.content["count"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let mut registry = ToolRegistry::new();
+        registry.set_limits(HashMap::from([
+            ("fs".to_string(), config::ToolLimit { rate_per_second: Some(0.1), burst: Some(1), ..Default::default() }),
+        ]));
+        assert!(registry.execute("fs", json!({ "action": "help" })).await.unwrap().success);
+        let result = registry.execute("fs", json!({ "action": "help" })).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.content["error"], "rate_limited");
+        assert!(result.content["retry_after_ms"].as_u64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_limits_follow_aliases() {
+        let registry = ToolRegistry::new();
+        let held: Vec<_> = (0..8).map(|_| registry.limiter.acquire("fs", Some("search")).unwrap()).collect();
+        let result = registry.execute("fs", json!({ "action": "grep", "pattern": "x" })).await.unwrap();
+        assert_eq!(result.content["error"], "concurrency_limited");
+        assert_eq!(result.content["limit"], "fs.search");
+        drop(held);
+        assert_eq!(canonical_action("fs", "grep").as_deref(), Some("search"));
+        assert_eq!(canonical_action("fs", "bogus"), None);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let mut registry = ToolRegistry::new();
+        registry.set_timeouts(config::TimeoutConfig {
+            default_secs: 300,
+            tools: HashMap::from([("exec".to_string(), 1)]),
+        });
+        let result = registry.execute("exec", json!({ "action": "exec", "command": "sleep 5" })).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.content["error"], "timeout");
+        assert_eq!(result.content["timeout_ms"], 1000);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let registry = ToolRegistry::new();
+        let result = registry.execute("fs", json!({ "action": "read", "limit": "ten", "paht": "x" })).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.content["error"], "invalid_argument");
+        assert_eq!(result.content["errors"][0]["message"], "field 'limit' must be integer, got string");
+        assert_eq!(result.content["errors"][1]["message"], "unknown field 'paht'; did you mean 'path'?");
+
+        let result = registry.execute("git", json!({})).await.unwrap();
+        assert!(result.error.unwrap().starts_with("Invalid arguments for git: missing required field 'action'"));
+
+        // Aliases the tool parses are not schema errors
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt");
+        std::fs::write(&path, "hi").unwrap();
+        let result = registry.execute("fs", json!({ "action": "stat", "path": path })).await.unwrap();
+        assert!(result.success);
+    }
+
+    #[tokio::test]
+    async fn test_error_codes() {
+        let registry = ToolRegistry::new();
+        let result = registry.execute("fs", json!({ "action": "read", "path": "/nonexistent/file.txt" })).await.unwrap();
+        assert_eq!(result.content["error"], "not_found");
+        assert!(result.content["hint"].is_string());
+
+        let result = registry.execute("fs", json!({ "action": "write" })).await.unwrap();
+        assert_eq!(result.content["error"], "invalid_argument");
+        assert_eq!(result.error.as_deref(), Some("path required"));
+
+        let result = registry.execute("nope", json!({})).await.unwrap();
+        assert_eq!(result.content["error"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_fs_read_resource_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "# Title\nbody").unwrap();
+        let registry = ToolRegistry::new();
+        let result = registry.execute("fs", json!({ "action": "read", "path": path })).await.unwrap();
+        let call = result.to_call_result(registry.declares_output("fs"));
+        assert_eq!(call["content"][1]["type"], "resource");
+        assert_eq!(call["content"][1]["resource"]["mimeType"], "text/markdown");
+        assert_eq!(call["content"][1]["resource"]["text"], "# Title\nbody");
+        let uri = url::Url::from_file_path(path.canonicalize().unwrap()).unwrap();
+        assert_eq!(call["content"][1]["resource"]["uri"], uri.as_str());
+        let text: Value = serde_json::from_str(call["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert!(text.get("content").is_none(), "the file's text is sent once, in the resource");
+        assert_eq!(call["structuredContent"]["total_lines"], 2);
+        assert!(registry.get_definitions().iter().any(|d| d["name"] == "fs" && d["outputSchema"]["type"] == "object"));
+
+        // Tools without an output schema answer in text only
+        let help = registry.execute("git", json!({ "action": "help" })).await.unwrap();
+        assert!(!registry.declares_output("git"));
+        assert!(help.to_call_result(registry.declares_output("git")).get("structuredContent").is_none());
+
+        // Failures carry their details in text too
+        let missing = registry.execute("fs", json!({ "action": "read", "path": dir.path().join("gone.md") })).await.unwrap();
+        let call = missing.to_call_result(false);
+        assert_eq!(call["isError"], true);
+        let details: Value = serde_json::from_str(call["content"][1]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(details["error"], "not_found");
+        assert!(details["hint"].is_string());
+    }
+
+    #[test]
+    fn test_take_image() {
+        let mut content = json!({ "format": "png", "base64": "iVBORw0KGgo=", "width": 1 });
+        let block = take_image(&mut content).unwrap();
+        assert_eq!(block, protocol::Content::image("iVBORw0KGgo=", "image/png"));
+        assert!(content.get("base64").is_none());
+    }
+
     #[test]
     fn test_version() {
         let v = version();
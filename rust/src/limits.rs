@@ -0,0 +1,158 @@
+/// Per-tool rate limits and concurrency caps
+///
+/// Limits are keyed by tool name (`browser`) or by tool and action
+/// (`fs.search`); every key matching a call applies. A call over a
+/// concurrency cap or out of rate tokens is rejected straight away with
+/// the time after which a retry should succeed.
+
+use crate::config::ToolLimit;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Retry hint when a concurrency cap is hit; running calls give no better estimate
+const CONCURRENCY_RETRY: Duration = Duration::from_secs(1);
+
+/// A call rejected by a limit
+#[derive(Debug, Clone)]
+pub struct LimitExceeded {
+    pub key: String,
+    pub reason: &'static str,
+    pub retry_after: Duration,
+}
+
+impl LimitExceeded {
+    pub fn message(&self) -> String {
+        format!(
+            "{} for {}; retry after {:.1}s",
+            if self.reason == "rate_limited" { "Rate limit exceeded" } else { "Too many concurrent calls" },
+            self.key,
+            self.retry_after.as_secs_f64()
+        )
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "error": self.reason,
+            "limit": self.key,
+            "retry_after_ms": self.retry_after.as_millis() as u64,
+            "hint": "Wait retry_after_ms before calling again"
+        })
+    }
+}
+
+/// Token bucket refilled continuously at `rate` tokens per second
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Held for the duration of a call; releases concurrency slots on drop
+#[derive(Debug, Default)]
+pub struct Permit {
+    _slots: Vec<OwnedSemaphorePermit>,
+}
+
+#[derive(Debug, Default)]
+pub struct Limiter {
+    limits: HashMap<String, ToolLimit>,
+    slots: HashMap<String, Arc<Semaphore>>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Limiter {
+    pub fn new(limits: HashMap<String, ToolLimit>) -> Self {
+        let slots = limits.iter()
+            .filter_map(|(key, limit)| limit.max_concurrent.map(|n| (key.clone(), Arc::new(Semaphore::new(n)))))
+            .collect();
+        Self { limits, slots, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// Admit a call to `tool`/`action` or say when to retry
+    pub fn acquire(&self, tool: &str, action: Option<&str>) -> Result<Permit, LimitExceeded> {
+        let mut keys = vec![tool.to_string()];
+        if let Some(action) = action {
+            keys.push(format!("{}.{}", tool, action));
+        }
+        let keys: Vec<&String> = keys.iter().filter(|k| self.limits.contains_key(*k)).collect();
+        if keys.is_empty() {
+            return Ok(Permit::default());
+        }
+
+        let mut slots = Vec::new();
+        for key in &keys {
+            if let Some(semaphore) = self.slots.get(*key) {
+                match semaphore.clone().try_acquire_owned() {
+                    Ok(slot) => slots.push(slot),
+                    Err(_) => return Err(LimitExceeded {
+                        key: key.to_string(),
+                        reason: "concurrency_limited",
+                        retry_after: CONCURRENCY_RETRY,
+                    }),
+                }
+            }
+        }
+
+        // Check every bucket before taking from any, so a rejected call
+        // costs no tokens
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let mut spend = Vec::new();
+        for key in &keys {
+            let limit = &self.limits[*key];
+            let Some(rate) = limit.rate_per_second.filter(|r| *r > 0.0) else { continue };
+            let burst = limit.burst.map_or(rate.max(1.0), |b| b as f64);
+            let bucket = buckets.entry(key.to_string()).or_insert(Bucket { tokens: burst, updated: now });
+            bucket.tokens = (bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate).min(burst);
+            bucket.updated = now;
+            if bucket.tokens < 1.0 {
+                return Err(LimitExceeded {
+                    key: key.to_string(),
+                    reason: "rate_limited",
+                    retry_after: Duration::from_secs_f64((1.0 - bucket.tokens) / rate),
+                });
+            }
+            spend.push(key.to_string());
+        }
+        for key in spend {
+            if let Some(bucket) = buckets.get_mut(&key) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(Permit { _slots: slots })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(key: &str, limit: ToolLimit) -> Limiter {
+        Limiter::new(HashMap::from([(key.to_string(), limit)]))
+    }
+
+    #[test]
+    fn test_concurrency_cap() {
+        let limiter = limiter("fs.search", ToolLimit { max_concurrent: Some(1), ..Default::default() });
+        let held = limiter.acquire("fs", Some("search")).unwrap();
+        let err = limiter.acquire("fs", Some("search")).unwrap_err();
+        assert_eq!(err.reason, "concurrency_limited");
+        assert!(limiter.acquire("fs", Some("read")).is_ok());
+        drop(held);
+        assert!(limiter.acquire("fs", Some("search")).is_ok());
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = limiter("fetch", ToolLimit { rate_per_second: Some(0.5), burst: Some(2), ..Default::default() });
+        assert!(limiter.acquire("fetch", None).is_ok());
+        assert!(limiter.acquire("fetch", Some("get")).is_ok());
+        let err = limiter.acquire("fetch", None).unwrap_err();
+        assert_eq!(err.reason, "rate_limited");
+        assert!(err.retry_after > Duration::from_millis(1500));
+        assert_eq!(err.to_json()["limit"], "fetch");
+    }
+}
@@ -0,0 +1,668 @@
+/// Structured logging with file rotation
+///
+/// Installs a tracing-subscriber registry as the global subscriber and
+/// bridges the `log` macros into it with tracing-log, so existing `log`
+/// calls and dependencies' tracing events land in the same sinks. Events
+/// go to a log file as JSON lines (or plain text) and optionally to
+/// stderr. Nothing is ever written to stdout, which a stdio transport owns.
+///
+/// The file is written through tracing-appender's non-blocking worker into
+/// a `RollingFile`, which rotates by size as well as by period (the
+/// appender's own rolling files only rotate by period) and is shared with
+/// the audit log.
+///
+/// Levels are an `EnvFilter`: the default level plus the configured module
+/// levels, the most specific target winning. `RUST_LOG` (e.g.
+/// `info,hanzo_mcp::tools=debug`) overrides the configured levels.
+///
+/// Logging never stops the server: a malformed `RUST_LOG` is ignored and a
+/// log file that can't be opened leaves stderr as the only sink, each with
+/// a warning once the logger is installed.
+///
+/// Records from this crate are also forwarded to MCP clients as
+/// `notifications/message` once a notification channel is attached. Each
+/// session gets those at or above the level it chose with `logging/setLevel`
+/// (warning by default), and records emitted while a call runs go only to
+/// the session that made it. Once the server sets a redactor, secrets are
+/// masked in both.
+
+use crate::config::LoggingConfig;
+use crate::events::AUDIENCE;
+use crate::redaction::Redactor;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use log::LevelFilter;
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Metadata, Subscriber};
+use tracing_appender::non_blocking::{NonBlockingBuilder, WorkerGuard};
+use tracing_log::{AsLog, NormalizeEvent};
+use tracing_subscriber::filter::{self, Directive, EnvFilter};
+use tracing_subscriber::fmt::format::{self, FormatEvent, FormatFields};
+use tracing_subscriber::fmt::{self, FmtContext, MakeWriter};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::{Layer, Registry};
+
+/// Target prefix of records forwarded to the MCP client
+const CLIENT_TARGET: &str = "hanzo_mcp";
+
+/// MCP log severity (RFC 5424), lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum McpLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl FromStr for McpLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "debug" => Ok(Self::Debug),
+            "info" => Ok(Self::Info),
+            "notice" => Ok(Self::Notice),
+            "warning" | "warn" => Ok(Self::Warning),
+            "error" => Ok(Self::Error),
+            "critical" => Ok(Self::Critical),
+            "alert" => Ok(Self::Alert),
+            "emergency" => Ok(Self::Emergency),
+            _ => Err(anyhow!("Unknown log level: {} (debug, info, notice, warning, error, critical, alert, emergency)", s)),
+        }
+    }
+}
+
+impl McpLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Debug => "debug",
+            Self::Info => "info",
+            Self::Notice => "notice",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Critical => "critical",
+            Self::Alert => "alert",
+            Self::Emergency => "emergency",
+        }
+    }
+
+    /// Most verbose `log` level that still reaches this severity
+    fn filter(&self) -> LevelFilter {
+        match self {
+            Self::Debug => LevelFilter::Trace,
+            Self::Info | Self::Notice => LevelFilter::Info,
+            Self::Warning => LevelFilter::Warn,
+            _ => LevelFilter::Error,
+        }
+    }
+}
+
+impl From<&tracing::Level> for McpLevel {
+    fn from(level: &tracing::Level) -> Self {
+        match *level {
+            tracing::Level::TRACE | tracing::Level::DEBUG => Self::Debug,
+            tracing::Level::INFO => Self::Info,
+            tracing::Level::WARN => Self::Warning,
+            _ => Self::Error,
+        }
+    }
+}
+
+/// Where and from which severity log messages go to the client
+struct ClientLog {
+    level: McpLevel,
+    sender: Option<broadcast::Sender<Value>>,
+}
+
+static CLIENT_LOG: Lazy<RwLock<ClientLog>> = Lazy::new(|| {
+    RwLock::new(ClientLog { level: McpLevel::Warning, sender: None })
+});
+
+/// Max level of the installed file and console filters, kept so client
+/// level changes can widen or narrow the global `log` max level
+static FILTER_MAX: AtomicUsize = AtomicUsize::new(LevelFilter::Off as usize);
+
+tokio::task_local! {
+    /// Session whose call is running, which its log messages are sent to
+    static CALL_SESSION: Option<String>;
+}
+
+/// Run `call` with the client log messages it emits addressed to `session`
+pub async fn in_session<F: Future>(session: Option<&str>, call: F) -> F::Output {
+    CALL_SESSION.scope(session.map(str::to_string), call).await
+}
+
+/// Masks secrets in every message before it is written or sent
+static REDACTOR: Lazy<RwLock<Option<Arc<Redactor>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Mask secrets in log lines and client log messages with `redactor`
+pub fn set_redactor(redactor: Option<Arc<Redactor>>) {
+    if let Ok(mut current) = REDACTOR.write() {
+        *current = redactor;
+    }
+}
+
+fn scrub(message: String) -> String {
+    let Ok(redactor) = REDACTOR.read() else { return message };
+    redactor.as_ref().and_then(|r| r.redact(&message)).unwrap_or(message)
+}
+
+/// Send client log notifications on `sender`
+pub fn attach_client(sender: broadcast::Sender<Value>) {
+    if let Ok(mut client) = CLIENT_LOG.write() {
+        client.sender = Some(sender);
+    }
+}
+
+/// Minimum severity forwarded to any client, the most verbose level a
+/// session chose with `logging/setLevel`; each session's outbox holds back
+/// what is below its own
+pub fn set_client_level(level: McpLevel) {
+    if let Ok(mut client) = CLIENT_LOG.write() {
+        client.level = level;
+    }
+    let filter_max = LevelFilter::iter().nth(FILTER_MAX.load(Ordering::Relaxed)).unwrap_or(LevelFilter::Off);
+    log::set_max_level(filter_max.max(level.filter()));
+}
+
+pub fn client_level() -> McpLevel {
+    CLIENT_LOG.read().map_or(McpLevel::Warning, |client| client.level)
+}
+
+fn client_accepts(level: McpLevel) -> bool {
+    CLIENT_LOG.read().is_ok_and(|client| client.sender.is_some() && level >= client.level)
+}
+
+/// Send a `notifications/message` to the client if its level admits it
+pub fn notify(level: McpLevel, logger: &str, mut data: Value) {
+    let Ok(client) = CLIENT_LOG.read() else { return };
+    let Some(sender) = &client.sender else { return };
+    if level < client.level {
+        return;
+    }
+    if let Some(redactor) = REDACTOR.read().ok().as_ref().and_then(|r| r.as_ref()) {
+        redactor.redact_value(&mut data);
+    }
+    let mut message = json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/message",
+        "params": {
+            "level": level.as_str(),
+            "logger": logger,
+            "data": data
+        }
+    });
+    if let Ok(Some(session)) = CALL_SESSION.try_with(Clone::clone) {
+        message[AUDIENCE] = json!([session]);
+    }
+    // No receivers just means no client is polling yet
+    let _ = sender.send(message);
+}
+
+/// Log file line format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Json,
+    Text,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "text" | "plain" => Ok(Self::Text),
+            _ => Err(anyhow!("Unknown log format: {} (json, text)", s)),
+        }
+    }
+}
+
+/// Time-based rotation period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl FromStr for Rotation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "never" | "none" => Ok(Self::Never),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            _ => Err(anyhow!("Unknown log rotation: {} (never, hourly, daily)", s)),
+        }
+    }
+}
+
+impl Rotation {
+    /// Label of the period containing `at`; a change means rotate
+    fn period(&self, at: DateTime<Local>) -> String {
+        match self {
+            Self::Never => String::new(),
+            Self::Hourly => at.format("%Y-%m-%dT%H").to_string(),
+            Self::Daily => at.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// The configured levels as `EnvFilter` directives: the default level,
+/// then each module's, then the `rust_log` entries, a later directive for
+/// the same target replacing an earlier one. A malformed `rust_log` is
+/// left out and reported in `warnings`.
+fn directives(config: &LoggingConfig, debug: bool, rust_log: Option<&str>, warnings: &mut Vec<String>) -> Result<Vec<Directive>> {
+    let mut default = parse_level(&config.level)?;
+    if debug {
+        default = default.max(filter::LevelFilter::DEBUG);
+    }
+    let mut directives = vec![default.into()];
+    for (module, level) in &config.modules {
+        directives.push(directive(&format!("{}={}", module, level))?);
+    }
+    if let Some(spec) = rust_log {
+        let entries = spec.split(',').map(str::trim).filter(|e| !e.is_empty());
+        match entries.map(directive).collect::<Result<Vec<_>>>() {
+            Ok(overrides) => directives.extend(overrides),
+            Err(e) => warnings.push(format!("Ignoring RUST_LOG={}: {}", spec, e)),
+        }
+    }
+    Ok(directives)
+}
+
+fn env_filter(directives: &[Directive]) -> EnvFilter {
+    directives.iter().cloned().fold(EnvFilter::default(), EnvFilter::add_directive)
+}
+
+fn parse_level(level: &str) -> Result<filter::LevelFilter> {
+    level.trim().parse().map_err(|_| anyhow!("Unknown log level: {}", level))
+}
+
+fn directive(entry: &str) -> Result<Directive> {
+    entry.parse().map_err(|e| anyhow!("Bad log directive {:?}: {}", entry, e))
+}
+
+/// Log file that rotates by size and period, keeping `max_files` old files
+/// as `<name>.1` (newest) through `<name>.<max_files>`
+pub struct RollingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    rotation: Rotation,
+    period: String,
+    max_files: usize,
+}
+
+impl RollingFile {
+    pub fn open(path: &Path, max_bytes: u64, rotation: Rotation, max_files: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        // Start in the period the existing file was last written in, so a
+        // restart on a new day still rotates yesterday's log away
+        let modified: DateTime<Local> = file.metadata()?.modified().map(Into::into).unwrap_or_else(|_| Local::now());
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_bytes,
+            rotation,
+            period: rotation.period(modified),
+            max_files,
+        })
+    }
+
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        let period = self.rotation.period(Local::now());
+        let full = self.max_bytes > 0 && self.size > 0 && self.size + line.len() as u64 + 1 > self.max_bytes;
+        if full || period != self.period {
+            self.rotate()?;
+            self.period = period;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(self.rotated(self.max_files));
+            for n in (1..self.max_files).rev() {
+                let from = self.rotated(n);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated(1))?;
+            self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// One formatted line per call, as the non-blocking worker hands them over
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let line = String::from_utf8_lossy(buf);
+        self.write_line(line.trim_end_matches('\n')).map_err(std::io::Error::other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Writers that pass each line through the redactor first
+#[derive(Clone)]
+struct Scrubbed<M>(M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Scrubbed<M> {
+    type Writer = Scrubbed<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        Scrubbed(self.0.make_writer())
+    }
+}
+
+impl<W: Write> Write for Scrubbed<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write_all(scrub(String::from_utf8_lossy(buf).into_owned()).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Forwards this crate's events to the MCP client as `notifications/message`
+struct ClientLayer;
+
+impl<S: Subscriber> Layer<S> for ClientLayer {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        let mut message = Message::default();
+        event.record(&mut message);
+        notify(metadata.level().into(), metadata.target(), json!(scrub(message.0)));
+    }
+}
+
+/// An event's message, followed by its other fields as key=value
+#[derive(Default)]
+struct Message(String);
+
+impl Visit for Message {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_debug(field, &format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        // tracing-log's copies of the record's target and location
+        if field.name().starts_with("log.") {
+            return;
+        }
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        let _ = match field.name() {
+            "message" => write!(self.0, "{:?}", value),
+            name => write!(self.0, "{}={:?}", name, value),
+        };
+    }
+}
+
+/// The log file's JSON lines: ts, level, target, message and location
+struct JsonLine;
+
+impl<S, N> FormatEvent<S, N> for JsonLine
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _: &FmtContext<'_, S, N>, mut writer: format::Writer<'_>, event: &Event<'_>) -> std::fmt::Result {
+        let normalized = event.normalized_metadata();
+        let metadata = normalized.as_ref().unwrap_or_else(|| event.metadata());
+        let mut message = Message::default();
+        event.record(&mut message);
+        let mut line = json!({
+            "ts": Utc::now().to_rfc3339(),
+            "level": metadata.level().as_str(),
+            "target": metadata.target(),
+            "message": message.0,
+        });
+        if let (Some(file), Some(n)) = (metadata.file(), metadata.line()) {
+            line["location"] = json!(format!("{}:{}", file, n));
+        }
+        writeln!(writer, "{}", line)
+    }
+}
+
+/// Whether an event goes to the MCP client
+fn forwarded(metadata: &Metadata) -> bool {
+    metadata.target().starts_with(CLIENT_TARGET) && client_accepts(metadata.level().into())
+}
+
+/// The sinks the subscriber writes to, set up from `LoggingConfig`
+pub struct Logger {
+    directives: Vec<Directive>,
+    format: LogFormat,
+    file: Option<RollingFile>,
+    console: bool,
+    /// Problems met while setting up, logged once installed
+    warnings: Vec<String>,
+}
+
+impl Logger {
+    pub fn from_config(config: &LoggingConfig, debug: bool) -> Result<Self> {
+        let mut warnings = Vec::new();
+        let directives = directives(config, debug, std::env::var("RUST_LOG").ok().as_deref(), &mut warnings)?;
+
+        let rotation = config.rotation.parse()?;
+        let mut console = config.console;
+        let file = if config.file_enabled {
+            let path = config.file.clone().unwrap_or_else(default_log_path);
+            let path = PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref());
+            match RollingFile::open(&path, config.max_size_mb * 1024 * 1024, rotation, config.max_files) {
+                Ok(file) => Some(file),
+                Err(e) => {
+                    warnings.push(format!("Cannot write the log file {}: {}; logging to stderr only", path.display(), e));
+                    console = true;
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(Self {
+            directives,
+            format: config.format.parse()?,
+            file,
+            console,
+            warnings,
+        })
+    }
+
+    /// Install as the global subscriber and route `log` records into it.
+    /// Hold the guard until exit: dropping it flushes the log file.
+    pub fn install(mut self) -> Result<Option<WorkerGuard>> {
+        let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = Vec::new();
+        let mut guard = None;
+        if let Some(file) = self.file.take() {
+            let (writer, flush) = NonBlockingBuilder::default().lossy(false).finish(file);
+            guard = Some(flush);
+            let layer = fmt::layer().with_ansi(false).with_writer(Scrubbed(writer));
+            layers.push(match self.format {
+                LogFormat::Json => layer.event_format(JsonLine).with_filter(env_filter(&self.directives)).boxed(),
+                LogFormat::Text => layer.with_filter(env_filter(&self.directives)).boxed(),
+            });
+        }
+        if self.console {
+            let layer = fmt::layer()
+                .with_ansi(std::io::stderr().is_terminal())
+                .with_writer(Scrubbed(std::io::stderr));
+            layers.push(layer.with_filter(env_filter(&self.directives)).boxed());
+        }
+        // Checked per event, since each session can change the client level
+        layers.push(ClientLayer.with_filter(filter::dynamic_filter_fn(|metadata, _| forwarded(metadata))).boxed());
+
+        let max = env_filter(&self.directives).max_level_hint().unwrap_or(filter::LevelFilter::TRACE).as_log();
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layers))
+            .map_err(|e| anyhow!("Logger already set: {}", e))?;
+        tracing_log::LogTracer::init().map_err(|e| anyhow!("Logger already set: {}", e))?;
+        FILTER_MAX.store(max as usize, Ordering::Relaxed);
+        log::set_max_level(max.max(client_level().filter()));
+        for warning in std::mem::take(&mut self.warnings) {
+            log::warn!("{}", warning);
+        }
+        Ok(guard)
+    }
+}
+
+fn default_log_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hanzo-mcp")
+        .join("logs")
+        .join("hanzo-mcp.log")
+}
+
+/// Install the logging described by `config`; keep the returned guard
+/// alive for as long as the server runs
+pub fn init(config: &LoggingConfig, debug: bool) -> Result<Option<WorkerGuard>> {
+    Logger::from_config(config, debug)?.install()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines a subscriber filtered by `directives` writes
+    #[derive(Clone, Default)]
+    struct Capture(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_module_levels() {
+        let mut config = LoggingConfig::default();
+        config.modules.insert("hanzo_mcp::tools".into(), "debug".into());
+        let mut warnings = Vec::new();
+        let levels = directives(&config, false, Some("warn,hanzo_mcp::tools::exec_tool=trace"), &mut warnings).unwrap();
+        assert!(warnings.is_empty());
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let layer = fmt::layer().with_ansi(false).with_writer(move || writer.clone()).with_filter(env_filter(&levels));
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+            tracing::info!(target: "hyper::proto", "hyper info");
+            tracing::warn!(target: "hyper::proto", "hyper warn");
+            tracing::debug!(target: "hanzo_mcp::tools::fs_tool", "fs debug");
+            tracing::trace!(target: "hanzo_mcp::tools::exec_tool", "exec trace");
+        });
+        let written = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        assert!(!written.contains("hyper info"), "{}", written);
+        assert!(written.contains("hyper warn"));
+        assert!(written.contains("fs debug"));
+        assert!(written.contains("exec trace"));
+
+        directives(&config, false, Some("hanzo_mcp=loud"), &mut warnings).unwrap();
+        assert!(warnings[0].starts_with("Ignoring RUST_LOG=hanzo_mcp=loud"), "{:?}", warnings);
+        config.level = "chatty".into();
+        assert!(directives(&config, false, None, &mut warnings).is_err());
+    }
+
+    #[test]
+    fn test_unwritable_file_falls_back_to_stderr() {
+        let dir = tempfile::tempdir().unwrap();
+        let blocker = dir.path().join("not-a-dir");
+        std::fs::write(&blocker, "").unwrap();
+        let config = LoggingConfig { file: Some(blocker.join("mcp.log")), console: false, ..Default::default() };
+        let logger = Logger::from_config(&config, false).unwrap();
+        assert!(logger.file.is_none());
+        assert!(logger.console);
+        assert!(logger.warnings[0].contains("logging to stderr only"), "{:?}", logger.warnings);
+    }
+
+    #[tokio::test]
+    async fn test_client_notifications() {
+        let (sender, mut rx) = broadcast::channel(16);
+        attach_client(sender);
+        set_client_level("error".parse().unwrap());
+        notify(McpLevel::Warning, "hanzo_mcp::tools", json!("skipped"));
+        notify(McpLevel::Critical, "hanzo_mcp::tools", json!("kept"));
+        let message = rx.try_recv().unwrap();
+        assert_eq!(message["method"], "notifications/message");
+        assert_eq!(message["params"]["level"], "critical");
+        assert_eq!(message["params"]["data"], "kept");
+        assert!(rx.try_recv().is_err());
+        assert!("verbose".parse::<McpLevel>().is_err());
+
+        // Messages emitted during a call are addressed to its session
+        in_session(Some("s1"), async { notify(McpLevel::Emergency, "hanzo_mcp::tools", json!("mine")) }).await;
+        let message = rx.try_recv().unwrap();
+        assert_eq!(message[AUDIENCE], json!(["s1"]));
+        notify(McpLevel::Emergency, "hanzo_mcp::server", json!("everyone's"));
+        assert!(rx.try_recv().unwrap().get(AUDIENCE).is_none());
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mcp.log");
+        let mut file = RollingFile::open(&path, 64, Rotation::Never, 2).unwrap();
+        for i in 0..10 {
+            file.write_line(&format!("{{\"line\":{},\"pad\":\"xxxxxxxxxxxxxxxxxxxx\"}}", i)).unwrap();
+        }
+        assert!(path.exists());
+        assert!(dir.path().join("mcp.log.1").exists());
+        assert!(dir.path().join("mcp.log.2").exists());
+        assert!(!dir.path().join("mcp.log.3").exists());
+        let current = std::fs::read_to_string(&path).unwrap();
+        assert!(current.contains("\"line\":9"));
+        assert!(current.len() <= 64);
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::Result;
 use clap::Parser;
-use hanzo_mcp::{Config, MCPServer};
+use hanzo_mcp::{logging, Config, MCPServer};
 use log::info;
 use std::path::PathBuf;
 
@@ -22,27 +22,41 @@ struct Args {
     /// Port to listen on
     #[clap(short, long, default_value = "3333")]
     port: u16,
+
+    /// Refuse writes, process execution, UI input and browser interaction
+    #[clap(long)]
+    read_only: bool,
+    /// Advertise every tool under this namespace, e.g. hanzo for hanzo.fs
+    #[clap(long)]
+    tool_prefix: Option<String>,
+
+    /// Also serve the tools as REST endpoints (POST /tools/{name}) to the
+    /// API keys in [gateway.keys]
+    #[clap(long)]
+    gateway: bool,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
-    if args.debug {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("debug"))
-            .init();
-    } else {
-        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
-            .init();
-    }
-
-    info!("Starting Hanzo MCP Server v{}", env!("CARGO_PKG_VERSION"));
-
-    let config = if args.config.exists() {
+    let mut config = if args.config.exists() {
         Config::from_file(&args.config)?
     } else {
         Config::default()
     };
+    config.read_only |= args.read_only;
+    config.gateway.enabled |= args.gateway;
+    if let Some(prefix) = args.tool_prefix {
+        config.naming.prefix = Some(prefix);
+    }
+
+    let _log_guard = logging::init(&config.logging, args.debug)?;
+
+    info!("Starting Hanzo MCP Server v{}", env!("CARGO_PKG_VERSION"));
+    if config.read_only {
+        info!("Read-only mode: mutating tool calls are refused");
+    }
 
     let server = MCPServer::new(config, args.port)?;
     info!("[MCP] JSON-RPC HTTP on http://127.0.0.1:{}", args.port);
@@ -0,0 +1,182 @@
+/// Per-tool execution metrics
+///
+/// The registry records every tool call here: invocation and failure counts,
+/// latency samples for percentiles, output volume, and the calls currently
+/// in flight. Everything is in memory and accumulates from server start
+/// until reset.
+
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime};
+
+/// Latency samples kept per tool for percentiles
+const MAX_SAMPLES: usize = 1000;
+
+#[derive(Debug, Default)]
+struct ToolStats {
+    calls: u64,
+    failures: u64,
+    output_bytes: u64,
+    total_ms: u64,
+    samples: VecDeque<u64>,
+}
+
+impl ToolStats {
+    fn to_json(&self) -> Value {
+        let mut sorted: Vec<u64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        json!({
+            "calls": self.calls,
+            "failures": self.failures,
+            "failure_rate": if self.calls == 0 { 0.0 } else { self.failures as f64 / self.calls as f64 },
+            "output_bytes": self.output_bytes,
+            "avg_ms": self.total_ms.checked_div(self.calls).unwrap_or(0),
+            "p50_ms": percentile(&sorted, 50.0),
+            "p95_ms": percentile(&sorted, 95.0),
+            "max_ms": sorted.last().copied().unwrap_or(0)
+        })
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Debug)]
+struct RunningOp {
+    tool: String,
+    action: Option<String>,
+    started: Instant,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    tools: BTreeMap<String, ToolStats>,
+    running: HashMap<u64, RunningOp>,
+    next_op: u64,
+}
+
+/// Metrics shared by all tool calls on a registry
+#[derive(Debug)]
+pub struct Metrics {
+    state: Mutex<State>,
+    since: SystemTime,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            since: SystemTime::now(),
+        }
+    }
+
+    /// Record the start of a call, returning its operation id
+    pub fn start(&self, tool: &str, action: Option<&str>) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.next_op += 1;
+        let op = state.next_op;
+        state.running.insert(op, RunningOp {
+            tool: tool.to_string(),
+            action: action.map(str::to_string),
+            started: Instant::now(),
+        });
+        op
+    }
+
+    /// Record the end of a call started with `start`
+    pub fn finish(&self, op: u64, success: bool, output_bytes: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(running) = state.running.remove(&op) else { return };
+        let elapsed = running.started.elapsed().as_millis() as u64;
+        let stats = state.tools.entry(running.tool).or_default();
+        stats.calls += 1;
+        if !success {
+            stats.failures += 1;
+        }
+        stats.output_bytes += output_bytes as u64;
+        stats.total_ms += elapsed;
+        if stats.samples.len() == MAX_SAMPLES {
+            stats.samples.pop_front();
+        }
+        stats.samples.push_back(elapsed);
+    }
+
+    /// Forget accumulated stats; running calls are kept
+    pub fn reset(&self) {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).tools.clear();
+    }
+
+    /// Stats for all tools, or only `tool`
+    pub fn snapshot(&self, tool: Option<&str>) -> Value {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let tools: serde_json::Map<String, Value> = state.tools.iter()
+            .filter(|(name, _)| tool.is_none_or(|t| t == name.as_str()))
+            .map(|(name, stats)| (name.clone(), stats.to_json()))
+            .collect();
+        let mut running: Vec<(&u64, &RunningOp)> = state.running.iter()
+            .filter(|(_, op)| tool.is_none_or(|t| t == op.tool))
+            .collect();
+        running.sort_by_key(|(id, _)| **id);
+        let running: Vec<Value> = running.into_iter()
+            .map(|(id, op)| json!({
+                "id": id,
+                "tool": op.tool,
+                "action": op.action,
+                "elapsed_ms": op.started.elapsed().as_millis() as u64
+            }))
+            .collect();
+        let since: chrono::DateTime<chrono::Utc> = self.since.into();
+        json!({
+            "since": since.to_rfc3339(),
+            "total_calls": state.tools.values().map(|s| s.calls).sum::<u64>(),
+            "tools": tools,
+            "running": running
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let samples: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&samples, 50.0), 50);
+        assert_eq!(percentile(&samples, 95.0), 95);
+        assert_eq!(percentile(&[7], 95.0), 7);
+        assert_eq!(percentile(&[], 50.0), 0);
+    }
+
+    #[test]
+    fn test_counts_and_running() {
+        let metrics = Metrics::new();
+        let first = metrics.start("fs", Some("read"));
+        let second = metrics.start("fs", Some("search"));
+        metrics.finish(first, true, 120);
+
+        let stats = metrics.snapshot(Some("fs"));
+        assert_eq!(stats["tools"]["fs"]["calls"], 1);
+        assert_eq!(stats["tools"]["fs"]["output_bytes"], 120);
+        assert_eq!(stats["running"][0]["action"], "search");
+
+        metrics.finish(second, false, 0);
+        let stats = metrics.snapshot(None);
+        assert_eq!(stats["tools"]["fs"]["failures"], 1);
+        assert_eq!(stats["tools"]["fs"]["failure_rate"], 0.5);
+        assert_eq!(stats["running"].as_array().unwrap().len(), 0);
+    }
+}
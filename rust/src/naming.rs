@@ -0,0 +1,113 @@
+/// Tool names as clients see them
+///
+/// A host aggregating several MCP servers may see more than one `fs` or
+/// `browser`. With a prefix configured every tool is advertised under a
+/// namespace (`hanzo.fs`), and aliases give tools further names (`proc`
+/// for exec). Calls resolve to the canonical name before anything else
+/// looks at them, so limits, hooks, metrics and the audit log only ever
+/// see `fs`, never `hanzo.fs` or an alias.
+
+use crate::config::NamingConfig;
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct Names {
+    /// Prefix and separator, e.g. `hanzo.`
+    namespace: Option<String>,
+    aliases: HashMap<String, String>,
+    list_aliases: bool,
+}
+
+impl Names {
+    /// Names for `config`, checked against the tools there are
+    pub fn new(config: &NamingConfig, tools: &[String]) -> Result<Self> {
+        for (alias, tool) in &config.aliases {
+            if !tools.contains(tool) {
+                return Err(ToolError::invalid(format!("Alias {} names unknown tool {}", alias, tool)).into());
+            }
+            if tools.contains(alias) {
+                return Err(ToolError::conflict(format!("Alias {} would hide the {} tool", alias, alias)).into());
+            }
+        }
+        let namespace = match config.prefix.as_deref().map(str::trim) {
+            Some("") | None => None,
+            Some(prefix) => Some(format!("{}{}", prefix, config.separator)),
+        };
+        Ok(Self { namespace, aliases: config.aliases.clone(), list_aliases: config.list_aliases })
+    }
+
+    /// Canonical tool name for a name a client called
+    pub fn resolve<'a>(&'a self, name: &'a str) -> &'a str {
+        let bare = self.namespace.as_deref()
+            .and_then(|namespace| name.strip_prefix(namespace))
+            .unwrap_or(name);
+        self.aliases.get(bare).map_or(bare, String::as_str)
+    }
+
+    /// Name a tool is advertised under
+    pub fn advertise(&self, name: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}{}", namespace, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Definitions renamed for clients, followed by copies under their
+    /// aliases when those are listed
+    pub fn definitions(&self, definitions: Vec<Value>) -> Vec<Value> {
+        let mut advertised = Vec::with_capacity(definitions.len());
+        for mut definition in definitions {
+            let name = definition["name"].as_str().unwrap_or_default().to_string();
+            definition["name"] = self.advertise(&name).into();
+            let mut aliases: Vec<&String> = match self.list_aliases {
+                true => self.aliases.iter().filter(|(_, tool)| **tool == name).map(|(alias, _)| alias).collect(),
+                false => Vec::new(),
+            };
+            aliases.sort();
+            let copies: Vec<Value> = aliases.into_iter().map(|alias| {
+                let mut copy = definition.clone();
+                copy["name"] = self.advertise(alias).into();
+                copy["description"] = format!("Alias of {}. {}", self.advertise(&name), definition["description"].as_str().unwrap_or("")).into();
+                copy
+            }).collect();
+            advertised.push(definition);
+            advertised.extend(copies);
+        }
+        advertised
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_names() {
+        let tools: Vec<String> = ["exec", "fs", "computer"].iter().map(|t| t.to_string()).collect();
+        let config = NamingConfig { prefix: Some("hanzo".into()), list_aliases: true, ..Default::default() };
+        let names = Names::new(&config, &tools).unwrap();
+        assert_eq!(names.resolve("hanzo.fs"), "fs");
+        assert_eq!(names.resolve("fs"), "fs");
+        assert_eq!(names.resolve("hanzo.proc"), "exec");
+        assert_eq!(names.resolve("ui"), "computer");
+        assert_eq!(names.resolve("other.fs"), "other.fs");
+        assert_eq!(names.advertise("fs"), "hanzo.fs");
+
+        let listed = names.definitions(vec![json!({ "name": "exec", "description": "Run" }), json!({ "name": "fs" })]);
+        let listed: Vec<&str> = listed.iter().map(|d| d["name"].as_str().unwrap()).collect();
+        assert_eq!(listed, ["hanzo.exec", "hanzo.proc", "hanzo.fs"]);
+
+        let plain = Names::new(&NamingConfig { separator: "_".into(), prefix: Some("h".into()), ..Default::default() }, &tools).unwrap();
+        assert_eq!(plain.resolve("h_exec"), "exec");
+        assert_eq!(plain.definitions(vec![json!({ "name": "exec" })]).len(), 1);
+
+        let unknown = NamingConfig { aliases: HashMap::from([("x".into(), "nope".into())]), ..Default::default() };
+        assert!(Names::new(&unknown, &tools).is_err());
+        let shadowing = NamingConfig { aliases: HashMap::from([("fs".into(), "exec".into())]), ..Default::default() };
+        assert!(Names::new(&shadowing, &tools).is_err());
+    }
+}
@@ -0,0 +1,228 @@
+/// Paging of oversized tool results
+///
+/// When a result serializes to more than the configured size, its largest
+/// array or string is cut to what fits and the full result is kept behind
+/// an opaque cursor. The `page` tool hands out the following slices in the
+/// same shape as the first, so a caller reads every page the same way.
+/// A cursor only pages for the session whose call produced it.
+
+use crate::config::PaginationConfig;
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Share of the budget a page's slice always gets, however large the rest
+/// of the result is
+const MIN_SLICE_BYTES: usize = 4096;
+
+/// A paged result kept for later pages
+struct Stored {
+    id: u64,
+    tool: String,
+    /// Session whose call produced the result
+    session: Option<String>,
+    content: Value,
+    pointer: String,
+    created: Instant,
+}
+
+pub struct Pager {
+    config: PaginationConfig,
+    stored: Mutex<VecDeque<Stored>>,
+    next: AtomicU64,
+}
+
+impl Pager {
+    pub fn new(config: PaginationConfig) -> Self {
+        Self { config, stored: Mutex::new(VecDeque::new()), next: AtomicU64::new(1) }
+    }
+
+    /// First page of `content` if it is over the limit, else `content`
+    pub fn paginate(&self, tool: &str, content: Value, session: Option<&str>) -> Value {
+        let limit = self.config.max_result_bytes;
+        if limit == 0 || !content.is_object() {
+            return content;
+        }
+        let size = content.to_string().len();
+        if size <= limit {
+            return content;
+        }
+        let Some((pointer, target_size)) = largest(&content, String::new()) else { return content };
+        // Paging the biggest field must be what brings the result down
+        if size - target_size > limit / 2 && target_size < size / 2 {
+            return content;
+        }
+
+        let id = self.keep(tool, content.clone(), &pointer, session);
+        self.slice(id, tool, &content, &pointer, 0)
+    }
+
+    /// Keep `content` for `session` to page through the field at `pointer`;
+    /// returns the id cursors for it are made from
+    pub fn keep(&self, tool: &str, content: Value, pointer: &str, session: Option<&str>) -> u64 {
+        let id = self.next.fetch_add(1, Ordering::SeqCst);
+        let mut stored = self.stored.lock().unwrap();
+        self.expire(&mut stored);
+        while stored.len() >= self.config.max_cursors.max(1) {
+            stored.pop_front();
+        }
+        stored.push_back(Stored {
+            id,
+            tool: tool.to_string(),
+            session: session.map(str::to_string),
+            content,
+            pointer: pointer.to_string(),
+            created: Instant::now(),
+        });
+        id
+    }
+
+    /// The page a cursor points at; other sessions' cursors read as expired
+    pub fn page(&self, cursor: &str, session: Option<&str>) -> Result<Value> {
+        let invalid = || ToolError::invalid(format!("Invalid cursor: {}", cursor));
+        let (id, offset) = cursor.strip_prefix('p').and_then(|c| c.split_once('.')).ok_or_else(invalid)?;
+        let id: u64 = id.parse().map_err(|_| invalid())?;
+        let offset: usize = offset.parse().map_err(|_| invalid())?;
+
+        let mut stored = self.stored.lock().unwrap();
+        self.expire(&mut stored);
+        let entry = stored.iter().find(|s| s.id == id && s.session.as_deref() == session).ok_or_else(|| {
+            ToolError::not_found(format!("Cursor {} has expired; run the original call again", cursor))
+        })?;
+        Ok(self.slice(id, &entry.tool, &entry.content, &entry.pointer, offset))
+    }
+
+    fn expire(&self, stored: &mut VecDeque<Stored>) {
+        let ttl = Duration::from_secs(self.config.cursor_ttl_secs);
+        stored.retain(|s| s.created.elapsed() < ttl);
+    }
+
+    pub fn cursor(id: u64, offset: usize) -> String {
+        format!("p{}.{}", id, offset)
+    }
+
+    /// `content` with the field at `pointer` cut to the page from `offset`
+    fn slice(&self, id: u64, tool: &str, content: &Value, pointer: &str, offset: usize) -> Value {
+        let mut page = content.clone();
+        let Some(target) = page.pointer_mut(pointer) else { return page };
+        let rest = content.to_string().len() - target.to_string().len();
+        let budget = self.config.max_result_bytes.saturating_sub(rest).max(MIN_SLICE_BYTES);
+
+        let (unit, total, end) = match target {
+            Value::Array(items) => {
+                let total = items.len();
+                let mut end = offset.min(total);
+                let mut used = 0;
+                while end < total {
+                    used += items[end].to_string().len() + 1;
+                    if used > budget && end > offset {
+                        break;
+                    }
+                    end += 1;
+                }
+                *target = Value::Array(items[offset.min(total)..end].to_vec());
+                ("items", total, end)
+            }
+            Value::String(text) => {
+                let chars: Vec<char> = text.chars().collect();
+                let total = chars.len();
+                let mut end = offset.min(total);
+                let mut used = 0;
+                while end < total {
+                    used += chars[end].len_utf8();
+                    if used > budget && end > offset {
+                        break;
+                    }
+                    end += 1;
+                }
+                *target = Value::String(chars[offset.min(total)..end].iter().collect());
+                ("chars", total, end)
+            }
+            _ => return page,
+        };
+
+        let more = end < total;
+        page["pagination"] = json!({
+            "tool": tool,
+            "field": pointer,
+            "unit": unit,
+            "offset": offset,
+            "returned": end.saturating_sub(offset),
+            "total": total,
+            "has_more": more,
+            "cursor": more.then(|| Self::cursor(id, end)),
+            "hint": if more { "Call page with this cursor for the rest" } else { "Last page" }
+        });
+        page
+    }
+}
+
+/// JSON pointer and serialized size of the largest array or string
+pub(crate) fn largest(value: &Value, pointer: String) -> Option<(String, usize)> {
+    let own = match value {
+        Value::Array(_) | Value::String(_) => Some((pointer.clone(), value.to_string().len())),
+        _ => None,
+    };
+    let children: Box<dyn Iterator<Item = (String, &Value)>> = match value {
+        Value::Object(map) => Box::new(map.iter().map(|(k, v)| (k.replace('~', "~0").replace('/', "~1"), v))),
+        _ => Box::new(std::iter::empty()),
+    };
+    // Arrays are paged as a whole rather than inside their items
+    children
+        .filter_map(|(key, child)| largest(child, format!("{}/{}", pointer, key)))
+        .chain(own)
+        .max_by_key(|(_, size)| *size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pager(max_result_bytes: usize) -> Pager {
+        Pager::new(PaginationConfig { max_result_bytes, ..Default::default() })
+    }
+
+    #[test]
+    fn test_pages_largest_array() {
+        let pager = pager(5000);
+        let items: Vec<Value> = (0..1000).map(|i| json!({ "file": format!("src/file{}.rs", i), "line": i })).collect();
+        let content = json!({ "data": { "matches": items, "query": "x" }, "ok": true });
+
+        let first = pager.paginate("search", content.clone(), Some("s1"));
+        let pagination = &first["pagination"];
+        assert_eq!(pagination["field"], "/data/matches");
+        assert_eq!(pagination["total"], 1000);
+        assert_eq!(first["data"]["query"], "x");
+        assert!(first.to_string().len() <= 5000 + 400);
+
+        let mut seen = first["data"]["matches"].as_array().unwrap().clone();
+        let mut cursor = pagination["cursor"].as_str().map(str::to_string);
+        while let Some(next) = cursor {
+            assert!(pager.page(&next, Some("s2")).is_err(), "another session cannot read the result");
+            assert!(pager.page(&next, None).is_err());
+            let page = pager.page(&next, Some("s1")).unwrap();
+            seen.extend(page["data"]["matches"].as_array().unwrap().iter().cloned());
+            cursor = page["pagination"]["cursor"].as_str().map(str::to_string);
+        }
+        assert_eq!(Value::Array(seen), content["data"]["matches"]);
+        assert!(pager.page("p99.0", Some("s1")).is_err());
+    }
+
+    #[test]
+    fn test_pages_long_string() {
+        let pager = pager(MIN_SLICE_BYTES);
+        let text = "é".repeat(3000);
+        let first = pager.paginate("fs", json!({ "content": text }), None);
+        assert_eq!(first["pagination"]["unit"], "chars");
+        let cursor = first["pagination"]["cursor"].as_str().unwrap();
+        let second = pager.page(cursor, None).unwrap();
+        let joined = format!("{}{}", first["content"].as_str().unwrap(), second["content"].as_str().unwrap());
+        assert_eq!(joined, text);
+        assert_eq!(second["pagination"]["has_more"], false);
+        assert_eq!(pager.paginate("fs", json!({ "small": 1 }), None), json!({ "small": 1 }));
+    }
+}
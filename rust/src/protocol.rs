@@ -86,7 +86,7 @@ pub struct ToolCallParams {
 }
 
 /// Content types for responses
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Content {
     #[serde(rename = "text")]
@@ -98,10 +98,40 @@ pub enum Content {
         mime_type: String,
     },
     #[serde(rename = "resource")]
-    Resource {
-        uri: String,
-        #[serde(rename = "mimeType")]
-        mime_type: Option<String>,
-        text: Option<String>,
-    },
-}
\ No newline at end of file
+    Resource { resource: ResourceContents },
+}
+
+/// Embedded resource body: `text` for textual data, base64 `blob` otherwise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+impl Content {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text { text: text.into() }
+    }
+
+    /// Image from base64 data
+    pub fn image(data: impl Into<String>, mime_type: impl Into<String>) -> Self {
+        Self::Image { data: data.into(), mime_type: mime_type.into() }
+    }
+
+    /// Embedded text resource
+    pub fn resource_text(uri: impl Into<String>, mime_type: Option<String>, text: impl Into<String>) -> Self {
+        Self::Resource {
+            resource: ResourceContents {
+                uri: uri.into(),
+                mime_type,
+                text: Some(text.into()),
+                blob: None,
+            },
+        }
+    }
+}
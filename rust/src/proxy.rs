@@ -0,0 +1,586 @@
+/// Other MCP servers mounted into this one
+///
+/// Each server in `[proxy.servers]` is started (a command speaking
+/// JSON-RPC lines over stdio) or reached (a streamable HTTP endpoint),
+/// initialized and asked for its tools, which the registry then serves as
+/// `<prefix>_<tool>` beside its own. Calls to them take the registry's
+/// path like any other, so limits, timeouts, argument validation, hooks,
+/// the audit log and read-only mode apply to every tool a client sees and
+/// this server is the one place they are permitted and recorded. In
+/// read-only mode a mounted tool is allowed only when its server
+/// annotates it `readOnlyHint`.
+
+use crate::config::ProxyServer;
+use crate::error::ToolError;
+use crate::protocol::{self, Content};
+use crate::{MCPTool, ToolResult};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+/// Pages of `tools/list` followed before giving up on a server
+const MAX_TOOL_PAGES: usize = 50;
+
+/// Bytes of a stdio server's stderr kept for error messages
+const MAX_STDERR: usize = 8_000;
+
+/// Calls waiting for their answer, by id
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// Writer half of a stdio server, shared with the reader answering the
+/// server's own requests
+type Writer = Arc<tokio::sync::Mutex<Option<ChildStdin>>>;
+
+/// A connection to one downstream MCP server
+pub struct Downstream {
+    name: String,
+    transport: Transport,
+    limit: Duration,
+    /// `serverInfo` from its initialize answer
+    info: Value,
+    next_id: AtomicU64,
+    calls: AtomicU64,
+}
+
+enum Transport {
+    Stdio(StdioServer),
+    Http(HttpServer),
+}
+
+/// A server run as a child process, one JSON-RPC message per line
+struct StdioServer {
+    child: Mutex<Child>,
+    stdin: Writer,
+    pending: Pending,
+    stderr: Arc<Mutex<String>>,
+}
+
+/// A server reached over streamable HTTP
+struct HttpServer {
+    client: reqwest::Client,
+    url: String,
+    headers: reqwest::header::HeaderMap,
+    /// `Mcp-Session-Id` the server gave at initialize
+    session: Mutex<Option<String>>,
+}
+
+impl Downstream {
+    /// Start or reach the server `name` and initialize it
+    pub async fn connect(name: &str, config: &ProxyServer) -> Result<Self> {
+        let transport = match (&config.command, &config.url) {
+            (Some(command), None) => Transport::Stdio(StdioServer::spawn(command, config)?),
+            (None, Some(url)) => Transport::Http(HttpServer::new(url, config)?),
+            (Some(_), Some(_)) => return Err(ToolError::invalid(format!("Proxied server {} has both a command and a url", name)).into()),
+            (None, None) => return Err(ToolError::invalid(format!("Proxied server {} needs a command or a url", name)).into()),
+        };
+        let mut downstream = Self {
+            name: name.to_string(),
+            transport,
+            limit: Duration::from_secs(config.timeout_secs.max(1)),
+            info: Value::Null,
+            next_id: AtomicU64::new(1),
+            calls: AtomicU64::new(0),
+        };
+        let initialized = downstream.request("initialize", json!({
+            "protocolVersion": protocol::PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "hanzo-mcp", "version": env!("CARGO_PKG_VERSION") }
+        })).await?;
+        downstream.info = initialized["serverInfo"].clone();
+        downstream.notify("notifications/initialized").await?;
+        Ok(downstream)
+    }
+
+    /// Every tool the server lists, following its cursors
+    pub async fn tools(&self) -> Result<Vec<Value>> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_TOOL_PAGES {
+            let params = cursor.map_or_else(|| json!({}), |c| json!({ "cursor": c }));
+            let mut page = self.request("tools/list", params).await?;
+            if let Some(listed) = page["tools"].as_array_mut() {
+                tools.append(listed);
+            }
+            cursor = page["nextCursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+        Err(ToolError::external(format!("{} listed more than {} pages of tools", self.name, MAX_TOOL_PAGES)).into())
+    }
+
+    /// Call the server's tool `tool`, returning its `tools/call` result
+    pub async fn call_tool(&self, tool: &str, arguments: Value) -> Result<Value> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.request("tools/call", json!({ "name": tool, "arguments": arguments })).await
+    }
+
+    pub fn describe(&self) -> Value {
+        let (transport, running) = match &self.transport {
+            Transport::Stdio(server) => ("stdio", matches!(server.child.lock().unwrap().try_wait(), Ok(None))),
+            Transport::Http(_) => ("http", true),
+        };
+        json!({
+            "server": self.name,
+            "info": self.info,
+            "transport": transport,
+            "running": running,
+            "calls": self.calls.load(Ordering::Relaxed)
+        })
+    }
+
+    /// Send a request and wait for its result
+    async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let answer = match tokio::time::timeout(self.limit, self.transport.exchange(id, &message)).await {
+            Ok(answer) => answer,
+            Err(_) => {
+                self.transport.forget(id);
+                return Err(ToolError::timeout(format!("{} did not answer {} within {}s", self.name, method, self.limit.as_secs())).into());
+            }
+        };
+        let mut response = answer.map_err(|e| ToolError::external(format!("{}: {}", self.name, e)))?;
+        let error = &response["error"];
+        if !error.is_null() {
+            let message = error["message"].as_str().unwrap_or("unknown error");
+            return Err(match error["code"].as_i64() {
+                Some(-32601) => ToolError::unsupported(format!("{} does not support {}: {}", self.name, method, message)),
+                Some(-32602) => ToolError::invalid(format!("{}: {}", self.name, message)),
+                _ => ToolError::external(format!("{}: {}", self.name, message)),
+            }.into());
+        }
+        Ok(response["result"].take())
+    }
+
+    async fn notify(&self, method: &str) -> Result<()> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+        match tokio::time::timeout(self.limit, self.transport.send(&message)).await {
+            Ok(sent) => sent.map_err(|e| ToolError::external(format!("{}: {}", self.name, e)).into()),
+            Err(_) => Err(ToolError::timeout(format!("{} did not take {} within {}s", self.name, method, self.limit.as_secs())).into()),
+        }
+    }
+}
+
+impl Transport {
+    /// Send a request and take the response with its id
+    async fn exchange(&self, id: u64, message: &Value) -> Result<Value> {
+        match self {
+            Transport::Stdio(server) => server.exchange(id, message).await,
+            Transport::Http(server) => {
+                // An event stream may carry the server's notifications
+                // ahead of the response
+                server.post(message).await?.into_iter()
+                    .find(|m| m["id"].as_u64() == Some(id) && m.get("method").is_none())
+                    .ok_or_else(|| anyhow::anyhow!("no response to request {}", id))
+            }
+        }
+    }
+
+    /// Send a message that has no response
+    async fn send(&self, message: &Value) -> Result<()> {
+        match self {
+            Transport::Stdio(server) => server.send(message).await,
+            Transport::Http(server) => server.post(message).await.map(drop),
+        }
+    }
+
+    /// Drop a request that timed out
+    fn forget(&self, id: u64) {
+        if let Transport::Stdio(server) = self {
+            server.pending.lock().unwrap().remove(&id);
+        }
+    }
+}
+
+impl StdioServer {
+    fn spawn(command: &str, config: &ProxyServer) -> Result<Self> {
+        let mut process = Command::new(command);
+        process.args(&config.args)
+            .envs(config.env.iter().map(|(key, value)| (key, expand(value))))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        if let Some(cwd) = &config.cwd {
+            process.current_dir(cwd);
+        }
+        let mut child = process.spawn().map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => ToolError::not_found(format!("{} is not installed", command)),
+            _ => ToolError::external(format!("Cannot start {}: {}", command, e)),
+        })?;
+
+        let stdin: Writer = Arc::new(tokio::sync::Mutex::new(child.stdin.take()));
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let pending = Pending::default();
+        let waiting = pending.clone();
+        let writer = stdin.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(line)) = stdout.next_line().await {
+                let Ok(message) = serde_json::from_str::<Value>(&line) else { continue };
+                match (&message["method"], &message["id"]) {
+                    // The server asking something of its client
+                    (Value::String(method), id) if !id.is_null() => {
+                        let reply = match method.as_str() {
+                            "ping" => json!({ "jsonrpc": "2.0", "id": id, "result": {} }),
+                            _ => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": -32601, "message": format!("Method not found: {}", method) } }),
+                        };
+                        if let Some(stdin) = writer.lock().await.as_mut() {
+                            let _ = stdin.write_all(format!("{}\n", reply).as_bytes()).await;
+                            let _ = stdin.flush().await;
+                        }
+                    }
+                    (Value::String(_), _) => {}
+                    (_, id) => {
+                        let sender = id.as_u64().and_then(|id| waiting.lock().unwrap().remove(&id));
+                        if let Some(sender) = sender {
+                            let _ = sender.send(message);
+                        }
+                    }
+                }
+            }
+            // The server exited: calls still waiting get no answer
+            waiting.lock().unwrap().clear();
+        });
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let mut pipe = child.stderr.take().expect("stderr is piped");
+        let sink = stderr.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = pipe.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                let mut kept = sink.lock().unwrap();
+                kept.push_str(&String::from_utf8_lossy(&buf[..n]));
+                if kept.len() > MAX_STDERR {
+                    let mut cut = kept.len() - MAX_STDERR;
+                    while !kept.is_char_boundary(cut) {
+                        cut += 1;
+                    }
+                    kept.drain(..cut);
+                }
+            }
+        });
+        Ok(Self { child: Mutex::new(child), stdin, pending, stderr })
+    }
+
+    async fn exchange(&self, id: u64, message: &Value) -> Result<Value> {
+        let (sender, answer) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+        if let Err(e) = self.send(message).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+        answer.await.map_err(|_| anyhow::anyhow!("server exited: {}", self.stderr.lock().unwrap().trim()))
+    }
+
+    async fn send(&self, message: &Value) -> Result<()> {
+        let line = format!("{}\n", message);
+        let written = match self.stdin.lock().await.as_mut() {
+            Some(stdin) => match stdin.write_all(line.as_bytes()).await {
+                Ok(()) => stdin.flush().await,
+                Err(e) => Err(e),
+            },
+            None => Err(std::io::ErrorKind::BrokenPipe.into()),
+        };
+        written.map_err(|e| anyhow::anyhow!("server has exited: {} {}", e, self.stderr.lock().unwrap().trim()))
+    }
+}
+
+impl HttpServer {
+    fn new(url: &str, config: &ProxyServer) -> Result<Self> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (key, value) in &config.headers {
+            let name = reqwest::header::HeaderName::from_bytes(key.as_bytes())
+                .map_err(|_| ToolError::invalid(format!("Invalid header name: {}", key)))?;
+            let value = reqwest::header::HeaderValue::from_str(&expand(value))
+                .map_err(|_| ToolError::invalid(format!("Invalid value for header {}", key)))?;
+            headers.insert(name, value);
+        }
+        Ok(Self { client: reqwest::Client::new(), url: url.to_string(), headers, session: Mutex::new(None) })
+    }
+
+    /// POST a message, returning the JSON-RPC messages of the answer
+    async fn post(&self, message: &Value) -> Result<Vec<Value>> {
+        let mut request = self.client.post(self.url.as_str())
+            .headers(self.headers.clone())
+            .header(reqwest::header::ACCEPT, "application/json, text/event-stream")
+            .json(message);
+        let session = self.session.lock().unwrap().clone();
+        if let Some(id) = session {
+            request = request.header("mcp-session-id", id);
+        }
+        let response = request.send().await?;
+        if let Some(id) = response.headers().get("mcp-session-id").and_then(|v| v.to_str().ok()) {
+            *self.session.lock().unwrap() = Some(id.to_string());
+        }
+        let status = response.status();
+        let streamed = response.headers().get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|t| t.starts_with("text/event-stream"));
+        let body = response.text().await?;
+        if !status.is_success() {
+            let body: String = body.chars().take(500).collect();
+            anyhow::bail!("HTTP {} {}", status, body.trim());
+        }
+        if body.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        if streamed {
+            return Ok(events(&body));
+        }
+        Ok(match serde_json::from_str(&body)? {
+            Value::Array(batch) => batch,
+            single => vec![single],
+        })
+    }
+}
+
+/// The JSON messages in a server-sent event stream
+fn events(body: &str) -> Vec<Value> {
+    body.replace("\r\n", "\n")
+        .split("\n\n")
+        .filter_map(|event| {
+            let data: Vec<&str> = event.lines()
+                .filter_map(|line| line.strip_prefix("data:"))
+                .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                .collect();
+            serde_json::from_str(&data.join("\n")).ok()
+        })
+        .collect()
+}
+
+/// `${VAR}` and `$VAR` replaced from the environment; unset ones are left
+fn expand(value: &str) -> String {
+    shellexpand::env_with_context_no_errors(value, |var| std::env::var(var).ok()).into_owned()
+}
+
+/// A downstream tool served under this server's name for it
+pub struct ProxyTool {
+    downstream: Arc<Downstream>,
+    name: String,
+    /// Its name on the downstream server
+    remote: String,
+    description: String,
+    schema: Value,
+    output: Option<Value>,
+    read_only: bool,
+}
+
+impl ProxyTool {
+    /// Whether its server says it changes nothing
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The downstream server it calls
+    pub fn downstream(&self) -> &Arc<Downstream> {
+        &self.downstream
+    }
+}
+
+#[async_trait::async_trait]
+impl MCPTool for ProxyTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.schema.clone()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.output.clone()
+    }
+
+    async fn execute(&self, params: Value) -> Result<ToolResult> {
+        let arguments = if params.is_null() { json!({}) } else { params };
+        let result = self.downstream.call_tool(&self.remote, arguments).await?;
+        Ok(to_result(&self.downstream.name, &self.remote, result))
+    }
+}
+
+/// Connect to the server `name` and wrap the tools it lists
+pub async fn mount(name: &str, config: &ProxyServer) -> Result<Vec<ProxyTool>> {
+    let downstream = Arc::new(Downstream::connect(name, config).await?);
+    let prefix = config.prefix.as_deref().unwrap_or(name);
+    let tools = downstream.tools().await?;
+    Ok(tools.into_iter()
+        .filter_map(|tool| {
+            let remote = tool["name"].as_str()?.to_string();
+            if !config.tools.is_empty() && !config.tools.contains(&remote) {
+                return None;
+            }
+            let schema = match &tool["inputSchema"] {
+                Value::Object(_) => tool["inputSchema"].clone(),
+                _ => json!({ "type": "object" }),
+            };
+            Some(ProxyTool {
+                downstream: downstream.clone(),
+                name: if prefix.is_empty() { remote.clone() } else { format!("{}_{}", prefix, remote) },
+                description: tool["description"].as_str().unwrap_or_default().to_string(),
+                schema,
+                output: tool["outputSchema"].is_object().then(|| tool["outputSchema"].clone()),
+                read_only: tool["annotations"]["readOnlyHint"].as_bool() == Some(true),
+                remote,
+            })
+        })
+        .collect())
+}
+
+/// A downstream `tools/call` result as one of this server's
+fn to_result(server: &str, tool: &str, mut result: Value) -> ToolResult {
+    let mut texts = Vec::new();
+    let mut blocks = Vec::new();
+    for block in result["content"].as_array().into_iter().flatten() {
+        match block["type"].as_str() {
+            Some("text") => texts.push(block["text"].as_str().unwrap_or_default().to_string()),
+            _ => match serde_json::from_value::<Content>(block.clone()) {
+                Ok(content) => blocks.push(content),
+                // Kinds this server has no block for pass as their JSON
+                Err(_) => texts.push(block.to_string()),
+            },
+        }
+    }
+    let text = texts.join("\n");
+    if result["isError"].as_bool() == Some(true) {
+        let message = if text.is_empty() { "tool failed".to_string() } else { text };
+        return ToolResult::from_error(&ToolError::external(format!("{} {}: {}", server, tool, message))).with_blocks(blocks);
+    }
+    let content = match result["structuredContent"].take() {
+        Value::Object(structured) => Value::Object(structured),
+        _ => match serde_json::from_str::<Value>(&text) {
+            Ok(parsed @ (Value::Object(_) | Value::Array(_))) => parsed,
+            _ => json!({ "text": text }),
+        },
+    };
+    ToolResult::ok(content).with_blocks(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ToolRegistry;
+
+    /// A stdio MCP server with an `echo` tool that reads and a `write`
+    /// tool that fails when asked to
+    const SERVER: &str = r#"
+const readline = require("node:readline");
+const send = (message) => process.stdout.write(JSON.stringify({ jsonrpc: "2.0", ...message }) + "\n");
+readline.createInterface({ input: process.stdin }).on("line", (line) => {
+  const { id, method, params } = JSON.parse(line);
+  if (id === undefined) return;
+  if (method === "initialize") return send({ id, result: { protocolVersion: params.protocolVersion, capabilities: { tools: {} }, serverInfo: { name: "mock", version: "1.0" } } });
+  if (method === "tools/list" && !params.cursor) return send({ id, result: { nextCursor: "2", tools: [
+    { name: "echo", description: "Echo text", inputSchema: { type: "object", properties: { text: { type: "string" } }, required: ["text"] }, annotations: { readOnlyHint: true } },
+  ] } });
+  if (method === "tools/list") return send({ id, result: { tools: [
+    { name: "write", description: "Write", inputSchema: { type: "object" } },
+  ] } });
+  if (method === "tools/call" && params.name === "echo") return send({ id, result: { content: [{ type: "text", text: params.arguments.text }] } });
+  if (method === "tools/call") return send({ id, result: { isError: !!params.arguments.fail, content: [{ type: "text", text: params.arguments.fail ? "disk full" : '{"written":true}' }] } });
+  send({ id, error: { code: -32601, message: "Method not found" } });
+});
+"#;
+
+    fn server() -> Option<ProxyServer> {
+        if std::process::Command::new("node").arg("--version").output().is_err() {
+            return None;
+        }
+        Some(ProxyServer {
+            command: Some("node".into()),
+            args: vec!["-e".into(), SERVER.into()],
+            timeout_secs: 10,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_events() {
+        let body = "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/progress\"}\n\nid: 2\r\ndata: {\"jsonrpc\":\"2.0\",\r\ndata: \"id\":1,\"result\":{}}\r\n\r\n";
+        let messages = events(body);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1]["id"], 1);
+    }
+
+    #[test]
+    fn test_to_result() {
+        let result = to_result("gh", "issue", json!({ "content": [{ "type": "text", "text": "plain" }] }));
+        assert!(result.success);
+        assert_eq!(result.content, json!({ "text": "plain" }));
+
+        let result = to_result("gh", "issue", json!({
+            "content": [{ "type": "text", "text": "{}" }, { "type": "image", "data": "aGk=", "mimeType": "image/png" }],
+            "structuredContent": { "number": 7 }
+        }));
+        assert_eq!(result.content["number"], 7);
+        assert_eq!(result.blocks.len(), 1);
+
+        let failed = to_result("gh", "issue", json!({ "isError": true, "content": [{ "type": "text", "text": "not found" }] }));
+        assert!(!failed.success);
+        assert_eq!(failed.error.as_deref(), Some("gh issue: not found"));
+        assert_eq!(failed.content["error"], "external");
+    }
+
+    #[tokio::test]
+    async fn test_connect_needs_one_transport() {
+        let err = Downstream::connect("none", &ProxyServer::default()).await.err().unwrap();
+        assert_eq!(ToolError::classify(&err).code(), "invalid_argument");
+        let both = ProxyServer { command: Some("node".into()), url: Some("http://127.0.0.1:9".into()), ..Default::default() };
+        assert!(Downstream::connect("both", &both).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mount_stdio() {
+        let Some(config) = server() else { return };
+        let tools = mount("mock", &config).await.unwrap();
+        let names: Vec<&str> = tools.iter().map(|t| t.name()).collect();
+        assert_eq!(names, ["mock_echo", "mock_write"]);
+        assert!(tools[0].read_only() && !tools[1].read_only());
+        assert_eq!(tools[0].downstream().describe()["info"]["name"], "mock");
+
+        let echoed = tools[0].execute(json!({ "text": "hi" })).await.unwrap();
+        assert_eq!(echoed.content["text"], "hi");
+        let written = tools[1].execute(json!({})).await.unwrap();
+        assert_eq!(written.content["written"], true);
+        let failed = tools[1].execute(json!({ "fail": true })).await.unwrap();
+        assert!(!failed.success);
+        assert_eq!(tools[1].downstream().describe()["calls"], 3);
+
+        let only = ProxyServer { prefix: Some("m".into()), tools: vec!["echo".into()], ..config };
+        let tools = mount("mock", &only).await.unwrap();
+        assert_eq!(tools.iter().map(|t| t.name()).collect::<Vec<_>>(), ["m_echo"]);
+    }
+
+    #[tokio::test]
+    async fn test_registry_mounts() {
+        let Some(config) = server() else { return };
+        let mut registry = ToolRegistry::new();
+        let proxy = crate::config::ProxyConfig { servers: HashMap::from([("mock".to_string(), config)]) };
+        registry.mount_proxies(&proxy).await;
+        assert!(registry.list().contains(&"mock_echo".to_string()));
+
+        let invalid = registry.execute("mock_echo", json!({})).await.unwrap();
+        assert_eq!(invalid.content["error"], "invalid_argument");
+        let mounts = registry.mounts();
+        assert_eq!(mounts[0]["server"], "mock");
+        assert_eq!(mounts[0]["tools"], json!(["mock_echo", "mock_write"]));
+
+        registry.set_read_only(true);
+        assert!(registry.execute("mock_echo", json!({ "text": "hi" })).await.unwrap().success);
+        let refused = registry.execute("mock_write", json!({})).await.unwrap();
+        assert_eq!(refused.content["error"], "permission_denied");
+    }
+}
@@ -0,0 +1,210 @@
+/// Read-only server mode
+///
+/// With `--read-only` (or `read_only = true` in the config) the registry
+/// refuses every call that could change files, processes, the desktop, a
+/// web page or stored memories: fs writes, process execution, UI input,
+/// browser interaction, git and memory updates, code rewrites, REPLs,
+/// project commands, linters (which build code), containers and cluster changes other than dry
+/// runs. Reading, searching, recall and the agent's own bookkeeping
+/// (plans, todos, scratch, thinking) stay available, short of exporting it
+/// to a file, which suits code-review bots and evaluating untrusted models.
+///
+/// Actions are parsed with each tool's own parser, so aliases are judged
+/// like the action they stand for. Actions a tool does not know are let
+/// through for the tool to reject.
+///
+/// Tools mounted from other MCP servers (see `proxy`) are not judged
+/// here: the registry lets through those their server annotates
+/// `readOnlyHint` and refuses the rest.
+
+use crate::tools::browser_tool::BrowserAction;
+use crate::tools::code_tool::CodeAction;
+use crate::tools::calendar_tool::CalendarAction;
+use crate::tools::diagnostics_tool::DiagnosticsAction;
+use crate::tools::computer_tool::UiAction;
+use crate::tools::docker_tool::DockerAction;
+use crate::tools::exec_tool::ProcAction;
+use crate::tools::fetch_tool::NetAction;
+use crate::tools::fs_tool::FsAction;
+use crate::tools::git_tool::VcsAction;
+use crate::tools::image_tool::ImageAction;
+use crate::tools::k8s_tool::{DryRun, K8sAction};
+use crate::tools::lsp_tool::LspAction;
+use crate::tools::memory_tool::MemoryAction;
+use crate::tools::notify_tool::NotifyAction;
+use crate::tools::plan_tool::PlanAction;
+use crate::tools::repl_tool::ReplAction;
+use crate::tools::scratch_tool::ScratchAction;
+use crate::tools::search_tool::SearchAction;
+use crate::tools::storage_tool::StorageAction;
+use crate::tools::task_tool::TaskAction;
+use crate::tools::test_tool::TestAction;
+use crate::tools::think_tool::LlmAction;
+use crate::tools::vector_tool::VectorAction;
+use serde_json::Value;
+use std::str::FromStr;
+
+/// Whether a call leaves everything as it found it
+pub fn permits(tool: &str, params: &Value) -> bool {
+    let action = params["action"].as_str().unwrap_or("");
+    match tool {
+        "fs" => reads::<FsAction>(action, |a| matches!(a,
+            FsAction::Read | FsAction::ReadLines | FsAction::Tree | FsAction::Find | FsAction::Search
+            | FsAction::Outline | FsAction::Info | FsAction::History | FsAction::ArchiveList | FsAction::Hash
+            | FsAction::Readlink | FsAction::Realpath | FsAction::Help)),
+        "exec" => reads::<ProcAction>(action, |a| matches!(a,
+            ProcAction::Wait | ProcAction::Ps | ProcAction::Logs | ProcAction::Status
+            | ProcAction::Ports | ProcAction::FreePort | ProcAction::Help)),
+        "computer" => reads::<UiAction>(action, |a| matches!(a,
+            UiAction::Screenshot | UiAction::ScreenshotRegion | UiAction::RecordScreenStart | UiAction::RecordScreenStop
+            | UiAction::GetActiveWindow | UiAction::ListWindows
+            | UiAction::GetScreens | UiAction::ScreenSize | UiAction::Position | UiAction::ListRegions
+            | UiAction::AxList | UiAction::Info)),
+        "browser" => reads::<BrowserAction>(action, |a| match a {
+            // Without cookies or storage_data to set, they are read
+            BrowserAction::Cookies => params["cookies"].is_null(),
+            BrowserAction::Storage => params["storage_data"].is_null(),
+            a => matches!(a,
+                BrowserAction::Navigate | BrowserAction::Reload | BrowserAction::GoBack | BrowserAction::GoForward
+                | BrowserAction::Content | BrowserAction::Url | BrowserAction::Title | BrowserAction::Frames
+                | BrowserAction::Extract | BrowserAction::Dialogs | BrowserAction::Contexts
+                | BrowserAction::Locator | BrowserAction::GetByRole | BrowserAction::GetByText | BrowserAction::GetByLabel
+                | BrowserAction::GetByPlaceholder | BrowserAction::GetByTestId | BrowserAction::GetByAltText
+                | BrowserAction::GetByTitle | BrowserAction::GetText | BrowserAction::GetInnerText
+                | BrowserAction::GetAttribute | BrowserAction::GetValue | BrowserAction::GetHtml
+                | BrowserAction::GetBoundingBox | BrowserAction::IsVisible | BrowserAction::IsEnabled
+                | BrowserAction::IsChecked | BrowserAction::IsHidden | BrowserAction::IsEditable
+                | BrowserAction::ExpectVisible | BrowserAction::ExpectHidden | BrowserAction::ExpectEnabled
+                | BrowserAction::ExpectText | BrowserAction::ExpectValue | BrowserAction::ExpectChecked
+                | BrowserAction::ExpectUrl | BrowserAction::ExpectTitle | BrowserAction::ExpectCount
+                | BrowserAction::ExpectAttribute | BrowserAction::Screenshot | BrowserAction::Snapshot
+                | BrowserAction::Wait | BrowserAction::WaitForLoad | BrowserAction::WaitForUrl
+                | BrowserAction::WaitForEvent | BrowserAction::WaitForRequest | BrowserAction::WaitForResponse
+                | BrowserAction::Tabs | BrowserAction::Status | BrowserAction::Console | BrowserAction::Errors
+                | BrowserAction::Help),
+        }),
+        "git" => reads::<VcsAction>(action, |a| matches!(a,
+            VcsAction::Status | VcsAction::Diff | VcsAction::Log | VcsAction::Blame | VcsAction::Show
+            | VcsAction::Reflog | VcsAction::Shortlog | VcsAction::RevParse | VcsAction::Describe | VcsAction::Help)),
+        "memory" => reads::<MemoryAction>(action, |a| match a {
+            MemoryAction::Recall | MemoryAction::List | MemoryAction::Stats | MemoryAction::Namespaces
+            | MemoryAction::History | MemoryAction::Help => true,
+            // Without facts to store, facts lists them; without a path,
+            // export returns the snapshot
+            MemoryAction::Facts => params["facts"].is_null(),
+            MemoryAction::Export => params["path"].is_null(),
+            _ => false,
+        }),
+        "code" => reads::<CodeAction>(action, |a| !matches!(a,
+            CodeAction::Transform | CodeAction::Rename | CodeAction::GrepReplace | CodeAction::Format)),
+        "lsp" => reads::<LspAction>(action, |a| match a {
+            LspAction::Rename => !params["apply"].as_bool().unwrap_or(false),
+            _ => true,
+        }),
+        "fetch" => reads::<NetAction>(action, |a| matches!(a,
+            NetAction::Fetch | NetAction::Head | NetAction::Search | NetAction::Crawl | NetAction::Help)),
+        "docker" => reads::<DockerAction>(action, |a| matches!(a,
+            DockerAction::Ps | DockerAction::Images | DockerAction::Logs | DockerAction::Help)),
+        // Results come back inline unless written to output
+        "image" => reads::<ImageAction>(action, |_| params["output"].is_null()),
+        "k8s" => reads::<K8sAction>(action, |a| match a {
+            K8sAction::Exec => false,
+            K8sAction::Apply => params["dry_run"].as_str().unwrap_or("none").parse::<DryRun>().is_ok_and(|d| d != DryRun::None),
+            _ => true,
+        }),
+        "storage" => reads::<StorageAction>(action, |a| match a {
+            StorageAction::Profiles | StorageAction::Buckets | StorageAction::List | StorageAction::Head
+            | StorageAction::Help => true,
+            // Inline gets leave the disk alone; presigned PUT URLs grant writes
+            StorageAction::Get => params["path"].is_null(),
+            StorageAction::Presign => !params["method"].as_str().is_some_and(|m| m.eq_ignore_ascii_case("put")),
+            StorageAction::Sync => params["dry_run"].as_bool().unwrap_or(false),
+            _ => false,
+        }),
+        "calendar" => reads::<CalendarAction>(action, |a| {
+            matches!(a, CalendarAction::Events | CalendarAction::Reminders | CalendarAction::Calendars | CalendarAction::Help)
+        }),
+        "notify" => reads::<NotifyAction>(action, |a| matches!(a, NotifyAction::Channels | NotifyAction::Help)),
+        // Bookkeeping stays writable, but exports may only come back inline
+        "plan" => reads::<PlanAction>(action, |a| a != PlanAction::Export || params["path"].is_null()),
+        "think" => reads::<LlmAction>(action, |a| a != LlmAction::Export || params["output"].is_null()),
+        "scratch" => reads::<ScratchAction>(action, |a| a != ScratchAction::Get || params["to_path"].is_null()),
+        "vector" => reads::<VectorAction>(action, |a| matches!(a,
+            VectorAction::Query | VectorAction::Get | VectorAction::Collections | VectorAction::Info | VectorAction::Help)),
+        "search" => reads::<SearchAction>(action, |a| a != SearchAction::Rewrite || params["dry_run"].as_bool().unwrap_or(false)),
+        "repl" => reads::<ReplAction>(action, |a| matches!(a, ReplAction::Sessions | ReplAction::Help)),
+        "task" => reads::<TaskAction>(action, |a| matches!(a, TaskAction::List | TaskAction::Help)),
+        "test" => reads::<TestAction>(action, |a| matches!(a, TestAction::Frameworks | TestAction::Help)),
+        // Linters such as cargo check and tsc run build scripts and plugins
+        "diagnostics" => reads::<DiagnosticsAction>(action, |a| matches!(a, DiagnosticsAction::Linters | DiagnosticsAction::Help)),
+        _ => true,
+    }
+}
+
+fn reads<A: FromStr>(action: &str, read: impl Fn(A) -> bool) -> bool {
+    action.parse::<A>().map_or(true, read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_permits() {
+        assert!(permits("fs", &json!({ "action": "read", "path": "a" })));
+        assert!(permits("fs", &json!({ "action": "grep" })));
+        assert!(!permits("fs", &json!({ "action": "write" })));
+        assert!(!permits("fs", &json!({ "action": "apply_patch" })));
+        assert!(!permits("exec", &json!({ "action": "exec", "command": "ls" })));
+        assert!(permits("exec", &json!({ "action": "ps" })));
+        assert!(!permits("exec", &json!({ "action": "input", "proc_id": "p1", "stdin": "y\n" })));
+        assert!(permits("exec", &json!({ "action": "status" })));
+        assert!(!permits("exec", &json!({ "action": "restart", "name": "web" })));
+        assert!(permits("exec", &json!({ "action": "ports" })));
+        assert!(!permits("exec", &json!({ "action": "kill_port", "port": 3000 })));
+        assert!(!permits("browser", &json!({ "action": "click" })));
+        assert!(permits("browser", &json!({ "action": "get_text" })));
+        assert!(permits("browser", &json!({ "action": "markdown" })));
+        assert!(permits("browser", &json!({ "action": "dialogs" })));
+        assert!(!permits("browser", &json!({ "action": "dialog", "accept": true })));
+        assert!(permits("browser", &json!({ "action": "cookies" })));
+        assert!(!permits("browser", &json!({ "action": "cookies", "cookies": [{ "name": "sid", "value": "x" }] })));
+        assert!(!permits("browser", &json!({ "action": "storage", "storage_data": { "token": "x" } })));
+        assert!(!permits("git", &json!({ "action": "commit" })));
+        assert!(permits("memory", &json!({ "action": "recall", "query": "x" })));
+        assert!(!permits("memory", &json!({ "action": "facts", "facts": ["a"] })));
+        assert!(!permits("lsp", &json!({ "action": "rename", "apply": true })));
+        assert!(permits("lsp", &json!({ "action": "rename" })));
+        assert!(permits("search", &json!({ "query": "x" })));
+        assert!(!permits("k8s", &json!({ "action": "apply", "manifest": "x" })));
+        assert!(permits("k8s", &json!({ "action": "apply", "manifest": "x", "dry_run": "server" })));
+        assert!(!permits("k8s", &json!({ "action": "exec", "name": "p", "command": ["ls"] })));
+        assert!(permits("docker", &json!({ "action": "ps" })));
+        assert!(!permits("docker", &json!({ "action": "up" })));
+        assert!(permits("storage", &json!({ "action": "get", "key": "a" })));
+        assert!(!permits("storage", &json!({ "action": "get", "key": "a", "path": "a" })));
+        assert!(!permits("storage", &json!({ "action": "presign", "key": "a", "method": "put" })));
+        assert!(permits("storage", &json!({ "action": "sync", "path": ".", "dry_run": true })));
+        assert!(permits("search", &json!({ "action": "rewrite", "pattern": "f($A)", "rewrite": "g($A)", "dry_run": true })));
+        assert!(!permits("search", &json!({ "action": "codemod", "pattern": "f($A)", "rewrite": "g($A)" })));
+        assert!(!permits("notify", &json!({ "message": "done" })));
+        assert!(permits("notify", &json!({ "action": "channels" })));
+        assert!(permits("calendar", &json!({})));
+        assert!(permits("image", &json!({ "action": "resize", "path": "a.png", "scale": 0.5 })));
+        assert!(!permits("image", &json!({ "action": "crop", "path": "a.png", "output": "b.png" })));
+        assert!(!permits("calendar", &json!({ "action": "remind", "title": "x" })));
+        assert!(permits("plan", &json!({ "action": "export" })));
+        assert!(permits("vector", &json!({ "collection": "c", "text": "x" })));
+        assert!(!permits("vector", &json!({ "action": "upsert", "collection": "c", "content": "x" })));
+        assert!(!permits("plan", &json!({ "action": "report", "path": "r.md" })));
+        assert!(!permits("think", &json!({ "action": "export", "format": "markdown", "output": "r.md" })));
+        assert!(permits("think", &json!({ "action": "think", "thought": "x", "output": "r.md" })));
+        assert!(permits("scratch", &json!({ "action": "get", "handle": "h1" })));
+        assert!(!permits("scratch", &json!({ "action": "get", "handle": "h1", "to_path": "diff.txt" })));
+        assert!(!permits("diagnostics", &json!({ "path": "." })));
+        assert!(!permits("diagnostics", &json!({ "action": "lint" })));
+        assert!(permits("diagnostics", &json!({ "action": "linters" })));
+        assert!(permits("fs", &json!({ "action": "bogus" })));
+    }
+}
@@ -0,0 +1,43 @@
+//! Server-to-client sampling requests
+//!
+//! Tools ask the client of the session calling them to run a prompt through
+//! its LLM with `sampling/createMessage`. The server attaches the channel:
+//! it queues each request for the session's `notifications/poll` and hands
+//! back the result the client POSTs. Without a server, or for a client that
+//! did not declare the sampling capability, requests fail as unsupported.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+
+/// Sends `sampling/createMessage` params to a session's client and resolves
+/// to the result it answers with
+pub type Client = Arc<dyn Fn(String, Value) -> BoxFuture<'static, Result<Value>> + Send + Sync>;
+
+static CLIENT: Lazy<RwLock<Option<Client>>> = Lazy::new(|| RwLock::new(None));
+
+/// Send sampling requests through `client`
+pub fn attach_client(client: Client) {
+    if let Ok(mut current) = CLIENT.write() {
+        *current = Some(client);
+    }
+}
+
+/// Ask `session`'s client to sample a message with `params`
+pub async fn create_message(session: Option<&str>, params: Value) -> Result<Value> {
+    let session = session.ok_or_else(|| ToolError::unsupported("Sampling needs an MCP session"))?;
+    let client = CLIENT.read().ok().and_then(|client| client.clone())
+        .ok_or_else(|| ToolError::unsupported("No MCP client to sample with"))?;
+    client(session.to_string(), params).await
+}
+
+/// Text of a sampling result's content, or None for image and audio content
+pub fn text(result: &Value) -> Option<&str> {
+    match result["content"]["type"].as_str() {
+        Some("text") => result["content"]["text"].as_str(),
+        _ => None,
+    }
+}
@@ -0,0 +1,547 @@
+/// Scheduled and recurring tool calls
+///
+/// The `schedule` tool registers a call to run once after a delay or at a
+/// time, every fixed interval, or on a cron expression. Jobs are kept in
+/// `jobs.json` under the schedule directory so they survive restarts; a
+/// recurring job whose time passed while the server was down runs once on
+/// start and then keeps its rhythm. Each run is appended to the job's own
+/// JSONL log, read back with `logs` like a background process's output.
+///
+/// A job added in a session runs in that session, under its roots and
+/// policies, and is dropped when the session ends; jobs left over from a
+/// session of an earlier server run are refused rather than run unscoped.
+///
+/// Cron expressions have the usual five fields (minute, hour, day of month,
+/// month, day of week) with `*`, lists, ranges and `/step`, evaluated in
+/// local time, plus `@hourly`, `@daily`, `@weekly` and `@monthly`.
+
+use crate::audit;
+use crate::config::ScheduleConfig;
+use crate::error::ToolError;
+use crate::redaction::Redactor;
+use crate::ToolResult;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Shortest interval a recurring job may have
+const MIN_INTERVAL_SECS: i64 = 10;
+
+/// Runs `logs` returns unless told otherwise
+const DEFAULT_TAIL: usize = 20;
+
+/// Minutes searched for the next time a cron expression matches
+const CRON_HORIZON_MINUTES: i64 = 366 * 24 * 60;
+
+pub fn default_schedule_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hanzo-mcp")
+        .join("schedule")
+}
+
+/// When a job runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Timing {
+    Once { at: String },
+    Every { secs: i64 },
+    Cron { expr: String },
+}
+
+impl Timing {
+    /// The timing `params` describe with `delay`, `at`, `every` or `cron`
+    pub fn from_params(params: &Value) -> Result<Self> {
+        let given: Vec<&str> = ["delay", "at", "every", "cron"].into_iter().filter(|k| !params[*k].is_null()).collect();
+        if given.len() != 1 {
+            return Err(ToolError::invalid("Give exactly one of delay, at, every or cron").into());
+        }
+        let text = |key: &str| params[key].as_str().ok_or_else(|| ToolError::invalid(format!("{} must be a string", key)));
+        match given[0] {
+            "delay" => Ok(Self::Once { at: (Utc::now() + Duration::seconds(parse_interval(text("delay")?)?)).to_rfc3339() }),
+            "at" => {
+                let at = DateTime::parse_from_rfc3339(text("at")?)
+                    .map_err(|e| ToolError::invalid(format!("at must be RFC 3339: {}", e)))?;
+                Ok(Self::Once { at: at.with_timezone(&Utc).to_rfc3339() })
+            }
+            "every" => {
+                let secs = parse_interval(text("every")?)?;
+                if secs < MIN_INTERVAL_SECS {
+                    return Err(ToolError::invalid(format!("every must be at least {}s", MIN_INTERVAL_SECS)).into());
+                }
+                Ok(Self::Every { secs })
+            }
+            _ => {
+                let expr = text("cron")?.to_string();
+                Cron::parse(&expr)?;
+                Ok(Self::Cron { expr })
+            }
+        }
+    }
+
+    /// First run strictly after `after`, if any
+    fn next(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Once { at } => DateTime::parse_from_rfc3339(at).ok()
+                .map(|at| at.with_timezone(&Utc))
+                .filter(|at| *at > after),
+            Self::Every { secs } => Some(after + Duration::seconds(*secs)),
+            Self::Cron { expr } => Cron::parse(expr).ok()?.next(after),
+        }
+    }
+}
+
+/// `30s`, `5m`, `1h30m`, `2d` or plain seconds, in seconds
+pub fn parse_interval(text: &str) -> Result<i64> {
+    let invalid = || ToolError::invalid(format!("Bad interval {:?} (use e.g. 30s, 5m, 1h30m, 2d)", text));
+    let text = text.trim();
+    if let Ok(secs) = text.parse::<i64>() {
+        return if secs > 0 { Ok(secs) } else { Err(invalid().into()) };
+    }
+    let mut total = 0i64;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            'w' => 7 * 86400,
+            _ => return Err(invalid().into()),
+        };
+        let n: i64 = digits.parse().map_err(|_| invalid())?;
+        total += n * unit;
+        digits.clear();
+    }
+    if !digits.is_empty() || total <= 0 {
+        return Err(invalid().into());
+    }
+    Ok(total)
+}
+
+/// A parsed five-field cron expression; each field is a bitset of the
+/// values it allows
+#[derive(Debug, PartialEq)]
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month and day of week were both restricted, so either may
+    /// match
+    either_day: bool,
+}
+
+impl Cron {
+    fn parse(expr: &str) -> Result<Self> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(ToolError::invalid(format!("Cron expression needs 5 fields, got {:?}", expr)).into());
+        }
+        let mut weekdays = field(fields[4], 0, 7)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: field(fields[0], 0, 59)?,
+            hours: field(fields[1], 0, 23)?,
+            days: field(fields[2], 1, 31)?,
+            months: field(fields[3], 1, 12)?,
+            weekdays,
+            either_day: fields[2] != "*" && fields[4] != "*",
+        })
+    }
+
+    fn matches(&self, time: &DateTime<Local>) -> bool {
+        let has = |set: u64, value: u32| set & (1 << value) != 0;
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_ok = if self.either_day { day || weekday } else { day && weekday };
+        has(self.minutes, time.minute()) && has(self.hours, time.hour()) && has(self.months, time.month()) && day_ok
+    }
+
+    fn next(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.with_timezone(&Local);
+        let start = Local.with_ymd_and_hms(start.year(), start.month(), start.day(), start.hour(), start.minute(), 0)
+            .earliest()?;
+        (1..=CRON_HORIZON_MINUTES)
+            .map(|n| start + Duration::minutes(n))
+            .find(|time| self.matches(time))
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+/// Bitset of the values one cron field allows
+fn field(text: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || ToolError::invalid(format!("Bad cron field {:?} (values {}-{})", text, min, max));
+    let mut set = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|s| *s > 0).ok_or_else(invalid)?),
+            None => (part, 1),
+        };
+        let (low, high) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((low, high)) => (low.parse().map_err(|_| invalid())?, high.parse().map_err(|_| invalid())?),
+                None => {
+                    let value = range.parse().map_err(|_| invalid())?;
+                    (value, if part.contains('/') { max } else { value })
+                }
+            },
+        };
+        if low < min || high > max || low > high {
+            return Err(invalid().into());
+        }
+        for value in (low..=high).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+/// A scheduled call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub tool: String,
+    pub params: Value,
+    pub timing: Timing,
+    /// Session that scheduled the job and whose context it runs in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    pub created_at: String,
+    /// None once a one-shot job has run or a job reached `max_runs`
+    pub next_run: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_runs: Option<u64>,
+    #[serde(default)]
+    pub runs: u64,
+    #[serde(default)]
+    pub failures: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_run: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<bool>,
+}
+
+impl Job {
+    fn next_run(&self) -> Option<DateTime<Utc>> {
+        self.next_run.as_deref()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Utc))
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Jobs {
+    counter: u64,
+    jobs: Vec<Job>,
+}
+
+pub struct Scheduler {
+    dir: PathBuf,
+    jobs: Mutex<Jobs>,
+    /// Jobs whose current run has not finished
+    running: Mutex<HashSet<String>>,
+    /// Sessions that added jobs since the scheduler opened and have not ended
+    sessions: Mutex<HashSet<String>>,
+    redactor: Option<Arc<Redactor>>,
+}
+
+impl Scheduler {
+    /// Scheduler keeping its jobs and logs in `config.dir`, loading the jobs
+    /// saved there
+    pub fn open(config: &ScheduleConfig) -> Result<Self> {
+        let dir = config.dir.clone().unwrap_or_else(default_schedule_dir);
+        let path = dir.join("jobs.json");
+        let jobs = if path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&path)?)
+                .map_err(|e| ToolError::invalid(format!("Cannot read {}: {}", path.display(), e)))?
+        } else {
+            Jobs::default()
+        };
+        Ok(Self {
+            dir,
+            jobs: Mutex::new(jobs),
+            running: Mutex::new(HashSet::new()),
+            sessions: Mutex::new(HashSet::new()),
+            redactor: None,
+        })
+    }
+
+    /// Mask secrets in logged results with `redactor`
+    pub fn redacting(mut self, redactor: Option<Arc<Redactor>>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+
+    fn save(&self, jobs: &Jobs) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join("jobs.json");
+        let temp = self.dir.join("jobs.json.tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(jobs)?)?;
+        std::fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    fn log_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", id))
+    }
+
+    /// Register a call to `tool` with `params.params` on the timing
+    /// `params` give
+    pub fn add(&self, params: &Value, session: Option<&str>) -> Result<Job> {
+        let tool = params["tool"].as_str().filter(|t| !t.is_empty()).ok_or_else(|| ToolError::invalid("tool required"))?;
+        if tool == "schedule" {
+            return Err(ToolError::invalid("A scheduled call cannot schedule further calls").into());
+        }
+        let timing = Timing::from_params(params)?;
+        let now = Utc::now();
+        let next_run = timing.next(now)
+            .ok_or_else(|| ToolError::invalid("That time has already passed"))?;
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.counter += 1;
+        let job = Job {
+            id: format!("job_{}", jobs.counter),
+            name: params["name"].as_str().map(str::to_string),
+            tool: tool.to_string(),
+            params: params.get("params").cloned().filter(|p| !p.is_null()).unwrap_or_else(|| json!({})),
+            timing,
+            session: session.map(str::to_string),
+            created_at: now.to_rfc3339(),
+            next_run: Some(next_run.to_rfc3339()),
+            max_runs: params["max_runs"].as_u64(),
+            runs: 0,
+            failures: 0,
+            last_run: None,
+            last_success: None,
+        };
+        jobs.jobs.push(job.clone());
+        self.save(&jobs)?;
+        if let Some(session) = session {
+            self.sessions.lock().unwrap().insert(session.to_string());
+        }
+        Ok(job)
+    }
+
+    /// Drop the jobs `session` added; their logs stay readable
+    pub fn end_session(&self, session: &str) {
+        self.sessions.lock().unwrap().remove(session);
+        let mut jobs = self.jobs.lock().unwrap();
+        let before = jobs.jobs.len();
+        jobs.jobs.retain(|j| j.session.as_deref() != Some(session));
+        if jobs.jobs.len() != before {
+            if let Err(e) = self.save(&jobs) {
+                log::warn!("Cannot save scheduled jobs: {}", e);
+            }
+        }
+    }
+
+    /// Whether `job` was added in a session that is no longer live
+    pub fn orphaned(&self, job: &Job) -> bool {
+        job.session.as_ref().is_some_and(|session| !self.sessions.lock().unwrap().contains(session))
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().jobs.clone()
+    }
+
+    pub fn get(&self, id: &str) -> Result<Job> {
+        self.jobs.lock().unwrap().jobs.iter()
+            .find(|j| j.id == id || j.name.as_deref() == Some(id))
+            .cloned()
+            .ok_or_else(|| ToolError::not_found(format!("Scheduled job not found: {}", id)).into())
+    }
+
+    /// Drop a job; its log stays readable
+    pub fn cancel(&self, id: &str) -> Result<Job> {
+        let job = self.get(id)?;
+        let mut jobs = self.jobs.lock().unwrap();
+        jobs.jobs.retain(|j| j.id != job.id);
+        self.save(&jobs)?;
+        Ok(job)
+    }
+
+    /// Jobs due at `now` that are not already running, marked running
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        let mut running = self.running.lock().unwrap();
+        jobs.jobs.iter()
+            .filter(|job| job.next_run().is_some_and(|at| at <= now))
+            .filter(|job| running.insert(job.id.clone()))
+            .cloned()
+            .collect()
+    }
+
+    /// Log a run of `job` and schedule its next one
+    pub fn finish(&self, job: &Job, result: &ToolResult, started: DateTime<Utc>) {
+        self.running.lock().unwrap().remove(&job.id);
+        let finished = Utc::now();
+        let mut entry = json!({
+            "ts": started.to_rfc3339(),
+            "success": result.success,
+            "duration_ms": (finished - started).num_milliseconds().max(0)
+        });
+        if result.success {
+            entry["result"] = audit::redact_params(&result.content);
+        } else {
+            entry["error"] = json!(result.error);
+            entry["details"] = audit::redact_params(&result.content);
+        }
+        if let Some(redactor) = &self.redactor {
+            redactor.redact_value(&mut entry);
+        }
+        if let Err(e) = self.append(&job.id, &entry) {
+            log::warn!("Cannot log run of {}: {}", job.id, e);
+        }
+
+        let mut jobs = self.jobs.lock().unwrap();
+        let Some(stored) = jobs.jobs.iter_mut().find(|j| j.id == job.id) else { return };
+        stored.runs += 1;
+        if !result.success {
+            stored.failures += 1;
+        }
+        stored.last_run = Some(started.to_rfc3339());
+        stored.last_success = Some(result.success);
+        let spent = matches!(stored.timing, Timing::Once { .. }) || stored.max_runs.is_some_and(|max| stored.runs >= max);
+        stored.next_run = if spent { None } else { stored.timing.next(finished).map(|at| at.to_rfc3339()) };
+        if let Err(e) = self.save(&jobs) {
+            log::warn!("Cannot save scheduled jobs: {}", e);
+        }
+    }
+
+    fn append(&self, id: &str, entry: &Value) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(self.log_path(id))?;
+        writeln!(file, "{}", entry)?;
+        Ok(())
+    }
+
+    /// The last `tail` runs of a job, oldest first
+    pub fn logs(&self, id: &str, tail: Option<usize>) -> Result<Value> {
+        let job = self.get(id).ok();
+        let job_id = job.as_ref().map_or(id, |j| j.id.as_str());
+        let path = self.log_path(job_id);
+        if job.is_none() && !path.exists() {
+            return Err(ToolError::not_found(format!("Scheduled job not found: {}", id)).into());
+        }
+        let runs: Vec<Value> = match std::fs::File::open(&path) {
+            Ok(file) => std::io::BufReader::new(file).lines()
+                .map_while(Result::ok)
+                .filter_map(|line| serde_json::from_str(&line).ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        let total = runs.len();
+        let tail = tail.unwrap_or(DEFAULT_TAIL);
+        let runs: Vec<Value> = runs.into_iter().skip(total.saturating_sub(tail)).collect();
+        Ok(json!({
+            "id": job_id,
+            "job": job,
+            "runs": runs,
+            "count": runs.len(),
+            "total_runs": total,
+            "log_file": path
+        }))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing() {
+        assert_eq!(parse_interval("90").unwrap(), 90);
+        assert_eq!(parse_interval("1h30m").unwrap(), 5400);
+        assert_eq!(parse_interval("2d").unwrap(), 172800);
+        assert!(parse_interval("5x").is_err());
+        assert!(parse_interval("m").is_err());
+
+        let hourly = Cron::parse("@hourly").unwrap();
+        assert_eq!(hourly, Cron::parse("0 * * * *").unwrap());
+        let after = Local.with_ymd_and_hms(2024, 3, 5, 10, 15, 30).unwrap().with_timezone(&Utc);
+        let next = hourly.next(after).unwrap().with_timezone(&Local);
+        assert_eq!((next.hour(), next.minute()), (11, 0));
+
+        let weekdays = Cron::parse("*/15 9-17 * * 1-5").unwrap();
+        let next = weekdays.next(after).unwrap().with_timezone(&Local);
+        assert_eq!((next.hour(), next.minute()), (10, 30));
+        // 2024-03-09 is a Saturday; the next run is Monday morning
+        let weekend = Local.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap().with_timezone(&Utc);
+        let next = weekdays.next(weekend).unwrap().with_timezone(&Local);
+        assert_eq!((next.day(), next.hour(), next.minute()), (11, 9, 0));
+
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("* * *").is_err());
+        assert!(Timing::from_params(&json!({ "every": "5m", "cron": "@daily" })).is_err());
+        assert!(Timing::from_params(&json!({ "every": "1s" })).is_err());
+        assert!(Timing::from_params(&json!({ "at": "2000-01-01T00:00:00Z" })).is_ok());
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ScheduleConfig { enabled: true, dir: Some(dir.path().to_path_buf()) };
+        let scheduler = Scheduler::open(&config).unwrap();
+        let job = scheduler.add(&json!({ "tool": "fs", "params": { "action": "info", "path": "." }, "every": "1h", "name": "poll" }), Some("s1")).unwrap();
+        assert!(!scheduler.orphaned(&job));
+        assert!(scheduler.add(&json!({ "tool": "fs", "at": "2000-01-01T00:00:00Z" }), None).is_err());
+        assert!(scheduler.add(&json!({ "tool": "schedule", "every": "1h" }), None).is_err());
+
+        assert!(scheduler.due(Utc::now()).is_empty());
+        let later = Utc::now() + Duration::hours(2);
+        assert_eq!(scheduler.due(later).len(), 1);
+        assert!(scheduler.due(later).is_empty(), "a running job is not started twice");
+        scheduler.finish(&job, &ToolResult::ok(json!({ "password": "pw", "size": 1 })), Utc::now());
+        scheduler.finish(&job, &ToolResult::err("boom"), Utc::now());
+
+        // Jobs and their logs outlive the scheduler
+        let reopened = Scheduler::open(&config).unwrap();
+        let stored = reopened.get("poll").unwrap();
+        assert_eq!((stored.runs, stored.failures), (2, 1));
+        assert!(reopened.orphaned(&stored), "the session that added it belongs to the earlier run");
+        assert!(stored.next_run().unwrap() > Utc::now());
+        let logs = reopened.logs("poll", Some(1)).unwrap();
+        assert_eq!(logs["total_runs"], 2);
+        assert_eq!(logs["runs"][0]["error"], "boom");
+        assert_eq!(reopened.logs(&job.id, None).unwrap()["runs"][0]["result"]["password"], crate::redaction::REDACTED);
+
+        reopened.cancel(&job.id).unwrap();
+        assert!(reopened.list().is_empty());
+        assert_eq!(reopened.logs(&job.id, None).unwrap()["total_runs"], 2);
+        let once = reopened.add(&json!({ "tool": "fs", "delay": "10s", "max_runs": 3 }), None).unwrap();
+        reopened.finish(&once, &ToolResult::ok(json!({})), Utc::now());
+        assert_eq!(reopened.get(&once.id).unwrap().next_run, None);
+        assert!(!reopened.orphaned(&once));
+
+        let owned = reopened.add(&json!({ "tool": "fs", "every": "1h" }), Some("s2")).unwrap();
+        reopened.end_session("s2");
+        assert!(reopened.get(&owned.id).is_err());
+        assert!(reopened.get(&once.id).is_ok());
+    }
+}
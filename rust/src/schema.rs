@@ -0,0 +1,223 @@
+/// JSON Schema validation of tool arguments
+///
+/// Covers the subset tool schemas use: `type` (single or list), `enum`,
+/// `required`, `properties`, `additionalProperties`, `items`, `oneOf` and
+/// `anyOf`. Errors name the offending field and what was expected, so a
+/// client can fix its call without reading serde messages.
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// One validation failure
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchemaError {
+    /// Dotted path of the field, e.g. `steps[0].id`; empty for the root
+    pub path: String,
+    /// Schema keyword that failed
+    pub keyword: &'static str,
+    pub message: String,
+}
+
+/// Validate `value` against `schema`, returning every failure found
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaError> {
+    validate_with(schema, value, &|_, _| false)
+}
+
+/// Like `validate`, but an enum mismatch is waived when `accepts(field, value)`
+/// holds; tools use this for aliases their parsers take but schemas don't list
+pub fn validate_with(schema: &Value, value: &Value, accepts: &dyn Fn(&str, &Value) -> bool) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    check(schema, value, "", accepts, &mut errors);
+    errors
+}
+
+fn field(path: &str) -> String {
+    if path.is_empty() { "arguments".to_string() } else { format!("field '{}'", path) }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() { key.to_string() } else { format!("{}.{}", path, key) }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn is_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "integer" => value.as_f64().is_some_and(|n| n.fract() == 0.0),
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+/// Short description of what a schema accepts, for oneOf/anyOf errors
+fn describe(schema: &Value) -> String {
+    match &schema["type"] {
+        Value::String(ty) => ty.clone(),
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(" or "),
+        _ => "schema".to_string(),
+    }
+}
+
+fn check(schema: &Value, value: &Value, path: &str, accepts: &dyn Fn(&str, &Value) -> bool, errors: &mut Vec<SchemaError>) {
+    let mut fail = |keyword, message| errors.push(SchemaError { path: path.to_string(), keyword, message });
+
+    for keyword in ["oneOf", "anyOf"] {
+        if let Some(options) = schema[keyword].as_array() {
+            if !options.iter().any(|option| validate_with(option, value, accepts).is_empty()) {
+                let expected: Vec<String> = options.iter().map(describe).collect();
+                fail(keyword, format!("{} must be {} (got {})", field(path), expected.join(" or "), type_name(value)));
+                return;
+            }
+        }
+    }
+
+    let types: Vec<&str> = match &schema["type"] {
+        Value::String(ty) => vec![ty.as_str()],
+        Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !types.is_empty() && !types.iter().any(|ty| is_type(value, ty)) {
+        fail("type", format!("{} must be {}, got {}", field(path), types.join(" or "), type_name(value)));
+        return;
+    }
+
+    if let Some(allowed) = schema["enum"].as_array() {
+        let key = path.rsplit('.').next().unwrap_or(path);
+        if !allowed.contains(value) && !accepts(key, value) {
+            let names: Vec<String> = allowed.iter()
+                .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+                .collect();
+            let mut message = format!("{} must be one of: {} (got {})", field(path), names.join(", "), value);
+            if let Some(close) = value.as_str().and_then(|v| closest(v, names.iter().map(String::as_str))) {
+                message.push_str(&format!("; did you mean '{}'?", close));
+            }
+            fail("enum", message);
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema["properties"].as_object();
+        if let Some(required) = schema["required"].as_array() {
+            for name in required.iter().filter_map(Value::as_str) {
+                if object.get(name).is_none_or(Value::is_null) {
+                    let hint = match properties.and_then(|p| p.get(name)).and_then(|p| p["enum"].as_array()) {
+                        Some(allowed) => format!(" (one of: {})", allowed.iter().filter_map(Value::as_str).collect::<Vec<_>>().join(", ")),
+                        None => String::new(),
+                    };
+                    errors.push(SchemaError {
+                        path: join(path, name),
+                        keyword: "required",
+                        message: format!("missing required field '{}'{}", join(path, name), hint),
+                    });
+                }
+            }
+        }
+        for (key, item) in object {
+            let item_path = join(path, key);
+            match properties.and_then(|p| p.get(key)) {
+                // Null means "not given" for the Option fields tools use
+                Some(_) if item.is_null() => {}
+                Some(property) => check(property, item, &item_path, accepts, errors),
+                None => match &schema["additionalProperties"] {
+                    Value::Bool(false) => {
+                        let mut message = format!("unknown field '{}'", item_path);
+                        if let Some(close) = properties.and_then(|p| closest(key, p.keys().map(String::as_str))) {
+                            message.push_str(&format!("; did you mean '{}'?", close));
+                        }
+                        errors.push(SchemaError { path: item_path, keyword: "additionalProperties", message });
+                    }
+                    extra @ Value::Object(_) => check(extra, item, &item_path, accepts, errors),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check(item_schema, item, &format!("{}[{}]", path, i), accepts, errors);
+        }
+    }
+}
+
+/// Candidate within edit distance 2 of `word`, for typo hints
+fn closest<'a>(word: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|c| (edit_distance(&word.to_lowercase(), &c.to_lowercase()), c))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb { prev } else { 1 + prev.min(row[j]).min(row[j + 1]) };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["action"],
+            "properties": {
+                "action": {"type": "string", "enum": ["read", "write"]},
+                "limit": {"type": "integer"},
+                "command": {"oneOf": [{"type": "string"}, {"type": "array", "items": {"type": "string"}}]},
+                "steps": {"type": "array", "items": {"type": "object", "properties": {"id": {"type": "integer"}}}}
+            },
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn test_valid_arguments() {
+        assert!(validate(&schema(), &json!({"action": "read", "limit": 5, "command": ["ls", "-l"]})).is_empty());
+        assert!(validate(&schema(), &json!({"action": "write", "limit": null})).is_empty());
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let errors = validate(&schema(), &json!({"limit": "ten"}));
+        assert_eq!(errors[0].message, "missing required field 'action' (one of: read, write)");
+        assert_eq!(errors[1].message, "field 'limit' must be integer, got string");
+
+        let errors = validate(&schema(), &json!({"action": "reed", "limt": 1}));
+        assert!(errors[0].message.ends_with("did you mean 'read'?"));
+        assert_eq!(errors[1].message, "unknown field 'limt'; did you mean 'limit'?");
+
+        let errors = validate(&schema(), &json!({"action": "read", "command": 3, "steps": [{"id": "a"}]}));
+        assert_eq!(errors[0].message, "field 'command' must be string or array (got integer)");
+        assert_eq!(errors[1].path, "steps[0].id");
+    }
+
+    #[test]
+    fn test_accepted_alias() {
+        let accepts = |key: &str, value: &Value| key == "action" && value == "cat";
+        assert!(validate_with(&schema(), &json!({"action": "cat"}), &accepts).is_empty());
+        assert_eq!(validate_with(&schema(), &json!({"action": "dog"}), &accepts).len(), 1);
+    }
+}
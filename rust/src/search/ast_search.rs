@@ -1,13 +1,21 @@
 /// AST search using tree-sitter for semantic code understanding
+///
+/// Patterns are either names (`function foo`, `class Bar`, an identifier
+/// regex) or structural code patterns with metavariables such as
+/// `if $COND { return $X }`; see `structural`.
 
+use super::structural::{self, StructuralMatch, StructuralPattern};
+use super::text::{self, TextOptions};
 use super::{SearchResult, MatchType};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::path::Path;
 use tree_sitter::{Language, Parser, Query, QueryCursor, Node};
 use walkdir::WalkDir;
 use std::fs;
 
 /// Get language from tree-sitter crate
-fn get_language(lang: &str) -> Option<Language> {
+pub(crate) fn get_language(lang: &str) -> Option<Language> {
     match lang {
         "rust" => Some(tree_sitter_rust::language()),
         "javascript" => Some(tree_sitter_javascript::language()),
@@ -51,6 +59,10 @@ impl AstSearcher {
         language: Option<&str>,
         max_results: usize,
     ) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+        if structural::is_structural(pattern) {
+            let found = self.search_structural(pattern, path, language, &TextOptions::default(), max_results)?;
+            return Ok(found.into_iter().map(SearchResult::from).collect());
+        }
         let mut results = Vec::new();
 
         // Walk directory tree
@@ -98,6 +110,61 @@ impl AstSearcher {
         Ok(results)
     }
 
+    /// Search for a structural pattern in the files under `path` that
+    /// `files` selects (only its walk settings apply). Without `language`
+    /// the pattern is compiled for each language the walk meets, and files
+    /// of a language it does not parse as are skipped
+    pub fn search_structural(
+        &self,
+        pattern: &str,
+        path: &Path,
+        language: Option<&str>,
+        files: &TextOptions,
+        max_results: usize,
+    ) -> anyhow::Result<Vec<StructuralMatch>> {
+        let mut compiled: HashMap<&'static str, Option<StructuralPattern>> = HashMap::new();
+        let mut failure = None;
+        if let Some(name) = language {
+            let language = detect_language(Path::new(&format!("x.{}", extension(name))));
+            if language == "text" {
+                return Err(crate::error::ToolError::unsupported(format!("No grammar for {}", name)).into());
+            }
+            compiled.insert(language, Some(StructuralPattern::compile(pattern, language)?));
+        }
+
+        let mut results = Vec::new();
+        for entry in text::walk(path, files)?.flatten() {
+            if results.len() >= max_results {
+                break;
+            }
+            let file_path = entry.path();
+            if !entry.file_type().is_some_and(|t| t.is_file()) || entry.metadata().is_ok_and(|m| m.len() > text::MAX_FILE_BYTES) {
+                continue;
+            }
+            let lang = detect_language(file_path);
+            if lang == "text" || (language.is_some() && !compiled.contains_key(lang)) {
+                continue;
+            }
+            let compiled = compiled.entry(lang).or_insert_with(|| match StructuralPattern::compile(pattern, lang) {
+                Ok(compiled) => Some(compiled),
+                Err(e) => {
+                    failure.get_or_insert(e);
+                    None
+                }
+            });
+            let Some(compiled) = compiled else { continue };
+            if let Ok(source) = fs::read_to_string(file_path) {
+                results.extend(compiled.find(&source, file_path, max_results - results.len()));
+            }
+        }
+
+        // A pattern no language could parse is a mistake, not a miss
+        match failure {
+            Some(e) if results.is_empty() && compiled.values().all(Option::is_none) => Err(e),
+            _ => Ok(results),
+        }
+    }
+
     /// Search within a parsed tree
     fn search_tree(
         &self,
@@ -165,7 +232,7 @@ impl Default for AstSearcher {
 }
 
 /// Detect language from file extension
-fn detect_language(path: &Path) -> &'static str {
+pub(crate) fn detect_language(path: &Path) -> &'static str {
     match path.extension().and_then(|s| s.to_str()) {
         Some("rs") => "rust",
         Some("js") | Some("mjs") => "javascript",
@@ -179,6 +246,21 @@ fn detect_language(path: &Path) -> &'static str {
     }
 }
 
+/// A file extension for a language name or alias, for `detect_language`
+fn extension(language: &str) -> &str {
+    match language {
+        "rust" => "rs",
+        "javascript" | "js" => "js",
+        "typescript" | "ts" | "tsx" => "ts",
+        "python" | "py" => "py",
+        "go" | "golang" => "go",
+        "java" => "java",
+        "cpp" | "c++" | "cc" => "cpp",
+        "c" => "c",
+        other => other,
+    }
+}
+
 /// Build tree-sitter query string from pattern
 fn build_query_string(pattern: &str, language: &str) -> String {
     // Check for common patterns and convert to tree-sitter queries
@@ -318,6 +400,135 @@ fn find_parent_context(mut node: Node) -> Option<Node> {
     None
 }
 
+/// One entry of a file outline
+#[derive(Debug, Clone, Serialize)]
+pub struct OutlineSymbol {
+    pub kind: &'static str,
+    pub name: String,
+    /// Declaration up to its body, whitespace collapsed
+    pub signature: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<OutlineSymbol>,
+}
+
+/// Symbol outline of `source`: functions, types, classes, impl blocks and
+/// modules with their line ranges, members nested under their container.
+/// `None` if `language` has no grammar or the source does not parse.
+pub fn outline(source: &str, language: &str) -> Option<Vec<OutlineSymbol>> {
+    let mut parser = Parser::new();
+    parser.set_language(get_language(language)?).ok()?;
+    let tree = parser.parse(source, None)?;
+    Some(outline_children(tree.root_node(), source, false))
+}
+
+/// Language name for `path`, as accepted by `outline`
+pub fn language_for(path: &Path) -> Option<&'static str> {
+    Some(detect_language(path)).filter(|l| *l != "text")
+}
+
+fn outline_children(node: Node, source: &str, in_type: bool) -> Vec<OutlineSymbol> {
+    let mut symbols = Vec::new();
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        match outline_symbol(child, source, in_type) {
+            Some(symbol) => symbols.push(symbol),
+            // Look through wrappers such as exports, decorators, `type (...)`
+            // groups and declaration lists, but not into function bodies
+            None if !is_function(child.kind()) => symbols.extend(outline_children(child, source, in_type)),
+            None => {}
+        }
+    }
+    symbols
+}
+
+fn is_function(kind: &str) -> bool {
+    matches!(
+        kind,
+        "function_item" | "function_definition" | "function_declaration" | "generator_function_declaration"
+            | "method_definition" | "method_declaration" | "constructor_declaration" | "arrow_function"
+            | "function_expression" | "function" | "block" | "statement_block"
+    )
+}
+
+fn outline_symbol(node: Node, source: &str, in_type: bool) -> Option<OutlineSymbol> {
+    let function = if in_type { "method" } else { "function" };
+    let (kind, container) = match node.kind() {
+        "function_item" | "function_signature_item" | "function_definition" | "function_declaration"
+        | "generator_function_declaration" => (function, false),
+        "method_definition" | "method_declaration" | "method_signature" | "abstract_method_signature" => ("method", false),
+        "constructor_declaration" => ("constructor", false),
+        "struct_item" | "union_item" => ("struct", false),
+        "enum_item" | "enum_declaration" => ("enum", false),
+        "trait_item" => ("trait", true),
+        "impl_item" => ("impl", true),
+        "mod_item" => ("module", true),
+        "type_item" | "type_alias_declaration" => ("type", false),
+        "const_item" | "static_item" => ("const", false),
+        "macro_definition" => ("macro", false),
+        "class_definition" | "class_declaration" | "abstract_class_declaration" | "class_specifier"
+        | "record_declaration" => ("class", true),
+        "interface_declaration" => ("interface", true),
+        "namespace_definition" | "internal_module" | "module" => ("namespace", true),
+        "type_spec" => match node.child_by_field_name("type").map(|t| t.kind()) {
+            Some("struct_type") => ("struct", false),
+            Some("interface_type") => ("interface", false),
+            _ => ("type", false),
+        },
+        "struct_specifier" if node.child_by_field_name("body").is_some() => ("struct", false),
+        "variable_declarator" => match node.child_by_field_name("value").map(|v| v.kind()) {
+            Some("arrow_function" | "function_expression" | "function") => (function, false),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or("").to_string();
+    let name = match node.kind() {
+        "impl_item" => {
+            let ty = node.child_by_field_name("type").map(text).unwrap_or_default();
+            match node.child_by_field_name("trait") {
+                Some(tr) => format!("{} for {}", text(tr), ty),
+                None => ty,
+            }
+        }
+        _ => match node.child_by_field_name("name") {
+            Some(name) => text(name),
+            // C and C++ functions name themselves through nested declarators
+            None => {
+                let mut declarator = node.child_by_field_name("declarator")?;
+                while let Some(inner) = declarator.child_by_field_name("declarator") {
+                    declarator = inner;
+                }
+                text(declarator)
+            }
+        },
+    };
+
+    let body = node.child_by_field_name("body")
+        .or_else(|| node.child_by_field_name("value").and_then(|v| v.child_by_field_name("body")));
+    let head_end = body.map_or(node.end_byte(), |b| b.start_byte());
+    let head = &source[node.start_byte()..head_end.max(node.start_byte())];
+    let mut signature = head.split_whitespace().collect::<Vec<_>>().join(" ");
+    if signature.chars().count() > 200 {
+        signature = signature.chars().take(200).collect::<String>() + "...";
+    }
+
+    let children = match body {
+        Some(body) if container => outline_children(body, source, true),
+        _ => Vec::new(),
+    };
+    Some(OutlineSymbol {
+        kind,
+        name,
+        signature: signature.trim_end_matches(['{', ':', '=', ' ']).trim_end().to_string(),
+        start_line: node.start_position().row + 1,
+        end_line: node.end_position().row + 1,
+        children,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,4 +554,26 @@ mod tests {
         assert_eq!(detect_language(Path::new("test.py")), "python");
         assert_eq!(detect_language(Path::new("test.go")), "go");
     }
+
+    #[test]
+    fn test_outline() {
+        let source = "use std::fmt;\n\npub struct Point {\n    x: i32,\n}\n\nimpl fmt::Display for Point {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {\n        write!(f, \"{}\", self.x)\n    }\n}\n\npub fn origin() -> Point {\n    Point { x: 0 }\n}\n";
+        let symbols = outline(source, "rust").unwrap();
+        let names: Vec<_> = symbols.iter().map(|s| (s.kind, s.name.as_str())).collect();
+        assert_eq!(names, [("struct", "Point"), ("impl", "fmt::Display for Point"), ("function", "origin")]);
+        assert_eq!(symbols[1].children[0].kind, "method");
+        assert_eq!(symbols[1].children[0].signature, "fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result");
+        assert_eq!((symbols[2].start_line, symbols[2].end_line), (13, 15));
+
+        let python = "class Shape:\n    def area(self):\n        return 0\n\n@cache\ndef build():\n    pass\n";
+        let symbols = outline(python, "python").unwrap();
+        assert_eq!(symbols[0].children[0].name, "area");
+        assert_eq!(symbols[1].name, "build");
+
+        let ts = "export class Api {\n  get(id: string): Item {\n    return load(id);\n  }\n}\nexport const handler = async (req) => {\n  return 1;\n};\n";
+        let symbols = outline(ts, "typescript").unwrap();
+        assert_eq!(symbols[0].children[0].name, "get");
+        assert_eq!(symbols[1].kind, "function");
+        assert!(outline("x", "cobol").is_none());
+    }
 }
@@ -0,0 +1,319 @@
+/// Search through git history for code that was added or removed
+///
+/// The pickaxe behind "when did this change": a literal pattern finds the
+/// commits that changed how often the string occurs in a file (`git log
+/// -S`), a regex the commits whose added or removed lines match it (`git
+/// log -G`). Each commit comes with the hunks that touched the pattern, so
+/// the answer is readable without a second `git show`.
+///
+/// History is read in process with libgit2, so the search needs no `git`
+/// binary and the pattern means the same as in the other text searches.
+/// Like `git log`, merge commits are passed over.
+
+use super::text::{self, TextOptions};
+use crate::error::ToolError;
+use crate::tools::fs_glob::PathGlobs;
+use anyhow::Result;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+use git2::{DiffOptions, Oid, Patch, Repository, Sort};
+use regex::Regex;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Lines kept per hunk; the rest of a longer hunk is dropped
+const MAX_HUNK_LINES: usize = 200;
+
+/// Which commits a history search reads
+#[derive(Debug, Clone, Default)]
+pub struct HistoryOptions {
+    /// Where to start walking (default HEAD)
+    pub reference: Option<String>,
+    /// RFC 3339, `2024-01-01` or `2 weeks ago`
+    pub since: Option<String>,
+    pub until: Option<String>,
+    /// Regex matched against `Name <email>`
+    pub author: Option<String>,
+    /// Files or directories the changes must touch, relative to the searched dir
+    pub paths: Vec<String>,
+    /// Globs a changed file must match, relative to the searched dir
+    pub include: Vec<String>,
+    /// Globs of changed files to leave out
+    pub exclude: Vec<String>,
+    /// Lines of context around each change
+    pub context: usize,
+    /// Commits returned at most
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryCommit {
+    pub commit: String,
+    pub author: String,
+    pub email: String,
+    /// Author date, ISO 8601
+    pub date: String,
+    pub subject: String,
+    pub files: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub file: String,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Hunk {
+    /// `@@ -12,4 +12,6 @@ fn name`
+    pub header: String,
+    /// The hunk's lines with their ` `, `+` and `-` markers
+    pub lines: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// The files of the searched dir a search looks at
+struct Scope {
+    /// The searched dir relative to the work tree
+    prefix: PathBuf,
+    paths: Vec<PathBuf>,
+    include: PathGlobs,
+    exclude: PathGlobs,
+}
+
+impl Scope {
+    /// Whether `file`, relative to the work tree, is in scope
+    fn admits(&self, file: &Path) -> bool {
+        let Ok(relative) = file.strip_prefix(&self.prefix) else { return false };
+        (self.paths.is_empty() || self.paths.iter().any(|path| relative.starts_with(path)))
+            && (self.include.is_empty() || self.include.is_match(relative))
+            && !self.exclude.is_match(relative)
+    }
+}
+
+/// Commits under `dir` that added or removed `pattern`, newest first
+pub fn search(dir: &Path, pattern: &str, text: &TextOptions, options: &HistoryOptions) -> Result<Vec<HistoryCommit>> {
+    let regex = text::compile(pattern, text)?;
+    let pickaxe = text.literal && !text.word;
+    let since = options.since.as_deref().map(|date| parse_date(date, false)).transpose()?;
+    let until = options.until.as_deref().map(|date| parse_date(date, true)).transpose()?;
+    let author = options.author.as_deref()
+        .map(|author| Regex::new(author).map_err(|e| ToolError::invalid(format!("Invalid author pattern: {}", e))))
+        .transpose()?;
+
+    let repo = Repository::discover(dir).map_err(|e| ToolError::external(format!("git error: {}", e.message())))?;
+    let workdir = repo.workdir().ok_or_else(|| ToolError::invalid("A bare repository has no files to search"))?;
+    let prefix = dir.canonicalize()?.strip_prefix(workdir.canonicalize()?).map(Path::to_path_buf).unwrap_or_default();
+    let scope = Scope {
+        paths: options.paths.iter().map(|path| PathBuf::from(path.trim_start_matches("./"))).collect(),
+        include: PathGlobs::new(&options.include)?,
+        exclude: PathGlobs::new(&options.exclude)?,
+        prefix,
+    };
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    match &options.reference {
+        Some(reference) => {
+            let commit = repo.revparse_single(reference).and_then(|object| object.peel_to_commit())
+                .map_err(|_| ToolError::invalid(format!("Not a git ref: {}", reference)))?;
+            walk.push(commit.id())?;
+        }
+        None => walk.push_head()?,
+    }
+
+    let mut found = Vec::new();
+    for oid in walk {
+        if found.len() >= options.limit.max(1) {
+            break;
+        }
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let committed = DateTime::from_timestamp(commit.time().seconds(), 0).unwrap_or_default();
+        if since.is_some_and(|since| committed < since) || until.is_some_and(|until| committed > until) {
+            continue;
+        }
+        let signature = commit.author();
+        let name = signature.name().unwrap_or_default().to_string();
+        let email = signature.email().unwrap_or_default().to_string();
+        if author.as_ref().is_some_and(|author| !author.is_match(&format!("{} <{}>", name, email))) {
+            continue;
+        }
+
+        let parent = commit.parents().next().map(|parent| parent.tree()).transpose()?;
+        let mut diff_options = DiffOptions::new();
+        diff_options.context_lines(options.context as u32);
+        let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&commit.tree()?), Some(&mut diff_options))?;
+        let mut files = Vec::new();
+        for index in 0..diff.deltas().len() {
+            // No patch for binary files
+            let Some(mut patch) = Patch::from_diff(&diff, index)? else { continue };
+            let delta = patch.delta();
+            let Some(file) = delta.new_file().path().or(delta.old_file().path()).map(Path::to_path_buf) else { continue };
+            if !scope.admits(&file) {
+                continue;
+            }
+            let changed = if pickaxe {
+                let count = |id: Oid| occurrences(&repo, id, &regex);
+                count(delta.old_file().id()) != count(delta.new_file().id())
+            } else {
+                true
+            };
+            let hunks = hunks(&mut patch)?;
+            let matching: Vec<Hunk> = hunks.iter().filter(|hunk| touches(hunk, &regex)).cloned().collect();
+            // A pickaxe match may only move lines, so no changed line matches
+            if (changed && pickaxe) || !matching.is_empty() {
+                let file = file.to_string_lossy().to_string();
+                files.push(FileChange { file, hunks: if matching.is_empty() { hunks } else { matching } });
+            }
+        }
+        if files.is_empty() {
+            continue;
+        }
+        let offset = FixedOffset::east_opt(signature.when().offset_minutes() * 60).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+        let date = DateTime::from_timestamp(signature.when().seconds(), 0).unwrap_or_default().with_timezone(&offset);
+        found.push(HistoryCommit {
+            commit: commit.id().to_string(),
+            author: name,
+            email,
+            date: date.to_rfc3339(),
+            subject: commit.summary().unwrap_or_default().to_string(),
+            files,
+        });
+    }
+    Ok(found)
+}
+
+/// How often `regex` matches in the blob `id`; none for a missing side
+fn occurrences(repo: &Repository, id: Oid, regex: &Regex) -> usize {
+    if id.is_zero() {
+        return 0;
+    }
+    repo.find_blob(id).map_or(0, |blob| regex.find_iter(&String::from_utf8_lossy(blob.content())).count())
+}
+
+/// Whether an added or removed line of `hunk` matches `regex`
+fn touches(hunk: &Hunk, regex: &Regex) -> bool {
+    hunk.lines.lines().any(|line| (line.starts_with('+') || line.starts_with('-')) && regex.is_match(&line[1..]))
+}
+
+/// The hunks of one file's patch, their lines marked as in `git diff`
+fn hunks(patch: &mut Patch) -> Result<Vec<Hunk>> {
+    let mut hunks = Vec::new();
+    for index in 0..patch.num_hunks() {
+        let (header, count) = patch.hunk(index)?;
+        let mut hunk = Hunk {
+            header: String::from_utf8_lossy(header.header()).trim_end().to_string(),
+            lines: String::new(),
+            truncated: count > MAX_HUNK_LINES,
+        };
+        for line in 0..count.min(MAX_HUNK_LINES) {
+            let line = patch.line_in_hunk(index, line)?;
+            if !hunk.lines.is_empty() {
+                hunk.lines.push('\n');
+            }
+            match line.origin() {
+                marker @ (' ' | '+' | '-') => {
+                    hunk.lines.push(marker);
+                    hunk.lines.push_str(String::from_utf8_lossy(line.content()).trim_end_matches(['\n', '\r']));
+                }
+                _ => hunk.lines.push_str("\\ No newline at end of file"),
+            }
+        }
+        hunks.push(hunk);
+    }
+    Ok(hunks)
+}
+
+/// An RFC 3339 timestamp, a YYYY-MM-DD date (its start, or its last second
+/// when `end_of_day` is set) or a relative date such as `2 weeks ago`
+fn parse_date(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let time = if end_of_day { date.and_hms_opt(23, 59, 59) } else { date.and_hms_opt(0, 0, 0) };
+        return Ok(time.expect("valid time of day").and_utc());
+    }
+    let ago = match value.split_whitespace().collect::<Vec<_>>()[..] {
+        [count, unit, "ago"] => {
+            let unit = match unit.trim_end_matches('s') {
+                "second" => 1,
+                "minute" => 60,
+                "hour" => 3600,
+                "day" => 86400,
+                "week" => 7 * 86400,
+                "month" => 30 * 86400,
+                "year" => 365 * 86400,
+                _ => 0,
+            };
+            count.parse::<i64>().ok()
+                .filter(|_| unit > 0)
+                .and_then(|count| count.checked_mul(unit))
+                .and_then(Duration::try_seconds)
+                .and_then(|ago| Utc::now().checked_sub_signed(ago))
+        }
+        _ => None,
+    };
+    ago.ok_or_else(|| ToolError::invalid(format!("Invalid date (expected RFC 3339, YYYY-MM-DD or e.g. 2 weeks ago): {}", value)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_history_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(dir.path()).output().unwrap();
+            assert!(output.status.success(), "{:?}", output);
+        };
+        let commit = |message: &str| git(&["-c", "user.name=Ada", "-c", "user.email=ada@example.com", "commit", "--quiet", "-am", message]);
+        git(&["init", "--quiet", "-b", "main"]);
+        std::fs::write(dir.path().join("lib.rs"), "fn timeout() -> u64 {\n    30\n}\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "timeout is 30\n").unwrap();
+        git(&["add", "."]);
+        commit("Add timeout");
+        std::fs::write(dir.path().join("lib.rs"), "fn timeout() -> u64 {\n    60\n}\n").unwrap();
+        commit("Raise timeout");
+        std::fs::write(dir.path().join("lib.rs"), "fn timeout() -> u64 {\n    60\n}\n\nfn retries() -> u32 {\n    3\n}\n").unwrap();
+        commit("Add retries");
+
+        let options = HistoryOptions { limit: 10, ..Default::default() };
+        let literal = TextOptions { literal: true, ..Default::default() };
+        let found = search(dir.path(), "60", &literal, &options).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].subject.as_str(), found[0].author.as_str()), ("Raise timeout", "Ada"));
+        assert_eq!(found[0].files[0].file, "lib.rs");
+        assert_eq!(found[0].files[0].hunks[0].lines, "-    30\n+    60");
+
+        let regex = search(dir.path(), r"^\s+[0-9]0$", &TextOptions::default(), &options).unwrap();
+        let subjects: Vec<&str> = regex.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, ["Raise timeout", "Add timeout"]);
+
+        let scoped = HistoryOptions { paths: vec!["notes.md".into()], ..options.clone() };
+        let notes = search(dir.path(), "timeout", &literal, &scoped).unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].files.len(), 1);
+        assert_eq!(notes[0].files[0].file, "notes.md");
+
+        let globbed = HistoryOptions { include: vec!["*.md".into()], ..options.clone() };
+        assert_eq!(search(dir.path(), "timeout", &literal, &globbed).unwrap()[0].files[0].file, "notes.md");
+        let excluded = HistoryOptions { exclude: vec!["*.rs".into()], ..options.clone() };
+        assert!(search(dir.path(), "60", &literal, &excluded).unwrap().is_empty());
+        let by = HistoryOptions { author: Some("ada@example".into()), since: Some("1 day ago".into()), ..options.clone() };
+        assert_eq!(search(dir.path(), "60", &literal, &by).unwrap().len(), 1);
+        let later = HistoryOptions { since: Some("2999-01-01".into()), ..options.clone() };
+        assert!(search(dir.path(), "60", &literal, &later).unwrap().is_empty());
+        assert!(search(dir.path(), "60", &literal, &HistoryOptions { since: Some("soon".into()), ..options.clone() }).is_err());
+
+        assert!(search(dir.path(), "nowhere", &literal, &options).unwrap().is_empty());
+        assert!(search(dir.path(), "(", &TextOptions::default(), &options).is_err());
+        let bad = HistoryOptions { reference: Some("--all".into()), ..options };
+        assert!(search(dir.path(), "60", &literal, &bad).is_err());
+    }
+}
@@ -0,0 +1,255 @@
+/// Background indexer that keeps search warm
+///
+/// Started with the server when `index.enabled` is set, it passes over the
+/// workspace roots every `interval_secs`, refreshing the symbol tables the
+/// search tool reads so a first lookup on a large tree does not pay for
+/// parsing it. Each pass stats every file and re-reads only those whose
+/// modification time or size changed, dropping the ones that went away;
+/// the walk also keeps the tree in the OS cache for text searches, which
+/// read files directly. With `index.vector`, every definition in a changed
+/// file is embedded into a vector collection as well.
+
+use super::symbol_index::{Refresh, Symbol, SymbolTables};
+use super::vector_store::{NewRecord, VectorStore};
+use crate::config::IndexConfig;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Characters of a definition embedded into the vector collection
+const MAX_EMBEDDED_CHARS: usize = 2000;
+
+/// How one root fared on its last pass
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RootStatus {
+    #[serde(flatten)]
+    pub symbols: Refresh,
+    /// Definitions embedded into the vector collection
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embedded: Option<usize>,
+    pub took_ms: u128,
+    pub indexed_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+struct State {
+    paused: bool,
+    /// A pass is under way
+    running: bool,
+    passes: u64,
+    roots: BTreeMap<PathBuf, RootStatus>,
+}
+
+pub struct Indexer {
+    config: IndexConfig,
+    symbols: SymbolTables,
+    state: Mutex<State>,
+    wake: Notify,
+}
+
+impl Indexer {
+    pub fn new(config: IndexConfig, symbols: SymbolTables) -> Self {
+        Self { config, symbols, state: Mutex::default(), wake: Notify::new() }
+    }
+
+    /// Pass over `roots()` until the process exits, every `interval_secs`
+    /// or as soon as a rebuild asks, skipping passes while paused
+    pub async fn run(self: Arc<Self>, roots: impl Fn() -> Vec<PathBuf> + Send + Sync, vectors: Arc<VectorStore>) {
+        let interval = Duration::from_secs(self.config.interval_secs.max(1));
+        let vectors = self.config.vector.then_some(vectors);
+        loop {
+            if !self.state().paused {
+                self.pass(&roots(), vectors.as_deref()).await;
+            }
+            let _ = tokio::time::timeout(interval, self.wake.notified()).await;
+        }
+    }
+
+    /// Bring every root's tables up to date once
+    pub async fn pass(&self, roots: &[PathBuf], vectors: Option<&VectorStore>) {
+        self.state().running = true;
+        for root in roots {
+            let started = Instant::now();
+            let mut status = RootStatus::default();
+            match self.refresh(root).await {
+                Ok(refresh) => {
+                    if let Some(vectors) = vectors {
+                        match self.embed(root, &refresh, vectors).await {
+                            Ok(embedded) => status.embedded = Some(embedded),
+                            Err(e) => status.error = Some(format!("Embedding failed: {}", e)),
+                        }
+                    }
+                    status.symbols = refresh;
+                }
+                Err(e) => status.error = Some(e.to_string()),
+            }
+            if let Some(error) = &status.error {
+                log::warn!("Indexing {}: {}", root.display(), error);
+            }
+            status.took_ms = started.elapsed().as_millis();
+            status.indexed_at = chrono::Utc::now().to_rfc3339();
+            self.state().roots.insert(root.clone(), status);
+        }
+        let mut state = self.state();
+        state.running = false;
+        state.passes += 1;
+        state.roots.retain(|root, _| roots.contains(root));
+    }
+
+    async fn refresh(&self, root: &Path) -> Result<Refresh> {
+        let (tables, root) = (self.symbols.clone(), root.to_path_buf());
+        tokio::task::spawn_blocking(move || {
+            let mut tables = tables.lock().unwrap_or_else(|e| e.into_inner());
+            tables.entry(root.clone()).or_default().refresh(&root)
+        }).await?
+    }
+
+    /// Replace the vector records of the files `refresh` touched with their
+    /// current definitions; how many were embedded
+    async fn embed(&self, root: &Path, refresh: &Refresh, vectors: &VectorStore) -> Result<usize> {
+        let touched: Vec<String> = refresh.changed.iter().chain(&refresh.gone).map(|p| p.display().to_string()).collect();
+        if touched.is_empty() {
+            return Ok(0);
+        }
+        let collection = &self.config.collection;
+        if vectors.info(collection).await.is_ok() {
+            vectors.delete(collection, None, Some(&json!({ "path": { "$in": touched } }))).await?;
+        }
+        let records = {
+            let tables = self.symbols.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(index) = tables.get(root) else { return Ok(0) };
+            refresh.changed.iter()
+                .flat_map(|file| {
+                    let source = std::fs::read_to_string(file).unwrap_or_default();
+                    let lines: Vec<&str> = source.lines().collect();
+                    index.file(file).iter().map(|symbol| record(root, symbol, &lines)).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let embedded = records.len();
+        if embedded > 0 {
+            vectors.upsert(collection, records).await?;
+        }
+        Ok(embedded)
+    }
+
+    /// Stop or resume passes; a pass under way finishes
+    pub fn pause(&self, paused: bool) {
+        self.state().paused = paused;
+        if !paused {
+            self.wake.notify_one();
+        }
+    }
+
+    /// Forget the tables of `root` (every root when `None`) and index
+    /// again now; the roots whose tables were dropped
+    pub fn rebuild(&self, root: Option<&Path>) -> Vec<PathBuf> {
+        let dropped = {
+            let mut tables = self.symbols.lock().unwrap_or_else(|e| e.into_inner());
+            let dropped: Vec<PathBuf> = tables.keys().filter(|r| root.is_none_or(|root| *r == root)).cloned().collect();
+            for root in &dropped {
+                tables.remove(root);
+            }
+            dropped
+        };
+        self.wake.notify_one();
+        dropped
+    }
+
+    pub fn status(&self) -> Value {
+        let state = self.state();
+        let roots: Vec<Value> = state.roots.iter()
+            .map(|(root, status)| {
+                let mut entry = json!(status);
+                entry["root"] = json!(root);
+                entry
+            })
+            .collect();
+        json!({
+            "enabled": true,
+            "paused": state.paused,
+            "running": state.running,
+            "passes": state.passes,
+            "interval_secs": self.config.interval_secs,
+            "vector": self.config.vector.then_some(&self.config.collection),
+            "roots": roots
+        })
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// A definition as a vector record: its signature and the start of its body
+fn record(root: &Path, symbol: &Symbol, lines: &[&str]) -> NewRecord {
+    let start = symbol.line.saturating_sub(1).min(lines.len());
+    let end = symbol.end_line.clamp(start, lines.len());
+    let mut content = format!("{} {}\n{}", symbol.kind, symbol.name, lines[start..end].join("\n"));
+    if let Some((cut, _)) = content.char_indices().nth(MAX_EMBEDDED_CHARS) {
+        content.truncate(cut);
+    }
+    let path = symbol.file.display().to_string();
+    NewRecord {
+        id: Some(format!("{}:{}:{}", path, symbol.line, symbol.name)),
+        content: Some(content),
+        metadata: Some(json!({
+            "path": path,
+            "root": root,
+            "name": symbol.name,
+            "kind": symbol.kind,
+            "line": symbol.line,
+            "end_line": symbol.end_line,
+            "container": symbol.container
+        })),
+        embedding: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::vector_store::{Embedder, Query};
+
+    #[tokio::test]
+    async fn test_indexer() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("lib.rs"), "fn parse_config() -> u32 {\n    1\n}\n\nstruct Config;\n").unwrap();
+        let symbols = SymbolTables::default();
+        let config = IndexConfig { enabled: true, vector: true, ..Default::default() };
+        let indexer = Indexer::new(config, symbols.clone());
+        let store = tempfile::tempdir().unwrap();
+        let vectors = VectorStore::open(store.path().to_path_buf(), Embedder::default());
+
+        indexer.pass(std::slice::from_ref(&root), Some(&vectors)).await;
+        let status = indexer.status();
+        assert_eq!(status["passes"], 1);
+        assert_eq!((status["roots"][0]["symbols"].as_u64(), status["roots"][0]["embedded"].as_u64()), (Some(2), Some(2)));
+        assert_eq!(symbols.lock().unwrap()[&root].lookup("prsCfg", &[], 5)[0].1.name, "parse_config");
+        let query = Query { text: Some("parse config".into()), limit: 5, ..Default::default() };
+        let found = vectors.query("code", query).await.unwrap();
+        assert_eq!(found[0]["metadata"]["name"], "parse_config");
+
+        indexer.pass(std::slice::from_ref(&root), Some(&vectors)).await;
+        assert_eq!(indexer.status()["roots"][0]["parsed"], 0);
+        std::fs::write(root.join("lib.rs"), "fn load_settings() {}\n").unwrap();
+        indexer.pass(std::slice::from_ref(&root), Some(&vectors)).await;
+        let all = vectors.get("code", None, None, 10, false).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0]["metadata"]["name"], "load_settings");
+
+        indexer.pause(true);
+        assert_eq!(indexer.status()["paused"], true);
+        assert_eq!(indexer.rebuild(Some(&root)), [root.clone()]);
+        assert!(symbols.lock().unwrap().is_empty());
+        assert!(indexer.rebuild(Some(Path::new("/elsewhere"))).is_empty());
+    }
+}
@@ -6,6 +6,12 @@ pub mod ast_search;
 pub mod symbol_search;
 pub mod vector_store;
 pub mod search;
+pub mod text;
+pub mod structural;
+pub mod symbol_index;
+pub mod history;
+pub mod indexer;
+pub mod query;
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -72,6 +78,9 @@ pub struct SearchConfig {
     pub context_lines: usize,
     pub file_pattern: Option<String>,
     pub language: Option<String>,
+    /// Flags and file filters for the text modality
+    #[serde(default)]
+    pub text: text::TextOptions,
 }
 
 impl Default for SearchConfig {
@@ -84,11 +93,13 @@ impl Default for SearchConfig {
             context_lines: 3,
             file_pattern: None,
             language: None,
+            text: text::TextOptions::default(),
         }
     }
 }
 
-/// Detect search modalities based on query
+/// Detect search modalities based on query, for free text that names
+/// none (see `query` for the syntax that does)
 pub fn detect_modalities(query: &str) -> Vec<SearchModality> {
     let mut modalities = Vec::new();
     
@@ -0,0 +1,219 @@
+/// Query syntax for the unified search
+///
+/// A query is free text plus `key:value` terms that pick modalities and
+/// narrow files, e.g. `sym:UserService lang:rust path:src/ -path:tests`:
+///
+/// - `text:`, `ast:`, `sym:`, `file:`, `vec:`, `mem:` run that modality
+///   for the term that follows (quoted to keep spaces: `text:"fn main"`);
+///   free text runs the modalities `detect_modalities` suggests for it
+/// - `lang:rust` keeps files of one language, `ext:rs` of one extension
+/// - `path:src/` keeps files under a path or matching a glob, `-path:`,
+///   `-ext:` rule them out
+/// - `case:yes`, `case:no`, `case:smart` set case sensitivity
+///
+/// Filters of different keys must all hold: `path:src lang:rust` keeps
+/// Rust files under src. Words whose prefix is not one of these keys
+/// (`std::fs`, `http://`) stay free text.
+
+use super::text::CaseMode;
+use super::{detect_modalities, SearchModality};
+use crate::error::ToolError;
+use anyhow::Result;
+use serde::Serialize;
+
+/// A parsed query
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Query {
+    /// Terms tied to no modality, joined by spaces
+    pub text: String,
+    /// The modalities the query named, each with its term
+    pub targeted: Vec<(SearchModality, String)>,
+    /// Tree-sitter language name: rust, python, typescript, ...
+    pub language: Option<String>,
+    /// ripgrep file type, for languages without known extensions
+    pub file_type: Option<String>,
+    /// Globs files must match (any of them)
+    pub include: Vec<String>,
+    /// Globs that rule files out
+    pub exclude: Vec<String>,
+    pub case: Option<CaseMode>,
+}
+
+impl Query {
+    /// Each search to run with its term: the modalities named, then those
+    /// the free text suggests
+    pub fn searches(&self) -> Vec<(SearchModality, String)> {
+        let mut searches = self.targeted.clone();
+        if !self.text.is_empty() {
+            searches.extend(detect_modalities(&self.text).into_iter().map(|m| (m, self.text.clone())));
+        }
+        searches.dedup();
+        searches
+    }
+}
+
+/// Parse `input` into its terms and filters
+pub fn parse(input: &str) -> Result<Query> {
+    let mut query = Query::default();
+    let mut text = Vec::new();
+    let (mut paths, mut names) = (Vec::new(), Vec::new());
+    for word in words(input)? {
+        let Some((key, value)) = word.split_once(':').filter(|(key, _)| is_key(key)) else {
+            text.push(word);
+            continue;
+        };
+        if value.is_empty() {
+            return Err(ToolError::invalid(format!("{}: needs a value", key)).into());
+        }
+        let value = value.to_string();
+        match key {
+            "text" | "re" => query.targeted.push((SearchModality::Text, value)),
+            "ast" => query.targeted.push((SearchModality::Ast, value)),
+            "sym" | "symbol" => query.targeted.push((SearchModality::Symbol, value)),
+            "file" => query.targeted.push((SearchModality::File, value)),
+            "vec" | "semantic" => query.targeted.push((SearchModality::Vector, value)),
+            "mem" | "memory" => query.targeted.push((SearchModality::Memory, value)),
+            "lang" | "language" => match language(&value) {
+                Some((language, extensions)) => {
+                    query.language = Some(language.to_string());
+                    names.extend(extensions.iter().map(|e| format!("*.{}", e)));
+                }
+                None => query.file_type = Some(value.to_lowercase()),
+            },
+            "ext" => names.push(format!("*.{}", value.trim_start_matches('.'))),
+            "-ext" => query.exclude.push(format!("*.{}", value.trim_start_matches('.'))),
+            "path" => paths.extend(globs(&value)),
+            "-path" => query.exclude.extend(globs(&value)),
+            "case" => query.case = Some(match value.to_lowercase().as_str() {
+                "yes" | "true" | "sensitive" => CaseMode::Sensitive,
+                "no" | "false" | "insensitive" => CaseMode::Insensitive,
+                "smart" => CaseMode::Smart,
+                _ => return Err(ToolError::invalid(format!("case: takes yes, no or smart, not {}", value)).into()),
+            }),
+            _ => unreachable!("is_key admits only the keys above"),
+        }
+    }
+    query.text = text.join(" ");
+    query.include = match (paths.is_empty(), names.is_empty()) {
+        (_, true) => paths,
+        (true, false) => names,
+        (false, false) => {
+            let both: Vec<String> = paths.iter()
+                .flat_map(|path| names.iter().filter_map(move |name| within(path, name)))
+                .collect();
+            if both.is_empty() {
+                return Err(ToolError::invalid(format!("No file can match both {} and {}", paths.join(", "), names.join(", "))).into());
+            }
+            both
+        }
+    };
+    Ok(query)
+}
+
+/// The glob for files named like `name` (`*.rs`) that `path` matches
+fn within(path: &str, name: &str) -> Option<String> {
+    let suffix = name.trim_start_matches('*');
+    if let Some(dir) = path.strip_suffix("/**") {
+        Some(format!("{}/**/{}", dir, name))
+    } else if path.ends_with('*') {
+        Some(format!("{}{}", path, suffix))
+    } else {
+        path.ends_with(suffix).then(|| path.to_string())
+    }
+}
+
+fn is_key(key: &str) -> bool {
+    matches!(key,
+        "text" | "re" | "ast" | "sym" | "symbol" | "file" | "vec" | "semantic" | "mem" | "memory"
+        | "lang" | "language" | "ext" | "-ext" | "path" | "-path" | "case")
+}
+
+/// The words of `input`, split on whitespace outside double quotes; quotes
+/// are dropped and `\"` keeps one
+fn words(input: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let (mut quoted, mut started) = (false, false);
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if quoted => word.extend(chars.next()),
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started || !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                started = false;
+            }
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        return Err(ToolError::invalid("Unclosed quote in query").into());
+    }
+    if started || !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// The tree-sitter language `name` stands for and its file extensions
+fn language(name: &str) -> Option<(&'static str, &'static [&'static str])> {
+    match name.to_lowercase().as_str() {
+        "rust" | "rs" => Some(("rust", &["rs"])),
+        "python" | "py" => Some(("python", &["py", "pyi"])),
+        "typescript" | "ts" | "tsx" => Some(("typescript", &["ts", "tsx"])),
+        "javascript" | "js" | "jsx" => Some(("javascript", &["js", "jsx", "mjs", "cjs"])),
+        "go" | "golang" => Some(("go", &["go"])),
+        "java" => Some(("java", &["java"])),
+        "cpp" | "c++" | "cc" => Some(("cpp", &["cpp", "cc", "cxx", "hpp", "hh", "h"])),
+        "c" => Some(("c", &["c", "h"])),
+        _ => None,
+    }
+}
+
+/// A path, or a glob when it has glob characters; a path covers the file
+/// or directory and everything under it
+fn globs(path: &str) -> Vec<String> {
+    if path.contains(['*', '?', '[', '{']) {
+        return vec![path.to_string()];
+    }
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    vec![path.to_string(), format!("{}/**", path)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse("sym:UserService lang:rust path:src/ -path:tests").unwrap();
+        assert_eq!(query.targeted, [(SearchModality::Symbol, "UserService".to_string())]);
+        assert_eq!((query.language.as_deref(), query.file_type.as_deref()), (Some("rust"), None));
+        assert_eq!(query.include, ["src/**/*.rs"]);
+        assert_eq!(query.exclude, ["tests", "tests/**"]);
+        assert_eq!(parse("path:src/main.rs path:lib/* ext:rs").unwrap().include, ["src/main.rs", "src/main.rs/**/*.rs", "lib/*.rs"]);
+        assert_eq!(parse("path:src").unwrap().include, ["src", "src/**"]);
+        assert!(parse("path:*.py lang:rust").is_err());
+        assert_eq!(query.searches(), [(SearchModality::Symbol, "UserService".to_string())]);
+
+        let query = parse(r#"text:"fn main" ext:rs -ext:.md case:yes std::fs "a \"b\"""#).unwrap();
+        assert_eq!(query.targeted, [(SearchModality::Text, "fn main".to_string())]);
+        assert_eq!((query.include.as_slice(), query.exclude.as_slice()), (&["*.rs".to_string()][..], &["*.md".to_string()][..]));
+        assert_eq!(query.case, Some(CaseMode::Sensitive));
+        assert_eq!(query.text, r#"std::fs a "b""#);
+
+        let free = parse("handleError lang:Markdown path:docs/*.md").unwrap();
+        assert_eq!((free.language.as_deref(), free.file_type.as_deref()), (None, Some("markdown")));
+        assert_eq!(free.include, ["docs/*.md"]);
+        assert!(free.searches().contains(&(SearchModality::Symbol, "handleError".to_string())));
+
+        assert!(parse("sym:").is_err());
+        assert!(parse("text:\"open").is_err());
+        assert!(parse("case:maybe").is_err());
+    }
+}
@@ -4,12 +4,13 @@
 use super::{SearchResult as InternalResult, MatchType, SearchModality};
 use super::ast_search::AstSearcher;
 use super::symbol_search::SymbolSearcher;
+use super::text;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::fs;
 use anyhow::Result;
+use crate::error::ToolError;
 use glob::glob;
-use std::process::Command;
 
 /// Search result structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,7 +135,7 @@ impl Search {
             }
             "vector" => {
                 // Vector store is currently disabled
-                Err(anyhow::anyhow!("Vector store not available"))
+                Err(ToolError::unsupported("Vector store not available").into())
             }
             "memory" => {
                 // Memory/knowledge base fetch not yet implemented
@@ -144,43 +145,10 @@ impl Search {
         }
     }
 
-    /// Execute text search using ripgrep
+    /// Execute text search; a query that is not a valid regex finds nothing
     async fn execute_text_search(&self, query: &str) -> Result<Vec<InternalResult>> {
-        let output = Command::new("rg")
-            .args(&[
-                "--json",
-                "--max-count", "20",
-                "-C", "3",
-                query,
-                ".",
-            ])
-            .output()?;
-        
-        let mut results = Vec::new();
-        
-        for line in std::str::from_utf8(&output.stdout)?.lines() {
-            if line.is_empty() { continue; }
-            
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                if json["type"] == "match" {
-                    let data = &json["data"];
-                    results.push(InternalResult {
-                        file_path: PathBuf::from(data["path"]["text"].as_str().unwrap_or("")),
-                        line_number: data["line_number"].as_u64().unwrap_or(0) as usize,
-                        column: data["submatches"][0]["start"].as_u64().unwrap_or(0) as usize,
-                        match_text: data["lines"]["text"].as_str().unwrap_or("").to_string(),
-                        context_before: vec![],
-                        context_after: vec![],
-                        match_type: MatchType::Text,
-                        score: 1.0,
-                        node_type: None,
-                        semantic_context: None,
-                    });
-                }
-            }
-        }
-        
-        Ok(results)
+        let outcome = text::search(Path::new("."), query, &Default::default(), 20, 3);
+        Ok(outcome.map(|o| o.results).unwrap_or_default())
     }
 
     /// Execute AST search
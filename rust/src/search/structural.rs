@@ -0,0 +1,442 @@
+/// Structural code patterns, ast-grep style
+///
+/// A pattern is code in the target language with metavariables standing
+/// in for parts of it: `$X` matches any one node and captures it,
+/// `$$$ARGS` any run of siblings (arguments, statements, parameters) and
+/// `$_` or `$_NAME` match without capturing. A metavariable used twice must
+/// match the same text both times. The pattern is parsed with the
+/// language's grammar and compiled to a tree-sitter query, so
+/// `if $COND { return $X }` finds every early return along with its
+/// condition and value. Delimiters between siblings are not compared;
+/// operators and keywords are.
+///
+/// A rewrite replaces each match with a template in which the pattern's
+/// metavariables stand for what they captured: `foo($A, $B)` to
+/// `foo($B, $A)`. Lines the template adds take the indentation of the line
+/// the match starts on and the file's line endings.
+
+use super::ast_search::get_language;
+use super::{MatchType, SearchResult};
+use crate::error::ToolError;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor};
+
+/// `$X`, `$$$X`, `$_` and a bare `$$$`
+static METAVAR: Lazy<Regex> = Lazy::new(|| Regex::new(r"\$\$\$([A-Z_][A-Z0-9_]*)?|\$([A-Z_][A-Z0-9_]*)").unwrap());
+
+/// Identifiers every grammar accepts, standing in for metavariables while
+/// the pattern is parsed
+const ONE: &str = "hzmv_";
+const MANY: &str = "hzmvs_";
+
+/// Tokens left out of the compiled query, so `foo($A, $B)` anchors on
+/// the two arguments rather than on the comma between them
+const DELIMITERS: &[&str] = &["(", ")", "[", "]", "{", "}", ",", ";", ":"];
+
+/// Whether `pattern` has metavariables and so is structural rather than a
+/// name to look for
+pub fn is_structural(pattern: &str) -> bool {
+    METAVAR.is_match(pattern)
+}
+
+/// Where one match sits and what its metavariables matched
+#[derive(Debug, Clone, Serialize)]
+pub struct StructuralMatch {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub text: String,
+    pub node_type: String,
+    pub captures: BTreeMap<String, String>,
+    /// Bytes of the file the match covers
+    #[serde(skip)]
+    pub range: Range<usize>,
+}
+
+impl From<StructuralMatch> for SearchResult {
+    fn from(found: StructuralMatch) -> Self {
+        let captures: Vec<String> = found.captures.iter().map(|(name, text)| format!("${}={}", name, text)).collect();
+        SearchResult {
+            file_path: found.file,
+            line_number: found.line,
+            column: found.column,
+            match_text: found.text,
+            context_before: vec![],
+            context_after: vec![],
+            match_type: MatchType::Ast,
+            score: 0.95,
+            node_type: Some(found.node_type),
+            semantic_context: (!captures.is_empty()).then(|| captures.join(", ")),
+        }
+    }
+}
+
+/// A `$$$` run: the capture on the node holding it, and how many named
+/// children come before and after it
+struct Run {
+    name: String,
+    parent: String,
+    before: usize,
+    after: usize,
+}
+
+/// A pattern compiled for one language
+pub struct StructuralPattern {
+    grammar: Language,
+    query: Query,
+    runs: Vec<Run>,
+}
+
+impl StructuralPattern {
+    /// Compile `pattern` for `language` (one of `get_language`'s names)
+    pub fn compile(pattern: &str, language: &str) -> Result<Self> {
+        let grammar = get_language(language)
+            .ok_or_else(|| ToolError::unsupported(format!("No grammar for {}", language)))?;
+        let mut runs = HashSet::new();
+        for caps in METAVAR.captures_iter(pattern) {
+            // A run has no single text to compare against
+            if let Some(name) = caps.get(1).map(|m| m.as_str()).filter(|name| !name.starts_with('_')) {
+                if !runs.insert(name) {
+                    return Err(ToolError::invalid(format!("${}{} appears twice; a run can only appear once", "$$", name)).into());
+                }
+            }
+        }
+        let source = METAVAR.replace_all(pattern.trim(), |caps: &regex::Captures| match (caps.get(1), caps.get(2)) {
+            (_, Some(name)) => format!("{}{}", ONE, name.as_str()),
+            (Some(name), _) => format!("{}{}", MANY, name.as_str()),
+            (None, None) => format!("{}_", MANY),
+        }).into_owned();
+
+        let mut parser = Parser::new();
+        parser.set_language(grammar)?;
+        let mut parsed = None;
+        for (prefix, suffix) in wrappers(language) {
+            let text = format!("{}{}{}", prefix, source, suffix);
+            let Some(tree) = parser.parse(&text, None) else { continue };
+            let range = prefix.len()..prefix.len() + source.len();
+            let whole = tree.root_node().named_descendant_for_byte_range(range.start, range.end)
+                .is_some_and(|node| node.byte_range() == range);
+            if whole && !tree.root_node().has_error() {
+                parsed = Some((text, tree, range));
+                break;
+            }
+        }
+        let Some((text, tree, range)) = parsed else {
+            return Err(ToolError::invalid(format!("Pattern does not parse as {}: {}", language, pattern)).into());
+        };
+        let Some(root) = tree.root_node().named_descendant_for_byte_range(range.start, range.end) else {
+            return Err(ToolError::invalid(format!("Pattern does not parse as {}: {}", language, pattern)).into());
+        };
+        if placeholder(&text[root.byte_range()], MANY).is_some() {
+            return Err(ToolError::invalid("A pattern cannot be only a $$$ run").into());
+        }
+
+        let mut compiler = Compiler { source: &text, predicates: Vec::new(), seen: HashMap::new(), leaves: 0, runs: Vec::new() };
+        let body = compiler.pattern(root)?;
+        let query = format!("({} @_match {})", body, compiler.predicates.join(" "));
+        let query = Query::new(grammar, &query)
+            .map_err(|e| ToolError::invalid(format!("Pattern compiles to an invalid query ({}): {}", e.message, query)))?;
+        Ok(Self { grammar, query, runs: compiler.runs })
+    }
+
+    /// Up to `limit` matches in one file's `source`, outermost first
+    pub fn find(&self, source: &str, file: &Path, limit: usize) -> Vec<StructuralMatch> {
+        let mut parser = Parser::new();
+        if parser.set_language(self.grammar).is_err() {
+            return vec![];
+        }
+        let Some(tree) = parser.parse(source, None) else { return vec![] };
+        let names = self.query.capture_names();
+        let index = |name: &str| names.iter().position(|n| n == name).map(|i| i as u32);
+        let whole = index("_match");
+        let mut seen = HashSet::new();
+        let mut cursor = QueryCursor::new();
+        let mut found = Vec::new();
+        for matched in cursor.matches(&self.query, tree.root_node(), source.as_bytes()) {
+            if found.len() >= limit {
+                break;
+            }
+            let Some(node) = matched.captures.iter().find(|c| Some(c.index) == whole).map(|c| c.node) else { continue };
+            if !seen.insert(node.byte_range()) {
+                continue;
+            }
+            let mut captures: BTreeMap<String, String> = matched.captures.iter()
+                .filter(|c| !names[c.index as usize].starts_with('_'))
+                .map(|c| (names[c.index as usize].clone(), source[c.node.byte_range()].to_string()))
+                .collect();
+            for run in &self.runs {
+                let Some(parent) = matched.captures.iter().find(|c| Some(c.index) == index(&run.parent)) else { continue };
+                let mut walk = parent.node.walk();
+                let children: Vec<Node> = parent.node.named_children(&mut walk).filter(|n| !n.is_extra()).collect();
+                let inner = children.get(run.before..children.len().saturating_sub(run.after)).unwrap_or_default();
+                let text = match (inner.first(), inner.last()) {
+                    (Some(first), Some(last)) => &source[first.start_byte()..last.end_byte()],
+                    _ => "",
+                };
+                captures.insert(run.name.clone(), text.to_string());
+            }
+            found.push(StructuralMatch {
+                file: file.to_path_buf(),
+                line: node.start_position().row + 1,
+                column: node.start_position().column + 1,
+                end_line: node.end_position().row + 1,
+                text: source[node.byte_range()].to_string(),
+                node_type: node.kind().to_string(),
+                captures,
+                range: node.byte_range(),
+            });
+        }
+        found
+    }
+}
+
+/// `source` with each of `matches` (found in it, in order) replaced by
+/// `template`, and how many were. A match inside one already replaced is
+/// left to the outer replacement.
+pub fn rewrite(source: &str, matches: &[StructuralMatch], template: &str) -> Result<(String, usize)> {
+    let newline = if source.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut out = String::with_capacity(source.len());
+    let (mut at, mut count) = (0, 0);
+    for found in matches {
+        if found.range.start < at {
+            continue;
+        }
+        let line_start = source[..found.range.start].rfind('\n').map_or(0, |i| i + 1);
+        let indent: String = source[line_start..].chars().take_while(|c| *c == ' ' || *c == '\t').collect();
+        out.push_str(&source[at..found.range.start]);
+        out.push_str(&expand(template, &found.captures, &indent, newline)?);
+        at = found.range.end;
+        count += 1;
+    }
+    out.push_str(&source[at..]);
+    Ok((out, count))
+}
+
+/// `template` with its metavariables replaced by `captures`. Lines the
+/// template adds are indented by `indent`; captures keep their own
+fn expand(template: &str, captures: &BTreeMap<String, String>, indent: &str, newline: &str) -> Result<String> {
+    let mut lines = Vec::new();
+    for (i, line) in template.split('\n').enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let mut missing = None;
+        let filled = METAVAR.replace_all(line, |caps: &regex::Captures| {
+            let name = caps.get(2).or(caps.get(1)).map_or("", |m| m.as_str());
+            captures.get(name).cloned().unwrap_or_else(|| {
+                missing.get_or_insert_with(|| caps[0].to_string());
+                String::new()
+            })
+        });
+        if let Some(name) = missing {
+            return Err(ToolError::invalid(format!("The template uses {}, which the pattern does not capture", name)).into());
+        }
+        lines.push(if i == 0 || filled.is_empty() { filled.into_owned() } else { format!("{}{}", indent, filled) });
+    }
+    Ok(lines.join(newline))
+}
+
+/// Whether `source` parses as `language` without syntax errors
+pub fn parses(source: &str, language: &str) -> bool {
+    let mut parser = Parser::new();
+    get_language(language)
+        .filter(|grammar| parser.set_language(*grammar).is_ok())
+        .and_then(|_| parser.parse(source, None))
+        .is_some_and(|tree| !tree.root_node().has_error())
+}
+
+/// Sources a pattern is tried in, in order, until one parses cleanly:
+/// bare, then as a statement or member of what the grammar needs around it
+fn wrappers(language: &str) -> &'static [(&'static str, &'static str)] {
+    match language {
+        "rust" => &[("", ""), ("fn w() { ", " }"), ("fn w() { ", "; }"), ("impl W { ", " }")],
+        "javascript" | "typescript" => &[("", ""), ("(", ")"), ("function w() { ", " }"), ("class W { ", " }")],
+        "go" => &[("package w\n", "\n"), ("package w\nfunc w() {\n", "\n}\n"), ("package w\nvar w = ", "\n")],
+        "java" => &[("", ""), ("class W { ", " }"), ("class W { void w() { ", " } }"), ("class W { void w() { ", "; } }")],
+        "c" | "cpp" => &[("", ""), ("void w() { ", " }"), ("void w() { ", "; }")],
+        _ => &[("", "")],
+    }
+}
+
+/// Pattern tree to query
+struct Compiler<'a> {
+    source: &'a str,
+    predicates: Vec<String>,
+    /// Times each metavariable has appeared
+    seen: HashMap<String, usize>,
+    leaves: usize,
+    runs: Vec<Run>,
+}
+
+enum Item {
+    Named(String),
+    Token(String),
+    Run(String),
+}
+
+impl Compiler<'_> {
+    fn pattern(&mut self, node: Node) -> Result<String> {
+        let text = &self.source[node.byte_range()];
+        if let Some(name) = placeholder(text, ONE) {
+            return Ok(self.metavariable(name));
+        }
+        if node.named_child_count() == 0 {
+            // Identifiers and literals match on their text
+            let capture = format!("_t{}", self.leaves);
+            self.leaves += 1;
+            self.predicates.push(format!("(#eq? @{} {})", capture, quote(text)));
+            return Ok(format!("({}) @{}", node.kind(), capture));
+        }
+
+        let mut items = Vec::new();
+        let mut cursor = node.walk();
+        let mut more = cursor.goto_first_child();
+        while more {
+            let child = cursor.node();
+            let field = cursor.field_name().map(|f| format!("{}: ", f)).unwrap_or_default();
+            let text = &self.source[child.byte_range()];
+            if child.is_extra() {
+                // Comments
+            } else if let Some(name) = placeholder(text, MANY) {
+                items.push(Item::Run(name.to_string()));
+            } else if child.is_named() {
+                items.push(Item::Named(format!("{}{}", field, self.pattern(child)?)));
+            } else if !DELIMITERS.contains(&text) {
+                items.push(Item::Token(format!("{}{}", field, quote(text))));
+            }
+            more = cursor.goto_next_sibling();
+        }
+
+        // Anchor named children to each other and to the ends of the node,
+        // except across a run
+        let mut query = format!("({}", node.kind());
+        let (mut anchored, mut named, mut run) = (true, 0, None);
+        for item in items {
+            match item {
+                Item::Named(pattern) => {
+                    if anchored {
+                        query.push_str(" .");
+                    }
+                    query.push(' ');
+                    query.push_str(&pattern);
+                    anchored = true;
+                    named += 1;
+                }
+                Item::Token(token) => {
+                    query.push(' ');
+                    query.push_str(&token);
+                }
+                Item::Run(name) => {
+                    if run.is_some() {
+                        return Err(ToolError::invalid("Only one $$$ run per list of siblings").into());
+                    }
+                    run = Some((name, named));
+                    anchored = false;
+                }
+            }
+        }
+        if anchored && named > 0 {
+            query.push_str(" .");
+        }
+        query.push(')');
+        if let Some((name, before)) = run.filter(|(name, _)| !name.starts_with('_')) {
+            let parent = format!("_r{}", self.runs.len());
+            query.push_str(&format!(" @{}", parent));
+            self.runs.push(Run { name, parent, before, after: named - before });
+        }
+        Ok(query)
+    }
+
+    fn metavariable(&mut self, name: &str) -> String {
+        if name.starts_with('_') {
+            return "(_)".to_string();
+        }
+        let count = self.seen.entry(name.to_string()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            return format!("(_) @{}", name);
+        }
+        let alias = format!("_{}_{}", name, count);
+        self.predicates.push(format!("(#eq? @{} @{})", name, alias));
+        format!("(_) @{}", alias)
+    }
+}
+
+/// The metavariable `text` stands for, when it is exactly a placeholder
+fn placeholder<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    text.strip_prefix(prefix).filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// A query string literal
+fn quote(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\r', "\\r").replace('\t', "\\t");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str, language: &str, source: &str) -> Vec<BTreeMap<String, String>> {
+        StructuralPattern::compile(pattern, language).unwrap()
+            .find(source, Path::new("x"), 100)
+            .into_iter()
+            .map(|found| found.captures)
+            .collect()
+    }
+
+    fn captured(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_structural() {
+        let rust = "fn f() {\n    if a > 1 { return b }\n    if c { return d; }\n    if e { log(); return g }\n    foo(1, 2);\n    foo(1);\n    foo(x, x);\n    bar(1, 2, 3);\n}\n";
+        assert_eq!(find("if $COND { return $X }", "rust", rust), [captured(&[("COND", "a > 1"), ("X", "b")])]);
+        assert_eq!(find("if $COND { return $X; }", "rust", rust), [captured(&[("COND", "c"), ("X", "d")])]);
+        assert_eq!(find("foo($A, $B)", "rust", rust).len(), 2);
+        assert_eq!(find("foo($A, $A)", "rust", rust), [captured(&[("A", "x")])]);
+        assert_eq!(find("foo($_)", "rust", rust), [captured(&[])]);
+        assert_eq!(find("bar(1, $$$REST)", "rust", rust), [captured(&[("REST", "2, 3")])]);
+        assert_eq!(find("foo($$$ARGS)", "rust", rust).len(), 3);
+        assert!(find("$A - $B", "rust", rust).is_empty());
+
+        let found = StructuralPattern::compile("if $C { $$$BODY }", "rust").unwrap().find(rust, Path::new("lib.rs"), 10);
+        assert_eq!(found.len(), 3);
+        assert_eq!((found[2].line, found[2].column, found[2].node_type.as_str()), (4, 5, "if_expression"));
+        assert_eq!(found[2].captures["BODY"], "log(); return g");
+        assert_eq!(SearchResult::from(found[0].clone()).semantic_context.as_deref(), Some("$BODY=return b, $C=a > 1"));
+
+        let python = "def area(self):\n    return self.w * self.h\n\ndef other():\n    return 1\n";
+        assert_eq!(find("return $A * $B", "python", python), [captured(&[("A", "self.w"), ("B", "self.h")])]);
+        let ts = "const a = await fetch(url, { method: 'POST' });\nconst b = fetch(url);\n";
+        assert_eq!(find("await fetch($$$)", "typescript", ts).len(), 1);
+        let go = "package main\n\nfunc f() error {\n\tif err != nil {\n\t\treturn err\n\t}\n\treturn nil\n}\n";
+        assert_eq!(find("if $E != nil { return $E }", "go", go), [captured(&[("E", "err")])]);
+
+        let rewritten = |pattern: &str, template: &str, language: &str, source: &str| {
+            let found = StructuralPattern::compile(pattern, language).unwrap().find(source, Path::new("x"), 100);
+            rewrite(source, &found, template).unwrap().0
+        };
+        let python = "class Shape:\n    def area(self):\n        if self.empty:\n            return 0\n        return self.w * self.h\n";
+        let guarded = rewritten("if $C:\n    return $V", "if $C:\n    log($C)\n    return $V", "python", python);
+        assert_eq!(guarded, "class Shape:\n    def area(self):\n        if self.empty:\n            log(self.empty)\n            return 0\n        return self.w * self.h\n");
+        assert!(parses(&guarded, "python"));
+        assert_eq!(rewritten("fetch($U)", "http.get($U)", "typescript", ts), "const a = await fetch(url, { method: 'POST' });\nconst b = http.get(url);\n");
+        let go = rewritten("if $E != nil { return $E }", "if $E != nil {\n\treturn fmt.Errorf(\"f: %w\", $E)\n}", "go", go);
+        assert!(go.contains("\tif err != nil {\n\t\treturn fmt.Errorf(\"f: %w\", err)\n\t}\n\treturn nil"));
+        assert!(parses(&go, "go") && !parses("func (", "go"));
+        let nested = rewritten("Some($X)", "Ok($X)", "rust", "let v = Some(Some(1));\n");
+        assert_eq!(nested, "let v = Ok(Some(1));\n");
+
+        assert!(is_structural("foo($X)") && !is_structural("function foo"));
+        assert!(StructuralPattern::compile("fn (", "rust").is_err());
+        assert!(StructuralPattern::compile("f($$$A, $$$A)", "rust").is_err());
+        assert!(StructuralPattern::compile("$X", "cobol").is_err());
+    }
+}
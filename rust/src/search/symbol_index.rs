@@ -0,0 +1,276 @@
+/// Workspace symbol table with fuzzy lookup
+///
+/// Every file a text search would read and tree-sitter can outline
+/// contributes its definitions: functions, methods, types, classes,
+/// traits, impls, modules, constants. The table is kept between lookups and
+/// refreshed by modification time, so only changed files are parsed again.
+/// Lookups match names fuzzily, the way editors' symbol pickers do:
+/// `usrSrv` finds `UserService`, ranking whole names and prefixes first and
+/// then matches on word boundaries.
+
+use super::ast_search::{language_for, outline, OutlineSymbol};
+use super::text::{self, TextOptions};
+use crate::error::ToolError;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Files indexed per root; the rest of a larger tree is left out
+const MAX_FILES: usize = 50_000;
+
+/// Symbol tables by root, shared by whoever reads or warms them
+pub type SymbolTables = Arc<Mutex<HashMap<PathBuf, SymbolIndex>>>;
+
+/// A definition and where to jump to it
+#[derive(Debug, Clone, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: &'static str,
+    /// Declaration up to its body
+    pub signature: String,
+    pub file: PathBuf,
+    pub line: usize,
+    pub end_line: usize,
+    /// The impl, class or module it is declared in
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+}
+
+struct Indexed {
+    modified: Option<SystemTime>,
+    len: u64,
+    symbols: Vec<Symbol>,
+}
+
+/// What a refresh did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Refresh {
+    pub files: usize,
+    pub symbols: usize,
+    /// Files read and parsed because they were new or changed
+    pub parsed: usize,
+    pub removed: usize,
+    /// Stopped at MAX_FILES
+    pub truncated: bool,
+    /// The files parsed
+    #[serde(skip)]
+    pub changed: Vec<PathBuf>,
+    /// The files no longer there
+    #[serde(skip)]
+    pub gone: Vec<PathBuf>,
+}
+
+/// Symbols of the files under one root
+#[derive(Default)]
+pub struct SymbolIndex {
+    files: HashMap<PathBuf, Indexed>,
+}
+
+impl SymbolIndex {
+    /// Bring the table up to date with the files under `root`
+    pub fn refresh(&mut self, root: &Path) -> Result<Refresh> {
+        let mut refresh = Refresh::default();
+        let mut present = HashSet::new();
+        for entry in text::walk(root, &TextOptions::default())?.flatten() {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let Some(language) = language_for(entry.path()) else { continue };
+            if present.len() >= MAX_FILES {
+                refresh.truncated = true;
+                break;
+            }
+            let Ok(metadata) = entry.metadata() else { continue };
+            if metadata.len() > text::MAX_FILE_BYTES {
+                continue;
+            }
+            let path = entry.path().to_path_buf();
+            present.insert(path.clone());
+            let modified = metadata.modified().ok();
+            if self.files.get(&path).is_some_and(|i| i.modified == modified && i.len == metadata.len()) {
+                continue;
+            }
+            let symbols = std::fs::read_to_string(&path).ok()
+                .and_then(|source| outline(&source, language))
+                .map(|outline| flatten(&path, outline, None))
+                .unwrap_or_default();
+            self.files.insert(path.clone(), Indexed { modified, len: metadata.len(), symbols });
+            refresh.changed.push(path);
+            refresh.parsed += 1;
+        }
+        refresh.gone = self.files.keys().filter(|path| !present.contains(*path)).cloned().collect();
+        self.files.retain(|path, _| present.contains(path));
+        refresh.removed = refresh.gone.len();
+        refresh.files = self.files.len();
+        refresh.symbols = self.files.values().map(|i| i.symbols.len()).sum();
+        Ok(refresh)
+    }
+
+    /// The symbols of one file, in file order
+    pub fn file(&self, path: &Path) -> &[Symbol] {
+        self.files.get(path).map_or(&[], |indexed| &indexed.symbols)
+    }
+
+    /// Up to `limit` symbols of `kinds` (all when empty) whose names match
+    /// `query`, best first with their scores. An empty query lists them in
+    /// file order
+    pub fn lookup(&self, query: &str, kinds: &[&str], limit: usize) -> Vec<(i64, &Symbol)> {
+        let mut found: Vec<(i64, &Symbol)> = self.files.values()
+            .flat_map(|indexed| &indexed.symbols)
+            .filter(|symbol| kinds.is_empty() || kinds.contains(&symbol.kind))
+            .filter_map(|symbol| fuzzy_score(query, &symbol.name).map(|score| (score, symbol)))
+            .collect();
+        found.sort_by(|(a, x), (b, y)| {
+            b.cmp(a)
+                .then(x.name.len().cmp(&y.name.len()))
+                .then_with(|| x.file.cmp(&y.file))
+                .then(x.line.cmp(&y.line))
+        });
+        found.truncate(limit);
+        found
+    }
+}
+
+fn flatten(file: &Path, outline: Vec<OutlineSymbol>, container: Option<&str>) -> Vec<Symbol> {
+    let mut symbols = Vec::new();
+    for symbol in outline {
+        symbols.push(Symbol {
+            name: symbol.name.clone(),
+            kind: symbol.kind,
+            signature: symbol.signature,
+            file: file.to_path_buf(),
+            line: symbol.start_line,
+            end_line: symbol.end_line,
+            container: container.map(str::to_string),
+        });
+        symbols.extend(flatten(file, symbol.children, Some(&symbol.name)));
+    }
+    symbols
+}
+
+/// The kind `name` (or a common alias of it) stands for
+pub fn kind(name: &str) -> Result<&'static str> {
+    match name.trim().to_lowercase().as_str() {
+        "function" | "fn" | "func" | "def" => Ok("function"),
+        "method" => Ok("method"),
+        "constructor" | "ctor" => Ok("constructor"),
+        "struct" => Ok("struct"),
+        "enum" => Ok("enum"),
+        "trait" => Ok("trait"),
+        "impl" => Ok("impl"),
+        "module" | "mod" => Ok("module"),
+        "type" | "typedef" | "alias" => Ok("type"),
+        "const" | "static" | "constant" => Ok("const"),
+        "macro" => Ok("macro"),
+        "class" => Ok("class"),
+        "interface" => Ok("interface"),
+        "namespace" => Ok("namespace"),
+        _ => Err(ToolError::invalid(format!(
+            "Unknown symbol kind: {} (function, method, constructor, struct, enum, trait, impl, module, type, const, macro, class, interface, namespace)",
+            name
+        )).into()),
+    }
+}
+
+/// How well `query` matches `candidate` as a case-insensitive subsequence,
+/// or `None` if it does not. Matches on word starts (`_x`, `camelCase`
+/// humps) and runs of adjacent letters score higher, gaps lower; the whole
+/// name or a prefix of it scores highest
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    const NONE: i64 = i64::MIN / 2;
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    let name: Vec<char> = candidate.chars().collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    if query.len() > name.len() {
+        return None;
+    }
+    let same = |a: char, b: char| a == b || a.to_lowercase().eq(b.to_lowercase());
+    let boundary = |j: usize| {
+        j == 0 || {
+            let (prev, this) = (name[j - 1], name[j]);
+            !prev.is_alphanumeric()
+                || (prev.is_lowercase() && this.is_uppercase())
+                || (prev.is_alphabetic() && this.is_ascii_digit())
+        }
+    };
+
+    // best[j]: the best score with the current query letter matched at j
+    let mut previous = vec![NONE; name.len()];
+    for (i, &letter) in query.iter().enumerate() {
+        let mut best = vec![NONE; name.len()];
+        // Best of previous[k] for k < j, less a point per letter skipped
+        let mut carry = NONE;
+        for j in 0..name.len() {
+            if j > 0 {
+                carry = (carry - 1).max(previous[j - 1]);
+            }
+            if !same(name[j], letter) {
+                continue;
+            }
+            let score = 16 + if boundary(j) { 8 } else { 0 } + if name[j] == letter { 1 } else { 0 };
+            let before = if i == 0 {
+                -(j.min(8) as i64)
+            } else {
+                let adjacent = if j > 0 && previous[j - 1] > NONE { previous[j - 1] + 6 } else { NONE };
+                adjacent.max(carry)
+            };
+            if before > NONE / 2 {
+                best[j] = before + score;
+            }
+        }
+        previous = best;
+    }
+    let score = previous.into_iter().max().filter(|score| *score > NONE / 2)?;
+    let query: String = query.into_iter().collect();
+    Some(if candidate.eq_ignore_ascii_case(&query) {
+        score + 100
+    } else if candidate.to_lowercase().starts_with(&query.to_lowercase()) {
+        score + 40
+    } else {
+        score
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_index() {
+        assert!(fuzzy_score("usrSrv", "UserService").is_some());
+        assert!(fuzzy_score("usrSrv", "UserStore").is_none());
+        assert!(fuzzy_score("us", "UserService") > fuzzy_score("us", "ActiveUsers"));
+        assert!(fuzzy_score("user", "user") > fuzzy_score("user", "UserService"));
+        assert!(fuzzy_score("gu", "get_user") > fuzzy_score("gu", "argument"));
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("service.rs"), "pub struct UserService {}\n\nimpl UserService {\n    pub fn new() -> Self {\n        UserService {}\n    }\n}\n\nfn user_store() {}\n").unwrap();
+        std::fs::write(dir.path().join("app.py"), "class UserSerializer:\n    def save(self):\n        pass\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "UserService\n").unwrap();
+        let mut index = SymbolIndex::default();
+        let first = index.refresh(dir.path()).unwrap();
+        assert_eq!((first.files, first.parsed, first.symbols), (2, 2, 6));
+
+        let found = index.lookup("usrSrv", &[], 10);
+        let names: Vec<(&str, &str)> = found.iter().map(|(_, s)| (s.name.as_str(), s.kind)).collect();
+        assert_eq!(names, [("UserService", "struct"), ("UserService", "impl")]);
+        let method = index.lookup("new", &["method"], 10);
+        assert_eq!(method[0].1.container.as_deref(), Some("UserService"));
+        assert_eq!((method[0].1.line, method[0].1.signature.as_str()), (4, "pub fn new() -> Self"));
+        assert_eq!(index.lookup("user", &[kind("def").unwrap()], 10)[0].1.name, "user_store");
+        assert_eq!(index.lookup("", &["class"], 10)[0].1.name, "UserSerializer");
+        assert!(kind("widget").is_err());
+
+        assert_eq!(index.refresh(dir.path()).unwrap().parsed, 0);
+        std::fs::remove_file(dir.path().join("app.py")).unwrap();
+        std::fs::write(dir.path().join("service.rs"), "fn renamed_service() {}\n").unwrap();
+        let again = index.refresh(dir.path()).unwrap();
+        assert_eq!((again.parsed, again.removed, again.symbols), (1, 1, 1));
+        assert!(index.lookup("usrSrv", &[], 10).is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+/// Text search over a directory tree
+///
+/// The one grep behind the search tool and the unified search's text
+/// modality: regex or literal patterns, whole words, multiline matches,
+/// case modes, ripgrep's file types (`rust`, `py`, ...), include and
+/// exclude globs and a cap on matches per file. Walks respect .gitignore
+/// and skip hidden and binary files unless told otherwise.
+
+use super::{MatchType, SearchResult};
+use crate::error::ToolError;
+use anyhow::Result;
+use ignore::overrides::OverrideBuilder;
+use ignore::types::TypesBuilder;
+use ignore::WalkBuilder;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Files larger than this are not searched
+pub(super) const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Bytes checked for a NUL when deciding whether a file is binary
+const BINARY_PROBE_BYTES: usize = 8192;
+
+/// How letter case is compared
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseMode {
+    Sensitive,
+    Insensitive,
+    /// Insensitive unless the pattern has an uppercase letter
+    #[default]
+    Smart,
+}
+
+impl CaseMode {
+    /// Whether `pattern` is matched ignoring case
+    pub fn ignores(self, pattern: &str) -> bool {
+        match self {
+            Self::Sensitive => false,
+            Self::Insensitive => true,
+            Self::Smart => !pattern.chars().any(char::is_uppercase),
+        }
+    }
+}
+
+/// How a text search matches and which files it reads
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextOptions {
+    /// Match the pattern as a fixed string rather than a regex
+    pub literal: bool,
+    /// Match only whole words
+    pub word: bool,
+    /// Let matches span lines; `.` then also matches newlines
+    pub multiline: bool,
+    pub case: CaseMode,
+    /// ripgrep file type names: `rust`, `py`, `ts`, ...
+    pub types: Vec<String>,
+    /// Globs a file must match, relative to the search root
+    pub include: Vec<String>,
+    /// Globs that rule a file out
+    pub exclude: Vec<String>,
+    pub max_per_file: Option<usize>,
+    /// Search hidden files and directories too
+    pub hidden: bool,
+    /// Search files .gitignore and .ignore leave out
+    pub no_ignore: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            literal: false,
+            word: false,
+            multiline: false,
+            case: CaseMode::Smart,
+            types: Vec::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            max_per_file: None,
+            hidden: false,
+            no_ignore: false,
+        }
+    }
+}
+
+/// What a search found
+#[derive(Debug, Default)]
+pub struct TextOutcome {
+    pub results: Vec<SearchResult>,
+    pub files_searched: usize,
+    pub files_matched: usize,
+    /// Reached `max_results`, so there may be more
+    pub truncated: bool,
+}
+
+/// The regex `pattern` compiles to under `options`
+pub fn compile(pattern: &str, options: &TextOptions) -> Result<Regex> {
+    let mut source = if options.literal { regex::escape(pattern) } else { pattern.to_string() };
+    if options.word {
+        source = format!(r"\b(?:{})\b", source);
+    }
+    RegexBuilder::new(&source)
+        .case_insensitive(options.case.ignores(pattern))
+        .multi_line(true)
+        .crlf(true)
+        .dot_matches_new_line(options.multiline)
+        .build()
+        .map_err(|e| ToolError::invalid(format!("Invalid pattern: {}", e)).into())
+}
+
+/// The files and directories under `root` (or `root` itself) that
+/// `options` lets a search read, in name order
+pub fn walk(root: &Path, options: &TextOptions) -> Result<ignore::Walk> {
+    let mut walk = WalkBuilder::new(root);
+    walk.hidden(!options.hidden)
+        .ignore(!options.no_ignore)
+        .git_ignore(!options.no_ignore)
+        .git_global(!options.no_ignore)
+        .git_exclude(!options.no_ignore)
+        .parents(!options.no_ignore)
+        .sort_by_file_name(|a, b| a.cmp(b));
+    if !options.types.is_empty() {
+        let mut types = TypesBuilder::new();
+        types.add_defaults();
+        for name in &options.types {
+            types.select(name);
+        }
+        walk.types(types.build().map_err(|e| ToolError::invalid(format!("File type: {}", e)))?);
+    }
+    if !options.include.is_empty() || !options.exclude.is_empty() {
+        let mut globs = OverrideBuilder::new(root);
+        for glob in &options.include {
+            globs.add(glob).map_err(|e| ToolError::invalid(format!("include glob {}: {}", glob, e)))?;
+        }
+        for glob in &options.exclude {
+            globs.add(&format!("!{}", glob)).map_err(|e| ToolError::invalid(format!("exclude glob {}: {}", glob, e)))?;
+        }
+        walk.overrides(globs.build()?);
+    }
+    Ok(walk.build())
+}
+
+/// Search the files under `root` (or `root` itself) for `pattern`,
+/// stopping after `max_results` matches
+pub fn search(root: &Path, pattern: &str, options: &TextOptions, max_results: usize, context: usize) -> Result<TextOutcome> {
+    let regex = compile(pattern, options)?;
+    let mut outcome = TextOutcome::default();
+    for entry in walk(root, options)?.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        if outcome.results.len() >= max_results {
+            break;
+        }
+        if entry.metadata().is_ok_and(|m| m.len() > MAX_FILE_BYTES) {
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(entry.path()) else { continue };
+        if bytes[..bytes.len().min(BINARY_PROBE_BYTES)].contains(&0) {
+            continue;
+        }
+        outcome.files_searched += 1;
+        let content = String::from_utf8_lossy(&bytes);
+        let room = (max_results - outcome.results.len()).min(options.max_per_file.unwrap_or(usize::MAX));
+        let found = search_file(entry.path(), &content, &regex, options.multiline, room, context);
+        if !found.is_empty() {
+            outcome.files_matched += 1;
+            outcome.results.extend(found);
+        }
+    }
+    outcome.truncated = outcome.results.len() >= max_results;
+    Ok(outcome)
+}
+
+/// Up to `limit` matches in one file's text. Line by line unless
+/// `multiline`, where one match may cover several lines; either way a line
+/// is reported once, at its first match
+fn search_file(path: &Path, content: &str, regex: &Regex, multiline: bool, limit: usize, context: usize) -> Vec<SearchResult> {
+    let lines: Vec<&str> = content.lines().collect();
+    // (first line, last line, column) of each match
+    let spans: Vec<(usize, usize, usize)> = if multiline {
+        // Byte offset each line starts at
+        let mut starts = Vec::with_capacity(lines.len());
+        let mut offset = 0;
+        for line in content.split_inclusive('\n') {
+            starts.push(offset);
+            offset += line.len();
+        }
+        let line_of = |at: usize| starts.partition_point(|&start| start <= at).saturating_sub(1);
+        let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+        for found in regex.find_iter(content) {
+            let first = line_of(found.start());
+            // An empty match at the very end is past the last line
+            if first >= lines.len() || spans.len() >= limit {
+                break;
+            }
+            if spans.last().is_some_and(|&(_, last, _)| last >= first) {
+                continue;
+            }
+            let last = line_of(found.end().saturating_sub(1).max(found.start())).min(lines.len() - 1);
+            spans.push((first, last, content[starts[first]..found.start()].chars().count() + 1));
+        }
+        spans
+    } else {
+        lines.iter().enumerate()
+            .filter_map(|(i, line)| regex.find(line).map(|m| (i, i, line[..m.start()].chars().count() + 1)))
+            .take(limit)
+            .collect()
+    };
+
+    spans.into_iter()
+        .map(|(first, last, column)| SearchResult {
+            file_path: path.to_path_buf(),
+            line_number: first + 1,
+            column,
+            match_text: lines[first..=last].join("\n"),
+            context_before: lines[first.saturating_sub(context)..first].iter().map(|l| l.to_string()).collect(),
+            context_after: lines[last + 1..(last + 1 + context).min(lines.len())].iter().map(|l| l.to_string()).collect(),
+            match_type: MatchType::Text,
+            score: 1.0,
+            node_type: None,
+            semantic_context: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_search() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "fn main() {\n    let total = a.b();\n    total\n}\n// Total\n").unwrap();
+        std::fs::write(dir.path().join("notes.md"), "a.b is the total\nsubtotal\n").unwrap();
+        std::fs::write(dir.path().join(".hidden.rs"), "total\n").unwrap();
+        std::fs::write(dir.path().join("blob.bin"), b"total\0").unwrap();
+        let run = |pattern: &str, options: TextOptions| search(dir.path(), pattern, &options, 50, 1).unwrap();
+
+        let all = run("total", TextOptions::default());
+        assert_eq!(all.results.len(), 5);
+        assert_eq!(all.files_searched, 2);
+        let sensitive = run("Total", TextOptions::default());
+        assert_eq!(sensitive.results.len(), 1);
+        assert_eq!(sensitive.results[0].line_number, 5);
+
+        let words = run("total", TextOptions { word: true, ..Default::default() });
+        assert!(words.results.iter().all(|r| !r.match_text.contains("subtotal")));
+        let code = words.results.iter().find(|r| r.file_path.ends_with("lib.rs")).unwrap();
+        assert_eq!((code.line_number, code.column), (2, 9));
+        assert_eq!(code.context_before, ["fn main() {"]);
+
+        let regex = run("a.b", TextOptions { types: vec!["rust".into()], ..Default::default() });
+        assert_eq!(regex.results.len(), 1);
+        let literal = run("a.b", TextOptions { literal: true, include: vec!["*.md".into()], ..Default::default() });
+        assert_eq!(literal.results.len(), 1);
+        assert!(literal.results[0].file_path.ends_with("notes.md"));
+        let excluded = run("total", TextOptions { exclude: vec!["src/**".into()], ..Default::default() });
+        assert!(excluded.results.iter().all(|r| r.file_path.ends_with("notes.md")));
+
+        let spanning = run(r"let total.*\n\s+total", TextOptions { multiline: true, ..Default::default() });
+        assert_eq!(spanning.results[0].match_text, "    let total = a.b();\n    total");
+        assert_eq!(spanning.results[0].context_after, ["}"]);
+        assert_eq!(run("total", TextOptions { hidden: true, max_per_file: Some(1), ..Default::default() }).results.len(), 3);
+
+        let capped = search(dir.path(), "total", &TextOptions::default(), 2, 0).unwrap();
+        assert!(capped.truncated);
+        assert!(search(dir.path(), "(", &TextOptions::default(), 2, 0).is_err());
+        assert!(search(dir.path(), "x", &TextOptions { types: vec!["nope".into()], ..Default::default() }, 2, 0).is_err());
+    }
+}
@@ -1,10 +1,11 @@
 /// Unified search implementation combining multiple search strategies
 
-use super::{SearchConfig, SearchModality, SearchResult, MatchType, detect_modalities, rank_and_deduplicate};
-use crate::search::{ast_search, symbol_search};
-use serde_json::Value;
+use super::{SearchConfig, SearchModality, SearchResult, MatchType, rank_and_deduplicate};
+use super::query::{self, Query};
+use crate::error::ToolError;
+use crate::search::{ast_search, symbol_search, text};
+use ignore::overrides::{Override, OverrideBuilder};
 use std::path::PathBuf;
-use std::process::Command;
 use anyhow::Result;
 
 /// Unified search executor
@@ -19,99 +20,110 @@ impl UnifiedSearch {
     }
 
     /// Execute unified search across all modalities
+    ///
+    /// The query is parsed for modality terms and filters (see `query`);
+    /// modalities set in the config run on its free text in place of the
+    /// ones detected for it
     pub async fn execute(&self) -> Result<Vec<SearchResult>> {
-        // Auto-detect modalities if not specified
-        let modalities = if self.config.modalities.is_empty() {
-            detect_modalities(&self.config.query)
-        } else {
-            self.config.modalities.clone()
-        };
+        let query = query::parse(&self.config.query)?;
+        let mut searches = query.searches();
+        if !self.config.modalities.is_empty() {
+            searches = query.targeted.clone();
+            let text = if query.text.is_empty() && searches.is_empty() { &self.config.query } else { &query.text };
+            if !text.is_empty() {
+                searches.extend(self.config.modalities.iter().map(|m| (*m, text.clone())));
+            }
+        }
+        let config = self.narrowed(&query);
+        let files = self.filter(&config, &query)?;
 
         // Execute searches sequentially (avoids Send bound issues)
         let mut all_results = Vec::new();
 
-        for modality in modalities {
+        for (modality, term) in searches {
             let results = match modality {
-                SearchModality::Text => self.execute_text_search().await?,
-                SearchModality::Ast => self.execute_ast_search().await?,
-                SearchModality::Symbol => self.execute_symbol_search().await?,
+                SearchModality::Text => Self::execute_text_search(&config, &term).await?,
+                SearchModality::Ast => Self::execute_ast_search(&config, &term).await?,
+                SearchModality::Symbol => Self::execute_symbol_search(&config, &term).await?,
                 SearchModality::Vector => self.execute_vector_search().await?,
                 SearchModality::Memory => self.execute_memory_search().await?,
-                SearchModality::File => self.execute_file_search().await?,
+                SearchModality::File => Self::execute_file_search(&config, &term).await?,
             };
-            all_results.extend(results);
+            // Text searches apply the filters as they walk; the rest are
+            // narrowed afterwards
+            all_results.extend(results.into_iter().filter(|r| {
+                modality == SearchModality::Text || files.as_ref().is_none_or(|files| !files.matched(&r.file_path, r.file_path.is_dir()).is_ignore())
+            }));
         }
 
         // Rank and deduplicate
         Ok(rank_and_deduplicate(all_results, self.config.max_results))
     }
 
-    /// Execute text search using ripgrep
-    async fn execute_text_search(&self) -> Result<Vec<SearchResult>> {
-        let path = self.config.path.clone().unwrap_or_else(|| PathBuf::from("."));
-
-        let mut cmd = Command::new("rg");
-        cmd.arg("--json")
-            .arg("--max-count").arg(self.config.max_results.to_string())
-            .arg("-C").arg(self.config.context_lines.to_string())
-            .arg(&self.config.query)
-            .arg(&path);
-
-        if let Some(pattern) = &self.config.file_pattern {
-            cmd.arg("--glob").arg(pattern);
+    /// The config with the query's language, file and case filters added
+    fn narrowed(&self, query: &Query) -> SearchConfig {
+        let mut config = self.config.clone();
+        config.text.include.extend(config.file_pattern.clone());
+        config.text.include.extend(query.include.iter().cloned());
+        config.text.exclude.extend(query.exclude.iter().cloned());
+        config.text.types.extend(query.file_type.clone());
+        if let Some(case) = query.case {
+            config.text.case = case;
         }
+        if query.language.is_some() {
+            config.language = query.language.clone();
+        }
+        config
+    }
 
-        let output = cmd.output()?;
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        let mut results = Vec::new();
-        for line in stdout.lines() {
-            if let Ok(json) = serde_json::from_str::<Value>(line) {
-                if json["type"] == "match" {
-                    let data = &json["data"];
-                    results.push(SearchResult {
-                        file_path: PathBuf::from(data["path"]["text"].as_str().unwrap_or("")),
-                        line_number: data["line_number"].as_u64().unwrap_or(0) as usize,
-                        column: data["submatches"][0]["start"].as_u64().unwrap_or(0) as usize,
-                        match_text: data["lines"]["text"].as_str().unwrap_or("").to_string(),
-                        context_before: vec![],
-                        context_after: vec![],
-                        match_type: MatchType::Text,
-                        score: 1.0,
-                        node_type: None,
-                        semantic_context: None,
-                    });
-                }
-            }
+    /// Matcher for the include and exclude globs, if there are any
+    fn filter(&self, config: &SearchConfig, query: &Query) -> Result<Option<Override>> {
+        if query.include.is_empty() && query.exclude.is_empty() {
+            return Ok(None);
         }
+        let root = config.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        let mut globs = OverrideBuilder::new(root);
+        for glob in &query.include {
+            globs.add(glob).map_err(|e| ToolError::invalid(format!("Bad path: {}: {}", glob, e)))?;
+        }
+        for glob in &query.exclude {
+            globs.add(&format!("!{}", glob)).map_err(|e| ToolError::invalid(format!("Bad path: {}: {}", glob, e)))?;
+        }
+        Ok(Some(globs.build()?))
+    }
 
-        Ok(results)
+    /// Execute text search; file_pattern narrows the files like an
+    /// include glob
+    async fn execute_text_search(config: &SearchConfig, term: &str) -> Result<Vec<SearchResult>> {
+        let path = config.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        let outcome = text::search(&path, term, &config.text, config.max_results, config.context_lines)?;
+        Ok(outcome.results)
     }
 
     /// Execute AST search using tree-sitter
-    async fn execute_ast_search(&self) -> Result<Vec<SearchResult>> {
+    async fn execute_ast_search(config: &SearchConfig, term: &str) -> Result<Vec<SearchResult>> {
         let searcher = ast_search::AstSearcher::new();
-        let path = self.config.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        let path = config.path.clone().unwrap_or_else(|| PathBuf::from("."));
 
         let results = searcher.search(
-            &self.config.query,
+            term,
             &path,
-            self.config.language.as_deref(),
-            self.config.max_results,
+            config.language.as_deref(),
+            config.max_results,
         ).await.unwrap_or_default();
 
         Ok(results)
     }
 
     /// Execute symbol search
-    async fn execute_symbol_search(&self) -> Result<Vec<SearchResult>> {
+    async fn execute_symbol_search(config: &SearchConfig, term: &str) -> Result<Vec<SearchResult>> {
         let searcher = symbol_search::SymbolSearcher::new();
-        let path = self.config.path.clone().unwrap_or_else(|| PathBuf::from("."));
+        let path = config.path.clone().unwrap_or_else(|| PathBuf::from("."));
 
         let results = searcher.search(
-            &self.config.query,
+            term,
             &path,
-            self.config.max_results,
+            config.max_results,
         ).await.unwrap_or_default();
 
         Ok(results)
@@ -130,8 +142,8 @@ impl UnifiedSearch {
     }
 
     /// Execute file search using glob patterns
-    async fn execute_file_search(&self) -> Result<Vec<SearchResult>> {
-        let pattern = format!("**/*{}*", self.config.query);
+    async fn execute_file_search(config: &SearchConfig, term: &str) -> Result<Vec<SearchResult>> {
+        let pattern = format!("**/*{}*", term);
 
         let entries = glob::glob_with(
             &pattern,
@@ -142,7 +154,7 @@ impl UnifiedSearch {
         )?;
 
         let mut results = Vec::new();
-        for entry in entries.flatten().take(self.config.max_results) {
+        for entry in entries.flatten().take(config.max_results) {
             results.push(SearchResult {
                 file_path: entry.clone(),
                 line_number: 0,
@@ -164,6 +176,7 @@ impl UnifiedSearch {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::detect_modalities;
 
     #[tokio::test]
     async fn test_unified_search() {
@@ -175,6 +188,7 @@ mod tests {
             context_lines: 3,
             file_pattern: Some("*.rs".to_string()),
             language: Some("rust".to_string()),
+            ..Default::default()
         };
 
         let search = UnifiedSearch::new(config);
@@ -183,6 +197,36 @@ mod tests {
         assert!(results.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_unified_query() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("tests")).unwrap();
+        std::fs::write(dir.path().join("src/service.rs"), "pub struct UserService;\n").unwrap();
+        std::fs::write(dir.path().join("src/service.py"), "class UserService:\n    pass\n").unwrap();
+        std::fs::write(dir.path().join("tests/service.rs"), "struct UserService;\n").unwrap();
+        let search = |query: &str| {
+            let config = SearchConfig { query: query.to_string(), path: Some(dir.path().to_path_buf()), ..Default::default() };
+            async move { UnifiedSearch::new(config).execute().await.unwrap() }
+        };
+        let files = |results: Vec<SearchResult>| {
+            let mut files: Vec<String> = results.iter()
+                .map(|r| r.file_path.strip_prefix(dir.path()).unwrap().display().to_string())
+                .collect();
+            files.sort();
+            files.dedup();
+            files
+        };
+
+        assert_eq!(files(search("text:UserService").await), ["src/service.py", "src/service.rs", "tests/service.rs"]);
+        assert_eq!(files(search("text:UserService lang:rust -path:tests").await), ["src/service.rs"]);
+        assert_eq!(files(search("text:userservice case:yes").await), Vec::<String>::new());
+        let symbols = search("sym:UserService path:src/ ext:py").await;
+        assert!(symbols.iter().all(|r| matches!(r.match_type, MatchType::Symbol)));
+        assert_eq!(files(symbols), ["src/service.py"]);
+        assert!(UnifiedSearch::new(SearchConfig { query: "case:maybe".into(), ..Default::default() }).execute().await.is_err());
+    }
+
     #[test]
     fn test_detect_modalities() {
         // Natural language query
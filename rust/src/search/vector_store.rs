@@ -1,191 +1,699 @@
-/// Vector store implementation (stub - LanceDB temporarily disabled)
+/// Vector store shared by the `vector` tool and anything else that needs
+/// similarity search
 ///
-/// This module provides the interface for vector embeddings and similarity search.
-/// The LanceDB backend is temporarily disabled due to arrow version conflicts.
-/// When re-enabled, this will support:
-/// - Document storage with embeddings
-/// - Symbol indexing for code search
-/// - Memory/knowledge base storage
-/// - Semantic similarity search
+/// Collections hold records (an id, optional text, JSON metadata and an
+/// embedding) and persist as one JSON file each under the store directory,
+/// rewritten atomically on every change. Queries scan the collection and
+/// rank exactly, which stays quick into the tens of thousands of records
+/// and never misses a neighbour the way an approximate index can.
+///
+/// Embeddings are either supplied by the caller or computed from text by
+/// the store's `Embedder`: an OpenAI-compatible `/embeddings` endpoint
+/// when one is configured, else a local hashing embedder that matches
+/// shared words rather than meaning. A collection remembers which
+/// embedder filled it so vectors from different models never mix.
 
+use crate::config::{self, EmbeddingConfig};
+use crate::error::ToolError;
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
 
-/// Document structure for vector store
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Document {
-    pub id: String,
-    pub content: String,
-    pub metadata: serde_json::Value,
-    pub embedding: Vec<f32>,
-    pub score: f32,
+/// Dimensions of the local hashing embedder
+pub const HASH_DIMENSIONS: usize = 256;
+
+/// Longest an embeddings request may take
+const EMBED_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Texts sent to an embeddings endpoint per request
+const EMBED_BATCH: usize = 64;
+
+pub fn default_vector_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hanzo-mcp")
+        .join("vectors")
 }
 
-/// Symbol structure for code symbols
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Symbol {
-    pub name: String,
-    pub symbol_type: String,
-    pub file_path: String,
-    pub line_number: usize,
-    pub signature: Option<String>,
-    pub docstring: Option<String>,
-    pub embedding: Vec<f32>,
+/// How closeness is scored; higher is always closer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    #[default]
+    Cosine,
+    Dot,
+    /// Scored as 1 / (1 + distance)
+    Euclidean,
+}
+
+impl std::str::FromStr for Metric {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cosine" | "cos" => Ok(Self::Cosine),
+            "dot" | "inner_product" | "ip" => Ok(Self::Dot),
+            "euclidean" | "l2" => Ok(Self::Euclidean),
+            _ => Err(ToolError::invalid(format!("Unknown metric: {} (cosine, dot, euclidean)", s)).into()),
+        }
+    }
+}
+
+impl Metric {
+    pub fn score(&self, a: &[f32], b: &[f32]) -> f32 {
+        match self {
+            Self::Cosine => cosine_similarity(a, b),
+            Self::Dot => a.iter().zip(b).map(|(x, y)| x * y).sum(),
+            Self::Euclidean => {
+                let distance: f32 = a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum::<f32>().sqrt();
+                1.0 / (1.0 + distance)
+            }
+        }
+    }
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
+
+/// Turns text into vectors
+#[derive(Debug, Clone)]
+pub enum Embedder {
+    /// Feature hashing of lowercased words: offline and instant, but only
+    /// finds texts that share vocabulary
+    Hashing { dimensions: usize },
+    /// An OpenAI-compatible embeddings endpoint (OpenAI, Ollama, vLLM, ...)
+    Http { url: String, model: Option<String>, api_key: Option<String>, dimensions: Option<usize> },
+}
+
+impl Default for Embedder {
+    fn default() -> Self {
+        Self::Hashing { dimensions: HASH_DIMENSIONS }
+    }
+}
+
+impl Embedder {
+    pub fn from_config(config: Option<&EmbeddingConfig>) -> Self {
+        match config {
+            Some(config) => Self::Http {
+                url: config.url.clone(),
+                model: config.model.clone(),
+                api_key: config::secret(&config.api_key, &config.api_key_env),
+                dimensions: config.dimensions,
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Recorded on collections to keep vectors from different models apart
+    pub fn name(&self) -> String {
+        match self {
+            Self::Hashing { dimensions } => format!("hash-{}", dimensions),
+            Self::Http { url, model: Some(model), .. } => format!("{} ({})", model, url),
+            Self::Http { url, model: None, .. } => url.clone(),
+        }
+    }
+
+    pub async fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        match self {
+            Self::Hashing { dimensions } => Ok(texts.iter().map(|t| hash_embedding(t, *dimensions)).collect()),
+            Self::Http { url, model, api_key, dimensions } => {
+                let client = reqwest::Client::builder().timeout(EMBED_TIMEOUT).build()?;
+                let mut vectors = Vec::with_capacity(texts.len());
+                for batch in texts.chunks(EMBED_BATCH) {
+                    let mut body = json!({ "input": batch });
+                    if let Some(model) = model {
+                        body["model"] = json!(model);
+                    }
+                    if let Some(dimensions) = dimensions {
+                        body["dimensions"] = json!(dimensions);
+                    }
+                    let mut request = client.post(url).json(&body);
+                    if let Some(key) = api_key {
+                        request = request.bearer_auth(key);
+                    }
+                    let response = request.send().await
+                        .map_err(|e| ToolError::external(format!("Embeddings request to {} failed: {}", url, e)))?;
+                    let status = response.status();
+                    let reply: Value = response.json().await.unwrap_or(Value::Null);
+                    if !status.is_success() {
+                        let message = reply["error"]["message"].as_str().or(reply["error"].as_str()).unwrap_or("");
+                        return Err(ToolError::external(format!("Embeddings endpoint returned {}: {}", status, message)).into());
+                    }
+                    vectors.extend(parse_embeddings(&reply, batch.len())?);
+                }
+                Ok(vectors)
+            }
+        }
+    }
+}
+
+/// Vectors from an OpenAI-style `{"data": [{"index", "embedding"}]}` reply
+fn parse_embeddings(reply: &Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+    let mut data: Vec<&Value> = reply["data"].as_array().into_iter().flatten().collect();
+    data.sort_by_key(|d| d["index"].as_u64().unwrap_or(0));
+    let vectors: Vec<Vec<f32>> = data.iter()
+        .filter_map(|d| d["embedding"].as_array())
+        .map(|v| v.iter().filter_map(Value::as_f64).map(|x| x as f32).collect())
+        .collect();
+    if vectors.len() != expected {
+        return Err(ToolError::external(format!("Embeddings endpoint returned {} vectors for {} texts", vectors.len(), expected)).into());
+    }
+    Ok(vectors)
+}
+
+/// Words hashed into signed buckets, scaled to unit length
+fn hash_embedding(text: &str, dimensions: usize) -> Vec<f32> {
+    let mut vector = vec![0.0f32; dimensions.max(1)];
+    let lower = text.to_lowercase();
+    for word in lower.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        // FNV-1a, stable across platforms and releases, then mixed so the
+        // low bits that pick the bucket depend on every byte
+        let mut hash = word.bytes().fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3));
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+        hash ^= hash >> 31;
+        let bucket = (hash % vector.len() as u64) as usize;
+        vector[bucket] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    }
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+    vector
 }
 
-/// Memory structure for conversation and knowledge
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Memory {
+pub struct Record {
     pub id: String,
-    pub memory_type: String,
-    pub content: String,
-    pub timestamp: i64,
-    pub metadata: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub metadata: Value,
     pub embedding: Vec<f32>,
+    pub updated_at: String,
 }
 
-/// Vector store configuration
-#[derive(Debug, Clone)]
-pub struct VectorStoreConfig {
-    pub data_dir: PathBuf,
-    pub embedding_model: String,
+impl Record {
+    /// The record as returned to callers, embedding left out unless asked for
+    pub fn to_json(&self, embedding: bool) -> Value {
+        let mut value = json!({
+            "id": self.id,
+            "content": self.content,
+            "metadata": self.metadata,
+            "updated_at": self.updated_at
+        });
+        if embedding {
+            value["embedding"] = json!(self.embedding);
+        }
+        value
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub name: String,
     pub dimensions: usize,
-    pub index_name: String,
+    pub metric: Metric,
+    /// Embedder that turned this collection's text into vectors, if any did
+    #[serde(default)]
+    pub embedder: Option<String>,
+    pub created_at: String,
+    pub records: BTreeMap<String, Record>,
 }
 
-impl Default for VectorStoreConfig {
-    fn default() -> Self {
-        Self {
-            data_dir: dirs::home_dir()
-                .unwrap_or_else(|| PathBuf::from("."))
-                .join(".hanzo")
-                .join("lancedb"),
-            embedding_model: "all-MiniLM-L6-v2".to_string(),
-            dimensions: 384,
-            index_name: "default".to_string(),
-        }
+impl Collection {
+    pub fn summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "count": self.records.len(),
+            "dimensions": self.dimensions,
+            "metric": self.metric,
+            "embedder": self.embedder,
+            "created_at": self.created_at
+        })
     }
 }
 
-/// Vector store (stub implementation - LanceDB disabled)
+/// A record to insert or replace; the embedding is computed from `content`
+/// when not given
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewRecord {
+    pub id: Option<String>,
+    #[serde(alias = "text")]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub metadata: Option<Value>,
+    #[serde(alias = "vector")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// A similarity query: by vector, or by text the store embeds
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub vector: Option<Vec<f32>>,
+    pub text: Option<String>,
+    pub filter: Option<Value>,
+    pub limit: usize,
+    pub min_score: Option<f32>,
+    pub include_embeddings: bool,
+}
+
 pub struct VectorStore {
-    config: VectorStoreConfig,
-    // In-memory store for basic functionality
-    documents: HashMap<String, Document>,
-    symbols: HashMap<String, Symbol>,
-    memories: HashMap<String, Memory>,
+    dir: PathBuf,
+    embedder: Embedder,
+    /// Collections read so far; files are the source of truth
+    collections: Mutex<HashMap<String, Collection>>,
 }
 
 impl VectorStore {
-    /// Create new vector store
-    pub async fn new(config: Option<VectorStoreConfig>) -> Result<Self, Box<dyn std::error::Error>> {
-        let config = config.unwrap_or_default();
-
-        Ok(Self {
-            config,
-            documents: HashMap::new(),
-            symbols: HashMap::new(),
-            memories: HashMap::new(),
-        })
+    pub fn open(dir: PathBuf, embedder: Embedder) -> Self {
+        Self { dir, embedder, collections: Mutex::new(HashMap::new()) }
     }
 
-    /// Initialize tables (no-op in stub)
-    pub async fn initialize(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        // LanceDB initialization disabled
-        Ok(())
+    pub fn dir(&self) -> &Path {
+        &self.dir
     }
 
-    /// Add document to vector store
-    pub async fn add_document(&mut self, doc: Document) -> Result<(), Box<dyn std::error::Error>> {
-        self.documents.insert(doc.id.clone(), doc);
-        Ok(())
+    pub fn embedder(&self) -> &Embedder {
+        &self.embedder
     }
 
-    /// Add symbol to vector store
-    pub async fn add_symbol(&mut self, symbol: Symbol) -> Result<(), Box<dyn std::error::Error>> {
-        self.symbols.insert(symbol.name.clone(), symbol);
-        Ok(())
+    /// Kept for callers of the old stub; see the free `cosine_similarity`
+    pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+        cosine_similarity(a, b)
     }
 
-    /// Add memory to vector store
-    pub async fn add_memory(&mut self, memory: Memory) -> Result<(), Box<dyn std::error::Error>> {
-        self.memories.insert(memory.id.clone(), memory);
-        Ok(())
+    fn file(&self, name: &str) -> Result<PathBuf> {
+        let valid = !name.is_empty() && name.len() <= 128
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !name.starts_with('.');
+        if !valid {
+            return Err(ToolError::invalid(format!("Invalid collection name: {:?} (letters, digits, '-', '_' and '.')", name)).into());
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    /// Load `name` into `cache` if it is on disk; whether it exists
+    async fn load(&self, cache: &mut HashMap<String, Collection>, name: &str) -> Result<bool> {
+        if cache.contains_key(name) {
+            return Ok(true);
+        }
+        let path = self.file(name)?;
+        let text = match tokio::fs::read_to_string(&path).await {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        let collection: Collection = serde_json::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("Corrupt collection {}: {}", path.display(), e))?;
+        cache.insert(name.to_string(), collection);
+        Ok(true)
     }
 
-    /// Search for similar documents (stub - returns empty)
-    pub async fn search(
-        &self,
-        _query: &str,
-        _table: &str,
-        _limit: usize,
-        _threshold: f32,
-    ) -> Result<Vec<Document>, Box<dyn std::error::Error>> {
-        // Vector search disabled - would use embeddings
-        Ok(vec![])
-    }
-
-    /// Search for similar documents
-    pub async fn search_documents(
-        &self,
-        _query: &str,
-        _limit: usize,
-        _threshold: f32,
-    ) -> Result<Vec<Document>, Box<dyn std::error::Error>> {
-        // Vector search disabled - would use embeddings
-        Ok(vec![])
-    }
-
-    /// Search for similar symbols
-    pub async fn search_symbols(
-        &self,
-        _query: &str,
-        _symbol_type: Option<&str>,
-        _limit: usize,
-    ) -> Result<Vec<Symbol>, Box<dyn std::error::Error>> {
-        // Vector search disabled - would use embeddings
-        Ok(vec![])
-    }
-
-    /// Search for similar memories
-    pub async fn search_memories(
-        &self,
-        _query: &str,
-        _memory_type: Option<&str>,
-        _limit: usize,
-    ) -> Result<Vec<Memory>, Box<dyn std::error::Error>> {
-        // Vector search disabled - would use embeddings
-        Ok(vec![])
-    }
-
-    /// Generate embedding for text (stub - returns zero vector)
-    pub async fn generate_embedding(&self, _text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
-        // This would use sentence-transformers or OpenAI API
-        Ok(vec![0.0; self.config.dimensions])
-    }
-
-    /// Index codebase (stub - no-op)
-    pub async fn index_codebase(&mut self, _directory: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        // Codebase indexing disabled without vector store
+    /// Write through a temporary file so readers never see half a collection
+    async fn save(&self, collection: &Collection) -> Result<()> {
+        let path = self.file(&collection.name)?;
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let temp = path.with_extension("json.tmp");
+        tokio::fs::write(&temp, serde_json::to_vec(collection)?).await?;
+        tokio::fs::rename(&temp, &path).await?;
         Ok(())
     }
 
-    /// Calculate cosine similarity
-    pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-        if a.len() != b.len() {
-            return 0.0;
+    pub async fn create(&self, name: &str, dimensions: Option<usize>, metric: Metric) -> Result<Value> {
+        let mut cache = self.collections.lock().await;
+        if self.load(&mut cache, name).await? {
+            return Err(ToolError::conflict(format!("Collection already exists: {}", name)).into());
         }
+        let collection = Collection {
+            name: name.to_string(),
+            dimensions: dimensions.unwrap_or(0),
+            metric,
+            embedder: None,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            records: BTreeMap::new(),
+        };
+        self.save(&collection).await?;
+        let summary = collection.summary();
+        cache.insert(name.to_string(), collection);
+        Ok(summary)
+    }
 
-        let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    /// Every collection on disk, by name
+    pub async fn collections(&self) -> Result<Vec<Value>> {
+        let mut names = Vec::new();
+        match tokio::fs::read_dir(&self.dir).await {
+            Ok(mut entries) => {
+                while let Some(entry) = entries.next_entry().await? {
+                    let path = entry.path();
+                    if path.extension().is_some_and(|e| e == "json") {
+                        names.extend(path.file_stem().map(|s| s.to_string_lossy().into_owned()));
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        names.sort();
+        let mut cache = self.collections.lock().await;
+        let mut summaries = Vec::new();
+        for name in names {
+            match self.load(&mut cache, &name).await {
+                Ok(true) => summaries.push(cache[&name].summary()),
+                Ok(false) => {}
+                Err(e) => log::warn!("Skipping vector collection {}: {}", name, e),
+            }
+        }
+        Ok(summaries)
+    }
+
+    pub async fn info(&self, name: &str) -> Result<Value> {
+        let mut cache = self.collections.lock().await;
+        if !self.load(&mut cache, name).await? {
+            return Err(not_found(name));
+        }
+        let mut summary = cache[name].summary();
+        summary["path"] = json!(self.file(name)?);
+        Ok(summary)
+    }
+
+    pub async fn drop_collection(&self, name: &str) -> Result<bool> {
+        let mut cache = self.collections.lock().await;
+        cache.remove(name);
+        match tokio::fs::remove_file(self.file(name)?).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Insert or replace records, creating the collection on first use
+    pub async fn upsert(&self, name: &str, records: Vec<NewRecord>) -> Result<Value> {
+        if records.is_empty() {
+            return Err(ToolError::invalid("No documents to upsert").into());
+        }
+        // Embed outside the lock: an endpoint may take seconds
+        let texts: Vec<&str> = records.iter()
+            .filter(|r| r.embedding.is_none())
+            .map(|r| r.content.as_deref().ok_or_else(|| ToolError::invalid("Each document needs content or an embedding")))
+            .collect::<std::result::Result<_, _>>()?;
+        let mut computed = if texts.is_empty() { Vec::new() } else { self.embedder.embed(&texts).await? }.into_iter();
+        let embedded = computed.len() > 0;
+
+        let mut cache = self.collections.lock().await;
+        if !self.load(&mut cache, name).await? {
+            cache.insert(name.to_string(), Collection {
+                name: name.to_string(),
+                dimensions: 0,
+                metric: Metric::default(),
+                embedder: None,
+                created_at: chrono::Utc::now().to_rfc3339(),
+                records: BTreeMap::new(),
+            });
+        }
+        let mut collection = cache[name].clone();
+        if embedded {
+            let embedder = self.embedder.name();
+            match &collection.embedder {
+                Some(used) if *used != embedder => {
+                    return Err(ToolError::conflict(format!(
+                        "Collection {} was embedded with {}, but the store now embeds with {}; pass embeddings or use another collection",
+                        name, used, embedder
+                    )).into());
+                }
+                _ => collection.embedder = Some(embedder),
+            }
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let (mut inserted, mut updated) = (0, 0);
+        let mut ids = Vec::with_capacity(records.len());
+        for record in records {
+            let embedding = match record.embedding {
+                Some(embedding) => embedding,
+                None => computed.next().expect("one embedding per text"),
+            };
+            if embedding.is_empty() {
+                return Err(ToolError::invalid("Embeddings must not be empty").into());
+            }
+            if collection.dimensions == 0 {
+                collection.dimensions = embedding.len();
+            } else if embedding.len() != collection.dimensions {
+                return Err(ToolError::invalid(format!(
+                    "Collection {} holds {}-dimensional vectors, got {}", name, collection.dimensions, embedding.len()
+                )).into());
+            }
+            let id = match record.id {
+                Some(id) => id,
+                // The same text always lands on the same record
+                None => content_id(record.content.as_deref().unwrap_or_default(), &embedding),
+            };
+            let new = Record {
+                id: id.clone(),
+                content: record.content,
+                metadata: record.metadata.unwrap_or_else(|| json!({})),
+                embedding,
+                updated_at: now.clone(),
+            };
+            if collection.records.insert(id.clone(), new).is_some() {
+                updated += 1;
+            } else {
+                inserted += 1;
+            }
+            ids.push(id);
+        }
+        self.save(&collection).await?;
+        let count = collection.records.len();
+        cache.insert(name.to_string(), collection);
+        Ok(json!({ "collection": name, "ids": ids, "inserted": inserted, "updated": updated, "count": count }))
+    }
+
+    /// Records closest to the query, best first
+    pub async fn query(&self, name: &str, query: Query) -> Result<Vec<Value>> {
+        let vector = match (query.vector, &query.text) {
+            (Some(vector), _) => vector,
+            (None, Some(text)) => {
+                {
+                    let mut cache = self.collections.lock().await;
+                    if !self.load(&mut cache, name).await? {
+                        return Err(not_found(name));
+                    }
+                    let embedder = self.embedder.name();
+                    if let Some(used) = cache[name].embedder.as_ref().filter(|used| **used != embedder) {
+                        return Err(ToolError::conflict(format!(
+                            "Collection {} was embedded with {}, but the store now embeds with {}; query with a vector", name, used, embedder
+                        )).into());
+                    }
+                }
+                self.embedder.embed(&[text]).await?.remove(0)
+            }
+            (None, None) => return Err(ToolError::invalid("query needs text or a vector").into()),
+        };
+
+        let mut cache = self.collections.lock().await;
+        if !self.load(&mut cache, name).await? {
+            return Err(not_found(name));
+        }
+        let collection = &cache[name];
+        if collection.dimensions != 0 && vector.len() != collection.dimensions {
+            return Err(ToolError::invalid(format!(
+                "Collection {} holds {}-dimensional vectors, the query has {}", name, collection.dimensions, vector.len()
+            )).into());
+        }
+        let mut scored = Vec::new();
+        for record in collection.records.values() {
+            if let Some(filter) = &query.filter {
+                if !matches_filter(filter, &record.metadata)? {
+                    continue;
+                }
+            }
+            let score = collection.metric.score(&vector, &record.embedding);
+            if query.min_score.is_some_and(|min| score < min) {
+                continue;
+            }
+            scored.push((score, record));
+        }
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        Ok(scored.into_iter()
+            .take(query.limit)
+            .map(|(score, record)| {
+                let mut value = record.to_json(query.include_embeddings);
+                value["score"] = json!(score);
+                value
+            })
+            .collect())
+    }
+
+    /// Records by id, or every record matching `filter`
+    pub async fn get(&self, name: &str, ids: Option<&[String]>, filter: Option<&Value>, limit: usize, embeddings: bool) -> Result<Vec<Value>> {
+        let mut cache = self.collections.lock().await;
+        if !self.load(&mut cache, name).await? {
+            return Err(not_found(name));
+        }
+        let collection = &cache[name];
+        let records: Vec<&Record> = match ids {
+            Some(ids) => ids.iter().filter_map(|id| collection.records.get(id)).collect(),
+            None => collection.records.values().collect(),
+        };
+        let mut found = Vec::new();
+        for record in records {
+            if let Some(filter) = filter {
+                if !matches_filter(filter, &record.metadata)? {
+                    continue;
+                }
+            }
+            if found.len() == limit {
+                break;
+            }
+            found.push(record.to_json(embeddings));
+        }
+        Ok(found)
+    }
+
+    /// Remove records by id and/or filter; how many went
+    pub async fn delete(&self, name: &str, ids: Option<&[String]>, filter: Option<&Value>) -> Result<usize> {
+        if ids.is_none() && filter.is_none() {
+            return Err(ToolError::invalid("delete needs ids or a filter; drop removes a whole collection").into());
+        }
+        let mut cache = self.collections.lock().await;
+        if !self.load(&mut cache, name).await? {
+            return Err(not_found(name));
+        }
+        let mut collection = cache[name].clone();
+        let mut doomed = Vec::new();
+        for record in collection.records.values() {
+            let named = ids.is_none_or(|ids| ids.contains(&record.id));
+            let matched = match filter {
+                Some(filter) => matches_filter(filter, &record.metadata)?,
+                None => true,
+            };
+            if named && matched {
+                doomed.push(record.id.clone());
+            }
+        }
+        for id in &doomed {
+            collection.records.remove(id);
+        }
+        if !doomed.is_empty() {
+            self.save(&collection).await?;
+            cache.insert(name.to_string(), collection);
+        }
+        Ok(doomed.len())
+    }
+}
+
+fn not_found(name: &str) -> anyhow::Error {
+    ToolError::not_found(format!("No such collection: {}", name)).into()
+}
+
+/// Stable id for a record given without one
+fn content_id(content: &str, embedding: &[f32]) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    if content.is_empty() {
+        embedding.iter().for_each(|x| hasher.update(x.to_le_bytes()));
+    } else {
+        hasher.update(content.as_bytes());
+    }
+    hasher.finalize().iter().take(8).map(|b| format!("{:02x}", b)).collect()
+}
 
-        if norm_a == 0.0 || norm_b == 0.0 {
-            return 0.0;
+/// Whether `metadata` satisfies a filter such as
+/// `{"lang": "rust", "stars": {"$gte": 10}, "$or": [{"tag": "a"}, {"tag": "b"}]}`
+///
+/// Plain values match equal fields, or arrays containing them. Operators:
+/// `$eq`, `$ne`, `$gt`, `$gte`, `$lt`, `$lte`, `$in`, `$nin`, `$exists`,
+/// `$contains`; `$and`, `$or` and `$not` combine filters. Dotted keys
+/// reach into nested objects.
+pub fn matches_filter(filter: &Value, metadata: &Value) -> Result<bool> {
+    let Some(conditions) = filter.as_object() else {
+        return Err(ToolError::invalid("filter must be an object").into());
+    };
+    for (key, condition) in conditions {
+        let matched = match key.as_str() {
+            "$and" => clauses(condition)?.iter().try_fold(true, |all, f| Ok::<_, anyhow::Error>(all && matches_filter(f, metadata)?))?,
+            "$or" => clauses(condition)?.iter().try_fold(false, |any, f| Ok::<_, anyhow::Error>(any || matches_filter(f, metadata)?))?,
+            "$not" => !matches_filter(condition, metadata)?,
+            op if op.starts_with('$') => return Err(ToolError::invalid(format!("Unknown filter operator: {}", op)).into()),
+            field => matches_field(lookup(metadata, field), condition)?,
+        };
+        if !matched {
+            return Ok(false);
         }
+    }
+    Ok(true)
+}
+
+fn clauses(value: &Value) -> Result<&Vec<Value>> {
+    value.as_array().ok_or_else(|| ToolError::invalid("$and and $or take a list of filters").into())
+}
+
+fn lookup<'a>(metadata: &'a Value, field: &str) -> Option<&'a Value> {
+    field.split('.').try_fold(metadata, |value, key| value.get(key))
+}
 
-        dot_product / (norm_a * norm_b)
+fn matches_field(value: Option<&Value>, condition: &Value) -> Result<bool> {
+    let operators = condition.as_object().filter(|o| o.keys().all(|k| k.starts_with('$')) && !o.is_empty());
+    let Some(operators) = operators else {
+        return Ok(value.is_some_and(|v| equals(v, condition)));
+    };
+    for (op, operand) in operators {
+        let matched = match op.as_str() {
+            "$eq" => value.is_some_and(|v| equals(v, operand)),
+            "$ne" => !value.is_some_and(|v| equals(v, operand)),
+            "$gt" => compare(value, operand).is_some_and(|o| o.is_gt()),
+            "$gte" => compare(value, operand).is_some_and(|o| o.is_ge()),
+            "$lt" => compare(value, operand).is_some_and(|o| o.is_lt()),
+            "$lte" => compare(value, operand).is_some_and(|o| o.is_le()),
+            "$in" | "$nin" => {
+                let options = operand.as_array().ok_or_else(|| ToolError::invalid(format!("{} takes a list", op)))?;
+                let found = value.is_some_and(|v| options.iter().any(|o| equals(v, o)));
+                found == (op == "$in")
+            }
+            "$exists" => value.is_some_and(|v| !v.is_null()) == operand.as_bool().unwrap_or(true),
+            "$contains" => match (value, operand.as_str()) {
+                (Some(Value::String(s)), Some(needle)) => s.to_lowercase().contains(&needle.to_lowercase()),
+                (Some(Value::Array(items)), _) => items.contains(operand),
+                _ => false,
+            },
+            _ => return Err(ToolError::invalid(format!("Unknown filter operator: {}", op)).into()),
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Equal values, numbers by value, or an array holding the wanted value
+fn equals(value: &Value, wanted: &Value) -> bool {
+    match (value, wanted) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(items), wanted) if !wanted.is_array() => items.iter().any(|item| equals(item, wanted)),
+        _ => value == wanted,
+    }
+}
+
+/// Numbers by value and strings lexically, which orders RFC 3339 times
+fn compare(value: Option<&Value>, operand: &Value) -> Option<std::cmp::Ordering> {
+    match (value?, operand) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.as_str().cmp(b.as_str())),
+        _ => None,
     }
 }
 
@@ -193,19 +701,89 @@ impl VectorStore {
 mod tests {
     use super::*;
 
-    #[tokio::test]
-    async fn test_vector_store() {
-        let store = VectorStore::new(None).await;
-        assert!(store.is_ok());
-    }
-
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0, 0.0, 0.0];
         let b = vec![1.0, 0.0, 0.0];
-        assert_eq!(VectorStore::cosine_similarity(&a, &b), 1.0);
+        assert_eq!(cosine_similarity(&a, &b), 1.0);
 
         let c = vec![0.0, 1.0, 0.0];
-        assert_eq!(VectorStore::cosine_similarity(&a, &c), 0.0);
+        assert_eq!(cosine_similarity(&a, &c), 0.0);
+        assert_eq!(VectorStore::cosine_similarity(&a, &b), 1.0);
+        assert_eq!(Metric::Euclidean.score(&a, &a), 1.0);
+        assert_eq!(Metric::Dot.score(&[2.0, 1.0], &[3.0, 4.0]), 10.0);
+    }
+
+    #[test]
+    fn test_matches_filter() {
+        let metadata = json!({ "lang": "rust", "stars": 12, "tags": ["cli", "mcp"], "repo": { "owner": "hanzo" } });
+        let yes = |f: Value| matches_filter(&f, &metadata).unwrap();
+        assert!(yes(json!({ "lang": "rust", "tags": "mcp" })));
+        assert!(yes(json!({ "stars": { "$gte": 10, "$lt": 13 } })));
+        assert!(yes(json!({ "repo.owner": { "$in": ["hanzo", "x"] } })));
+        assert!(yes(json!({ "$or": [{ "lang": "go" }, { "stars": 12.0 }] })));
+        assert!(yes(json!({ "missing": { "$exists": false }, "$not": { "lang": "go" } })));
+        assert!(!yes(json!({ "lang": { "$ne": "rust" } })));
+        assert!(!yes(json!({ "stars": { "$gt": "10" } })));
+        assert!(matches_filter(&json!({ "stars": { "$near": 1 } }), &metadata).is_err());
+        assert!(matches_filter(&json!(["lang"]), &metadata).is_err());
+    }
+
+    #[test]
+    fn test_hash_embedding() {
+        let parse = hash_embedding("Parse the config file", 64);
+        let config = hash_embedding("config file parser", 64);
+        let other = hash_embedding("render a chart", 64);
+        assert_eq!(parse.len(), 64);
+        assert!(cosine_similarity(&parse, &config) > cosine_similarity(&parse, &other));
+        assert_eq!(hash_embedding("", 8), vec![0.0; 8]);
+        let reply = json!({ "data": [{ "index": 1, "embedding": [0.5] }, { "index": 0, "embedding": [0.25] }] });
+        assert_eq!(parse_embeddings(&reply, 2).unwrap(), vec![vec![0.25], vec![0.5]]);
+        assert!(parse_embeddings(&reply, 3).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_vector_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = VectorStore::open(dir.path().to_path_buf(), Embedder::default());
+        assert_eq!(store.dir(), dir.path());
+        assert!(store.collections().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = VectorStore::open(dir.path().to_path_buf(), Embedder::default());
+        let docs = vec![
+            NewRecord { id: Some("a".into()), content: Some("rust config parser".into()), metadata: Some(json!({ "lang": "rust" })), ..Default::default() },
+            NewRecord { content: Some("python chart rendering".into()), metadata: Some(json!({ "lang": "python" })), ..Default::default() },
+        ];
+        let upserted = store.upsert("code", docs).await.unwrap();
+        assert_eq!(upserted["inserted"], 2);
+        assert!(store.upsert("code", vec![NewRecord { embedding: Some(vec![1.0]), ..Default::default() }]).await.is_err());
+        assert!(store.create("code", None, Metric::Cosine).await.is_err());
+        assert!(store.upsert("../x", vec![NewRecord { content: Some("x".into()), ..Default::default() }]).await.is_err());
+
+        // A fresh store reads the collection back from disk
+        let store = VectorStore::open(dir.path().to_path_buf(), Embedder::default());
+        let query = Query { text: Some("parse the config".into()), limit: 5, ..Default::default() };
+        let hits = store.query("code", query.clone()).await.unwrap();
+        assert_eq!(hits[0]["id"], "a");
+        assert!(hits[0]["score"].as_f64().unwrap() > hits[1]["score"].as_f64().unwrap());
+        let filtered = store.query("code", Query { filter: Some(json!({ "lang": "python" })), ..query }).await.unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0]["metadata"]["lang"], "python");
+
+        assert_eq!(store.delete("code", None, Some(&json!({ "lang": "python" }))).await.unwrap(), 1);
+        assert_eq!(store.get("code", None, None, 10, false).await.unwrap().len(), 1);
+        let summaries = store.collections().await.unwrap();
+        assert_eq!(summaries[0]["name"], "code");
+        assert_eq!(summaries[0]["embedder"], "hash-256");
+
+        // Another embedder may not add text to the collection
+        let other = VectorStore::open(dir.path().to_path_buf(), Embedder::Hashing { dimensions: 32 });
+        assert!(other.upsert("code", vec![NewRecord { content: Some("x".into()), ..Default::default() }]).await.is_err());
+        assert!(store.drop_collection("code").await.unwrap());
+        assert!(store.query("code", Query { vector: Some(vec![0.0; 256]), limit: 1, ..Default::default() }).await.is_err());
     }
 }
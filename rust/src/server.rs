@@ -1,36 +1,391 @@
-use crate::{Config, ToolRegistry};
+use crate::gateway::Gateway;
+use crate::error::ToolError;
+use crate::{events, logging, sampling, Config, ToolRegistry};
 use anyhow::Result;
-use jsonrpc_core::{IoHandler, Params};
-use jsonrpc_http_server::ServerBuilder;
-use log::{debug, info, error};
-use serde_json::json;
+use jsonrpc_core::{MetaIoHandler, Params};
+use jsonrpc_http_server::hyper::{self, Body, Method};
+use jsonrpc_http_server::{RequestMiddlewareAction, ServerBuilder};
+use log::{debug, info, error, warn};
+use serde_json::{json, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, oneshot, Mutex, RwLock};
+
+/// Header carrying the MCP session id on every request after initialize
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Sessions idle for longer than this are ended on the next tool call
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Largest request body read, for JSON-RPC and the REST gateway alike
+const MAX_REQUEST_BYTES: usize = 5 * 1024 * 1024;
+
+/// Longest a tool waits for the client to answer a sampling request
+const SAMPLING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How often the scheduler is checked for due calls
+const SCHEDULE_TICK: Duration = Duration::from_secs(5);
+
+/// Per-request metadata extracted from the HTTP request
+#[derive(Clone, Debug, Default)]
+pub struct RequestMeta {
+    pub session_id: Option<String>,
+}
+
+impl jsonrpc_core::Metadata for RequestMeta {}
+
+/// Live MCP sessions, when each was last seen, what its client sent to
+/// initialize and what it has yet to be sent
+#[derive(Default)]
+struct Sessions {
+    last_seen: HashMap<String, Instant>,
+    clients: HashMap<String, Value>,
+    outboxes: HashMap<String, Outbox>,
+}
+
+/// A session's own view of the notifications tools emit, so one client
+/// polling or subscribing leaves the others' deliveries alone
+struct Outbox {
+    session: String,
+    notifications: broadcast::Receiver<Value>,
+    /// Resource URIs whose updates the client subscribed to
+    subscriptions: HashSet<String>,
+    /// Least severe log message the client is sent (`logging/setLevel`)
+    level: logging::McpLevel,
+}
+
+impl Outbox {
+    /// Notifications emitted since the last poll that are for this client
+    fn drain(&mut self) -> Vec<Value> {
+        let mut pending = Vec::new();
+        loop {
+            match self.notifications.try_recv() {
+                Ok(message) => {
+                    if let Some(message) = deliverable(message, &self.session, &self.subscriptions, self.level) {
+                        pending.push(message);
+                    }
+                }
+                Err(broadcast::error::TryRecvError::Lagged(skipped)) => {
+                    debug!("Dropped {} notifications", skipped);
+                }
+                Err(_) => return pending,
+            }
+        }
+    }
+}
+
+impl Sessions {
+    /// Start a session the server issued, receiving notifications from now
+    fn start(&mut self, id: &str, client: Value, notifications: broadcast::Receiver<Value>) {
+        self.last_seen.insert(id.to_string(), Instant::now());
+        self.clients.insert(id.to_string(), client);
+        self.outboxes.insert(id.to_string(), Outbox {
+            session: id.to_string(),
+            notifications,
+            subscriptions: HashSet::new(),
+            level: logging::McpLevel::Warning,
+        });
+    }
+
+    /// The most verbose log level any live session asked for
+    fn log_level(&self) -> logging::McpLevel {
+        self.outboxes.values().map(|outbox| outbox.level).min().unwrap_or(logging::McpLevel::Warning)
+    }
+
+    /// The outbox of a live session
+    fn outbox(&mut self, id: Option<&str>) -> jsonrpc_core::Result<&mut Outbox> {
+        let id = id.ok_or_else(|| jsonrpc_core::Error::invalid_params("Send the Mcp-Session-Id from initialize"))?;
+        self.outboxes.get_mut(id).ok_or_else(|| unknown_session(id))
+    }
+
+    fn known(&self, id: &str) -> bool {
+        self.last_seen.contains_key(id)
+    }
+
+    /// Mark a session active; false when the server never issued it or it
+    /// has ended
+    fn touch(&mut self, id: &str) -> bool {
+        match self.last_seen.get_mut(id) {
+            Some(seen) => {
+                *seen = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, id: &str) -> bool {
+        self.clients.remove(id);
+        self.outboxes.remove(id);
+        self.last_seen.remove(id).is_some()
+    }
+
+    /// Initialize params of the session's client, null when unknown
+    fn client(&self, id: Option<&str>) -> Value {
+        id.and_then(|id| self.clients.get(id)).cloned().unwrap_or(Value::Null)
+    }
+
+    /// Remove and return sessions idle for longer than `timeout`
+    fn expire(&mut self, timeout: Duration) -> Vec<String> {
+        let expired: Vec<String> = self.last_seen.iter()
+            .filter(|(_, seen)| seen.elapsed() > timeout)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            self.remove(id);
+        }
+        expired
+    }
+}
+
+/// JSON-RPC error code for a session id the server does not know
+const UNKNOWN_SESSION: i64 = -32001;
+
+fn unknown_session(id: &str) -> jsonrpc_core::Error {
+    jsonrpc_core::Error {
+        code: jsonrpc_core::ErrorCode::ServerError(UNKNOWN_SESSION),
+        message: format!("Unknown session {}; initialize again", id),
+        data: None,
+    }
+}
+
+fn new_session_id() -> String {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let hash = RandomState::new().hash_one(nanos);
+    format!("{:016x}{:08x}", hash, nanos as u32)
+}
+
+/// Requests the server sends to clients. HTTP has no server push, so they
+/// go out through `notifications/poll` and clients POST their responses
+/// back with the session header.
+#[derive(Default)]
+struct ClientRequests {
+    next: u64,
+    queued: HashMap<String, Vec<Value>>,
+    /// Method and session of each unanswered request, by id
+    pending: HashMap<String, (String, String)>,
+    /// Callers waiting on the response to a request, by id
+    waiting: HashMap<String, oneshot::Sender<Value>>,
+}
+
+impl ClientRequests {
+    /// Queue a `method` request for `session` unless one is unanswered
+    fn send(&mut self, session: &str, method: &str) {
+        if self.pending.values().any(|(s, m)| s == session && m == method) {
+            return;
+        }
+        self.queue(session, method, None);
+    }
+
+    /// Queue a `method` request for `session`, returning its id and where
+    /// the client's response arrives
+    fn ask(&mut self, session: &str, method: &str, params: Value) -> (String, oneshot::Receiver<Value>) {
+        let id = self.queue(session, method, Some(params));
+        let (reply, response) = oneshot::channel();
+        self.waiting.insert(id.clone(), reply);
+        (id, response)
+    }
+
+    fn queue(&mut self, session: &str, method: &str, params: Option<Value>) -> String {
+        self.next += 1;
+        let id = format!("{}-{}", method.replace('/', "-"), self.next);
+        self.pending.insert(id.clone(), (session.to_string(), method.to_string()));
+        let mut request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method
+        });
+        if let Some(params) = params {
+            request["params"] = params;
+        }
+        self.queued.entry(session.to_string()).or_default().push(request);
+        id
+    }
+
+    /// Drop a request nobody waits for any more
+    fn forget(&mut self, id: &str) {
+        self.pending.remove(id);
+        self.waiting.remove(id);
+        for requests in self.queued.values_mut() {
+            requests.retain(|request| request["id"] != id);
+        }
+    }
+
+    fn take(&mut self, session: &str) -> Vec<Value> {
+        self.queued.remove(session).unwrap_or_default()
+    }
+
+    /// Method of the request `response` answers, if it went to `session`
+    fn answer(&mut self, session: &str, response: &Value) -> Option<String> {
+        let id = response["id"].as_str()?;
+        if self.pending.get(id)?.0 != session {
+            return None;
+        }
+        if let Some(reply) = self.waiting.remove(id) {
+            let _ = reply.send(response.clone());
+        }
+        self.pending.remove(id).map(|(_, method)| method)
+    }
+
+    fn end_session(&mut self, session: &str) {
+        self.queued.remove(session);
+        self.pending.retain(|_, (s, _)| s != session);
+        let pending = &self.pending;
+        self.waiting.retain(|id, _| pending.contains_key(id));
+    }
+}
+
+/// Whether a POSTed message is a client's response rather than a request
+fn is_client_response(message: &Value) -> bool {
+    match message {
+        Value::Array(items) => !items.is_empty() && items.iter().all(is_client_response),
+        Value::Object(map) => {
+            !map.contains_key("method") && map.contains_key("id")
+                && (map.contains_key("result") || map.contains_key("error"))
+        }
+        _ => false,
+    }
+}
+
+/// Apply the client responses in `message` to their requests
+async fn answer_client(tools: &RwLock<ToolRegistry>, requests: &Mutex<ClientRequests>, session: &str, message: &Value) {
+    let responses = match message {
+        Value::Array(items) => items.iter().collect(),
+        single => vec![single],
+    };
+    for response in responses {
+        let Some(method) = requests.lock().await.answer(session, response) else {
+            debug!("Ignoring response to unknown request {}", response["id"]);
+            continue;
+        };
+        if !response["error"].is_null() {
+            warn!("Client refused {}: {}", method, response["error"]);
+            continue;
+        }
+        if method == "roots/list" {
+            set_client_roots(&*tools.read().await, session, &response["result"]);
+        }
+    }
+}
+
+/// Sampling through the session's client: the request goes out on its next
+/// `notifications/poll` and the tool waits for the response it POSTs
+fn sampling_client(sessions: Arc<Mutex<Sessions>>, requests: Arc<Mutex<ClientRequests>>) -> sampling::Client {
+    Arc::new(move |session: String, params: Value| {
+        let sessions = sessions.clone();
+        let requests = requests.clone();
+        Box::pin(async move {
+            if sessions.lock().await.client(Some(&session))["capabilities"]["sampling"].is_null() {
+                return Err(ToolError::unsupported("The client does not support sampling").into());
+            }
+            let (id, response) = requests.lock().await.ask(&session, "sampling/createMessage", params);
+            let response = match tokio::time::timeout(SAMPLING_TIMEOUT, response).await {
+                Ok(Ok(response)) => response,
+                Ok(Err(_)) => return Err(ToolError::unsupported(format!("Session {} ended before sampling", session)).into()),
+                Err(_) => {
+                    requests.lock().await.forget(&id);
+                    return Err(ToolError::timeout(format!("The client did not answer sampling within {}s", SAMPLING_TIMEOUT.as_secs())).into());
+                }
+            };
+            match response["error"]["message"].as_str() {
+                Some(message) => Err(ToolError::external(format!("The client refused sampling: {}", message)).into()),
+                None if !response["error"].is_null() => Err(ToolError::external("The client refused sampling").into()),
+                None => Ok(response["result"].clone()),
+            }
+        })
+    })
+}
+
+/// Take the roots in `value.roots` as `session`'s client roots, if any
+fn set_client_roots(tools: &ToolRegistry, session: &str, value: &Value) {
+    let Some(roots) = value["roots"].as_array() else { return };
+    match tools.roots().set_client_roots(Some(session), roots) {
+        Ok(roots) => debug!("Session {} has {} client roots", session, roots.len()),
+        Err(e) => warn!("Ignoring client roots: {}", e),
+    }
+}
 
 pub struct MCPServer {
     config: Config,
     port: u16,
     tools: Arc<RwLock<ToolRegistry>>,
-    handler: IoHandler,
+    sessions: Arc<Mutex<Sessions>>,
+    requests: Arc<Mutex<ClientRequests>>,
+    handler: MetaIoHandler<RequestMeta>,
+    /// REST endpoints served beside MCP, when enabled
+    gateway: Option<Arc<Gateway>>,
 }
 
 impl MCPServer {
     pub fn new(config: Config, port: u16) -> Result<Self> {
-        let tools = Arc::new(RwLock::new(ToolRegistry::with_defaults()));
-        let mut handler = IoHandler::new();
+        let mut registry = ToolRegistry::with_defaults();
+        registry.set_limits(config.limits.clone());
+        registry.set_timeouts(config.timeouts.clone());
+        registry.configure_exec(config.exec.clone());
+        registry.configure_fs(config.journal.clone(), config.fs.clone());
+        registry.configure_code(config.code.clone());
+        registry.configure_storage(config.storage.clone());
+        registry.configure_notify(config.notify.clone());
+        registry.configure_calendar(config.calendar.clone())?;
+        registry.configure_vector(config.vector.clone());
+        registry.configure_browser(config.browser.clone());
+        registry.configure_naming(&config.naming)?;
+        registry.configure_advertise(config.advertise.clone());
+        registry.set_pagination(config.pagination.clone());
+        registry.configure_hooks(&config.hooks)?;
+        registry.set_read_only(config.read_only);
+        registry.configure_workspaces(&config.workspaces)?;
+        registry.configure_workflows(&config.workflows)?;
+        registry.configure_schedule(&config.schedule)?;
+        registry.configure_index(&config.index);
+        let gateway = match config.gateway.enabled {
+            true => Some(Arc::new(Gateway::new(&config.gateway)?)),
+            false => None,
+        };
+        logging::set_redactor(registry.redactor());
+        logging::attach_client(registry.notifier());
+        events::attach_client(registry.notifier());
+        let tools = Arc::new(RwLock::new(registry));
+        let sessions = Arc::new(Mutex::new(Sessions::default()));
+        let requests = Arc::new(Mutex::new(ClientRequests::default()));
+        sampling::attach_client(sampling_client(sessions.clone(), requests.clone()));
+        let mut handler = MetaIoHandler::default();
         
         // Clone for move into closures
         let tools_clone = tools.clone();
         
-        // Initialize method
+        // Initialize method starts a session under an id the server mints,
+        // never one the client offers; it goes back in the Mcp-Session-Id
+        // header and clients send it on every later request, so
+        // session-scoped state stays per connection. Clients with the roots
+        // capability are asked for their roots.
+        let sessions_clone = sessions.clone();
+        let requests_clone = requests.clone();
         handler.add_method("initialize", move |params: Params| {
             let tools = tools_clone.clone();
+            let sessions = sessions_clone.clone();
+            let requests = requests_clone.clone();
             Box::pin(async move {
                 debug!("Received initialize request: {:?}", params);
                 
-                let _tools = tools.read().await;
+                let tools = tools.read().await;
+                let session_id = new_session_id();
+                let params = params.parse::<Value>().unwrap_or(Value::Null);
+                sessions.lock().await.start(&session_id, json!({
+                    "clientInfo": params["clientInfo"],
+                    "capabilities": params["capabilities"]
+                }), tools.subscribe_notifications());
+                if params["roots"].is_array() {
+                    set_client_roots(&tools, &session_id, &params);
+                } else if !params["capabilities"]["roots"].is_null() {
+                    requests.lock().await.send(&session_id, "roots/list");
+                }
                 
                 Ok(json!({
+                    "sessionId": session_id,
                     "protocolVersion": "2024-11-05",
                     "serverInfo": {
                         "name": "hanzo-mcp",
@@ -38,20 +393,28 @@ impl MCPServer {
                     },
                     "capabilities": {
                         "tools": {},
-                        "resources": {},
-                        "prompts": {}
+                        "logging": {},
+                        "resources": {
+                            "subscribe": true,
+                            "listChanged": true
+                        },
+                        "prompts": {},
+                        "completions": {}
                     }
                 }))
             })
         });
         
-        // List tools method
+        // List tools method: the tools suited to the session's client
         let tools_clone = tools.clone();
-        handler.add_method("tools/list", move |_params: Params| {
+        let sessions_clone = sessions.clone();
+        handler.add_method_with_meta("tools/list", move |_params: Params, meta: RequestMeta| {
             let tools = tools_clone.clone();
+            let sessions = sessions_clone.clone();
             Box::pin(async move {
+                let client = sessions.lock().await.client(meta.session_id.as_deref());
                 let tools = tools.read().await;
-                let tool_list = tools.get_definitions();
+                let tool_list = tools.definitions_for(&client);
                 
                 Ok(json!({
                     "tools": tool_list
@@ -61,8 +424,13 @@ impl MCPServer {
         
         // Call tool method
         let tools_clone = tools.clone();
-        handler.add_method("tools/call", move |params: Params| {
+        let sessions_clone = sessions.clone();
+        let requests_clone = requests.clone();
+        let archive = config.server.archive_session_memory;
+        handler.add_method_with_meta("tools/call", move |params: Params, meta: RequestMeta| {
             let tools = tools_clone.clone();
+            let sessions = sessions_clone.clone();
+            let requests = requests_clone.clone();
             Box::pin(async move {
                 let params = params.parse::<serde_json::Value>()
                     .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
@@ -72,24 +440,28 @@ impl MCPServer {
                 
                 let tool_params = params.get("arguments").cloned().unwrap_or(json!({}));
                 
+                let expired = {
+                    let mut sessions = sessions.lock().await;
+                    if let Some(id) = &meta.session_id {
+                        if !sessions.touch(id) {
+                            return Err(unknown_session(id));
+                        }
+                    }
+                    sessions.expire(SESSION_IDLE_TIMEOUT)
+                };
                 let tools = tools.read().await;
-                match tools.execute(tool_name, tool_params).await {
-                    Ok(result) => {
-                        let text = if result.success {
-                            serde_json::to_string(&result.content).unwrap_or_default()
-                        } else {
-                            result.error.unwrap_or_else(|| "Unknown tool error".to_string())
-                        };
-                        Ok(json!({
-                            "content": [{
-                                "type": "text",
-                                "text": text
-                            }],
-                            "isError": !result.success
-                        }))
-                    },
+                for id in expired {
+                    debug!("Ending idle session {}", id);
+                    requests.lock().await.end_session(&id);
+                    if let Err(e) = tools.end_session(&id, archive).await {
+                        error!("Failed to end session {}: {}", id, e);
+                    }
+                }
+                let session = meta.session_id.as_deref();
+                match logging::in_session(session, tools.execute_in_session(tool_name, tool_params, session)).await {
+                    Ok(result) => Ok(result.to_call_result(tools.declares_output(tool_name))),
                     Err(e) => {
-                        error!("Tool execution failed: {}", e);
+                        error!("Tool {} failed: {}", tool_name, e);
                         Ok(json!({
                             "content": [{
                                 "type": "text",
@@ -103,14 +475,120 @@ impl MCPServer {
         });
         
         // List resources method
-        handler.add_method("resources/list", |_params: Params| {
+        let tools_clone = tools.clone();
+        handler.add_method("resources/list", move |_params: Params| {
+            let tools = tools_clone.clone();
+            Box::pin(async move {
+                let resources = tools.read().await.list_resources().await;
+                Ok(json!({
+                    "resources": resources
+                }))
+            })
+        });
+
+        // Read resource method
+        let tools_clone = tools.clone();
+        handler.add_method("resources/read", move |params: Params| {
+            let tools = tools_clone.clone();
+            Box::pin(async move {
+                let uri = resource_uri(params)?;
+                let tools = tools.read().await;
+                tools.read_resource(&uri).await
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+            })
+        });
+
+        // Resource subscriptions filter which update notifications the
+        // subscribing session is sent
+        let sessions_clone = sessions.clone();
+        handler.add_method_with_meta("resources/subscribe", move |params: Params, meta: RequestMeta| {
+            let sessions = sessions_clone.clone();
+            Box::pin(async move {
+                let uri = resource_uri(params)?;
+                sessions.lock().await.outbox(meta.session_id.as_deref())?.subscriptions.insert(uri);
+                Ok(json!({}))
+            })
+        });
+
+        let sessions_clone = sessions.clone();
+        handler.add_method_with_meta("resources/unsubscribe", move |params: Params, meta: RequestMeta| {
+            let sessions = sessions_clone.clone();
             Box::pin(async move {
+                let uri = resource_uri(params)?;
+                sessions.lock().await.outbox(meta.session_id.as_deref())?.subscriptions.remove(&uri);
+                Ok(json!({}))
+            })
+        });
+
+        // HTTP has no server push, so clients poll for pending notifications,
+        // each session its own; requests for the polling session come first
+        let sessions_clone = sessions.clone();
+        let requests_clone = requests.clone();
+        handler.add_method_with_meta("notifications/poll", move |_params: Params, meta: RequestMeta| {
+            let sessions = sessions_clone.clone();
+            let requests = requests_clone.clone();
+            Box::pin(async move {
+                let notifications = sessions.lock().await.outbox(meta.session_id.as_deref())?.drain();
+                let mut pending = match &meta.session_id {
+                    Some(session) => requests.lock().await.take(session),
+                    None => Vec::new(),
+                };
+                pending.extend(notifications);
                 Ok(json!({
-                    "resources": []
+                    "notifications": pending
                 }))
             })
         });
         
+        // Roots sent along with the change notification are taken as is;
+        // otherwise the client is asked for them again
+        let tools_clone = tools.clone();
+        let requests_clone = requests.clone();
+        handler.add_method_with_meta("notifications/roots/list_changed", move |params: Params, meta: RequestMeta| {
+            let tools = tools_clone.clone();
+            let requests = requests_clone.clone();
+            Box::pin(async move {
+                let Some(session) = meta.session_id else { return Ok(json!({})) };
+                let params = params.parse::<Value>().unwrap_or(Value::Null);
+                if params["roots"].is_array() {
+                    set_client_roots(&*tools.read().await, &session, &params);
+                } else {
+                    requests.lock().await.send(&session, "roots/list");
+                }
+                Ok(json!({}))
+            })
+        });
+
+        // Argument suggestions for clients that autocomplete tool calls
+        let tools_clone = tools.clone();
+        handler.add_method_with_meta("completion/complete", move |params: Params, meta: RequestMeta| {
+            let tools = tools_clone.clone();
+            Box::pin(async move {
+                let params = params.parse::<Value>()
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                tools.read().await.complete(&params, meta.session_id.as_deref()).await
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))
+            })
+        });
+
+        // Minimum severity of the session's notifications/message log events
+        let sessions_clone = sessions.clone();
+        handler.add_method_with_meta("logging/setLevel", move |params: Params, meta: RequestMeta| {
+            let sessions = sessions_clone.clone();
+            Box::pin(async move {
+                let params = params.parse::<Value>()
+                    .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                let level: logging::McpLevel = params["level"].as_str()
+                    .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing level"))?
+                    .parse()
+                    .map_err(|e: anyhow::Error| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+                let mut sessions = sessions.lock().await;
+                sessions.outbox(meta.session_id.as_deref())?.level = level;
+                logging::set_client_level(sessions.log_level());
+                Ok(json!({}))
+            })
+        });
+
         // List prompts method
         handler.add_method("prompts/list", |_params: Params| {
             Box::pin(async move {
@@ -131,16 +609,137 @@ impl MCPServer {
             config,
             port,
             tools,
+            sessions,
+            requests,
             handler,
+            gateway,
         })
     }
     
     pub async fn run(self) -> Result<()> {
-        let server = ServerBuilder::new(self.handler)
+        self.tools.write().await.mount_proxies(&self.config.proxy).await;
+        tokio::spawn(run_schedule(self.tools.clone()));
+        tokio::spawn(run_index(self.tools.clone()));
+        let tools = self.tools.clone();
+        let sessions = self.sessions.clone();
+        let requests = self.requests.clone();
+        let handler = Arc::new(self.handler.clone());
+        let archive = self.config.server.archive_session_memory;
+        let gateway = self.gateway.clone();
+        let server = ServerBuilder::with_meta_extractor(self.handler, |req: &hyper::Request<Body>| RequestMeta {
+                session_id: session_header(req),
+            })
+            .request_middleware(move |req: hyper::Request<Body>| {
+                if let Some(gateway) = gateway.clone().filter(|_| Gateway::serves(req.uri().path())) {
+                    let tools = tools.clone();
+                    return RequestMiddlewareAction::Respond {
+                        should_validate_hosts: true,
+                        response: Box::pin(async move {
+                            let (parts, body) = req.into_parts();
+                            let header = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok());
+                            let credential = Gateway::credential(header("authorization"), header("x-api-key"));
+                            let (code, reply) = match gateway.refuses(credential) {
+                                Some(refused) => refused,
+                                None => match read_body(body, MAX_REQUEST_BYTES).await? {
+                                    Some(body) => {
+                                        let tools = tools.read().await;
+                                        gateway.route(parts.method.as_str(), parts.uri.path(), credential, &body, &tools).await
+                                    }
+                                    None => Gateway::too_large(MAX_REQUEST_BYTES),
+                                },
+                            };
+                            Ok(hyper::Response::builder()
+                                .status(code)
+                                .header(hyper::header::CONTENT_TYPE, "application/json")
+                                .body(Body::from(reply.to_string()))
+                                .unwrap_or_else(|_| status(hyper::StatusCode::INTERNAL_SERVER_ERROR)))
+                        }),
+                    };
+                }
+                let tools = tools.clone();
+                let sessions = sessions.clone();
+                let requests = requests.clone();
+
+                // Every POST is answered here: an initialize gets its new
+                // session id in the Mcp-Session-Id header, other requests
+                // must carry an id the server issued, and session POSTs may
+                // carry responses to the server's own requests, which
+                // JSON-RPC request handling would reject
+                if req.method() == Method::POST {
+                    let handler = handler.clone();
+                    return RequestMiddlewareAction::Respond {
+                        should_validate_hosts: true,
+                        response: Box::pin(async move {
+                            let session_id = session_header(&req);
+                            let Some(body) = read_body(req.into_body(), MAX_REQUEST_BYTES).await? else {
+                                return Ok(status(hyper::StatusCode::PAYLOAD_TOO_LARGE));
+                            };
+                            let message: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+                            let initializing = is_initialize(&message);
+                            let session_id = if initializing { None } else { session_id };
+                            if let Some(id) = &session_id {
+                                if !sessions.lock().await.known(id) {
+                                    debug!("Refusing unknown session {}", id);
+                                    let error = jsonrpc_core::Failure {
+                                        jsonrpc: Some(jsonrpc_core::Version::V2),
+                                        error: unknown_session(id),
+                                        id: serde_json::from_value(message["id"].clone()).unwrap_or(jsonrpc_core::Id::Null),
+                                    };
+                                    return Ok(json_response(hyper::StatusCode::NOT_FOUND, json!(error).to_string(), None));
+                                }
+                            }
+                            if is_client_response(&message) {
+                                if let Some(id) = &session_id {
+                                    answer_client(&tools, &requests, id, &message).await;
+                                }
+                                return Ok(status(hyper::StatusCode::ACCEPTED));
+                            }
+                            let meta = RequestMeta { session_id };
+                            let reply = handler.handle_request(&String::from_utf8_lossy(&body), meta).await;
+                            Ok(match reply {
+                                Some(reply) => {
+                                    let issued = initializing.then(|| issued_session(&reply)).flatten();
+                                    json_response(hyper::StatusCode::OK, reply, issued)
+                                }
+                                None => status(hyper::StatusCode::ACCEPTED),
+                            })
+                        }),
+                    };
+                }
+
+                // HTTP DELETE with the session header ends the session
+                let session_id = match session_header(&req) {
+                    Some(id) if req.method() == Method::DELETE => id,
+                    _ => return RequestMiddlewareAction::Proceed {
+                        should_continue_on_invalid_cors: false,
+                        request: req,
+                    },
+                };
+                RequestMiddlewareAction::Respond {
+                    should_validate_hosts: true,
+                    response: Box::pin(async move {
+                        if !sessions.lock().await.remove(&session_id) {
+                            debug!("Closing unknown session {}", session_id);
+                            return Ok(status(hyper::StatusCode::NOT_FOUND));
+                        }
+                        requests.lock().await.end_session(&session_id);
+                        let body = match tools.read().await.end_session(&session_id, archive).await {
+                            Ok(result) => result,
+                            Err(e) => json!({ "error": e.to_string() }),
+                        };
+                        info!("Session {} closed", session_id);
+                        Ok(hyper::Response::new(Body::from(body.to_string())))
+                    }),
+                }
+            })
+            .max_request_body_size(MAX_REQUEST_BYTES)
             .start_http(&format!("127.0.0.1:{}", self.port).parse()?)
             .map_err(|e| anyhow::anyhow!("Failed to start server: {}", e))?;
         
         info!("MCP Server running on http://127.0.0.1:{}", self.port);
+        if self.config.gateway.enabled {
+            info!("REST gateway on http://127.0.0.1:{}{}/{{name}}", self.port, crate::gateway::TOOLS_PATH);
+        }
         
         // Keep server running
         server.wait();
@@ -153,3 +752,227 @@ impl MCPServer {
         tools.register(tool);
     }
 }
+
+/// Start due scheduled calls, each on its own task so a slow one does not
+/// hold up the rest
+async fn run_schedule(tools: Arc<RwLock<ToolRegistry>>) {
+    let mut tick = tokio::time::interval(SCHEDULE_TICK);
+    loop {
+        tick.tick().await;
+        let Some(scheduler) = tools.read().await.scheduler() else { return };
+        for job in scheduler.due(chrono::Utc::now()) {
+            let tools = tools.clone();
+            tokio::spawn(async move {
+                debug!("Running scheduled {} ({})", job.id, job.tool);
+                tools.read().await.run_scheduled(&job).await;
+            });
+        }
+    }
+}
+
+/// Index the roots in the background for as long as the server runs
+async fn run_index(tools: Arc<RwLock<ToolRegistry>>) {
+    let (indexer, roots, vectors) = {
+        let tools = tools.read().await;
+        (tools.indexer(), tools.roots(), tools.vector_store().await)
+    };
+    let Some(indexer) = indexer else { return };
+    info!("Indexing workspace roots in the background");
+    indexer.run(move || roots.visible(None).into_iter().map(|root| root.path).collect(), vectors).await;
+}
+
+fn status(code: hyper::StatusCode) -> hyper::Response<Body> {
+    let mut response = hyper::Response::new(Body::empty());
+    *response.status_mut() = code;
+    response
+}
+
+/// Whether a POSTed message is, or a batch holds, an initialize request
+fn is_initialize(message: &Value) -> bool {
+    match message {
+        Value::Array(items) => items.iter().any(is_initialize),
+        message => message["method"] == "initialize",
+    }
+}
+
+/// The session id an initialize reply carries
+fn issued_session(reply: &str) -> Option<String> {
+    let reply: Value = serde_json::from_str(reply).ok()?;
+    let replies = match reply {
+        Value::Array(items) => items,
+        single => vec![single],
+    };
+    replies.iter().find_map(|r| r["result"]["sessionId"].as_str().map(str::to_string))
+}
+
+fn json_response(code: hyper::StatusCode, body: String, session: Option<String>) -> hyper::Response<Body> {
+    let mut response = hyper::Response::builder()
+        .status(code)
+        .header(hyper::header::CONTENT_TYPE, "application/json");
+    if let Some(id) = session {
+        response = response.header(SESSION_HEADER, id);
+    }
+    response.body(Body::from(body)).unwrap_or_else(|_| status(hyper::StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// The body of a request, or None once it passes `limit` bytes; read
+/// by chunk so an oversized one is never held whole
+async fn read_body(mut body: Body, limit: usize) -> Result<Option<Vec<u8>>, hyper::Error> {
+    use hyper::body::HttpBody;
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if bytes.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Some(bytes))
+}
+
+fn session_header(req: &hyper::Request<Body>) -> Option<String> {
+    req.headers().get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .filter(|id| !id.is_empty())
+}
+
+fn resource_uri(params: Params) -> jsonrpc_core::Result<String> {
+    let params = params.parse::<Value>()
+        .map_err(|e| jsonrpc_core::Error::invalid_params(e.to_string()))?;
+    params["uri"].as_str()
+        .map(str::to_string)
+        .ok_or_else(|| jsonrpc_core::Error::invalid_params("Missing resource uri"))
+}
+
+/// `message` as `session` is sent it, if at all: notifications addressed
+/// to some sessions only reach those, resource updates only reach
+/// sessions subscribed to the URI and log messages only those whose level
+/// admits them
+fn deliverable(mut message: Value, session: &str, subscriptions: &HashSet<String>, level: logging::McpLevel) -> Option<Value> {
+    if let Some(audience) = message.as_object_mut().and_then(|m| m.remove(events::AUDIENCE)) {
+        if !audience.as_array().is_some_and(|sessions| sessions.iter().any(|s| s == session)) {
+            return None;
+        }
+    }
+    if message["method"] == "notifications/resources/updated"
+        && !message["params"]["uri"].as_str().is_some_and(|uri| subscriptions.contains(uri))
+    {
+        return None;
+    }
+    if message["method"] == "notifications/message"
+        && message["params"]["level"].as_str().and_then(|l| l.parse::<logging::McpLevel>().ok()).is_some_and(|l| l < level)
+    {
+        return None;
+    }
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_are_issued() {
+        let mut sessions = Sessions::default();
+        assert!(!sessions.touch("guessed"), "ids the server never issued are not adopted");
+        assert!(!sessions.known("guessed"));
+        sessions.start("s1", json!({}), broadcast::channel(1).1);
+        assert!(sessions.touch("s1"));
+        assert!(sessions.remove("s1"));
+        assert!(!sessions.touch("s1"));
+
+        assert!(is_initialize(&json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize" })));
+        assert!(is_initialize(&json!([{ "method": "ping" }, { "method": "initialize" }])));
+        assert!(!is_initialize(&json!({ "method": "tools/call" })));
+        let reply = json!({ "jsonrpc": "2.0", "id": 1, "result": { "sessionId": "abc" } }).to_string();
+        assert_eq!(issued_session(&reply).as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn test_outboxes_are_per_session() {
+        let (sender, _) = broadcast::channel(16);
+        let mut sessions = Sessions::default();
+        sessions.start("a", json!({}), sender.subscribe());
+        sessions.start("b", json!({}), sender.subscribe());
+        sessions.outbox(Some("a")).unwrap().subscriptions.insert("file:///x".into());
+
+        let _ = sender.send(json!({ "method": "notifications/message" }));
+        let _ = sender.send(json!({ "method": "notifications/resources/updated", "params": { "uri": "file:///x" } }));
+        assert_eq!(sessions.outbox(Some("a")).unwrap().drain().len(), 2);
+        let seen_by_b = sessions.outbox(Some("b")).unwrap().drain();
+        assert_eq!(seen_by_b.len(), 1, "a's poll leaves b's queue and a's subscription leaves b alone");
+        assert!(sessions.outbox(Some("a")).unwrap().drain().is_empty());
+
+        let _ = sender.send(json!({ "method": events::NOTIFICATION, "params": {}, events::AUDIENCE: ["b"] }));
+        assert!(sessions.outbox(Some("a")).unwrap().drain().is_empty(), "events b subscribed to stay b's");
+        let event = sessions.outbox(Some("b")).unwrap().drain();
+        assert_eq!(event.len(), 1);
+        assert!(event[0].get(events::AUDIENCE).is_none());
+
+        sessions.outbox(Some("a")).unwrap().level = logging::McpLevel::Debug;
+        assert_eq!(sessions.log_level(), logging::McpLevel::Debug);
+        let _ = sender.send(json!({ "method": "notifications/message", "params": { "level": "info" } }));
+        assert_eq!(sessions.outbox(Some("a")).unwrap().drain().len(), 1);
+        assert!(sessions.outbox(Some("b")).unwrap().drain().is_empty(), "b keeps the default warning level");
+
+        assert!(sessions.remove("a"));
+        assert!(sessions.outbox(Some("a")).is_err());
+        assert!(sessions.outbox(None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_body() {
+        let body = Body::wrap_stream(futures::stream::iter(vec![Ok::<_, std::io::Error>(vec![b'a'; 6]), Ok(vec![b'b'; 6])]));
+        assert_eq!(read_body(body, 12).await.unwrap().map(|b| b.len()), Some(12));
+        let body = Body::wrap_stream(futures::stream::iter(vec![Ok::<_, std::io::Error>(vec![b'a'; 6]), Ok(vec![b'b'; 6])]));
+        assert_eq!(read_body(body, 10).await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_client_requests() {
+        let mut requests = ClientRequests::default();
+        requests.send("s1", "roots/list");
+        requests.send("s1", "roots/list");
+        let queued = requests.take("s1");
+        assert_eq!(queued.len(), 1);
+        assert!(requests.take("s1").is_empty());
+
+        let response = json!({ "jsonrpc": "2.0", "id": queued[0]["id"], "result": { "roots": [] } });
+        assert!(is_client_response(&response));
+        assert!(!is_client_response(&json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" })));
+        assert_eq!(requests.answer("s2", &response), None, "only the session asked may answer");
+        assert_eq!(requests.answer("s1", &response).as_deref(), Some("roots/list"));
+        assert_eq!(requests.answer("s1", &response), None);
+    }
+
+    #[tokio::test]
+    async fn test_sampling_client() {
+        let sessions = Arc::new(Mutex::new(Sessions::default()));
+        let requests = Arc::new(Mutex::new(ClientRequests::default()));
+        let (sender, _) = broadcast::channel(4);
+        sessions.lock().await.start("plain", json!({ "capabilities": {} }), sender.subscribe());
+        sessions.lock().await.start("llm", json!({ "capabilities": { "sampling": {} } }), sender.subscribe());
+        let client = sampling_client(sessions, requests.clone());
+
+        let refused = client("plain".into(), json!({})).await.unwrap_err();
+        assert!(refused.to_string().contains("does not support sampling"));
+
+        let call = tokio::spawn(client("llm".into(), json!({ "maxTokens": 10 })));
+        let request = loop {
+            if let Some(request) = requests.lock().await.take("llm").pop() {
+                break request;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(request["method"], "sampling/createMessage");
+        assert_eq!(request["params"]["maxTokens"], 10);
+        let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": { "content": { "type": "text", "text": "ok" } } });
+        assert_eq!(requests.lock().await.answer("llm", &response).as_deref(), Some("sampling/createMessage"));
+        assert_eq!(call.await.unwrap().unwrap()["content"]["text"], "ok");
+
+        let (id, _) = requests.lock().await.ask("llm", "sampling/createMessage", json!({}));
+        requests.lock().await.forget(&id);
+        assert!(requests.lock().await.take("llm").is_empty());
+    }
+}
@@ -0,0 +1,872 @@
+/// Persistent Playwright session behind the browser tool
+///
+/// One node process runs a small driver that launches Chromium on first
+/// use and keeps the browser, its contexts and their pages between calls,
+/// so a page navigated to, a login or a half-filled form is still there
+/// for the next action. Requests and answers are JSON lines, like the repl
+/// sessions, matched by id: the driver runs requests concurrently, so
+/// actions in different named contexts (each with its own cookies and
+/// storage) don't wait for each other. Page event handlers registered by
+/// the driver (file choosers, dialogs) outlive the call that set them up.
+/// Actions given a `frame` act inside that iframe instead of the top page.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+
+/// Calls waiting for their answer, by id
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+
+/// Reads `{id, op, params}` lines and answers each with one marked line
+/// holding `result` or `error`. Run with `node -e` so `require` finds
+/// Playwright the way a script in the working directory would.
+const DRIVER: &str = r##"
+const readline = require("node:readline");
+
+const MARK = process.env.HANZO_BROWSER_MARK;
+const HEADLESS = process.env.HANZO_BROWSER_HEADLESS !== "0";
+// Named contexts, each { name, context, page, chooser, options, seen }
+// with its own cookies and storage; actions without a context use
+// "default". `seen` is the traffic since the context's last action that
+// is not a wait, so a wait after a click still sees a response that
+// arrived before it was asked for.
+const state = { browser: null, launching: null, contexts: new Map() };
+const MAX_SEEN = 200;
+// How dialogs are answered: by the first rule whose origin pattern matches
+// the page, else by `policy`; dismissing is what Playwright does unasked
+const dialogs = { policy: { accept: false, prompt_text: null }, rules: [], log: [] };
+
+function playwright() {
+  try {
+    return require("playwright");
+  } catch (error) {
+    throw new Error("Playwright is not installed: npm install playwright && npx playwright install chromium");
+  }
+}
+
+function watch(page, entry) {
+  // Keeps the native dialog from opening; upload fills the chooser later
+  page.on("filechooser", (chooser) => { entry.chooser = chooser; });
+  page.on("request", (request) => remember(entry.seen.requests, request));
+  page.on("response", (response) => remember(entry.seen.responses, response));
+  page.on("dialog", (dialog) => answer(page, dialog, entry));
+}
+
+async function answer(page, dialog, entry) {
+  let origin = page.url();
+  try {
+    origin = new URL(origin).origin;
+  } catch {}
+  const rule = dialogs.rules.find((r) => matcher(r.origin)(origin));
+  const { accept, prompt_text } = rule ?? dialogs.policy;
+  const text = dialog.type() === "prompt" ? (prompt_text ?? dialog.defaultValue()) : undefined;
+  const logged = {
+    at: new Date().toISOString(),
+    type: dialog.type(),
+    message: dialog.message(),
+    url: page.url(),
+    context: entry.name,
+    answer: accept ? "accepted" : "dismissed",
+    rule: rule?.origin ?? null,
+  };
+  if (accept && text !== undefined) logged.prompt_text = text;
+  try {
+    if (accept) await dialog.accept(text);
+    else await dialog.dismiss();
+  } catch (error) {
+    logged.error = error.message;
+  }
+  dialogs.log.push(logged);
+  if (dialogs.log.length > MAX_SEEN) dialogs.log.shift();
+}
+
+function remember(list, item) {
+  list.push({ at: Date.now(), item });
+  if (list.length > MAX_SEEN) list.shift();
+}
+
+// Take the first entry since the context's last action whose URL matches
+function recall(seen, list, matches) {
+  const index = list.findIndex((entry) => entry.at >= seen.since && matches(entry.item.url()));
+  return index < 0 ? null : list.splice(index, 1)[0].item;
+}
+
+// `/regex/`, a glob (`**/api/*`), or else part of the URL
+function matcher(pattern) {
+  const regex = pattern.match(/^\/(.+)\/([a-z]*)$/);
+  if (regex) {
+    const compiled = new RegExp(regex[1], regex[2]);
+    return (url) => compiled.test(url);
+  }
+  if (pattern.includes("*")) {
+    const source = pattern.split("**").map((part) => part.split("*")
+      .map((text) => text.replace(/[.+?^${}()|[\]\\]/g, "\\$&")).join("[^/]*")).join(".*");
+    const compiled = new RegExp(`^${source}$`);
+    return (url) => compiled.test(url);
+  }
+  return (url) => url.includes(pattern);
+}
+
+const describeRequest = (request) => ({
+  url: request.url(),
+  method: request.method(),
+  resource_type: request.resourceType(),
+  post_data: request.postData()?.slice(0, 2000) ?? null,
+});
+
+async function describeResponse(response) {
+  const type = response.headers()["content-type"] ?? null;
+  const textual = type && /json|text|xml|javascript|html/.test(type);
+  const body = textual ? await response.text().catch(() => null) : null;
+  return {
+    url: response.url(),
+    status: response.status(),
+    status_text: response.statusText(),
+    method: response.request().method(),
+    content_type: type,
+    body: body?.slice(0, 2000) ?? null,
+  };
+}
+
+async function describeEvent(name, value) {
+  if (name === "request" || name === "requestfinished" || name === "requestfailed") return describeRequest(value);
+  if (name === "response") return describeResponse(value);
+  if (name === "download") return { url: value.url(), suggested_filename: value.suggestedFilename() };
+  if (name === "console") return { type: value.type(), text: value.text() };
+  if (name === "dialog") return { type: value.type(), message: value.message() };
+  if (value && typeof value.url === "function") return { url: value.url() };
+  return value === undefined ? null : String(value);
+}
+
+// Requests run concurrently, so launching and opening are shared by the
+// calls that arrive while they are under way
+async function launch() {
+  state.launching ??= playwright().chromium.launch({ headless: HEADLESS });
+  try {
+    state.browser = await state.launching;
+  } catch (error) {
+    state.launching = null;
+    throw error;
+  }
+  return state.browser;
+}
+
+// The context `p.context` names, opened with `options` on first use
+async function open(p, options = {}) {
+  const name = p.context ?? "default";
+  let entry = state.contexts.get(name);
+  if (!entry) {
+    entry = { name, context: null, page: null, chooser: null, options, seen: { since: Date.now(), requests: [], responses: [] } };
+    entry.ready = launch().then(async (browser) => { entry.context = await browser.newContext(options); });
+    state.contexts.set(name, entry);
+  }
+  try {
+    await entry.ready;
+  } catch (error) {
+    if (state.contexts.get(name) === entry) state.contexts.delete(name);
+    throw error;
+  }
+  return entry;
+}
+
+// The page of the context `p.context` names
+async function current(p) {
+  const entry = await open(p);
+  if (!entry.page || entry.page.isClosed()) {
+    entry.opening ??= entry.context.newPage()
+      .then((page) => { watch(page, entry); entry.page = page; })
+      .finally(() => { entry.opening = null; });
+    await entry.opening;
+  }
+  return entry.page;
+}
+
+// The page, or the frame `p.frame` names: a frame name, part of its URL,
+// or a selector of its iframe element, searched in every frame
+async function target(p) {
+  const page = await current(p);
+  if (!p.frame) return page;
+  const frames = page.frames().filter((f) => f !== page.mainFrame());
+  const found = frames.find((f) => f.name() === p.frame) ?? frames.find((f) => f.url().includes(p.frame));
+  if (found) return found;
+  for (const frame of page.frames()) {
+    const element = await frame.$(p.frame).catch(() => null);
+    const content = element && (await element.contentFrame());
+    if (content) return content;
+  }
+  throw new Error(`No frame matches ${p.frame}; list them with the frames action`);
+}
+
+// Whether cookie `c` is one `filter` ({name, domain, path}) picks out;
+// a domain picks its subdomains' cookies too
+function picks(c, filter) {
+  const domain = c.domain.replace(/^\./, "");
+  const wanted = filter.domain?.replace(/^\./, "");
+  return (!filter.name || c.name === filter.name)
+    && (!wanted || domain === wanted || domain.endsWith(`.${wanted}`))
+    && (!filter.path || c.path === filter.path);
+}
+
+// Roles of the controls fill_form fills
+const FIELD_ROLES = ["textbox", "searchbox", "combobox", "spinbutton", "checkbox", "radio", "switch", "listbox", "slider"];
+
+// The controls in `scope` that `key` names, by the first way that finds
+// any: label, accessible name, placeholder, name or id attribute, exactly
+// and then as part of the text
+async function field(scope, key) {
+  const named = (exact) => FIELD_ROLES.map((role) => scope.getByRole(role, { name: key, exact }))
+    .reduce((all, locator) => all.or(locator));
+  const quoted = JSON.stringify(key);
+  const ways = [
+    ["label", () => scope.getByLabel(key, { exact: true })],
+    ["name", () => named(true)],
+    ["placeholder", () => scope.getByPlaceholder(key, { exact: true })],
+    ["attribute", () => scope.locator(`[name=${quoted}], [id=${quoted}]`)],
+    ["label", () => scope.getByLabel(key)],
+    ["name", () => named(false)],
+    ["placeholder", () => scope.getByPlaceholder(key)],
+  ];
+  for (const [by, locate] of ways) {
+    const locator = locate();
+    const count = await locator.count();
+    if (count) return { by, locator, count };
+  }
+  return null;
+}
+
+const checked = (value) => value === true || ["true", "yes", "on", "1", "checked"].includes(String(value).toLowerCase());
+
+// Put `value` in the control(s) `locator` found: check boxes, pick
+// options, choose the radio of a group whose value or label is `value`,
+// and type into the rest
+async function enter(scope, locator, count, value, timeout) {
+  const kind = await locator.first().evaluate((el) => (el.tagName === "SELECT" ? "select" : (el.getAttribute("role") || el.type || "text").toLowerCase()));
+  if (kind === "radio" && typeof value === "string" && !checked(value)) {
+    const group = await locator.first().getAttribute("name");
+    const choice = [
+      group && scope.locator(`input[type=radio][name=${JSON.stringify(group)}][value=${JSON.stringify(value)}]`),
+      locator.and(scope.getByRole("radio", { name: value, exact: true })),
+      scope.getByRole("radio", { name: value, exact: true }),
+    ].filter(Boolean);
+    for (const option of choice) {
+      if (await option.count() === 1) {
+        await option.check({ timeout });
+        return kind;
+      }
+    }
+    throw new Error(`No option ${value}`);
+  }
+  if (count > 1) throw new Error(`${count} fields match`);
+  if (kind === "file") throw new Error("File inputs take the upload action");
+  if (["checkbox", "switch", "radio"].includes(kind)) await locator.setChecked(checked(value), { timeout });
+  else if (kind === "select" || kind === "listbox") await locator.selectOption(Array.isArray(value) ? value.map(String) : String(value), { timeout });
+  else await locator.fill(String(value), { timeout });
+  return kind;
+}
+
+// Draw a box around the element `locator` finds, over the page, until
+// unmark() takes it away
+async function mark(locator, color) {
+  await locator.evaluate((el, color) => {
+    const box = el.getBoundingClientRect();
+    const overlay = document.createElement("div");
+    overlay.id = "__hanzo_highlight";
+    Object.assign(overlay.style, {
+      position: "absolute",
+      left: `${box.left + window.scrollX - 4}px`,
+      top: `${box.top + window.scrollY - 4}px`,
+      width: `${box.width + 8}px`,
+      height: `${box.height + 8}px`,
+      border: `3px solid ${color}`,
+      borderRadius: "4px",
+      boxSizing: "border-box",
+      pointerEvents: "none",
+      zIndex: "2147483647",
+    });
+    document.documentElement.appendChild(overlay);
+  }, color);
+}
+
+async function unmark(locator) {
+  await locator.evaluate(() => document.getElementById("__hanzo_highlight")?.remove()).catch(() => {});
+}
+
+// Runs in the page: the subtree at `selector`, or the page's main content
+// without navigation, banners, ads and other boilerplate, as markdown
+// (or plain text with links in parentheses)
+function readable({ selector, plain }) {
+  const DROP = new Set(["SCRIPT", "STYLE", "NOSCRIPT", "TEMPLATE", "SVG", "CANVAS", "IFRAME", "OBJECT", "EMBED",
+    "BUTTON", "SELECT", "INPUT", "TEXTAREA", "DIALOG"]);
+  const BLOCK = new Set(["P", "DIV", "SECTION", "ARTICLE", "MAIN", "HEADER", "FOOTER", "FIGURE", "FIGCAPTION",
+    "DL", "DT", "DD", "ADDRESS", "DETAILS", "SUMMARY", "FORM", "FIELDSET", "BODY"]);
+  const ROLES = new Set(["navigation", "banner", "contentinfo", "complementary", "search", "dialog", "alertdialog"]);
+  const NOISE = /(^|[-_\s])(nav|navbar|menu|footer|sidebar|cookies?|consent|banner|advert|ads|promo|share|social|breadcrumbs?|popup|modal|newsletter|related|subscribe)([-_\s]|$)/i;
+  // Indents nested lines and code; unlike spaces, clean() leaves it be
+  const INDENT = "\u0001";
+
+  let root;
+  if (selector) {
+    root = document.querySelector(selector);
+    if (!root) throw new Error(`No element matches ${selector}`);
+  } else {
+    const candidates = Array.from(document.querySelectorAll("main, [role=main], article"));
+    candidates.sort((a, b) => b.textContent.length - a.textContent.length);
+    root = candidates.find((el) => el.textContent.trim().length > 200) ?? document.body;
+  }
+  const total = root.textContent.length || 1;
+
+  const skipped = (el) => {
+    if (DROP.has(el.tagName)) return true;
+    if (el === root) return false;
+    if (el.hidden || el.getAttribute("aria-hidden") === "true") return true;
+    const style = getComputedStyle(el);
+    if (style.display === "none" || style.visibility === "hidden") return true;
+    // A chosen subtree is kept whole
+    if (selector) return false;
+    if (el.tagName === "NAV" || el.tagName === "ASIDE" || ROLES.has(el.getAttribute("role"))) return true;
+    if ((el.tagName === "HEADER" || el.tagName === "FOOTER") && !el.closest("article")) return true;
+    const names = `${el.id} ${typeof el.className === "string" ? el.className : ""}`;
+    return NOISE.test(names) && el.textContent.length < total / 4;
+  };
+  const clean = (text) => text.replace(/[ \t]+\n/g, "\n").replace(/\n[ \t]+/g, "\n").replace(/\n{3,}/g, "\n\n").trim();
+  const block = (text) => `\n\n${text}\n\n`;
+  const children = (el) => Array.from(el.childNodes, render).join("");
+  // Marks hug the text; the spaces around it stay outside them
+  const around = (text, marked) => `${/^\s/.test(text) ? " " : ""}${marked}${/\s$/.test(text) ? " " : ""}`;
+  const wrap = (mark, text) => (plain || !text.trim() ? text : around(text, `${mark}${text.trim()}${mark}`));
+
+  function list(el) {
+    const items = Array.from(el.children).filter((child) => child.tagName === "LI" && !skipped(child));
+    return block(items.map((item, i) => {
+      const marker = el.tagName === "OL" ? `${i + 1}. ` : "- ";
+      return marker + clean(children(item)).split("\n").join(`\n${INDENT.repeat(marker.length)}`);
+    }).join("\n"));
+  }
+
+  function table(el) {
+    const rows = Array.from(el.rows).map((row) => Array.from(row.cells, (cell) => clean(children(cell)).replace(/\s*\n+\s*/g, " ").replace(/\|/g, "\\|")));
+    if (!rows.length) return "";
+    if (plain) return block(rows.map((row) => row.join("\t")).join("\n"));
+    const line = (row) => `| ${row.join(" | ")} |`;
+    return block([line(rows[0]), line(rows[0].map(() => "---")), ...rows.slice(1).map(line)].join("\n"));
+  }
+
+  function render(node) {
+    if (node.nodeType === Node.TEXT_NODE) return node.textContent.replace(/\s+/g, " ");
+    if (node.nodeType !== Node.ELEMENT_NODE || skipped(node)) return "";
+    const el = node;
+    const tag = el.tagName;
+    switch (tag) {
+      case "H1": case "H2": case "H3": case "H4": case "H5": case "H6": {
+        const text = clean(children(el)).replace(/\n+/g, " ");
+        return text ? block(plain ? text : `${"#".repeat(Number(tag[1]))} ${text}`) : "";
+      }
+      case "BR": return "\n";
+      case "HR": return block(plain ? "" : "---");
+      case "A": {
+        const text = children(el);
+        const href = el.href;
+        if (!text.trim() || !href || href.startsWith("javascript:")) return text;
+        if (plain) return around(text, `${text.trim()} (${href})`);
+        return around(text, `[${text.trim().replace(/\n+/g, " ")}](${href})`);
+      }
+      case "IMG": return plain || !el.alt ? "" : `![${el.alt}](${el.src})`;
+      case "STRONG": case "B": return wrap("**", children(el));
+      case "EM": case "I": return wrap("_", children(el));
+      case "CODE": return plain ? el.textContent : `\`${el.textContent}\``;
+      case "PRE": {
+        // Kept through clean(), which trims spaces around newlines
+        const code = el.textContent.replace(/\n$/, "").replace(/^[ \t]+/gm, (space) => INDENT.repeat(space.replace(/\t/g, "    ").length));
+        return block(plain ? code : `\`\`\`\n${code}\n\`\`\``);
+      }
+      case "BLOCKQUOTE": return block(clean(children(el)).split("\n").map((line) => (plain ? line : `> ${line}`)).join("\n"));
+      case "UL": case "OL": return list(el);
+      case "TABLE": return table(el);
+      default: return BLOCK.has(tag) ? block(children(el)) : children(el);
+    }
+  }
+
+  return { content: clean(render(root)).replaceAll(INDENT, " "), root: root === document.body ? "body" : root.tagName.toLowerCase() };
+}
+
+async function tree(frame, main) {
+  const children = [];
+  for (const child of frame.childFrames()) children.push(await tree(child, main));
+  let selector = null;
+  if (frame !== main) {
+    const element = await frame.frameElement().catch(() => null);
+    selector = element && (await element.evaluate((el) => el.id ? `#${el.id}` : el.name ? `iframe[name="${el.name}"]` : null));
+  }
+  return { name: frame.name(), url: frame.url(), selector, children };
+}
+
+const files = (element) => element.evaluate((el) => ({
+  multiple: !!el.multiple,
+  attached: Array.from(el.files || [], (f) => ({ name: f.name, size: f.size, type: f.type })),
+}));
+
+const ops = {
+  async navigate(p) {
+    const page = await current(p);
+    await page.goto(p.url, { timeout: p.timeout });
+    return { url: page.url(), title: await page.title() };
+  },
+  async click(p) {
+    const scope = await target(p);
+    await scope.click(p.selector, { timeout: p.timeout });
+    return { clicked: p.selector, file_chooser: !!(await open(p)).chooser };
+  },
+  async type(p) {
+    const scope = await target(p);
+    await scope.type(p.selector, p.text, { timeout: p.timeout });
+    return { typed: p.text.length };
+  },
+  async fill(p) {
+    const scope = await target(p);
+    await scope.fill(p.selector, p.text, { timeout: p.timeout });
+    return { filled: p.selector };
+  },
+  async fill_form(p) {
+    const frame = await target(p);
+    const scope = p.selector ? frame.locator(p.selector) : frame;
+    const filled = [];
+    const unmatched = [];
+    for (const [key, value] of Object.entries(p.fields)) {
+      const found = await field(scope, key);
+      if (!found) {
+        unmatched.push({ field: key, reason: "No field has this label, name, placeholder or id" });
+        continue;
+      }
+      try {
+        const kind = await enter(scope, found.locator, found.count, value, p.timeout);
+        filled.push({ field: key, by: found.by, kind });
+      } catch (error) {
+        unmatched.push({ field: key, by: found.by, reason: error.message.split("\n")[0] });
+      }
+    }
+    return { filled, unmatched };
+  },
+  async get_text(p) {
+    const scope = await target(p);
+    return { selector: p.selector, text: await scope.textContent(p.selector, { timeout: p.timeout }) };
+  },
+  async screenshot(p) {
+    const page = await current(p);
+    if (!p.selector) {
+      const data = await page.screenshot({ fullPage: p.full_page, path: p.path, timeout: p.timeout });
+      return { path: p.path, size: data.length };
+    }
+    const element = (await target(p)).locator(p.selector).first();
+    await element.scrollIntoViewIfNeeded({ timeout: p.timeout });
+    if (!p.highlight) {
+      const data = await element.screenshot({ path: p.path, timeout: p.timeout });
+      return { path: p.path, size: data.length, selector: p.selector, box: await element.boundingBox() };
+    }
+    // The page around the element, so the box shows where it sits
+    await mark(element, p.highlight);
+    try {
+      const data = await page.screenshot({ fullPage: p.full_page, path: p.path, timeout: p.timeout });
+      return { path: p.path, size: data.length, selector: p.selector, box: await element.boundingBox(), highlight: p.highlight };
+    } finally {
+      await unmark(element);
+    }
+  },
+  async scroll_into_view(p) {
+    const page = await current(p);
+    const element = (await target(p)).locator(p.selector).first();
+    await element.scrollIntoViewIfNeeded({ timeout: p.timeout });
+    const box = await element.boundingBox();
+    const viewport = page.viewportSize();
+    const visible = !!box && !!viewport && box.y < viewport.height && box.y + box.height > 0
+      && box.x < viewport.width && box.x + box.width > 0;
+    return { selector: p.selector, box, in_viewport: visible, scroll: await page.evaluate(() => ({ x: window.scrollX, y: window.scrollY })) };
+  },
+  async evaluate(p) {
+    const scope = await target(p);
+    return { result: await scope.evaluate(`(async () => { ${p.code} })()`) };
+  },
+  async content(p) {
+    const page = await current(p);
+    return { content: (await page.content()).substring(0, p.max_chars) };
+  },
+  async url(p) {
+    return { url: (await current(p)).url() };
+  },
+  async title(p) {
+    return { title: await (await current(p)).title() };
+  },
+  async wait(p) {
+    const started = Date.now();
+    if (!p.selector) {
+      await (await current(p)).waitForTimeout(p.timeout);
+      return { waited_ms: Date.now() - started };
+    }
+    const scope = await target(p);
+    const element = await scope.waitForSelector(p.selector, { state: p.state, timeout: p.timeout });
+    const found = element && (await element.evaluate((el) => ({
+      tag: el.tagName.toLowerCase(),
+      text: (el.innerText ?? el.textContent ?? "").trim().slice(0, 500),
+      visible: !!(el.offsetWidth || el.offsetHeight || el.getClientRects().length),
+    })));
+    return { selector: p.selector, state: p.state, element: found, waited_ms: Date.now() - started };
+  },
+  async wait_for_load(p) {
+    const page = await current(p);
+    const started = Date.now();
+    await page.waitForLoadState(p.state, { timeout: p.timeout });
+    return { state: p.state, url: page.url(), waited_ms: Date.now() - started };
+  },
+  async wait_for_url(p) {
+    const page = await current(p);
+    const started = Date.now();
+    const matches = matcher(p.pattern);
+    await page.waitForURL((url) => matches(url.href), { timeout: p.timeout });
+    return { url: page.url(), waited_ms: Date.now() - started };
+  },
+  async wait_for_request(p) {
+    const page = await current(p);
+    const started = Date.now();
+    const matches = matcher(p.pattern);
+    const { seen } = await open(p);
+    const earlier = recall(seen, seen.requests, matches);
+    const request = earlier ?? (await page.waitForRequest((r) => matches(r.url()), { timeout: p.timeout }));
+    return { request: describeRequest(request), already_seen: !!earlier, waited_ms: Date.now() - started };
+  },
+  async wait_for_response(p) {
+    const page = await current(p);
+    const started = Date.now();
+    const matches = matcher(p.pattern);
+    const { seen } = await open(p);
+    const earlier = recall(seen, seen.responses, matches);
+    const response = earlier ?? (await page.waitForResponse((r) => matches(r.url()), { timeout: p.timeout }));
+    return { response: await describeResponse(response), already_seen: !!earlier, waited_ms: Date.now() - started };
+  },
+  async wait_for_function(p) {
+    const scope = await target(p);
+    const started = Date.now();
+    // An expression, or a function body when it returns
+    const predicate = /\breturn\b/.test(p.code) ? `(() => { ${p.code} })()` : p.code;
+    const handle = await scope.waitForFunction(predicate, undefined, { timeout: p.timeout });
+    return { value: await handle.jsonValue().catch(() => null), waited_ms: Date.now() - started };
+  },
+  async wait_for_event(p) {
+    const page = await current(p);
+    const started = Date.now();
+    const value = await page.waitForEvent(p.event, { timeout: p.timeout });
+    return { event: p.event, value: await describeEvent(p.event, value), waited_ms: Date.now() - started };
+  },
+  async cookies(p) {
+    const page = await current(p);
+    const { context } = await open(p);
+    if (p.set) {
+      const cookies = p.set.map((c) => (c.url || c.domain ? c : { ...c, url: page.url() }));
+      if (cookies.some((c) => !c.domain && !/^https?:/.test(c.url))) {
+        throw new Error("Give each cookie a url or a domain and path, or navigate to the site first");
+      }
+      await context.addCookies(cookies);
+      return { set: cookies.map((c) => c.name), count: cookies.length };
+    }
+    const cookies = (await context.cookies(p.urls)).filter((c) => !p.name || c.name === p.name);
+    return { cookies, count: cookies.length };
+  },
+  async clear_cookies(p) {
+    const { context } = await open(p);
+    const all = await context.cookies();
+    await context.clearCookies();
+    if (!p.filters.length) return { cleared: all.length };
+    // Playwright before 1.43 clears only everything; put the rest back
+    const doomed = all.filter((c) => p.filters.some((filter) => picks(c, filter)));
+    await context.addCookies(all.filter((c) => !doomed.includes(c)));
+    return { cleared: doomed.length, cookies: doomed.map(({ name, domain, path }) => ({ name, domain, path })) };
+  },
+  async storage(p) {
+    const scope = await target(p);
+    return scope.evaluate(({ kind, key, data }) => {
+      const store = kind === "session" ? sessionStorage : localStorage;
+      const origin = location.origin;
+      if (data) {
+        for (const [name, value] of Object.entries(data)) {
+          if (value === null) store.removeItem(name);
+          else store.setItem(name, value);
+        }
+        return { origin, set: Object.keys(data), count: store.length };
+      }
+      if (key) return { origin, key, value: store.getItem(key) };
+      const items = {};
+      for (let i = 0; i < store.length; i++) items[store.key(i)] = store.getItem(store.key(i));
+      return { origin, items, count: store.length };
+    }, { kind: p.kind, key: p.key, data: p.data });
+  },
+  async emulate(p) {
+    const page = await current(p);
+    const entry = await open(p);
+    const url = page.url();
+    // Viewport aside, a context's device can't change: replace it, keeping
+    // cookies and storage, and reopen the page it was on
+    const storageState = await entry.context.storageState();
+    await entry.context.close();
+    entry.options = p.options;
+    entry.context = await state.browser.newContext({ ...entry.options, storageState });
+    entry.page = null;
+    entry.chooser = null;
+    const fresh = await current(p);
+    if (/^https?:/.test(url)) await fresh.goto(url, { timeout: p.timeout });
+    return { url: fresh.url(), user_agent: await fresh.evaluate(() => navigator.userAgent) };
+  },
+  async extract(p) {
+    const scope = await target(p);
+    const page = await current(p);
+    const { content, root } = await scope.evaluate(readable, { selector: p.selector ?? null, plain: p.plain });
+    const truncated = content.length > p.max_chars;
+    return {
+      url: scope.url(),
+      title: await page.title(),
+      root,
+      content: truncated ? content.slice(0, p.max_chars) : content,
+      chars: content.length,
+      truncated,
+    };
+  },
+  dialog(p) {
+    const policy = { accept: p.accept, prompt_text: p.prompt_text ?? null };
+    if (p.origin) {
+      dialogs.rules = dialogs.rules.filter((r) => r.origin !== p.origin);
+      dialogs.rules.push({ origin: p.origin, ...policy });
+    } else {
+      dialogs.policy = policy;
+    }
+    return { policy: dialogs.policy, rules: dialogs.rules };
+  },
+  dialogs() {
+    return { policy: dialogs.policy, rules: dialogs.rules, log: dialogs.log, count: dialogs.log.length };
+  },
+  async new_context(p) {
+    const fresh = !state.contexts.has(p.context);
+    const entry = await open(p, p.options ?? {});
+    return { context: entry.name, created: fresh, contexts: [...state.contexts.keys()] };
+  },
+  async contexts() {
+    const contexts = [];
+    for (const entry of state.contexts.values()) {
+      const page = entry.page && !entry.page.isClosed() ? entry.page : null;
+      contexts.push({
+        name: entry.name,
+        url: page?.url() ?? null,
+        pages: entry.context?.pages().length ?? 0,
+        cookies: entry.context ? (await entry.context.cookies()).length : 0,
+        viewport: entry.options.viewport ?? null,
+      });
+    }
+    return { contexts, count: contexts.length };
+  },
+  async close_context(p) {
+    const entry = state.contexts.get(p.context);
+    if (!entry) throw new Error(`No context named ${p.context}`);
+    state.contexts.delete(p.context);
+    await entry.context?.close();
+    return { closed: p.context, contexts: [...state.contexts.keys()] };
+  },
+  async frames(p) {
+    const page = await current(p);
+    return { frames: await tree(page.mainFrame(), page.mainFrame()), count: page.frames().length };
+  },
+  async upload(p) {
+    const page = await current(p);
+    const entry = await open(p);
+    let chooser = null;
+    if (!p.selector) {
+      chooser = entry.chooser;
+      if (!chooser) throw new Error("No file chooser is open; give the selector of a file input or of the control that opens one");
+    } else {
+      const element = (await target(p)).locator(p.selector).first();
+      const input = await element.evaluate((el) => el instanceof HTMLInputElement && el.type === "file", null, { timeout: p.timeout });
+      if (input) {
+        await element.setInputFiles(p.files, { timeout: p.timeout });
+        return { selector: p.selector, via: "input", ...(await files(element)) };
+      }
+      entry.chooser = null;
+      [chooser] = await Promise.all([page.waitForEvent("filechooser", { timeout: p.timeout }), element.click({ timeout: p.timeout })]);
+    }
+    entry.chooser = null;
+    if (p.files.length > 1 && !chooser.isMultiple()) {
+      throw new Error(`The file chooser takes one file, not ${p.files.length}`);
+    }
+    await chooser.setFiles(p.files, { timeout: p.timeout });
+    return { selector: p.selector ?? null, via: "file_chooser", ...(await files(chooser.element())) };
+  },
+};
+
+async function handle(request) {
+  const response = { id: request.id };
+  const entry = state.contexts.get(request.params.context ?? "default");
+  if (entry && !request.op.startsWith("wait")) entry.seen.since = Date.now();
+  try {
+    const op = ops[request.op];
+    if (!op) throw new Error(`Unknown operation: ${request.op}`);
+    response.result = await op(request.params);
+  } catch (error) {
+    response.error = { type: error?.name ?? "Error", message: error?.message ?? String(error) };
+  }
+  process.stdout.write(MARK + JSON.stringify(response) + "\n");
+}
+
+(async () => {
+  // Not awaited: an action in one context doesn't hold up the others
+  const running = new Set();
+  for await (const line of readline.createInterface({ input: process.stdin })) {
+    const call = handle(JSON.parse(line)).finally(() => running.delete(call));
+    running.add(call);
+  }
+  await Promise.allSettled(running);
+  if (state.browser) await state.browser.close();
+})();
+"##;
+
+pub struct BrowserSession {
+    child: Mutex<Child>,
+    /// None once closed
+    stdin: tokio::sync::Mutex<Option<ChildStdin>>,
+    pending: Pending,
+    stderr: Arc<Mutex<String>>,
+    headless: bool,
+    calls: AtomicU64,
+    next_id: AtomicU64,
+    started: chrono::DateTime<chrono::Utc>,
+}
+
+impl BrowserSession {
+    pub async fn spawn(headless: bool) -> Result<Self> {
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let mark = format!("\u{1e}hanzo-browser:{:x}:", nanos);
+        let mut child = Command::new("node")
+            .arg("-e")
+            .arg(DRIVER)
+            .env("HANZO_BROWSER_MARK", &mark)
+            .env("HANZO_BROWSER_HEADLESS", if headless { "1" } else { "0" })
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => ToolError::unsupported("node is not installed; the browser tool runs Playwright under node"),
+                _ => ToolError::external(format!("Cannot start the browser driver: {}", e)),
+            })?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let pending = Pending::default();
+        let waiting = pending.clone();
+        tokio::spawn(async move {
+            while let Ok(Some(line)) = stdout.next_line().await {
+                let Some(response) = line.strip_prefix(mark.as_str()).and_then(|m| serde_json::from_str::<Value>(m).ok()) else { continue };
+                let sender = response["id"].as_u64().and_then(|id| waiting.lock().unwrap().remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(response);
+                }
+            }
+            // The driver exited: calls still waiting get no answer
+            waiting.lock().unwrap().clear();
+        });
+        let stderr = Arc::new(Mutex::new(String::new()));
+        let mut pipe = child.stderr.take().expect("stderr is piped");
+        let sink = stderr.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = pipe.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                sink.lock().unwrap().push_str(&String::from_utf8_lossy(&buf[..n]));
+            }
+        });
+
+        Ok(Self {
+            child: Mutex::new(child),
+            stdin: tokio::sync::Mutex::new(Some(stdin)),
+            pending,
+            stderr,
+            headless,
+            calls: AtomicU64::new(0),
+            next_id: AtomicU64::new(1),
+            started: chrono::Utc::now(),
+        })
+    }
+
+    /// Run `op` and wait for its result; None if `limit` passed or the
+    /// driver exited first. Calls may overlap.
+    pub async fn call(&self, op: &str, params: Value, limit: Duration) -> Result<Option<Value>> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, answer) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+        let line = format!("{}\n", json!({ "id": id, "op": op, "params": params }));
+        let written = match self.stdin.lock().await.as_mut() {
+            Some(stdin) => match stdin.write_all(line.as_bytes()).await {
+                Ok(()) => stdin.flush().await,
+                Err(e) => Err(e),
+            },
+            None => Err(std::io::ErrorKind::BrokenPipe.into()),
+        };
+        if let Err(e) = written {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(ToolError::external(format!("Browser driver has exited: {} {}", e, self.take_stderr().trim())).into());
+        }
+        self.calls.fetch_add(1, Ordering::Relaxed);
+
+        let mut response = match tokio::time::timeout(limit, answer).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) | Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                return Ok(None);
+            }
+        };
+        let error = &response["error"];
+        if !error.is_null() {
+            let message = format!("Playwright error: {}", error["message"].as_str().unwrap_or("unknown"));
+            return Err(match error["type"].as_str() {
+                Some("TimeoutError") => ToolError::timeout(message),
+                _ => ToolError::external(message),
+            }.into());
+        }
+        Ok(Some(response["result"].take()))
+    }
+
+    /// What the driver wrote to stderr since last asked
+    pub fn take_stderr(&self) -> String {
+        std::mem::take(&mut *self.stderr.lock().unwrap())
+    }
+
+    pub fn running(&self) -> bool {
+        matches!(self.child.lock().unwrap().try_wait(), Ok(None))
+    }
+
+    pub async fn close(&self) {
+        // Closing stdin lets the driver close the browser; kill it if it hangs
+        drop(self.stdin.lock().await.take());
+        for _ in 0..50 {
+            if !self.running() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        let _ = self.child.lock().unwrap().start_kill();
+    }
+
+    pub fn describe(&self) -> Value {
+        json!({
+            "pid": self.child.lock().unwrap().id(),
+            "running": self.running(),
+            "headless": self.headless,
+            "calls": self.calls.load(Ordering::Relaxed),
+            "started": self.started.to_rfc3339()
+        })
+    }
+}
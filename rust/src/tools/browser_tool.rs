@@ -5,14 +5,54 @@
 /// - click/type/fill: Interact with elements
 /// - screenshot: Capture page
 /// - evaluate: Run JavaScript
+/// - upload: Attach files to a file input or an open file chooser
 /// - And 90+ more actions
+///
+/// Actions run in one browser that stays open between calls (see
+/// `browser_session`), so each acts on the page the last one left. A
+/// `context` name gives an action its own isolated context (cookies,
+/// storage, page); actions in different contexts can run at once. Named
+/// contexts belong to the session that opened them: other sessions can't
+/// see or use them, and they close when it ends.
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use crate::error::ToolError;
+use super::browser_session::BrowserSession;
+use super::workspace_roots::Roots;
+use crate::config::{BrowserConfig, DeviceProfile};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Timeout of actions that take one and were given none
+const ACTION_TIMEOUT_MS: i32 = 30_000;
+
+/// Outline of an element a screenshot highlights, unless one is named
+const HIGHLIGHT_COLOR: &str = "#ff2d55";
+
+/// Characters of page text extract returns at most
+const MAX_EXTRACT_CHARS: usize = 100_000;
+
+/// Between a session's id and its own name for a context
+const CONTEXT_SEP: char = '/';
+
+/// Time on top of an action's timeout for launching the browser
+const LAUNCH_GRACE: Duration = Duration::from_secs(60);
+
+const IPHONE_UA: &str = "Mozilla/5.0 (iPhone; CPU iPhone OS 16_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.0 Mobile/15E148 Safari/604.1";
+const IPAD_UA: &str = "Mozilla/5.0 (iPad; CPU OS 16_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.0 Mobile/15E148 Safari/604.1";
+const PIXEL_UA: &str = "Mozilla/5.0 (Linux; Android 14; Pixel 7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36";
+const MAC_UA: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+const WINDOWS_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36";
+
+/// Built-in devices, as `emulate` and help name them
+const DEVICES: &[&str] = &["mobile", "tablet", "laptop", "desktop", "iphone_14", "pixel_7", "ipad_pro"];
 
 /// Browser actions (subset of Playwright API)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -29,11 +69,14 @@ pub enum BrowserAction {
     Url,
     Title,
     SetContent,
+    Frames,
+    Extract,
     // Input
     Click,
     Dblclick,
     Type,
     Fill,
+    FillForm,
     Clear,
     Press,
     SelectOption,
@@ -48,6 +91,7 @@ pub enum BrowserAction {
     MouseUp,
     MouseWheel,
     Scroll,
+    ScrollIntoView,
     // Touch
     Tap,
     Swipe,
@@ -118,9 +162,12 @@ pub enum BrowserAction {
     Off,
     // Dialog
     Dialog,
+    Dialogs,
     // Browser management
     NewPage,
     NewContext,
+    Contexts,
+    CloseContext,
     NewTab,
     CloseTab,
     Tabs,
@@ -157,10 +204,13 @@ impl std::str::FromStr for BrowserAction {
             "url" => Ok(Self::Url),
             "title" => Ok(Self::Title),
             "set_content" => Ok(Self::SetContent),
+            "frames" | "iframes" => Ok(Self::Frames),
+            "extract" | "readable" | "markdown" => Ok(Self::Extract),
             "click" => Ok(Self::Click),
             "dblclick" | "double_click" => Ok(Self::Dblclick),
             "type" => Ok(Self::Type),
             "fill" => Ok(Self::Fill),
+            "fill_form" | "autofill" => Ok(Self::FillForm),
             "clear" => Ok(Self::Clear),
             "press" => Ok(Self::Press),
             "select_option" | "select" => Ok(Self::SelectOption),
@@ -174,6 +224,7 @@ impl std::str::FromStr for BrowserAction {
             "mouse_up" => Ok(Self::MouseUp),
             "mouse_wheel" => Ok(Self::MouseWheel),
             "scroll" => Ok(Self::Scroll),
+            "scroll_into_view" | "scroll_to" => Ok(Self::ScrollIntoView),
             "tap" => Ok(Self::Tap),
             "swipe" => Ok(Self::Swipe),
             "pinch" => Ok(Self::Pinch),
@@ -212,7 +263,7 @@ impl std::str::FromStr for BrowserAction {
             "evaluate" | "eval" | "js" => Ok(Self::Evaluate),
             "focus" => Ok(Self::Focus),
             "blur" => Ok(Self::Blur),
-            "wait" => Ok(Self::Wait),
+            "wait" | "wait_for_selector" => Ok(Self::Wait),
             "wait_for_load" | "wait_load" => Ok(Self::WaitForLoad),
             "wait_for_url" => Ok(Self::WaitForUrl),
             "wait_for_event" => Ok(Self::WaitForEvent),
@@ -225,15 +276,18 @@ impl std::str::FromStr for BrowserAction {
             "permissions" => Ok(Self::Permissions),
             "route" => Ok(Self::Route),
             "unroute" => Ok(Self::Unroute),
-            "cookies" => Ok(Self::Cookies),
-            "clear_cookies" => Ok(Self::ClearCookies),
+            "cookies" | "get_cookies" | "set_cookies" => Ok(Self::Cookies),
+            "clear_cookies" | "delete_cookies" => Ok(Self::ClearCookies),
             "storage" => Ok(Self::Storage),
             "storage_state" => Ok(Self::StorageState),
             "on" | "listen" => Ok(Self::On),
             "off" | "unlisten" => Ok(Self::Off),
-            "dialog" => Ok(Self::Dialog),
+            "dialog" | "on_dialog" => Ok(Self::Dialog),
+            "dialogs" | "dialog_log" => Ok(Self::Dialogs),
             "new_page" => Ok(Self::NewPage),
             "new_context" => Ok(Self::NewContext),
+            "contexts" | "list_contexts" => Ok(Self::Contexts),
+            "close_context" => Ok(Self::CloseContext),
             "new_tab" => Ok(Self::NewTab),
             "close_tab" => Ok(Self::CloseTab),
             "tabs" => Ok(Self::Tabs),
@@ -246,7 +300,7 @@ impl std::str::FromStr for BrowserAction {
             "console" => Ok(Self::Console),
             "errors" => Ok(Self::Errors),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -265,6 +319,8 @@ pub struct BrowserToolArgs {
     pub ref_: Option<String>,
     // Text/Input
     pub text: Option<String>,
+    /// fill_form: label, name or placeholder of each field and its value
+    pub fields: Option<serde_json::Map<String, Value>>,
     pub key: Option<String>,
     pub value: Option<String>,
     // Coordinates
@@ -275,6 +331,8 @@ pub struct BrowserToolArgs {
     // Options
     pub timeout: Option<i32>,
     pub full_page: Option<bool>,
+    /// screenshot: outline the element at selector, true or a CSS color
+    pub highlight: Option<Value>,
     pub exact: Option<bool>,
     #[serde(default)]
     pub not_: bool,
@@ -300,6 +358,8 @@ pub struct BrowserToolArgs {
     pub longitude: Option<f64>,
     // Files
     pub files: Option<Vec<String>>,
+    #[serde(default)]
+    pub dry_run: bool,
     // JavaScript
     pub code: Option<String>,
     // Network
@@ -320,12 +380,18 @@ pub struct BrowserToolArgs {
     #[serde(default = "default_true")]
     pub accept: bool,
     pub prompt_text: Option<String>,
+    /// dialog: answer this way only on pages of matching origins
+    pub origin: Option<String>,
     // Console
     pub level: Option<String>,
     // Permission
     pub permission: Option<String>,
     // Frame
     pub frame: Option<String>,
+    /// Isolated browser context to act in (default: "default")
+    pub context: Option<String>,
+    // Extract: markdown (default) or text
+    pub format: Option<String>,
     // Connection
     pub cdp_endpoint: Option<String>,
     pub auth_file: Option<String>,
@@ -338,12 +404,45 @@ pub struct BrowserToolArgs {
     pub distance: Option<i32>,
     pub scale: Option<f64>,
     pub button: Option<String>,
+    /// Session of the call, whose workspace roots bound uploads
+    #[serde(skip)]
+    pub session_id: Option<String>,
 }
 
 fn default_true() -> bool {
     true
 }
 
+/// The browser session's name for the context `session` calls `name`
+fn scoped(session: Option<&str>, name: &str) -> String {
+    match session {
+        Some(session) => format!("{}{}{}", session, CONTEXT_SEP, name),
+        None => name.to_string(),
+    }
+}
+
+/// What `session` calls the context the browser session names `name`, if
+/// it may use it: its own, or one shared by all such as the default
+fn unscoped<'a>(session: Option<&str>, name: &'a str) -> Option<&'a str> {
+    let own = session.and_then(|session| name.strip_prefix(session)?.strip_prefix(CONTEXT_SEP));
+    own.or_else(|| (!name.contains(CONTEXT_SEP)).then_some(name))
+}
+
+/// `result` with context names as `session` knows them and other
+/// sessions' contexts left out
+fn own_contexts(mut result: Value, session: Option<&str>) -> Value {
+    for key in ["context", "closed"] {
+        if let Some(name) = result[key].as_str().and_then(|name| unscoped(session, name)) {
+            result[key] = json!(name);
+        }
+    }
+    if let Some(names) = result["contexts"].as_array() {
+        let names: Vec<&str> = names.iter().filter_map(|name| unscoped(session, name.as_str()?)).collect();
+        result["contexts"] = json!(names);
+    }
+    result
+}
+
 /// Browser state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BrowserState {
@@ -353,38 +452,86 @@ pub struct BrowserState {
     pub current_url: Option<String>,
 }
 
-/// Browser tool - delegates to Playwright via subprocess or CDP
+/// Browser tool - delegates to Playwright in a persistent node session
 pub struct BrowserTool {
     headless: bool,
     cdp_port: u16,
+    /// Roots files to upload must lie in
+    roots: Arc<Roots>,
+    /// Started by the first action that needs a page, ended by close
+    session: Mutex<Option<Arc<BrowserSession>>>,
+    /// Device profiles from the config, by normalized name
+    devices: HashMap<String, DeviceProfile>,
 }
 
 impl BrowserTool {
     pub fn new() -> Self {
+        Self::with_roots(Arc::new(Roots::new()))
+    }
+
+    /// A browser that uploads only files inside `roots`
+    pub fn with_roots(roots: Arc<Roots>) -> Self {
         Self {
             headless: true,
             cdp_port: 9222,
+            roots,
+            session: Mutex::new(None),
+            devices: HashMap::new(),
         }
     }
 
-    pub async fn execute(&self, args: BrowserToolArgs) -> Result<String> {
+    /// Offer the config's device profiles to emulate
+    pub fn configured(mut self, config: BrowserConfig) -> Self {
+        self.devices = config.devices.into_iter().map(|(name, profile)| (device_name(&name), profile)).collect();
+        self
+    }
+
+    pub async fn execute(&self, mut args: BrowserToolArgs) -> Result<String> {
         let action: BrowserAction = if args.action.is_empty() {
             BrowserAction::Status
         } else {
             args.action.parse()?
         };
+        if matches!(action, BrowserAction::NewContext | BrowserAction::CloseContext) && args.context.is_none() {
+            args.context = args.name.take();
+        }
+        if args.context.as_deref().is_some_and(|name| name.trim().is_empty()) {
+            return Err(ToolError::invalid("context is empty").into());
+        }
+        args.context = args.context.as_deref().map(|name| scoped(args.session_id.as_deref(), name.trim()));
 
         let result = match action {
             BrowserAction::Navigate => self.navigate(args).await?,
             BrowserAction::Click => self.click(args).await?,
             BrowserAction::Type => self.type_text(args).await?,
             BrowserAction::Fill => self.fill(args).await?,
+            BrowserAction::FillForm => self.fill_form(args).await?,
             BrowserAction::Screenshot => self.screenshot(args).await?,
+            BrowserAction::ScrollIntoView => self.scroll_into_view(args).await?,
             BrowserAction::Evaluate => self.evaluate(args).await?,
             BrowserAction::Content => self.content(args).await?,
             BrowserAction::Url => self.url(args).await?,
             BrowserAction::Title => self.title(args).await?,
+            BrowserAction::Frames => self.frames(args).await?,
+            BrowserAction::Extract => self.extract(args).await?,
+            BrowserAction::Cookies => self.cookies(args).await?,
+            BrowserAction::ClearCookies => self.clear_cookies(args).await?,
+            BrowserAction::Storage => self.storage(args).await?,
+            BrowserAction::Emulate => self.emulate(args).await?,
+            BrowserAction::Dialog => self.dialog(args).await?,
+            BrowserAction::NewContext => self.new_context(args).await?,
+            BrowserAction::Contexts => self.contexts(args).await?,
+            BrowserAction::CloseContext => self.close_context(args).await?,
+            BrowserAction::Dialogs => self.call(None, "dialogs", json!({}), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await?,
+            BrowserAction::GetText => self.get_text(args).await?,
+            BrowserAction::Wait | BrowserAction::WaitForLoad | BrowserAction::WaitForUrl | BrowserAction::WaitForEvent
+            | BrowserAction::WaitForRequest | BrowserAction::WaitForResponse | BrowserAction::WaitForFunction => {
+                self.wait(&action, args).await?
+            }
             BrowserAction::Status => self.status(args).await?,
+            BrowserAction::Close => self.close().await,
+            BrowserAction::Upload if args.dry_run => self.upload_preview(args)?,
+            BrowserAction::Upload => self.upload(args).await?,
             BrowserAction::Help => self.help()?,
             // Delegate other actions to generic handler
             _ => self.generic_action(args).await?,
@@ -393,189 +540,410 @@ impl BrowserTool {
         Ok(serde_json::to_string(&result)?)
     }
 
-    async fn run_playwright_command(&self, script: &str) -> Result<Value> {
-        // Use node with playwright to execute browser commands
-        let full_script = format!(
-            r#"
-const {{ chromium }} = require('playwright');
-(async () => {{
-    const browser = await chromium.launch({{ headless: {} }});
-    const context = await browser.newContext();
-    const page = await context.newPage();
-    try {{
-        {}
-    }} finally {{
-        await browser.close();
-    }}
-}})().catch(e => console.error(JSON.stringify({{ error: e.message }})));
-"#,
-            self.headless, script
-        );
-
-        let output = Command::new("node")
-            .arg("-e")
-            .arg(&full_script)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .output()
-            .await?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let stderr = String::from_utf8_lossy(&output.stderr);
-
-        if !output.status.success() {
-            if !stderr.is_empty() {
-                return Err(anyhow!("Playwright error: {}", stderr));
+    /// Run `op` in the browser session, in the named context (else the
+    /// default one), starting the session on first use. A session that
+    /// does not answer within the action's timeout (plus time to launch
+    /// the browser) is stopped, losing the pages of every context.
+    async fn call(&self, context: Option<&str>, op: &str, mut params: Value, timeout_ms: i32) -> Result<Value> {
+        let session = {
+            let mut slot = self.session.lock().await;
+            match slot.as_ref().filter(|session| session.running()) {
+                Some(session) => session.clone(),
+                None => slot.insert(Arc::new(BrowserSession::spawn(self.headless).await?)).clone(),
             }
+        };
+        if let Some(context) = context {
+            params["context"] = json!(context);
         }
-
-        if let Ok(val) = serde_json::from_str(&stdout) {
-            Ok(val)
-        } else {
-            Ok(json!({"output": stdout.trim()}))
+        let limit = Duration::from_millis(timeout_ms.max(0) as u64) + LAUNCH_GRACE;
+        match session.call(op, params, limit).await? {
+            Some(result) => Ok(result),
+            None => {
+                let stderr = session.take_stderr();
+                // Unless a call that also timed out already replaced it
+                let mut slot = self.session.lock().await;
+                if slot.as_ref().is_some_and(|current| Arc::ptr_eq(current, &session)) {
+                    slot.take();
+                }
+                drop(slot);
+                session.close().await;
+                Err(ToolError::timeout(format!(
+                    "Browser did not answer {} within {}s and was closed {}", op, limit.as_secs(), stderr.trim()
+                )).into())
+            }
         }
     }
 
     async fn navigate(&self, args: BrowserToolArgs) -> Result<Value> {
-        let url = args.url.ok_or_else(|| anyhow!("url required"))?;
+        let url = args.url.ok_or_else(|| ToolError::invalid("url required"))?;
         let timeout = args.timeout.unwrap_or(30000);
-
-        let script = format!(
-            r#"
-await page.goto('{}', {{ timeout: {} }});
-console.log(JSON.stringify({{ url: page.url(), title: await page.title() }}));
-"#,
-            url.replace('\'', "\\'"),
-            timeout
-        );
-
-        self.run_playwright_command(&script).await
+        self.call(args.context.as_deref(), "navigate", json!({ "url": url, "timeout": timeout }), timeout).await
     }
 
     async fn click(&self, args: BrowserToolArgs) -> Result<Value> {
         let selector = args.selector.or(args.ref_)
-            .ok_or_else(|| anyhow!("selector required"))?;
+            .ok_or_else(|| ToolError::invalid("selector required"))?;
         let timeout = args.timeout.unwrap_or(5000);
-
-        let script = format!(
-            r#"
-await page.click('{}', {{ timeout: {} }});
-console.log(JSON.stringify({{ clicked: '{}' }}));
-"#,
-            selector.replace('\'', "\\'"),
-            timeout,
-            selector.replace('\'', "\\'")
-        );
-
-        self.run_playwright_command(&script).await
+        self.call(args.context.as_deref(), "click", json!({ "selector": selector, "frame": args.frame, "timeout": timeout }), timeout).await
     }
 
     async fn type_text(&self, args: BrowserToolArgs) -> Result<Value> {
         let selector = args.selector.or(args.ref_)
-            .ok_or_else(|| anyhow!("selector required"))?;
-        let text = args.text.ok_or_else(|| anyhow!("text required"))?;
-
-        let script = format!(
-            r#"
-await page.type('{}', '{}');
-console.log(JSON.stringify({{ typed: {} }}));
-"#,
-            selector.replace('\'', "\\'"),
-            text.replace('\'', "\\'"),
-            text.len()
-        );
-
-        self.run_playwright_command(&script).await
+            .ok_or_else(|| ToolError::invalid("selector required"))?;
+        let text = args.text.ok_or_else(|| ToolError::invalid("text required"))?;
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        self.call(args.context.as_deref(), "type", json!({ "selector": selector, "text": text, "frame": args.frame, "timeout": timeout }), timeout).await
     }
 
     async fn fill(&self, args: BrowserToolArgs) -> Result<Value> {
         let selector = args.selector.or(args.ref_)
-            .ok_or_else(|| anyhow!("selector required"))?;
-        let text = args.text.ok_or_else(|| anyhow!("text required"))?;
-
-        let script = format!(
-            r#"
-await page.fill('{}', '{}');
-console.log(JSON.stringify({{ filled: '{}' }}));
-"#,
-            selector.replace('\'', "\\'"),
-            text.replace('\'', "\\'"),
-            selector.replace('\'', "\\'")
-        );
+            .ok_or_else(|| ToolError::invalid("selector required"))?;
+        let text = args.text.ok_or_else(|| ToolError::invalid("text required"))?;
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        self.call(args.context.as_deref(), "fill", json!({ "selector": selector, "text": text, "frame": args.frame, "timeout": timeout }), timeout).await
+    }
 
-        self.run_playwright_command(&script).await
+    /// Fill each field `fields` names (by label, accessible name,
+    /// placeholder, or name or id attribute) with its value, within the
+    /// form at selector if given; which fields were filled and which not
+    async fn fill_form(&self, args: BrowserToolArgs) -> Result<Value> {
+        let fields = args.fields.filter(|fields| !fields.is_empty())
+            .ok_or_else(|| ToolError::invalid("fields required: {label: value, ...}"))?;
+        for (name, value) in &fields {
+            let valid = match value {
+                Value::String(_) | Value::Number(_) | Value::Bool(_) => true,
+                Value::Array(options) => options.iter().all(Value::is_string),
+                _ => false,
+            };
+            if !valid {
+                return Err(ToolError::invalid(format!("{}: values are text, numbers, booleans or lists of options", name)).into());
+            }
+        }
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        let total = timeout.saturating_mul(fields.len() as i32);
+        let params = json!({ "fields": fields, "selector": args.selector.or(args.ref_), "frame": args.frame, "timeout": timeout });
+        self.call(args.context.as_deref(), "fill_form", params, total).await
     }
 
+    async fn get_text(&self, args: BrowserToolArgs) -> Result<Value> {
+        let selector = args.selector.or(args.ref_)
+            .ok_or_else(|| ToolError::invalid("selector required"))?;
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        self.call(args.context.as_deref(), "get_text", json!({ "selector": selector, "frame": args.frame, "timeout": timeout }), timeout).await
+    }
+
+    /// The page, or only the element at selector once scrolled to; with
+    /// highlight, the page with that element outlined
     async fn screenshot(&self, args: BrowserToolArgs) -> Result<Value> {
         let full_page = args.full_page.unwrap_or(false);
+        let selector = args.selector.or(args.ref_);
+        let highlight = match args.highlight {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => None,
+            Some(Value::Bool(true)) => Some(HIGHLIGHT_COLOR.to_string()),
+            Some(Value::String(color)) if !color.trim().is_empty() => Some(color),
+            Some(other) => return Err(ToolError::invalid(format!("highlight is true or a CSS color, not {}", other)).into()),
+        };
+        if highlight.is_some() && selector.is_none() {
+            return Err(ToolError::invalid("highlight needs the selector of the element to outline").into());
+        }
         let path = format!("/tmp/screenshot_{}.png", chrono::Utc::now().timestamp());
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        let params = json!({
+            "full_page": full_page,
+            "path": path,
+            "selector": selector,
+            "frame": args.frame,
+            "highlight": highlight,
+            "timeout": timeout,
+        });
+        self.call(args.context.as_deref(), "screenshot", params, timeout).await
+    }
 
-        let script = format!(
-            r#"
-const data = await page.screenshot({{ fullPage: {}, path: '{}' }});
-console.log(JSON.stringify({{ path: '{}', size: data.length }}));
-"#,
-            full_page, path, path
-        );
-
-        self.run_playwright_command(&script).await
+    async fn scroll_into_view(&self, args: BrowserToolArgs) -> Result<Value> {
+        let selector = args.selector.or(args.ref_)
+            .ok_or_else(|| ToolError::invalid("selector required"))?;
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        self.call(args.context.as_deref(), "scroll_into_view", json!({ "selector": selector, "frame": args.frame, "timeout": timeout }), timeout).await
     }
 
     async fn evaluate(&self, args: BrowserToolArgs) -> Result<Value> {
-        let code = args.code.ok_or_else(|| anyhow!("code required"))?;
-
-        let script = format!(
-            r#"
-const result = await page.evaluate(() => {{ {} }});
-console.log(JSON.stringify({{ result }}));
-"#,
-            code
-        );
-
-        self.run_playwright_command(&script).await
+        let code = args.code.ok_or_else(|| ToolError::invalid("code required"))?;
+        self.call(args.context.as_deref(), "evaluate", json!({ "code": code, "frame": args.frame }), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await
     }
 
     async fn content(&self, args: BrowserToolArgs) -> Result<Value> {
-        let script = r#"
-const content = await page.content();
-console.log(JSON.stringify({ content: content.substring(0, 10000) }));
-"#;
+        self.call(args.context.as_deref(), "content", json!({ "max_chars": 10000 }), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await
+    }
+
+    /// The page, or the subtree at selector, as markdown (links kept) or
+    /// plain text. Without a selector the main content is picked and
+    /// navigation, banners, footers and hidden elements are left out.
+    async fn extract(&self, args: BrowserToolArgs) -> Result<Value> {
+        let plain = match args.format.as_deref().unwrap_or("markdown") {
+            "markdown" | "md" => false,
+            "text" | "plain" => true,
+            other => return Err(ToolError::invalid(format!("format must be markdown or text, not {}", other)).into()),
+        };
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        let params = json!({
+            "selector": args.selector.or(args.ref_),
+            "frame": args.frame,
+            "plain": plain,
+            "max_chars": MAX_EXTRACT_CHARS,
+        });
+        self.call(args.context.as_deref(), "extract", params, timeout).await
+    }
+
+    async fn url(&self, args: BrowserToolArgs) -> Result<Value> {
+        self.call(args.context.as_deref(), "url", json!({}), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await
+    }
+
+    async fn title(&self, args: BrowserToolArgs) -> Result<Value> {
+        self.call(args.context.as_deref(), "title", json!({}), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await
+    }
+
+    /// Wait for a selector to reach a state, the page to load, the URL to
+    /// change, a request, response or event, or a JS predicate to hold;
+    /// what was observed. Without a selector, wait just sleeps.
+    async fn wait(&self, action: &BrowserAction, args: BrowserToolArgs) -> Result<Value> {
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        let pattern = || args.pattern.clone().or_else(|| args.url.clone())
+            .ok_or_else(|| ToolError::invalid("pattern required: part of the URL, a glob like **/api/*, or /regex/"));
+        let (op, mut params) = match action {
+            BrowserAction::Wait => {
+                let selector = args.selector.clone().or_else(|| args.ref_.clone());
+                if selector.is_none() && args.timeout.is_none() {
+                    return Err(ToolError::invalid("selector or timeout required").into());
+                }
+                let state = args.state.as_deref().unwrap_or("visible");
+                if !["attached", "detached", "visible", "hidden"].contains(&state) {
+                    return Err(ToolError::invalid(format!("Unknown state: {} (attached, detached, visible, hidden)", state)).into());
+                }
+                ("wait", json!({ "selector": selector, "state": state, "frame": args.frame }))
+            }
+            BrowserAction::WaitForLoad => {
+                let state = args.state.as_deref().unwrap_or("load");
+                if !["load", "domcontentloaded", "networkidle"].contains(&state) {
+                    return Err(ToolError::invalid(format!("Unknown load state: {} (load, domcontentloaded, networkidle)", state)).into());
+                }
+                ("wait_for_load", json!({ "state": state }))
+            }
+            BrowserAction::WaitForUrl => ("wait_for_url", json!({ "pattern": pattern()? })),
+            BrowserAction::WaitForRequest => ("wait_for_request", json!({ "pattern": pattern()? })),
+            BrowserAction::WaitForResponse => ("wait_for_response", json!({ "pattern": pattern()? })),
+            BrowserAction::WaitForFunction => {
+                let code = args.code.clone().ok_or_else(|| ToolError::invalid("code required: a JS expression, or a function body that returns"))?;
+                ("wait_for_function", json!({ "code": code, "frame": args.frame }))
+            }
+            BrowserAction::WaitForEvent => {
+                let event = args.event.clone().ok_or_else(|| ToolError::invalid("event required (popup, download, console, dialog, request, response, ...)"))?;
+                ("wait_for_event", json!({ "event": event }))
+            }
+            _ => unreachable!("execute routes only wait actions here"),
+        };
+        params["timeout"] = json!(timeout);
+        self.call(args.context.as_deref(), op, params, timeout).await
+    }
 
-        self.run_playwright_command(script).await
+    /// The context's cookies, for url and name when given; with cookies,
+    /// set those instead
+    async fn cookies(&self, args: BrowserToolArgs) -> Result<Value> {
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        let params = match &args.cookies {
+            Some(cookies) if !cookies.is_empty() => json!({ "set": cookies.iter().map(cookie).collect::<Result<Vec<_>>>()? }),
+            Some(_) => return Err(ToolError::invalid("cookies is empty").into()),
+            None => json!({ "urls": args.url.iter().collect::<Vec<_>>(), "name": args.name }),
+        };
+        self.call(args.context.as_deref(), "cookies", params, timeout).await
     }
 
-    async fn url(&self, _args: BrowserToolArgs) -> Result<Value> {
-        let script = r#"
-console.log(JSON.stringify({ url: page.url() }));
-"#;
+    /// Delete the cookies that name or the cookies list of filters
+    /// ({name, domain, path}) pick out; every cookie without either
+    async fn clear_cookies(&self, args: BrowserToolArgs) -> Result<Value> {
+        let mut filters = args.cookies.clone().unwrap_or_default();
+        if let Some(name) = &args.name {
+            filters.push(json!({ "name": name }));
+        }
+        if filters.iter().any(|f| !f.is_object()) {
+            return Err(ToolError::invalid("cookies to clear are objects with name, domain or path").into());
+        }
+        self.call(args.context.as_deref(), "clear_cookies", json!({ "filters": filters }), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await
+    }
 
-        self.run_playwright_command(script).await
+    /// Read localStorage or sessionStorage of the page's origin: every item,
+    /// or the one at key. With storage_data, set its items instead (null
+    /// removes one).
+    async fn storage(&self, args: BrowserToolArgs) -> Result<Value> {
+        let kind = match args.storage_type.as_deref().unwrap_or("local").to_lowercase().as_str() {
+            "local" | "localstorage" => "local",
+            "session" | "sessionstorage" => "session",
+            other => return Err(ToolError::invalid(format!("Unknown storage_type: {} (local, session)", other)).into()),
+        };
+        let data = match args.storage_data {
+            Some(Value::Object(items)) => Some(items.into_iter()
+                .map(|(key, value)| match value {
+                    Value::String(_) | Value::Null => (key, value),
+                    other => (key, json!(other.to_string())),
+                })
+                .collect::<serde_json::Map<_, _>>()),
+            Some(_) => return Err(ToolError::invalid("storage_data must be an object of key: value").into()),
+            None => None,
+        };
+        let params = json!({ "kind": kind, "key": args.key, "data": data, "frame": args.frame });
+        let mut result = self.call(args.context.as_deref(), "storage", params, args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await?;
+        result["storage_type"] = json!(kind);
+        Ok(result)
+    }
+
+    /// Emulate a device: its viewport, user agent, pixel ratio and touch.
+    /// The context acted in is replaced with one for the device, keeping
+    /// cookies and storage and reopening the page. width and height
+    /// override the device's viewport, or alone give a desktop one.
+    async fn emulate(&self, args: BrowserToolArgs) -> Result<Value> {
+        let (name, profile) = self.profile(&args)?;
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        let params = json!({ "options": context_options(&profile), "timeout": timeout });
+        let page = self.call(args.context.as_deref(), "emulate", params, timeout).await?;
+        Ok(json!({
+            "device": name,
+            "width": profile.width,
+            "height": profile.height,
+            "device_scale_factor": profile.device_scale_factor,
+            "is_mobile": profile.is_mobile,
+            "has_touch": profile.has_touch,
+            "user_agent": page["user_agent"],
+            "url": page["url"]
+        }))
     }
 
-    async fn title(&self, _args: BrowserToolArgs) -> Result<Value> {
-        let script = r#"
-const title = await page.title();
-console.log(JSON.stringify({ title }));
-"#;
+    /// The device args name, with width and height over its viewport
+    fn profile(&self, args: &BrowserToolArgs) -> Result<(String, DeviceProfile)> {
+        let (name, mut profile) = match args.device.as_deref() {
+            Some(device) => self.device(device)?,
+            None => match (args.width, args.height) {
+                (Some(_), Some(_)) => ("custom".to_string(), DeviceProfile {
+                    width: 0, height: 0, device_scale_factor: 1.0, user_agent: None, is_mobile: false, has_touch: false,
+                }),
+                _ => return Err(ToolError::invalid("device, or width and height, required").into()),
+            },
+        };
+        for (size, given) in [(&mut profile.width, args.width), (&mut profile.height, args.height)] {
+            if let Some(given) = given {
+                *size = u32::try_from(given).ok().filter(|&s| s > 0)
+                    .ok_or_else(|| ToolError::invalid(format!("Viewport sizes are positive, not {}", given)))?;
+            }
+        }
+        Ok((name, profile))
+    }
+
+    /// A device by name: from the config, else built in
+    fn device(&self, name: &str) -> Result<(String, DeviceProfile)> {
+        let name = device_name(name);
+        let profile = self.devices.get(&name).cloned().or_else(|| preset(&name))
+            .ok_or_else(|| ToolError::not_found(format!("Unknown device: {} ({})", name, self.device_names().join(", "))))?;
+        Ok((name, profile))
+    }
+
+    fn device_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = DEVICES.iter().map(|d| d.to_string()).collect();
+        let mut configured: Vec<String> = self.devices.keys().filter(|d| !DEVICES.contains(&d.as_str())).cloned().collect();
+        configured.sort();
+        names.extend(configured);
+        names
+    }
+
+    /// Open the isolated context context (or name) names, emulating device
+    /// if given; later actions naming it act in it. One that is open
+    /// already is left as it is.
+    async fn new_context(&self, args: BrowserToolArgs) -> Result<Value> {
+        let name = args.context.clone()
+            .ok_or_else(|| ToolError::invalid("context required: the name to address it by"))?;
+        let (mut params, mut device) = (json!({}), None);
+        if args.device.is_some() || args.width.is_some() || args.height.is_some() {
+            let (name, profile) = self.profile(&args)?;
+            params["options"] = context_options(&profile);
+            device = Some(name);
+        }
+        let mut result = self.call(Some(&name), "new_context", params, args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await?;
+        result["device"] = json!(device);
+        Ok(own_contexts(result, args.session_id.as_deref()))
+    }
 
-        self.run_playwright_command(script).await
+    async fn close_context(&self, args: BrowserToolArgs) -> Result<Value> {
+        let name = args.context.as_deref().ok_or_else(|| ToolError::invalid("context required"))?;
+        let result = self.call(None, "close_context", json!({ "context": name }), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await?;
+        Ok(own_contexts(result, args.session_id.as_deref()))
+    }
+
+    /// The calling session's contexts and the shared default one
+    async fn contexts(&self, args: BrowserToolArgs) -> Result<Value> {
+        let session = args.session_id.as_deref();
+        let mut result = self.call(None, "contexts", json!({}), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await?;
+        let contexts: Vec<Value> = result["contexts"].as_array().into_iter().flatten()
+            .filter_map(|context| {
+                let name = unscoped(session, context["name"].as_str()?)?.to_string();
+                let mut context = context.clone();
+                context["name"] = json!(name);
+                Some(context)
+            })
+            .collect();
+        result["count"] = json!(contexts.len());
+        result["contexts"] = json!(contexts);
+        Ok(result)
+    }
+
+    /// Close the contexts `session` opened; a browser that isn't running
+    /// has none to close and is not started for it
+    pub async fn end_session(&self, session: &str) {
+        let Some(browser) = self.session.lock().await.clone().filter(|browser| browser.running()) else { return };
+        let limit = Duration::from_millis(ACTION_TIMEOUT_MS as u64);
+        let Ok(Some(listed)) = browser.call("contexts", json!({}), limit).await else { return };
+        let names = listed["contexts"].as_array().into_iter().flatten().filter_map(|context| context["name"].as_str());
+        let own = names.filter(|name| name.strip_prefix(session).is_some_and(|rest| rest.starts_with(CONTEXT_SEP)));
+        for name in own {
+            if let Err(e) = browser.call("close_context", json!({ "context": name }), limit).await {
+                log::warn!("Cannot close browser context {}: {}", name, e);
+            }
+        }
+    }
+
+    /// How alerts, confirms, prompts and beforeunload dialogs are answered
+    /// from now on, on pages of origin if given (rules for an origin come
+    /// before the default); the session answers them and logs each
+    async fn dialog(&self, args: BrowserToolArgs) -> Result<Value> {
+        let origin = match args.origin.as_deref().map(str::trim) {
+            Some("") => return Err(ToolError::invalid("origin is empty").into()),
+            origin => origin,
+        };
+        let params = json!({ "accept": args.accept, "prompt_text": args.prompt_text, "origin": origin });
+        self.call(None, "dialog", params, args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await
+    }
+
+    /// The page's frame tree: name, url and a selector for each iframe
+    async fn frames(&self, args: BrowserToolArgs) -> Result<Value> {
+        self.call(args.context.as_deref(), "frames", json!({}), args.timeout.unwrap_or(ACTION_TIMEOUT_MS)).await
     }
 
     async fn status(&self, _args: BrowserToolArgs) -> Result<Value> {
         // Check if playwright is available
         let output = Command::new("npx")
             .args(["playwright", "--version"])
+            .kill_on_drop(true)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .output()
             .await;
 
         let playwright_available = output.map(|o| o.status.success()).unwrap_or(false);
+        let session = self.session.lock().await.as_ref()
+            .map(|session| session.describe())
+            .filter(|session| session["running"] == true);
 
         Ok(json!({
             "playwright_available": playwright_available,
             "headless": self.headless,
             "cdp_port": self.cdp_port,
+            "session": session,
             "actions_available": 90,
             "categories": [
                 "navigation", "input", "mouse", "touch", "locators",
@@ -585,6 +953,81 @@ console.log(JSON.stringify({ title }));
         }))
     }
 
+    /// End the session and its browser; the next action starts a fresh one
+    async fn close(&self) -> Value {
+        let session = self.session.lock().await.take();
+        let closed = session.is_some();
+        if let Some(session) = session {
+            session.close().await;
+        }
+        json!({ "closed": closed })
+    }
+
+    /// Set files on a file input, or on the file chooser a click on the
+    /// selector opens; without a selector, on the chooser the page opened
+    /// last (say from a click)
+    async fn upload(&self, args: BrowserToolArgs) -> Result<Value> {
+        let files = self.upload_files(&args)?;
+        let selector = args.selector.or(args.ref_);
+        let timeout = args.timeout.unwrap_or(ACTION_TIMEOUT_MS);
+        let mut result = self.call(args.context.as_deref(), "upload", json!({ "selector": selector, "files": files, "frame": args.frame, "timeout": timeout }), timeout).await?;
+        result["files"] = json!(files);
+        Ok(result)
+    }
+
+    /// Files to upload, each an existing file inside a workspace root the
+    /// calling session can see; relative paths are taken from its root
+    fn upload_files(&self, args: &BrowserToolArgs) -> Result<Vec<PathBuf>> {
+        let files = args.files.as_ref().filter(|f| !f.is_empty())
+            .ok_or_else(|| ToolError::invalid("files required"))?;
+        let session = args.session_id.as_deref();
+        files.iter()
+            .map(|file| {
+                let path = self.resolve(file, session).canonicalize().ok()
+                    .filter(|path| path.is_file())
+                    .ok_or_else(|| ToolError::not_found(format!("File not found: {}", file)))?;
+                if !self.allowed(&path, session) {
+                    return Err(ToolError::permission_denied(format!("{} is outside the workspace roots", path.display())).into());
+                }
+                Ok(path)
+            })
+            .collect()
+    }
+
+    fn resolve(&self, file: &str, session: Option<&str>) -> PathBuf {
+        let path = PathBuf::from(shellexpand::tilde(file).as_ref());
+        if path.is_relative() {
+            self.roots.active(session).path.join(path)
+        } else {
+            path
+        }
+    }
+
+    fn allowed(&self, path: &Path, session: Option<&str>) -> bool {
+        self.roots.visible(session).iter().any(|root| path.starts_with(&root.path))
+    }
+
+    /// Files an upload would send, resolved and checked, without a browser
+    fn upload_preview(&self, args: BrowserToolArgs) -> Result<Value> {
+        let files = args.files.as_ref().filter(|f| !f.is_empty())
+            .ok_or_else(|| ToolError::invalid("files required"))?;
+        let session = args.session_id.as_deref();
+        let files: Vec<Value> = files.iter()
+            .map(|file| {
+                let path = self.resolve(file, session);
+                let path = path.canonicalize().unwrap_or(path);
+                let size = std::fs::metadata(&path).ok().filter(|m| m.is_file()).map(|m| m.len());
+                json!({ "path": path, "exists": size.is_some(), "bytes": size, "allowed": self.allowed(&path, session) })
+            })
+            .collect();
+        Ok(json!({
+            "action": "upload",
+            "dry_run": true,
+            "selector": args.selector.or(args.ref_),
+            "files": files
+        }))
+    }
+
     async fn generic_action(&self, args: BrowserToolArgs) -> Result<Value> {
         // For actions not yet fully implemented, return guidance
         Ok(json!({
@@ -602,26 +1045,113 @@ console.log(JSON.stringify({ title }));
             "action_count": 90,
             "categories": {
                 "navigation": ["navigate", "reload", "go_back", "go_forward", "close"],
-                "input": ["click", "dblclick", "type", "fill", "clear", "press", "select_option", "check", "uncheck", "upload"],
-                "mouse": ["hover", "drag", "mouse_move", "mouse_down", "mouse_up", "mouse_wheel", "scroll"],
+                "input": ["click", "dblclick", "type", "fill", "fill_form", "clear", "press", "select_option", "check", "uncheck", "upload"],
+                "mouse": ["hover", "drag", "mouse_move", "mouse_down", "mouse_up", "mouse_wheel", "scroll", "scroll_into_view"],
                 "touch": ["tap", "swipe", "pinch"],
                 "locators": ["locator", "get_by_role", "get_by_text", "get_by_label", "get_by_placeholder", "get_by_test_id"],
-                "content": ["get_text", "get_inner_text", "get_attribute", "get_value", "get_html", "get_bounding_box"],
+                "content": ["get_text", "get_inner_text", "get_attribute", "get_value", "get_html", "get_bounding_box", "frames", "extract"],
                 "state": ["is_visible", "is_enabled", "is_checked", "is_hidden", "is_editable"],
                 "assertions": ["expect_visible", "expect_hidden", "expect_enabled", "expect_text", "expect_value"],
                 "screen": ["screenshot", "pdf", "snapshot"],
                 "javascript": ["evaluate", "focus", "blur"],
-                "wait": ["wait", "wait_for_load", "wait_for_url", "wait_for_event"],
+                "wait": ["wait", "wait_for_selector", "wait_for_load", "wait_for_url", "wait_for_event", "wait_for_request", "wait_for_response", "wait_for_function"],
                 "viewport": ["viewport", "emulate", "geolocation", "permissions"],
                 "network": ["route", "unroute"],
+                "dialogs": ["dialog", "dialogs"],
                 "storage": ["cookies", "clear_cookies", "storage", "storage_state"],
-                "browser": ["new_page", "new_context", "new_tab", "close_tab", "tabs", "status"]
+                "browser": ["new_page", "new_context", "contexts", "close_context", "new_tab", "close_tab", "tabs", "status"]
             },
-            "devices": ["mobile", "tablet", "laptop", "iphone_14", "pixel_7", "ipad_pro"]
+            "devices": self.device_names(),
+            "emulate": "emulate device (or width and height) sets viewport, user agent, pixel ratio and touch; the page reopens in a context for it, keeping cookies and storage",
+            "extract": "The page as readable markdown with links kept (format text for plain text): the main content without navigation, banners, footers or hidden elements, or all of the subtree at selector",
+            "screenshot": "The viewport (full_page for all of it), or with selector only that element, scrolled to first; highlight (true or a CSS color) outlines the element on a page capture instead. Returns the path, and for an element its box",
+            "scroll_into_view": "Scroll until the element at selector is in view; returns its box and the scroll position",
+            "fill_form": "fields {label: value} fills each field its label, accessible name, placeholder, or name or id attribute names (within the form at selector): text is typed, booleans check boxes, options are selected, a radio group takes the value or label of its choice. Returns filled and unmatched fields with why",
+            "frame": "click, type, fill, fill_form, get_text, evaluate, extract, screenshot, scroll_into_view and upload act inside the iframe frame names: its name, part of its URL, or a selector of the iframe element (see frames)",
+            "wait": "wait/wait_for_selector: selector reaches state (attached, detached, visible, hidden); wait_for_load: state (load, domcontentloaded, networkidle); wait_for_url/request/response: pattern (part of the URL, glob, or /regex/), counting traffic since the last action; wait_for_function: code holds; wait_for_event: event fires. Each waits up to timeout ms and returns what it saw",
+            "dialog": "Sets how dialogs are answered from now on: accept (default true, false dismisses) with prompt_text for prompts (else their default); with origin (part of it, a glob or /regex/) only on those pages, ahead of the default. Until set, dialogs are dismissed. dialogs lists the policy, rules and every dialog answered",
+            "cookies": "cookies lists the context's cookies (url, name narrow them) or, given cookies [{name, value, url | domain + path, expires, httpOnly, secure, sameSite}], sets them; clear_cookies deletes those name or cookies [{name, domain, path}] pick out, else all",
+            "storage": "storage reads localStorage (storage_type session for sessionStorage) of the page's origin, all items or key; storage_data {key: value} sets items, null removes one",
+            "session": "One browser stays open between calls, each acting on the page the last left; close ends it",
+            "context": "Any action given context acts in that isolated context (own cookies, storage and page), opened on first use; new_context opens one ahead, emulating device if given, contexts lists them and close_context closes one. Actions in different contexts run at once; without context they use \"default\"",
+            "upload": "files (inside the workspace roots) go to the file input at selector, to the file chooser a click on selector opens, or without a selector to the chooser the page opened last"
         }))
     }
 }
 
+/// Playwright context options emulating `profile`
+fn context_options(profile: &DeviceProfile) -> Value {
+    let mut options = json!({
+        "viewport": { "width": profile.width, "height": profile.height },
+        "deviceScaleFactor": profile.device_scale_factor,
+        "isMobile": profile.is_mobile,
+        "hasTouch": profile.has_touch
+    });
+    if let Some(user_agent) = &profile.user_agent {
+        options["userAgent"] = json!(user_agent);
+    }
+    options
+}
+
+/// `iPhone 14` and `iphone-14` are `iphone_14`
+fn device_name(name: &str) -> String {
+    name.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+/// Built-in device profiles, after Playwright's device descriptors
+fn preset(name: &str) -> Option<DeviceProfile> {
+    let (width, height, device_scale_factor, user_agent, mobile) = match name {
+        "iphone_14" | "mobile" | "iphone" => (390, 664, 3.0, IPHONE_UA, true),
+        "pixel_7" | "android" => (412, 839, 2.625, PIXEL_UA, true),
+        "ipad_pro" | "tablet" | "ipad" => (834, 1194, 2.0, IPAD_UA, true),
+        "laptop" => (1440, 900, 2.0, MAC_UA, false),
+        "desktop" => (1920, 1080, 1.0, WINDOWS_UA, false),
+        _ => return None,
+    };
+    Some(DeviceProfile {
+        width,
+        height,
+        device_scale_factor,
+        user_agent: Some(user_agent.to_string()),
+        is_mobile: mobile,
+        has_touch: mobile,
+    })
+}
+
+/// A cookie to set in Playwright's shape: `expires` in Unix seconds (an
+/// RFC 3339 date is converted), snake_case flags and any case of sameSite
+fn cookie(entry: &Value) -> Result<Value> {
+    let mut cookie = entry.as_object().cloned()
+        .ok_or_else(|| ToolError::invalid("cookies must be objects with name and value"))?;
+    for (from, to) in [("http_only", "httpOnly"), ("same_site", "sameSite")] {
+        if let Some(value) = cookie.remove(from) {
+            cookie.insert(to.to_string(), value);
+        }
+    }
+    if !(cookie.get("name").is_some_and(Value::is_string) && cookie.get("value").is_some_and(Value::is_string)) {
+        return Err(ToolError::invalid("Each cookie needs a name and a string value").into());
+    }
+    match cookie.get("expires") {
+        Some(Value::String(date)) => {
+            let at = chrono::DateTime::parse_from_rfc3339(date)
+                .map_err(|_| ToolError::invalid(format!("expires is Unix seconds or an RFC 3339 date, not {}", date)))?;
+            cookie.insert("expires".to_string(), json!(at.timestamp()));
+        }
+        Some(Value::Number(_)) | None => {}
+        Some(other) => return Err(ToolError::invalid(format!("expires is Unix seconds or an RFC 3339 date, not {}", other)).into()),
+    }
+    if let Some(same_site) = cookie.get("sameSite").and_then(Value::as_str) {
+        let same_site = match same_site.to_lowercase().as_str() {
+            "strict" => "Strict",
+            "lax" => "Lax",
+            "none" => "None",
+            _ => return Err(ToolError::invalid(format!("sameSite is Strict, Lax or None, not {}", same_site)).into()),
+        };
+        cookie.insert("sameSite".to_string(), json!(same_site));
+    }
+    Ok(Value::Object(cookie))
+}
+
 /// MCP Tool Definition
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BrowserToolDefinition {
@@ -638,14 +1168,19 @@ impl BrowserToolDefinition {
 
 90+ actions including:
 - Navigation: navigate, reload, go_back, go_forward
-- Input: click, type, fill, press, select_option
-- Mouse: hover, drag, scroll
-- Screen: screenshot, pdf, snapshot
+- Input: click, type, fill, fill_form, press, select_option, upload
+- Mouse: hover, drag, scroll, scroll_into_view
+- Screen: screenshot (of the page or one element), pdf, snapshot
 - JavaScript: evaluate
+- Content: extract turns the page (or selector) into readable markdown
+- Frames: frames lists iframes; frame targets one
+- Dialogs: dialog sets how they are answered (per origin too); dialogs logs them
+- Contexts: context names an isolated context (own cookies and storage) for any action; new_context, contexts, close_context
+- Wait: wait_for_selector, wait_for_response, wait_for_function, ...
 - Locators: get_by_role, get_by_text, get_by_label
 - Assertions: expect_visible, expect_text, expect_url
 
-Devices: mobile, tablet, laptop, iphone_14, pixel_7, ipad_pro"#.to_string(),
+Devices: mobile, tablet, laptop, desktop, iphone_14, pixel_7, ipad_pro, and [browser.devices] from the config"#.to_string(),
             input_schema: json!({
                 "type": "object",
                 "required": ["action"],
@@ -657,17 +1192,36 @@ Devices: mobile, tablet, laptop, iphone_14, pixel_7, ipad_pro"#.to_string(),
                     "url": {"type": "string", "description": "URL for navigation"},
                     "selector": {"type": "string", "description": "CSS/XPath selector"},
                     "ref": {"type": "string", "description": "Alias for selector"},
+                    "context": {"type": "string", "description": "Isolated browser context to act in, opened on first use (default: \"default\"); each has its own cookies, storage and page, and contexts run concurrently"},
+                    "frame": {"type": "string", "description": "Iframe to act in for click/type/fill/fill_form/get_text/evaluate/extract/screenshot/scroll_into_view/upload: frame name, part of its URL, or selector of the iframe element; list them with frames"},
                     "text": {"type": "string", "description": "Text for type/fill"},
-                    "key": {"type": "string", "description": "Key for press"},
+                    "fields": {"type": "object", "description": "For fill_form: {label, name or placeholder: value}; booleans check boxes, lists select several options"},
+                    "key": {"type": "string", "description": "Key for press; for storage: the item to read"},
                     "value": {"type": "string", "description": "Value for select/assertions"},
                     "x": {"type": "integer", "description": "X coordinate"},
                     "y": {"type": "integer", "description": "Y coordinate"},
                     "timeout": {"type": "integer", "description": "Timeout in ms"},
+                    "state": {"type": "string", "description": "For wait: attached, detached, visible (default) or hidden; for wait_for_load: load (default), domcontentloaded or networkidle"},
+                    "pattern": {"type": "string", "description": "For wait_for_url/request/response: part of the URL, a glob (**/api/*) or /regex/"},
+                    "event": {"type": "string", "description": "For wait_for_event: popup, download, console, dialog, request, response, ..."},
+                    "format": {"type": "string", "enum": ["markdown", "text"], "description": "For extract: markdown (default) or plain text"},
+                    "accept": {"type": "boolean", "description": "For dialog: accept (default) or dismiss dialogs"},
+                    "prompt_text": {"type": "string", "description": "For dialog: text to answer prompts with"},
+                    "origin": {"type": "string", "description": "For dialog: apply only on pages whose origin matches (part of it, a glob or /regex/)"},
                     "full_page": {"type": "boolean", "description": "Full page screenshot"},
-                    "code": {"type": "string", "description": "JavaScript code"},
-                    "device": {"type": "string", "description": "Device to emulate"},
-                    "width": {"type": "integer", "description": "Viewport width"},
-                    "height": {"type": "integer", "description": "Viewport height"}
+                    "highlight": {"type": ["boolean", "string"], "description": "For screenshot with selector: capture the page with the element outlined, true or in this CSS color"},
+                    "code": {"type": "string", "description": "JavaScript code; for wait_for_function an expression, or a function body that returns"},
+                    "device": {"type": "string", "description": "Device to emulate: mobile, tablet, laptop, desktop, iphone_14, pixel_7, ipad_pro or one from the config"},
+                    "width": {"type": "integer", "description": "Viewport width; for emulate, overrides the device's"},
+                    "height": {"type": "integer", "description": "Viewport height"},
+                    "cookies": {"type": "array", "items": {"type": "object"}, "description": "For cookies: cookies to set ({name, value, url or domain and path, expires as Unix seconds or RFC 3339, httpOnly, secure, sameSite}); for clear_cookies: filters ({name, domain, path})"},
+                    "name": {"type": "string", "description": "For cookies and clear_cookies: cookie name"},
+                    "storage_type": {"type": "string", "enum": ["local", "session"], "description": "For storage: localStorage (default) or sessionStorage"},
+                    "storage_data": {"type": "object", "description": "For storage: items to set; a null value removes the item"},
+                    "files": {"type": "array", "items": {"type": "string"}, "description": "Files for upload, inside the workspace roots; set on the file input at selector, on the file chooser clicking selector opens, or without selector on the chooser the page last opened"},
+                    "dry_run": {"type": "boolean", "description": "For upload: list the files that would be sent without sending them"},
+                    "max_bytes": {"type": "integer", "minimum": 1, "description": "Cap on the result size; longer output is cut (head and tail of logs, leading results of searches) with a cursor for the rest"},
+                    "max_output_tokens": {"type": "integer", "minimum": 1, "description": "Like max_bytes, counting about 4 bytes per token"}
                 }
             }),
         }
@@ -706,4 +1260,211 @@ mod tests {
         assert!(output.contains("browser"));
         assert!(output.contains("navigation"));
     }
+
+    #[tokio::test]
+    async fn test_upload_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("report.pdf"), "%PDF").unwrap();
+        let roots = Arc::new(Roots::new());
+        roots.add(&root.to_string_lossy(), Some("docs".into()), crate::tools::RootSource::Added).unwrap();
+        let tool = BrowserTool::with_roots(roots);
+        let upload = |files: Vec<String>, dry_run: bool| BrowserToolArgs {
+            action: "upload".to_string(),
+            selector: Some("#file".to_string()),
+            files: Some(files),
+            dry_run,
+            ..Default::default()
+        };
+
+        let inside = root.join("report.pdf").to_string_lossy().to_string();
+        assert_eq!(tool.upload_files(&upload(vec![inside.clone()], false)).unwrap(), [root.join("report.pdf")]);
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        let outside = outside.path().to_string_lossy().to_string();
+        let preview: Value = serde_json::from_str(&tool.execute(upload(vec![inside, outside.clone()], true)).await.unwrap()).unwrap();
+        assert_eq!((preview["files"][0]["allowed"].as_bool(), preview["files"][0]["bytes"].as_u64()), (Some(true), Some(4)));
+        assert_eq!(preview["files"][1]["allowed"], false);
+
+        // Refused before any browser starts
+        assert!(tool.execute(upload(vec![outside], false)).await.is_err());
+        let escape = root.join("..").join(root.file_name().unwrap()).join("missing.txt");
+        assert!(tool.execute(upload(vec![escape.to_string_lossy().to_string()], false)).await.is_err());
+        assert!(tool.execute(upload(Vec::new(), false)).await.is_err());
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_frame_targeting() {
+        assert_eq!("iframes".parse::<BrowserAction>().unwrap(), BrowserAction::Frames);
+        let args: BrowserToolArgs = serde_json::from_value(json!({ "action": "get_text", "frame": "#checkout" })).unwrap();
+        assert_eq!(args.frame.as_deref(), Some("#checkout"));
+        let tool = BrowserTool::new();
+        assert!(tool.execute(args).await.is_err());
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fill_form_fields() {
+        assert_eq!("autofill".parse::<BrowserAction>().unwrap(), BrowserAction::FillForm);
+        let tool = BrowserTool::new();
+        for args in [
+            json!({ "action": "fill_form" }),
+            json!({ "action": "fill_form", "fields": {} }),
+            json!({ "action": "fill_form", "fields": { "Email": { "value": "a@b.c" } } }),
+            json!({ "action": "fill_form", "fields": { "Toppings": ["ham", 2] } }),
+        ] {
+            let args: BrowserToolArgs = serde_json::from_value(args).unwrap();
+            let error = tool.execute(args).await.unwrap_err();
+            assert_eq!(ToolError::classify(&error).code(), "invalid_argument", "{}", error);
+        }
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_element_screenshot_arguments() {
+        assert_eq!("scroll_to".parse::<BrowserAction>().unwrap(), BrowserAction::ScrollIntoView);
+        let tool = BrowserTool::new();
+        for args in [
+            json!({ "action": "scroll_into_view" }),
+            json!({ "action": "screenshot", "highlight": true }),
+            json!({ "action": "screenshot", "selector": "#total", "highlight": 3 }),
+        ] {
+            let args: BrowserToolArgs = serde_json::from_value(args).unwrap();
+            let error = tool.execute(args).await.unwrap_err();
+            assert_eq!(ToolError::classify(&error).code(), "invalid_argument", "{}", error);
+        }
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dialog_policy_arguments() {
+        assert_eq!("dialog_log".parse::<BrowserAction>().unwrap(), BrowserAction::Dialogs);
+        let args: BrowserToolArgs = serde_json::from_value(json!({ "action": "dialog", "prompt_text": "yes" })).unwrap();
+        assert!(args.accept);
+        let args: BrowserToolArgs = serde_json::from_value(json!({ "action": "dialog", "accept": false, "origin": " " })).unwrap();
+        assert!(!args.accept);
+        let tool = BrowserTool::new();
+        let error = tool.execute(args).await.unwrap_err();
+        assert_eq!(ToolError::classify(&error).code(), "invalid_argument");
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_context_arguments() {
+        assert_eq!("list_contexts".parse::<BrowserAction>().unwrap(), BrowserAction::Contexts);
+        let args: BrowserToolArgs = serde_json::from_value(json!({ "action": "navigate", "url": "https://example.com", "context": "checkout" })).unwrap();
+        assert_eq!(args.context.as_deref(), Some("checkout"));
+        let tool = BrowserTool::new();
+        for args in [
+            json!({ "action": "new_context" }),
+            json!({ "action": "new_context", "context": "phone", "device": "nokia_3310" }),
+            json!({ "action": "close_context" }),
+            json!({ "action": "url", "context": " " }),
+        ] {
+            let args: BrowserToolArgs = serde_json::from_value(args).unwrap();
+            assert!(tool.execute(args).await.is_err());
+        }
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[test]
+    fn test_contexts_per_session() {
+        assert_eq!(scoped(Some("s1"), "checkout"), "s1/checkout");
+        assert_eq!(scoped(None, "checkout"), "checkout");
+        assert_eq!(unscoped(Some("s1"), "s1/checkout"), Some("checkout"));
+        assert_eq!(unscoped(Some("s2"), "s1/checkout"), None);
+        assert_eq!(unscoped(Some("s2"), "default"), Some("default"));
+        assert_eq!(unscoped(None, "s1/checkout"), None);
+        let listed = own_contexts(json!({ "context": "s1/a", "contexts": ["default", "s1/a", "s2/b"] }), Some("s1"));
+        assert_eq!(listed, json!({ "context": "a", "contexts": ["default", "a"] }));
+    }
+
+    #[tokio::test]
+    async fn test_session_calls_overlap() {
+        if which::which("node").is_err() {
+            return;
+        }
+        let tool = BrowserTool::new();
+        let run = |args: Value| tool.execute(serde_json::from_value(args).unwrap());
+        let (set, listed, contexts) = tokio::join!(
+            run(json!({ "action": "dialog", "accept": true, "prompt_text": "yes", "origin": "example.com" })),
+            run(json!({ "action": "dialogs" })),
+            run(json!({ "action": "contexts" })),
+        );
+        let set: Value = serde_json::from_str(&set.unwrap()).unwrap();
+        assert_eq!(set["rules"][0]["origin"], "example.com");
+        assert_eq!(set["policy"]["accept"], false);
+        assert!(listed.unwrap().contains("\"log\":[]"));
+        assert!(contexts.unwrap().contains("\"count\":0"));
+        let rules: Value = serde_json::from_str(&run(json!({ "action": "dialogs" })).await.unwrap()).unwrap();
+        assert_eq!(rules["rules"][0]["prompt_text"], "yes");
+        assert!(run(json!({ "action": "close_context", "context": "nowhere" })).await.is_err());
+        assert_eq!(tool.session.lock().await.as_ref().unwrap().describe()["calls"], 5);
+        tool.close().await;
+    }
+
+    #[tokio::test]
+    async fn test_extract_format() {
+        assert_eq!("readable".parse::<BrowserAction>().unwrap(), BrowserAction::Extract);
+        let args: BrowserToolArgs = serde_json::from_value(json!({ "action": "extract", "format": "html" })).unwrap();
+        let tool = BrowserTool::new();
+        let error = tool.execute(args).await.unwrap_err();
+        assert_eq!(ToolError::classify(&error).code(), "invalid_argument");
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[test]
+    fn test_cookie() {
+        let set = cookie(&json!({ "name": "sid", "value": "abc", "domain": ".example.com", "path": "/", "expires": "2030-01-01T00:00:00Z", "http_only": true, "same_site": "lax" })).unwrap();
+        assert_eq!(set["expires"], 1893456000);
+        assert_eq!((set["httpOnly"].as_bool(), set["sameSite"].as_str()), (Some(true), Some("Lax")));
+        assert!(set.get("http_only").is_none());
+        assert_eq!(cookie(&json!({ "name": "a", "value": "b", "expires": 1700000000 })).unwrap()["expires"], 1700000000);
+        assert!(cookie(&json!({ "name": "a" })).is_err());
+        assert!(cookie(&json!({ "name": "a", "value": "b", "expires": "tomorrow" })).is_err());
+        assert!(cookie(&json!({ "name": "a", "value": "b", "sameSite": "loose" })).is_err());
+        assert!(cookie(&json!("sid=abc")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_devices() {
+        let kiosk = DeviceProfile { width: 1080, height: 1920, device_scale_factor: 1.0, user_agent: None, is_mobile: false, has_touch: true };
+        let config = BrowserConfig { devices: HashMap::from([("Kiosk".to_string(), kiosk.clone())]) };
+        let tool = BrowserTool::new().configured(config);
+        assert_eq!(tool.device("kiosk").unwrap(), ("kiosk".to_string(), kiosk));
+        let (name, iphone) = tool.device("iPhone 14").unwrap();
+        assert_eq!((name.as_str(), iphone.width, iphone.device_scale_factor, iphone.has_touch), ("iphone_14", 390, 3.0, true));
+        assert!(iphone.user_agent.unwrap().contains("iPhone"));
+        assert_eq!(tool.device("mobile").unwrap().1.width, 390);
+        assert!(!tool.device("laptop").unwrap().1.is_mobile);
+        assert!(tool.device("nokia_3310").unwrap_err().to_string().contains("kiosk"));
+        for name in DEVICES {
+            assert!(preset(name).is_some(), "{}", name);
+        }
+
+        for args in [json!({ "action": "emulate" }), json!({ "action": "emulate", "width": 800 }), json!({ "action": "emulate", "device": "laptop", "width": -1 })] {
+            let args: BrowserToolArgs = serde_json::from_value(args).unwrap();
+            assert!(tool.execute(args).await.is_err());
+        }
+        assert!(tool.session.lock().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wait_arguments() {
+        assert_eq!("wait_for_selector".parse::<BrowserAction>().unwrap(), BrowserAction::Wait);
+        let tool = BrowserTool::new();
+        for args in [
+            json!({ "action": "wait" }),
+            json!({ "action": "wait_for_selector", "selector": "#done", "state": "gone" }),
+            json!({ "action": "wait_for_load", "state": "idle" }),
+            json!({ "action": "wait_for_response" }),
+            json!({ "action": "wait_for_function" }),
+            json!({ "action": "wait_for_event" }),
+        ] {
+            let args: BrowserToolArgs = serde_json::from_value(args).unwrap();
+            let error = tool.execute(args).await.unwrap_err();
+            assert_eq!(ToolError::classify(&error).code(), "invalid_argument", "{}", error);
+        }
+        assert!(tool.session.lock().await.is_none());
+    }
 }
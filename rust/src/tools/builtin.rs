@@ -0,0 +1,311 @@
+/// Built-in tools behind the `MCPTool` trait
+///
+/// `ToolWrapper<T>` wraps any of the crate's tools so another crate can embed
+/// it on its own, register it on a `ToolRegistry` next to its own tools,
+/// or drive it through `MCPTool::execute` with raw JSON params:
+///
+/// ```no_run
+/// use hanzo_mcp::tools::{FsTool, GitTool};
+/// use hanzo_mcp::{MCPTool, ToolRegistry, ToolWrapper};
+///
+/// # async fn run() -> anyhow::Result<()> {
+/// let fs = ToolWrapper::new(FsTool::new());
+/// let listing = fs.execute(serde_json::json!({ "action": "tree", "path": "." })).await?;
+///
+/// let mut registry = ToolRegistry::new();
+/// registry.register(Box::new(ToolWrapper::new(GitTool::new())));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Results match what the registry returns for the same call, images and
+/// file contents included as extra content blocks. Session-scoped tools
+/// (browser, exec, fs, memory, search, think, workspace) take their session
+/// from `execute_with`.
+
+use super::*;
+use crate::{CallContext, MCPTool, ToolResult, ToolWrapper};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// A tool the crate ships: its MCP definition and how one call runs
+#[async_trait::async_trait]
+pub trait BuiltinTool: Send + Sync + Sized + 'static {
+    /// `{name, description, inputSchema}`
+    fn definition() -> Value;
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult>;
+}
+
+impl<T: BuiltinTool> ToolWrapper<T> {
+    pub fn new(tool: T) -> Self {
+        Self::shared(Arc::new(RwLock::new(tool)))
+    }
+
+    /// Wrap a tool some other owner also holds
+    pub fn shared(tool: Arc<RwLock<T>>) -> Self {
+        let mut definition = T::definition();
+        Self {
+            tool,
+            name: definition["name"].as_str().unwrap_or_default().to_string(),
+            description: definition["description"].as_str().unwrap_or_default().to_string(),
+            schema: definition["inputSchema"].take(),
+            output: Some(definition["outputSchema"].take()).filter(|schema| !schema.is_null()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T: BuiltinTool> MCPTool for ToolWrapper<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters(&self) -> Value {
+        self.schema.clone()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.output.clone()
+    }
+
+    async fn execute(&self, params: Value) -> Result<ToolResult> {
+        T::call(&self.tool, params, &CallContext::default()).await
+    }
+
+    async fn execute_with(&self, params: Value, context: &CallContext) -> Result<ToolResult> {
+        T::call(&self.tool, params, context).await
+    }
+}
+
+/// Names the crate's tools went by before HIP-0300
+pub type FileSystemTool = ToolWrapper<FsTool>;
+pub type ShellTool = ToolWrapper<ExecTool>;
+pub type ComputerControlTool = ToolWrapper<ComputerTool>;
+
+/// Definition from a `*ToolDefinition` with description and input_schema fields
+macro_rules! definition {
+    ($name:literal, $definition:ty) => {{
+        let definition = <$definition>::new();
+        json!({ "name": $name, "description": definition.description, "inputSchema": definition.input_schema })
+    }};
+    ($name:literal, $definition:ty, output) => {{
+        let definition = <$definition>::new();
+        json!({
+            "name": $name,
+            "description": definition.description,
+            "inputSchema": definition.input_schema,
+            "outputSchema": definition.output_schema
+        })
+    }};
+}
+
+/// A tool whose `execute` takes its args type and returns JSON, as a
+/// `Value` or as text
+macro_rules! builtin {
+    ($tool:ty, $args:ty, $definition:expr, $content:expr) => {
+        #[async_trait::async_trait]
+        impl BuiltinTool for $tool {
+            fn definition() -> Value {
+                $definition
+            }
+
+            async fn call(tool: &RwLock<Self>, params: Value, _context: &CallContext) -> Result<ToolResult> {
+                let args: $args = serde_json::from_value(params)?;
+                let result = tool.read().await.execute(args).await?;
+                Ok(ToolResult::ok($content(result)?))
+            }
+        }
+    };
+}
+
+fn parsed(text: String) -> Result<Value> {
+    Ok(serde_json::from_str(&text)?)
+}
+
+fn value(value: Value) -> Result<Value> {
+    Ok(value)
+}
+
+builtin!(PlanTool, PlanToolArgs, definition!("plan", PlanToolDefinition, output), parsed);
+builtin!(ModeTool, ModeToolArgs, definition!("mode", ModeToolDefinition), parsed);
+builtin!(CodeTool, CodeToolArgs, CodeToolDefinition::schema(), value);
+builtin!(DiagnosticsTool, DiagnosticsToolArgs, DiagnosticsToolDefinition::schema(), value);
+builtin!(TestTool, TestToolArgs, TestToolDefinition::schema(), value);
+builtin!(TaskTool, TaskToolArgs, TaskToolDefinition::schema(), value);
+builtin!(LspTool, LspToolArgs, LspToolDefinition::schema(), value);
+builtin!(ReplTool, ReplToolArgs, ReplToolDefinition::schema(), value);
+builtin!(ScratchTool, ScratchToolArgs, ScratchToolDefinition::schema(), value);
+builtin!(GitTool, GitToolArgs, GitToolDefinition::schema(), value);
+builtin!(FetchTool, FetchToolArgs, FetchToolDefinition::schema(), value);
+builtin!(DockerTool, DockerToolArgs, DockerToolDefinition::schema(), value);
+builtin!(K8sTool, K8sToolArgs, K8sToolDefinition::schema(), value);
+builtin!(StorageTool, StorageToolArgs, StorageToolDefinition::schema(), value);
+builtin!(NotifyTool, NotifyToolArgs, NotifyToolDefinition::schema(), value);
+builtin!(CalendarTool, CalendarToolArgs, CalendarToolDefinition::schema(), value);
+builtin!(VectorStoreTool, VectorStoreToolArgs, VectorStoreToolDefinition::schema(), value);
+builtin!(SysinfoTool, SysinfoToolArgs, SysinfoToolDefinition::schema(), value);
+builtin!(TasksTool, TasksToolArgs, TasksToolDefinition::schema(), value);
+builtin!(HanzoTool, HanzoToolArgs, HanzoToolDefinition::schema(), value);
+builtin!(IndexTool, IndexToolArgs, IndexToolDefinition::schema(), value);
+
+#[async_trait::async_trait]
+impl BuiltinTool for ExecTool {
+    fn definition() -> Value {
+        definition!("exec", ExecToolDefinition, output)
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult> {
+        let mut args: ExecToolArgs = serde_json::from_value(params)?;
+        args.root = context.project.clone();
+        Ok(ToolResult::ok(serde_json::from_str(&tool.read().await.execute(args).await?)?))
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for FsTool {
+    fn definition() -> Value {
+        definition!("fs", FsToolDefinition, output)
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult> {
+        let mut args: FsToolArgs = serde_json::from_value(params)?;
+        args.session_id = context.session.clone();
+        let is_read = args.action == "read";
+        let content: Value = serde_json::from_str(&tool.read().await.execute(args).await?)?;
+        let resource = if is_read { crate::file_resource(&content) } else { None };
+        Ok(match resource {
+            Some(resource) => ToolResult::ok(content).embedding("content", resource),
+            None => ToolResult::ok(content),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for MemoryTool {
+    fn definition() -> Value {
+        definition!("memory", MemoryToolDefinition, output)
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult> {
+        let mut args: MemoryToolArgs = serde_json::from_value(params)?;
+        args.session_id = context.session.clone();
+        args.project = context.project.as_ref().map(|p| p.to_string_lossy().to_string());
+        Ok(ToolResult::ok(serde_json::from_str(&tool.read().await.execute(args).await?)?))
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for ThinkTool {
+    fn definition() -> Value {
+        definition!("think", ThinkToolDefinition)
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult> {
+        let mut args: ThinkToolArgs = serde_json::from_value(params)?;
+        args.session_id = context.session.clone();
+        Ok(ToolResult::ok(tool.read().await.execute(args).await?))
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for WorkspaceTool {
+    fn definition() -> Value {
+        WorkspaceToolDefinition::schema()
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult> {
+        let mut args: WorkspaceToolArgs = serde_json::from_value(params)?;
+        args.session_id = context.session.clone();
+        Ok(ToolResult::ok(tool.read().await.execute(args).await?))
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for SearchTool {
+    fn definition() -> Value {
+        SearchToolDefinition::schema()
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult> {
+        let mut args: SearchToolArgs = serde_json::from_value(params)?;
+        args.session_id = context.session.clone();
+        Ok(ToolResult::ok(tool.read().await.execute(args).await?))
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for BrowserTool {
+    fn definition() -> Value {
+        definition!("browser", BrowserToolDefinition)
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, context: &CallContext) -> Result<ToolResult> {
+        let mut args: BrowserToolArgs = serde_json::from_value(params)?;
+        args.session_id = context.session.clone();
+        Ok(ToolResult::ok(parsed(tool.read().await.execute(args).await?)?))
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for ComputerTool {
+    fn definition() -> Value {
+        definition!("computer", ComputerToolDefinition)
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, _context: &CallContext) -> Result<ToolResult> {
+        let args: ComputerToolArgs = serde_json::from_value(params)?;
+        // Input actions track pointer and recording state
+        let result = tool.write().await.execute(args).await?;
+        let mut content: Value = serde_json::from_str(&result)?;
+        let blocks = crate::take_image(&mut content).into_iter().collect();
+        Ok(ToolResult::ok(content).with_blocks(blocks))
+    }
+}
+
+#[async_trait::async_trait]
+impl BuiltinTool for ImageTool {
+    fn definition() -> Value {
+        ImageToolDefinition::schema()
+    }
+
+    async fn call(tool: &RwLock<Self>, params: Value, _context: &CallContext) -> Result<ToolResult> {
+        let args: ImageToolArgs = serde_json::from_value(params)?;
+        let mut content = tool.read().await.execute(args).await?;
+        let blocks = content.get_mut("data").and_then(crate::take_image).into_iter().collect();
+        Ok(ToolResult::ok(content).with_blocks(blocks))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_builtin() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("a.txt");
+        std::fs::write(&file, "hello\n").unwrap();
+
+        let fs = FileSystemTool::new(FsTool::new());
+        assert_eq!(fs.name(), "fs");
+        assert!(fs.parameters()["properties"]["action"].is_object());
+        let read = fs.execute(json!({ "action": "read", "path": file })).await.unwrap();
+        assert!(read.success);
+        assert_eq!(read.blocks.len(), 1);
+        assert!(fs.execute(json!({ "action": 7 })).await.is_err());
+
+        let scratch: Box<dyn MCPTool> = Box::new(ToolWrapper::new(ScratchTool::new()));
+        assert_eq!(scratch.name(), "scratch");
+        let stored = scratch.execute(json!({ "action": "store", "handle": "h", "content": "x" })).await.unwrap();
+        assert_eq!(stored.content["ok"], true);
+        assert_eq!(ToolWrapper::new(VectorStoreTool::new()).name(), "vector");
+        assert_eq!(ComputerControlTool::new(ComputerTool::new()).name(), "computer");
+    }
+}
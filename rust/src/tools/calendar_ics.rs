@@ -0,0 +1,396 @@
+/// iCalendar (RFC 5545) files for the calendar tool
+///
+/// Enough of the format to keep events (VEVENT) and reminders (VTODO) in
+/// a file other programs can subscribe to or import. Components this
+/// module does not understand, such as VTIMEZONE, and properties it does
+/// not use are kept as they were when the file is written back.
+///
+/// Times with a TZID are read as local time, since no time zone database
+/// is bundled; UTC and floating times are exact. Recurring events are
+/// reported at their first occurrence with `recurring` set.
+
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde_json::{json, Value};
+
+/// Longest content line, in octets, before it is folded
+const FOLD_AT: usize = 75;
+
+/// A component inside the calendar, with its content lines unfolded;
+/// nested components (VALARM) stay in `lines` with their BEGIN and END
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub kind: String,
+    pub lines: Vec<String>,
+}
+
+/// A parsed content line
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Property {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calendar {
+    /// Properties of the VCALENDAR itself
+    pub properties: Vec<String>,
+    pub components: Vec<Component>,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self {
+            properties: vec![
+                "VERSION:2.0".to_string(),
+                "PRODID:-//Hanzo AI//hanzo-mcp//EN".to_string(),
+                "CALSCALE:GREGORIAN".to_string(),
+            ],
+            components: Vec::new(),
+        }
+    }
+}
+
+impl Calendar {
+    pub fn parse(text: &str) -> Self {
+        let mut calendar = Self { properties: Vec::new(), components: Vec::new() };
+        let mut current: Option<Component> = None;
+        let mut depth = 0usize;
+        for line in unfold(text) {
+            let upper = line.to_ascii_uppercase();
+            if let Some(kind) = upper.strip_prefix("BEGIN:") {
+                depth += 1;
+                match depth {
+                    2 => current = Some(Component { kind: kind.trim().to_string(), lines: Vec::new() }),
+                    d if d > 2 => current.iter_mut().for_each(|c| c.lines.push(line.clone())),
+                    _ => {}
+                }
+            } else if upper.starts_with("END:") {
+                match depth {
+                    2 => calendar.components.extend(current.take()),
+                    d if d > 2 => current.iter_mut().for_each(|c| c.lines.push(line.clone())),
+                    _ => {}
+                }
+                depth = depth.saturating_sub(1);
+            } else {
+                match (depth, current.as_mut()) {
+                    (1, _) => calendar.properties.push(line),
+                    (_, Some(component)) => component.lines.push(line),
+                    _ => {}
+                }
+            }
+        }
+        calendar
+    }
+
+    pub fn to_ics(&self) -> String {
+        let mut out = String::new();
+        let mut push = |line: &str| {
+            out.push_str(&fold(line));
+            out.push_str("\r\n");
+        };
+        push("BEGIN:VCALENDAR");
+        self.properties.iter().for_each(|p| push(p));
+        for component in &self.components {
+            push(&format!("BEGIN:{}", component.kind));
+            component.lines.iter().for_each(|l| push(l));
+            push(&format!("END:{}", component.kind));
+        }
+        push("END:VCALENDAR");
+        out
+    }
+
+    /// Display name, from X-WR-CALNAME
+    pub fn name(&self) -> Option<String> {
+        self.properties.iter()
+            .filter_map(|l| parse_line(l))
+            .find(|p| p.name == "X-WR-CALNAME")
+            .map(|p| unescape(&p.value))
+    }
+
+    pub fn of_kind<'a>(&'a self, kind: &'a str) -> impl Iterator<Item = &'a Component> + 'a {
+        self.components.iter().filter(move |c| c.kind == kind)
+    }
+
+    /// Remove the component with this UID, returning it
+    pub fn remove(&mut self, kind: &str, uid: &str) -> Option<Component> {
+        let index = self.components.iter().position(|c| c.kind == kind && c.uid().as_deref() == Some(uid))?;
+        Some(self.components.remove(index))
+    }
+
+    pub fn find_mut(&mut self, kind: &str, uid: &str) -> Option<&mut Component> {
+        self.components.iter_mut().find(|c| c.kind == kind && c.uid().as_deref() == Some(uid))
+    }
+}
+
+impl Component {
+    /// Top-level properties, skipping those of nested components
+    pub fn properties(&self) -> Vec<Property> {
+        let mut depth = 0usize;
+        let mut out = Vec::new();
+        for line in &self.lines {
+            let upper = line.to_ascii_uppercase();
+            if upper.starts_with("BEGIN:") {
+                depth += 1;
+            } else if upper.starts_with("END:") {
+                depth = depth.saturating_sub(1);
+            } else if depth == 0 {
+                out.extend(parse_line(line));
+            }
+        }
+        out
+    }
+
+    pub fn get(&self, name: &str) -> Option<Property> {
+        self.properties().into_iter().find(|p| p.name == name)
+    }
+
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.get(name).map(|p| unescape(&p.value)).filter(|v| !v.is_empty())
+    }
+
+    pub fn uid(&self) -> Option<String> {
+        self.text("UID")
+    }
+
+    /// Replace a top-level property, or add it when absent
+    pub fn set(&mut self, line: String) {
+        let name = parse_line(&line).map(|p| p.name);
+        self.lines.retain(|l| parse_line(l).map(|p| p.name) != name);
+        self.lines.push(line);
+    }
+
+    /// JSON for a VEVENT
+    pub fn event(&self, calendar: &str) -> Option<Value> {
+        let (start, all_day) = self.get("DTSTART").and_then(|p| parse_time(&p))?;
+        let end = self.get("DTEND").and_then(|p| parse_time(&p)).map(|(t, _)| t)
+            .or_else(|| self.text("DURATION").and_then(|d| parse_duration(&d)).map(|d| start + d))
+            .unwrap_or(if all_day { start + chrono::Duration::days(1) } else { start });
+        Some(json!({
+            "id": self.uid(),
+            "title": self.text("SUMMARY"),
+            "start": start.to_rfc3339(),
+            "end": end.to_rfc3339(),
+            "all_day": all_day,
+            "location": self.text("LOCATION"),
+            "notes": self.text("DESCRIPTION"),
+            "recurring": self.get("RRULE").is_some(),
+            "calendar": calendar,
+        }))
+    }
+
+    /// JSON for a VTODO
+    pub fn reminder(&self, calendar: &str) -> Value {
+        let completed = self.text("STATUS").is_some_and(|s| s.eq_ignore_ascii_case("COMPLETED"))
+            || self.get("COMPLETED").is_some();
+        json!({
+            "id": self.uid(),
+            "title": self.text("SUMMARY"),
+            "due": self.get("DUE").and_then(|p| parse_time(&p)).map(|(t, _)| t.to_rfc3339()),
+            "completed": completed,
+            "priority": self.text("PRIORITY").and_then(|p| p.parse::<u8>().ok()).filter(|p| *p > 0),
+            "notes": self.text("DESCRIPTION"),
+            "list": calendar,
+        })
+    }
+}
+
+/// Join folded lines: a line starting with a space or tab continues the
+/// one before it
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if raw.is_empty() => {}
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+/// Split a content line into octet-limited pieces, never inside a character
+pub fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / FOLD_AT * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        // Continuation lines spend one octet on the leading space
+        if width + c.len_utf8() > FOLD_AT {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+pub fn parse_line(line: &str) -> Option<Property> {
+    // The value starts at the first colon outside a quoted parameter
+    let mut quoted = false;
+    let colon = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == ':' && !quoted
+    })?.0;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = split_unquoted(head, ';').into_iter();
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = parts.filter_map(|p| {
+        let (key, value) = p.split_once('=')?;
+        Some((key.to_ascii_uppercase(), value.trim_matches('"').to_string()))
+    }).collect();
+    Some(Property { name, params, value: value.to_string() })
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => {
+                out.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    out.push(&text[start..]);
+    out
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// A DATE or DATE-TIME value, and whether it is a whole day
+pub fn parse_time(property: &Property) -> Option<(DateTime<Utc>, bool)> {
+    let value = property.value.trim();
+    let is_date = property.param("VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || value.len() == 8;
+    if is_date {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return local(date.and_hms_opt(0, 0, 0)?).map(|t| (t, true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok().map(|t| (t.and_utc(), false));
+    }
+    local(NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?).map(|t| (t, false))
+}
+
+fn local(time: NaiveDateTime) -> Option<DateTime<Utc>> {
+    Local.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc))
+}
+
+/// A DTSTART, DTEND or DUE line
+pub fn time_line(name: &str, time: DateTime<Utc>, all_day: bool) -> String {
+    if all_day {
+        format!("{};VALUE=DATE:{}", name, time.with_timezone(&Local).format("%Y%m%d"))
+    } else {
+        format!("{}:{}", name, time.format("%Y%m%dT%H%M%SZ"))
+    }
+}
+
+/// An RFC 5545 duration such as `PT1H30M` or `P1D`
+pub fn parse_duration(value: &str) -> Option<chrono::Duration> {
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P')?;
+    let (mut total, mut number, mut in_time) = (chrono::Duration::zero(), String::new(), false);
+    for c in value.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let n: i64 = std::mem::take(&mut number).parse().ok()?;
+                total += match (unit, in_time) {
+                    ('W', false) => chrono::Duration::weeks(n),
+                    ('D', false) => chrono::Duration::days(n),
+                    ('H', true) => chrono::Duration::hours(n),
+                    ('M', true) => chrono::Duration::minutes(n),
+                    ('S', true) => chrono::Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(if negative { -total } else { total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nX-WR-CALNAME:Team\r\nBEGIN:VTIMEZONE\r\nTZID:Europe/Paris\r\nEND:VTIMEZONE\r\nBEGIN:VEVENT\r\nUID:a@x\r\nDTSTART:20261016T090000Z\r\nDURATION:PT1H30M\r\nSUMMARY:Standup\\, daily\r\nDESCRIPTION:line one\\nline \r\n two\r\nRRULE:FREQ=DAILY\r\nBEGIN:VALARM\r\nTRIGGER:-PT15M\r\nEND:VALARM\r\nEND:VEVENT\r\nBEGIN:VTODO\r\nUID:b@x\r\nSUMMARY:Send report\r\nSTATUS:COMPLETED\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+
+    #[test]
+    fn test_parse_and_write() {
+        let calendar = Calendar::parse(SAMPLE);
+        assert_eq!(calendar.name().as_deref(), Some("Team"));
+        assert_eq!(calendar.components.len(), 3);
+
+        let event = calendar.of_kind("VEVENT").next().unwrap().event("Team").unwrap();
+        assert_eq!(event["title"], "Standup, daily");
+        assert_eq!(event["notes"], "line one\nline two");
+        assert_eq!(event["end"], "2026-10-16T10:30:00+00:00");
+        assert_eq!(event["recurring"], true);
+        let reminder = calendar.of_kind("VTODO").next().unwrap().reminder("Team");
+        assert_eq!(reminder["completed"], true);
+
+        // Unknown components and nested alarms survive a round trip
+        let again = Calendar::parse(&calendar.to_ics());
+        assert_eq!(again, calendar);
+        assert!(calendar.to_ics().contains("BEGIN:VALARM\r\nTRIGGER:-PT15M\r\nEND:VALARM"));
+
+        let long = format!("DESCRIPTION:{}", "é".repeat(60));
+        assert!(fold(&long).split("\r\n").all(|l| l.len() <= FOLD_AT));
+        assert_eq!(unfold(&fold(&long)), vec![long]);
+    }
+
+    #[test]
+    fn test_values() {
+        let property = parse_line("DTSTART;TZID=\"America/New_York\";VALUE=DATE-TIME:20261016T090000").unwrap();
+        assert_eq!(property.param("tzid"), Some("America/New_York"));
+        assert_eq!(property.value, "20261016T090000");
+        assert_eq!(parse_time(&parse_line("DUE;VALUE=DATE:20261016").unwrap()).unwrap().1, true);
+        assert_eq!(parse_duration("P1W2DT3H"), Some(chrono::Duration::hours(9 * 24 + 3)));
+        assert_eq!(parse_duration("-PT15M"), Some(chrono::Duration::minutes(-15)));
+        assert_eq!(unescape(&escape("a,b;c\\d\ne")), "a,b;c\\d\ne");
+        let time = Utc.with_ymd_and_hms(2026, 10, 16, 9, 0, 0).unwrap();
+        assert_eq!(time_line("DTSTART", time, false), "DTSTART:20261016T090000Z");
+    }
+}
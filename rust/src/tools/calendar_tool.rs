@@ -0,0 +1,728 @@
+/// Calendar tool
+///
+/// Actions: events, create, delete, reminders, remind, complete, calendars, help
+///
+/// Reads and adds calendar events and reminders so an assistant can put
+/// follow-ups where the user will see them. Two backends (see
+/// `CalendarConfig`): `system` drives Calendar and Reminders on macOS
+/// through their scripting interface, which reads and writes the
+/// EventKit store everything else on the Mac syncs from; `ics` keeps
+/// events and to-dos in an iCalendar file that any calendar app can
+/// subscribe to or import.
+///
+/// Times are RFC 3339, `YYYY-MM-DD HH:MM` in local time, a bare date for
+/// whole days, or relative to now such as `+30m`, `+2h` or `+1d`.
+
+use anyhow::Result;
+use crate::config::CalendarConfig;
+use crate::error::ToolError;
+use crate::tools::calendar_ics::{self, Calendar, Component};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Days of events listed when no end is given
+const DEFAULT_DAYS: i64 = 7;
+
+/// Events or reminders returned unless told otherwise
+const DEFAULT_LIMIT: usize = 100;
+
+/// Longest an osascript call may take; the first one can wait on the
+/// user granting access
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarAction {
+    #[default]
+    Events,
+    Create,
+    Delete,
+    Reminders,
+    Remind,
+    Complete,
+    Calendars,
+    Help,
+}
+
+impl std::str::FromStr for CalendarAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "events" | "list" | "agenda" | "" => Ok(Self::Events),
+            "create" | "add" | "event" => Ok(Self::Create),
+            "delete" | "remove" | "cancel" => Ok(Self::Delete),
+            "reminders" | "todos" => Ok(Self::Reminders),
+            "remind" | "reminder" | "todo" => Ok(Self::Remind),
+            "complete" | "done" => Ok(Self::Complete),
+            "calendars" | "lists" => Ok(Self::Calendars),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl CalendarAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Events => "events",
+            Self::Create => "create",
+            Self::Delete => "delete",
+            Self::Reminders => "reminders",
+            Self::Remind => "remind",
+            Self::Complete => "complete",
+            Self::Calendars => "calendars",
+            Self::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    System,
+    Ics,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "system" | "eventkit" | "macos" => Ok(Self::System),
+            "ics" | "ical" | "file" => Ok(Self::Ics),
+            _ => Err(ToolError::invalid(format!("Unknown calendar backend: {} (use system or ics)", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalendarToolArgs {
+    pub action: Option<String>,
+    pub title: Option<String>,
+    /// Event start, or the beginning of the range events lists
+    pub start: Option<String>,
+    /// Event end, or the end of the range events lists
+    pub end: Option<String>,
+    /// Event length when no end is given
+    pub duration_minutes: Option<i64>,
+    pub all_day: Option<bool>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    /// Reminder due time
+    pub due: Option<String>,
+    /// Calendar or reminder list; the default one when left out
+    pub calendar: Option<String>,
+    /// Event or reminder id, for delete and complete
+    pub id: Option<String>,
+    /// Case-insensitive text to look for in titles, notes and locations
+    pub query: Option<String>,
+    /// For reminders: include completed ones
+    pub include_completed: Option<bool>,
+    pub limit: Option<usize>,
+}
+
+pub struct CalendarToolDefinition;
+
+impl CalendarToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "calendar",
+            "description": "Calendar events and reminders (macOS Calendar/Reminders, or an iCalendar file elsewhere): events, create, delete, reminders, remind, complete, calendars, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["events", "create", "delete", "reminders", "remind", "complete", "calendars", "help"],
+                        "default": "events"
+                    },
+                    "title": { "type": "string" },
+                    "start": { "type": "string", "description": "RFC 3339, 'YYYY-MM-DD HH:MM' local, YYYY-MM-DD or +30m/+2h/+1d (events: range start, default now)" },
+                    "end": { "type": "string", "description": "Same formats (events: range end, default a week on)" },
+                    "duration_minutes": { "type": "integer", "description": "For create without end (default 60)" },
+                    "all_day": { "type": "boolean" },
+                    "location": { "type": "string" },
+                    "notes": { "type": "string" },
+                    "due": { "type": "string", "description": "For remind: when it is due" },
+                    "calendar": { "type": "string", "description": "Calendar or reminder list name" },
+                    "id": { "type": "string", "description": "For delete and complete" },
+                    "query": { "type": "string", "description": "Text to match in titles, notes and locations" },
+                    "include_completed": { "type": "boolean", "default": false },
+                    "limit": { "type": "integer", "default": 100 }
+                }
+            }
+        })
+    }
+}
+
+pub struct CalendarTool {
+    backend: Backend,
+    path: PathBuf,
+    /// Serializes read-modify-write cycles on the iCalendar file
+    lock: Mutex<()>,
+}
+
+impl CalendarTool {
+    pub fn new() -> Self {
+        Self::with_config(CalendarConfig::default()).expect("the default calendar config is valid")
+    }
+
+    pub fn with_config(config: CalendarConfig) -> Result<Self> {
+        let backend = match config.backend.as_deref() {
+            Some(name) => name.parse()?,
+            None if cfg!(target_os = "macos") => Backend::System,
+            None => Backend::Ics,
+        };
+        let path = config.path
+            .map(|p| PathBuf::from(shellexpand::tilde(&p.to_string_lossy()).into_owned()))
+            .unwrap_or_else(default_path);
+        Ok(Self { backend, path, lock: Mutex::new(()) })
+    }
+
+    pub async fn execute(&self, args: CalendarToolArgs) -> Result<Value> {
+        let action: CalendarAction = args.action.as_deref().unwrap_or("events").parse()?;
+        if action == CalendarAction::Help {
+            return Ok(self.help());
+        }
+        if self.backend == Backend::System && !cfg!(target_os = "macos") {
+            return Err(ToolError::unsupported("The system calendar backend needs macOS; set [calendar] backend = \"ics\"").into());
+        }
+        let data = match (action.clone(), self.backend) {
+            (CalendarAction::Events, Backend::Ics) => self.ics_events(&args).await?,
+            (CalendarAction::Create, Backend::Ics) => self.ics_create(&args).await?,
+            (CalendarAction::Delete, Backend::Ics) => self.ics_delete(&args).await?,
+            (CalendarAction::Reminders, Backend::Ics) => self.ics_reminders(&args).await?,
+            (CalendarAction::Remind, Backend::Ics) => self.ics_remind(&args).await?,
+            (CalendarAction::Complete, Backend::Ics) => self.ics_complete(&args).await?,
+            (CalendarAction::Calendars, Backend::Ics) => self.ics_calendars().await?,
+            (action, Backend::System) => system(&action, &args).await?,
+            (CalendarAction::Help, _) => unreachable!("help returns early"),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "calendar", "action": action.name() }
+        }))
+    }
+
+    async fn load(&self) -> Result<Calendar> {
+        match tokio::fs::read_to_string(&self.path).await {
+            Ok(text) => Ok(Calendar::parse(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Calendar::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write through a temporary file so readers never see half a calendar
+    async fn save(&self, calendar: &Calendar) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let temp = self.path.with_extension("ics.tmp");
+        tokio::fs::write(&temp, calendar.to_ics()).await?;
+        tokio::fs::rename(&temp, &self.path).await?;
+        Ok(())
+    }
+
+    fn calendar_name(&self, calendar: &Calendar) -> String {
+        calendar.name().unwrap_or_else(|| {
+            self.path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "calendar".to_string())
+        })
+    }
+
+    async fn ics_events(&self, args: &CalendarToolArgs) -> Result<Value> {
+        let (from, to) = range(args)?;
+        let calendar = self.load().await?;
+        let name = self.calendar_name(&calendar);
+        let mut events: Vec<Value> = calendar.of_kind("VEVENT")
+            .filter_map(|c| c.event(&name))
+            .filter(|e| {
+                let start = time_of(&e["start"]);
+                let end = time_of(&e["end"]);
+                // Recurring events may have an occurrence anywhere after their first
+                let in_range = if e["recurring"] == true { start < to } else { start < to && end.max(start) >= from };
+                in_range && matches(e, args.query.as_deref())
+            })
+            .collect();
+        events.sort_by_key(|e| time_of(&e["start"]));
+        Ok(limited("events", events, args.limit, json!({ "from": from.to_rfc3339(), "to": to.to_rfc3339(), "path": self.path })))
+    }
+
+    async fn ics_create(&self, args: &CalendarToolArgs) -> Result<Value> {
+        let event = NewEvent::from_args(args)?;
+        let _guard = self.lock.lock().await;
+        let mut calendar = self.load().await?;
+        let uid = new_uid();
+        let mut lines = vec![
+            format!("UID:{}", uid),
+            format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+            calendar_ics::time_line("DTSTART", event.start, event.all_day),
+            calendar_ics::time_line("DTEND", event.end, event.all_day),
+            format!("SUMMARY:{}", calendar_ics::escape(&event.title)),
+        ];
+        lines.extend(args.location.as_deref().map(|l| format!("LOCATION:{}", calendar_ics::escape(l))));
+        lines.extend(args.notes.as_deref().map(|n| format!("DESCRIPTION:{}", calendar_ics::escape(n))));
+        let component = Component { kind: "VEVENT".to_string(), lines };
+        let name = self.calendar_name(&calendar);
+        let created = component.event(&name);
+        calendar.components.push(component);
+        self.save(&calendar).await?;
+        Ok(json!({ "event": created, "path": self.path }))
+    }
+
+    async fn ics_delete(&self, args: &CalendarToolArgs) -> Result<Value> {
+        let id = args.id.as_deref().ok_or_else(|| ToolError::invalid("id required"))?;
+        let _guard = self.lock.lock().await;
+        let mut calendar = self.load().await?;
+        let removed = calendar.remove("VEVENT", id)
+            .or_else(|| calendar.remove("VTODO", id))
+            .ok_or_else(|| ToolError::not_found(format!("No event or reminder with id {}", id)))?;
+        self.save(&calendar).await?;
+        Ok(json!({ "deleted": id, "kind": if removed.kind == "VTODO" { "reminder" } else { "event" } }))
+    }
+
+    async fn ics_reminders(&self, args: &CalendarToolArgs) -> Result<Value> {
+        let calendar = self.load().await?;
+        let name = self.calendar_name(&calendar);
+        let mut reminders: Vec<Value> = calendar.of_kind("VTODO")
+            .map(|c| c.reminder(&name))
+            .filter(|r| args.include_completed.unwrap_or(false) || r["completed"] == false)
+            .filter(|r| matches(r, args.query.as_deref()))
+            .collect();
+        // Due ones first, soonest first
+        reminders.sort_by_key(|r| (r["due"].is_null(), r["due"].as_str().map(|d| time_of(&json!(d)))));
+        Ok(limited("reminders", reminders, args.limit, json!({ "path": self.path })))
+    }
+
+    async fn ics_remind(&self, args: &CalendarToolArgs) -> Result<Value> {
+        let title = args.title.as_deref().ok_or_else(|| ToolError::invalid("title required"))?;
+        let due = args.due.as_deref().map(parse_when).transpose()?;
+        let _guard = self.lock.lock().await;
+        let mut calendar = self.load().await?;
+        let mut lines = vec![
+            format!("UID:{}", new_uid()),
+            format!("DTSTAMP:{}", Utc::now().format("%Y%m%dT%H%M%SZ")),
+            format!("SUMMARY:{}", calendar_ics::escape(title)),
+            "STATUS:NEEDS-ACTION".to_string(),
+        ];
+        lines.extend(due.map(|(time, date_only)| calendar_ics::time_line("DUE", time, date_only)));
+        lines.extend(args.notes.as_deref().map(|n| format!("DESCRIPTION:{}", calendar_ics::escape(n))));
+        if let Some((time, false)) = due {
+            // Alert at the due time in apps that import the file
+            lines.extend([
+                "BEGIN:VALARM".to_string(),
+                "ACTION:DISPLAY".to_string(),
+                format!("DESCRIPTION:{}", calendar_ics::escape(title)),
+                format!("TRIGGER;VALUE=DATE-TIME:{}", time.format("%Y%m%dT%H%M%SZ")),
+                "END:VALARM".to_string(),
+            ]);
+        }
+        let component = Component { kind: "VTODO".to_string(), lines };
+        let created = component.reminder(&self.calendar_name(&calendar));
+        calendar.components.push(component);
+        self.save(&calendar).await?;
+        Ok(json!({ "reminder": created, "path": self.path }))
+    }
+
+    async fn ics_complete(&self, args: &CalendarToolArgs) -> Result<Value> {
+        let id = args.id.as_deref().ok_or_else(|| ToolError::invalid("id required"))?;
+        let _guard = self.lock.lock().await;
+        let mut calendar = self.load().await?;
+        let name = self.calendar_name(&calendar);
+        let todo = calendar.find_mut("VTODO", id)
+            .ok_or_else(|| ToolError::not_found(format!("No reminder with id {}", id)))?;
+        todo.set("STATUS:COMPLETED".to_string());
+        todo.set(format!("COMPLETED:{}", Utc::now().format("%Y%m%dT%H%M%SZ")));
+        todo.set("PERCENT-COMPLETE:100".to_string());
+        let reminder = todo.reminder(&name);
+        self.save(&calendar).await?;
+        Ok(json!({ "reminder": reminder }))
+    }
+
+    async fn ics_calendars(&self) -> Result<Value> {
+        let calendar = self.load().await?;
+        Ok(json!({
+            "backend": "ics",
+            "calendars": [{
+                "name": self.calendar_name(&calendar),
+                "path": self.path,
+                "exists": self.path.exists(),
+                "events": calendar.of_kind("VEVENT").count(),
+                "reminders": calendar.of_kind("VTODO").count()
+            }]
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "calendar",
+                "backend": match self.backend { Backend::System => "system", Backend::Ics => "ics" },
+                "path": if self.backend == Backend::Ics { Some(&self.path) } else { None },
+                "actions": {
+                    "events": "Events between start and end (default the next 7 days), optionally matching query",
+                    "create": "Add an event: title, start, end or duration_minutes, all_day, location, notes, calendar",
+                    "delete": "Remove an event or reminder by id",
+                    "reminders": "Open reminders (include_completed for all), due ones first",
+                    "remind": "Add a reminder: title, due, notes, calendar (the list)",
+                    "complete": "Mark a reminder done by id",
+                    "calendars": "Calendars and reminder lists",
+                    "help": "Show tool help"
+                },
+                "times": "RFC 3339, 'YYYY-MM-DD HH:MM' (local), YYYY-MM-DD (whole day) or +30m, +2h, +1d, +1w"
+            },
+            "error": null,
+            "meta": { "tool": "calendar", "action": "help" }
+        })
+    }
+}
+
+impl Default for CalendarTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn default_path() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hanzo-mcp")
+        .join("calendar.ics")
+}
+
+fn new_uid() -> String {
+    let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    format!("{:x}-{:x}@hanzo-mcp", nanos, std::process::id())
+}
+
+/// An event to create, with its times worked out
+#[derive(Debug, PartialEq)]
+struct NewEvent {
+    title: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    all_day: bool,
+}
+
+impl NewEvent {
+    fn from_args(args: &CalendarToolArgs) -> Result<Self> {
+        let title = args.title.clone().ok_or_else(|| ToolError::invalid("title required"))?;
+        let (start, date_only) = parse_when(args.start.as_deref().ok_or_else(|| ToolError::invalid("start required"))?)?;
+        let all_day = args.all_day.unwrap_or(date_only);
+        let end = match (&args.end, args.duration_minutes) {
+            (Some(end), _) => {
+                let (end, end_date_only) = parse_when(end)?;
+                // A whole-day end date is inclusive; iCalendar wants the day after
+                if all_day && end_date_only { end + chrono::Duration::days(1) } else { end }
+            }
+            (None, Some(minutes)) => start + chrono::Duration::minutes(minutes),
+            (None, None) if all_day => start + chrono::Duration::days(1),
+            (None, None) => start + chrono::Duration::hours(1),
+        };
+        if end < start {
+            return Err(ToolError::invalid("end is before start").into());
+        }
+        Ok(Self { title, start, end, all_day })
+    }
+}
+
+/// A time as the tool accepts it, and whether it was a bare date
+pub fn parse_when(value: &str) -> Result<(DateTime<Utc>, bool)> {
+    let value = value.trim();
+    if let Some(relative) = value.strip_prefix('+') {
+        let (number, unit) = relative.split_at(relative.find(|c: char| !c.is_ascii_digit()).unwrap_or(relative.len()));
+        let n: i64 = number.parse().map_err(|_| ToolError::invalid(format!("Invalid relative time: {}", value)))?;
+        let delta = match unit {
+            "m" | "min" | "mins" => chrono::Duration::minutes(n),
+            "h" | "hr" | "hrs" | "hours" => chrono::Duration::hours(n),
+            "d" | "days" => chrono::Duration::days(n),
+            "w" | "weeks" => chrono::Duration::weeks(n),
+            _ => return Err(ToolError::invalid(format!("Invalid relative time unit in {} (use m, h, d or w)", value)).into()),
+        };
+        return Ok((Utc::now() + delta, false));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok((time.with_timezone(&Utc), false));
+    }
+    let local = |naive: NaiveDateTime| Local.from_local_datetime(&naive).earliest().map(|t| t.with_timezone(&Utc));
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Some(time) = NaiveDateTime::parse_from_str(value, format).ok().and_then(local) {
+            return Ok((time, false));
+        }
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .and_then(local)
+        .map(|t| (t, true))
+        .ok_or_else(|| ToolError::invalid(format!(
+            "Invalid time: {} (expected RFC 3339, 'YYYY-MM-DD HH:MM', YYYY-MM-DD or +2h)", value
+        )).into())
+}
+
+/// The window events lists
+fn range(args: &CalendarToolArgs) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let from = args.start.as_deref().map(parse_when).transpose()?.map_or_else(Utc::now, |(t, _)| t);
+    let to = match args.end.as_deref().map(parse_when).transpose()? {
+        // A bare end date covers that whole day
+        Some((t, true)) => t + chrono::Duration::days(1),
+        Some((t, false)) => t,
+        None => from + chrono::Duration::days(DEFAULT_DAYS),
+    };
+    Ok((from, to))
+}
+
+fn time_of(value: &Value) -> DateTime<Utc> {
+    value.as_str()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map_or(DateTime::<Utc>::MIN_UTC, |t| t.with_timezone(&Utc))
+}
+
+fn matches(item: &Value, query: Option<&str>) -> bool {
+    let Some(query) = query.map(str::to_lowercase) else { return true };
+    ["title", "notes", "location"].iter()
+        .any(|key| item[key].as_str().is_some_and(|v| v.to_lowercase().contains(&query)))
+}
+
+fn limited(key: &str, mut items: Vec<Value>, limit: Option<usize>, extra: Value) -> Value {
+    let total = items.len();
+    items.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    let mut data = json!({ key: items, "count": items.len(), "total": total });
+    if let (Some(data), Some(extra)) = (data.as_object_mut(), extra.as_object()) {
+        data.extend(extra.clone());
+    }
+    data
+}
+
+/// JavaScript for Automation run against Calendar and Reminders; each
+/// script takes its arguments as one JSON string and prints JSON
+const SCRIPT_EVENTS: &str = r#"
+function run(argv) {
+  const a = JSON.parse(argv[0]);
+  const app = Application('Calendar');
+  const from = new Date(a.from), to = new Date(a.to);
+  const out = [];
+  for (const cal of app.calendars()) {
+    if (a.calendar && cal.name() !== a.calendar) continue;
+    const events = cal.events.whose({_and: [{startDate: {_lessThan: to}}, {endDate: {_greaterThan: from}}]})();
+    for (const e of events) {
+      out.push({id: e.uid(), title: e.summary(), start: e.startDate().toISOString(), end: e.endDate().toISOString(),
+        all_day: e.alldayEvent(), location: e.location() || null, notes: e.description() || null,
+        recurring: !!e.recurrence(), calendar: cal.name()});
+    }
+  }
+  return JSON.stringify(out);
+}"#;
+
+const SCRIPT_CREATE: &str = r#"
+function run(argv) {
+  const a = JSON.parse(argv[0]);
+  const app = Application('Calendar');
+  const cal = a.calendar ? app.calendars.byName(a.calendar) : app.calendars().find(c => c.writable());
+  if (!cal) throw new Error('No writable calendar');
+  const props = {summary: a.title, startDate: new Date(a.start), endDate: new Date(a.end), alldayEvent: a.all_day};
+  if (a.location) props.location = a.location;
+  if (a.notes) props.description = a.notes;
+  const e = app.Event(props);
+  cal.events.push(e);
+  return JSON.stringify({id: e.uid(), title: a.title, start: a.start, end: a.end, all_day: a.all_day, calendar: cal.name()});
+}"#;
+
+const SCRIPT_DELETE: &str = r#"
+function run(argv) {
+  const a = JSON.parse(argv[0]);
+  const cal = Application('Calendar');
+  for (const c of cal.calendars()) {
+    const found = c.events.whose({uid: a.id})();
+    if (found.length) { cal.delete(found[0]); return JSON.stringify({deleted: a.id, kind: 'event'}); }
+  }
+  const rem = Application('Reminders');
+  const found = rem.reminders.whose({id: a.id})();
+  if (found.length) { rem.delete(found[0]); return JSON.stringify({deleted: a.id, kind: 'reminder'}); }
+  throw new Error('No event or reminder with id ' + a.id);
+}"#;
+
+const SCRIPT_REMINDERS: &str = r#"
+function run(argv) {
+  const a = JSON.parse(argv[0]);
+  const app = Application('Reminders');
+  const out = [];
+  for (const list of app.lists()) {
+    if (a.calendar && list.name() !== a.calendar) continue;
+    const items = a.include_completed ? list.reminders() : list.reminders.whose({completed: false})();
+    for (const r of items) {
+      const due = r.dueDate();
+      out.push({id: r.id(), title: r.name(), due: due ? due.toISOString() : null, completed: r.completed(),
+        priority: r.priority() || null, notes: r.body() || null, list: list.name()});
+    }
+  }
+  return JSON.stringify(out);
+}"#;
+
+const SCRIPT_REMIND: &str = r#"
+function run(argv) {
+  const a = JSON.parse(argv[0]);
+  const app = Application('Reminders');
+  const list = a.calendar ? app.lists.byName(a.calendar) : app.defaultList();
+  const props = {name: a.title};
+  if (a.notes) props.body = a.notes;
+  if (a.due) props.dueDate = new Date(a.due);
+  const r = app.Reminder(props);
+  list.reminders.push(r);
+  return JSON.stringify({id: r.id(), title: a.title, due: a.due || null, completed: false, list: list.name()});
+}"#;
+
+const SCRIPT_COMPLETE: &str = r#"
+function run(argv) {
+  const a = JSON.parse(argv[0]);
+  const found = Application('Reminders').reminders.whose({id: a.id})();
+  if (!found.length) throw new Error('No reminder with id ' + a.id);
+  found[0].completed = true;
+  return JSON.stringify({id: a.id, title: found[0].name(), completed: true});
+}"#;
+
+const SCRIPT_CALENDARS: &str = r#"
+function run(argv) {
+  const calendars = Application('Calendar').calendars().map(c => ({name: c.name(), writable: c.writable()}));
+  const lists = Application('Reminders').lists().map(l => ({name: l.name()}));
+  return JSON.stringify({backend: 'system', calendars: calendars, reminder_lists: lists});
+}"#;
+
+/// Run an action against the macOS Calendar and Reminders apps
+async fn system(action: &CalendarAction, args: &CalendarToolArgs) -> Result<Value> {
+    match action {
+        CalendarAction::Events => {
+            let (from, to) = range(args)?;
+            let events = jxa(SCRIPT_EVENTS, json!({ "from": from.to_rfc3339(), "to": to.to_rfc3339(), "calendar": args.calendar })).await?;
+            let mut events: Vec<Value> = events.as_array().cloned().unwrap_or_default()
+                .into_iter()
+                .filter(|e| matches(e, args.query.as_deref()))
+                .collect();
+            events.sort_by_key(|e| time_of(&e["start"]));
+            Ok(limited("events", events, args.limit, json!({ "from": from.to_rfc3339(), "to": to.to_rfc3339() })))
+        }
+        CalendarAction::Create => {
+            let event = NewEvent::from_args(args)?;
+            let created = jxa(SCRIPT_CREATE, json!({
+                "title": event.title,
+                "start": event.start.to_rfc3339(),
+                "end": event.end.to_rfc3339(),
+                "all_day": event.all_day,
+                "location": args.location,
+                "notes": args.notes,
+                "calendar": args.calendar
+            })).await?;
+            Ok(json!({ "event": created }))
+        }
+        CalendarAction::Delete | CalendarAction::Complete => {
+            let id = args.id.as_deref().ok_or_else(|| ToolError::invalid("id required"))?;
+            let script = if *action == CalendarAction::Delete { SCRIPT_DELETE } else { SCRIPT_COMPLETE };
+            jxa(script, json!({ "id": id })).await
+        }
+        CalendarAction::Reminders => {
+            let reminders = jxa(SCRIPT_REMINDERS, json!({ "calendar": args.calendar, "include_completed": args.include_completed.unwrap_or(false) })).await?;
+            let mut reminders: Vec<Value> = reminders.as_array().cloned().unwrap_or_default()
+                .into_iter()
+                .filter(|r| matches(r, args.query.as_deref()))
+                .collect();
+            reminders.sort_by_key(|r| (r["due"].is_null(), time_of(&r["due"])));
+            Ok(limited("reminders", reminders, args.limit, json!({})))
+        }
+        CalendarAction::Remind => {
+            let title = args.title.as_deref().ok_or_else(|| ToolError::invalid("title required"))?;
+            let due = args.due.as_deref().map(parse_when).transpose()?.map(|(t, _)| t.to_rfc3339());
+            let created = jxa(SCRIPT_REMIND, json!({ "title": title, "due": due, "notes": args.notes, "calendar": args.calendar })).await?;
+            Ok(json!({ "reminder": created }))
+        }
+        CalendarAction::Calendars => jxa(SCRIPT_CALENDARS, json!({})).await,
+        CalendarAction::Help => unreachable!("help returns early"),
+    }
+}
+
+async fn jxa(script: &str, args: Value) -> Result<Value> {
+    let mut command = Command::new("osascript");
+    command.args(["-l", "JavaScript", "-e", script, &args.to_string()]).kill_on_drop(true);
+    let output = tokio::time::timeout(SCRIPT_TIMEOUT, command.output()).await
+        .map_err(|_| ToolError::timeout(format!(
+            "Calendar did not answer within {}s; macOS may be waiting for calendar access to be granted", SCRIPT_TIMEOUT.as_secs()
+        )))??;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr.trim();
+        // -1743 is "not authorized to send Apple events"
+        if message.contains("-1743") || message.contains("Not authorized") {
+            return Err(ToolError::permission_denied(
+                "Not allowed to control Calendar or Reminders; grant access under System Settings > Privacy & Security > Automation"
+            ).into());
+        }
+        return Err(ToolError::external(format!("osascript: {}", message)).into());
+    }
+    serde_json::from_slice(&output.stdout)
+        .map_err(|e| ToolError::external(format!("Unexpected osascript output: {}", e)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_times() {
+        let (time, date_only) = parse_when("2026-10-16T09:00:00Z").unwrap();
+        assert_eq!(time.to_rfc3339(), "2026-10-16T09:00:00+00:00");
+        assert!(!date_only);
+        assert!(parse_when("2026-10-16").unwrap().1);
+        assert!(!parse_when("2026-10-16 14:30").unwrap().1);
+        let soon = parse_when("+2h").unwrap().0 - Utc::now();
+        assert!(soon > chrono::Duration::minutes(119) && soon <= chrono::Duration::hours(2));
+        assert!(parse_when("+2y").is_err());
+        assert!(parse_when("next tuesday").is_err());
+
+        let args = CalendarToolArgs { title: Some("Trip".into()), start: Some("2026-10-16".into()), end: Some("2026-10-18".into()), ..Default::default() };
+        let event = NewEvent::from_args(&args).unwrap();
+        assert!(event.all_day);
+        assert_eq!(event.end - event.start, chrono::Duration::days(3));
+    }
+
+    #[tokio::test]
+    async fn test_ics_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = CalendarConfig { backend: Some("ics".into()), path: Some(dir.path().join("agent.ics")) };
+        let tool = CalendarTool::with_config(config).unwrap();
+        let run = |args: CalendarToolArgs| tool.execute(args);
+
+        let created = run(CalendarToolArgs {
+            action: Some("create".into()),
+            title: Some("Review PR, then merge".into()),
+            start: Some("+1h".into()),
+            duration_minutes: Some(30),
+            ..Default::default()
+        }).await.unwrap();
+        let id = created["data"]["event"]["id"].as_str().unwrap().to_string();
+
+        let listed = run(CalendarToolArgs { query: Some("review".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(listed["data"]["count"], 1);
+        assert_eq!(listed["data"]["events"][0]["title"], "Review PR, then merge");
+        let later = run(CalendarToolArgs { start: Some("+2d".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(later["data"]["count"], 0);
+
+        let reminder = run(CalendarToolArgs { action: Some("remind".into()), title: Some("Follow up".into()), due: Some("+1d".into()), ..Default::default() }).await.unwrap();
+        let reminder_id = reminder["data"]["reminder"]["id"].as_str().unwrap().to_string();
+        run(CalendarToolArgs { action: Some("complete".into()), id: Some(reminder_id), ..Default::default() }).await.unwrap();
+        let open = run(CalendarToolArgs { action: Some("reminders".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(open["data"]["count"], 0);
+        let all = run(CalendarToolArgs { action: Some("reminders".into()), include_completed: Some(true), ..Default::default() }).await.unwrap();
+        assert_eq!(all["data"]["reminders"][0]["completed"], true);
+
+        run(CalendarToolArgs { action: Some("delete".into()), id: Some(id.clone()), ..Default::default() }).await.unwrap();
+        let err = run(CalendarToolArgs { action: Some("delete".into()), id: Some(id), ..Default::default() }).await.unwrap_err();
+        assert!(err.to_string().contains("No event or reminder"));
+        let text = std::fs::read_to_string(dir.path().join("agent.ics")).unwrap();
+        assert!(text.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    }
+}
@@ -0,0 +1,117 @@
+/// Source formatting for code format
+///
+/// Formatters are external programs that read the source on stdin and write
+/// the formatted source to stdout. They run in the file's directory so
+/// project settings (rustfmt.toml, pyproject.toml, .prettierrc) apply, and
+/// `{path}` in a command is replaced by the file's path, or by a stand-in
+/// name with the language's extension when formatting bare content.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Languages and their extensions, the first one canonical
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("rust", &["rs"]),
+    ("python", &["py", "pyi"]),
+    ("javascript", &["js", "jsx", "mjs", "cjs"]),
+    ("typescript", &["ts", "tsx", "mts", "cts"]),
+    ("json", &["json"]),
+    ("css", &["css", "scss", "less"]),
+    ("markdown", &["md", "markdown"]),
+    ("html", &["html", "vue"]),
+    ("yaml", &["yaml", "yml"]),
+    ("go", &["go"]),
+];
+
+/// Formatter commands used unless configured otherwise
+pub fn default_formatters() -> HashMap<String, Vec<String>> {
+    let prettier = ["prettier", "--stdin-filepath", "{path}"];
+    let mut formatters: HashMap<String, Vec<String>> = ["javascript", "typescript", "json", "css", "markdown", "html", "yaml"]
+        .into_iter()
+        .map(|language| (language.to_string(), prettier.iter().map(|s| s.to_string()).collect()))
+        .collect();
+    formatters.insert("rust".into(), vec!["rustfmt".into(), "--edition".into(), "2021".into()]);
+    formatters.insert("python".into(), ["black", "--quiet", "--stdin-filename", "{path}", "-"].map(String::from).to_vec());
+    formatters.insert("go".into(), vec!["gofmt".into()]);
+    formatters
+}
+
+/// Language of `path`, from its extension
+pub fn language_for(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    LANGUAGES.iter().find(|(_, extensions)| extensions.contains(&extension.as_str())).map(|(language, _)| *language)
+}
+
+/// Command for `language`, or for the extension of `path`, which lets a
+/// config single out one extension (`tsx`) from its language
+pub fn command_for<'a>(formatters: &'a HashMap<String, Vec<String>>, language: &str, path: Option<&Path>) -> Option<&'a [String]> {
+    path.and_then(|p| p.extension())
+        .and_then(|e| formatters.get(&*e.to_string_lossy()))
+        .or_else(|| formatters.get(language))
+        .filter(|command| !command.is_empty())
+        .map(Vec::as_slice)
+}
+
+/// Run `command` over `source`, returning the formatted text
+pub async fn run(command: &[String], source: &str, language: &str, path: Option<&Path>) -> Result<String> {
+    let name = match path {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => {
+            let extension = LANGUAGES.iter().find(|(l, _)| *l == language).map_or("txt", |(_, e)| e[0]);
+            format!("stdin.{}", extension)
+        }
+    };
+    let program = &command[0];
+    let mut cmd = Command::new(program);
+    cmd.args(command[1..].iter().map(|arg| arg.replace("{path}", &name)))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(dir) = path.and_then(Path::parent).filter(|d| d.is_dir()) {
+        cmd.current_dir(dir);
+    }
+
+    let mut child = cmd.spawn().map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => ToolError::unsupported(format!(
+            "Formatter '{}' for {} is not installed; install it or configure code.formatters.{}",
+            program, language, language
+        )),
+        _ => ToolError::external(format!("Could not start {}: {}", program, e)),
+    })?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = source.to_string();
+    let writer = tokio::spawn(async move { stdin.write_all(input.as_bytes()).await });
+    let output = child.wait_with_output().await?;
+    // A formatter that rejects the input may exit before reading it all
+    let _ = writer.await;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(ToolError::invalid(format!("{} failed on {}: {}", program, name, stderr.trim())).into());
+    }
+    String::from_utf8(output.stdout).map_err(|_| ToolError::external(format!("{} produced invalid UTF-8", program)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_for() {
+        let mut formatters = default_formatters();
+        assert_eq!(language_for(Path::new("src/App.TSX")), Some("typescript"));
+        assert_eq!(language_for(Path::new("Makefile")), None);
+        assert_eq!(command_for(&formatters, "go", None).unwrap()[0], "gofmt");
+
+        formatters.insert("tsx".into(), vec!["dprint".into(), "fmt".into()]);
+        assert_eq!(command_for(&formatters, "typescript", Some(Path::new("a.tsx"))).unwrap()[0], "dprint");
+        assert_eq!(command_for(&formatters, "typescript", Some(Path::new("a.ts"))).unwrap()[0], "prettier");
+        assert!(command_for(&formatters, "cobol", None).is_none());
+    }
+}
@@ -16,8 +16,14 @@
 /// - hierarchy: Build class inheritance tree
 /// - rename: Rename symbols across files
 /// - grep_replace: Pattern replacement across files
-
-use anyhow::{anyhow, Result};
+/// - format: Run the language's formatter over a file or content
+
+use anyhow::Result;
+use crate::config::CodeConfig;
+use crate::error::ToolError;
+use super::code_format;
+use super::diff;
+use super::fs_atomic::write_atomic;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -45,6 +51,7 @@ pub enum CodeAction {
     Hierarchy,
     Rename,
     GrepReplace,
+    Format,
     Help,
 }
 
@@ -74,8 +81,9 @@ impl std::str::FromStr for CodeAction {
             "hierarchy" => Ok(Self::Hierarchy),
             "rename" => Ok(Self::Rename),
             "grep_replace" => Ok(Self::GrepReplace),
+            "format" | "fmt" => Ok(Self::Format),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -96,6 +104,9 @@ pub struct CodeToolArgs {
     pub replacement: Option<String>,
     pub max_results: Option<usize>,
     pub scope: Option<String>,
+    /// For format: write the result back to the file
+    #[serde(default)]
+    pub in_place: bool,
 }
 
 /// Tool definition for MCP registration
@@ -105,13 +116,13 @@ impl CodeToolDefinition {
     pub fn schema() -> Value {
         json!({
             "name": "code",
-            "description": "Code semantics: parse, serialize, symbols, outline, definition, references, search_symbol, transform, summarize, metrics, exports, types, hierarchy, rename, grep_replace",
+            "description": "Code semantics: parse, serialize, symbols, outline, definition, references, search_symbol, transform, summarize, metrics, exports, types, hierarchy, rename, grep_replace, format",
             "inputSchema": {
                 "type": "object",
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["parse", "serialize", "symbols", "outline", "definition", "references", "search_symbol", "transform", "summarize", "metrics", "exports", "types", "hierarchy", "rename", "grep_replace", "help"],
+                        "enum": ["parse", "serialize", "symbols", "outline", "definition", "references", "search_symbol", "transform", "summarize", "metrics", "exports", "types", "hierarchy", "rename", "grep_replace", "format", "help"],
                         "description": "Code action"
                     },
                     "uri": { "type": "string", "description": "File path" },
@@ -125,7 +136,8 @@ impl CodeToolDefinition {
                     "new_name": { "type": "string", "description": "New name for rename" },
                     "replacement": { "type": "string", "description": "Replacement for grep_replace" },
                     "max_results": { "type": "number", "default": 20 },
-                    "scope": { "type": "string", "description": "Search scope" }
+                    "scope": { "type": "string", "description": "Search scope" },
+                    "in_place": { "type": "boolean", "description": "For format: write the formatted source back to uri instead of returning it", "default": false }
                 },
                 "required": ["action"]
             }
@@ -134,11 +146,17 @@ impl CodeToolDefinition {
 }
 
 /// Code tool implementation
-pub struct CodeTool;
+pub struct CodeTool {
+    config: CodeConfig,
+}
 
 impl CodeTool {
     pub fn new() -> Self {
-        Self
+        Self::with_config(CodeConfig::default())
+    }
+
+    pub fn with_config(config: CodeConfig) -> Self {
+        Self { config }
     }
 
     fn resolve_uri<'a>(&self, args: &'a CodeToolArgs) -> Option<&'a str> {
@@ -233,12 +251,13 @@ impl CodeTool {
             CodeAction::Hierarchy => self.hierarchy(&args).await,
             CodeAction::Rename => self.rename(&args).await,
             CodeAction::GrepReplace => self.grep_replace(&args).await,
+            CodeAction::Format => self.format(&args).await,
             CodeAction::Help => Ok(self.help()),
         }
     }
 
     async fn parse(&self, args: &CodeToolArgs) -> Result<Value> {
-        let uri = self.resolve_uri(args).ok_or_else(|| anyhow!("uri required"))?;
+        let uri = self.resolve_uri(args).ok_or_else(|| ToolError::invalid("uri required"))?;
         let content = tokio::fs::read_to_string(uri).await?;
         let lines = content.lines().count();
         let lang = args.language.clone().unwrap_or_else(|| {
@@ -263,7 +282,7 @@ impl CodeTool {
     }
 
     async fn symbols(&self, args: &CodeToolArgs) -> Result<Value> {
-        let uri = self.resolve_uri(args).ok_or_else(|| anyhow!("uri required"))?;
+        let uri = self.resolve_uri(args).ok_or_else(|| ToolError::invalid("uri required"))?;
         let content = args.text.clone().unwrap_or(tokio::fs::read_to_string(uri).await?);
         let mut symbols = Vec::new();
 
@@ -282,7 +301,7 @@ impl CodeTool {
     }
 
     async fn outline(&self, args: &CodeToolArgs) -> Result<Value> {
-        let uri = self.resolve_uri(args).ok_or_else(|| anyhow!("uri required"))?;
+        let uri = self.resolve_uri(args).ok_or_else(|| ToolError::invalid("uri required"))?;
         let content = args.text.clone().unwrap_or(tokio::fs::read_to_string(uri).await?);
         let mut symbols = Vec::new();
         let mut imports = 0;
@@ -307,8 +326,8 @@ impl CodeTool {
     }
 
     async fn definition(&self, args: &CodeToolArgs) -> Result<Value> {
-        let uri = self.resolve_uri(args).ok_or_else(|| anyhow!("uri required"))?;
-        let symbol = args.symbol.as_deref().or(args.query.as_deref()).ok_or_else(|| anyhow!("symbol or query required"))?;
+        let uri = self.resolve_uri(args).ok_or_else(|| ToolError::invalid("uri required"))?;
+        let symbol = args.symbol.as_deref().or(args.query.as_deref()).ok_or_else(|| ToolError::invalid("symbol or query required"))?;
         let content = tokio::fs::read_to_string(uri).await?;
 
         for (i, line) in content.lines().enumerate() {
@@ -333,8 +352,8 @@ impl CodeTool {
     }
 
     async fn references(&self, args: &CodeToolArgs) -> Result<Value> {
-        let symbol = args.symbol.as_deref().or(args.query.as_deref()).ok_or_else(|| anyhow!("symbol or query required"))?;
-        let uri = self.resolve_uri(args).ok_or_else(|| anyhow!("uri required"))?;
+        let symbol = args.symbol.as_deref().or(args.query.as_deref()).ok_or_else(|| ToolError::invalid("symbol or query required"))?;
+        let uri = self.resolve_uri(args).ok_or_else(|| ToolError::invalid("uri required"))?;
         let content = tokio::fs::read_to_string(uri).await?;
         let mut refs = Vec::new();
 
@@ -353,7 +372,7 @@ impl CodeTool {
     }
 
     async fn search_symbol(&self, args: &CodeToolArgs) -> Result<Value> {
-        let query = args.query.as_deref().ok_or_else(|| anyhow!("query required"))?;
+        let query = args.query.as_deref().ok_or_else(|| ToolError::invalid("query required"))?;
         let dir = self.resolve_uri(args).unwrap_or(".");
         let max = args.max_results.unwrap_or(20);
         let files = Self::walk_files(Path::new(dir));
@@ -403,7 +422,7 @@ impl CodeTool {
         } else if let Some(uri) = uri {
             tokio::fs::read_to_string(uri).await?
         } else {
-            return Err(anyhow!("uri or text required"));
+            return Err(ToolError::invalid("uri or text required").into());
         };
 
         let lines = content.lines().count();
@@ -461,7 +480,7 @@ impl CodeTool {
     }
 
     async fn exports(&self, args: &CodeToolArgs) -> Result<Value> {
-        let uri = self.resolve_uri(args).ok_or_else(|| anyhow!("uri required"))?;
+        let uri = self.resolve_uri(args).ok_or_else(|| ToolError::invalid("uri required"))?;
         let content = tokio::fs::read_to_string(uri).await?;
         let mut exports = Vec::new();
 
@@ -481,7 +500,7 @@ impl CodeTool {
     }
 
     async fn types(&self, args: &CodeToolArgs) -> Result<Value> {
-        let uri = self.resolve_uri(args).ok_or_else(|| anyhow!("uri required"))?;
+        let uri = self.resolve_uri(args).ok_or_else(|| ToolError::invalid("uri required"))?;
         let content = tokio::fs::read_to_string(uri).await?;
         let mut type_defs = Vec::new();
 
@@ -505,7 +524,7 @@ impl CodeTool {
     }
 
     async fn hierarchy(&self, args: &CodeToolArgs) -> Result<Value> {
-        let query = args.query.as_deref().ok_or_else(|| anyhow!("query (class name) required"))?;
+        let query = args.query.as_deref().ok_or_else(|| ToolError::invalid("query (class name) required"))?;
         let dir = self.resolve_uri(args).unwrap_or(".");
         let files = Self::walk_files(Path::new(dir));
         let mut classes: HashMap<String, Vec<String>> = HashMap::new();
@@ -546,8 +565,8 @@ impl CodeTool {
     }
 
     async fn rename(&self, args: &CodeToolArgs) -> Result<Value> {
-        let query = args.query.as_deref().ok_or_else(|| anyhow!("query (old name) required"))?;
-        let new_name = args.new_name.as_deref().ok_or_else(|| anyhow!("new_name required"))?;
+        let query = args.query.as_deref().ok_or_else(|| ToolError::invalid("query (old name) required"))?;
+        let new_name = args.new_name.as_deref().ok_or_else(|| ToolError::invalid("new_name required"))?;
         let dir = self.resolve_uri(args).unwrap_or(".");
         let files = Self::walk_files(Path::new(dir));
         let re = Regex::new(&format!(r"\b{}\b", regex::escape(query)))?;
@@ -575,8 +594,8 @@ impl CodeTool {
     }
 
     async fn grep_replace(&self, args: &CodeToolArgs) -> Result<Value> {
-        let pattern = args.query.as_deref().ok_or_else(|| anyhow!("query (pattern) required"))?;
-        let replacement = args.replacement.as_deref().ok_or_else(|| anyhow!("replacement required"))?;
+        let pattern = args.query.as_deref().ok_or_else(|| ToolError::invalid("query (pattern) required"))?;
+        let replacement = args.replacement.as_deref().ok_or_else(|| ToolError::invalid("replacement required"))?;
         let dir = self.resolve_uri(args).unwrap_or(".");
         let files = Self::walk_files(Path::new(dir));
         let re = Regex::new(pattern)?;
@@ -603,6 +622,50 @@ impl CodeTool {
         }))
     }
 
+    async fn format(&self, args: &CodeToolArgs) -> Result<Value> {
+        let uri = self.resolve_uri(args).map(|u| shellexpand::tilde(u).to_string());
+        let path = uri.as_deref().map(Path::new);
+        let language = args.language.as_deref().map(str::to_lowercase)
+            .or_else(|| path.and_then(code_format::language_for).map(String::from))
+            .ok_or_else(|| ToolError::invalid("language required when it can't be told from the uri extension"))?;
+        let source = match (&args.text, &uri) {
+            (Some(text), _) => text.clone(),
+            (None, Some(uri)) => tokio::fs::read_to_string(uri).await?,
+            (None, None) => return Err(ToolError::invalid("uri or text required").into()),
+        };
+        if args.in_place && uri.is_none() {
+            return Err(ToolError::invalid("in_place requires uri").into());
+        }
+        let command = code_format::command_for(&self.config.formatters, &language, path)
+            .ok_or_else(|| ToolError::unsupported(format!("No formatter configured for {}", language)))?;
+
+        let formatted = code_format::run(command, &source, &language, path).await?;
+        let changed = formatted != source;
+        let mut data = json!({
+            "uri": uri,
+            "language": language,
+            "formatter": command[0],
+            "changed": changed
+        });
+        match &uri {
+            Some(uri) if args.in_place => {
+                if changed {
+                    write_atomic(uri, formatted.as_bytes(), false).await?;
+                }
+                data["written"] = json!(changed);
+                data["diff"] = json!(diff::unified(uri, Some(&source), Some(&formatted)));
+            }
+            _ => data["formatted"] = json!(formatted),
+        }
+
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "code", "action": "format" }
+        }))
+    }
+
     fn help(&self) -> Value {
         json!({
             "ok": true,
@@ -623,7 +686,8 @@ impl CodeTool {
                     "types": "Find type definitions (requires uri)",
                     "hierarchy": "Build class inheritance tree (requires query)",
                     "rename": "Rename symbols across files (requires query, new_name)",
-                    "grep_replace": "Pattern replacement across files (requires query, replacement)"
+                    "grep_replace": "Pattern replacement across files (requires query, replacement)",
+                    "format": "Format uri or text with the language's formatter (rustfmt, black, prettier, gofmt; configurable); in_place writes it back"
                 }
             },
             "error": null,
@@ -659,5 +723,36 @@ mod tests {
         assert_eq!("rename".parse::<CodeAction>().unwrap(), CodeAction::Rename);
         assert_eq!("grep_replace".parse::<CodeAction>().unwrap(), CodeAction::GrepReplace);
         assert_eq!("serialize".parse::<CodeAction>().unwrap(), CodeAction::Serialize);
+        assert_eq!("fmt".parse::<CodeAction>().unwrap(), CodeAction::Format);
+    }
+
+    #[tokio::test]
+    async fn test_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, "fn main( ){let x=1;}\n").unwrap();
+        let tool = CodeTool::new();
+        let args = |in_place| CodeToolArgs {
+            action: Some("format".into()),
+            uri: Some(file.to_string_lossy().to_string()),
+            in_place,
+            ..Default::default()
+        };
+
+        let result = tool.execute(args(false)).await.unwrap();
+        assert_eq!(result["data"]["formatted"], "fn main() {\n    let x = 1;\n}\n");
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn main( ){let x=1;}\n");
+
+        let result = tool.execute(args(true)).await.unwrap();
+        assert_eq!(result["data"]["written"], true);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn main() {\n    let x = 1;\n}\n");
+
+        let broken = CodeToolArgs {
+            action: Some("format".into()),
+            text: Some("fn (".into()),
+            language: Some("rust".into()),
+            ..Default::default()
+        };
+        assert!(tool.execute(broken).await.is_err());
     }
 }
@@ -1,14 +1,248 @@
 /// Linux native control using xdotool/scrot
 ///
 /// Requires: xdotool, scrot, xdpyinfo
+/// Screen capture runs in-process via Xlib (MIT-SHM when available).
 
 use anyhow::{anyhow, Result};
+use crate::error::ToolError;
 use std::collections::HashMap;
 use std::process::Command;
 use std::thread;
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
+use x11::xlib;
 
-use super::{NativeControl, PlatformInfo, WindowInfo};
+use super::{output_with_timeout, parse_ax_elements, AxElement, NativeControl, PlatformInfo, RawImage, WindowInfo, HELPER_TIMEOUT};
+
+// MIT-SHM extension (libXext); not covered by the x11 crate
+mod xshm {
+    use std::os::raw::{c_char, c_int, c_uint, c_ulong};
+    use x11::xlib::{Bool, Display, Drawable, Visual, XImage};
+
+    #[repr(C)]
+    pub struct XShmSegmentInfo {
+        pub shmseg: c_ulong,
+        pub shmid: c_int,
+        pub shmaddr: *mut c_char,
+        pub read_only: Bool,
+    }
+
+    #[link(name = "Xext")]
+    extern "C" {
+        pub fn XShmQueryExtension(display: *mut Display) -> Bool;
+        pub fn XShmCreateImage(
+            display: *mut Display,
+            visual: *mut Visual,
+            depth: c_uint,
+            format: c_int,
+            data: *mut c_char,
+            shminfo: *mut XShmSegmentInfo,
+            width: c_uint,
+            height: c_uint,
+        ) -> *mut XImage;
+        pub fn XShmAttach(display: *mut Display, shminfo: *mut XShmSegmentInfo) -> Bool;
+        pub fn XShmDetach(display: *mut Display, shminfo: *mut XShmSegmentInfo) -> Bool;
+        pub fn XShmGetImage(
+            display: *mut Display,
+            d: Drawable,
+            image: *mut XImage,
+            x: c_int,
+            y: c_int,
+            plane_mask: c_ulong,
+        ) -> Bool;
+    }
+}
+
+/// Set by `record_x_error` while a capture is in progress
+static X_ERROR: AtomicBool = AtomicBool::new(false);
+
+/// Xlib error handler that records the failure instead of exiting the process
+unsafe extern "C" fn record_x_error(_: *mut xlib::Display, _: *mut xlib::XErrorEvent) -> c_int {
+    X_ERROR.store(true, Ordering::SeqCst);
+    0
+}
+
+/// Convert an XImage to packed RGBA using its channel masks
+///
+/// # Safety
+/// `image` must point to a valid XImage of at least `width` x `height`.
+unsafe fn ximage_to_rgba(image: *mut xlib::XImage, width: u32, height: u32) -> Vec<u8> {
+    let img = &*image;
+    let shift = |mask: u64| mask.trailing_zeros();
+    let (rs, gs, bs) = (shift(img.red_mask), shift(img.green_mask), shift(img.blue_mask));
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+
+    for y in 0..height as i32 {
+        if img.bits_per_pixel == 32 {
+            // Fast path: read rows directly
+            let row = img.data.add((y * img.bytes_per_line) as usize) as *const u32;
+            for x in 0..width as usize {
+                let p = row.add(x).read_unaligned() as u64;
+                rgba.extend_from_slice(&[
+                    ((p & img.red_mask) >> rs) as u8,
+                    ((p & img.green_mask) >> gs) as u8,
+                    ((p & img.blue_mask) >> bs) as u8,
+                    255,
+                ]);
+            }
+        } else {
+            for x in 0..width as i32 {
+                let p = xlib::XGetPixel(image, x, y);
+                rgba.extend_from_slice(&[
+                    ((p & img.red_mask) >> rs) as u8,
+                    ((p & img.green_mask) >> gs) as u8,
+                    ((p & img.blue_mask) >> bs) as u8,
+                    255,
+                ]);
+            }
+        }
+    }
+
+    rgba
+}
+
+/// Grab a root window area through a shared memory segment
+///
+/// # Safety
+/// `display` must be an open X display and the area must lie on screen.
+unsafe fn grab_shm(display: *mut xlib::Display, root: xlib::Window, x: i32, y: i32, w: u32, h: u32) -> Result<Vec<u8>> {
+    if xshm::XShmQueryExtension(display) == 0 {
+        return Err(ToolError::unsupported("MIT-SHM not available").into());
+    }
+
+    let screen = xlib::XDefaultScreen(display);
+    let visual = xlib::XDefaultVisual(display, screen);
+    let depth = xlib::XDefaultDepth(display, screen) as u32;
+    let mut info: xshm::XShmSegmentInfo = std::mem::zeroed();
+
+    let image = xshm::XShmCreateImage(display, visual, depth, xlib::ZPixmap, std::ptr::null_mut(), &mut info, w, h);
+    if image.is_null() {
+        return Err(anyhow!("XShmCreateImage failed"));
+    }
+
+    let size = ((*image).bytes_per_line * (*image).height) as usize;
+    info.shmid = libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600);
+    if info.shmid < 0 {
+        xlib::XDestroyImage(image);
+        return Err(anyhow!("shmget failed"));
+    }
+    info.shmaddr = libc::shmat(info.shmid, std::ptr::null(), 0) as *mut _;
+    if info.shmaddr as isize == -1 {
+        libc::shmctl(info.shmid, libc::IPC_RMID, std::ptr::null_mut());
+        xlib::XDestroyImage(image);
+        return Err(anyhow!("shmat failed"));
+    }
+    (*image).data = info.shmaddr;
+    info.read_only = 0;
+
+    // Attach fails asynchronously (e.g. remote display); sync to surface it
+    let attached = xshm::XShmAttach(display, &mut info) != 0
+        && xlib::XSync(display, 0) != 0
+        && !X_ERROR.load(Ordering::SeqCst);
+    // Both sides are attached (or never will be); the segment can go once detached
+    libc::shmctl(info.shmid, libc::IPC_RMID, std::ptr::null_mut());
+
+    let result = if attached
+        && xshm::XShmGetImage(display, root, image, x, y, xlib::XAllPlanes()) != 0
+        && !X_ERROR.load(Ordering::SeqCst)
+    {
+        Ok(ximage_to_rgba(image, w, h))
+    } else {
+        Err(anyhow!("XShmGetImage failed"))
+    };
+
+    if attached {
+        xshm::XShmDetach(display, &mut info);
+    }
+    xlib::XSync(display, 0);
+    // Detach our mapping before XDestroyImage would free() the shm address
+    libc::shmdt(info.shmaddr as *const _);
+    (*image).data = std::ptr::null_mut();
+    xlib::XDestroyImage(image);
+
+    result
+}
+
+/// Grab a root window area with a plain XGetImage round trip
+///
+/// # Safety
+/// `display` must be an open X display and the area must lie on screen.
+unsafe fn grab_plain(display: *mut xlib::Display, root: xlib::Window, x: i32, y: i32, w: u32, h: u32) -> Result<Vec<u8>> {
+    let image = xlib::XGetImage(display, root, x, y, w, h, xlib::XAllPlanes(), xlib::ZPixmap);
+    if image.is_null() {
+        return Err(anyhow!("XGetImage failed"));
+    }
+    let rgba = ximage_to_rgba(image, w, h);
+    xlib::XDestroyImage(image);
+    Ok(rgba)
+}
+
+/// AT-SPI helper run through python3 + gi (pyatspi2 bindings).
+///
+/// Usage: `ax.py list` | `ax.py press <id>` | `ax.py set <id> <value>`.
+/// Element ids are pre-order indices within the active window.
+const AX_SCRIPT: &str = r#"
+import sys
+import gi
+gi.require_version('Atspi', '2.0')
+from gi.repository import Atspi
+
+INTERACTIVE = {
+    'push button', 'toggle button', 'check box', 'radio button', 'menu item',
+    'check menu item', 'radio menu item', 'combo box', 'text', 'entry',
+    'password text', 'link', 'slider', 'spin button', 'page tab', 'list item',
+    'menu', 'tree item', 'table cell',
+}
+
+def active_window():
+    desktop = Atspi.get_desktop(0)
+    for i in range(desktop.get_child_count()):
+        app = desktop.get_child_at_index(i)
+        if app is None:
+            continue
+        for j in range(app.get_child_count()):
+            win = app.get_child_at_index(j)
+            if win is not None and win.get_state_set().contains(Atspi.StateType.ACTIVE):
+                return win
+    return None
+
+def walk(node, out):
+    out.append(node)
+    for i in range(node.get_child_count()):
+        child = node.get_child_at_index(i)
+        if child is not None:
+            walk(child, out)
+
+win = active_window()
+if win is None:
+    sys.exit("no active window")
+nodes = []
+walk(win, nodes)
+mode = sys.argv[1]
+
+if mode == 'list':
+    for idx, el in enumerate(nodes):
+        role = el.get_role_name()
+        if role not in INTERACTIVE:
+            continue
+        value = ''
+        text = el.get_text_iface()
+        if text is not None:
+            value = text.get_text(0, text.get_character_count())
+        ext = el.get_extents(Atspi.CoordType.SCREEN)
+        row = [str(idx), role, el.get_name() or '', value,
+               str(ext.x), str(ext.y), str(ext.width), str(ext.height)]
+        print('\x1f'.join(f.replace('\n', ' ').replace('\x1f', ' ') for f in row))
+else:
+    el = nodes[int(sys.argv[2])]
+    if mode == 'press':
+        action = el.get_action_iface()
+        print('1' if action is not None and action.do_action(0) else '0')
+    elif mode == 'set':
+        editable = el.get_editable_text_iface()
+        print('1' if editable is not None and editable.set_text_contents(sys.argv[3]) else '0')
+"#;
 
 fn check_command(cmd: &str) -> bool {
     Command::new("which")
@@ -31,20 +265,32 @@ impl LinuxControl {
         }
     }
 
+    fn run_ax(&self, args: &[&str]) -> Result<String> {
+        let mut cmd = Command::new("python3");
+        cmd.arg("-c").arg(AX_SCRIPT).args(args);
+        let output = output_with_timeout(&mut cmd, HELPER_TIMEOUT)
+            .map_err(|e| anyhow!("AT-SPI helper failed: {}", e))?;
+
+        if output.status.success() {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Err(ToolError::external(format!("AT-SPI error: {}", stderr.trim())).into())
+        }
+    }
+
     fn run_xdotool(&self, args: &[&str]) -> Result<String> {
         if !self.has_xdotool {
-            return Err(anyhow!("xdotool not available"));
+            return Err(ToolError::unsupported("xdotool not available").into());
         }
 
-        let output = Command::new("xdotool")
-            .args(args)
-            .output()?;
+        let output = output_with_timeout(Command::new("xdotool").args(args), HELPER_TIMEOUT)?;
 
         if output.status.success() {
             Ok(String::from_utf8_lossy(&output.stdout).to_string())
         } else {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow!("xdotool error: {}", stderr))
+            Err(ToolError::external(format!("xdotool error: {}", stderr)).into())
         }
     }
 }
@@ -66,7 +312,7 @@ impl NativeControl for LinuxControl {
 
     fn get_pixel(&self, x: i32, y: i32) -> Result<(u8, u8, u8)> {
         if !self.has_scrot {
-            return Err(anyhow!("scrot not available for pixel reading"));
+            return Err(ToolError::unsupported("scrot not available for pixel reading").into());
         }
 
         let tmp_path = format!("/tmp/hanzo_pixel_{}.png", std::process::id());
@@ -311,9 +557,50 @@ impl NativeControl for LinuxControl {
         Ok(())
     }
 
+    fn capture(&self, region: Option<&[i32]>) -> Result<RawImage> {
+        unsafe {
+            let display = xlib::XOpenDisplay(std::ptr::null());
+            if display.is_null() {
+                return Err(anyhow!("Cannot open X display"));
+            }
+
+            let screen = xlib::XDefaultScreen(display);
+            let root = xlib::XRootWindow(display, screen);
+            let sw = xlib::XDisplayWidth(display, screen);
+            let sh = xlib::XDisplayHeight(display, screen);
+
+            let (x, y, w, h) = match region {
+                Some(r) if r.len() == 4 => {
+                    // Clamp to the screen so X does not reject the request
+                    let x = r[0].clamp(0, sw - 1);
+                    let y = r[1].clamp(0, sh - 1);
+                    (x, y, r[2].min(sw - x).max(1), r[3].min(sh - y).max(1))
+                }
+                Some(_) => {
+                    xlib::XCloseDisplay(display);
+                    return Err(ToolError::invalid("Invalid region").into());
+                }
+                None => (0, 0, sw, sh),
+            };
+            let (w, h) = (w as u32, h as u32);
+
+            X_ERROR.store(false, Ordering::SeqCst);
+            let previous = xlib::XSetErrorHandler(Some(record_x_error));
+            let rgba = grab_shm(display, root, x, y, w, h).or_else(|_| {
+                X_ERROR.store(false, Ordering::SeqCst);
+                grab_plain(display, root, x, y, w, h)
+            });
+            xlib::XSync(display, 0);
+            xlib::XSetErrorHandler(previous);
+            xlib::XCloseDisplay(display);
+
+            Ok(RawImage { width: w, height: h, rgba: rgba? })
+        }
+    }
+
     fn screenshot(&self, region: Option<&[i32]>) -> Result<Vec<u8>> {
         if !self.has_scrot {
-            return Err(anyhow!("scrot not available"));
+            return Err(ToolError::unsupported("scrot not available").into());
         }
 
         let tmp_path = format!("/tmp/hanzo_screenshot_{}.png", std::process::id());
@@ -376,4 +663,19 @@ impl NativeControl for LinuxControl {
         let result = self.run_xdotool(&["search", "--name", title, "windowactivate"]);
         Ok(result.is_ok())
     }
+
+    fn ax_elements(&self) -> Result<Vec<AxElement>> {
+        let output = self.run_ax(&["list"])?;
+        Ok(parse_ax_elements(&output))
+    }
+
+    fn ax_press(&self, id: usize) -> Result<bool> {
+        let output = self.run_ax(&["press", &id.to_string()])?;
+        Ok(output.trim() == "1")
+    }
+
+    fn ax_set_value(&self, id: usize, value: &str) -> Result<bool> {
+        let output = self.run_ax(&["set", &id.to_string(), value])?;
+        Ok(output.trim() == "1")
+    }
 }
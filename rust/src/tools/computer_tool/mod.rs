@@ -11,6 +11,7 @@
 /// - Screenshot: <50ms
 
 use anyhow::{anyhow, Result};
+use crate::error::ToolError;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -25,6 +26,10 @@ mod linux;
 #[cfg(target_os = "windows")]
 mod windows;
 
+mod recording;
+
+pub use recording::VideoFormat;
+
 /// Platform-independent action types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -49,6 +54,8 @@ pub enum UiAction {
     // Screen
     Screenshot,
     ScreenshotRegion,
+    RecordScreenStart,
+    RecordScreenStop,
     // Window
     GetActiveWindow,
     ListWindows,
@@ -61,6 +68,13 @@ pub enum UiAction {
     Sleep,
     SetPause,
     SetFailsafe,
+    // Regions
+    DefineRegion,
+    ListRegions,
+    // Accessibility
+    AxList,
+    AxClick,
+    AxSetValue,
     // Batch
     Batch,
     // Info
@@ -73,6 +87,31 @@ impl Default for UiAction {
     }
 }
 
+impl UiAction {
+    /// Whether the action synthesizes mouse or keyboard input. Only these are
+    /// subject to the failsafe check and the post-action pause.
+    pub fn is_input(&self) -> bool {
+        matches!(
+            self,
+            Self::Click
+                | Self::DoubleClick
+                | Self::RightClick
+                | Self::MiddleClick
+                | Self::Move
+                | Self::MoveRelative
+                | Self::Drag
+                | Self::DragRelative
+                | Self::Scroll
+                | Self::Type
+                | Self::Write
+                | Self::Press
+                | Self::KeyDown
+                | Self::KeyUp
+                | Self::Hotkey
+        )
+    }
+}
+
 impl std::str::FromStr for UiAction {
     type Err = anyhow::Error;
 
@@ -95,6 +134,8 @@ impl std::str::FromStr for UiAction {
             "hotkey" => Ok(Self::Hotkey),
             "screenshot" => Ok(Self::Screenshot),
             "screenshot_region" | "screenshotregion" => Ok(Self::ScreenshotRegion),
+            "record_screen_start" | "recordscreenstart" | "record_start" => Ok(Self::RecordScreenStart),
+            "record_screen_stop" | "recordscreenstop" | "record_stop" => Ok(Self::RecordScreenStop),
             "get_active_window" | "getactivewindow" => Ok(Self::GetActiveWindow),
             "list_windows" | "listwindows" => Ok(Self::ListWindows),
             "focus_window" | "focuswindow" => Ok(Self::FocusWindow),
@@ -104,9 +145,14 @@ impl std::str::FromStr for UiAction {
             "sleep" => Ok(Self::Sleep),
             "set_pause" | "setpause" => Ok(Self::SetPause),
             "set_failsafe" | "setfailsafe" => Ok(Self::SetFailsafe),
+            "define_region" | "defineregion" => Ok(Self::DefineRegion),
+            "list_regions" | "listregions" => Ok(Self::ListRegions),
+            "ax_list" | "axlist" => Ok(Self::AxList),
+            "ax_click" | "axclick" => Ok(Self::AxClick),
+            "ax_set_value" | "axsetvalue" => Ok(Self::AxSetValue),
             "batch" => Ok(Self::Batch),
             "info" => Ok(Self::Info),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -136,11 +182,13 @@ pub struct ComputerToolArgs {
     #[serde(default = "default_interval")]
     pub interval: f64,
     pub region: Option<Vec<i32>>,
+    /// Named region defined via define_region
+    pub region_name: Option<String>,
     #[serde(default)]
     pub clear: bool,
     // Window
     pub title: Option<String>,
-    // Name (for screenshot file)
+    // Name (for screenshot or recording file)
     pub name: Option<String>,
     // Width/height
     pub width: Option<i32>,
@@ -149,6 +197,16 @@ pub struct ComputerToolArgs {
     pub value: Option<f64>,
     // Batch
     pub actions: Option<Vec<Value>>,
+    // Accessibility
+    pub element: Option<usize>,
+    pub role: Option<String>,
+    // Screenshot encoding
+    pub format: Option<String>,
+    pub quality: Option<u8>,
+    pub scale: Option<f64>,
+    // Screen recording
+    pub fps: Option<u32>,
+    pub max_seconds: Option<u64>,
 }
 
 fn default_button() -> String {
@@ -174,6 +232,166 @@ pub struct WindowInfo {
     pub height: i32,
 }
 
+/// Interactive element exposed by the OS accessibility API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AxElement {
+    /// Index within the focused window's element tree; valid until the UI changes
+    pub id: usize,
+    pub role: String,
+    pub label: String,
+    pub value: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Parse accessibility rows of the form
+/// `id<US>role<US>label<US>value<US>x<US>y<US>w<US>h`, one per line.
+/// All platform backends emit this format from their helper scripts.
+fn parse_ax_elements(output: &str) -> Vec<AxElement> {
+    let num = |s: &str| s.trim().parse::<f64>().map(|v| v as i32).unwrap_or(0);
+
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split('\x1f').collect();
+            if parts.len() < 8 {
+                return None;
+            }
+            Some(AxElement {
+                id: parts[0].trim().parse().ok()?,
+                role: parts[1].to_string(),
+                label: parts[2].to_string(),
+                value: Some(parts[3].to_string()).filter(|v| !v.is_empty()),
+                x: num(parts[4]),
+                y: num(parts[5]),
+                width: num(parts[6]),
+                height: num(parts[7]),
+            })
+        })
+        .collect()
+}
+
+/// Raw RGBA frame captured in-process
+pub struct RawImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Screenshot encoding
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureFormat {
+    Png,
+    Jpeg,
+    /// Lossless WebP; `quality` does not apply
+    Webp,
+}
+
+impl CaptureFormat {
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Webp => "webp",
+        }
+    }
+}
+
+impl std::str::FromStr for CaptureFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "webp" => Ok(Self::Webp),
+            _ => Err(ToolError::invalid(format!("Unknown image format: {} (png, jpeg, webp)", s)).into()),
+        }
+    }
+}
+
+/// Screenshot encoding options
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CaptureOptions {
+    pub(crate) format: CaptureFormat,
+    pub(crate) quality: u8,
+    pub(crate) scale: f64,
+}
+
+/// Encoded screenshot
+struct Captured {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    native: bool,
+}
+
+/// Capture the screen in-process, falling back to the platform screenshot
+/// command when native capture is unavailable.
+fn capture_screen(ctrl: &dyn NativeControl, region: Option<&[i32]>, opts: CaptureOptions) -> Result<Captured> {
+    let (raw, native) = match ctrl.capture(region) {
+        Ok(raw) => (raw, true),
+        Err(e) => {
+            log::debug!("Native capture unavailable, using screenshot command: {}", e);
+            let png = ctrl.screenshot(region)?;
+            if opts.format == CaptureFormat::Png && opts.scale >= 1.0 {
+                let (width, height) = image::ImageReader::new(std::io::Cursor::new(&png))
+                    .with_guessed_format()?
+                    .into_dimensions()?;
+                return Ok(Captured { data: png, width, height, native: false });
+            }
+            let rgba = image::load_from_memory(&png)?.to_rgba8();
+            let (width, height) = rgba.dimensions();
+            (RawImage { width, height, rgba: rgba.into_raw() }, false)
+        }
+    };
+
+    let (data, width, height) = encode_image(raw, opts)?;
+    Ok(Captured { data, width, height, native })
+}
+
+/// Downscale and encode a raw frame
+pub(crate) fn encode_image(raw: RawImage, opts: CaptureOptions) -> Result<(Vec<u8>, u32, u32)> {
+    use image::codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder};
+    use image::{ExtendedColorType, ImageEncoder};
+
+    let mut img = image::RgbaImage::from_raw(raw.width, raw.height, raw.rgba)
+        .ok_or_else(|| anyhow!("Captured buffer does not match {}x{}", raw.width, raw.height))?;
+
+    if opts.scale < 1.0 {
+        let width = ((img.width() as f64 * opts.scale).round() as u32).max(1);
+        let height = ((img.height() as f64 * opts.scale).round() as u32).max(1);
+        img = image::imageops::resize(&img, width, height, image::imageops::FilterType::Triangle);
+    }
+
+    let (width, height) = img.dimensions();
+    let mut data = Vec::new();
+    match opts.format {
+        CaptureFormat::Png => {
+            // Fast compression keeps full-screen encodes within the latency budget
+            PngEncoder::new_with_quality(
+                &mut data,
+                image::codecs::png::CompressionType::Fast,
+                image::codecs::png::FilterType::Adaptive,
+            )
+            .write_image(&img, width, height, ExtendedColorType::Rgba8)?;
+        }
+        CaptureFormat::Jpeg => {
+            let rgb = image::DynamicImage::ImageRgba8(img).to_rgb8();
+            JpegEncoder::new_with_quality(&mut data, opts.quality)
+                .write_image(&rgb, width, height, ExtendedColorType::Rgb8)?;
+        }
+        CaptureFormat::Webp => {
+            WebPEncoder::new_lossless(&mut data)
+                .write_image(&img, width, height, ExtendedColorType::Rgba8)?;
+        }
+    }
+
+    Ok((data, width, height))
+}
+
 /// Platform capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlatformInfo {
@@ -232,7 +450,10 @@ pub trait NativeControl: Send + Sync {
     fn type_text(&self, text: &str, interval: f64) -> Result<()>;
 
     // Screen Capture
-    /// Take screenshot
+    /// Capture screen pixels in-process (no subprocess)
+    fn capture(&self, region: Option<&[i32]>) -> Result<RawImage>;
+
+    /// Take screenshot as PNG using the platform screenshot command
     fn screenshot(&self, region: Option<&[i32]>) -> Result<Vec<u8>>;
 
     /// Get pixel color at position
@@ -262,6 +483,92 @@ pub trait NativeControl: Send + Sync {
 
     /// Close window by title
     fn close_window(&self, title: &str) -> Result<bool>;
+
+    // Accessibility
+    /// List interactive elements of the focused window
+    fn ax_elements(&self) -> Result<Vec<AxElement>>;
+
+    /// Invoke the default action (press) of an element
+    fn ax_press(&self, id: usize) -> Result<bool>;
+
+    /// Set the value of an editable element
+    fn ax_set_value(&self, id: usize, value: &str) -> Result<bool>;
+}
+
+/// Raised when the mouse is in a screen corner while failsafe is enabled.
+///
+/// Mirrors PyAutoGUI's `FailSafeException`: batch execution stops at the
+/// first action that trips it.
+#[derive(Debug, thiserror::Error)]
+#[error("Failsafe triggered: mouse at corner ({x}, {y}). Move the mouse away or call set_failsafe(value=0)")]
+pub struct FailsafeTriggered {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Longest a helper process (osascript, AT-SPI script, xdotool) may run
+const HELPER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Run a helper process, killing it once `timeout` passes. Native control
+/// is synchronous, so the registry's timeout cannot interrupt a hung helper.
+#[cfg_attr(target_os = "windows", allow(dead_code))]
+fn output_with_timeout(cmd: &mut std::process::Command, timeout: std::time::Duration) -> Result<std::process::Output> {
+    use std::io::Read;
+    use std::process::Stdio;
+
+    let mut child = cmd.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    // Drain pipes on threads so a chatty child cannot block on a full pipe
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut buf);
+            }
+            buf
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as Box<dyn Read + Send>));
+
+    let deadline = std::time::Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if std::time::Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(ToolError::timeout(format!("Helper process timed out after {}s", timeout.as_secs())).into());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    Ok(std::process::Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Check whether a point lies in one of the four screen corners
+fn in_failsafe_corner(x: i32, y: i32, width: i32, height: i32) -> bool {
+    let right = width - 1;
+    let bottom = height - 1;
+    (x <= 0 || x >= right) && (y <= 0 || y >= bottom)
+}
+
+/// Where a named screenshot or recording goes: absolute and `~` paths as
+/// given, bare names in the temp directory, with `ext` added if missing
+fn output_path(name: &str, ext: &str) -> String {
+    let path = if name.starts_with('/') || name.starts_with('~') {
+        shellexpand::tilde(name).to_string()
+    } else {
+        format!("{}/{}", std::env::temp_dir().display(), name)
+    };
+    if path.ends_with(&format!(".{}", ext)) {
+        path
+    } else {
+        format!("{}.{}", path, ext)
+    }
 }
 
 /// Get the native control implementation for current platform
@@ -293,6 +600,7 @@ pub struct ComputerTool {
     defined_regions: HashMap<String, (i32, i32, i32, i32)>,
     pause: f64,
     failsafe: bool,
+    recording: Option<recording::Recording>,
 }
 
 impl ComputerTool {
@@ -302,9 +610,17 @@ impl ComputerTool {
             defined_regions: HashMap::new(),
             pause: 0.1,
             failsafe: true,
+            recording: None,
         }
     }
 
+    /// Titles of the open windows
+    pub async fn window_titles(&self) -> Result<Vec<String>> {
+        let ctrl = Arc::clone(&self.control);
+        let windows = tokio::task::spawn_blocking(move || ctrl.list_windows()).await??;
+        Ok(windows.into_iter().map(|w| w.title).filter(|t| !t.is_empty()).collect())
+    }
+
     pub async fn execute(&mut self, args: ComputerToolArgs) -> Result<String> {
         let action: UiAction = if args.action.is_empty() {
             UiAction::Info
@@ -312,51 +628,65 @@ impl ComputerTool {
             args.action.parse()?
         };
 
+        let is_input = action.is_input();
+        if is_input && self.failsafe {
+            self.check_failsafe().await?;
+        }
+
         // Clone Arc for use in spawn_blocking closures
         let ctrl = Arc::clone(&self.control);
 
         let result = match action {
             // Fast native operations - no spawn_blocking needed
             UiAction::Click => {
-                let x = args.x.ok_or_else(|| anyhow!("x required"))?;
-                let y = args.y.ok_or_else(|| anyhow!("y required"))?;
+                let (x, y) = match args.region_name.as_deref() {
+                    Some(name) => {
+                        let (rx, ry, rw, rh) = self.region(name)?;
+                        // x/y are offsets into the region; default to its center
+                        (rx + args.x.unwrap_or(rw / 2), ry + args.y.unwrap_or(rh / 2))
+                    }
+                    None => (
+                        args.x.ok_or_else(|| ToolError::invalid("x required"))?,
+                        args.y.ok_or_else(|| ToolError::invalid("y required"))?,
+                    ),
+                };
                 let button = args.button.clone();
                 tokio::task::spawn_blocking(move || ctrl.click(x, y, &button)).await??;
                 json!({"success": true, "clicked": [x, y], "button": args.button})
             }
 
             UiAction::DoubleClick => {
-                let x = args.x.ok_or_else(|| anyhow!("x required"))?;
-                let y = args.y.ok_or_else(|| anyhow!("y required"))?;
+                let x = args.x.ok_or_else(|| ToolError::invalid("x required"))?;
+                let y = args.y.ok_or_else(|| ToolError::invalid("y required"))?;
                 // Double click has internal sleep - must use spawn_blocking
                 tokio::task::spawn_blocking(move || ctrl.double_click(x, y)).await??;
                 json!({"success": true, "double_clicked": [x, y]})
             }
 
             UiAction::RightClick => {
-                let x = args.x.ok_or_else(|| anyhow!("x required"))?;
-                let y = args.y.ok_or_else(|| anyhow!("y required"))?;
+                let x = args.x.ok_or_else(|| ToolError::invalid("x required"))?;
+                let y = args.y.ok_or_else(|| ToolError::invalid("y required"))?;
                 tokio::task::spawn_blocking(move || ctrl.click(x, y, "right")).await??;
                 json!({"success": true, "right_clicked": [x, y]})
             }
 
             UiAction::MiddleClick => {
-                let x = args.x.ok_or_else(|| anyhow!("x required"))?;
-                let y = args.y.ok_or_else(|| anyhow!("y required"))?;
+                let x = args.x.ok_or_else(|| ToolError::invalid("x required"))?;
+                let y = args.y.ok_or_else(|| ToolError::invalid("y required"))?;
                 tokio::task::spawn_blocking(move || ctrl.click(x, y, "middle")).await??;
                 json!({"success": true, "middle_clicked": [x, y]})
             }
 
             UiAction::Move => {
-                let x = args.x.ok_or_else(|| anyhow!("x required"))?;
-                let y = args.y.ok_or_else(|| anyhow!("y required"))?;
+                let x = args.x.ok_or_else(|| ToolError::invalid("x required"))?;
+                let y = args.y.ok_or_else(|| ToolError::invalid("y required"))?;
                 tokio::task::spawn_blocking(move || ctrl.move_to(x, y)).await??;
                 json!({"success": true, "moved_to": [x, y]})
             }
 
             UiAction::MoveRelative => {
-                let dx = args.dx.ok_or_else(|| anyhow!("dx required"))?;
-                let dy = args.dy.ok_or_else(|| anyhow!("dy required"))?;
+                let dx = args.dx.ok_or_else(|| ToolError::invalid("dx required"))?;
+                let dy = args.dy.ok_or_else(|| ToolError::invalid("dy required"))?;
                 // mouse_position uses osascript on macOS - blocking
                 let (cx, cy) = tokio::task::spawn_blocking({
                     let ctrl = Arc::clone(&ctrl);
@@ -367,8 +697,8 @@ impl ComputerTool {
             }
 
             UiAction::Drag => {
-                let x = args.x.ok_or_else(|| anyhow!("x required"))?;
-                let y = args.y.ok_or_else(|| anyhow!("y required"))?;
+                let x = args.x.ok_or_else(|| ToolError::invalid("x required"))?;
+                let y = args.y.ok_or_else(|| ToolError::invalid("y required"))?;
                 let (start_x, start_y) = tokio::task::spawn_blocking({
                     let ctrl = Arc::clone(&ctrl);
                     move || ctrl.mouse_position()
@@ -384,8 +714,8 @@ impl ComputerTool {
             }
 
             UiAction::DragRelative => {
-                let dx = args.dx.ok_or_else(|| anyhow!("dx required"))?;
-                let dy = args.dy.ok_or_else(|| anyhow!("dy required"))?;
+                let dx = args.dx.ok_or_else(|| ToolError::invalid("dx required"))?;
+                let dy = args.dy.ok_or_else(|| ToolError::invalid("dy required"))?;
                 let (cx, cy) = tokio::task::spawn_blocking({
                     let ctrl = Arc::clone(&ctrl);
                     move || ctrl.mouse_position()
@@ -398,7 +728,7 @@ impl ComputerTool {
             }
 
             UiAction::Scroll => {
-                let amount = args.amount.ok_or_else(|| anyhow!("amount required"))?;
+                let amount = args.amount.ok_or_else(|| ToolError::invalid("amount required"))?;
                 let x = args.x;
                 let y = args.y;
                 tokio::task::spawn_blocking(move || ctrl.scroll(amount, x, y)).await??;
@@ -406,7 +736,7 @@ impl ComputerTool {
             }
 
             UiAction::Type => {
-                let text = args.text.ok_or_else(|| anyhow!("text required"))?;
+                let text = args.text.ok_or_else(|| ToolError::invalid("text required"))?;
                 let len = text.len();
                 let interval = args.interval;
                 // type_text has internal sleeps - must use spawn_blocking
@@ -415,7 +745,7 @@ impl ComputerTool {
             }
 
             UiAction::Write => {
-                let text = args.text.ok_or_else(|| anyhow!("text required"))?;
+                let text = args.text.ok_or_else(|| ToolError::invalid("text required"))?;
                 let len = text.len();
                 if args.clear {
                     // Select all and clear
@@ -435,70 +765,126 @@ impl ComputerTool {
             }
 
             UiAction::Press => {
-                let key = args.key.ok_or_else(|| anyhow!("key required"))?;
+                let key = args.key.ok_or_else(|| ToolError::invalid("key required"))?;
                 let key_clone = key.clone();
                 tokio::task::spawn_blocking(move || ctrl.press(&key_clone)).await??;
                 json!({"success": true, "pressed": key})
             }
 
             UiAction::KeyDown => {
-                let key = args.key.ok_or_else(|| anyhow!("key required"))?;
+                let key = args.key.ok_or_else(|| ToolError::invalid("key required"))?;
                 let key_clone = key.clone();
                 tokio::task::spawn_blocking(move || ctrl.key_down(&key_clone)).await??;
                 json!({"success": true, "key_down": key})
             }
 
             UiAction::KeyUp => {
-                let key = args.key.ok_or_else(|| anyhow!("key required"))?;
+                let key = args.key.ok_or_else(|| ToolError::invalid("key required"))?;
                 let key_clone = key.clone();
                 tokio::task::spawn_blocking(move || ctrl.key_up(&key_clone)).await??;
                 json!({"success": true, "key_up": key})
             }
 
             UiAction::Hotkey => {
-                let keys = args.keys.ok_or_else(|| anyhow!("keys required"))?;
+                let keys = args.keys.ok_or_else(|| ToolError::invalid("keys required"))?;
                 let combo = keys.join("+");
                 tokio::task::spawn_blocking(move || ctrl.hotkey(&keys)).await??;
                 json!({"success": true, "hotkey": combo})
             }
 
             UiAction::Screenshot | UiAction::ScreenshotRegion => {
-                let region: Option<Vec<i32>> = args.region.clone();
-                // Screenshot uses subprocess - must use spawn_blocking
-                let data = tokio::task::spawn_blocking(move || {
-                    ctrl.screenshot(region.as_deref())
+                let region: Option<Vec<i32>> = match args.region_name.as_deref() {
+                    Some(name) => {
+                        let (x, y, w, h) = self.region(name)?;
+                        Some(vec![x, y, w, h])
+                    }
+                    None => args.region.clone(),
+                };
+                let format: CaptureFormat = match args.format.as_deref() {
+                    Some(f) => f.parse()?,
+                    None => CaptureFormat::Png,
+                };
+                let scale = args.scale.unwrap_or(1.0);
+                if !(scale > 0.0 && scale <= 1.0) {
+                    return Err(ToolError::invalid("scale must be in (0, 1]").into());
+                }
+                let opts = CaptureOptions {
+                    format,
+                    quality: args.quality.unwrap_or(80).clamp(1, 100),
+                    scale,
+                };
+
+                let start = std::time::Instant::now();
+                // Capture and encoding are CPU-bound - must use spawn_blocking
+                let captured = tokio::task::spawn_blocking(move || {
+                    capture_screen(ctrl.as_ref(), region.as_deref(), opts)
                 }).await??;
+                let elapsed = start.elapsed().as_millis();
+                let ext = format.extension();
+
+                let mut result = json!({
+                    "success": true,
+                    "format": ext,
+                    "width": captured.width,
+                    "height": captured.height,
+                    "size": captured.data.len(),
+                    "backend": if captured.native { "native" } else { "command" },
+                    "elapsed_ms": elapsed
+                });
 
                 // If name provided, save to file
                 if let Some(name) = args.name {
-                    let path = if name.starts_with('/') || name.starts_with('~') {
-                        shellexpand::tilde(&name).to_string()
-                    } else {
-                        format!("{}/{}", std::env::temp_dir().display(), name)
-                    };
-                    let path = if !path.ends_with(".png") {
-                        format!("{}.png", path)
-                    } else {
-                        path
-                    };
+                    let path = output_path(&name, ext);
                     // Async file write
-                    tokio::fs::write(&path, &data).await?;
-                    json!({
-                        "success": true,
-                        "format": "png",
-                        "size": data.len(),
-                        "path": path
-                    })
+                    tokio::fs::write(&path, &captured.data).await?;
+                    result["path"] = json!(path);
                 } else {
                     use base64::{Engine, engine::general_purpose::STANDARD};
-                    let b64 = STANDARD.encode(&data);
-                    json!({
-                        "success": true,
-                        "format": "png",
-                        "size": data.len(),
-                        "base64": b64
-                    })
+                    result["base64"] = json!(STANDARD.encode(&captured.data));
+                }
+                result
+            }
+
+            UiAction::RecordScreenStart => {
+                if let Some(recording) = &self.recording {
+                    return Err(ToolError::conflict(format!(
+                        "Already recording to {}; call record_screen_stop first",
+                        recording.status()["path"].as_str().unwrap_or("")
+                    )).into());
                 }
+                let region = match args.region_name.as_deref() {
+                    Some(name) => {
+                        let (x, y, w, h) = self.region(name)?;
+                        Some([x, y, w, h])
+                    }
+                    None => match args.region.as_deref() {
+                        Some(&[x, y, w, h]) => Some([x, y, w, h]),
+                        Some(_) => return Err(ToolError::invalid("region must be [x, y, width, height]").into()),
+                        None => None,
+                    },
+                };
+                let format: recording::VideoFormat = args.format.as_deref().unwrap_or("mp4").parse()?;
+                let name = args.name.clone()
+                    .unwrap_or_else(|| format!("recording-{}", chrono::Local::now().format("%Y%m%d-%H%M%S")));
+                let options = recording::RecordOptions {
+                    path: output_path(&name, format.extension()).into(),
+                    format,
+                    region,
+                    fps: args.fps.unwrap_or(recording::DEFAULT_FPS).clamp(1, 60),
+                    max_secs: args.max_seconds.unwrap_or(recording::DEFAULT_MAX_SECS).max(1),
+                };
+                let started = recording::Recording::start(options).await?;
+                let mut result = started.status();
+                result["success"] = json!(true);
+                result["recording"] = json!(true);
+                self.recording = Some(started);
+                result
+            }
+
+            UiAction::RecordScreenStop => {
+                let recording = self.recording.take()
+                    .ok_or_else(|| ToolError::invalid("No recording in progress; call record_screen_start first"))?;
+                recording.stop().await?
             }
 
             UiAction::GetActiveWindow => {
@@ -518,7 +904,7 @@ impl ComputerTool {
             }
 
             UiAction::FocusWindow => {
-                let title = args.title.or(args.text).ok_or_else(|| anyhow!("title required"))?;
+                let title = args.title.or(args.text).ok_or_else(|| ToolError::invalid("title required"))?;
                 let title_clone = title.clone();
                 // Uses osascript/xdotool - must use spawn_blocking
                 let success = tokio::task::spawn_blocking(move || {
@@ -551,28 +937,92 @@ impl ComputerTool {
             }
 
             UiAction::Sleep => {
-                let secs = args.value.ok_or_else(|| anyhow!("value required"))?;
+                let secs = args.value.ok_or_else(|| ToolError::invalid("value required"))?;
                 // Use async sleep - does not block runtime
                 tokio::time::sleep(std::time::Duration::from_secs_f64(secs)).await;
                 json!({"success": true, "slept": secs})
             }
 
             UiAction::SetPause => {
-                let val = args.value.ok_or_else(|| anyhow!("value required"))?;
+                let val = args.value.ok_or_else(|| ToolError::invalid("value required"))?;
+                if !val.is_finite() || val < 0.0 {
+                    return Err(ToolError::invalid("pause must be a non-negative number of seconds").into());
+                }
                 self.pause = val;
                 json!({"success": true, "pause": self.pause})
             }
 
             UiAction::SetFailsafe => {
-                let val = args.value.ok_or_else(|| anyhow!("value required"))?;
+                let val = args.value.ok_or_else(|| ToolError::invalid("value required"))?;
                 self.failsafe = val != 0.0;
                 json!({"success": true, "failsafe": self.failsafe})
             }
 
+            UiAction::DefineRegion => {
+                let name = args.name.ok_or_else(|| ToolError::invalid("name required"))?;
+                let x = args.x.ok_or_else(|| ToolError::invalid("x required"))?;
+                let y = args.y.ok_or_else(|| ToolError::invalid("y required"))?;
+                let w = args.width.ok_or_else(|| ToolError::invalid("width required"))?;
+                let h = args.height.ok_or_else(|| ToolError::invalid("height required"))?;
+                if w <= 0 || h <= 0 {
+                    return Err(ToolError::invalid("width and height must be positive").into());
+                }
+                self.defined_regions.insert(name.clone(), (x, y, w, h));
+                json!({"success": true, "region": name, "bounds": [x, y, w, h]})
+            }
+
+            UiAction::ListRegions => {
+                let mut names: Vec<&String> = self.defined_regions.keys().collect();
+                names.sort();
+                let regions: Vec<Value> = names
+                    .into_iter()
+                    .map(|name| {
+                        let (x, y, w, h) = self.defined_regions[name];
+                        json!({"name": name, "x": x, "y": y, "width": w, "height": h})
+                    })
+                    .collect();
+                json!({"regions": regions, "count": regions.len()})
+            }
+
+            UiAction::AxList => {
+                // Accessibility queries run helper scripts - must use spawn_blocking
+                let mut elements = tokio::task::spawn_blocking(move || {
+                    ctrl.ax_elements()
+                }).await??;
+                if let Some(role) = args.role.as_deref() {
+                    let role = role.to_lowercase();
+                    elements.retain(|e| e.role.to_lowercase().contains(&role));
+                }
+                if let Some(text) = args.text.as_deref() {
+                    let text = text.to_lowercase();
+                    elements.retain(|e| {
+                        e.label.to_lowercase().contains(&text)
+                            || e.value.as_deref().is_some_and(|v| v.to_lowercase().contains(&text))
+                    });
+                }
+                json!({"elements": elements, "count": elements.len()})
+            }
+
+            UiAction::AxClick => {
+                let id = args.element.ok_or_else(|| ToolError::invalid("element required (id from ax_list)"))?;
+                let success = tokio::task::spawn_blocking(move || ctrl.ax_press(id)).await??;
+                json!({"success": success, "pressed": id})
+            }
+
+            UiAction::AxSetValue => {
+                let id = args.element.ok_or_else(|| ToolError::invalid("element required (id from ax_list)"))?;
+                let text = args.text.ok_or_else(|| ToolError::invalid("text required"))?;
+                let success = tokio::task::spawn_blocking(move || {
+                    ctrl.ax_set_value(id, &text)
+                }).await??;
+                json!({"success": success, "element": id})
+            }
+
             UiAction::Batch => {
-                let actions = args.actions.ok_or_else(|| anyhow!("actions required"))?;
+                let actions = args.actions.ok_or_else(|| ToolError::invalid("actions required"))?;
                 let start = std::time::Instant::now();
                 let mut results = Vec::new();
+                let mut aborted = false;
 
                 for (i, action_val) in actions.iter().enumerate() {
                     let action_args: ComputerToolArgs = serde_json::from_value(action_val.clone())
@@ -582,6 +1032,11 @@ impl ComputerTool {
                         Ok(_) => {
                             results.push(json!({"index": i, "success": true}));
                         }
+                        Err(e) if e.is::<FailsafeTriggered>() => {
+                            results.push(json!({"index": i, "error": e.to_string()}));
+                            aborted = true;
+                            break;
+                        }
                         Err(e) => {
                             results.push(json!({"index": i, "error": e.to_string()}));
                         }
@@ -590,8 +1045,10 @@ impl ComputerTool {
 
                 let elapsed = start.elapsed().as_millis();
                 json!({
-                    "success": true,
+                    "success": !aborted,
                     "count": results.len(),
+                    "total": actions.len(),
+                    "aborted": aborted,
                     "elapsed_ms": elapsed,
                     "results": results
                 })
@@ -615,13 +1072,43 @@ impl ComputerTool {
                     "platform": platform_info,
                     "pause": self.pause,
                     "failsafe": self.failsafe,
-                    "regions": self.defined_regions.keys().collect::<Vec<_>>()
+                    "regions": self.defined_regions.keys().collect::<Vec<_>>(),
+                    "recording": self.recording.as_ref().map(|r| r.status())
                 })
             }
         };
 
+        if is_input && self.pause > 0.0 {
+            tokio::time::sleep(std::time::Duration::from_secs_f64(self.pause)).await;
+        }
+
         Ok(serde_json::to_string(&result)?)
     }
+
+    /// Look up a named region as (x, y, width, height)
+    fn region(&self, name: &str) -> Result<(i32, i32, i32, i32)> {
+        let region = self.defined_regions
+            .get(name)
+            .copied()
+            .ok_or_else(|| ToolError::not_found(format!("Unknown region: {}. Use define_region first", name)))?;
+        Ok(region)
+    }
+
+    /// Abort if the mouse sits in a screen corner. If the position or screen
+    /// size cannot be read (e.g. headless), the check is skipped.
+    async fn check_failsafe(&self) -> Result<()> {
+        let ctrl = Arc::clone(&self.control);
+        let probe = tokio::task::spawn_blocking(move || {
+            Ok::<_, anyhow::Error>((ctrl.mouse_position()?, ctrl.screen_size()?))
+        }).await?;
+
+        if let Ok(((x, y), (w, h))) = probe {
+            if in_failsafe_corner(x, y, w, h) {
+                return Err(FailsafeTriggered { x, y }.into());
+            }
+        }
+        Ok(())
+    }
 }
 
 /// MCP Tool Definition
@@ -665,6 +1152,9 @@ KEYBOARD (< 2ms native):
 
 SCREEN (< 50ms native):
 - screenshot() / screenshot_region(region)
+- screenshot(format="jpeg", quality=70, scale=0.5): png|jpeg|webp, downscale factor
+- record_screen_start(region?, format="mp4"|"webm", fps=15, max_seconds=600, name?): Start a video (needs ffmpeg)
+- record_screen_stop(): Finish the video; returns path and duration
 - get_screens(): List displays
 - screen_size() / position()
 
@@ -673,8 +1163,24 @@ WINDOWS:
 - list_windows(): All windows with bounds
 - focus_window(title): Activate window
 
+SETTINGS:
+- set_pause(value): Seconds to wait after each mouse/keyboard action (default 0.1)
+- set_failsafe(value): 1 = abort when mouse is in a screen corner, 0 = off
+- sleep(value): Wait for value seconds
+
+REGIONS:
+- define_region(name, x, y, width, height): Name a screen area, e.g. "sidebar"
+- list_regions(): Defined regions with bounds
+- click(region_name, x?, y?): Click region center, or x/y offset within it
+- screenshot(region_name): Capture a named region
+
+ACCESSIBILITY (AXUIElement / UIAutomation / AT-SPI):
+- ax_list(role?, text?): Interactive elements of the focused window with roles, labels, bounds
+- ax_click(element): Press element by id from ax_list
+- ax_set_value(element, text): Set value of a text field or control
+
 BATCH:
-- batch(actions): Execute multiple actions
+- batch(actions): Execute multiple actions (aborts on failsafe)
 
 INFO:
 - info()
@@ -724,15 +1230,25 @@ Examples:
                         "items": {"type": "integer"},
                         "description": "Region [x,y,w,h]"
                     },
+                    "region_name": {"type": "string", "description": "Named region (see define_region)"},
                     "clear": {"type": "boolean", "description": "Clear before write", "default": false},
                     "title": {"type": "string", "description": "Window title"},
-                    "name": {"type": "string", "description": "Screenshot filename"},
+                    "name": {"type": "string", "description": "Screenshot or recording filename, or region name"},
+                    "width": {"type": "integer", "description": "Region width"},
+                    "height": {"type": "integer", "description": "Region height"},
                     "value": {"type": "number", "description": "Value for settings"},
                     "actions": {
                         "type": "array",
                         "items": {"type": "object"},
                         "description": "Batch actions"
-                    }
+                    },
+                    "format": {"type": "string", "enum": ["png", "jpeg", "webp", "mp4", "webm"], "description": "Screenshot encoding (png, jpeg, webp) or recording container (mp4, webm)"},
+                    "fps": {"type": "integer", "description": "Recording frame rate", "default": 15},
+                    "max_seconds": {"type": "integer", "description": "Recording stops on its own after this long", "default": 600},
+                    "quality": {"type": "integer", "description": "JPEG quality 1-100", "default": 80},
+                    "scale": {"type": "number", "description": "Screenshot downscale factor (0, 1]", "default": 1.0},
+                    "element": {"type": "integer", "description": "Accessibility element id from ax_list"},
+                    "role": {"type": "string", "description": "Filter ax_list by role"}
                 }
             }),
         }
@@ -758,6 +1274,132 @@ mod tests {
         assert!(output.contains("mouse"));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_helper_timeout_kills_child() {
+        let mut cmd = std::process::Command::new("sh");
+        cmd.arg("-c").arg("echo started; sleep 10");
+        let started = std::time::Instant::now();
+        let err = output_with_timeout(&mut cmd, std::time::Duration::from_millis(200)).unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        let mut cmd = std::process::Command::new("sh");
+        cmd.arg("-c").arg("echo done");
+        let output = output_with_timeout(&mut cmd, HELPER_TIMEOUT).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "done");
+    }
+
+    #[test]
+    fn test_failsafe_corners() {
+        assert!(in_failsafe_corner(0, 0, 1920, 1080));
+        assert!(in_failsafe_corner(1919, 0, 1920, 1080));
+        assert!(in_failsafe_corner(0, 1079, 1920, 1080));
+        assert!(in_failsafe_corner(1919, 1079, 1920, 1080));
+        assert!(!in_failsafe_corner(0, 500, 1920, 1080));
+        assert!(!in_failsafe_corner(960, 540, 1920, 1080));
+    }
+
+    #[tokio::test]
+    async fn test_define_and_list_regions() {
+        let mut tool = ComputerTool::new();
+        let args = ComputerToolArgs {
+            action: "define_region".to_string(),
+            name: Some("sidebar".to_string()),
+            x: Some(0),
+            y: Some(40),
+            width: Some(240),
+            height: Some(800),
+            ..Default::default()
+        };
+        assert!(tool.execute(args).await.is_ok());
+
+        let args = ComputerToolArgs {
+            action: "list_regions".to_string(),
+            ..Default::default()
+        };
+        let output: Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+        assert_eq!(output["count"], 1);
+        assert_eq!(output["regions"][0]["name"], "sidebar");
+        assert_eq!(output["regions"][0]["width"], 240);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_region_name() {
+        let mut tool = ComputerTool::new();
+        let args = ComputerToolArgs {
+            action: "screenshot".to_string(),
+            region_name: Some("missing".to_string()),
+            ..Default::default()
+        };
+        let err = tool.execute(args).await.unwrap_err();
+        assert!(err.to_string().contains("Unknown region"));
+    }
+
+    #[tokio::test]
+    async fn test_record_screen_validation() {
+        let mut tool = ComputerTool::new();
+        let stop = ComputerToolArgs { action: "record_screen_stop".to_string(), ..Default::default() };
+        assert!(tool.execute(stop).await.unwrap_err().to_string().contains("No recording"));
+        let start = ComputerToolArgs {
+            action: "record_screen_start".to_string(),
+            format: Some("gif".to_string()),
+            ..Default::default()
+        };
+        assert!(tool.execute(start).await.unwrap_err().to_string().contains("Unknown video format"));
+        assert!(output_path("run", "mp4").ends_with("/run.mp4"));
+        assert_eq!(output_path("/tmp/run.webm", "webm"), "/tmp/run.webm");
+    }
+
+    #[test]
+    fn test_parse_ax_elements() {
+        let output = "3\x1fAXButton\x1fOK\x1f\x1f100\x1f200.5\x1f80\x1f24\n\
+                      7\x1fAXTextField\x1fSearch\x1fhello\x1f10\x1f10\x1f300\x1f22\n\
+                      garbage line\n";
+        let elements = parse_ax_elements(output);
+        assert_eq!(elements.len(), 2);
+        assert_eq!(elements[0].id, 3);
+        assert_eq!(elements[0].label, "OK");
+        assert!(elements[0].value.is_none());
+        assert_eq!(elements[0].y, 200);
+        assert_eq!(elements[1].value.as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn test_encode_image_formats() {
+        let raw = || RawImage {
+            width: 4,
+            height: 2,
+            rgba: vec![200; 4 * 2 * 4],
+        };
+        let opts = |format| CaptureOptions { format, quality: 80, scale: 1.0 };
+
+        let (png, w, h) = encode_image(raw(), opts(CaptureFormat::Png)).unwrap();
+        assert_eq!((w, h), (4, 2));
+        assert!(png.starts_with(b"\x89PNG"));
+
+        let (jpeg, _, _) = encode_image(raw(), opts(CaptureFormat::Jpeg)).unwrap();
+        assert!(jpeg.starts_with(&[0xFF, 0xD8]));
+
+        let (webp, _, _) = encode_image(raw(), opts(CaptureFormat::Webp)).unwrap();
+        assert_eq!(&webp[0..4], b"RIFF");
+
+        let scaled = CaptureOptions { scale: 0.5, ..opts(CaptureFormat::Png) };
+        let (_, w, h) = encode_image(raw(), scaled).unwrap();
+        assert_eq!((w, h), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_set_pause_rejects_negative() {
+        let mut tool = ComputerTool::new();
+        let args = ComputerToolArgs {
+            action: "set_pause".to_string(),
+            value: Some(-1.0),
+            ..Default::default()
+        };
+        assert!(tool.execute(args).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_position_action() {
         let mut tool = ComputerTool::new();
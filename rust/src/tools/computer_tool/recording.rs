@@ -0,0 +1,236 @@
+//! Screen recording through ffmpeg's platform capture devices:
+//! AVFoundation on macOS, X11 on Linux and GDI on Windows.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::{Child, Command};
+
+/// Frames per second unless told otherwise
+pub const DEFAULT_FPS: u32 = 15;
+
+/// Recordings stop on their own after this long unless told otherwise
+pub const DEFAULT_MAX_SECS: u64 = 600;
+
+/// How long ffmpeg gets to finish the file after being asked to stop
+const STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// How long start waits to catch a capture device that fails at once
+const START_CHECK: Duration = Duration::from_millis(700);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VideoFormat {
+    Mp4,
+    Webm,
+}
+
+impl VideoFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Webm => "webm",
+        }
+    }
+
+    fn codec_args(&self) -> &'static [&'static str] {
+        match self {
+            Self::Mp4 => &["-c:v", "libx264", "-preset", "veryfast", "-pix_fmt", "yuv420p", "-movflags", "+faststart"],
+            Self::Webm => &["-c:v", "libvpx-vp9", "-deadline", "realtime", "-cpu-used", "8", "-b:v", "2M", "-pix_fmt", "yuv420p"],
+        }
+    }
+}
+
+impl std::str::FromStr for VideoFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "mp4" | "h264" => Ok(Self::Mp4),
+            "webm" | "vp9" => Ok(Self::Webm),
+            _ => Err(ToolError::invalid(format!("Unknown video format: {} (mp4, webm)", s)).into()),
+        }
+    }
+}
+
+/// What to capture and how
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    pub path: PathBuf,
+    pub format: VideoFormat,
+    /// x, y, width, height
+    pub region: Option<[i32; 4]>,
+    pub fps: u32,
+    pub max_secs: u64,
+}
+
+/// A recording in progress
+pub struct Recording {
+    child: Child,
+    options: RecordOptions,
+    log: PathBuf,
+    started: Instant,
+}
+
+/// The capture device input for this platform; `display` is the X11
+/// display to grab on Linux
+fn input_args(region: Option<[i32; 4]>, fps: u32, display: Option<&str>) -> Result<(Vec<String>, Option<String>)> {
+    let fps = fps.to_string();
+    let mut args: Vec<String> = Vec::new();
+    let mut filter = None;
+    if cfg!(target_os = "macos") {
+        args.extend(["-f", "avfoundation", "-capture_cursor", "1", "-framerate", &fps, "-i", "Capture screen 0:none"].map(String::from));
+        // AVFoundation captures whole screens, so a region is cropped afterwards
+        filter = region.map(|[x, y, w, h]| format!("crop={}:{}:{}:{}", w, h, x, y));
+    } else if cfg!(target_os = "windows") {
+        args.extend(["-f", "gdigrab", "-draw_mouse", "1", "-framerate", &fps].map(String::from));
+        if let Some([x, y, w, h]) = region {
+            args.extend(["-offset_x".into(), x.to_string(), "-offset_y".into(), y.to_string(), "-video_size".into(), format!("{}x{}", w, h)]);
+        }
+        args.extend(["-i".into(), "desktop".into()]);
+    } else {
+        let display = display.ok_or_else(|| ToolError::unsupported(
+            "Screen recording needs an X11 display ($DISPLAY is not set)"
+        ))?.to_string();
+        args.extend(["-f", "x11grab", "-draw_mouse", "1", "-framerate", &fps].map(String::from));
+        let input = match region {
+            Some([x, y, w, h]) => {
+                args.extend(["-video_size".into(), format!("{}x{}", w, h)]);
+                format!("{}+{},{}", display, x, y)
+            }
+            None => display,
+        };
+        args.extend(["-i".into(), input]);
+    }
+    Ok((args, filter))
+}
+
+/// Full ffmpeg command line after the program name
+pub fn ffmpeg_args(options: &RecordOptions, display: Option<&str>) -> Result<Vec<String>> {
+    // yuv420p needs even dimensions
+    let region = options.region.map(|[x, y, w, h]| [x, y, w & !1, h & !1]);
+    if region.is_some_and(|[x, y, w, h]| x < 0 || y < 0 || w <= 0 || h <= 0) {
+        return Err(ToolError::invalid("Recording region must be on screen and at least 2x2").into());
+    }
+    let (input, filter) = input_args(region, options.fps, display)?;
+    let mut args: Vec<String> = ["-hide_banner", "-loglevel", "error", "-nostats", "-y"].map(String::from).to_vec();
+    args.extend(input);
+    if let Some(filter) = filter {
+        args.extend(["-vf".into(), filter]);
+    }
+    args.extend(["-t".into(), options.max_secs.to_string()]);
+    args.extend(options.format.codec_args().iter().map(|a| a.to_string()));
+    args.push(options.path.to_string_lossy().into_owned());
+    Ok(args)
+}
+
+impl Recording {
+    pub async fn start(options: RecordOptions) -> Result<Self> {
+        let ffmpeg = which::which("ffmpeg").map_err(|_| ToolError::unsupported(
+            "Screen recording needs ffmpeg on PATH (brew install ffmpeg, apt install ffmpeg, winget install ffmpeg)"
+        ))?;
+        if let Some(parent) = options.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // ffmpeg's errors go to a file: an undrained pipe would stall it
+        let log = options.path.with_extension("log");
+        let started = Instant::now();
+        let mut child = Command::new(ffmpeg)
+            .args(ffmpeg_args(&options, std::env::var("DISPLAY").ok().as_deref())?)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(std::fs::File::create(&log)?)
+            .kill_on_drop(true)
+            .spawn()?;
+        tokio::time::sleep(START_CHECK).await;
+        if let Some(status) = child.try_wait()? {
+            let message = std::fs::read_to_string(&log).unwrap_or_default();
+            let _ = std::fs::remove_file(&log);
+            return Err(ToolError::external(format!("ffmpeg exited with {}: {}", status, message.trim())).into());
+        }
+        Ok(Self { child, options, log, started })
+    }
+
+    fn elapsed(&self) -> f64 {
+        self.started.elapsed().as_secs_f64().min(self.options.max_secs as f64)
+    }
+
+    pub fn status(&self) -> Value {
+        json!({
+            "path": self.options.path,
+            "format": self.options.format.extension(),
+            "region": self.options.region,
+            "elapsed_secs": (self.elapsed() * 10.0).round() / 10.0,
+            "max_secs": self.options.max_secs
+        })
+    }
+
+    /// Ask ffmpeg to finish the file, killing it if it will not
+    pub async fn stop(mut self) -> Result<Value> {
+        let duration = self.elapsed();
+        // Reaching max_secs, or a capture error, ends ffmpeg before stop
+        let ended_early = self.child.try_wait()?.is_some();
+        if !ended_early {
+            if let Some(mut stdin) = self.child.stdin.take() {
+                let _ = stdin.write_all(b"q").await;
+            }
+        }
+        let status = match tokio::time::timeout(STOP_GRACE, self.child.wait()).await {
+            Ok(status) => Some(status?),
+            Err(_) => {
+                let _ = self.child.kill().await;
+                None
+            }
+        };
+        let message = std::fs::read_to_string(&self.log).unwrap_or_default();
+        let _ = std::fs::remove_file(&self.log);
+        let size = std::fs::metadata(&self.options.path).map(|m| m.len()).unwrap_or(0);
+        if size == 0 {
+            return Err(ToolError::external(format!("Recording produced no video: {}", message.trim())).into());
+        }
+        Ok(json!({
+            "success": true,
+            "path": self.options.path,
+            "format": self.options.format.extension(),
+            "duration_secs": (duration * 10.0).round() / 10.0,
+            "size": size,
+            "fps": self.options.fps,
+            "region": self.options.region,
+            "ended_early": ended_early,
+            // Killed ffmpeg may leave an mp4 without its index
+            "complete": status.is_some()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ffmpeg_args() {
+        let options = RecordOptions {
+            path: PathBuf::from("/tmp/run.webm"),
+            format: VideoFormat::Webm,
+            region: Some([10, 20, 301, 200]),
+            fps: 15,
+            max_secs: 60,
+        };
+        let args = ffmpeg_args(&options, Some(":1")).unwrap();
+        assert!(args.windows(2).any(|w| w == ["-t", "60"]));
+        assert!(args.iter().any(|a| a.contains("300")));
+        assert!(!args.iter().any(|a| a.contains("301")));
+        assert_eq!(args.last().unwrap(), "/tmp/run.webm");
+        assert!(args.contains(&"libvpx-vp9".to_string()));
+        assert!("avi".parse::<VideoFormat>().is_err());
+        if cfg!(target_os = "linux") {
+            assert!(args.contains(&":1+10,20".to_string()));
+            assert!(ffmpeg_args(&options, None).is_err());
+        }
+        let offscreen = RecordOptions { region: Some([-5, 0, 100, 100]), ..options };
+        assert!(ffmpeg_args(&offscreen, Some(":1")).is_err());
+    }
+}
@@ -1,8 +1,10 @@
 /// Windows native control using winapi
 ///
-/// Uses ctypes/winapi for direct Win32 API access
+/// Uses ctypes/winapi for direct Win32 API access.
+/// Screen capture runs in-process via GDI BitBlt.
 
 use anyhow::{anyhow, Result};
+use crate::error::ToolError;
 use std::collections::HashMap;
 use std::thread;
 use std::time::Duration;
@@ -17,9 +19,44 @@ use winapi::um::winuser::{
     MOUSEEVENTF_RIGHTDOWN, MOUSEEVENTF_RIGHTUP, MOUSEEVENTF_MIDDLEDOWN,
     MOUSEEVENTF_MIDDLEUP, MOUSEEVENTF_WHEEL, WHEEL_DELTA,
 };
-use winapi::um::wingdi::{GetPixel, GetDC, ReleaseDC};
+use winapi::um::wingdi::{
+    BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, DeleteDC, DeleteObject, GetDIBits,
+    GetPixel, GetDC, ReleaseDC, SelectObject, BITMAPINFO, BITMAPINFOHEADER, BI_RGB,
+    CAPTUREBLT, DIB_RGB_COLORS, SRCCOPY,
+};
 
-use super::{NativeControl, PlatformInfo, WindowInfo};
+use super::{parse_ax_elements, AxElement, NativeControl, PlatformInfo, RawImage, WindowInfo};
+
+/// PowerShell prelude that loads UIAutomation and collects every descendant
+/// of the foreground window into `$els`. Element ids index into `$els`.
+const AX_PRELUDE: &str = r#"
+Add-Type -AssemblyName UIAutomationClient
+Add-Type -AssemblyName UIAutomationTypes
+$root = [System.Windows.Automation.AutomationElement]::FromHandle([IntPtr]{hwnd})
+$els = $root.FindAll([System.Windows.Automation.TreeScope]::Descendants, [System.Windows.Automation.Condition]::TrueCondition)
+"#;
+
+/// Run a UIAutomation script against the foreground window
+fn run_uia(body: &str) -> Result<String> {
+    let hwnd = unsafe { GetForegroundWindow() };
+    if hwnd.is_null() {
+        return Err(anyhow!("No active window"));
+    }
+    let script = format!("{}{}", AX_PRELUDE.replace("{hwnd}", &(hwnd as isize).to_string()), body);
+
+    let output = std::process::Command::new("powershell")
+        .arg("-NoProfile")
+        .arg("-Command")
+        .arg(&script)
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(ToolError::external(format!("UIAutomation error: {}", stderr.trim())).into())
+    }
+}
 
 // Virtual key codes
 fn get_vk_code(key: &str) -> Option<u8> {
@@ -172,7 +209,7 @@ impl NativeControl for WindowsControl {
             }
             Ok(())
         } else {
-            Err(anyhow!("Unknown key: {}", key))
+            Err(ToolError::invalid(format!("Unknown key: {}", key)).into())
         }
     }
 
@@ -183,7 +220,7 @@ impl NativeControl for WindowsControl {
             }
             Ok(())
         } else {
-            Err(anyhow!("Unknown key: {}", key))
+            Err(ToolError::invalid(format!("Unknown key: {}", key)).into())
         }
     }
 
@@ -221,6 +258,56 @@ impl NativeControl for WindowsControl {
         Ok(())
     }
 
+    fn capture(&self, region: Option<&[i32]>) -> Result<RawImage> {
+        let (x, y, w, h) = match region {
+            Some(r) if r.len() == 4 => (r[0], r[1], r[2], r[3]),
+            Some(_) => return Err(ToolError::invalid("Invalid region").into()),
+            None => unsafe { (0, 0, GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) },
+        };
+        if w <= 0 || h <= 0 {
+            return Err(ToolError::invalid("Invalid region").into());
+        }
+
+        unsafe {
+            // GDI blit into a memory DC, then read back top-down 32-bit BGRA rows
+            let screen_dc = GetDC(std::ptr::null_mut());
+            let mem_dc = CreateCompatibleDC(screen_dc);
+            let bitmap = CreateCompatibleBitmap(screen_dc, w, h);
+            let old = SelectObject(mem_dc, bitmap as _);
+            let blitted = BitBlt(mem_dc, 0, 0, w, h, screen_dc, x, y, SRCCOPY | CAPTUREBLT);
+            SelectObject(mem_dc, old);
+
+            let mut info: BITMAPINFO = std::mem::zeroed();
+            info.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+            info.bmiHeader.biWidth = w;
+            info.bmiHeader.biHeight = -h;
+            info.bmiHeader.biPlanes = 1;
+            info.bmiHeader.biBitCount = 32;
+            info.bmiHeader.biCompression = BI_RGB;
+
+            let mut rgba = vec![0u8; (w * h * 4) as usize];
+            let lines = GetDIBits(
+                mem_dc, bitmap, 0, h as UINT,
+                rgba.as_mut_ptr() as *mut _, &mut info, DIB_RGB_COLORS,
+            );
+
+            DeleteObject(bitmap as _);
+            DeleteDC(mem_dc);
+            ReleaseDC(std::ptr::null_mut(), screen_dc);
+
+            if blitted == 0 || lines == 0 {
+                return Err(anyhow!("GDI screen capture failed"));
+            }
+
+            for px in rgba.chunks_exact_mut(4) {
+                px.swap(0, 2);
+                px[3] = 255;
+            }
+
+            Ok(RawImage { width: w as u32, height: h as u32, rgba })
+        }
+    }
+
     fn screenshot(&self, region: Option<&[i32]>) -> Result<Vec<u8>> {
         // Use PowerShell for screenshot on Windows
         let tmp_path = format!("{}\\hanzo_screenshot_{}.png",
@@ -242,7 +329,7 @@ impl NativeControl for WindowsControl {
                     r[0], r[1], r[2], r[3], tmp_path
                 )
             } else {
-                return Err(anyhow!("Invalid region"));
+                return Err(ToolError::invalid("Invalid region").into());
             }
         } else {
             format!(
@@ -467,4 +554,52 @@ impl NativeControl for WindowsControl {
 
         Ok(false)
     }
+
+    fn ax_elements(&self) -> Result<Vec<AxElement>> {
+        let body = r#"
+$interactive = @('button','check box','radio button','edit','combo box','hyperlink','menu item','slider','spinner','tab item','list item','tree item','split button','document')
+$d = [char]31
+for ($i = 0; $i -lt $els.Count; $i++) {
+    $c = $els[$i].Current
+    $role = $c.ControlType.LocalizedControlType
+    if ($interactive -notcontains $role) { continue }
+    $val = ''
+    $vp = $null
+    if ($els[$i].TryGetCurrentPattern([System.Windows.Automation.ValuePattern]::Pattern, [ref]$vp)) { $val = $vp.Current.Value }
+    $r = $c.BoundingRectangle
+    $name = ($c.Name -replace "[`r`n$d]", ' ')
+    $val = ($val -replace "[`r`n$d]", ' ')
+    Write-Output ("$i$d$role$d$name$d$val$d" + [int]$r.X + $d + [int]$r.Y + $d + [int]$r.Width + $d + [int]$r.Height)
+}
+"#;
+        let output = run_uia(body)?;
+        Ok(parse_ax_elements(&output))
+    }
+
+    fn ax_press(&self, id: usize) -> Result<bool> {
+        let body = format!(
+            r#"
+$el = $els[{}]
+$p = $null
+if ($el.TryGetCurrentPattern([System.Windows.Automation.InvokePattern]::Pattern, [ref]$p)) {{ $p.Invoke(); 'ok' }}
+elseif ($el.TryGetCurrentPattern([System.Windows.Automation.TogglePattern]::Pattern, [ref]$p)) {{ $p.Toggle(); 'ok' }}
+elseif ($el.TryGetCurrentPattern([System.Windows.Automation.SelectionItemPattern]::Pattern, [ref]$p)) {{ $p.Select(); 'ok' }}
+"#,
+            id
+        );
+        Ok(run_uia(&body)?.trim() == "ok")
+    }
+
+    fn ax_set_value(&self, id: usize, value: &str) -> Result<bool> {
+        let body = format!(
+            r#"
+$el = $els[{}]
+$p = $null
+if ($el.TryGetCurrentPattern([System.Windows.Automation.ValuePattern]::Pattern, [ref]$p)) {{ $p.SetValue('{}'); 'ok' }}
+"#,
+            id,
+            value.replace('\'', "''")
+        );
+        Ok(run_uia(&body)?.trim() == "ok")
+    }
 }
@@ -0,0 +1,503 @@
+/// Diagnostics tool: linters and compilers as structured findings
+///
+/// Actions: run, linters, help
+///
+/// Runs cargo check (or clippy), eslint, ruff and tsc in a project and
+/// parses their machine-readable output into one list of
+/// {file, line, column, severity, code, message, source}. Linters are
+/// detected from the project's manifests unless named explicitly, and each
+/// one's command can be replaced in config as long as it keeps the output
+/// format.
+
+use anyhow::Result;
+use crate::config::CodeConfig;
+use crate::error::ToolError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::process::Command;
+use which::which;
+
+/// Default cap on diagnostics returned
+const MAX_RESULTS: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticsAction {
+    #[default]
+    Run,
+    Linters,
+    Help,
+}
+
+impl std::str::FromStr for DiagnosticsAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "run" | "check" | "lint" | "" => Ok(Self::Run),
+            "linters" | "detect" => Ok(Self::Linters),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+/// Linters whose output can be parsed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Linter {
+    Cargo,
+    Clippy,
+    Eslint,
+    Ruff,
+    Tsc,
+}
+
+impl std::str::FromStr for Linter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cargo" | "cargo_check" | "rustc" => Ok(Self::Cargo),
+            "clippy" | "cargo_clippy" => Ok(Self::Clippy),
+            "eslint" => Ok(Self::Eslint),
+            "ruff" => Ok(Self::Ruff),
+            "tsc" | "typescript" => Ok(Self::Tsc),
+            _ => Err(ToolError::invalid(format!("Unknown linter: {} (use cargo, clippy, eslint, ruff or tsc)", s)).into()),
+        }
+    }
+}
+
+impl Linter {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Clippy => "clippy",
+            Self::Eslint => "eslint",
+            Self::Ruff => "ruff",
+            Self::Tsc => "tsc",
+        }
+    }
+
+    fn default_command(self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo => &["cargo", "check", "--all-targets", "--message-format=json"],
+            Self::Clippy => &["cargo", "clippy", "--all-targets", "--message-format=json"],
+            Self::Eslint => &["npx", "--no-install", "eslint", "--format", "json", "."],
+            Self::Ruff => &["ruff", "check", "--output-format", "json", "--exit-zero", "."],
+            Self::Tsc => &["npx", "--no-install", "tsc", "--noEmit", "--pretty", "false"],
+        }
+    }
+
+    /// Linters that apply to the project at `root`; clippy is opt-in as
+    /// it repeats cargo check
+    pub fn detect(root: &Path) -> Vec<Self> {
+        let has = |name: &str| root.join(name).exists();
+        let has_prefix = |prefix: &str| {
+            std::fs::read_dir(root).into_iter().flatten().flatten()
+                .any(|e| e.file_name().to_string_lossy().starts_with(prefix))
+        };
+        let mut linters = Vec::new();
+        if has("Cargo.toml") {
+            linters.push(Self::Cargo);
+        }
+        if has("package.json") && (has_prefix("eslint.config.") || has_prefix(".eslintrc")) {
+            linters.push(Self::Eslint);
+        }
+        if has("tsconfig.json") {
+            linters.push(Self::Tsc);
+        }
+        if has("pyproject.toml") || has("ruff.toml") || has(".ruff.toml") || has("setup.py") {
+            linters.push(Self::Ruff);
+        }
+        linters
+    }
+
+    /// Diagnostics in the linter's output
+    pub fn parse(self, stdout: &str, root: &Path) -> Vec<Diagnostic> {
+        match self {
+            Self::Cargo | Self::Clippy => parse_cargo(stdout, self.name(), root),
+            Self::Eslint => parse_eslint(stdout, root),
+            Self::Ruff => parse_ruff(stdout, root),
+            Self::Tsc => parse_tsc(stdout, root),
+        }
+    }
+}
+
+/// One finding, normalised across linters
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Diagnostic {
+    /// Path relative to the project root when inside it
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    /// error, warning or info
+    pub severity: &'static str,
+    pub code: Option<String>,
+    pub message: String,
+    pub source: &'static str,
+}
+
+fn relative(file: &str, root: &Path) -> String {
+    let path = Path::new(file);
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().into_owned()
+}
+
+/// `cargo --message-format=json`: one JSON object per line
+fn parse_cargo(stdout: &str, source: &'static str, root: &Path) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for line in stdout.lines() {
+        let Ok(record) = serde_json::from_str::<Value>(line) else { continue };
+        if record["reason"] != "compiler-message" {
+            continue;
+        }
+        let message = &record["message"];
+        let severity = match message["level"].as_str() {
+            Some("error" | "error: internal compiler error") => "error",
+            Some("warning") => "warning",
+            Some("note" | "help") => "info",
+            _ => continue,
+        };
+        // Summaries such as "aborting due to 2 previous errors" have no span
+        let Some(span) = message["spans"].as_array()
+            .and_then(|spans| spans.iter().find(|s| s["is_primary"] == true))
+        else { continue };
+        diagnostics.push(Diagnostic {
+            file: relative(span["file_name"].as_str().unwrap_or(""), root),
+            line: span["line_start"].as_u64().unwrap_or(0) as usize,
+            column: span["column_start"].as_u64().unwrap_or(0) as usize,
+            severity,
+            code: message["code"]["code"].as_str().map(String::from),
+            message: message["message"].as_str().unwrap_or("").to_string(),
+            source,
+        });
+    }
+    diagnostics
+}
+
+/// `eslint --format json`: files with their messages
+fn parse_eslint(stdout: &str, root: &Path) -> Vec<Diagnostic> {
+    let Ok(files) = serde_json::from_str::<Vec<Value>>(stdout.trim()) else { return Vec::new() };
+    files.iter().flat_map(|file| {
+        let path = relative(file["filePath"].as_str().unwrap_or(""), root);
+        file["messages"].as_array().into_iter().flatten().map(move |m| Diagnostic {
+            file: path.clone(),
+            line: m["line"].as_u64().unwrap_or(0) as usize,
+            column: m["column"].as_u64().unwrap_or(0) as usize,
+            severity: if m["severity"] == 2 || m["fatal"] == true { "error" } else { "warning" },
+            code: m["ruleId"].as_str().map(String::from),
+            message: m["message"].as_str().unwrap_or("").to_string(),
+            source: "eslint",
+        })
+    }).collect()
+}
+
+/// `ruff check --output-format json`: rule violations, syntax errors
+/// without a code
+fn parse_ruff(stdout: &str, root: &Path) -> Vec<Diagnostic> {
+    let Ok(items) = serde_json::from_str::<Vec<Value>>(stdout.trim()) else { return Vec::new() };
+    items.iter().map(|item| Diagnostic {
+        file: relative(item["filename"].as_str().unwrap_or(""), root),
+        line: item["location"]["row"].as_u64().unwrap_or(0) as usize,
+        column: item["location"]["column"].as_u64().unwrap_or(0) as usize,
+        severity: if item["code"].is_string() { "warning" } else { "error" },
+        code: item["code"].as_str().map(String::from),
+        message: item["message"].as_str().unwrap_or("").to_string(),
+        source: "ruff",
+    }).collect()
+}
+
+/// `tsc --pretty false`: `file(line,col): error TS2322: message`
+fn parse_tsc(stdout: &str, root: &Path) -> Vec<Diagnostic> {
+    static LINE: Lazy<Regex> = Lazy::new(|| {
+        Regex::new(r"^(.+?)\((\d+),(\d+)\): (error|warning|message) (TS\d+): (.*)$").unwrap()
+    });
+    stdout.lines().filter_map(|line| {
+        let caps = LINE.captures(line)?;
+        Some(Diagnostic {
+            file: relative(&caps[1], root),
+            line: caps[2].parse().unwrap_or(0),
+            column: caps[3].parse().unwrap_or(0),
+            severity: match &caps[4] {
+                "error" => "error",
+                "warning" => "warning",
+                _ => "info",
+            },
+            code: Some(caps[5].to_string()),
+            message: caps[6].to_string(),
+            source: "tsc",
+        })
+    }).collect()
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "error" => 0,
+        "warning" => 1,
+        _ => 2,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiagnosticsToolArgs {
+    pub action: Option<String>,
+    /// Project root
+    pub path: Option<String>,
+    /// Linters to run instead of the detected ones
+    pub linters: Option<Vec<String>>,
+    /// Only report diagnostics in these files (relative to path)
+    pub files: Option<Vec<String>>,
+    /// Lowest severity reported: error, warning (default) or info
+    pub severity: Option<String>,
+    pub max_results: Option<usize>,
+}
+
+pub struct DiagnosticsToolDefinition;
+
+impl DiagnosticsToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "diagnostics",
+            "description": "Run linters and compilers (cargo check, clippy, eslint, ruff, tsc) and return their findings as one structured list of {file, line, column, severity, code, message, source}: run, linters, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["run", "linters", "help"],
+                        "description": "Diagnostics action",
+                        "default": "run"
+                    },
+                    "path": { "type": "string", "description": "Project root", "default": "." },
+                    "linters": {
+                        "type": "array",
+                        "items": { "type": "string", "enum": ["cargo", "clippy", "eslint", "ruff", "tsc"] },
+                        "description": "Linters to run; detected from the project's manifests when omitted"
+                    },
+                    "files": { "type": "array", "items": { "type": "string" }, "description": "Only report diagnostics in these files, relative to path" },
+                    "severity": { "type": "string", "enum": ["error", "warning", "info"], "description": "Lowest severity to report", "default": "warning" },
+                    "max_results": { "type": "number", "default": MAX_RESULTS }
+                }
+            }
+        })
+    }
+}
+
+pub struct DiagnosticsTool {
+    config: CodeConfig,
+}
+
+impl Default for DiagnosticsTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiagnosticsTool {
+    pub fn new() -> Self {
+        Self::with_config(CodeConfig::default())
+    }
+
+    pub fn with_config(config: CodeConfig) -> Self {
+        Self { config }
+    }
+
+    pub async fn execute(&self, args: DiagnosticsToolArgs) -> Result<Value> {
+        let action: DiagnosticsAction = args.action.as_deref().unwrap_or("run").parse()?;
+
+        match action {
+            DiagnosticsAction::Run => self.run(&args).await,
+            DiagnosticsAction::Linters => self.linters(&args),
+            DiagnosticsAction::Help => Ok(self.help()),
+        }
+    }
+
+    fn root(args: &DiagnosticsToolArgs) -> Result<PathBuf> {
+        let root = shellexpand::tilde(args.path.as_deref().unwrap_or(".")).to_string();
+        std::fs::canonicalize(&root)
+            .map_err(|e| ToolError::not_found(format!("{}: {}", root, e)).into())
+    }
+
+    fn selected(args: &DiagnosticsToolArgs, root: &Path) -> Result<Vec<Linter>> {
+        match &args.linters {
+            Some(names) => names.iter().map(|n| n.parse()).collect(),
+            None => Ok(Linter::detect(root)),
+        }
+    }
+
+    fn command(&self, linter: Linter) -> Vec<String> {
+        self.config.linters.get(linter.name())
+            .filter(|command| !command.is_empty())
+            .cloned()
+            .unwrap_or_else(|| linter.default_command().iter().map(|s| s.to_string()).collect())
+    }
+
+    fn linters(&self, args: &DiagnosticsToolArgs) -> Result<Value> {
+        let root = Self::root(args)?;
+        let linters: Vec<Value> = Self::selected(args, &root)?.into_iter().map(|linter| {
+            let command = self.command(linter);
+            json!({
+                "name": linter.name(),
+                "command": command.join(" "),
+                "installed": which(&command[0]).is_ok()
+            })
+        }).collect();
+
+        Ok(json!({
+            "ok": true,
+            "data": { "root": root.to_string_lossy(), "linters": linters },
+            "error": null,
+            "meta": { "tool": "diagnostics", "action": "linters" }
+        }))
+    }
+
+    async fn run(&self, args: &DiagnosticsToolArgs) -> Result<Value> {
+        let root = Self::root(args)?;
+        let linters = Self::selected(args, &root)?;
+        if linters.is_empty() {
+            return Err(ToolError::unsupported(format!(
+                "No linters detected in {}; name them with linters", root.display()
+            )).into());
+        }
+        let lowest = severity_rank(args.severity.as_deref().unwrap_or("warning"));
+        let files: Option<HashSet<String>> = args.files.as_ref().map(|files| {
+            files.iter().map(|f| relative(f.trim_start_matches("./"), &root)).collect()
+        });
+
+        let mut diagnostics = Vec::new();
+        let mut runs = Vec::new();
+        let mut skipped = Vec::new();
+        for linter in linters {
+            let command = self.command(linter);
+            if which(&command[0]).is_err() {
+                skipped.push(json!({ "linter": linter.name(), "reason": format!("{} is not installed", command[0]) }));
+                continue;
+            }
+            let started = Instant::now();
+            let output = Command::new(&command[0])
+                .args(&command[1..])
+                .current_dir(&root)
+                .kill_on_drop(true)
+                .output()
+                .await
+                .map_err(|e| ToolError::external(format!("Could not run {}: {}", command[0], e)))?;
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let found = linter.parse(&stdout, &root);
+
+            let mut run = json!({
+                "linter": linter.name(),
+                "command": command.join(" "),
+                "exit_code": output.status.code(),
+                "count": found.len(),
+                "duration_ms": started.elapsed().as_millis() as u64
+            });
+            // A failing run with nothing parsed didn't get as far as linting
+            if !output.status.success() && found.is_empty() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let tail: Vec<&str> = stderr.lines().rev().take(20).collect();
+                run["failed"] = json!(true);
+                run["stderr"] = json!(tail.into_iter().rev().collect::<Vec<_>>().join("\n"));
+            }
+            runs.push(run);
+            diagnostics.extend(found);
+        }
+
+        let mut seen = HashSet::new();
+        diagnostics.retain(|d| {
+            severity_rank(d.severity) <= lowest
+                && files.as_ref().is_none_or(|files| files.contains(&d.file))
+                // cargo reports a lib's warnings again for each target
+                && seen.insert((d.file.clone(), d.line, d.column, d.code.clone(), d.message.clone()))
+        });
+        diagnostics.sort_by(|a, b| {
+            severity_rank(a.severity).cmp(&severity_rank(b.severity))
+                .then_with(|| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)))
+        });
+        let errors = diagnostics.iter().filter(|d| d.severity == "error").count();
+        let warnings = diagnostics.iter().filter(|d| d.severity == "warning").count();
+        let total = diagnostics.len();
+        let limit = args.max_results.unwrap_or(MAX_RESULTS);
+        diagnostics.truncate(limit);
+
+        Ok(json!({
+            "ok": true,
+            "data": {
+                "root": root.to_string_lossy(),
+                "diagnostics": diagnostics,
+                "count": total,
+                "errors": errors,
+                "warnings": warnings,
+                "truncated": total > limit,
+                "runs": runs,
+                "skipped": skipped
+            },
+            "error": null,
+            "meta": { "tool": "diagnostics", "action": "run" }
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "diagnostics",
+                "actions": {
+                    "run": "Run the project's linters (or those in linters) and return structured diagnostics, errors first",
+                    "linters": "List the linters that run would use, their commands and whether they are installed",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "diagnostics", "action": "help" }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_outputs() {
+        let root = Path::new("/work/app");
+        let cargo = concat!(
+            r#"{"reason":"compiler-artifact","package_id":"app"}"#, "\n",
+            r#"{"reason":"compiler-message","message":{"message":"mismatched types","level":"error","code":{"code":"E0308"},"spans":[{"file_name":"src/main.rs","line_start":4,"column_start":9,"is_primary":true}]}}"#, "\n",
+            r#"{"reason":"compiler-message","message":{"message":"aborting due to 1 previous error","level":"error","code":null,"spans":[]}}"#, "\n",
+        );
+        let found = Linter::Cargo.parse(cargo, root);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].file.as_str(), found[0].line, found[0].severity), ("src/main.rs", 4, "error"));
+        assert_eq!(found[0].code.as_deref(), Some("E0308"));
+
+        let eslint = r#"[{"filePath":"/work/app/src/a.js","messages":[{"ruleId":"no-unused-vars","severity":1,"message":"'x' is unused","line":2,"column":7}]}]"#;
+        let found = Linter::Eslint.parse(eslint, root);
+        assert_eq!((found[0].file.as_str(), found[0].severity), ("src/a.js", "warning"));
+
+        let ruff = r#"[{"code":"F401","filename":"/work/app/m.py","location":{"row":1,"column":8},"message":"`os` imported but unused"},{"code":null,"filename":"/work/app/m.py","location":{"row":3,"column":1},"message":"SyntaxError: invalid syntax"}]"#;
+        let found = Linter::Ruff.parse(ruff, root);
+        assert_eq!(found[0].code.as_deref(), Some("F401"));
+        assert_eq!(found[1].severity, "error");
+
+        let tsc = "src/index.ts(3,5): error TS2322: Type 'string' is not assignable to type 'number'.\nFound 1 error.\n";
+        let found = Linter::Tsc.parse(tsc, root);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].line, found[0].column, found[0].code.as_deref()), (3, 5, Some("TS2322")));
+    }
+
+    #[test]
+    fn test_detect() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.path().join("package.json"), "{}").unwrap();
+        std::fs::write(dir.path().join("tsconfig.json"), "{}").unwrap();
+        assert_eq!(Linter::detect(dir.path()), [Linter::Cargo, Linter::Tsc]);
+        std::fs::write(dir.path().join("eslint.config.js"), "").unwrap();
+        assert_eq!(Linter::detect(dir.path()), [Linter::Cargo, Linter::Eslint, Linter::Tsc]);
+        assert!("pylint".parse::<Linter>().is_err());
+    }
+}
@@ -0,0 +1,149 @@
+/// Unified diffs between two versions of a file
+///
+/// Lines are matched by longest common subsequence after trimming the
+/// common prefix and suffix; changes too large to compare line by line
+/// are shown as a whole-block replacement. Hunks carry three lines of
+/// context, as `diff -u` and git do.
+
+use std::iter::repeat_n;
+
+/// Context lines around each change
+const CONTEXT: usize = 3;
+
+/// Largest LCS table (old lines x new lines) computed before falling back
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Keep,
+    Delete,
+    Insert,
+}
+
+fn ops(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+
+    let mut out = vec![Op::Keep; prefix];
+    if old.len() * new.len() > MAX_CELLS {
+        out.extend(repeat_n(Op::Delete, old.len()));
+        out.extend(repeat_n(Op::Insert, new.len()));
+    } else {
+        // lcs[i * width + j] is the LCS length of old[i..] and new[j..]
+        let width = new.len() + 1;
+        let mut lcs = vec![0u32; (old.len() + 1) * width];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i * width + j] = if old[i] == new[j] {
+                    lcs[(i + 1) * width + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * width + j].max(lcs[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() && j < new.len() {
+            if old[i] == new[j] {
+                out.push(Op::Keep);
+                i += 1;
+                j += 1;
+            } else if lcs[(i + 1) * width + j] >= lcs[i * width + j + 1] {
+                out.push(Op::Delete);
+                i += 1;
+            } else {
+                out.push(Op::Insert);
+                j += 1;
+            }
+        }
+        out.extend(repeat_n(Op::Delete, old.len() - i));
+        out.extend(repeat_n(Op::Insert, new.len() - j));
+    }
+    out.extend(repeat_n(Op::Keep, suffix));
+    out
+}
+
+/// Unified diff of `path` from `old` to `new`; `None` stands for a file
+/// that does not exist. Empty when nothing changed.
+pub fn unified(path: &str, old: Option<&str>, new: Option<&str>) -> String {
+    let old_lines: Vec<&str> = old.map(|s| s.lines().collect()).unwrap_or_default();
+    let new_lines: Vec<&str> = new.map(|s| s.lines().collect()).unwrap_or_default();
+    let ops = ops(&old_lines, &new_lines);
+    let changes: Vec<usize> = ops.iter().enumerate().filter(|(_, op)| **op != Op::Keep).map(|(i, _)| i).collect();
+    if changes.is_empty() && old.is_some() == new.is_some() {
+        return String::new();
+    }
+
+    // Old and new line numbers before each op
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut o, mut n) = (0, 0);
+    for op in &ops {
+        positions.push((o, n));
+        match op {
+            Op::Keep => { o += 1; n += 1; }
+            Op::Delete => o += 1,
+            Op::Insert => n += 1,
+        }
+    }
+    positions.push((o, n));
+
+    let mut out = format!(
+        "--- {}\n+++ {}\n",
+        if old.is_some() { path } else { "/dev/null" },
+        if new.is_some() { path } else { "/dev/null" }
+    );
+    let mut k = 0;
+    while k < changes.len() {
+        // Extend the hunk while the next change is within shared context
+        let mut last = k;
+        while last + 1 < changes.len() && changes[last + 1] - changes[last] <= 2 * CONTEXT + 1 {
+            last += 1;
+        }
+        let start = changes[k].saturating_sub(CONTEXT);
+        let end = (changes[last] + 1 + CONTEXT).min(ops.len());
+        let (old_start, new_start) = positions[start];
+        let old_count = positions[end].0 - old_start;
+        let new_count = positions[end].1 - new_start;
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            if old_count == 0 { old_start } else { old_start + 1 }, old_count,
+            if new_count == 0 { new_start } else { new_start + 1 }, new_count
+        ));
+        for i in start..end {
+            let (o, n) = positions[i];
+            match ops[i] {
+                Op::Keep => out.push_str(&format!(" {}\n", old_lines[o])),
+                Op::Delete => out.push_str(&format!("-{}\n", old_lines[o])),
+                Op::Insert => out.push_str(&format!("+{}\n", new_lines[n])),
+            }
+        }
+        k = last + 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\nm\n";
+        let diff = unified("f.txt", Some(old), Some(new));
+        assert_eq!(diff, "--- f.txt\n+++ f.txt\n\
+            @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+            @@ -10,3 +10,4 @@\n j\n k\n l\n+m\n");
+        assert_eq!(unified("f.txt", Some(old), Some(old)), "");
+    }
+
+    #[test]
+    fn test_create_and_delete() {
+        assert_eq!(unified("n.txt", None, Some("x\n")), "--- /dev/null\n+++ n.txt\n@@ -0,0 +1,1 @@\n+x\n");
+        assert_eq!(unified("n.txt", Some("x\n"), None), "--- n.txt\n+++ /dev/null\n@@ -1,1 +0,0 @@\n-x\n");
+    }
+}
@@ -0,0 +1,781 @@
+/// Docker tool
+///
+/// Actions: ps, images, run, stop, logs, exec, build, pull, compose_up,
+/// compose_down, help
+///
+/// Talks to the Engine API through bollard, at DOCKER_HOST or the local
+/// socket, so containers and images come back as the daemon's own fields.
+/// Builds, pulls, compose runs and attached `run`s go through the exec
+/// tool's ProcessManager like project tasks do: the call waits up to
+/// `timeout` seconds, then leaves the work in the background under a
+/// proc_id that exec(action="logs") and exec(action="wait") follow.
+/// Compose has no Engine API, so compose_up and compose_down still run
+/// `docker compose`.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use super::exec_tool::{ProcessInfo, ProcessManager};
+use bollard::container::{
+    AttachContainerOptions, Config, CreateContainerOptions, ListContainersOptions, LogOutput, LogsOptions,
+    StartContainerOptions, WaitContainerOptions,
+};
+use bollard::exec::{CreateExecOptions, StartExecResults};
+use bollard::image::{BuildImageOptions, CreateImageOptions, ListImagesOptions};
+use bollard::models::{ContainerSummary, HostConfig, ImageSummary, PortBinding, PortMap};
+use bollard::Docker;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// Seconds a backgroundable action waits before leaving the work running
+const WAIT_SECS: u64 = 30;
+
+/// Longest a quick docker call may run
+const CALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Log lines returned when the caller gives no tail
+const TAIL_LINES: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DockerAction {
+    #[default]
+    Ps,
+    Images,
+    Run,
+    Stop,
+    Logs,
+    Exec,
+    Build,
+    Pull,
+    ComposeUp,
+    ComposeDown,
+    Help,
+}
+
+impl std::str::FromStr for DockerAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "ps" | "containers" | "list" | "" => Ok(Self::Ps),
+            "images" | "image_list" => Ok(Self::Images),
+            "run" | "start" => Ok(Self::Run),
+            "stop" => Ok(Self::Stop),
+            "logs" | "log" => Ok(Self::Logs),
+            "exec" => Ok(Self::Exec),
+            "build" => Ok(Self::Build),
+            "pull" => Ok(Self::Pull),
+            "compose_up" | "up" => Ok(Self::ComposeUp),
+            "compose_down" | "down" => Ok(Self::ComposeDown),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl DockerAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Ps => "ps",
+            Self::Images => "images",
+            Self::Run => "run",
+            Self::Stop => "stop",
+            Self::Logs => "logs",
+            Self::Exec => "exec",
+            Self::Build => "build",
+            Self::Pull => "pull",
+            Self::ComposeUp => "compose_up",
+            Self::ComposeDown => "compose_down",
+            Self::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DockerToolArgs {
+    pub action: Option<String>,
+    /// Container name or id; for run, the name to give it
+    pub name: Option<String>,
+    /// Image to run or pull
+    pub image: Option<String>,
+    /// For build: tag of the built image
+    pub tag: Option<String>,
+    /// For run and exec: command and arguments
+    pub command: Option<Vec<String>>,
+    /// For ps: stopped containers too
+    #[serde(default)]
+    pub all: bool,
+    /// For run: port mappings such as 8080:80
+    pub ports: Option<Vec<String>>,
+    /// For run and exec: environment variables
+    pub env: Option<BTreeMap<String, String>>,
+    /// For run: volume mounts such as ./data:/data
+    pub volumes: Option<Vec<String>>,
+    /// For run: return once started instead of waiting for it to exit (default true)
+    pub detach: Option<bool>,
+    /// For run: remove the container when it exits
+    #[serde(default)]
+    pub remove: bool,
+    /// Build context or compose project directory
+    pub path: Option<String>,
+    /// For build: Dockerfile, relative to path
+    pub dockerfile: Option<String>,
+    pub build_args: Option<BTreeMap<String, String>>,
+    /// For compose: compose file
+    pub file: Option<String>,
+    /// For compose: project name
+    pub project: Option<String>,
+    /// For compose_up: services to start (default all)
+    pub services: Option<Vec<String>>,
+    /// For compose_up: build images before starting
+    #[serde(default)]
+    pub build: bool,
+    /// For compose_down: remove named volumes too
+    #[serde(default)]
+    pub remove_volumes: bool,
+    /// For exec: working directory in the container
+    pub workdir: Option<String>,
+    /// For logs: lines from the end
+    pub tail: Option<usize>,
+    /// For logs: only newer than this, e.g. 10m
+    pub since: Option<String>,
+    /// Seconds to wait before leaving build, pull, compose or attached run in the background
+    pub timeout: Option<u64>,
+}
+
+pub struct DockerToolDefinition;
+
+impl DockerToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "docker",
+            "description": "Docker containers, images and compose: ps, images, run, stop, logs, exec, build, pull, compose_up, compose_down, help. Long builds, pulls and compose runs continue in the background under a proc_id that exec(action=\"logs\") follows",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["ps", "images", "run", "stop", "logs", "exec", "build", "pull", "compose_up", "compose_down", "help"],
+                        "default": "ps"
+                    },
+                    "name": { "type": "string", "description": "Container name or id; for run, the name to give it" },
+                    "image": { "type": "string", "description": "For run and pull: image" },
+                    "tag": { "type": "string", "description": "For build: image tag" },
+                    "command": { "type": "array", "items": { "type": "string" }, "description": "For run and exec: command and arguments" },
+                    "all": { "type": "boolean", "description": "For ps: stopped containers too" },
+                    "ports": { "type": "array", "items": { "type": "string" }, "description": "For run: port mappings, e.g. 8080:80" },
+                    "env": { "type": "object", "additionalProperties": { "type": "string" }, "description": "For run and exec: environment variables" },
+                    "volumes": { "type": "array", "items": { "type": "string" }, "description": "For run: mounts, e.g. ./data:/data" },
+                    "detach": { "type": "boolean", "description": "For run: return once started", "default": true },
+                    "remove": { "type": "boolean", "description": "For run: remove the container when it exits" },
+                    "path": { "type": "string", "description": "Build context or compose project directory", "default": "." },
+                    "dockerfile": { "type": "string", "description": "For build: Dockerfile relative to path" },
+                    "build_args": { "type": "object", "additionalProperties": { "type": "string" }, "description": "For build: --build-arg values" },
+                    "file": { "type": "string", "description": "For compose: compose file" },
+                    "project": { "type": "string", "description": "For compose: project name" },
+                    "services": { "type": "array", "items": { "type": "string" }, "description": "For compose_up: services to start" },
+                    "build": { "type": "boolean", "description": "For compose_up: build images first" },
+                    "remove_volumes": { "type": "boolean", "description": "For compose_down: remove named volumes" },
+                    "workdir": { "type": "string", "description": "For exec: working directory in the container" },
+                    "tail": { "type": "integer", "description": "For logs: lines from the end", "default": TAIL_LINES },
+                    "since": { "type": "string", "description": "For logs: only newer than this, e.g. 10m" },
+                    "timeout": { "type": "integer", "description": "Seconds to wait before leaving long work in the background", "default": WAIT_SECS }
+                }
+            }
+        })
+    }
+}
+
+pub struct DockerTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl DockerTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+
+    pub async fn execute(&self, args: DockerToolArgs) -> Result<Value> {
+        let action: DockerAction = args.action.as_deref().unwrap_or("ps").parse()?;
+        let data = match action {
+            DockerAction::Ps => self.ps(&args).await?,
+            DockerAction::Images => self.images().await?,
+            DockerAction::Run => self.run(&args).await?,
+            DockerAction::Stop => self.stop(&args).await?,
+            DockerAction::Logs => self.logs(&args).await?,
+            DockerAction::Exec => self.exec(&args).await?,
+            DockerAction::Build => self.build(&args).await?,
+            DockerAction::Pull => self.pull(&args).await?,
+            DockerAction::ComposeUp => self.compose(&args, true).await?,
+            DockerAction::ComposeDown => self.compose(&args, false).await?,
+            DockerAction::Help => return Ok(self.help()),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "docker", "action": action.name() }
+        }))
+    }
+
+    async fn ps(&self, args: &DockerToolArgs) -> Result<Value> {
+        let options = ListContainersOptions::<String> { all: args.all, ..Default::default() };
+        let containers: Vec<Value> = quick(client()?.list_containers(Some(options))).await?
+            .iter()
+            .map(container_summary)
+            .collect();
+        Ok(json!({ "containers": containers, "count": containers.len() }))
+    }
+
+    async fn images(&self) -> Result<Value> {
+        let images: Vec<Value> = quick(client()?.list_images(None::<ListImagesOptions<String>>)).await?
+            .iter()
+            .map(image_summary)
+            .collect();
+        Ok(json!({ "images": images, "count": images.len() }))
+    }
+
+    async fn run(&self, args: &DockerToolArgs) -> Result<Value> {
+        let image = args.image.as_deref().ok_or_else(|| ToolError::invalid("image required"))?;
+        let detach = args.detach.unwrap_or(true);
+        let ports = port_bindings(args.ports.as_deref().unwrap_or_default())?;
+        let config = Config {
+            image: Some(image.to_string()),
+            cmd: args.command.clone().filter(|c| !c.is_empty()),
+            env: args.env.as_ref().map(env_list),
+            exposed_ports: Some(ports.keys().map(|port| (port.clone(), HashMap::new())).collect()),
+            host_config: Some(HostConfig {
+                port_bindings: Some(ports),
+                binds: args.volumes.as_deref().map(binds).transpose()?,
+                // An attached run removes the container itself once it has its exit code
+                auto_remove: Some(args.remove && detach),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let options = args.name.clone().map(|name| CreateContainerOptions { name, platform: None });
+
+        let docker = client()?;
+        let created = match docker.create_container(options.clone(), config.clone()).await {
+            Err(bollard::errors::Error::DockerResponseServerError { status_code: 404, .. }) => {
+                pull_image(&docker, image, |_| ()).await?;
+                quick(docker.create_container(options, config)).await?
+            }
+            created => created.map_err(api_error)?,
+        };
+        let id = created.id;
+        if detach {
+            quick(docker.start_container(&id, None::<StartContainerOptions<String>>)).await?;
+            return Ok(json!({ "id": id, "name": args.name, "image": image }));
+        }
+
+        // Attach before starting so no early output is missed
+        let attach = AttachContainerOptions::<String> {
+            stdout: Some(true),
+            stderr: Some(true),
+            stream: Some(true),
+            logs: Some(true),
+            ..Default::default()
+        };
+        let mut output = quick(docker.attach_container(&id, Some(attach))).await?.output;
+        quick(docker.start_container(&id, None::<StartContainerOptions<String>>)).await?;
+        let remove = args.remove;
+        self.task(format!("docker run {}", image), args.timeout, move |lines| async move {
+            while let Some(Ok(chunk)) = output.next().await {
+                for line in chunk.to_string().lines() {
+                    let _ = lines.send(line.to_string());
+                }
+            }
+            let code = exit_code(&docker, &id).await;
+            if remove {
+                let _ = docker.remove_container(&id, None).await;
+            }
+            code
+        }).await
+    }
+
+    async fn stop(&self, args: &DockerToolArgs) -> Result<Value> {
+        let name = args.name.as_deref().ok_or_else(|| ToolError::invalid("name required"))?;
+        quick(client()?.stop_container(name, None)).await?;
+        Ok(json!({ "stopped": name }))
+    }
+
+    async fn logs(&self, args: &DockerToolArgs) -> Result<Value> {
+        let name = args.name.as_deref().ok_or_else(|| ToolError::invalid("name required"))?;
+        let options = LogsOptions {
+            stdout: true,
+            stderr: true,
+            tail: args.tail.unwrap_or(TAIL_LINES).to_string(),
+            since: args.since.as_deref().map(since).transpose()?.unwrap_or_default(),
+            ..Default::default()
+        };
+        let docker = client()?;
+        let (stdout, stderr) = quick(async {
+            let mut stream = docker.logs(name, Some(options));
+            let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+            while let Some(output) = stream.next().await {
+                match output? {
+                    LogOutput::StdErr { message } => stderr.extend(String::from_utf8_lossy(&message).lines().map(str::to_string)),
+                    other => stdout.extend(other.to_string().lines().map(str::to_string)),
+                }
+            }
+            Ok((stdout, stderr))
+        }).await?;
+        Ok(json!({ "stdout": stdout, "stderr": stderr }))
+    }
+
+    async fn exec(&self, args: &DockerToolArgs) -> Result<Value> {
+        let name = args.name.as_deref().ok_or_else(|| ToolError::invalid("name required"))?;
+        let command = args.command.as_ref()
+            .filter(|c| !c.is_empty())
+            .ok_or_else(|| ToolError::invalid("command required"))?;
+        let options = CreateExecOptions {
+            cmd: Some(command.clone()),
+            env: args.env.as_ref().map(env_list),
+            working_dir: args.workdir.clone(),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            ..Default::default()
+        };
+        let docker = client()?;
+        let (stdout, stderr, exit_code) = quick(async {
+            let exec = docker.create_exec(name, options).await?;
+            let (mut stdout, mut stderr) = (String::new(), String::new());
+            if let StartExecResults::Attached { mut output, .. } = docker.start_exec(&exec.id, None).await? {
+                while let Some(chunk) = output.next().await {
+                    match chunk? {
+                        LogOutput::StdErr { message } => stderr.push_str(&String::from_utf8_lossy(&message)),
+                        other => stdout.push_str(&other.to_string()),
+                    }
+                }
+            }
+            Ok((stdout, stderr, docker.inspect_exec(&exec.id).await?.exit_code))
+        }).await?;
+        Ok(json!({ "exit_code": exit_code, "stdout": stdout, "stderr": stderr }))
+    }
+
+    async fn build(&self, args: &DockerToolArgs) -> Result<Value> {
+        let dir = project_dir(args)?;
+        let context = {
+            let dir = dir.clone();
+            tokio::task::spawn_blocking(move || build_context(&dir)).await??
+        };
+        let options = BuildImageOptions {
+            dockerfile: args.dockerfile.clone().unwrap_or_else(|| "Dockerfile".into()),
+            t: args.tag.clone().unwrap_or_default(),
+            buildargs: args.build_args.iter().flatten().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            rm: true,
+            ..Default::default()
+        };
+        let docker = client()?;
+        let command = format!("docker build {}", dir.display());
+        self.task(command, args.timeout, move |lines| async move {
+            let mut stream = docker.build_image(options, None, Some(context.into()));
+            while let Some(info) = stream.next().await {
+                match info {
+                    Ok(info) => {
+                        if let Some(error) = info.error {
+                            let _ = lines.send(error);
+                            return 1;
+                        }
+                        for line in info.stream.iter().chain(&info.status).flat_map(|text| text.lines()) {
+                            let _ = lines.send(line.to_string());
+                        }
+                    }
+                    Err(e) => {
+                        let _ = lines.send(format!("docker error: {}", e));
+                        return 1;
+                    }
+                }
+            }
+            0
+        }).await
+    }
+
+    async fn pull(&self, args: &DockerToolArgs) -> Result<Value> {
+        let image = args.image.clone().ok_or_else(|| ToolError::invalid("image required"))?;
+        let docker = client()?;
+        self.task(format!("docker pull {}", image), args.timeout, move |lines| async move {
+            let progress = |line: String| {
+                let _ = lines.send(line);
+            };
+            match pull_image(&docker, &image, progress).await {
+                Ok(()) => 0,
+                Err(e) => {
+                    let _ = lines.send(e.to_string());
+                    1
+                }
+            }
+        }).await
+    }
+
+    async fn compose(&self, args: &DockerToolArgs, up: bool) -> Result<Value> {
+        let (program, mut argv) = compose_command().await?;
+        if let Some(file) = &args.file {
+            argv.extend(["-f".into(), file.clone()]);
+        }
+        if let Some(project) = &args.project {
+            argv.extend(["-p".into(), project.clone()]);
+        }
+        if up {
+            argv.extend(["up".into(), "-d".into()]);
+            if args.build {
+                argv.push("--build".into());
+            }
+            argv.extend(args.services.iter().flatten().cloned());
+        } else {
+            argv.push("down".into());
+            if args.remove_volumes {
+                argv.push("-v".into());
+            }
+        }
+        self.background(&program, &argv, Some(project_dir(args)?), args.timeout).await
+    }
+
+    /// Run through the ProcessManager, waiting up to `timeout` seconds
+    /// before leaving it in the background
+    async fn background(&self, program: &str, argv: &[String], cwd: Option<PathBuf>, timeout: Option<u64>) -> Result<Value> {
+        let program = which::which(program)
+            .map_err(|_| ToolError::unsupported(format!("{} not found on PATH", program)))?;
+        let mut cmd = Command::new(&program);
+        cmd.args(argv);
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+        let command = format!("{} {}", program.file_name().unwrap_or_default().to_string_lossy(), argv.join(" "));
+        let info = self.manager.spawn_logged(cmd, command).await?;
+        self.follow(info, timeout).await
+    }
+
+    /// Run an Engine API stream as a ProcessManager task, waiting like
+    /// `background`
+    async fn task<F>(&self, command: String, timeout: Option<u64>, work: impl FnOnce(mpsc::UnboundedSender<String>) -> F) -> Result<Value>
+    where
+        F: Future<Output = i32> + Send + 'static,
+    {
+        let info = self.manager.spawn_task(command, work).await?;
+        self.follow(info, timeout).await
+    }
+
+    /// Wait up to `timeout` seconds for `info` to finish, then report its
+    /// status and the tail of its log
+    async fn follow(&self, info: ProcessInfo, timeout: Option<u64>) -> Result<Value> {
+        let deadline = Duration::from_secs(timeout.unwrap_or(WAIT_SECS));
+        let started = Instant::now();
+        let mut current = info.clone();
+        while current.running && started.elapsed() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            current = self.manager.get(&info.proc_id).await.unwrap_or(current);
+        }
+
+        let log = match &info.log_file {
+            Some(file) => tokio::fs::read_to_string(file).await.unwrap_or_default(),
+            None => String::new(),
+        };
+        let lines: Vec<&str> = log.lines().collect();
+        let mut data = json!({
+            "command": info.command,
+            "proc_id": info.proc_id,
+            "log_file": info.log_file,
+            "output": lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n"),
+            "total_lines": lines.len()
+        });
+        if current.running {
+            data["status"] = json!("running");
+            data["message"] = json!(format!(
+                "Still running after {}s; exec(action=\"logs\", proc_id=\"{}\") shows its output so far and exec(action=\"wait\") waits for it",
+                deadline.as_secs(), info.proc_id
+            ));
+        } else {
+            data["status"] = json!(if current.exit_code == Some(0) { "success" } else { "failed" });
+            data["exit_code"] = json!(current.exit_code);
+            data["duration_ms"] = json!(started.elapsed().as_millis() as u64);
+        }
+        Ok(data)
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "docker",
+                "actions": {
+                    "ps": "Containers with image, state, status and ports (all for stopped ones too)",
+                    "images": "Local images with tag, id and size",
+                    "run": "Start a container from image (name, ports, env, volumes, command); detach=false waits for it like build",
+                    "stop": "Stop a container (requires name)",
+                    "logs": "A container's stdout and stderr lines (tail, since)",
+                    "exec": "Run a command in a running container (requires name, command)",
+                    "build": "Build path into an image (tag, dockerfile, build_args), backgrounded after timeout",
+                    "pull": "Pull an image, backgrounded after timeout",
+                    "compose_up": "docker compose up -d in path (file, project, services, build)",
+                    "compose_down": "docker compose down in path (remove_volumes)",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "docker", "action": "help" }
+        })
+    }
+}
+
+fn project_dir(args: &DockerToolArgs) -> Result<PathBuf> {
+    let dir = shellexpand::tilde(args.path.as_deref().unwrap_or(".")).to_string();
+    std::fs::canonicalize(&dir).map_err(|e| ToolError::not_found(format!("{}: {}", dir, e)).into())
+}
+
+/// A client for DOCKER_HOST, or the local socket when it is unset
+fn client() -> Result<Docker> {
+    Docker::connect_with_defaults().map_err(api_error)
+}
+
+/// An Engine API call that should answer within CALL_TIMEOUT
+async fn quick<T>(call: impl Future<Output = std::result::Result<T, bollard::errors::Error>>) -> Result<T> {
+    tokio::time::timeout(CALL_TIMEOUT, call)
+        .await
+        .map_err(|_| ToolError::timeout(format!("Docker did not answer within {}s", CALL_TIMEOUT.as_secs())))?
+        .map_err(api_error)
+}
+
+fn api_error(error: bollard::errors::Error) -> anyhow::Error {
+    use bollard::errors::Error;
+    let message = format!("docker error: {}", error);
+    match &error {
+        Error::DockerResponseServerError { status_code: 404, .. } => ToolError::not_found(message),
+        Error::DockerResponseServerError { status_code: 401 | 403, .. } => ToolError::permission_denied(message),
+        Error::IOError { err } if err.kind() == std::io::ErrorKind::PermissionDenied => ToolError::permission_denied(message),
+        Error::RequestTimeoutError => ToolError::timeout(message),
+        Error::IOError { .. } | Error::SocketNotFoundError(_) | Error::HyperLegacyError { .. } | Error::UnsupportedURISchemeError { .. } => {
+            ToolError::unsupported(format!("Cannot reach the Docker daemon: {}", error))
+        }
+        _ => ToolError::external(message),
+    }
+    .into()
+}
+
+/// `docker compose` when the plugin is installed, else standalone docker-compose
+async fn compose_command() -> Result<(String, Vec<String>)> {
+    if let Ok(docker) = which::which("docker") {
+        let probe = Command::new(docker).args(["compose", "version"]).kill_on_drop(true).output();
+        if tokio::time::timeout(CALL_TIMEOUT, probe).await.is_ok_and(|o| o.is_ok_and(|o| o.status.success())) {
+            return Ok(("docker".into(), vec!["compose".into()]));
+        }
+    }
+    if which::which("docker-compose").is_ok() {
+        return Ok(("docker-compose".into(), Vec::new()));
+    }
+    Err(ToolError::unsupported("Neither docker compose nor docker-compose is installed").into())
+}
+
+/// Pull `image`, passing each progress line to `progress`
+async fn pull_image(docker: &Docker, image: &str, progress: impl Fn(String)) -> Result<()> {
+    let (from_image, tag) = image_tag(image);
+    let options = CreateImageOptions { from_image, tag, ..Default::default() };
+    let mut stream = docker.create_image(Some(options), None, None);
+    while let Some(info) = stream.next().await {
+        let info = info.map_err(api_error)?;
+        if let Some(error) = info.error {
+            return Err(ToolError::external(format!("docker error: pulling {}: {}", image, error)).into());
+        }
+        let line = [info.id, info.status, info.progress].into_iter().flatten().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            progress(line);
+        }
+    }
+    Ok(())
+}
+
+/// An image reference as name and tag, the tag defaulting to latest; a
+/// digest stays in the name, since an empty tag would pull every tag
+fn image_tag(image: &str) -> (&str, &str) {
+    match image.rsplit_once(':') {
+        _ if image.contains('@') => (image, ""),
+        Some((name, tag)) if !tag.contains('/') => (name, tag),
+        _ => (image, "latest"),
+    }
+}
+
+/// The exit code of a container that has been started, once it stops
+async fn exit_code(docker: &Docker, id: &str) -> i32 {
+    let mut wait = std::pin::pin!(docker.wait_container(id, None::<WaitContainerOptions<String>>));
+    match wait.next().await {
+        Some(Ok(done)) => done.status_code as i32,
+        // A non-zero exit comes back as an error carrying the code
+        Some(Err(bollard::errors::Error::DockerContainerWaitError { code, .. })) => code as i32,
+        _ => -1,
+    }
+}
+
+fn env_list(env: &BTreeMap<String, String>) -> Vec<String> {
+    env.iter().map(|(key, value)| format!("{}={}", key, value)).collect()
+}
+
+/// `-p` style mappings ([ip:][host:]container[/proto]) keyed by container port
+fn port_bindings(specs: &[String]) -> Result<PortMap> {
+    let mut bindings = PortMap::new();
+    for spec in specs {
+        let (ports, proto) = spec.split_once('/').unwrap_or((spec, "tcp"));
+        let mut parts = ports.rsplitn(3, ':');
+        let container = parts.next()
+            .filter(|port| port.parse::<u16>().is_ok())
+            .ok_or_else(|| ToolError::invalid(format!("Bad port mapping {:?} (use e.g. 8080:80)", spec)))?;
+        let binding = PortBinding {
+            host_port: parts.next().filter(|port| !port.is_empty()).map(str::to_string),
+            host_ip: parts.next().map(|ip| ip.trim_matches(['[', ']']).to_string()),
+        };
+        bindings.entry(format!("{}/{}", container, proto))
+            .or_insert(None)
+            .get_or_insert_with(Vec::new)
+            .push(binding);
+    }
+    Ok(bindings)
+}
+
+/// `-v` style mounts, relative host paths taken from the working directory
+/// since the daemon only accepts absolute ones
+fn binds(volumes: &[String]) -> Result<Vec<String>> {
+    let cwd = std::env::current_dir()?;
+    Ok(volumes.iter()
+        .map(|volume| match volume.split_once(':') {
+            Some((source, target)) if source.starts_with('.') || source.starts_with('~') => {
+                let source = cwd.join(shellexpand::tilde(source).as_ref()).components().collect::<PathBuf>();
+                format!("{}:{}", source.display(), target)
+            }
+            _ => volume.clone(),
+        })
+        .collect())
+}
+
+/// `since` as a unix time: RFC 3339, or an interval back from now like 10m
+fn since(text: &str) -> Result<i64> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(text) {
+        return Ok(time.timestamp());
+    }
+    Ok(chrono::Utc::now().timestamp() - crate::schedule::parse_interval(text)?)
+}
+
+/// `dir` as a tar build context, leaving out what .dockerignore names
+fn build_context(dir: &Path) -> Result<Vec<u8>> {
+    let mut archive = tar::Builder::new(Vec::new());
+    let walk = ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .add_custom_ignore_filename(".dockerignore")
+        .build();
+    for entry in walk {
+        let entry = entry?;
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(dir)?;
+        archive.append_path_with_name(entry.path(), relative)?;
+    }
+    Ok(archive.into_inner()?)
+}
+
+fn timestamp(secs: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp(secs, 0).map(|time| time.to_rfc3339())
+}
+
+fn container_summary(container: &ContainerSummary) -> Value {
+    let name = container.names.iter().flatten().next().map(|name| name.trim_start_matches('/'));
+    let ports: Vec<String> = container.ports.iter().flatten()
+        .map(|port| {
+            let typ = port.typ.map(|t| t.to_string()).unwrap_or_else(|| "tcp".into());
+            match port.public_port {
+                Some(public) => format!("{}:{}->{}/{}", port.ip.as_deref().unwrap_or(""), public, port.private_port, typ),
+                None => format!("{}/{}", port.private_port, typ),
+            }
+        })
+        .collect();
+    json!({
+        "id": container.id,
+        "name": name,
+        "image": container.image,
+        "state": container.state,
+        "status": container.status,
+        "ports": ports,
+        "command": container.command,
+        "created": container.created.and_then(timestamp)
+    })
+}
+
+fn image_summary(image: &ImageSummary) -> Value {
+    let reference = image.repo_tags.first().map(String::as_str).unwrap_or("<none>:<none>");
+    let (repository, tag) = reference.rsplit_once(':').unwrap_or((reference, "<none>"));
+    json!({
+        "repository": repository,
+        "tag": tag,
+        "id": image.id,
+        "size": image.size,
+        "created": timestamp(image.created)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::{Port, PortTypeEnum};
+
+    #[test]
+    fn test_container_summary() {
+        let web = ContainerSummary {
+            id: Some("abc123".into()),
+            names: Some(vec!["/web".into()]),
+            image: Some("nginx:1.25".into()),
+            command: Some("nginx -g".into()),
+            created: Some(1714557600),
+            ports: Some(vec![Port {
+                ip: Some("0.0.0.0".into()),
+                private_port: 80,
+                public_port: Some(8080),
+                typ: Some(PortTypeEnum::TCP),
+            }]),
+            state: Some("running".into()),
+            status: Some("Up 2 hours".into()),
+            ..Default::default()
+        };
+        let cache = ContainerSummary {
+            id: Some("def456".into()),
+            names: Some(vec!["/cache".into()]),
+            ports: Some(vec![Port { private_port: 6379, typ: Some(PortTypeEnum::TCP), ..Default::default() }]),
+            state: Some("exited".into()),
+            ..Default::default()
+        };
+        let web = container_summary(&web);
+        assert_eq!(web["name"], "web");
+        assert_eq!(web["ports"], json!(["0.0.0.0:8080->80/tcp"]));
+        assert_eq!(web["created"], "2024-05-01T10:00:00+00:00");
+        let cache = container_summary(&cache);
+        assert_eq!(cache["state"], "exited");
+        assert_eq!(cache["ports"], json!(["6379/tcp"]));
+
+        assert_eq!("up".parse::<DockerAction>().unwrap(), DockerAction::ComposeUp);
+        assert_eq!("compose-down".parse::<DockerAction>().unwrap(), DockerAction::ComposeDown);
+    }
+
+    #[test]
+    fn test_run_options() {
+        let ports = port_bindings(&["8080:80".into(), "127.0.0.1:5353:53/udp".into(), "9000".into()]).unwrap();
+        assert_eq!(ports["80/tcp"], Some(vec![PortBinding { host_ip: None, host_port: Some("8080".into()) }]));
+        assert_eq!(ports["53/udp"], Some(vec![PortBinding { host_ip: Some("127.0.0.1".into()), host_port: Some("5353".into()) }]));
+        assert_eq!(ports["9000/tcp"], Some(vec![PortBinding { host_ip: None, host_port: None }]));
+        assert!(port_bindings(&["8080:http".into()]).is_err());
+
+        let cwd = std::env::current_dir().unwrap();
+        let mounts = binds(&["./data:/data:ro".into(), "cache:/cache".into()]).unwrap();
+        assert_eq!(mounts, vec![format!("{}:/data:ro", cwd.join("data").display()), "cache:/cache".to_string()]);
+
+        assert_eq!(image_tag("redis"), ("redis", "latest"));
+        assert_eq!(image_tag("localhost:5000/app:1.2"), ("localhost:5000/app", "1.2"));
+        assert_eq!(image_tag("localhost:5000/app"), ("localhost:5000/app", "latest"));
+        assert_eq!(image_tag("redis@sha256:abc"), ("redis@sha256:abc", ""));
+    }
+}
@@ -0,0 +1,148 @@
+/// Environment and PATH for commands exec runs
+///
+/// MCP servers started by an editor or desktop app often inherit a bare
+/// PATH without the user's toolchains, so `cargo` or `node` work in a
+/// terminal but not through exec. Toolchain directories that exist but
+/// are missing from PATH go in front of it, and the inherited environment
+/// is narrowed to an allowlist (or nothing) when configured.
+
+use crate::config::{EnvInherit, ExecConfig};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// What a command starts with before the call's own env is applied
+#[derive(Debug, Clone)]
+pub struct Environment {
+    /// Start from an empty environment rather than the server's
+    clear: bool,
+    vars: Vec<(OsString, OsString)>,
+    /// Directories put in front of the inherited PATH
+    pub added: Vec<PathBuf>,
+}
+
+impl Environment {
+    pub fn new(config: &ExecConfig) -> Self {
+        let inherited = std::env::var_os("PATH").unwrap_or_default();
+        let present: Vec<PathBuf> = std::env::split_paths(&inherited).collect();
+        let mut added: Vec<PathBuf> = config.path.iter()
+            .map(|dir| PathBuf::from(shellexpand::tilde(&dir.to_string_lossy()).into_owned()))
+            .collect();
+        if config.augment_path {
+            added.extend(toolchain_dirs().into_iter().filter(|dir| dir.is_dir()));
+        }
+        let mut seen = present.clone();
+        added.retain(|dir| {
+            let new = !seen.contains(dir);
+            seen.push(dir.clone());
+            new
+        });
+        let path = std::env::join_paths(added.iter().chain(&present)).unwrap_or(inherited);
+
+        let mut vars: Vec<(OsString, OsString)> = match config.env_inherit {
+            EnvInherit::All => Vec::new(),
+            EnvInherit::Allowlist => std::env::vars_os()
+                .filter(|(key, _)| key != "PATH" && allowed(&key.to_string_lossy(), &config.env_allow))
+                .collect(),
+            EnvInherit::None => Vec::new(),
+        };
+        vars.push(("PATH".into(), path));
+        vars.extend(config.env.iter().map(|(k, v)| (k.into(), v.into())));
+        Self { clear: config.env_inherit != EnvInherit::All, vars, added }
+    }
+
+    pub fn apply(&self, cmd: &mut Command) {
+        if self.clear {
+            cmd.env_clear();
+        }
+        cmd.envs(self.vars.iter().map(|(k, v)| (k, v)));
+    }
+}
+
+fn allowed(key: &str, allow: &[String]) -> bool {
+    allow.iter().any(|pattern| match pattern.strip_suffix('*') {
+        Some(prefix) => key.starts_with(prefix),
+        None => key == pattern,
+    })
+}
+
+/// Where common toolchains install their binaries, whether or not they exist
+fn toolchain_dirs() -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else { return Vec::new() };
+    let under = |var: &str, default: &str| std::env::var_os(var).map(PathBuf::from).unwrap_or_else(|| home.join(default));
+    let mut dirs = vec![
+        under("CARGO_HOME", ".cargo").join("bin"),
+        under("PYENV_ROOT", ".pyenv").join("shims"),
+        under("PYENV_ROOT", ".pyenv").join("bin"),
+        home.join(".rbenv/shims"),
+        under("ASDF_DATA_DIR", ".asdf").join("shims"),
+        under("VOLTA_HOME", ".volta").join("bin"),
+        under("BUN_INSTALL", ".bun").join("bin"),
+        under("DENO_INSTALL", ".deno").join("bin"),
+        under("GOPATH", "go").join("bin"),
+        home.join(".local/bin"),
+    ];
+    dirs.extend(nvm_node(&under("NVM_DIR", ".nvm")));
+    if cfg!(target_os = "macos") {
+        dirs.extend([PathBuf::from("/opt/homebrew/bin"), PathBuf::from("/usr/local/bin")]);
+    }
+    dirs
+}
+
+/// bin directory of nvm's default node: the newest installed version
+/// matching `alias/default`, or the newest installed at all
+fn nvm_node(nvm: &Path) -> Option<PathBuf> {
+    let alias = std::fs::read_to_string(nvm.join("alias/default")).unwrap_or_default();
+    let alias = alias.trim().trim_start_matches('v');
+    let versions: Vec<(Vec<u64>, PathBuf)> = std::fs::read_dir(nvm.join("versions/node")).ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let parts = name.trim_start_matches('v').split('.').map(|p| p.parse().ok()).collect::<Option<Vec<u64>>>()?;
+            Some((parts, entry.path()))
+        })
+        .collect();
+    let matches = |parts: &[u64]| {
+        let version = parts.iter().map(u64::to_string).collect::<Vec<_>>().join(".");
+        !alias.is_empty() && (version == alias || version.starts_with(&format!("{}.", alias)))
+    };
+    let newest = |candidates: Vec<&(Vec<u64>, PathBuf)>| candidates.into_iter().max_by(|a, b| a.0.cmp(&b.0)).map(|(_, path)| path.join("bin"));
+    newest(versions.iter().filter(|(parts, _)| matches(parts)).collect())
+        .or_else(|| newest(versions.iter().collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_environment() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = ExecConfig {
+            env_inherit: EnvInherit::None,
+            env: HashMap::from([("MODE".to_string(), "ci".to_string())]),
+            augment_path: false,
+            path: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        };
+        let env = Environment::new(&config);
+        assert_eq!(env.added, [dir.path().to_path_buf()]);
+        let path = env.vars.iter().find(|(k, _)| k == "PATH").unwrap();
+        assert_eq!(std::env::split_paths(&path.1).next().unwrap(), dir.path());
+        assert!(env.vars.iter().any(|(k, v)| k == "MODE" && v == "ci"));
+        assert!(env.clear);
+        assert!(!env.vars.iter().any(|(k, _)| k == "HOME"));
+
+        assert!(allowed("LC_ALL", &["LC_*".to_string()]));
+        assert!(!allowed("HOMEBREW", &["HOME".to_string()]));
+
+        std::fs::create_dir_all(dir.path().join("versions/node/v18.19.0/bin")).unwrap();
+        std::fs::create_dir_all(dir.path().join("versions/node/v20.9.0/bin")).unwrap();
+        std::fs::create_dir_all(dir.path().join("versions/node/v20.11.1/bin")).unwrap();
+        assert_eq!(nvm_node(dir.path()).unwrap(), dir.path().join("versions/node/v20.11.1/bin"));
+        std::fs::create_dir_all(dir.path().join("alias")).unwrap();
+        std::fs::write(dir.path().join("alias/default"), "18\n").unwrap();
+        assert_eq!(nvm_node(dir.path()).unwrap(), dir.path().join("versions/node/v18.19.0/bin"));
+    }
+}
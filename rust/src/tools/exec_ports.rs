@@ -0,0 +1,230 @@
+/// Which processes listen on which TCP ports
+///
+/// Linux reads `/proc/net/tcp{,6}` and matches socket inodes to the
+/// `/proc/<pid>/fd` links that hold them, macOS asks `lsof`, and Windows
+/// parses `netstat -ano`. Sockets owned by other users show without a pid
+/// where the platform hides them.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde::Serialize;
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener};
+
+/// A TCP socket accepting connections
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Listener {
+    pub port: u16,
+    pub address: String,
+    pub pid: Option<u32>,
+    /// Program name, where the platform reports it
+    pub process: Option<String>,
+}
+
+/// Every listening TCP socket, by port
+pub async fn listeners() -> Result<Vec<Listener>> {
+    let mut all = platform_listeners().await?;
+    all.sort_by(|a, b| (a.port, &a.address).cmp(&(b.port, &b.address)));
+    all.dedup();
+    Ok(all)
+}
+
+/// A port on 127.0.0.1 nothing listens on: `from` or the first free one
+/// after it, or any the OS hands out
+pub fn free_port(from: Option<u16>) -> Result<u16> {
+    let Some(from) = from else {
+        return Ok(TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?.local_addr()?.port());
+    };
+    (from..=u16::MAX)
+        .find(|port| TcpListener::bind((Ipv4Addr::LOCALHOST, *port)).is_ok())
+        .ok_or_else(|| ToolError::not_found(format!("No free port from {}", from)).into())
+}
+
+/// Send `signal` (a Unix signal number) to `pid`; false if it was
+/// already gone. Windows has no signals, so it always force-kills
+pub async fn terminate(pid: u32, signal: i32) -> Result<bool> {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        let signal = Signal::try_from(signal).unwrap_or(Signal::SIGTERM);
+        match kill(Pid::from_raw(pid as i32), signal) {
+            Ok(()) => Ok(true),
+            Err(nix::errno::Errno::ESRCH) => Ok(false),
+            Err(e) => Err(ToolError::external(format!("Cannot kill {}: {}", pid, e)).into()),
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = signal;
+        let output = tokio::process::Command::new("taskkill").args(["/PID", &pid.to_string(), "/F"]).output().await?;
+        Ok(output.status.success())
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (pid, signal);
+        Err(ToolError::unsupported("kill not supported on this platform").into())
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn platform_listeners() -> Result<Vec<Listener>> {
+    tokio::task::spawn_blocking(|| {
+        let mut sockets = Vec::new();
+        for (file, v6) in [("/proc/net/tcp", false), ("/proc/net/tcp6", true)] {
+            if let Ok(table) = std::fs::read_to_string(file) {
+                sockets.extend(table.lines().skip(1).filter_map(|line| parse_proc_net(line, v6)));
+            }
+        }
+        let owners = socket_owners();
+        sockets.into_iter()
+            .map(|(port, address, inode)| {
+                let pid = owners.get(&inode).copied();
+                let process = pid.and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+                    .map(|comm| comm.trim().to_string());
+                Listener { port, address, pid, process }
+            })
+            .collect()
+    }).await.map_err(Into::into)
+}
+
+/// Port, address and socket inode of a LISTEN row of /proc/net/tcp{,6}
+#[cfg(any(target_os = "linux", test))]
+fn parse_proc_net(line: &str, v6: bool) -> Option<(u16, String, u64)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    // st 0A is TCP_LISTEN
+    if fields.get(3) != Some(&"0A") {
+        return None;
+    }
+    let (address, port) = fields.get(1)?.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+    // The kernel writes each 32-bit word of the address in host order
+    let words = (0..address.len() / 8)
+        .map(|i| u32::from_str_radix(&address[i * 8..i * 8 + 8], 16).map(u32::swap_bytes))
+        .collect::<Result<Vec<_>, _>>().ok()?;
+    let address = match (v6, words.as_slice()) {
+        (false, [word]) => Ipv4Addr::from(*word).to_string(),
+        (true, [a, b, c, d]) => Ipv6Addr::from(((*a as u128) << 96) | ((*b as u128) << 64) | ((*c as u128) << 32) | *d as u128).to_string(),
+        _ => return None,
+    };
+    Some((port, address, fields.get(9)?.parse().ok()?))
+}
+
+/// Socket inode to the pid holding it, for the processes we can inspect
+#[cfg(target_os = "linux")]
+fn socket_owners() -> std::collections::HashMap<u64, u32> {
+    let mut owners = std::collections::HashMap::new();
+    let Ok(procs) = std::fs::read_dir("/proc") else { return owners };
+    for entry in procs.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else { continue };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else { continue };
+        for fd in fds.flatten() {
+            let inode = std::fs::read_link(fd.path()).ok()
+                .and_then(|target| target.to_str()?.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok());
+            if let Some(inode) = inode {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+#[cfg(target_os = "macos")]
+async fn platform_listeners() -> Result<Vec<Listener>> {
+    let output = tokio::process::Command::new("lsof").args(["-nP", "-iTCP", "-sTCP:LISTEN", "-F", "pcn"]).output().await
+        .map_err(|e| ToolError::unsupported(format!("lsof is needed to list ports: {}", e)))?;
+    Ok(parse_lsof(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `lsof -F pcn` output: a `p<pid>` and `c<command>` line per process,
+/// then an `n<address>:<port>` line per socket
+#[cfg(any(target_os = "macos", test))]
+fn parse_lsof(output: &str) -> Vec<Listener> {
+    let (mut pid, mut process) = (None, None);
+    let mut listeners = Vec::new();
+    for line in output.lines() {
+        let (tag, value) = line.split_at(line.len().min(1));
+        match tag {
+            "p" => (pid, process) = (value.parse().ok(), None),
+            "c" => process = Some(value.to_string()),
+            "n" => {
+                if let Some((address, port)) = value.rsplit_once(':') {
+                    if let Ok(port) = port.parse() {
+                        let address = address.trim_start_matches('[').trim_end_matches(']').to_string();
+                        listeners.push(Listener { port, address, pid, process: process.clone() });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    listeners
+}
+
+#[cfg(windows)]
+async fn platform_listeners() -> Result<Vec<Listener>> {
+    let output = tokio::process::Command::new("netstat").arg("-ano").output().await?;
+    Ok(parse_netstat(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `netstat -ano` rows: proto, local, foreign, state, pid
+#[cfg(any(windows, test))]
+fn parse_netstat(output: &str) -> Vec<Listener> {
+    output.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() != 5 || fields[0] != "TCP" || fields[3] != "LISTENING" {
+                return None;
+            }
+            let (address, port) = fields[1].rsplit_once(':')?;
+            Some(Listener {
+                port: port.parse().ok()?,
+                address: address.trim_start_matches('[').trim_end_matches(']').to_string(),
+                pid: fields[4].parse().ok(),
+                process: None,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+async fn platform_listeners() -> Result<Vec<Listener>> {
+    Err(ToolError::unsupported("listing ports is not supported on this platform").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_listeners() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let found = listeners().await.unwrap();
+        let ours = found.iter().find(|l| l.port == port).unwrap();
+        assert_eq!(ours.address, "127.0.0.1");
+        if cfg!(target_os = "linux") {
+            assert_eq!(ours.pid, Some(std::process::id()));
+        }
+        assert_ne!(free_port(Some(port)).unwrap(), port);
+        drop(listener);
+        assert_eq!(free_port(Some(port)).unwrap(), port);
+
+        let row = "   1: 0100007F:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 424242 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(parse_proc_net(row, false), Some((8080, "127.0.0.1".to_string(), 424242)));
+        let v6 = "   0: 00000000000000000000000001000000:0BB8 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 7 1";
+        assert_eq!(parse_proc_net(v6, true), Some((3000, "::1".to_string(), 7)));
+        assert_eq!(parse_proc_net(&row.replace(" 0A ", " 01 "), false), None);
+
+        let lsof = "p501\ncnode\nn*:3000\nn[::1]:3000\np77\ncpostgres\nn127.0.0.1:5432\n";
+        let parsed = parse_lsof(lsof);
+        assert_eq!(parsed[1], Listener { port: 3000, address: "::1".into(), pid: Some(501), process: Some("node".into()) });
+        assert_eq!(parsed[2].process.as_deref(), Some("postgres"));
+        let netstat = "  Proto  Local Address  Foreign Address  State  PID\n  TCP    0.0.0.0:135    0.0.0.0:0    LISTENING    1234\n  TCP    [::]:445    [::]:0    LISTENING    4\n  TCP    10.0.0.2:5555    1.2.3.4:443    ESTABLISHED    9\n";
+        let parsed = parse_netstat(netstat);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!((parsed[1].port, parsed[1].address.as_str(), parsed[1].pid), (445, "::", Some(4)));
+    }
+}
@@ -0,0 +1,345 @@
+/// Named long-running services on top of `ProcessManager`
+///
+/// A service is a command meant to stay up (a dev server, a database)
+/// rather than finish. Starting one waits for its readiness probe, a local
+/// port accepting connections or a line of output matching a regex, and a
+/// supervisor restarts it per its policy when it exits. Each run is an
+/// ordinary logged process, so `ps` and `logs` see it too.
+
+use super::exec_tool::ProcessManager;
+use crate::error::ToolError;
+use anyhow::Result;
+use regex::Regex;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+/// Longest wait between restarts; the first is a second, doubling after
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// How long stop waits after SIGTERM before SIGKILL
+const STOP_GRACE: Duration = Duration::from_secs(5);
+
+const POLL: Duration = Duration::from_millis(200);
+
+/// When an exited service starts again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestartPolicy {
+    Never,
+    OnFailure,
+    Always,
+}
+
+impl FromStr for RestartPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "never" | "no" => Ok(Self::Never),
+            "on-failure" | "on_failure" | "failure" => Ok(Self::OnFailure),
+            "always" => Ok(Self::Always),
+            _ => Err(ToolError::invalid(format!("Unknown restart policy: {} (never, on-failure, always)", s)).into()),
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Never => "never",
+            Self::OnFailure => "on-failure",
+            Self::Always => "always",
+        }
+    }
+}
+
+/// When a started service counts as ready
+#[derive(Debug, Clone)]
+pub enum Probe {
+    /// As soon as it is running
+    Running,
+    /// Once this local port accepts connections
+    Port(u16),
+    /// Once a line of its output matches
+    Log(Regex),
+}
+
+impl Probe {
+    fn describe(&self) -> Value {
+        match self {
+            Self::Running => json!("running"),
+            Self::Port(port) => json!({ "port": port }),
+            Self::Log(pattern) => json!({ "log": pattern.as_str() }),
+        }
+    }
+
+    async fn ready(&self, manager: &ProcessManager, proc_id: &str) -> bool {
+        match self {
+            Self::Running => true,
+            Self::Port(port) => {
+                let connect = tokio::net::TcpStream::connect(("127.0.0.1", *port));
+                matches!(tokio::time::timeout(Duration::from_secs(1), connect).await, Ok(Ok(_)))
+            }
+            Self::Log(pattern) => match manager.get(proc_id).await.and_then(|info| info.log_file) {
+                Some(log) => tokio::fs::read_to_string(log).await
+                    .is_ok_and(|text| text.lines().any(|line| pattern.is_match(line))),
+                None => false,
+            },
+        }
+    }
+}
+
+/// How a service runs
+pub struct ServiceSpec {
+    /// The command as shown in status and ps
+    pub command: String,
+    /// Builds the command afresh for every start and restart
+    pub launch: Box<dyn Fn() -> Command + Send + Sync>,
+    pub probe: Probe,
+    pub restart: RestartPolicy,
+    pub max_restarts: u32,
+    /// How long start waits for the probe
+    pub ready_timeout: Duration,
+}
+
+struct Service {
+    spec: Arc<ServiceSpec>,
+    proc_id: String,
+    restarts: u32,
+    /// Bumped by every start and stop so an older supervisor stands down
+    generation: u64,
+    stopped: bool,
+    /// Exited and waiting out the delay before a restart
+    restarting: bool,
+}
+
+/// Services by name
+#[derive(Clone, Default)]
+pub struct Services {
+    services: Arc<Mutex<HashMap<String, Service>>>,
+}
+
+impl Services {
+    pub async fn contains(&self, name: &str) -> bool {
+        self.services.lock().await.contains_key(name)
+    }
+
+    /// Start `name` and wait for it to be ready; a conflict if it is
+    /// already up
+    pub async fn start(&self, manager: &ProcessManager, name: &str, spec: ServiceSpec) -> Result<Value> {
+        self.launch(manager, name, Arc::new(spec)).await
+    }
+
+    /// Stop `name` if it runs, then start it again: with `spec`, or as
+    /// it was last started
+    pub async fn restart(&self, manager: &ProcessManager, name: &str, spec: Option<ServiceSpec>) -> Result<Value> {
+        let current = self.services.lock().await.get(name).map(|s| s.spec.clone());
+        let spec = match (spec, current) {
+            (Some(spec), _) => Arc::new(spec),
+            (None, Some(current)) => current,
+            (None, None) => return Err(ToolError::not_found(format!("No service named {}", name)).into()),
+        };
+        if self.contains(name).await {
+            self.stop(manager, name).await?;
+        }
+        self.launch(manager, name, spec).await
+    }
+
+    /// Stop `name`: SIGTERM to its process group, then SIGKILL if it is
+    /// still up after a grace period
+    pub async fn stop(&self, manager: &ProcessManager, name: &str) -> Result<Value> {
+        let proc_id = {
+            let mut services = self.services.lock().await;
+            let service = services.get_mut(name)
+                .ok_or_else(|| ToolError::not_found(format!("No service named {}", name)))?;
+            service.stopped = true;
+            service.restarting = false;
+            service.generation += 1;
+            service.proc_id.clone()
+        };
+        if let Some(pid) = manager.get(&proc_id).await.filter(|info| info.running).and_then(|info| info.pid) {
+            signal(pid, false)?;
+            let deadline = Instant::now() + STOP_GRACE;
+            while manager.get(&proc_id).await.is_some_and(|info| info.running) {
+                if Instant::now() >= deadline {
+                    signal(pid, true)?;
+                    break;
+                }
+                tokio::time::sleep(POLL).await;
+            }
+        }
+        self.status(manager, Some(name)).await
+    }
+
+    /// One service, or all of them as `{services: [...]}`
+    pub async fn status(&self, manager: &ProcessManager, name: Option<&str>) -> Result<Value> {
+        let services = self.services.lock().await;
+        if let Some(name) = name {
+            let service = services.get(name)
+                .ok_or_else(|| ToolError::not_found(format!("No service named {}", name)))?;
+            return Ok(describe(manager, name, service).await);
+        }
+        let mut names: Vec<&String> = services.keys().collect();
+        names.sort();
+        let mut all = Vec::new();
+        for name in names {
+            all.push(describe(manager, name, &services[name]).await);
+        }
+        Ok(json!({ "services": all }))
+    }
+
+    async fn launch(&self, manager: &ProcessManager, name: &str, spec: Arc<ServiceSpec>) -> Result<Value> {
+        let (proc_id, generation) = {
+            let mut services = self.services.lock().await;
+            let previous = services.get(name);
+            if let Some(service) = previous.filter(|s| !s.stopped) {
+                if manager.get(&service.proc_id).await.is_some_and(|info| info.running) {
+                    return Err(ToolError::conflict(format!("Service {} is already running as {}; use restart", name, service.proc_id)).into());
+                }
+            }
+            let generation = previous.map_or(0, |s| s.generation + 1);
+            let info = manager.spawn_logged(command(&spec), spec.command.clone()).await?;
+            services.insert(name.to_string(), Service {
+                spec: spec.clone(),
+                proc_id: info.proc_id.clone(),
+                restarts: 0,
+                generation,
+                stopped: false,
+                restarting: false,
+            });
+            (info.proc_id, generation)
+        };
+        self.supervise(manager.clone(), name.to_string(), generation);
+
+        let deadline = Instant::now() + spec.ready_timeout;
+        loop {
+            let info = manager.get(&proc_id).await;
+            if !info.as_ref().is_some_and(|info| info.running) {
+                let exit_code = info.and_then(|info| info.exit_code);
+                return Err(ToolError::external(format!(
+                    "Service {} exited with {} before it was ready; see exec(action='logs', proc_id='{}')",
+                    name, exit_code.map_or("no status".to_string(), |c| c.to_string()), proc_id
+                )).into());
+            }
+            if spec.probe.ready(manager, &proc_id).await || Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(POLL).await;
+        }
+        // Still "starting" when the probe timed out
+        self.status(manager, Some(name)).await
+    }
+
+    /// Watch `name` and restart it per its policy until it is stopped,
+    /// started anew or out of restarts
+    fn supervise(&self, manager: ProcessManager, name: String, generation: u64) {
+        let services = self.services.clone();
+        tokio::spawn(async move {
+            loop {
+                let current = |services: &HashMap<String, Service>| services.get(&name)
+                    .filter(|s| s.generation == generation && !s.stopped)
+                    .map(|s| (s.spec.clone(), s.proc_id.clone(), s.restarts));
+                let Some((spec, proc_id, restarts)) = current(&*services.lock().await) else { return };
+                let exit_code = loop {
+                    match manager.get(&proc_id).await {
+                        Some(info) if info.running => tokio::time::sleep(POLL).await,
+                        Some(info) => break info.exit_code,
+                        None => return,
+                    }
+                };
+                let wanted = match spec.restart {
+                    RestartPolicy::Never => false,
+                    RestartPolicy::OnFailure => exit_code != Some(0),
+                    RestartPolicy::Always => true,
+                };
+                if !wanted || restarts >= spec.max_restarts {
+                    return;
+                }
+                {
+                    let mut services = services.lock().await;
+                    match services.get_mut(&name).filter(|s| s.generation == generation && !s.stopped) {
+                        Some(service) => service.restarting = true,
+                        None => return,
+                    }
+                }
+                let delay = Duration::from_secs(1 << restarts.min(5)).min(MAX_RESTART_DELAY);
+                log::warn!("Service {} exited with {:?}; restarting in {}s", name, exit_code, delay.as_secs());
+                tokio::time::sleep(delay).await;
+
+                let mut services = services.lock().await;
+                let Some(service) = services.get_mut(&name).filter(|s| s.generation == generation && !s.stopped) else { return };
+                service.restarting = false;
+                match manager.spawn_logged(command(&spec), spec.command.clone()).await {
+                    Ok(info) => {
+                        service.proc_id = info.proc_id;
+                        service.restarts += 1;
+                    }
+                    Err(e) => {
+                        log::warn!("Cannot restart service {}: {}", name, e);
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// The service's command in its own process group, so stop reaches
+/// whatever it started
+fn command(spec: &ServiceSpec) -> Command {
+    let mut cmd = (spec.launch)();
+    #[cfg(unix)]
+    cmd.process_group(0);
+    cmd
+}
+
+async fn describe(manager: &ProcessManager, name: &str, service: &Service) -> Value {
+    let info = manager.get(&service.proc_id).await;
+    let running = info.as_ref().is_some_and(|info| info.running);
+    let exit_code = info.as_ref().and_then(|info| info.exit_code);
+    let state = if service.stopped {
+        "stopped"
+    } else if service.restarting {
+        "restarting"
+    } else if running {
+        if service.spec.probe.ready(manager, &service.proc_id).await { "ready" } else { "starting" }
+    } else if exit_code == Some(0) {
+        "exited"
+    } else {
+        "failed"
+    };
+    json!({
+        "name": name,
+        "state": state,
+        "command": service.spec.command,
+        "proc_id": service.proc_id,
+        "pid": info.as_ref().and_then(|info| info.pid),
+        "exit_code": if running { None } else { exit_code },
+        "started": info.map(|info| info.started),
+        "restarts": service.restarts,
+        "restart": service.spec.restart.name(),
+        "max_restarts": service.spec.max_restarts,
+        "probe": service.spec.probe.describe()
+    })
+}
+
+#[cfg(unix)]
+fn signal(pid: u32, kill: bool) -> Result<()> {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    let signal = if kill { Signal::SIGKILL } else { Signal::SIGTERM };
+    match killpg(Pid::from_raw(pid as i32), signal) {
+        Ok(()) | Err(nix::errno::Errno::ESRCH) => Ok(()),
+        Err(e) => Err(ToolError::external(format!("Cannot stop process group {}: {}", pid, e)).into()),
+    }
+}
+
+#[cfg(not(unix))]
+fn signal(_pid: u32, _kill: bool) -> Result<()> {
+    Err(ToolError::unsupported("stopping services is not supported on this platform").into())
+}
@@ -6,22 +6,56 @@
 /// - ps: List processes
 /// - kill: Kill process
 /// - logs: Get process logs
+/// - input: Write to the stdin of a process started with keep_stdin
+/// - start/stop/restart/status: Supervise named long-running services
+/// - ports/free_port/kill_port: Inspect and free listening TCP ports
+///
+/// Commands run in one of two modes: `shell` hands a command string to the
+/// shell with `-c`, so pipes, globs and variables work; `direct` runs an
+/// argv array as is, with nothing expanded or reinterpreted. Arrays run
+/// directly unless told otherwise.
+///
+/// `ExecConfig` sets where commands without a cwd run (the session's
+/// project root by default), what environment they inherit and which
+/// toolchain directories are added to PATH (see `exec_env`).
 
 use anyhow::{anyhow, Result};
+use crate::config::{DefaultCwd, ExecConfig};
+use crate::error::ToolError;
+use super::exec_env::Environment;
+use super::exec_ports;
+use super::exec_service::{Probe, RestartPolicy, ServiceSpec, Services};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::PathBuf;
 use std::process::Stdio;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{ChildStdin, Command};
+use tokio::sync::{mpsc, Mutex, RwLock};
 
 /// Auto-background timeout in seconds
 const AUTO_BACKGROUND_TIMEOUT: u64 = 45;
 
+/// Restarts a service gets unless it asks for more or fewer
+const DEFAULT_MAX_RESTARTS: u32 = 5;
+
+/// Seconds start waits for a service's readiness probe
+const DEFAULT_READY_TIMEOUT: u64 = 30;
+const MAX_READY_TIMEOUT: u64 = 600;
+
+/// Most retries one exec call may ask for
+const MAX_RETRIES: u32 = 10;
+
+/// Longest wait between attempts, however far the backoff has grown
+const MAX_BACKOFF_MS: u64 = 60_000;
+
+/// Stderr kept per attempt in the attempts report
+const ATTEMPT_STDERR_CHARS: usize = 500;
+
 /// Process info tracked by the manager
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
@@ -34,10 +68,14 @@ pub struct ProcessInfo {
     pub log_file: Option<PathBuf>,
 }
 
-/// Process manager singleton
+/// Process manager singleton; clones share its state
+#[derive(Clone)]
 pub struct ProcessManager {
     processes: Arc<RwLock<HashMap<String, ProcessInfo>>>,
     counter: Arc<RwLock<u64>>,
+    /// Open stdin of processes started interactively, by proc_id
+    stdins: Arc<Mutex<HashMap<String, ChildStdin>>>,
+    services: Services,
 }
 
 impl ProcessManager {
@@ -45,9 +83,16 @@ impl ProcessManager {
         Self {
             processes: Arc::new(RwLock::new(HashMap::new())),
             counter: Arc::new(RwLock::new(0)),
+            stdins: Arc::new(Mutex::new(HashMap::new())),
+            services: Services::default(),
         }
     }
 
+    /// Named long-running services whose processes this manager runs
+    pub fn services(&self) -> &Services {
+        &self.services
+    }
+
     async fn next_id(&self) -> String {
         let mut counter = self.counter.write().await;
         *counter += 1;
@@ -74,6 +119,154 @@ impl ProcessManager {
     pub async fn get(&self, proc_id: &str) -> Option<ProcessInfo> {
         self.processes.read().await.get(proc_id).cloned()
     }
+
+    /// Start `cmd`, appending its stdout and stderr lines to a log file as
+    /// they arrive, so `logs` can follow it while it runs; the process is
+    /// marked finished once it exits and its output is flushed
+    pub async fn spawn_logged(&self, cmd: Command, command: String) -> Result<ProcessInfo> {
+        self.start_logged(cmd, command, false).await
+    }
+
+    /// Like `spawn_logged`, keeping the process's stdin open for
+    /// `write_stdin`
+    pub async fn spawn_interactive(&self, cmd: Command, command: String) -> Result<ProcessInfo> {
+        self.start_logged(cmd, command, true).await
+    }
+
+    /// Run `task` in the background like a process with no pid: the lines it
+    /// sends go to a log file `logs` follows, and the code it returns is
+    /// its exit code
+    pub async fn spawn_task<F>(&self, command: String, task: impl FnOnce(mpsc::UnboundedSender<String>) -> F) -> Result<ProcessInfo>
+    where
+        F: Future<Output = i32> + Send + 'static,
+    {
+        let proc_id = self.next_id().await;
+        let log_file = Self::log_file(&proc_id).await?;
+        let mut log = tokio::fs::File::create(&log_file).await?;
+        let (lines, mut received) = mpsc::unbounded_channel::<String>();
+        let pump = tokio::spawn(async move {
+            while let Some(line) = received.recv().await {
+                if log.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            let _ = log.flush().await;
+        });
+
+        let info = ProcessInfo {
+            proc_id: proc_id.clone(),
+            pid: None,
+            command,
+            running: true,
+            exit_code: None,
+            started: chrono::Utc::now().to_rfc3339(),
+            log_file: Some(log_file),
+        };
+        self.register(info.clone()).await;
+
+        let work = task(lines);
+        let processes = self.processes.clone();
+        tokio::spawn(async move {
+            let code = work.await;
+            let _ = pump.await;
+            if let Some(info) = processes.write().await.get_mut(&proc_id) {
+                info.running = false;
+                info.exit_code = Some(code);
+                crate::events::publish(crate::events::PROCESS_EXITED, json!({
+                    "proc_id": proc_id,
+                    "pid": info.pid,
+                    "command": info.command,
+                    "exit_code": info.exit_code
+                }));
+            }
+        });
+        Ok(info)
+    }
+
+    async fn log_file(proc_id: &str) -> Result<PathBuf> {
+        let log_dir = std::env::temp_dir().join("hanzo-mcp-logs");
+        tokio::fs::create_dir_all(&log_dir).await?;
+        Ok(log_dir.join(format!("{}-{}.log", std::process::id(), proc_id)))
+    }
+
+    /// Write to the stdin of a process started with `spawn_interactive`,
+    /// closing it afterwards when `close` is set
+    pub async fn write_stdin(&self, proc_id: &str, input: &[u8], close: bool) -> Result<()> {
+        let mut stdins = self.stdins.lock().await;
+        let stdin = stdins.get_mut(proc_id)
+            .ok_or_else(|| ToolError::not_found(format!("No open stdin for {}", proc_id)))?;
+        let written = async {
+            stdin.write_all(input).await?;
+            stdin.flush().await
+        }.await;
+        if close || written.is_err() {
+            stdins.remove(proc_id);
+        }
+        written.map_err(|e| ToolError::external(format!("Cannot write to {}: {}", proc_id, e)).into())
+    }
+
+    async fn start_logged(&self, mut cmd: Command, command: String, interactive: bool) -> Result<ProcessInfo> {
+        let proc_id = self.next_id().await;
+        let log_file = Self::log_file(&proc_id).await?;
+        let log = Arc::new(Mutex::new(tokio::fs::File::create(&log_file).await?));
+
+        cmd.stdin(if interactive { Stdio::piped() } else { Stdio::null() });
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = cmd.spawn()
+            .map_err(|e| ToolError::external(format!("Cannot start {}: {}", command, e)))?;
+        if let Some(stdin) = child.stdin.take() {
+            self.stdins.lock().await.insert(proc_id.clone(), stdin);
+        }
+        let pumps = [
+            child.stdout.take().map(|out| Self::pump(out, log.clone())),
+            child.stderr.take().map(|err| Self::pump(err, log.clone())),
+        ];
+
+        let info = ProcessInfo {
+            proc_id: proc_id.clone(),
+            pid: child.id(),
+            command,
+            running: true,
+            exit_code: None,
+            started: chrono::Utc::now().to_rfc3339(),
+            log_file: Some(log_file),
+        };
+        self.register(info.clone()).await;
+
+        let processes = self.processes.clone();
+        let stdins = self.stdins.clone();
+        tokio::spawn(async move {
+            let status = child.wait().await;
+            stdins.lock().await.remove(&proc_id);
+            for pump in pumps.into_iter().flatten() {
+                let _ = pump.await;
+            }
+            let _ = log.lock().await.flush().await;
+            if let Some(info) = processes.write().await.get_mut(&proc_id) {
+                info.running = false;
+                info.exit_code = Some(status.ok().and_then(|s| s.code()).unwrap_or(-1));
+                crate::events::publish(crate::events::PROCESS_EXITED, json!({
+                    "proc_id": proc_id,
+                    "pid": info.pid,
+                    "command": info.command,
+                    "exit_code": info.exit_code
+                }));
+            }
+        });
+        Ok(info)
+    }
+
+    fn pump(stream: impl AsyncRead + Unpin + Send + 'static, log: Arc<Mutex<tokio::fs::File>>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stream).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let mut log = log.lock().await;
+                if log.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
 }
 
 /// Actions for the proc tool
@@ -85,6 +278,14 @@ pub enum ProcAction {
     Ps,
     Kill,
     Logs,
+    Input,
+    Start,
+    Stop,
+    Restart,
+    Status,
+    Ports,
+    FreePort,
+    KillPort,
     Help,
 }
 
@@ -104,8 +305,47 @@ impl std::str::FromStr for ProcAction {
             "ps" | "list" => Ok(Self::Ps),
             "kill" => Ok(Self::Kill),
             "logs" | "log" => Ok(Self::Logs),
+            "input" | "write" | "stdin" => Ok(Self::Input),
+            "start" | "up" => Ok(Self::Start),
+            "stop" | "down" => Ok(Self::Stop),
+            "restart" => Ok(Self::Restart),
+            "status" | "services" => Ok(Self::Status),
+            "ports" | "listening" => Ok(Self::Ports),
+            "free_port" | "freeport" => Ok(Self::FreePort),
+            "kill_port" | "killport" => Ok(Self::KillPort),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+/// How exec starts a command
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecMode {
+    /// Through the shell with `-c`
+    Shell,
+    /// The argv array itself, without a shell
+    Direct,
+}
+
+impl std::str::FromStr for ExecMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "shell" | "sh" => Ok(Self::Shell),
+            "direct" | "argv" => Ok(Self::Direct),
+            _ => Err(ToolError::invalid(format!("Unknown mode: {} (shell, direct)", s)).into()),
+        }
+    }
+}
+
+impl ExecMode {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Shell => "shell",
+            Self::Direct => "direct",
         }
     }
 }
@@ -127,6 +367,8 @@ pub struct ExecToolArgs {
     pub timeout: Option<u64>,
     /// Shell to use
     pub shell: Option<String>,
+    /// shell or direct; arrays default to direct, strings to shell
+    pub mode: Option<String>,
     /// Process ID for wait/kill/logs
     pub proc_id: Option<String>,
     /// Timeout in milliseconds for wait
@@ -137,22 +379,78 @@ pub struct ExecToolArgs {
     pub tail: Option<usize>,
     /// Filter for ps
     pub filter: Option<String>,
+    /// Report the command that would run without running it
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Text piped to the command's stdin
+    pub stdin: Option<String>,
+    /// Bytes piped to the command's stdin, base64 encoded
+    pub stdin_base64: Option<String>,
+    /// Leave stdin open: exec starts the command in the background for
+    /// later input calls; input keeps it open unless false
+    pub keep_stdin: Option<bool>,
+    /// Times to re-run a failed command
+    pub retries: Option<u32>,
+    /// Failures worth retrying: exit codes and/or stderr regexes; any
+    /// non-zero exit when unset
+    pub retry_on: Option<Value>,
+    /// Wait before the first retry in milliseconds
+    pub backoff_ms: Option<u64>,
+    /// Multiplier applied to the wait after each retry
+    pub backoff_factor: Option<f64>,
+    /// Service name for start, stop, restart and status
+    pub name: Option<String>,
+    /// Service is ready once this local port accepts connections
+    pub ready_port: Option<u16>,
+    /// Service is ready once a line of output matches this regex
+    pub ready_log: Option<String>,
+    /// Seconds start waits for the service to be ready
+    pub ready_timeout: Option<u64>,
+    /// never, on-failure or always
+    pub restart_policy: Option<String>,
+    /// Restarts allowed before the service is left down
+    pub max_restarts: Option<u32>,
+    /// Port for ports (filter), free_port (first to try) and kill_port
+    pub port: Option<u16>,
+    /// Project root of the calling session, set by the server rather than
+    /// the client; the default cwd
+    #[serde(skip)]
+    pub root: Option<PathBuf>,
 }
 
 /// Shell execution tool
 pub struct ExecTool {
     manager: Arc<ProcessManager>,
     shell: String,
+    config: ExecConfig,
+    env: Environment,
 }
 
 impl ExecTool {
     pub fn new() -> Self {
+        Self::with_config(ExecConfig::default())
+    }
+
+    pub fn with_config(config: ExecConfig) -> Self {
         Self {
             manager: Arc::new(ProcessManager::new()),
             shell: Self::resolve_shell(),
+            env: Environment::new(&config),
+            config,
         }
     }
 
+    /// Track processes in `manager`, shared with other tools
+    pub fn with_manager(mut self, manager: Arc<ProcessManager>) -> Self {
+        self.manager = manager;
+        self
+    }
+
+    /// Process manager shared with tools that start their own processes
+    pub fn manager(&self) -> Arc<ProcessManager> {
+        self.manager.clone()
+    }
+
     fn resolve_shell() -> String {
         // Check environment override
         if let Ok(shell) = std::env::var("HANZO_MCP_FORCE_SHELL") {
@@ -188,55 +486,173 @@ impl ExecTool {
             ProcAction::Ps => self.ps(args).await?,
             ProcAction::Kill => self.kill(args).await?,
             ProcAction::Logs => self.logs(args).await?,
+            ProcAction::Input => self.input(args).await?,
+            ProcAction::Start => self.start(args).await?,
+            ProcAction::Stop => self.stop(args).await?,
+            ProcAction::Restart => self.restart(args).await?,
+            ProcAction::Status => self.status(args).await?,
+            ProcAction::Ports => self.ports(args).await?,
+            ProcAction::FreePort => json!({ "port": exec_ports::free_port(args.port)? }),
+            ProcAction::KillPort => self.kill_port(args).await?,
             ProcAction::Help => self.help()?,
         };
 
         Ok(serde_json::to_string(&result)?)
     }
 
+    /// Run the command, re-running it while it fails in a way `retry_on`
+    /// accepts and retries remain
     async fn exec(&self, args: ExecToolArgs) -> Result<Value> {
-        let command = args.command.ok_or_else(|| anyhow!("command required"))?;
+        let Some(policy) = RetryPolicy::new(&args)? else {
+            return self.run(args).await;
+        };
+        if args.keep_stdin == Some(true) {
+            return Err(ToolError::invalid("retries cannot be combined with keep_stdin").into());
+        }
+        if args.dry_run {
+            let mut result = self.run(args).await?;
+            result["retry"] = policy.describe();
+            return Ok(result);
+        }
+
+        let mut attempts = Vec::new();
+        let mut delay = policy.backoff_ms;
+        loop {
+            let mut result = self.run(args.clone()).await?;
+            let exit_code = result["exit_code"].as_i64();
+            let stderr = result["stderr"].as_str().unwrap_or_default();
+            let tail_at = stderr.char_indices().rev().nth(ATTEMPT_STDERR_CHARS - 1).map_or(0, |(at, _)| at);
+            attempts.push(json!({
+                "attempt": attempts.len() + 1,
+                "proc_id": result["proc_id"],
+                "exit_code": exit_code,
+                "duration_ms": result["duration_ms"],
+                "stderr_tail": &stderr[tail_at..]
+            }));
+            // Backgrounded commands are still running, so there is
+            // nothing to judge yet
+            let retry = exit_code.is_some_and(|code| code != 0 && policy.matches(code, stderr))
+                && attempts.len() <= policy.retries as usize;
+            if !retry {
+                result["attempts"] = json!(attempts);
+                result["retried"] = json!(attempts.len() - 1);
+                return Ok(result);
+            }
+            log::info!("Retrying after exit {} in {}ms ({}/{})", exit_code.unwrap_or(-1), delay, attempts.len(), policy.retries);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            delay = ((delay as f64 * policy.backoff_factor) as u64).min(MAX_BACKOFF_MS);
+        }
+    }
 
-        // Support both string and array format
-        let cmd_str = match command {
-            Value::String(s) => s,
+    /// Resolve the command, cwd and env exec args describe
+    fn launch(&self, args: &ExecToolArgs) -> Result<Launch> {
+        let command = args.command.clone().ok_or_else(|| ToolError::invalid("command required"))?;
+
+        // Support both string and array format; display is the array
+        // quoted for a shell, which is also how it is shown
+        let (display, words) = match command {
+            Value::String(s) => (s, None),
             Value::Array(arr) => {
-                // Join array into shell command
-                arr.iter()
-                    .filter_map(|v| v.as_str())
+                let words = arr.iter()
+                    .map(|v| v.as_str().map(str::to_string))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| ToolError::invalid("command array must hold strings"))?;
+                let quoted = words.iter()
                     .map(|s| shell_escape::escape(s.into()).to_string())
                     .collect::<Vec<_>>()
-                    .join(" ")
+                    .join(" ");
+                (quoted, Some(words))
             }
-            _ => return Err(anyhow!("command must be string or array")),
+            _ => return Err(ToolError::invalid("command must be string or array").into()),
+        };
+        let mode = match args.mode.as_deref() {
+            Some(mode) => mode.parse()?,
+            None if words.is_some() => ExecMode::Direct,
+            None => ExecMode::Shell,
         };
 
-        let cwd = args.workdir.or(args.cwd);
-        let timeout = args.timeout.unwrap_or(AUTO_BACKGROUND_TIMEOUT);
-        let shell = args.shell.unwrap_or_else(|| self.shell.clone());
-
-        let proc_id = self.manager.next_id().await;
-        let started = chrono::Utc::now().to_rfc3339();
+        let cwd = args.workdir.clone().or(args.cwd.clone()).or_else(|| match self.config.default_cwd {
+            DefaultCwd::Root => args.root.as_ref().map(|root| root.display().to_string()),
+            DefaultCwd::Server => None,
+        });
+        let shell = match mode {
+            ExecMode::Shell => Some(args.shell.clone().unwrap_or_else(|| self.shell.clone())),
+            ExecMode::Direct => None,
+        };
+        let argv = match &shell {
+            Some(shell) => vec![shell.clone(), "-c".to_string(), display.clone()],
+            None => words.filter(|w| !w.is_empty())
+                .ok_or_else(|| ToolError::invalid("direct mode runs an argv array: pass command as [program, args...]"))?,
+        };
+        Ok(Launch { display, mode, shell, argv, cwd, env: args.env.clone().unwrap_or_default() })
+    }
 
-        // Build command
-        let mut cmd = Command::new(&shell);
-        cmd.arg("-c").arg(&cmd_str);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+    async fn run(&self, args: ExecToolArgs) -> Result<Value> {
+        let input = stdin_bytes(&args)?;
+        let keep_stdin = args.keep_stdin.unwrap_or(false);
+        let launch = self.launch(&args)?;
+        let mut cmd = launch.command(&self.env);
+        let Launch { display: cmd_str, mode, shell, argv, cwd, env } = launch;
+        let timeout = args.timeout.unwrap_or(AUTO_BACKGROUND_TIMEOUT);
 
-        if let Some(ref dir) = cwd {
-            cmd.current_dir(dir);
+        if args.dry_run {
+            let mut env: Vec<&String> = env.keys().collect();
+            env.sort();
+            return Ok(json!({
+                "dry_run": true,
+                "command": cmd_str,
+                "mode": mode.name(),
+                "shell": shell,
+                "argv": argv,
+                "cwd": cwd.or_else(|| std::env::current_dir().ok().map(|d| d.display().to_string())),
+                "env": env,
+                "stdin_bytes": input.as_ref().map(Vec::len),
+                "keep_stdin": keep_stdin,
+                "timeout": timeout
+            }));
         }
 
-        if let Some(ref env_vars) = args.env {
-            for (k, v) in env_vars {
-                cmd.env(k, v);
+        // Session-style processes run in the background, taking further
+        // input through the input action
+        if keep_stdin {
+            let info = self.manager.spawn_interactive(cmd, cmd_str).await?;
+            if let Some(input) = input.as_deref().filter(|i| !i.is_empty()) {
+                self.manager.write_stdin(&info.proc_id, input, false).await?;
             }
+            return Ok(json!({
+                "proc_id": info.proc_id,
+                "pid": info.pid,
+                "exit_code": null,
+                "status": "running",
+                "stdin": "open",
+                "mode": mode.name(),
+                "shell": shell,
+                "message": format!("Started with stdin open. Use exec(action='input', proc_id='{}') to write and proc(action='logs') to read output.", info.proc_id)
+            }));
         }
 
+        let proc_id = self.manager.next_id().await;
+        let started = chrono::Utc::now().to_rfc3339();
+        // Never hand the command the server's own stdin, which carries the
+        // MCP transport
+        cmd.stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() });
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
         let start = Instant::now();
-        let mut child = cmd.spawn()?;
+        let mut child = cmd.spawn().map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => ToolError::not_found(format!("Program not found: {}", argv[0])),
+            _ => ToolError::external(format!("Cannot start {}: {}", argv[0], e)),
+        })?;
         let pid = child.id();
+        // Written from a task so a command that fills its stdout before
+        // reading all of its input cannot deadlock; dropping the handle
+        // closes stdin
+        if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+            tokio::spawn(async move {
+                let _ = stdin.write_all(&input).await;
+            });
+        }
 
         // Register process
         self.manager.register(ProcessInfo {
@@ -269,18 +685,23 @@ impl ExecTool {
                     "stdout": stdout,
                     "stderr": stderr,
                     "duration_ms": duration_ms,
-                    "status": if exit_code == 0 { "success" } else { "failed" }
+                    "status": if exit_code == 0 { "success" } else { "failed" },
+                    "mode": mode.name(),
+                    "shell": shell
                 }))
             }
-            Ok(Err(e)) => Err(anyhow!("Process failed: {}", e)),
+            Ok(Err(e)) => Err(ToolError::external(format!("Process failed: {}", e)).into()),
             Err(_) => {
                 // Timeout - process is backgrounded
+                log::warn!("Command backgrounded after {}s as {}: {}", timeout, proc_id, cmd_str);
                 Ok(json!({
                     "proc_id": proc_id,
                     "exit_code": null,
                     "stdout_ref": format!("proc:{}:stdout", proc_id),
                     "stderr_ref": format!("proc:{}:stderr", proc_id),
                     "status": "running",
+                    "mode": mode.name(),
+                    "shell": shell,
                     "message": format!("Command backgrounded after {}s. Use proc(action='logs', proc_id='{}') to view output.", timeout, proc_id)
                 }))
             }
@@ -288,7 +709,7 @@ impl ExecTool {
     }
 
     async fn wait(&self, args: ExecToolArgs) -> Result<Value> {
-        let proc_id = args.proc_id.ok_or_else(|| anyhow!("proc_id required"))?;
+        let proc_id = args.proc_id.ok_or_else(|| ToolError::invalid("proc_id required"))?;
 
         let max_timeout_ms = 3_600_000u64; // 1 hour
         let default_timeout_ms = 600_000u64; // 10 minutes
@@ -296,7 +717,7 @@ impl ExecTool {
         let timeout_sec = timeout_ms as f64 / 1000.0;
 
         let info = self.manager.get(&proc_id).await
-            .ok_or_else(|| anyhow!("Process not found: {}", proc_id))?;
+            .ok_or_else(|| ToolError::not_found(format!("Process not found: {}", proc_id)))?;
 
         // If already completed, return immediately
         if !info.running {
@@ -334,7 +755,7 @@ impl ExecTool {
                     }));
                 }
             } else {
-                return Err(anyhow!("Process disappeared: {}", proc_id));
+                return Err(ToolError::not_found(format!("Process disappeared: {}", proc_id)).into());
             }
 
             tokio::time::sleep(poll_interval).await;
@@ -377,21 +798,14 @@ impl ExecTool {
     }
 
     async fn kill(&self, args: ExecToolArgs) -> Result<Value> {
-        let proc_id = args.proc_id.ok_or_else(|| anyhow!("proc_id required"))?;
+        let proc_id = args.proc_id.ok_or_else(|| ToolError::invalid("proc_id required"))?;
 
         let info = self.manager.get(&proc_id).await
-            .ok_or_else(|| anyhow!("Process not found: {}", proc_id))?;
+            .ok_or_else(|| ToolError::not_found(format!("Process not found: {}", proc_id)))?;
 
         let pid = info.pid.ok_or_else(|| anyhow!("Process has no PID"))?;
 
-        // Resolve signal
-        let sig = match args.signal.as_deref() {
-            Some("KILL") | Some("9") => 9,
-            Some("INT") | Some("2") => 2,
-            Some("HUP") | Some("1") => 1,
-            Some("QUIT") | Some("3") => 3,
-            _ => 15, // TERM
-        };
+        let sig = signal_number(args.signal.as_deref());
 
         #[cfg(unix)]
         {
@@ -413,21 +827,67 @@ impl ExecTool {
                     "killed": false,
                     "message": "Process already terminated"
                 })),
-                Err(e) => Err(anyhow!("Cannot kill process: {}", e)),
+                Err(e) => Err(ToolError::external(format!("Cannot kill process: {}", e)).into()),
             }
         }
 
         #[cfg(not(unix))]
         {
-            Err(anyhow!("kill not supported on this platform"))
+            Err(ToolError::unsupported("kill not supported on this platform").into())
+        }
+    }
+
+    /// Listening TCP sockets, with the proc_id of those exec started
+    async fn ports(&self, args: ExecToolArgs) -> Result<Value> {
+        let processes = self.manager.list().await;
+        let listeners: Vec<Value> = exec_ports::listeners().await?.into_iter()
+            .filter(|l| args.port.is_none_or(|port| l.port == port))
+            .map(|l| {
+                let proc_id = processes.values().find(|p| p.running && p.pid.is_some() && p.pid == l.pid).map(|p| p.proc_id.clone());
+                let mut listener = json!(l);
+                listener["proc_id"] = json!(proc_id);
+                listener
+            })
+            .collect();
+        Ok(json!({
+            "listeners": listeners,
+            "total": listeners.len()
+        }))
+    }
+
+    /// Signal every process listening on a port
+    async fn kill_port(&self, args: ExecToolArgs) -> Result<Value> {
+        let port = args.port.ok_or_else(|| ToolError::invalid("port required"))?;
+        let sig = signal_number(args.signal.as_deref());
+        let mut pids: Vec<u32> = exec_ports::listeners().await?.into_iter()
+            .filter(|l| l.port == port)
+            .filter_map(|l| l.pid)
+            .collect();
+        pids.sort_unstable();
+        pids.dedup();
+        if pids.contains(&std::process::id()) {
+            return Err(ToolError::invalid(format!("Port {} is held by this server", port)).into());
+        }
+        let mut killed = Vec::new();
+        for pid in &pids {
+            if exec_ports::terminate(*pid, sig).await? {
+                killed.push(*pid);
+            }
         }
+        Ok(json!({
+            "port": port,
+            "pids": pids,
+            "killed": killed,
+            "signal": sig,
+            "message": if pids.is_empty() { Some(format!("Nothing visible listens on port {}", port)) } else { None }
+        }))
     }
 
     async fn logs(&self, args: ExecToolArgs) -> Result<Value> {
-        let proc_id = args.proc_id.ok_or_else(|| anyhow!("proc_id required"))?;
+        let proc_id = args.proc_id.ok_or_else(|| ToolError::invalid("proc_id required"))?;
 
         let info = self.manager.get(&proc_id).await
-            .ok_or_else(|| anyhow!("Process not found: {}", proc_id))?;
+            .ok_or_else(|| ToolError::not_found(format!("Process not found: {}", proc_id)))?;
 
         // If log file exists, read it
         if let Some(ref log_file) = info.log_file {
@@ -460,6 +920,104 @@ impl ExecTool {
         }))
     }
 
+    async fn input(&self, args: ExecToolArgs) -> Result<Value> {
+        let proc_id = args.proc_id.clone().ok_or_else(|| ToolError::invalid("proc_id required"))?;
+        let input = stdin_bytes(&args)?.unwrap_or_default();
+        let close = !args.keep_stdin.unwrap_or(true);
+        if input.is_empty() && !close {
+            return Err(ToolError::invalid("stdin, stdin_base64 or keep_stdin=false required").into());
+        }
+        self.manager.write_stdin(&proc_id, &input, close).await?;
+        Ok(json!({
+            "proc_id": proc_id,
+            "written": input.len(),
+            "closed": close
+        }))
+    }
+
+    async fn start(&self, args: ExecToolArgs) -> Result<Value> {
+        let name = args.name.clone().ok_or_else(|| ToolError::invalid("name required"))?;
+        let spec = self.service(args)?;
+        self.manager.services().start(&self.manager, &name, spec).await
+    }
+
+    async fn stop(&self, args: ExecToolArgs) -> Result<Value> {
+        let name = args.name.ok_or_else(|| ToolError::invalid("name required"))?;
+        self.manager.services().stop(&self.manager, &name).await
+    }
+
+    /// Restart as last started, unless the call gives a command or the
+    /// service has only been declared in config
+    async fn restart(&self, args: ExecToolArgs) -> Result<Value> {
+        let name = args.name.clone().ok_or_else(|| ToolError::invalid("name required"))?;
+        let spec = if args.command.is_some() || !self.manager.services().contains(&name).await {
+            Some(self.service(args)?)
+        } else {
+            None
+        };
+        self.manager.services().restart(&self.manager, &name, spec).await
+    }
+
+    /// Started services, then declared ones not started yet
+    async fn status(&self, args: ExecToolArgs) -> Result<Value> {
+        let services = self.manager.services();
+        if let Some(name) = args.name.as_deref() {
+            if !services.contains(name).await {
+                if let Some(declared) = self.config.services.get(name) {
+                    return Ok(json!({ "name": name, "state": "declared", "command": declared.command }));
+                }
+            }
+            return services.status(&self.manager, Some(name)).await;
+        }
+        let mut status = services.status(&self.manager, None).await?;
+        let mut declared: Vec<&String> = self.config.services.keys().collect();
+        declared.sort();
+        for name in declared {
+            if !services.contains(name).await {
+                if let Some(all) = status["services"].as_array_mut() {
+                    all.push(json!({ "name": name, "state": "declared", "command": self.config.services[name].command }));
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    /// The service the args describe, filled in from its declaration in
+    /// config when they give no command
+    fn service(&self, mut args: ExecToolArgs) -> Result<ServiceSpec> {
+        let name = args.name.clone().unwrap_or_default();
+        if args.command.is_none() {
+            let declared = self.config.services.get(&name)
+                .ok_or_else(|| ToolError::not_found(format!("No service named {} is declared; pass command to start one", name)))?;
+            args.command = Some(declared.command.clone());
+            args.cwd = args.cwd.or(declared.cwd.clone());
+            let mut env = declared.env.clone();
+            env.extend(args.env.unwrap_or_default());
+            args.env = Some(env);
+            args.ready_port = args.ready_port.or(declared.ready_port);
+            args.ready_log = args.ready_log.or(declared.ready_log.clone());
+            args.restart_policy = args.restart_policy.or(declared.restart.clone());
+            args.max_restarts = args.max_restarts.or(declared.max_restarts);
+        }
+        let probe = match (args.ready_port, &args.ready_log) {
+            (Some(_), Some(_)) => return Err(ToolError::invalid("pass ready_port or ready_log, not both").into()),
+            (Some(port), None) => Probe::Port(port),
+            (None, Some(pattern)) => Probe::Log(regex::Regex::new(pattern)
+                .map_err(|e| ToolError::invalid(format!("ready_log regex: {}", e)))?),
+            (None, None) => Probe::Running,
+        };
+        let launch = self.launch(&args)?;
+        let env = self.env.clone();
+        Ok(ServiceSpec {
+            command: launch.display.clone(),
+            launch: Box::new(move || launch.command(&env)),
+            probe,
+            restart: args.restart_policy.as_deref().map_or(Ok(RestartPolicy::OnFailure), str::parse)?,
+            max_restarts: args.max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS),
+            ready_timeout: Duration::from_secs(args.ready_timeout.unwrap_or(DEFAULT_READY_TIMEOUT).min(MAX_READY_TIMEOUT)),
+        })
+    }
+
     fn help(&self) -> Result<Value> {
         let shell_name = std::path::Path::new(&self.shell)
             .file_name()
@@ -475,20 +1033,158 @@ impl ExecTool {
                 "wait": "Wait for background process to complete",
                 "ps": "List processes",
                 "kill": "Kill process",
-                "logs": "Get process logs"
+                "logs": "Get process logs",
+                "input": "Write stdin/stdin_base64 to a process started with keep_stdin; keep_stdin=false closes it",
+                "start": "Start a named service (command, or one declared in config) and wait until it is ready",
+                "stop": "Stop a service and its process group",
+                "restart": "Stop and start a service again",
+                "status": "State of one service (name) or all: declared, starting, ready, restarting, exited, failed, stopped",
+                "ports": "List listening TCP ports with pid, program and proc_id (port filters)",
+                "free_port": "Find a free local port: port or the first free one after it, else any",
+                "kill_port": "Signal whatever listens on port (signal, default TERM)"
+            },
+            "services": {
+                "ready": "ready_port (local port accepts connections) or ready_log (an output line matches); running otherwise",
+                "restart_policy": "never, on-failure (default) or always, up to max_restarts (default 5) with growing delays",
+                "declared": self.config.services.keys().collect::<Vec<_>>()
+            },
+            "modes": {
+                "shell": format!("Command string run by {} -c (default for strings)", shell_name),
+                "direct": "argv array run without a shell; nothing is expanded (default for arrays)"
             },
-            "returns": "proc_id, exit_code, stdout, stderr",
+            "environment": {
+                "default_cwd": self.config.default_cwd,
+                "inherit": self.config.env_inherit,
+                "path_added": self.env.added
+            },
+            "stdin": "stdin (text) or stdin_base64 is piped to the command and then closed; keep_stdin=true starts it in the background with stdin left open",
+            "retry": format!("retries (at most {}) re-runs a failed command when its exit code or stderr matches retry_on (any failure when unset), waiting backoff_ms then multiplying the wait by backoff_factor; results list every attempt", MAX_RETRIES),
+            "returns": "proc_id, exit_code, stdout, stderr, mode, shell",
             "auto_background": format!("{}s", AUTO_BACKGROUND_TIMEOUT)
         }))
     }
 }
 
+/// A command resolved from exec args, ready to build
+#[derive(Debug, Clone)]
+struct Launch {
+    /// The command as shown: the string, or the argv quoted for a shell
+    display: String,
+    mode: ExecMode,
+    shell: Option<String>,
+    argv: Vec<String>,
+    cwd: Option<String>,
+    env: HashMap<String, String>,
+}
+
+impl Launch {
+    /// A fresh `Command`; the call's env goes over the configured one
+    fn command(&self, environment: &Environment) -> Command {
+        let mut cmd = Command::new(&self.argv[0]);
+        cmd.args(&self.argv[1..]);
+        if let Some(ref dir) = self.cwd {
+            cmd.current_dir(dir);
+        }
+        environment.apply(&mut cmd);
+        cmd.envs(&self.env);
+        cmd
+    }
+}
+
+/// When a failed exec runs again
+struct RetryPolicy {
+    retries: u32,
+    codes: Vec<i64>,
+    patterns: Vec<regex::Regex>,
+    backoff_ms: u64,
+    backoff_factor: f64,
+}
+
+impl RetryPolicy {
+    /// The policy the args ask for; `None` without retries
+    fn new(args: &ExecToolArgs) -> Result<Option<Self>> {
+        let retries = match args.retries {
+            None | Some(0) => return Ok(None),
+            Some(n) if n > MAX_RETRIES => return Err(ToolError::invalid(format!("retries is at most {}", MAX_RETRIES)).into()),
+            Some(n) => n,
+        };
+        let conditions = match &args.retry_on {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(items)) => items.clone(),
+            Some(item) => vec![item.clone()],
+        };
+        let mut codes = Vec::new();
+        let mut patterns = Vec::new();
+        for condition in conditions {
+            match condition {
+                Value::Number(n) if n.is_i64() => codes.push(n.as_i64().unwrap_or_default()),
+                Value::String(pattern) => patterns.push(regex::Regex::new(&pattern)
+                    .map_err(|e| ToolError::invalid(format!("retry_on regex: {}", e)))?),
+                other => return Err(ToolError::invalid(format!("retry_on takes exit codes and stderr regexes, got {}", other)).into()),
+            }
+        }
+        let backoff_factor = args.backoff_factor.unwrap_or(2.0);
+        if !(1.0..=10.0).contains(&backoff_factor) {
+            return Err(ToolError::invalid("backoff_factor must be between 1 and 10").into());
+        }
+        Ok(Some(Self {
+            retries,
+            codes,
+            patterns,
+            backoff_ms: args.backoff_ms.unwrap_or(1000).min(MAX_BACKOFF_MS),
+            backoff_factor,
+        }))
+    }
+
+    /// Whether a failure is worth retrying: any listed exit code or
+    /// stderr pattern matches, or anything when none are listed
+    fn matches(&self, exit_code: i64, stderr: &str) -> bool {
+        (self.codes.is_empty() && self.patterns.is_empty())
+            || self.codes.contains(&exit_code)
+            || self.patterns.iter().any(|p| p.is_match(stderr))
+    }
+
+    fn describe(&self) -> Value {
+        json!({
+            "retries": self.retries,
+            "exit_codes": self.codes,
+            "patterns": self.patterns.iter().map(regex::Regex::as_str).collect::<Vec<_>>(),
+            "backoff_ms": self.backoff_ms,
+            "backoff_factor": self.backoff_factor
+        })
+    }
+}
+
+/// Unix signal number for a kill signal name or number; TERM by default
+fn signal_number(name: Option<&str>) -> i32 {
+    match name {
+        Some("KILL") | Some("9") => 9,
+        Some("INT") | Some("2") => 2,
+        Some("HUP") | Some("1") => 1,
+        Some("QUIT") | Some("3") => 3,
+        _ => 15,
+    }
+}
+
+/// Input from `stdin` or `stdin_base64`, if either is given
+fn stdin_bytes(args: &ExecToolArgs) -> Result<Option<Vec<u8>>> {
+    use base64::Engine;
+    match (&args.stdin, &args.stdin_base64) {
+        (Some(_), Some(_)) => Err(ToolError::invalid("pass stdin or stdin_base64, not both").into()),
+        (Some(text), None) => Ok(Some(text.clone().into_bytes())),
+        (None, Some(encoded)) => Ok(Some(base64::engine::general_purpose::STANDARD.decode(encoded.trim())
+            .map_err(|e| ToolError::invalid(format!("stdin_base64: {}", e)))?)),
+        (None, None) => Ok(None),
+    }
+}
+
 /// MCP Tool Definition
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExecToolDefinition {
     pub name: String,
     pub description: String,
     pub input_schema: Value,
+    pub output_schema: Value,
 }
 
 impl ExecToolDefinition {
@@ -504,8 +1200,18 @@ Actions:
 - ps: List processes
 - kill: Kill process
 - logs: Get process logs
-
-Returns: {{proc_id, exit_code, stdout, stderr}}
+- input: Write to a process started with keep_stdin
+- start/stop/restart/status: Named long-running services with a
+  readiness probe and restart policy
+- ports/free_port/kill_port: Listening ports, a free port, or kill what
+  holds one
+
+Command strings run through the shell; argv arrays run directly without
+one (mode: shell | direct to choose). stdin or stdin_base64 is piped to
+the command; keep_stdin leaves it open for later input calls. retries
+re-runs failures matching retry_on with exponential backoff.
+
+Returns: {{proc_id, exit_code, stdout, stderr, mode, shell}}
 Auto-backgrounds commands after {}s."#,
                 AUTO_BACKGROUND_TIMEOUT
             ),
@@ -514,7 +1220,7 @@ Auto-backgrounds commands after {}s."#,
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["exec", "wait", "ps", "kill", "logs", "help"],
+                        "enum": ["exec", "wait", "ps", "kill", "logs", "input", "start", "stop", "restart", "status", "ports", "free_port", "kill_port", "help"],
                         "default": "help",
                         "description": "Action to perform"
                     },
@@ -523,7 +1229,12 @@ Auto-backgrounds commands after {}s."#,
                             {"type": "string"},
                             {"type": "array", "items": {"type": "string"}}
                         ],
-                        "description": "Command to execute (string or array)"
+                        "description": "Command to execute: a shell string, or an argv array run without a shell"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": ["shell", "direct"],
+                        "description": "shell runs the command with the shell's -c (arrays are quoted); direct runs an argv array with no shell, so spaces, globs and $ reach the program verbatim. Defaults to direct for arrays, shell for strings"
                     },
                     "cwd": {"type": "string", "description": "Working directory"},
                     "workdir": {"type": "string", "description": "Alias for cwd (Rust parity)"},
@@ -532,13 +1243,50 @@ Auto-backgrounds commands after {}s."#,
                         "additionalProperties": {"type": "string"},
                         "description": "Environment variables"
                     },
+                    "stdin": {"type": "string", "description": "Text piped to the command's stdin (exec) or written to a running process (input)"},
+                    "stdin_base64": {"type": "string", "description": "Like stdin, for binary input"},
+                    "keep_stdin": {"type": "boolean", "description": "exec: start in the background with stdin left open (default false). input: false closes stdin after writing (default true)"},
+                    "retries": {"type": "integer", "minimum": 0, "maximum": MAX_RETRIES, "description": "Re-run a failed command up to this many times; the result lists every attempt"},
+                    "retry_on": {
+                        "oneOf": [
+                            {"type": "integer"},
+                            {"type": "string"},
+                            {"type": "array", "items": {"type": ["integer", "string"]}}
+                        ],
+                        "description": "Failures to retry: exit codes and/or regexes matched against stderr. Any non-zero exit when unset"
+                    },
+                    "backoff_ms": {"type": "integer", "minimum": 0, "default": 1000, "description": "Wait before the first retry"},
+                    "backoff_factor": {"type": "number", "minimum": 1, "maximum": 10, "default": 2, "description": "Multiplier for the wait after each retry"},
+                    "name": {"type": "string", "description": "Service name for start, stop, restart and status"},
+                    "ready_port": {"type": "integer", "minimum": 1, "maximum": 65535, "description": "start: ready once this local port accepts connections"},
+                    "ready_log": {"type": "string", "description": "start: ready once an output line matches this regex"},
+                    "ready_timeout": {"type": "integer", "default": DEFAULT_READY_TIMEOUT, "description": "start: seconds to wait for readiness"},
+                    "restart_policy": {"type": "string", "enum": ["never", "on-failure", "always"], "default": "on-failure", "description": "When an exited service starts again"},
+                    "max_restarts": {"type": "integer", "minimum": 0, "default": DEFAULT_MAX_RESTARTS, "description": "Restarts before the service is left down"},
                     "timeout": {"type": "integer", "description": "Timeout in seconds"},
                     "shell": {"type": "string", "description": "Shell to use"},
                     "proc_id": {"type": "string", "description": "Process ID"},
                     "timeout_ms": {"type": "integer", "description": "Wait timeout in milliseconds"},
                     "signal": {"type": "string", "description": "Kill signal"},
+                    "port": {"type": "integer", "minimum": 0, "maximum": 65535, "description": "ports: only this port. free_port: first port to try. kill_port: port to free"},
                     "tail": {"type": "integer", "description": "Number of log lines"},
-                    "filter": {"type": "string", "description": "Filter for ps"}
+                    "filter": {"type": "string", "description": "Filter for ps"},
+                    "dry_run": {"type": "boolean", "description": "Return the resolved command instead of running it", "default": false},
+                    "max_bytes": {"type": "integer", "minimum": 1, "description": "Cap on the result size; longer output is cut (head and tail of logs, leading results of searches) with a cursor for the rest"},
+                    "max_output_tokens": {"type": "integer", "minimum": 1, "description": "Like max_bytes, counting about 4 bytes per token"}
+                }
+            }),
+            output_schema: json!({
+                "type": "object",
+                "description": "Fields depend on the action",
+                "properties": {
+                    "proc_id": {"type": "string"},
+                    "status": {"type": "string"},
+                    "exit_code": {"type": ["integer", "null"]},
+                    "stdout": {"type": "string"},
+                    "stderr": {"type": "string"},
+                    "duration_ms": {"type": "integer"},
+                    "processes": {"type": "array", "items": {"type": "object"}, "description": "For ps: tracked processes"}
                 }
             }),
         }
@@ -580,6 +1328,234 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_exec_direct() {
+        let tool = ExecTool::new();
+        let run = |command: Value, mode: Option<&str>| ExecToolArgs {
+            action: "exec".to_string(),
+            command: Some(command),
+            mode: mode.map(str::to_string),
+            ..Default::default()
+        };
+
+        let direct: Value = serde_json::from_str(&tool.execute(run(json!(["echo", "a  b", "*", "$HOME"]), None)).await.unwrap()).unwrap();
+        assert_eq!(direct["mode"], "direct");
+        assert!(direct["shell"].is_null());
+        assert_eq!(direct["stdout"], "a  b * $HOME\n");
+
+        let shell: Value = serde_json::from_str(&tool.execute(run(json!("echo $((1 + 2))"), None)).await.unwrap()).unwrap();
+        assert_eq!(shell["mode"], "shell");
+        assert_eq!(shell["stdout"], "3\n");
+        assert!(shell["shell"].is_string());
+        let quoted: Value = serde_json::from_str(&tool.execute(run(json!(["echo", "$HOME"]), Some("shell"))).await.unwrap()).unwrap();
+        assert_eq!(quoted["stdout"], "$HOME\n");
+
+        assert!(tool.execute(run(json!("echo hi"), Some("direct"))).await.is_err());
+        assert!(tool.execute(run(json!([]), None)).await.is_err());
+        let missing = tool.execute(run(json!(["hanzo-no-such-program"]), None)).await.unwrap_err();
+        assert_eq!(ToolError::classify(&missing).code(), "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_exec_retry() {
+        let tool = ExecTool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let counter = dir.path().join("count");
+        // Fails with "busy" twice, then succeeds
+        let flaky = format!("echo x >> {0}; test $(wc -l < {0}) -ge 3 || {{ echo registry busy >&2; exit 75; }}", counter.display());
+        let run = |extra: Value| {
+            let mut args = json!({ "action": "exec", "command": flaky, "backoff_ms": 10 });
+            args.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            let args: ExecToolArgs = serde_json::from_value(args).unwrap();
+            async { serde_json::from_str::<Value>(&tool.execute(args).await.unwrap()).unwrap() }
+        };
+
+        let result = run(json!({ "retries": 3, "retry_on": "busy" })).await;
+        assert_eq!(result["exit_code"], 0);
+        assert_eq!(result["retried"], 2);
+        let attempts = result["attempts"].as_array().unwrap();
+        assert_eq!(attempts.len(), 3);
+        assert_eq!(attempts[0]["exit_code"], 75);
+        assert_eq!(attempts[0]["stderr_tail"], "registry busy\n");
+
+        std::fs::remove_file(&counter).unwrap();
+        let unmatched = run(json!({ "retries": 3, "retry_on": [1, "timeout"] })).await;
+        assert_eq!(unmatched["exit_code"], 75);
+        assert_eq!(unmatched["retried"], 0);
+        std::fs::remove_file(&counter).unwrap();
+        let exhausted = run(json!({ "retries": 1, "retry_on": [75] })).await;
+        assert_eq!(exhausted["exit_code"], 75);
+        assert_eq!(exhausted["attempts"].as_array().unwrap().len(), 2);
+
+        let planned = run(json!({ "retries": 2, "dry_run": true })).await;
+        assert_eq!(planned["retry"]["retries"], 2);
+        let invalid = ExecToolArgs { retries: Some(2), retry_on: Some(json!("(")), command: Some(json!("true")), action: "exec".into(), ..Default::default() };
+        assert!(tool.execute(invalid).await.is_err());
+        let too_many = ExecToolArgs { retries: Some(MAX_RETRIES + 1), command: Some(json!("true")), action: "exec".into(), ..Default::default() };
+        assert!(tool.execute(too_many).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_services() {
+        let declared = crate::config::ServiceConfig { command: json!(["sleep", "60"]), restart: Some("never".into()), ..Default::default() };
+        let tool = ExecTool::with_config(ExecConfig { services: HashMap::from([("db".to_string(), declared)]), ..Default::default() });
+        let call = |args: Value| {
+            let args: ExecToolArgs = serde_json::from_value(args).unwrap();
+            async { tool.execute(args).await.map(|out| serde_json::from_str::<Value>(&out).unwrap()) }
+        };
+
+        let web = call(json!({ "action": "start", "name": "web", "command": "echo booting; sleep 0.3; echo listening on 8080; sleep 60", "ready_log": "listening on \\d+" })).await.unwrap();
+        assert_eq!(web["state"], "ready");
+        assert_eq!(web["restart"], "on-failure");
+        let again = call(json!({ "action": "start", "name": "web", "command": "true" })).await.unwrap_err();
+        assert_eq!(ToolError::classify(&again).code(), "conflict");
+
+        let all = call(json!({ "action": "status" })).await.unwrap();
+        let states: Vec<(&str, &str)> = all["services"].as_array().unwrap().iter()
+            .map(|s| (s["name"].as_str().unwrap(), s["state"].as_str().unwrap()))
+            .collect();
+        assert_eq!(states, [("web", "ready"), ("db", "declared")]);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let api = call(json!({ "action": "up", "name": "api", "command": ["sleep", "60"], "ready_port": port })).await.unwrap();
+        assert_eq!(api["state"], "ready");
+        assert_eq!(api["probe"]["port"], port);
+
+        let db = call(json!({ "action": "start", "name": "db" })).await.unwrap();
+        assert_eq!(db["command"], "sleep 60");
+        let restarted = call(json!({ "action": "restart", "name": "db" })).await.unwrap();
+        assert_eq!(restarted["state"], "ready");
+        assert_ne!(restarted["proc_id"], db["proc_id"]);
+
+        let flaky = call(json!({ "action": "start", "name": "flaky", "command": "sleep 0.2; exit 3", "restart_policy": "always", "max_restarts": 1 })).await.unwrap();
+        assert_eq!(flaky["state"], "ready");
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        let flaky = call(json!({ "action": "status", "name": "flaky" })).await.unwrap();
+        assert_eq!(flaky["restarts"], 1);
+        assert_eq!(flaky["state"], "failed");
+        assert_eq!(flaky["exit_code"], 3);
+
+        let early = call(json!({ "action": "start", "name": "early", "command": "exit 2", "ready_log": "never" })).await.unwrap_err();
+        assert!(early.to_string().contains("exited with 2"));
+
+        for name in ["web", "api", "db"] {
+            let stopped = call(json!({ "action": "stop", "name": name })).await.unwrap();
+            assert_eq!(stopped["state"], "stopped");
+        }
+        let ps = call(json!({ "action": "ps", "filter": "listening" })).await.unwrap();
+        assert_eq!(ps["processes"][0]["running"], false);
+        assert!(call(json!({ "action": "stop", "name": "nope" })).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ports() {
+        let tool = ExecTool::new();
+        let call = |args: Value| {
+            let args: ExecToolArgs = serde_json::from_value(args).unwrap();
+            async { tool.execute(args).await.map(|out| serde_json::from_str::<Value>(&out).unwrap()) }
+        };
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let ports = call(json!({ "action": "ports", "port": port })).await.unwrap();
+        assert_eq!(ports["total"], 1);
+        assert_eq!(ports["listeners"][0]["port"], port);
+        let free = call(json!({ "action": "free_port", "port": port })).await.unwrap();
+        assert!(free["port"].as_u64().unwrap() > port as u64);
+        if cfg!(target_os = "linux") {
+            assert!(call(json!({ "action": "kill_port", "port": port })).await.is_err());
+        }
+        drop(listener);
+        let nothing = call(json!({ "action": "kill_port", "port": port })).await.unwrap();
+        assert_eq!(nothing["pids"], json!([]));
+        assert!(call(json!({ "action": "kill_port" })).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exec_stdin() {
+        let tool = ExecTool::new();
+        let run = |args: Value| async { serde_json::from_str::<Value>(&tool.execute(serde_json::from_value(args).unwrap()).await.unwrap()).unwrap() };
+
+        let piped = run(json!({ "action": "exec", "command": ["cat"], "stdin": "line 1\nline 2\n" })).await;
+        assert_eq!(piped["stdout"], "line 1\nline 2\n");
+        let binary = run(json!({ "action": "exec", "command": "wc -c", "stdin_base64": "AAEC" })).await;
+        assert_eq!(binary["stdout"].as_str().unwrap().trim(), "3");
+        // No stdin reads as an empty, closed one rather than the server's
+        let empty = run(json!({ "action": "exec", "command": ["cat"], "timeout": 5 })).await;
+        assert_eq!(empty["status"], "success");
+        assert!(tool.execute(serde_json::from_value(json!({ "action": "exec", "command": "cat", "stdin": "a", "stdin_base64": "YQ==" })).unwrap()).await.is_err());
+
+        let session = run(json!({ "action": "exec", "command": ["cat"], "stdin": "first\n", "keep_stdin": true })).await;
+        assert_eq!(session["status"], "running");
+        let proc_id = session["proc_id"].as_str().unwrap();
+        let written = run(json!({ "action": "input", "proc_id": proc_id, "stdin": "second\n" })).await;
+        assert_eq!(written["written"], 7);
+        assert_eq!(written["closed"], false);
+        let closed = run(json!({ "action": "stdin", "proc_id": proc_id, "keep_stdin": false })).await;
+        assert_eq!(closed["closed"], true);
+        let done = run(json!({ "action": "wait", "proc_id": proc_id, "timeout_ms": 5000 })).await;
+        assert_eq!(done["exit_code"], 0);
+        let logs = run(json!({ "action": "logs", "proc_id": proc_id })).await;
+        assert!(logs["output"].as_str().unwrap().contains("first\nsecond"));
+        assert!(tool.execute(serde_json::from_value(json!({ "action": "input", "proc_id": proc_id, "stdin": "x" })).unwrap()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_exec_config() {
+        let root = tempfile::tempdir().unwrap();
+        let config = ExecConfig {
+            env_inherit: crate::config::EnvInherit::Allowlist,
+            env_allow: vec!["HOME".into()],
+            env: HashMap::from([("HANZO_MODE".to_string(), "ci".to_string())]),
+            ..Default::default()
+        };
+        let tool = ExecTool::with_config(config);
+        let args = ExecToolArgs {
+            action: "exec".to_string(),
+            command: Some(json!("pwd; echo \"${HANZO_MODE}-${HANZO_SECRET:-unset}-${HOME:+home}\"")),
+            root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        };
+        std::env::set_var("HANZO_SECRET", "leaked");
+        let result: Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+        let stdout = result["stdout"].as_str().unwrap();
+        let cwd = std::fs::canonicalize(root.path()).unwrap();
+        assert!(stdout.starts_with(&format!("{}\n", cwd.display())), "{}", stdout);
+        assert!(stdout.ends_with("ci-unset-home\n"), "{}", stdout);
+
+        let server = ExecTool::with_config(ExecConfig { default_cwd: DefaultCwd::Server, ..Default::default() });
+        let args = ExecToolArgs {
+            action: "exec".to_string(),
+            command: Some(json!(["pwd"])),
+            root: Some(root.path().to_path_buf()),
+            dry_run: true,
+            ..Default::default()
+        };
+        let result: Value = serde_json::from_str(&server.execute(args).await.unwrap()).unwrap();
+        assert_eq!(result["cwd"], json!(std::env::current_dir().unwrap().display().to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_exec_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("ran");
+        let tool = ExecTool::new();
+        let args = ExecToolArgs {
+            action: "exec".to_string(),
+            command: Some(json!(["touch", marker.to_string_lossy()])),
+            env: Some(HashMap::from([("TOKEN".to_string(), "secret".to_string())])),
+            dry_run: true,
+            ..Default::default()
+        };
+
+        let result: Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+        assert_eq!(result["dry_run"], true);
+        assert!(result["command"].as_str().unwrap().starts_with("touch "));
+        assert_eq!(result["env"], json!(["TOKEN"]));
+        assert!(!marker.exists());
+    }
+
     #[tokio::test]
     async fn test_ps() {
         let tool = ExecTool::new();
@@ -594,6 +1570,29 @@ mod tests {
         assert!(output.contains("processes"));
     }
 
+    #[tokio::test]
+    async fn test_spawn_task() {
+        let manager = ProcessManager::new();
+        let info = manager.spawn_task("pull".into(), |lines| async move {
+            let _ = lines.send("one".into());
+            let _ = lines.send("two".into());
+            3
+        }).await.unwrap();
+        assert!(info.pid.is_none());
+
+        let mut current = info.clone();
+        for _ in 0..50 {
+            if !current.running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            current = manager.get(&info.proc_id).await.unwrap();
+        }
+        assert_eq!(current.exit_code, Some(3));
+        let log = tokio::fs::read_to_string(info.log_file.unwrap()).await.unwrap();
+        assert_eq!(log, "one\ntwo\n");
+    }
+
     #[tokio::test]
     async fn test_help() {
         let tool = ExecTool::new();
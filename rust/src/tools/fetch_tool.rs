@@ -9,7 +9,8 @@
 /// - search: Web search query
 /// - crawl: Recursive site mirror
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use crate::error::ToolError;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -46,7 +47,7 @@ impl std::str::FromStr for NetAction {
             "search" => Ok(Self::Search),
             "crawl" | "mirror" => Ok(Self::Crawl),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -126,7 +127,7 @@ impl FetchTool {
     }
 
     async fn request(&self, args: &FetchToolArgs) -> Result<Value> {
-        let url = args.url.as_deref().ok_or_else(|| anyhow!("url required"))?;
+        let url = args.url.as_deref().ok_or_else(|| ToolError::invalid("url required"))?;
         let method = args.method.as_deref().unwrap_or("GET");
         let timeout = args.timeout.unwrap_or(30000);
         let client = self.build_client(timeout)?;
@@ -138,7 +139,7 @@ impl FetchTool {
             "DELETE" => client.delete(url),
             "PATCH" => client.patch(url),
             "HEAD" => client.head(url),
-            _ => return Err(anyhow!("Unsupported method: {}", method)),
+            _ => return Err(ToolError::unsupported(format!("Unsupported method: {}", method)).into()),
         };
 
         if let Some(headers) = &args.headers {
@@ -174,7 +175,7 @@ impl FetchTool {
     }
 
     async fn fetch_url(&self, args: &FetchToolArgs) -> Result<Value> {
-        let url = args.url.as_deref().ok_or_else(|| anyhow!("url required"))?;
+        let url = args.url.as_deref().ok_or_else(|| ToolError::invalid("url required"))?;
         let timeout = args.timeout.unwrap_or(30000);
         let client = self.build_client(timeout)?;
 
@@ -203,7 +204,7 @@ impl FetchTool {
     }
 
     async fn head(&self, args: &FetchToolArgs) -> Result<Value> {
-        let url = args.url.as_deref().ok_or_else(|| anyhow!("url required"))?;
+        let url = args.url.as_deref().ok_or_else(|| ToolError::invalid("url required"))?;
         let timeout = args.timeout.unwrap_or(30000);
         let client = self.build_client(timeout)?;
 
@@ -230,8 +231,8 @@ impl FetchTool {
     }
 
     async fn download(&self, args: &FetchToolArgs) -> Result<Value> {
-        let url = args.url.as_deref().ok_or_else(|| anyhow!("url required"))?;
-        let output = args.output.as_deref().ok_or_else(|| anyhow!("output path required"))?;
+        let url = args.url.as_deref().ok_or_else(|| ToolError::invalid("url required"))?;
+        let output = args.output.as_deref().ok_or_else(|| ToolError::invalid("output path required"))?;
         let timeout = args.timeout.unwrap_or(60000);
         let client = self.build_client(timeout)?;
 
@@ -249,6 +250,7 @@ impl FetchTool {
             tokio::fs::create_dir_all(parent).await?;
         }
         tokio::fs::write(output, &bytes).await?;
+        crate::events::publish(crate::events::DOWNLOAD_FINISHED, json!({ "url": url, "output": output, "size": bytes.len() }));
 
         Ok(json!({
             "ok": true,
@@ -259,7 +261,7 @@ impl FetchTool {
     }
 
     async fn open(&self, args: &FetchToolArgs) -> Result<Value> {
-        let url = args.url.as_deref().ok_or_else(|| anyhow!("url required"))?;
+        let url = args.url.as_deref().ok_or_else(|| ToolError::invalid("url required"))?;
 
         #[cfg(target_os = "macos")]
         let cmd = "open";
@@ -279,7 +281,7 @@ impl FetchTool {
     }
 
     async fn search(&self, args: &FetchToolArgs) -> Result<Value> {
-        let query = args.query.as_deref().ok_or_else(|| anyhow!("query required"))?;
+        let query = args.query.as_deref().ok_or_else(|| ToolError::invalid("query required"))?;
         let timeout = args.timeout.unwrap_or(15000);
         let client = self.build_client(timeout)?;
 
@@ -321,8 +323,8 @@ impl FetchTool {
     }
 
     async fn crawl(&self, args: &FetchToolArgs) -> Result<Value> {
-        let url = args.url.as_deref().ok_or_else(|| anyhow!("url required"))?;
-        let output = args.output.as_deref().ok_or_else(|| anyhow!("output directory required"))?;
+        let url = args.url.as_deref().ok_or_else(|| ToolError::invalid("url required"))?;
+        let output = args.output.as_deref().ok_or_else(|| ToolError::invalid("output directory required"))?;
         let max_depth = args.depth.unwrap_or(2);
         let max_pages = args.limit.unwrap_or(100);
         let timeout = args.timeout.unwrap_or(10000);
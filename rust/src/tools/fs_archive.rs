@@ -0,0 +1,505 @@
+/// Zip and tar archives for fs
+///
+/// Create, list and extract `.zip`, `.tar` and `.tar.gz`/`.tgz` archives.
+/// Include/exclude globs are matched against each entry's path inside the
+/// archive and against its file name. Extraction only writes below the
+/// destination: absolute names, `..` components, links and paths that
+/// would escape through an existing symlink are skipped and reported.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl Format {
+    /// Format named by `explicit`, or else by the archive's extension
+    pub fn detect(archive: &str, explicit: Option<&str>) -> Result<Self> {
+        let name = explicit.map(str::to_lowercase).unwrap_or_else(|| archive.to_lowercase());
+        if name == "zip" || name.ends_with(".zip") {
+            Ok(Self::Zip)
+        } else if ["tar.gz", "tgz", "gz"].contains(&name.as_str()) || name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Self::TarGz)
+        } else if name == "tar" || name.ends_with(".tar") {
+            Ok(Self::Tar)
+        } else {
+            Err(ToolError::invalid(format!(
+                "Cannot tell the archive format of {}; pass format=zip, tar or tar.gz",
+                explicit.unwrap_or(archive)
+            )).into())
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::Tar => "tar",
+            Self::TarGz => "tar.gz",
+        }
+    }
+}
+
+/// Archive path with its archive extension removed, the default extract
+/// destination
+pub fn default_dest(archive: &str) -> PathBuf {
+    let lower = archive.to_lowercase();
+    let cut = [".tar.gz", ".tgz", ".zip", ".tar"].iter()
+        .find(|ext| lower.ends_with(*ext))
+        .map_or(0, |ext| ext.len());
+    PathBuf::from(&archive[..archive.len() - cut])
+}
+
+/// Include/exclude globs
+#[derive(Debug, Default)]
+pub struct Filter {
+    include: Vec<glob::Pattern>,
+    exclude: Vec<glob::Pattern>,
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |patterns: &[String]| -> Result<Vec<glob::Pattern>> {
+            patterns.iter()
+                .map(|p| glob::Pattern::new(p).map_err(|e| ToolError::invalid(format!("Invalid glob '{}': {}", p, e)).into()))
+                .collect()
+        };
+        Ok(Self { include: compile(include)?, exclude: compile(exclude)? })
+    }
+
+    fn matches(patterns: &[glob::Pattern], path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        patterns.iter().any(|p| p.matches(path) || p.matches(name))
+    }
+
+    pub fn excludes(&self, path: &str) -> bool {
+        Self::matches(&self.exclude, path)
+    }
+
+    /// Whether a file entry is selected
+    fn allows(&self, path: &str) -> bool {
+        (self.include.is_empty() || Self::matches(&self.include, path)) && !self.excludes(path)
+    }
+}
+
+enum Kind {
+    File,
+    Dir,
+    Symlink,
+}
+
+struct Entry {
+    full: PathBuf,
+    name: String,
+    kind: Kind,
+}
+
+/// Pack `source` (a directory's contents, or a single file) into `archive`
+pub fn create(archive: &Path, source: &Path, format: Format, filter: &Filter, overwrite: bool) -> Result<Value> {
+    if archive.exists() && !overwrite {
+        return Err(ToolError::conflict(format!(
+            "{} already exists; pass overwrite=true to replace it", archive.display()
+        )).into());
+    }
+    let is_dir = fs::metadata(source)?.is_dir();
+    let root = if is_dir { source.to_path_buf() } else { source.parent().unwrap_or(Path::new("")).to_path_buf() };
+    let archive_abs = std::path::absolute(archive)?;
+
+    let name_of = |path: &Path| -> String {
+        path.strip_prefix(&root).unwrap_or(path)
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    };
+    let mut entries = Vec::new();
+    let walker = WalkDir::new(source)
+        .follow_links(false)
+        .sort_by_file_name()
+        .min_depth(usize::from(is_dir))
+        .into_iter()
+        .filter_entry(|e| !filter.excludes(&name_of(e.path())));
+    for entry in walker {
+        let entry = entry?;
+        if std::path::absolute(entry.path())? == archive_abs {
+            continue;
+        }
+        let name = name_of(entry.path());
+        let file_type = entry.file_type();
+        let kind = if file_type.is_dir() {
+            if !filter.include.is_empty() {
+                continue;
+            }
+            Kind::Dir
+        } else if !filter.allows(&name) {
+            continue;
+        } else if file_type.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        };
+        entries.push(Entry { full: entry.into_path(), name, kind });
+    }
+
+    if let Some(parent) = archive.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::create(archive)?;
+    let written = match format {
+        Format::Zip => write_zip(file, &entries),
+        Format::Tar => write_tar(file, &entries).map(drop),
+        Format::TarGz => write_tar(GzEncoder::new(file, flate2::Compression::default()), &entries)
+            .and_then(|gz| gz.finish().map(drop).map_err(Into::into)),
+    };
+    if let Err(e) = written {
+        let _ = fs::remove_file(archive);
+        return Err(e);
+    }
+
+    let files = entries.iter().filter(|e| !matches!(e.kind, Kind::Dir)).count();
+    let bytes: u64 = entries.iter()
+        .filter(|e| matches!(e.kind, Kind::File))
+        .filter_map(|e| fs::metadata(&e.full).ok())
+        .map(|m| m.len())
+        .sum();
+    Ok(json!({
+        "archive": archive.display().to_string(),
+        "format": format.name(),
+        "files": files,
+        "directories": entries.len() - files,
+        "bytes": bytes,
+        "size": fs::metadata(archive)?.len()
+    }))
+}
+
+fn write_tar<W: Write>(writer: W, entries: &[Entry]) -> Result<W> {
+    let mut builder = tar::Builder::new(writer);
+    builder.follow_symlinks(false);
+    for entry in entries {
+        builder.append_path_with_name(&entry.full, &entry.name)?;
+    }
+    Ok(builder.into_inner()?)
+}
+
+fn write_zip(file: File, entries: &[Entry]) -> Result<()> {
+    use zip::write::SimpleFileOptions;
+
+    let mut zip = zip::ZipWriter::new(file);
+    for entry in entries {
+        let metadata = fs::symlink_metadata(&entry.full)?;
+        let mut options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .large_file(metadata.len() >= u32::MAX as u64);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            options = options.unix_permissions(metadata.permissions().mode() & 0o777);
+        }
+        match entry.kind {
+            Kind::Dir => zip.add_directory(format!("{}/", entry.name), options)?,
+            Kind::Symlink => {
+                let target = fs::read_link(&entry.full)?;
+                zip.add_symlink(&entry.name, target.to_string_lossy(), options)?;
+            }
+            Kind::File => {
+                zip.start_file(&entry.name, options)?;
+                io::copy(&mut File::open(&entry.full)?, &mut zip)?;
+            }
+        }
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn open_tar(archive: &Path, format: Format) -> Result<tar::Archive<Box<dyn Read>>> {
+    let file = File::open(archive)?;
+    let reader: Box<dyn Read> = match format {
+        Format::TarGz => Box::new(GzDecoder::new(file)),
+        _ => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+/// Entries of `archive` without extracting it
+pub fn list(archive: &Path, format: Format, filter: &Filter, limit: usize) -> Result<Value> {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    let mut bytes = 0;
+    let mut push = |name: String, kind: &str, size: u64| {
+        if !filter.allows(name.trim_end_matches('/')) {
+            return;
+        }
+        total += 1;
+        bytes += size;
+        if entries.len() < limit {
+            entries.push(json!({
+                "path": name,
+                "type": kind,
+                "size": size,
+                "safe": safe_relative(&name).is_some()
+            }));
+        }
+    };
+
+    if format == Format::Zip {
+        let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            let file = zip.by_index_raw(i)?;
+            let kind = if file.is_dir() { "directory" } else if file.is_symlink() { "symlink" } else { "file" };
+            push(file.name().to_string(), kind, file.size());
+        }
+    } else {
+        let mut tar = open_tar(archive, format)?;
+        for entry in tar.entries()? {
+            let entry = entry?;
+            let header = entry.header();
+            let kind = match header.entry_type() {
+                tar::EntryType::Directory => "directory",
+                tar::EntryType::Symlink => "symlink",
+                tar::EntryType::Link => "hardlink",
+                _ => "file",
+            };
+            push(String::from_utf8_lossy(&entry.path_bytes()).into_owned(), kind, header.size().unwrap_or(0));
+        }
+    }
+
+    Ok(json!({
+        "archive": archive.display().to_string(),
+        "format": format.name(),
+        "entries": entries,
+        "total": total,
+        "bytes": bytes,
+        "truncated": total > limit
+    }))
+}
+
+/// Relative path of an archive entry, or None if it could land outside
+/// the destination
+fn safe_relative(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in Path::new(&name.replace('\\', "/")).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+struct Extractor<'a> {
+    root: PathBuf,
+    filter: &'a Filter,
+    overwrite: bool,
+    files: usize,
+    directories: usize,
+    bytes: u64,
+    skipped: Vec<Value>,
+}
+
+impl Extractor<'_> {
+    fn skip(&mut self, name: &str, reason: &str) {
+        self.skipped.push(json!({ "path": name, "reason": reason }));
+    }
+
+    /// Where `name` goes, creating its parent directories; None (and a
+    /// skipped entry) when it must not be written
+    fn target(&mut self, name: &str, is_dir: bool) -> Result<Option<PathBuf>> {
+        let Some(relative) = safe_relative(name) else {
+            self.skip(name, "path escapes the destination");
+            return Ok(None);
+        };
+        if !self.filter.allows(name.trim_end_matches('/')) {
+            return Ok(None);
+        }
+        let target = self.root.join(relative);
+        let parent = if is_dir { target.as_path() } else { target.parent().unwrap_or(&self.root) };
+        // An existing symlinked directory could point anywhere, so check
+        // before creating anything under it
+        let existing = parent.ancestors().find_map(|dir| dir.canonicalize().ok());
+        if !existing.is_some_and(|dir| dir.starts_with(&self.root)) {
+            self.skip(name, "path escapes the destination through a symlink");
+            return Ok(None);
+        }
+        fs::create_dir_all(parent)?;
+        if !is_dir {
+            if let Ok(existing) = fs::symlink_metadata(&target) {
+                if existing.file_type().is_symlink() || existing.is_dir() || !self.overwrite {
+                    self.skip(name, "already exists");
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(target))
+    }
+
+    fn dir(&mut self, name: &str) -> Result<()> {
+        if self.target(name, true)?.is_some() {
+            self.directories += 1;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, target: &Path, reader: &mut dyn Read, mode: Option<u32>) -> Result<()> {
+        let mut file = File::create(target)?;
+        self.bytes += io::copy(reader, &mut file)?;
+        self.files += 1;
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o777))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        Ok(())
+    }
+}
+
+/// Unpack `archive` below `dest`
+pub fn extract(archive: &Path, dest: &Path, format: Format, filter: &Filter, overwrite: bool) -> Result<Value> {
+    fs::create_dir_all(dest)?;
+    let mut extractor = Extractor {
+        root: dest.canonicalize()?,
+        filter,
+        overwrite,
+        files: 0,
+        directories: 0,
+        bytes: 0,
+        skipped: Vec::new(),
+    };
+
+    if format == Format::Zip {
+        let mut zip = zip::ZipArchive::new(File::open(archive)?)?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            let name = file.name().to_string();
+            if file.is_symlink() {
+                extractor.skip(&name, "links are not extracted");
+            } else if file.is_dir() {
+                extractor.dir(&name)?;
+            } else if let Some(target) = extractor.target(&name, false)? {
+                let mode = file.unix_mode();
+                extractor.write(&target, &mut file, mode)?;
+            }
+        }
+    } else {
+        let mut tar = open_tar(archive, format)?;
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+            match entry.header().entry_type() {
+                tar::EntryType::Directory => extractor.dir(&name)?,
+                tar::EntryType::Regular | tar::EntryType::Continuous => {
+                    if let Some(target) = extractor.target(&name, false)? {
+                        let mode = entry.header().mode().ok();
+                        extractor.write(&target, &mut entry, mode)?;
+                    }
+                }
+                tar::EntryType::Symlink | tar::EntryType::Link => extractor.skip(&name, "links are not extracted"),
+                // PAX and GNU long-name headers are consumed by the reader
+                _ => {}
+            }
+        }
+    }
+
+    Ok(json!({
+        "archive": archive.display().to_string(),
+        "dest": extractor.root.display().to_string(),
+        "format": format.name(),
+        "files": extractor.files,
+        "directories": extractor.directories,
+        "bytes": extractor.bytes,
+        "skipped": extractor.skipped
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("lib/target")).unwrap();
+        fs::write(src.join("main.rs"), "fn main() {}").unwrap();
+        fs::write(src.join("lib/util.rs"), "pub fn f() {}").unwrap();
+        fs::write(src.join("lib/target/out.o"), "junk").unwrap();
+        let filter = Filter::new(&[], &["target".to_string()]).unwrap();
+
+        for name in ["a.zip", "b.tar.gz", "c.tar"] {
+            let archive = dir.path().join(name);
+            let format = Format::detect(&archive.to_string_lossy(), None).unwrap();
+            let created = create(&archive, &src, format, &filter, false).unwrap();
+            assert_eq!(created["files"], 2);
+            assert!(create(&archive, &src, format, &filter, false).is_err());
+
+            let listed = list(&archive, format, &Filter::default(), 100).unwrap();
+            let paths: Vec<&str> = listed["entries"].as_array().unwrap().iter()
+                .filter(|e| e["type"] == "file")
+                .map(|e| e["path"].as_str().unwrap())
+                .collect();
+            assert_eq!(paths, ["lib/util.rs", "main.rs"]);
+
+            let dest = default_dest(&archive.to_string_lossy());
+            let only_rs = Filter::new(&["main.rs".to_string()], &[]).unwrap();
+            let extracted = extract(&archive, &dest, format, &only_rs, false).unwrap();
+            assert_eq!(extracted["files"], 1);
+            assert_eq!(fs::read_to_string(dest.join("main.rs")).unwrap(), "fn main() {}");
+            assert!(!dest.join("lib/util.rs").exists());
+        }
+    }
+
+    #[test]
+    fn test_traversal_is_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("evil.tar");
+        let mut builder = tar::Builder::new(File::create(&archive).unwrap());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        // set_path refuses "..", so write the raw name as a hostile archive would
+        header.as_gnu_mut().unwrap().name[..11].copy_from_slice(b"../evil.txt");
+        header.set_cksum();
+        builder.append(&header, &b"evil"[..]).unwrap();
+        builder.into_inner().unwrap();
+
+        let dest = dir.path().join("out");
+        let result = extract(&archive, &dest, Format::Tar, &Filter::default(), false).unwrap();
+        assert_eq!(result["files"], 0);
+        assert_eq!(result["skipped"][0]["path"], "../evil.txt");
+        assert!(!dir.path().join("evil.txt").exists());
+        assert_eq!(safe_relative("/etc/passwd"), None);
+        assert_eq!(safe_relative("./a/b"), Some(PathBuf::from("a/b")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_dirs_are_not_followed() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("link/sub")).unwrap();
+        fs::write(src.join("link/sub/x.txt"), "x").unwrap();
+        let archive = dir.path().join("a.tar");
+        create(&archive, &src, Format::Tar, &Filter::default(), false).unwrap();
+
+        let outside = dir.path().join("outside");
+        let dest = dir.path().join("out");
+        fs::create_dir_all(&outside).unwrap();
+        fs::create_dir_all(&dest).unwrap();
+        std::os::unix::fs::symlink(&outside, dest.join("link")).unwrap();
+        let result = extract(&archive, &dest, Format::Tar, &Filter::default(), false).unwrap();
+        assert_eq!(result["files"], 0);
+        assert!(!outside.join("sub").exists(), "no directory is created through the link");
+    }
+}
@@ -0,0 +1,105 @@
+/// Atomic file replacement
+///
+/// Contents go to a temporary file in the target's directory which is then
+/// renamed over the target, so readers see either the old file or the new
+/// one, never a partial write. With `fsync` the data and the directory entry
+/// are flushed to disk before returning.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::AsyncWriteExt;
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Replace `path` with `contents`, keeping the existing file's permissions.
+/// A symlink is followed so the link itself survives.
+pub async fn write_atomic(path: &str, contents: &[u8], fsync: bool) -> Result<()> {
+    let mut target = PathBuf::from(path);
+    if tokio::fs::symlink_metadata(&target).await.is_ok_and(|m| m.file_type().is_symlink()) {
+        if let Ok(resolved) = tokio::fs::canonicalize(&target).await {
+            target = resolved;
+        }
+    }
+    let name = target.file_name()
+        .ok_or_else(|| ToolError::invalid(format!("Not a file path: {}", path)))?
+        .to_string_lossy()
+        .to_string();
+    let dir = match target.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    tokio::fs::create_dir_all(&dir).await?;
+
+    let temp = dir.join(format!(
+        ".{}.{}.{}.tmp",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let permissions = tokio::fs::metadata(&target).await.ok().map(|m| m.permissions());
+
+    let result = async {
+        let mut file = tokio::fs::File::create(&temp).await?;
+        file.write_all(contents).await?;
+        if let Some(permissions) = permissions {
+            file.set_permissions(permissions).await?;
+        }
+        if fsync {
+            file.sync_all().await?;
+        }
+        drop(file);
+        tokio::fs::rename(&temp, &target).await?;
+        if fsync {
+            sync_dir(&dir).await?;
+        }
+        Ok(())
+    }.await;
+
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+    result
+}
+
+/// Flush a directory entry so a rename survives a crash
+#[cfg(unix)]
+async fn sync_dir(dir: &Path) -> Result<()> {
+    tokio::fs::File::open(dir).await?.sync_all().await?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_write_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sub/a.txt");
+        let path = path.to_string_lossy();
+
+        write_atomic(&path, b"one", false).await.unwrap();
+        write_atomic(&path, b"two", true).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&*path).unwrap(), "two");
+
+        // Only the target is left behind
+        let entries: Vec<_> = std::fs::read_dir(dir.path().join("sub")).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.txt");
+            std::os::unix::fs::symlink(&*path, &link).unwrap();
+            write_atomic(&link.to_string_lossy(), b"three", false).await.unwrap();
+            assert!(std::fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+            assert_eq!(std::fs::read_to_string(&*path).unwrap(), "three");
+        }
+    }
+}
@@ -0,0 +1,229 @@
+/// Duplicate and near-duplicate files for fs
+///
+/// Exact duplicates are found by size, then by blake3 digest. Near
+/// duplicates are text files whose token 5-gram (shingle) sets overlap by at
+/// least the requested Jaccard similarity, estimated from 64-value MinHash
+/// signatures so large trees stay cheap to compare. Files are grouped into
+/// clusters by linking every similar pair.
+
+use super::fs_glob::PathGlobs;
+use super::hash::{self, Algorithm};
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Tokens per shingle
+const SHINGLE: usize = 5;
+
+/// MinHash signature length
+const SIGNATURE: usize = 64;
+
+/// Text files larger than this are only checked for exact duplicates
+const MAX_TEXT_BYTES: u64 = 512 * 1024;
+
+pub struct Options {
+    pub include_hidden: bool,
+    pub include: PathGlobs,
+    pub exclude: PathGlobs,
+    /// Minimum estimated Jaccard similarity for near duplicates
+    pub similarity: f64,
+    /// Clusters reported per kind
+    pub limit: usize,
+}
+
+/// Exact and near-duplicate clusters under `root`
+pub fn scan(root: &Path, options: &Options) -> Result<Value> {
+    let mut files = Vec::new();
+    for entry in ignore::WalkBuilder::new(root)
+        .hidden(!options.include_hidden)
+        .require_git(false)
+        .build()
+        .flatten()
+    {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+        if (!options.include.is_empty() && !options.include.is_match(relative)) || options.exclude.is_match(relative) {
+            continue;
+        }
+        if let Ok(metadata) = entry.metadata() {
+            files.push((entry.into_path(), metadata.len()));
+        }
+    }
+
+    let exact = exact_duplicates(&files);
+    let duplicate_of: HashMap<&Path, usize> = exact.iter().enumerate()
+        .flat_map(|(i, (_, _, paths))| paths.iter().map(move |p| (p.as_path(), i)))
+        .collect();
+    let wasted: u64 = exact.iter().map(|(_, size, paths)| size * (paths.len() as u64 - 1)).sum();
+
+    // One representative per exact cluster, so copies don't show up again
+    // as 100% similar
+    let mut seen = std::collections::HashSet::new();
+    let signatures: Vec<(&Path, u64, [u64; SIGNATURE])> = files.iter()
+        .filter(|(path, size)| *size <= MAX_TEXT_BYTES && duplicate_of.get(path.as_path()).is_none_or(|i| seen.insert(*i)))
+        .filter_map(|(path, size)| Some((path.as_path(), *size, signature(&std::fs::read_to_string(path).ok()?)?)))
+        .collect();
+    let similar = similar_clusters(&signatures, options.similarity);
+
+    let display = |p: &Path| p.to_string_lossy().into_owned();
+    let exact_json: Vec<Value> = exact.iter().take(options.limit).map(|(digest, size, paths)| json!({
+        "hash": digest,
+        "size": size,
+        "count": paths.len(),
+        "wasted_bytes": size * (paths.len() as u64 - 1),
+        "files": paths.iter().map(|p| display(p)).collect::<Vec<_>>()
+    })).collect();
+    let similar_json: Vec<Value> = similar.iter().take(options.limit).map(|(members, score)| json!({
+        "similarity": (score * 100.0).round() / 100.0,
+        "count": members.len(),
+        "files": members.iter().map(|&i| json!({
+            "path": display(signatures[i].0),
+            "size": signatures[i].1
+        })).collect::<Vec<_>>()
+    })).collect();
+
+    Ok(json!({
+        "path": display(root),
+        "files_scanned": files.len(),
+        "exact": exact_json,
+        "exact_clusters": exact.len(),
+        "wasted_bytes": wasted,
+        "similar": similar_json,
+        "similar_clusters": similar.len(),
+        "truncated": exact.len() > options.limit || similar.len() > options.limit
+    }))
+}
+
+/// (digest, size, paths) for every group of identical files, most wasted
+/// space first
+fn exact_duplicates(files: &[(PathBuf, u64)]) -> Vec<(String, u64, Vec<PathBuf>)> {
+    let mut by_size: HashMap<u64, Vec<&PathBuf>> = HashMap::new();
+    for (path, size) in files {
+        if *size > 0 {
+            by_size.entry(*size).or_default().push(path);
+        }
+    }
+    let mut clusters = Vec::new();
+    for (size, paths) in by_size.into_iter().filter(|(_, p)| p.len() > 1) {
+        let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            if let Ok(digest) = hash::file_hex(Algorithm::Blake3, path) {
+                by_hash.entry(digest).or_default().push(path.clone());
+            }
+        }
+        for (digest, mut paths) in by_hash.into_iter().filter(|(_, p)| p.len() > 1) {
+            paths.sort();
+            clusters.push((digest, size, paths));
+        }
+    }
+    clusters.sort_by(|a, b| (b.1 * b.2.len() as u64).cmp(&(a.1 * a.2.len() as u64)).then_with(|| a.2.cmp(&b.2)));
+    clusters
+}
+
+/// MinHash of the file's token shingles; `None` for text too short to judge
+fn signature(text: &str) -> Option<[u64; SIGNATURE]> {
+    let tokens: Vec<&str> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| !t.is_empty())
+        .collect();
+    if tokens.len() < SHINGLE * 4 {
+        return None;
+    }
+    let mut signature = [u64::MAX; SIGNATURE];
+    for shingle in tokens.windows(SHINGLE) {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let base = hasher.finish();
+        for (i, slot) in signature.iter_mut().enumerate() {
+            *slot = (*slot).min(mix(base ^ (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)));
+        }
+    }
+    Some(signature)
+}
+
+/// splitmix64 finaliser, one independent hash per signature slot
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Groups of indices into `signatures` linked by pairs at least `threshold`
+/// similar, with the lowest linking similarity; largest groups first
+fn similar_clusters(signatures: &[(&Path, u64, [u64; SIGNATURE])], threshold: f64) -> Vec<(Vec<usize>, f64)> {
+    let mut parent: Vec<usize> = (0..signatures.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut lowest: HashMap<usize, f64> = HashMap::new();
+    let mut links = Vec::new();
+    for i in 0..signatures.len() {
+        for j in i + 1..signatures.len() {
+            let same = signatures[i].2.iter().zip(&signatures[j].2).filter(|(a, b)| a == b).count();
+            let score = same as f64 / SIGNATURE as f64;
+            if score >= threshold {
+                let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+                parent[a] = b;
+                links.push((j, score));
+            }
+        }
+    }
+    for (member, score) in links {
+        let group = root(&mut parent, member);
+        let entry = lowest.entry(group).or_insert(score);
+        *entry = entry.min(score);
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..signatures.len() {
+        let group = root(&mut parent, i);
+        groups.entry(group).or_default().push(i);
+    }
+    let mut clusters: Vec<(Vec<usize>, f64)> = groups.into_iter()
+        .filter(|(_, members)| members.len() > 1)
+        .map(|(group, members)| (members, lowest.get(&group).copied().unwrap_or(1.0)))
+        .collect();
+    clusters.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let source: String = (0..40).map(|i| format!("fn handler_{}(request: Request) -> Response {{ route(request, {}) }}\n", i, i)).collect();
+        std::fs::write(dir.path().join("a.rs"), &source).unwrap();
+        std::fs::write(dir.path().join("copy.rs"), &source).unwrap();
+        std::fs::write(dir.path().join("b.rs"), source.replace("handler_7(", "handler_seven(")).unwrap();
+        std::fs::write(dir.path().join("other.rs"), (0..40).map(|i| format!("let value{} = compute({});\n", i, i * 3)).collect::<String>()).unwrap();
+
+        let options = Options {
+            include_hidden: false,
+            include: PathGlobs::new::<&str>(&[]).unwrap(),
+            exclude: PathGlobs::new::<&str>(&[]).unwrap(),
+            similarity: 0.8,
+            limit: 10,
+        };
+        let result = scan(dir.path(), &options).unwrap();
+        assert_eq!(result["files_scanned"], 4);
+        assert_eq!(result["exact"][0]["count"], 2);
+        assert_eq!(result["wasted_bytes"], source.len() as u64);
+        assert_eq!(result["similar_clusters"], 1);
+        let similar = result["similar"][0]["files"].as_array().unwrap();
+        assert_eq!(similar.len(), 2);
+        assert!(similar.iter().any(|f| f["path"].as_str().unwrap().ends_with("b.rs")));
+        assert!(result["similar"][0]["similarity"].as_f64().unwrap() >= 0.8);
+    }
+}
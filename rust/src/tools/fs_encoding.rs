@@ -0,0 +1,187 @@
+/// Text encodings and line endings for fs
+///
+/// Files are decoded by BOM first, then as UTF-16 without a BOM (spotted by
+/// its NUL-byte pattern), UTF-8, Shift_JIS (when it decodes cleanly into
+/// kana) and finally Latin-1. Tools work on the text with LF line
+/// endings; writing it back re-applies the file's encoding, BOM and CRLF
+/// line endings so edits leave the rest of the file byte-for-byte intact.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use encoding_rs::{Encoding, SHIFT_JIS, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+
+/// How a text file is stored on disk
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextFormat {
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+    pub crlf: bool,
+}
+
+impl Default for TextFormat {
+    fn default() -> Self {
+        Self { encoding: UTF_8, bom: false, crlf: false }
+    }
+}
+
+impl TextFormat {
+    /// New files in `encoding`, written with a BOM where one is customary
+    pub fn with_encoding(encoding: &'static Encoding) -> Self {
+        Self {
+            encoding,
+            bom: encoding == UTF_16LE || encoding == UTF_16BE,
+            crlf: false,
+        }
+    }
+
+    pub fn line_endings(&self) -> &'static str {
+        if self.crlf { "crlf" } else { "lf" }
+    }
+
+    /// Encode LF-terminated `text` the way this file is stored
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>> {
+        let text = if self.crlf {
+            text.replace("\r\n", "\n").replace('\n', "\r\n")
+        } else {
+            text.to_string()
+        };
+
+        let mut out = Vec::with_capacity(text.len() + 3);
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            // encoding_rs only decodes UTF-16, so encode it here
+            let little = self.encoding == UTF_16LE;
+            if self.bom {
+                out.extend_from_slice(if little { &[0xFF, 0xFE] } else { &[0xFE, 0xFF] });
+            }
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&if little { unit.to_le_bytes() } else { unit.to_be_bytes() });
+            }
+            return Ok(out);
+        }
+
+        if self.bom && self.encoding == UTF_8 {
+            out.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+        }
+        let (bytes, _, unmappable) = self.encoding.encode(&text);
+        if unmappable {
+            return Err(ToolError::invalid(format!(
+                "Text contains characters that cannot be written as {}",
+                self.encoding.name()
+            )).into());
+        }
+        out.extend_from_slice(&bytes);
+        Ok(out)
+    }
+}
+
+/// Look up an encoding by name, e.g. "utf-16le", "shift_jis" or "latin1"
+pub fn for_label(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+        .ok_or_else(|| ToolError::invalid(format!("Unknown encoding: {}", label)).into())
+}
+
+/// Decode `bytes` read from `path` into LF-terminated text, detecting the
+/// encoding unless `forced`
+pub fn decode(path: &str, bytes: &[u8], forced: Option<&'static Encoding>) -> Result<(String, TextFormat)> {
+    let (encoding, bom_len) = match (forced, Encoding::for_bom(bytes)) {
+        (Some(forced), Some((found, len))) if found == forced => (forced, len),
+        (Some(forced), _) => (forced, 0),
+        (None, Some(found)) => found,
+        (None, None) => (detect(path, bytes)?, 0),
+    };
+
+    let (text, had_errors) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+    if had_errors {
+        return Err(ToolError::invalid(format!("{} is not valid {}", path, encoding.name())).into());
+    }
+
+    let crlf_count = text.matches("\r\n").count();
+    let crlf = crlf_count > 0 && crlf_count >= text.matches('\n').count() - crlf_count;
+    let text = if crlf { text.replace("\r\n", "\n") } else { text.into_owned() };
+    Ok((text, TextFormat { encoding, bom: bom_len > 0, crlf }))
+}
+
+fn detect(path: &str, bytes: &[u8]) -> Result<&'static Encoding> {
+    // Before UTF-8: ASCII-range UTF-16 is also valid UTF-8
+    if let Some(encoding) = utf16_without_bom(bytes) {
+        return Ok(encoding);
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return Ok(UTF_8);
+    }
+    if bytes.contains(&0) {
+        return Err(ToolError::unsupported(format!("{} looks like a binary file", path)).into());
+    }
+    let kana = |c: char| ('\u{3040}'..='\u{30FF}').contains(&c);
+    if SHIFT_JIS.decode_without_bom_handling_and_without_replacement(bytes)
+        .is_some_and(|text| text.chars().any(kana))
+    {
+        return Ok(SHIFT_JIS);
+    }
+    Ok(WINDOWS_1252)
+}
+
+/// Mostly-ASCII UTF-16 has a NUL in every other byte
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    let pairs = sample.len() / 2;
+    if pairs == 0 {
+        return None;
+    }
+    let even = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd = sample.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+    if odd * 10 >= pairs * 4 && even * 20 < pairs {
+        Some(UTF_16LE)
+    } else if even * 10 >= pairs * 4 && odd * 20 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(bytes: &[u8]) -> (String, TextFormat) {
+        let (text, format) = decode("f", bytes, None).unwrap();
+        assert_eq!(format.encode(&text).unwrap(), bytes);
+        (text, format)
+    }
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        let (text, format) = round_trip(b"a\r\nb\r\n");
+        assert_eq!(text, "a\nb\n");
+        assert!(format.crlf);
+
+        let utf16: Vec<u8> = [0xFF, 0xFE].into_iter()
+            .chain("h\u{e9}\n".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        let (text, format) = round_trip(&utf16);
+        assert_eq!(text, "h\u{e9}\n");
+        assert_eq!((format.encoding, format.bom), (UTF_16LE, true));
+
+        let be: Vec<u8> = "hi there\n".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(round_trip(&be).1.encoding, UTF_16BE);
+
+        // "こんにちは" in Shift_JIS
+        let sjis = [0x82, 0xb1, 0x82, 0xf1, 0x82, 0xc9, 0x82, 0xbf, 0x82, 0xcd];
+        let (text, format) = round_trip(&sjis);
+        assert_eq!(text, "こんにちは");
+        assert_eq!(format.encoding, SHIFT_JIS);
+
+        let (text, format) = round_trip(b"caf\xe9 r\xe9sum\xe9\n");
+        assert_eq!(text, "caf\u{e9} r\u{e9}sum\u{e9}\n");
+        assert_eq!(format.encoding, WINDOWS_1252);
+
+        assert!(decode("f", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\xff", None).is_err());
+    }
+
+    #[test]
+    fn test_unencodable() {
+        let format = TextFormat::with_encoding(for_label("latin1").unwrap());
+        assert_eq!(format.encode("\u{e9}").unwrap(), b"\xe9");
+        assert!(format.encode("こ").is_err());
+    }
+}
@@ -0,0 +1,68 @@
+/// Path globs for fs find
+///
+/// A pattern containing `/` matches the whole path relative to the search
+/// root (`src/**/*.rs`), any other pattern matches the file name alone
+/// (`*.rs`), as in .gitignore. `*` never crosses a `/`; `**` does, and
+/// `{a,b}` alternatives are supported.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+pub struct PathGlobs {
+    names: GlobSet,
+    paths: GlobSet,
+    empty: bool,
+}
+
+impl PathGlobs {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.as_ref().trim_start_matches("./");
+            let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+                .literal_separator(true)
+                .build()
+                .map_err(|e| ToolError::invalid(format!("Invalid glob '{}': {}", pattern, e)))?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        Ok(Self {
+            names: names.build()?,
+            paths: paths.build()?,
+            empty: patterns.is_empty(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    /// Whether `relative`, a path below the search root, matches any pattern
+    pub fn is_match(&self, relative: &Path) -> bool {
+        relative.file_name().is_some_and(|name| self.names.is_match(name)) || self.paths.is_match(relative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_globs() {
+        let globs = PathGlobs::new(&["src/**/*.rs", "*.toml", "docs/*.{md,txt}"]).unwrap();
+        assert!(globs.is_match(Path::new("src/main.rs")));
+        assert!(globs.is_match(Path::new("src/tools/fs/mod.rs")));
+        assert!(!globs.is_match(Path::new("tests/main.rs")));
+        assert!(globs.is_match(Path::new("crates/a/Cargo.toml")));
+        assert!(globs.is_match(Path::new("docs/guide.txt")));
+        assert!(!globs.is_match(Path::new("docs/api/guide.md")));
+        assert!(PathGlobs::new(&["src/[a"]).is_err());
+        assert!(PathGlobs::new::<&str>(&[]).unwrap().is_empty());
+    }
+}
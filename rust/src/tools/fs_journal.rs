@@ -0,0 +1,259 @@
+/// Undo journal for fs mutations
+///
+/// fs write/edit/patch record the bytes of each file before and after the
+/// change, one entry per file, under the MCP session that made it. Undo
+/// restores the pre-image and redo reapplies the post-image, but only while
+/// the file still holds what the journal expects, so edits made since are
+/// never clobbered. Each session keeps its newest changes within the
+/// configured entry and byte budgets; a session's journal is dropped when
+/// the session ends.
+
+use crate::config::JournalConfig;
+use crate::error::ToolError;
+use super::fs_atomic::write_atomic;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone)]
+struct Change {
+    id: u64,
+    path: String,
+    action: String,
+    /// Contents before the change; None when the file did not exist
+    before: Option<Vec<u8>>,
+    /// Contents after the change; None when the change deleted the file
+    after: Option<Vec<u8>>,
+    at: DateTime<Utc>,
+}
+
+impl Change {
+    fn bytes(&self) -> usize {
+        self.before.as_ref().map_or(0, Vec::len) + self.after.as_ref().map_or(0, Vec::len)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "path": self.path,
+            "action": self.action,
+            "op": match (&self.before, &self.after) {
+                (None, _) => "create",
+                (_, None) => "delete",
+                _ => "update",
+            },
+            "bytes_before": self.before.as_ref().map(Vec::len),
+            "bytes_after": self.after.as_ref().map(Vec::len),
+            "at": self.at.to_rfc3339()
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct History {
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    sessions: HashMap<String, History>,
+    next_id: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Journal {
+    config: JournalConfig,
+    state: Mutex<State>,
+}
+
+/// Current contents of `path`, or None if it does not exist
+pub async fn snapshot(path: &str) -> Result<Option<Vec<u8>>> {
+    match tokio::fs::read(path).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn restore(path: &str, contents: Option<&[u8]>) -> Result<()> {
+    match contents {
+        Some(bytes) => write_atomic(path, bytes, false).await?,
+        None => {
+            if tokio::fs::try_exists(path).await? {
+                tokio::fs::remove_file(path).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn key(session: Option<&str>) -> String {
+    session.unwrap_or_default().to_string()
+}
+
+impl Journal {
+    pub fn new(config: JournalConfig) -> Self {
+        Self { config, state: Mutex::new(State::default()) }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Record a change made by `action`; clears the session's redo stack
+    pub fn record(&self, session: Option<&str>, path: &str, action: &str, before: Option<Vec<u8>>, after: Option<Vec<u8>>) {
+        crate::events::publish(crate::events::FILE_CHANGED, json!({
+            "path": path,
+            "action": action,
+            "session": session,
+            "created": before.is_none(),
+            "deleted": after.is_none()
+        }));
+        if self.config.max_entries == 0 {
+            return;
+        }
+        let mut state = self.state();
+        state.next_id += 1;
+        let change = Change {
+            id: state.next_id,
+            path: path.to_string(),
+            action: action.to_string(),
+            before,
+            after,
+            at: Utc::now(),
+        };
+        let history = state.sessions.entry(key(session)).or_default();
+        history.redo.clear();
+        history.undo.push_back(change);
+        let mut total: usize = history.undo.iter().map(Change::bytes).sum();
+        while history.undo.len() > self.config.max_entries || (total > self.config.max_bytes && history.undo.len() > 1) {
+            if let Some(dropped) = history.undo.pop_front() {
+                total -= dropped.bytes();
+            }
+        }
+        // A single change over the byte budget is not kept at all
+        if total > self.config.max_bytes {
+            history.undo.clear();
+        }
+    }
+
+    /// Revert the session's latest change, or its latest change to `path`
+    pub async fn undo(&self, session: Option<&str>, path: Option<&str>) -> Result<Value> {
+        let change = {
+            let mut state = self.state();
+            let history = state.sessions.entry(key(session)).or_default();
+            let index = history.undo.iter().rposition(|c| path.is_none_or(|p| p == c.path))
+                .ok_or_else(|| ToolError::not_found(match path {
+                    Some(p) => format!("No recorded changes to undo for {}", p),
+                    None => "No recorded changes to undo".to_string(),
+                }))?;
+            history.undo.remove(index).expect("index from rposition")
+        };
+        if let Err(e) = self.apply(&change, change.after.as_deref(), change.before.as_deref()).await {
+            self.state().sessions.entry(key(session)).or_default().undo.push_back(change);
+            return Err(e);
+        }
+        let result = json!({ "undone": change.to_json() });
+        self.state().sessions.entry(key(session)).or_default().redo.push(change);
+        Ok(result)
+    }
+
+    /// Reapply the change most recently undone in the session
+    pub async fn redo(&self, session: Option<&str>, path: Option<&str>) -> Result<Value> {
+        let change = {
+            let mut state = self.state();
+            let history = state.sessions.entry(key(session)).or_default();
+            let index = history.redo.iter().rposition(|c| path.is_none_or(|p| p == c.path))
+                .ok_or_else(|| ToolError::not_found("No undone changes to redo"))?;
+            history.redo.remove(index)
+        };
+        if let Err(e) = self.apply(&change, change.before.as_deref(), change.after.as_deref()).await {
+            self.state().sessions.entry(key(session)).or_default().redo.push(change);
+            return Err(e);
+        }
+        let result = json!({ "redone": change.to_json() });
+        self.state().sessions.entry(key(session)).or_default().undo.push_back(change);
+        Ok(result)
+    }
+
+    /// Move `change.path` from `expected` to `target`, refusing if the file
+    /// has been modified outside the journal
+    async fn apply(&self, change: &Change, expected: Option<&[u8]>, target: Option<&[u8]>) -> Result<()> {
+        let current = snapshot(&change.path).await?;
+        if current.as_deref() != expected {
+            return Err(ToolError::conflict(format!(
+                "{} changed since journal entry {}; refusing to overwrite it",
+                change.path, change.id
+            )).into());
+        }
+        restore(&change.path, target).await
+    }
+
+    /// Changes the session can undo and redo, newest first
+    pub fn history(&self, session: Option<&str>, path: Option<&str>) -> Value {
+        let state = self.state();
+        let Some(history) = state.sessions.get(&key(session)) else {
+            return json!({ "undo": [], "redo": [] });
+        };
+        let matching = |c: &&Change| path.is_none_or(|p| p == c.path);
+        let undo: Vec<Value> = history.undo.iter().rev().filter(matching).map(Change::to_json).collect();
+        let redo: Vec<Value> = history.redo.iter().rev().filter(matching).map(Change::to_json).collect();
+        json!({ "undo": undo, "redo": redo })
+    }
+
+    /// Forget everything recorded for a session
+    pub fn end_session(&self, session: &str) {
+        self.state().sessions.remove(session);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn test_undo_redo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.txt").to_string_lossy().to_string();
+        let journal = Journal::new(JournalConfig::default());
+
+        std::fs::write(&path, "one").unwrap();
+        journal.record(None, &path, "write", None, Some(b"one".to_vec()));
+        std::fs::write(&path, "two").unwrap();
+        journal.record(None, &path, "edit", Some(b"one".to_vec()), Some(b"two".to_vec()));
+
+        journal.undo(None, Some(&path)).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one");
+        journal.undo(None, None).await.unwrap();
+        assert!(!Path::new(&path).exists());
+        assert_eq!(journal.history(None, None)["redo"].as_array().unwrap().len(), 2);
+
+        journal.redo(None, None).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one");
+
+        // Edits made outside the journal are never overwritten
+        std::fs::write(&path, "mine").unwrap();
+        journal.redo(None, None).await.unwrap_err();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "mine");
+    }
+
+    #[test]
+    fn test_budget_and_sessions() {
+        let journal = Journal::new(JournalConfig { max_entries: 2, max_bytes: 10 });
+        for i in 0..3 {
+            journal.record(Some("s1"), &format!("f{}", i), "write", None, Some(b"abc".to_vec()));
+        }
+        let history = journal.history(Some("s1"), None);
+        assert_eq!(history["undo"].as_array().unwrap().len(), 2);
+        assert_eq!(history["undo"][0]["path"], "f2");
+
+        journal.record(Some("s1"), "big", "write", None, Some(vec![0; 11]));
+        assert_eq!(journal.history(Some("s1"), None)["undo"].as_array().unwrap().len(), 0);
+        assert_eq!(journal.history(Some("s2"), None)["undo"].as_array().unwrap().len(), 0);
+        journal.end_session("s1");
+    }
+}
@@ -0,0 +1,145 @@
+/// Symlinks for fs
+///
+/// The `symlink`, `readlink` and `realpath` actions, link details for
+/// `info`, and cycle reporting for walks with `follow_symlinks`. Followed
+/// walks rely on walkdir's ancestor check, so a link pointing back into its
+/// own tree is reported once instead of being descended forever.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// Target, resolved path and broken state of `path` if it is a symlink
+pub fn describe(path: &Path) -> Option<Value> {
+    let metadata = std::fs::symlink_metadata(path).ok()?;
+    if !metadata.file_type().is_symlink() {
+        return None;
+    }
+    let target = std::fs::read_link(path).ok()?;
+    let resolved = std::fs::canonicalize(path).ok();
+    Some(json!({
+        "target": target.to_string_lossy(),
+        "resolved": resolved.as_ref().map(|p| p.to_string_lossy().into_owned()),
+        "broken": resolved.is_none()
+    }))
+}
+
+/// Create a symlink at `link` pointing to `target`. `target` is stored as
+/// given, so relative targets resolve against the link's directory. Only
+/// an existing symlink is replaced, and only with `overwrite`.
+pub fn symlink(link: &Path, target: &Path, overwrite: bool) -> Result<Value> {
+    if let Ok(existing) = std::fs::symlink_metadata(link) {
+        if !existing.file_type().is_symlink() {
+            return Err(ToolError::conflict(format!("{} exists and is not a symlink", link.display())).into());
+        }
+        if !overwrite {
+            return Err(ToolError::conflict(format!(
+                "{} already links to {} (set overwrite to replace it)",
+                link.display(),
+                std::fs::read_link(link)?.display()
+            )).into());
+        }
+        std::fs::remove_file(link)?;
+    }
+    if let Some(parent) = link.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    create(link, target)?;
+    let mut result = describe(link).unwrap_or_else(|| json!({}));
+    result["path"] = json!(link.to_string_lossy());
+    Ok(result)
+}
+
+#[cfg(unix)]
+fn create(link: &Path, target: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn create(link: &Path, target: &Path) -> Result<()> {
+    let base = link.parent().unwrap_or(Path::new("."));
+    if base.join(target).is_dir() {
+        std::os::windows::fs::symlink_dir(target, link)?;
+    } else {
+        std::os::windows::fs::symlink_file(target, link)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+fn create(_link: &Path, _target: &Path) -> Result<()> {
+    Err(ToolError::unsupported("Symlinks are not supported on this platform").into())
+}
+
+pub fn readlink(path: &Path) -> Result<Value> {
+    let mut result = describe(path)
+        .ok_or_else(|| ToolError::invalid(format!("{} is not a symlink", path.display())))?;
+    result["path"] = json!(path.to_string_lossy());
+    Ok(result)
+}
+
+/// Absolute path with every symlink and `..` resolved
+pub fn realpath(path: &Path) -> Result<Value> {
+    let resolved = std::fs::canonicalize(path).map_err(|e| {
+        if describe(path).is_some() {
+            ToolError::not_found(format!("{} is a broken symlink", path.display()))
+        } else {
+            ToolError::not_found(format!("{}: {}", path.display(), e))
+        }
+    })?;
+    Ok(json!({
+        "path": path.to_string_lossy(),
+        "realpath": resolved.to_string_lossy(),
+        "symlink": describe(path).is_some()
+    }))
+}
+
+/// The link and the ancestor it leads back to, if `err` is a symlink cycle
+pub fn cycle(err: &walkdir::Error) -> Option<Value> {
+    let ancestor = err.loop_ancestor()?;
+    let link = err.path().map(PathBuf::from).unwrap_or_default();
+    Some(json!({
+        "link": link.to_string_lossy(),
+        "ancestor": ancestor.to_string_lossy()
+    }))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use walkdir::WalkDir;
+
+    #[test]
+    fn test_links_and_cycles() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir(root.join("pkg")).unwrap();
+        std::fs::write(root.join("pkg/lib.rs"), "").unwrap();
+
+        let created = symlink(&root.join("current"), Path::new("pkg"), false).unwrap();
+        assert_eq!(created["target"], "pkg");
+        assert_eq!(created["broken"], false);
+        assert!(symlink(&root.join("current"), Path::new("pkg"), false).is_err());
+        assert!(symlink(&root.join("pkg/lib.rs"), Path::new("pkg"), true).is_err());
+
+        let real = realpath(&root.join("current/lib.rs")).unwrap();
+        assert!(real["realpath"].as_str().unwrap().ends_with("pkg/lib.rs"));
+
+        symlink(&root.join("gone"), Path::new("missing"), false).unwrap();
+        assert_eq!(readlink(&root.join("gone")).unwrap()["broken"], true);
+        assert!(realpath(&root.join("gone")).is_err());
+        assert!(readlink(&root.join("pkg")).is_err());
+
+        // pkg/loop -> .. leads back into itself through the root
+        std::fs::remove_file(root.join("current")).unwrap();
+        symlink(&root.join("pkg/loop"), Path::new(".."), false).unwrap();
+        let cycles: Vec<_> = WalkDir::new(root.join("pkg")).follow_links(true).into_iter()
+            .filter_map(|e| e.err())
+            .filter_map(|e| cycle(&e))
+            .collect();
+        assert_eq!(cycles.len(), 1);
+        assert!(cycles[0]["ancestor"].as_str().unwrap().ends_with("pkg/loop"));
+    }
+}
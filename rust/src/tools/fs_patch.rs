@@ -0,0 +1,502 @@
+/// Patch parsing and application for fs
+///
+/// Reads unified diffs (`git diff`, `diff -u`, including git's new, deleted
+/// and renamed file headers) as well as the `*** Begin Patch` format. A hunk
+/// is tried where its header says, then at the nearest offset where its old
+/// lines match, then with up to `fuzz` lines of leading and trailing context
+/// ignored, as GNU patch does. Hunks without line numbers (the `*** Begin
+/// Patch` format) must match exactly one place in the file.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::{json, Value};
+
+/// Context lines a hunk may ignore at each end unless told otherwise
+pub const DEFAULT_FUZZ: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Context,
+    Delete,
+    Insert,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Hunk {
+    /// 1-based first old line, from a unified diff header
+    pub old_start: Option<usize>,
+    pub new_start: Option<usize>,
+    /// Text after `@@` in the `*** Begin Patch` format, used to pick
+    /// between several matches
+    pub anchor: Option<String>,
+    pub lines: Vec<(Kind, String)>,
+    /// `\ No newline at end of file` followed the old or new side
+    pub old_no_newline: bool,
+    pub new_no_newline: bool,
+}
+
+impl Hunk {
+    fn side(&self, skip: Kind) -> Vec<&str> {
+        self.lines.iter().filter(|(k, _)| *k != skip).map(|(_, l)| l.as_str()).collect()
+    }
+
+    pub fn header(&self) -> String {
+        match (self.old_start, self.new_start) {
+            (Some(old), Some(new)) => format!(
+                "@@ -{},{} +{},{} @@",
+                old,
+                self.side(Kind::Insert).len(),
+                new,
+                self.side(Kind::Delete).len()
+            ),
+            _ => format!("@@ {}", self.anchor.as_deref().unwrap_or("")).trim_end().to_string(),
+        }
+    }
+}
+
+/// One file's changes. A missing old path creates the file, a missing new
+/// path deletes it.
+#[derive(Debug, Clone, Default)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub rename: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    pub fn op(&self) -> &'static str {
+        match (&self.old_path, &self.new_path) {
+            (None, _) => "add",
+            (_, None) => "delete",
+            _ if self.rename => "rename",
+            _ => "update",
+        }
+    }
+
+    /// The patch that undoes this one
+    pub fn reverse(&mut self) {
+        std::mem::swap(&mut self.old_path, &mut self.new_path);
+        for hunk in &mut self.hunks {
+            std::mem::swap(&mut hunk.old_start, &mut hunk.new_start);
+            std::mem::swap(&mut hunk.old_no_newline, &mut hunk.new_no_newline);
+            for (kind, _) in &mut hunk.lines {
+                *kind = match kind {
+                    Kind::Delete => Kind::Insert,
+                    Kind::Insert => Kind::Delete,
+                    Kind::Context => Kind::Context,
+                };
+            }
+        }
+    }
+}
+
+/// Parse a unified diff or a `*** Begin Patch` block. `strip` drops that
+/// many leading path components, like `patch -p`; by default git's `a/`
+/// and `b/` prefixes are dropped.
+pub fn parse(text: &str, strip: Option<usize>) -> Result<Vec<FilePatch>> {
+    let legacy = text.lines().any(|l| {
+        l.starts_with("*** Begin Patch")
+            || l.starts_with("*** Add File:")
+            || l.starts_with("*** Update File:")
+            || l.starts_with("*** Delete File:")
+    });
+    let files = if legacy { parse_legacy(text) } else { parse_unified(text, strip)? };
+    if files.is_empty() {
+        return Err(ToolError::invalid("No file changes found in patch (expected a unified diff or *** Begin Patch)").into());
+    }
+    Ok(files)
+}
+
+fn parse_unified(text: &str, strip: Option<usize>) -> Result<Vec<FilePatch>> {
+    let mut files = Vec::new();
+    let mut current: Option<FilePatch> = None;
+    // Whether the current file started at `diff --git`, whose header lines
+    // all belong to it
+    let mut git = false;
+    let mut lines = text.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            files.extend(current.take());
+            git = true;
+            let (old, new) = rest.split_once(" b/")
+                .map(|(a, b)| (a.to_string(), format!("b/{}", b)))
+                .unwrap_or_else(|| (rest.to_string(), rest.to_string()));
+            current = Some(FilePatch {
+                old_path: Some(strip_path(&old, strip, true)),
+                new_path: Some(strip_path(&new, strip, true)),
+                ..Default::default()
+            });
+        } else if line.starts_with("new file mode") {
+            if let Some(file) = current.as_mut() {
+                file.old_path = None;
+            }
+        } else if line.starts_with("deleted file mode") {
+            if let Some(file) = current.as_mut() {
+                file.new_path = None;
+            }
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            if let Some(file) = current.as_mut() {
+                file.old_path = Some(path.to_string());
+                file.rename = true;
+            }
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            if let Some(file) = current.as_mut() {
+                file.new_path = Some(path.to_string());
+                file.rename = true;
+            }
+        } else if line.starts_with("Binary files ") || line.starts_with("GIT binary patch") {
+            return Err(ToolError::unsupported("Binary patches are not supported").into());
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            let continues = git && current.as_ref().is_some_and(|f| f.hunks.is_empty());
+            if !continues {
+                files.extend(current.take());
+                git = false;
+                current = Some(FilePatch::default());
+            }
+            let file = current.as_mut().expect("file started above");
+            file.old_path = header_path(path).map(|p| strip_path(&p, strip, git || p.starts_with("a/")));
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if let Some(file) = current.as_mut() {
+                file.new_path = header_path(path).map(|p| strip_path(&p, strip, git || p.starts_with("b/")));
+            }
+        } else if line.starts_with("@@ ") {
+            let file = current.as_mut()
+                .ok_or_else(|| ToolError::invalid(format!("Hunk before any file header: {}", line)))?;
+            let (old_start, mut old_left, new_start, mut new_left) = hunk_header(line)?;
+            let mut hunk = Hunk { old_start: Some(old_start), new_start: Some(new_start), ..Default::default() };
+            let too_long = || ToolError::invalid(format!("Hunk {} has more lines than its header says", line));
+            while old_left > 0 || new_left > 0 || lines.peek().is_some_and(|l| l.starts_with('\\')) {
+                let Some(line) = lines.next() else { break };
+                let (kind, body) = match line.chars().next() {
+                    Some('\\') => {
+                        match hunk.lines.last().map(|(k, _)| *k) {
+                            Some(Kind::Delete) => hunk.old_no_newline = true,
+                            Some(Kind::Insert) => hunk.new_no_newline = true,
+                            _ => {
+                                hunk.old_no_newline = true;
+                                hunk.new_no_newline = true;
+                            }
+                        }
+                        continue;
+                    }
+                    // Editors often strip the space from empty context lines
+                    None | Some(' ') => (Kind::Context, line.get(1..).unwrap_or("")),
+                    Some('-') => (Kind::Delete, &line[1..]),
+                    Some('+') => (Kind::Insert, &line[1..]),
+                    _ => return Err(too_long().into()),
+                };
+                if kind != Kind::Insert {
+                    old_left = old_left.checked_sub(1).ok_or_else(too_long)?;
+                }
+                if kind != Kind::Delete {
+                    new_left = new_left.checked_sub(1).ok_or_else(too_long)?;
+                }
+                hunk.lines.push((kind, body.to_string()));
+            }
+            if old_left > 0 || new_left > 0 {
+                return Err(ToolError::invalid(format!("Patch ends in the middle of hunk {}", hunk.header())).into());
+            }
+            file.hunks.push(hunk);
+        }
+        // Anything else (index lines, mode changes, commit messages) is ignored
+    }
+    files.extend(current.take());
+    Ok(files.into_iter().filter(|f| f.old_path.is_some() || f.new_path.is_some()).collect())
+}
+
+/// Path from a `---`/`+++` line, without any timestamp; `None` for /dev/null
+fn header_path(text: &str) -> Option<String> {
+    let path = text.split('\t').next().unwrap_or(text).trim_end();
+    let path = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')).unwrap_or(path);
+    (path != "/dev/null").then(|| path.to_string())
+}
+
+fn strip_path(path: &str, strip: Option<usize>, git_prefix: bool) -> String {
+    let count = strip.unwrap_or(usize::from(git_prefix && (path.starts_with("a/") || path.starts_with("b/"))));
+    path.splitn(count + 1, '/').nth(count).unwrap_or(path).to_string()
+}
+
+/// `@@ -a,b +c,d @@` into (a, b, c, d); a missing count means 1
+fn hunk_header(line: &str) -> Result<(usize, usize, usize, usize)> {
+    let invalid = || ToolError::invalid(format!("Malformed hunk header: {}", line));
+    let mut parts = line.split_whitespace().skip(1);
+    let mut range = |sign: char| -> Result<(usize, usize)> {
+        let part = parts.next().and_then(|p| p.strip_prefix(sign)).ok_or_else(invalid)?;
+        let (start, count) = part.split_once(',').unwrap_or((part, "1"));
+        Ok((start.parse().map_err(|_| invalid())?, count.parse().map_err(|_| invalid())?))
+    };
+    let (old_start, old_count) = range('-')?;
+    let (new_start, new_count) = range('+')?;
+    Ok((old_start, old_count, new_start, new_count))
+}
+
+fn parse_legacy(text: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = Vec::new();
+    for line in text.lines() {
+        if line.starts_with("*** Begin Patch") || line.starts_with("*** End Patch") || line.starts_with("*** End of File") {
+            continue;
+        }
+        let header = |prefix: &str| line.strip_prefix(prefix).map(|p| p.trim().to_string());
+        if let Some(path) = header("*** Add File:") {
+            files.push(FilePatch { new_path: Some(path), hunks: vec![Hunk::default()], ..Default::default() });
+        } else if let Some(path) = header("*** Update File:") {
+            files.push(FilePatch { old_path: Some(path.clone()), new_path: Some(path), ..Default::default() });
+        } else if let Some(path) = header("*** Delete File:") {
+            files.push(FilePatch { old_path: Some(path), ..Default::default() });
+        } else if let Some(path) = header("*** Move to:") {
+            if let Some(file) = files.last_mut() {
+                file.new_path = Some(path);
+                file.rename = true;
+            }
+        } else if let Some(file) = files.last_mut() {
+            if let Some(anchor) = line.strip_prefix("@@") {
+                let anchor = anchor.trim();
+                file.hunks.push(Hunk {
+                    anchor: (!anchor.is_empty()).then(|| anchor.to_string()),
+                    ..Default::default()
+                });
+                continue;
+            }
+            let kind = match line.chars().next() {
+                Some('+') => Kind::Insert,
+                Some('-') => Kind::Delete,
+                Some(' ') => Kind::Context,
+                _ => continue,
+            };
+            if file.hunks.is_empty() {
+                file.hunks.push(Hunk::default());
+            }
+            file.hunks.last_mut().expect("pushed above").lines.push((kind, line[1..].to_string()));
+        }
+    }
+    files
+}
+
+/// Result of applying one file's hunks
+pub struct Applied {
+    pub text: String,
+    /// Per-hunk status: where it applied, with what offset and fuzz, or why not
+    pub hunks: Vec<Value>,
+    pub failed: usize,
+}
+
+/// Apply `hunks` to LF-terminated `text` (`None` for a file being created)
+pub fn apply(text: Option<&str>, hunks: &[Hunk], fuzz: usize) -> Applied {
+    let text = text.unwrap_or("");
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let mut newline = text.is_empty() || text.ends_with('\n');
+    // Where the file has drifted from the line numbers in the headers
+    let mut adjust: isize = 0;
+    let mut reports = Vec::new();
+    let mut failed = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let old_len = hunk.lines.iter().filter(|(k, _)| *k != Kind::Insert).count();
+        // A hunk with no old lines inserts after its start line
+        let base = hunk.old_start.map(|start| if old_len == 0 { start } else { start.saturating_sub(1) });
+        let expected = base.map(|base| (base as isize + adjust).clamp(0, lines.len() as isize) as usize);
+
+        match place(&lines, hunk, expected, fuzz) {
+            Ok((at, front, back, used)) => {
+                let body = &hunk.lines[front..hunk.lines.len() - back];
+                let old = body.iter().filter(|(k, _)| *k != Kind::Insert).count();
+                let new: Vec<String> = body.iter()
+                    .filter(|(k, _)| *k != Kind::Delete)
+                    .map(|(_, l)| l.clone())
+                    .collect();
+                let added = new.len() as isize - old as isize;
+                let reaches_end = at + old == lines.len();
+                lines.splice(at..at + old, new);
+                if reaches_end && hunk.new_no_newline {
+                    newline = false;
+                } else if reaches_end && hunk.old_no_newline {
+                    newline = true;
+                }
+
+                let mut report = json!({
+                    "hunk": index + 1,
+                    "header": hunk.header(),
+                    "status": "applied",
+                    "line": at + 1,
+                    "fuzz": used
+                });
+                if let (Some(expected), Some(base)) = (expected, base) {
+                    report["offset"] = json!(at as isize - (expected + front) as isize);
+                    adjust = (at - front) as isize - base as isize + added;
+                }
+                reports.push(report);
+            }
+            Err(reason) => {
+                failed += 1;
+                reports.push(json!({
+                    "hunk": index + 1,
+                    "header": hunk.header(),
+                    "status": "failed",
+                    "reason": reason
+                }));
+            }
+        }
+    }
+
+    let mut text = lines.join("\n");
+    if newline && !lines.is_empty() {
+        text.push('\n');
+    }
+    Applied { text, hunks: reports, failed }
+}
+
+/// Where `hunk` applies: (line index, context lines trimmed from the front
+/// and back, fuzz used)
+fn place(lines: &[String], hunk: &Hunk, expected: Option<usize>, fuzz: usize) -> std::result::Result<(usize, usize, usize, usize), String> {
+    let leading = hunk.lines.iter().take_while(|(k, _)| *k == Kind::Context).count();
+    let trailing = hunk.lines.iter().rev().take_while(|(k, _)| *k == Kind::Context).count();
+    let mut error = String::from("context not found");
+
+    for used in 0..=fuzz {
+        let (front, back) = (used.min(leading), used.min(trailing));
+        if used > 0 && (front + back == 0 || front + back >= hunk.lines.len()) {
+            break;
+        }
+        let old: Vec<&str> = hunk.lines[front..hunk.lines.len() - back].iter()
+            .filter(|(k, _)| *k != Kind::Insert)
+            .map(|(_, l)| l.as_str())
+            .collect();
+        match locate(lines, &old, expected.map(|e| e + front), hunk.anchor.as_deref()) {
+            Ok(at) => return Ok((at, front, back, used)),
+            Err(reason) if used == 0 => error = reason,
+            Err(_) => {}
+        }
+    }
+    Err(match expected {
+        Some(expected) => format!("{} near line {}", error, expected + 1),
+        None => error,
+    })
+}
+
+fn locate(lines: &[String], old: &[&str], expected: Option<usize>, anchor: Option<&str>) -> std::result::Result<usize, String> {
+    if old.is_empty() {
+        // Pure insertion: at its line, else after the anchor, else at the end
+        let after_anchor = anchor.and_then(|a| lines.iter().position(|l| l.contains(a)).map(|i| i + 1));
+        return Ok(expected.or(after_anchor).unwrap_or(lines.len()).min(lines.len()));
+    }
+    if old.len() > lines.len() {
+        return Err("context not found".into());
+    }
+    let matches: Vec<usize> = (0..=lines.len() - old.len())
+        .filter(|&at| lines[at..at + old.len()].iter().zip(old).all(|(a, b)| a == b))
+        .collect();
+
+    if let Some(expected) = expected {
+        return matches.into_iter()
+            .min_by_key(|&at| at.abs_diff(expected))
+            .ok_or_else(|| "context not found".into());
+    }
+    match matches.as_slice() {
+        [] => Err("context not found".into()),
+        [at] => Ok(*at),
+        _ => {
+            let after_anchor = anchor
+                .and_then(|a| lines.iter().position(|l| l.contains(a)))
+                .and_then(|line| matches.iter().find(|&&at| at >= line));
+            after_anchor.copied().ok_or_else(|| format!(
+                "ambiguous: old lines match at lines {}; add context to pick one",
+                matches.iter().map(|m| (m + 1).to_string()).collect::<Vec<_>>().join(", ")
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,4 +1,4 @@
+ one
+-two
++TWO
+ three
+ four
+@@ -7,3 +7,4 @@ fn six()
+ seven
+ eight
+ nine
++ten
+diff --git a/notes.txt b/notes.txt
+new file mode 100644
+--- /dev/null
++++ b/notes.txt
+@@ -0,0 +1,2 @@
++hello
++world
+\\ No newline at end of file
+";
+
+    fn file(lines: &[&str]) -> String {
+        lines.iter().map(|l| format!("{}\n", l)).collect()
+    }
+
+    #[test]
+    fn test_parse_git_diff() {
+        let files = parse(DIFF, None).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].op(), "update");
+        assert_eq!(files[0].new_path.as_deref(), Some("src/lib.rs"));
+        assert_eq!(files[0].hunks.len(), 2);
+        assert_eq!(files[0].hunks[1].header(), "@@ -7,3 +7,4 @@");
+        assert_eq!(files[1].op(), "add");
+        assert!(files[1].hunks[0].new_no_newline);
+
+        let added = apply(None, &files[1].hunks, 0);
+        assert_eq!(added.text, "hello\nworld");
+    }
+
+    #[test]
+    fn test_offset_fuzz_and_reverse() {
+        let mut files = parse(DIFF, None).unwrap();
+        let original = file(&["one", "two", "three", "four", "five", "six", "seven", "eight", "nine"]);
+
+        let exact = apply(Some(&original), &files[0].hunks, 0);
+        assert_eq!(exact.failed, 0);
+        assert_eq!(exact.text, file(&["one", "TWO", "three", "four", "five", "six", "seven", "eight", "nine", "ten"]));
+
+        // Two lines inserted at the top shift both hunks by two
+        let shifted = format!("x\ny\n{}", original);
+        let moved = apply(Some(&shifted), &files[0].hunks, 0);
+        assert_eq!(moved.failed, 0);
+        assert_eq!(moved.hunks[0]["offset"], 2);
+        assert_eq!(moved.hunks[1]["line"], 9);
+
+        // A changed context line needs fuzz
+        let drifted = original.replace("four", "FOUR");
+        assert_eq!(apply(Some(&drifted), &files[0].hunks, 0).failed, 1);
+        let fuzzed = apply(Some(&drifted), &files[0].hunks, 1);
+        assert_eq!(fuzzed.failed, 0);
+        assert_eq!(fuzzed.hunks[0]["fuzz"], 1);
+
+        files[0].reverse();
+        assert_eq!(apply(Some(&exact.text), &files[0].hunks, 0).text, original);
+        files[1].reverse();
+        assert_eq!(files[1].op(), "delete");
+    }
+
+    #[test]
+    fn test_legacy_format() {
+        let patch = "*** Begin Patch\n*** Update File: a.txt\n@@ fn b\n-    x\n+    y\n*** End Patch";
+        let files = parse(patch, None).unwrap();
+        assert_eq!(files[0].op(), "update");
+
+        let text = "fn a\n    x\nfn b\n    x\n";
+        assert_eq!(apply(Some(text), &files[0].hunks, 0).text, "fn a\n    x\nfn b\n    y\n");
+
+        let unanchored = parse("*** Update File: a.txt\n-    x\n+    y\n", None).unwrap();
+        let ambiguous = apply(Some(text), &unanchored[0].hunks, 0);
+        assert_eq!(ambiguous.failed, 1);
+        assert!(ambiguous.hunks[0]["reason"].as_str().unwrap().starts_with("ambiguous"));
+    }
+}
@@ -0,0 +1,246 @@
+/// Permissions, ownership and timestamps for fs
+///
+/// Mode bits, owner/group and extended attributes for `info`, and the
+/// `chmod`, `chown` and `touch` actions. Modes are octal (`755`) or
+/// symbolic (`u+x,go-w`). Recursive changes never follow symlinks;
+/// ownership and extended attributes are Unix-only.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// `-rwxr-xr-x` style rendering of the low mode bits
+pub fn mode_string(mode: u32, is_dir: bool, is_symlink: bool) -> String {
+    let kind = if is_symlink { 'l' } else if is_dir { 'd' } else { '-' };
+    let mut out = String::from(kind);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 7;
+        out.push(if bits & 4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 2 != 0 { 'w' } else { '-' });
+        out.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    out
+}
+
+/// New mode from `spec`, octal or symbolic (`[ugoa]*[+-=][rwxX]*`, comma
+/// separated), applied to `current`
+pub fn parse_mode(spec: &str, current: u32, is_dir: bool) -> Result<u32> {
+    let spec = spec.trim();
+    let octal = spec.trim_start_matches("0o");
+    if !octal.is_empty() && octal.chars().all(|c| c.is_digit(8)) {
+        return u32::from_str_radix(octal, 8)
+            .ok()
+            .filter(|m| *m <= 0o7777)
+            .ok_or_else(|| ToolError::invalid(format!("Invalid mode: {}", spec)).into());
+    }
+
+    let invalid = || ToolError::invalid(format!("Invalid mode: {} (use octal like 755 or symbolic like u+x,go-w)", spec));
+    let mut mode = current & 0o7777;
+    for clause in spec.split(',') {
+        let op_at = clause.find(['+', '-', '=']).ok_or_else(invalid)?;
+        let (who, rest) = clause.split_at(op_at);
+        let (op, perms) = rest.split_at(1);
+        let mut mask = 0;
+        for c in who.chars() {
+            mask |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                'a' => 0o777,
+                _ => return Err(invalid().into()),
+            };
+        }
+        if mask == 0 {
+            mask = 0o777;
+        }
+        let mut bits = 0;
+        for c in perms.chars() {
+            bits |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                // Execute only for directories or files already executable by someone
+                'X' if is_dir || mode & 0o111 != 0 => 0o111,
+                'X' => 0,
+                _ => return Err(invalid().into()),
+            };
+        }
+        match op {
+            "+" => mode |= bits & mask,
+            "-" => mode &= !(bits & mask),
+            _ => mode = (mode & !mask) | (bits & mask),
+        }
+    }
+    Ok(mode)
+}
+
+/// Paths to change: `path` itself, plus everything below it when
+/// `recursive`, without following symlinks
+fn targets(path: &Path, recursive: bool) -> impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> {
+    WalkDir::new(path).follow_links(false).max_depth(if recursive { usize::MAX } else { 0 }).into_iter()
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use nix::unistd::{Gid, Group, Uid, User};
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    /// Mode, owner, group and extended attributes of `path`
+    pub fn describe(path: &Path) -> Value {
+        let Ok(metadata) = std::fs::symlink_metadata(path) else {
+            return json!({});
+        };
+        let mode = metadata.mode() & 0o7777;
+        let owner = User::from_uid(Uid::from_raw(metadata.uid())).ok().flatten().map(|u| u.name);
+        let group = Group::from_gid(Gid::from_raw(metadata.gid())).ok().flatten().map(|g| g.name);
+        let mut xattrs = serde_json::Map::new();
+        if let Ok(names) = xattr::list(path) {
+            for name in names {
+                let name = name.to_string_lossy().into_owned();
+                if let Ok(Some(value)) = xattr::get(path, &name) {
+                    xattrs.insert(name, json!(String::from_utf8(value.clone()).unwrap_or_else(|_| {
+                        use base64::Engine;
+                        format!("base64:{}", base64::engine::general_purpose::STANDARD.encode(value))
+                    })));
+                }
+            }
+        }
+        json!({
+            "mode": format!("{:04o}", mode),
+            "permissions": mode_string(mode, metadata.is_dir(), metadata.file_type().is_symlink()),
+            "uid": metadata.uid(),
+            "gid": metadata.gid(),
+            "owner": owner,
+            "group": group,
+            "xattrs": xattrs
+        })
+    }
+
+    pub fn chmod(path: &Path, spec: &str, recursive: bool) -> Result<Value> {
+        let mut changed = 0;
+        for entry in targets(path, recursive) {
+            let entry = entry?;
+            if entry.path_is_symlink() {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let mode = parse_mode(spec, metadata.mode(), metadata.is_dir())?;
+            std::fs::set_permissions(entry.path(), std::fs::Permissions::from_mode(mode))?;
+            changed += 1;
+        }
+        Ok(json!({ "path": path.display().to_string(), "changed": changed, "info": describe(path) }))
+    }
+
+    fn uid(owner: &str) -> Result<u32> {
+        if let Ok(uid) = owner.parse() {
+            return Ok(uid);
+        }
+        User::from_name(owner)?
+            .map(|u| u.uid.as_raw())
+            .ok_or_else(|| ToolError::not_found(format!("No such user: {}", owner)).into())
+    }
+
+    fn gid(group: &str) -> Result<u32> {
+        if let Ok(gid) = group.parse() {
+            return Ok(gid);
+        }
+        Group::from_name(group)?
+            .map(|g| g.gid.as_raw())
+            .ok_or_else(|| ToolError::not_found(format!("No such group: {}", group)).into())
+    }
+
+    pub fn chown(path: &Path, owner: Option<&str>, group: Option<&str>, recursive: bool) -> Result<Value> {
+        if owner.is_none() && group.is_none() {
+            return Err(ToolError::invalid("owner or group required").into());
+        }
+        let uid = owner.map(uid).transpose()?;
+        let gid = group.map(gid).transpose()?;
+        let mut changed = 0;
+        for entry in targets(path, recursive) {
+            std::os::unix::fs::lchown(entry?.path(), uid, gid)?;
+            changed += 1;
+        }
+        Ok(json!({ "path": path.display().to_string(), "changed": changed, "info": describe(path) }))
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{chmod, chown, describe};
+
+#[cfg(not(unix))]
+pub fn describe(_path: &Path) -> Value {
+    json!({})
+}
+
+#[cfg(not(unix))]
+pub fn chmod(_path: &Path, _spec: &str, _recursive: bool) -> Result<Value> {
+    Err(ToolError::unsupported("chmod is only available on Unix").into())
+}
+
+#[cfg(not(unix))]
+pub fn chown(_path: &Path, _owner: Option<&str>, _group: Option<&str>, _recursive: bool) -> Result<Value> {
+    Err(ToolError::unsupported("chown is only available on Unix").into())
+}
+
+/// Create `path` if missing and set its access and modification times to
+/// `time`, or now
+pub fn touch(path: &Path, time: Option<chrono::DateTime<chrono::Utc>>) -> Result<Value> {
+    let created = !path.exists();
+    if created {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::File::create(path)?;
+    }
+    let time = match time {
+        Some(t) => filetime::FileTime::from_unix_time(t.timestamp(), t.timestamp_subsec_nanos()),
+        None => filetime::FileTime::now(),
+    };
+    filetime::set_file_times(path, time, time)?;
+    let modified = std::fs::metadata(path)?.modified().ok().map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+    Ok(json!({ "path": path.display().to_string(), "created": created, "modified": modified }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode("755", 0, false).unwrap(), 0o755);
+        assert_eq!(parse_mode("0o600", 0o777, false).unwrap(), 0o600);
+        assert_eq!(parse_mode("u+x,go-w", 0o666, false).unwrap(), 0o744);
+        assert_eq!(parse_mode("a=r", 0o777, false).unwrap(), 0o444);
+        assert_eq!(parse_mode("+X", 0o644, true).unwrap(), 0o755);
+        assert_eq!(parse_mode("+X", 0o644, false).unwrap(), 0o644);
+        assert!(parse_mode("u+z", 0, false).is_err());
+        assert!(parse_mode("99999", 0, false).is_err());
+        assert_eq!(mode_string(0o750, true, false), "drwxr-x---");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_chmod_and_touch() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("deploy/run.sh");
+        let touched = touch(&file, Some(chrono::DateTime::from_timestamp(1_000_000_000, 0).unwrap())).unwrap();
+        assert_eq!(touched["created"], true);
+        assert!(touched["modified"].as_str().unwrap().starts_with("2001-09-09"));
+
+        chmod(&dir.path().join("deploy"), "go-rwx,u+x", true).unwrap();
+        assert_eq!(std::fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o700);
+        let info = describe(&file);
+        assert_eq!(info["mode"], "0700");
+        assert_eq!(info["permissions"], "-rwx------");
+
+        // chown to ourselves is always allowed
+        let uid = info["uid"].as_u64().unwrap().to_string();
+        chown(&file, Some(&uid), None, false).unwrap();
+        assert!(chown(&file, None, None, false).is_err());
+    }
+}
@@ -0,0 +1,113 @@
+/// Template rendering for fs
+///
+/// Renders Handlebars or Tera templates with JSON variables for the
+/// `render` action. Output is source code rather than HTML, so nothing is
+/// escaped, and a variable the template uses but the caller did not supply
+/// is an error rather than an empty string.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::Value;
+
+/// Template languages offered by `fs render`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Engine {
+    #[default]
+    Handlebars,
+    Tera,
+}
+
+impl std::str::FromStr for Engine {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "handlebars" | "hbs" | "mustache" => Ok(Self::Handlebars),
+            "tera" | "jinja" | "jinja2" | "j2" => Ok(Self::Tera),
+            _ => Err(ToolError::invalid(format!("Unknown template engine: {} (use handlebars or tera)", s)).into()),
+        }
+    }
+}
+
+impl Engine {
+    /// Engine named by `engine`, else guessed from the template file's
+    /// extension, else Handlebars
+    pub fn detect(engine: Option<&str>, template_path: Option<&str>) -> Result<Self> {
+        if let Some(engine) = engine {
+            return engine.parse();
+        }
+        let extension = template_path
+            .and_then(|p| std::path::Path::new(p).extension())
+            .map(|e| e.to_string_lossy().to_lowercase());
+        Ok(match extension.as_deref() {
+            Some("tera" | "jinja" | "jinja2" | "j2") => Self::Tera,
+            _ => Self::Handlebars,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Handlebars => "handlebars",
+            Self::Tera => "tera",
+        }
+    }
+}
+
+/// Render `template` with `variables`, which must be a JSON object (or null)
+pub fn render(engine: Engine, template: &str, variables: &Value) -> Result<String> {
+    let empty = Value::Object(Default::default());
+    let variables = match variables {
+        Value::Null => &empty,
+        Value::Object(_) => variables,
+        _ => return Err(ToolError::invalid("variables must be a JSON object").into()),
+    };
+
+    match engine {
+        Engine::Handlebars => {
+            let mut handlebars = handlebars::Handlebars::new();
+            handlebars.set_strict_mode(true);
+            handlebars.register_escape_fn(handlebars::no_escape);
+            handlebars.render_template(template, variables)
+                .map_err(|e| ToolError::invalid(format!("Template error: {}", e)).into())
+        }
+        Engine::Tera => {
+            let context = tera::Context::from_value(variables.clone())?;
+            tera::Tera::one_off(template, &context, false).map_err(|e| {
+                // Tera keeps the useful part (which variable, which line) in the source chain
+                let mut message = e.to_string();
+                let mut source = std::error::Error::source(&e);
+                while let Some(cause) = source {
+                    message = format!("{}: {}", message, cause);
+                    source = cause.source();
+                }
+                ToolError::invalid(format!("Template error: {}", message)).into()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let vars = json!({"name": "parser", "items": ["a", "b"], "generic": "Vec<u8>"});
+        assert_eq!(
+            render(Engine::Handlebars, "mod {{name}};{{#each items}} {{this}}{{/each}} {{generic}}", &vars).unwrap(),
+            "mod parser; a b Vec<u8>"
+        );
+        assert_eq!(
+            render(Engine::Tera, "mod {{ name | upper }};{% for i in items %} {{ i }}{% endfor %} {{ generic }}", &vars).unwrap(),
+            "mod PARSER; a b Vec<u8>"
+        );
+        assert!(render(Engine::Handlebars, "{{missing}}", &vars).is_err());
+        assert!(render(Engine::Tera, "{{ missing }}", &vars).unwrap_err().to_string().contains("missing"));
+        assert!(render(Engine::Tera, "x", &json!([1])).is_err());
+
+        assert_eq!(Engine::detect(None, Some("mod.rs.j2")).unwrap(), Engine::Tera);
+        assert_eq!(Engine::detect(None, Some("mod.rs.hbs")).unwrap(), Engine::Handlebars);
+        assert_eq!(Engine::detect(Some("tera"), None).unwrap(), Engine::Tera);
+    }
+}
@@ -2,32 +2,87 @@
 ///
 /// Handles all file operations:
 /// - read: Read file contents
+/// - read_lines / edit_lines: Read or replace an exact line range
 /// - write: Write file contents
 /// - edit: Edit file with old/new replacement
-/// - patch: Apply Rust-style patch format
-/// - tree: Display directory tree
-/// - find: Find files by pattern
+/// - patch: Apply a unified diff (git diff) or `*** Begin Patch` block
+/// - tree: Display directory tree, as text or nested JSON
+/// - find: Find files by name or relative-path globs
 /// - search: Search file contents
-
-use anyhow::{anyhow, Result};
+/// - replace: Regex search-and-replace across a directory
+/// - dedupe: Exact and near-duplicate files
+/// - outline: Symbols of a source file, via tree-sitter
+/// - archive_create / archive_extract / archive_list: zip and tar archives
+/// - hash: md5/sha1/sha256/blake3 of a file or directory tree
+/// - chmod / chown / touch: permissions, ownership and timestamps
+/// - symlink / readlink / realpath: create and resolve symlinks
+/// - render: Handlebars or Tera template to a file
+
+use anyhow::Result;
+use crate::error::ToolError;
+use super::diff;
+use super::fs_archive;
+use super::fs_dedupe;
+use super::fs_atomic::write_atomic;
+use super::fs_encoding::{self, TextFormat};
+use super::fs_glob::PathGlobs;
+use super::fs_journal::{self, Journal};
+use super::fs_links;
+use super::fs_patch;
+use super::fs_perms;
+use super::fs_template;
+use super::fs_tree;
+use crate::search::ast_search;
+use super::hash;
+use crate::config::{FsConfig, JournalConfig};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::io::SeekFrom;
 use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt};
 use walkdir::WalkDir;
 
+/// Default byte-range read size
+const CHUNK_BYTES: u64 = 64 * 1024;
+
+/// Default cap on entries listed by tree
+const TREE_ENTRIES: usize = 1000;
+
+/// Default cap on matches changed by one replace
+const MAX_REPLACEMENTS: usize = 1000;
+
 /// Actions for the fs tool
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub enum FsAction {
     Read,
+    ReadLines,
     Write,
     Edit,
+    EditLines,
     Patch,
     Tree,
     Find,
     Search,
+    Replace,
+    Dedupe,
+    Outline,
     Info,
+    Undo,
+    Redo,
+    History,
+    ArchiveCreate,
+    ArchiveExtract,
+    ArchiveList,
+    Hash,
+    Chmod,
+    Chown,
+    Touch,
+    Symlink,
+    Readlink,
+    Realpath,
+    Render,
     Help,
 }
 
@@ -43,15 +98,34 @@ impl std::str::FromStr for FsAction {
     fn from_str(s: &str) -> Result<Self> {
         match s.to_lowercase().as_str() {
             "read" => Ok(Self::Read),
+            "read_lines" => Ok(Self::ReadLines),
             "write" => Ok(Self::Write),
             "edit" => Ok(Self::Edit),
+            "edit_lines" => Ok(Self::EditLines),
             "patch" | "apply_patch" => Ok(Self::Patch),
             "tree" | "ls" => Ok(Self::Tree),
             "find" | "glob" => Ok(Self::Find),
             "search" | "grep" => Ok(Self::Search),
+            "replace" | "sed" => Ok(Self::Replace),
+            "dedupe" | "duplicates" => Ok(Self::Dedupe),
+            "outline" | "symbols" => Ok(Self::Outline),
             "info" | "stat" => Ok(Self::Info),
+            "undo" => Ok(Self::Undo),
+            "redo" => Ok(Self::Redo),
+            "history" => Ok(Self::History),
+            "archive_create" | "archive" | "zip" => Ok(Self::ArchiveCreate),
+            "archive_extract" | "extract" | "unzip" => Ok(Self::ArchiveExtract),
+            "archive_list" => Ok(Self::ArchiveList),
+            "hash" | "checksum" => Ok(Self::Hash),
+            "chmod" => Ok(Self::Chmod),
+            "chown" => Ok(Self::Chown),
+            "touch" => Ok(Self::Touch),
+            "symlink" | "ln" => Ok(Self::Symlink),
+            "readlink" => Ok(Self::Readlink),
+            "realpath" | "canonicalize" => Ok(Self::Realpath),
+            "render" | "template" => Ok(Self::Render),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -80,6 +154,16 @@ pub struct FsToolArgs {
     pub replace_all: bool,
     /// Patch text
     pub patch: Option<String>,
+    /// Only report which patch hunks would fail, like `git apply --check`
+    #[serde(default)]
+    pub check: bool,
+    /// Apply the patch in reverse, undoing it
+    #[serde(default)]
+    pub reverse: bool,
+    /// Context lines a hunk may ignore at each end (default 2)
+    pub fuzz: Option<usize>,
+    /// Leading path components to drop from patch paths, like `patch -p`
+    pub strip: Option<usize>,
     /// Pattern for find/search
     pub pattern: Option<String>,
     /// Max depth for tree
@@ -88,6 +172,70 @@ pub struct FsToolArgs {
     pub limit: Option<usize>,
     /// Offset for pagination
     pub offset: Option<usize>,
+    /// First line (1-based) for read_lines/edit_lines
+    pub start: Option<usize>,
+    /// Last line (inclusive) for read_lines/edit_lines
+    pub end: Option<usize>,
+    /// Replacement text for edit_lines; alias content
+    pub new_content: Option<String>,
+    /// Text encoding to read with or write in, overriding detection
+    pub encoding: Option<String>,
+    /// Read mode: head or tail (limit lines) instead of the whole file
+    pub mode: Option<String>,
+    /// Read raw bytes starting here instead of lines
+    pub byte_offset: Option<u64>,
+    /// Bytes to read with byte_offset
+    pub length: Option<u64>,
+    /// Directory or file to pack for archive_create
+    pub source: Option<String>,
+    /// Directory to unpack into for archive_extract
+    pub dest: Option<String>,
+    /// Globs selecting archive entries
+    pub include: Option<Vec<String>>,
+    /// Globs of archive entries to leave out
+    pub exclude: Option<Vec<String>>,
+    /// Archive format: zip, tar or tar.gz; defaults from the extension.
+    /// For tree: text (default) or json
+    pub format: Option<String>,
+    /// Entry order for tree: name, size or mtime
+    pub sort: Option<String>,
+    /// Replacement for replace; `$1` / `${name}` insert capture groups
+    pub replacement: Option<String>,
+    /// Refuse a replace that would change more matches than this
+    pub max_replacements: Option<usize>,
+    /// Minimum similarity (0-1) for dedupe's near duplicates
+    pub similarity: Option<f64>,
+    /// Source language for outline, when the extension doesn't tell
+    pub language: Option<String>,
+    /// Entry type for find: file, dir or symlink
+    pub file_type: Option<String>,
+    /// Replace an existing archive or extracted files
+    #[serde(default)]
+    pub overwrite: bool,
+    /// Digest for hash: md5, sha1, sha256 (default) or blake3
+    pub algorithm: Option<String>,
+    /// Mode for chmod: octal (755) or symbolic (u+x,go-w)
+    pub permissions: Option<String>,
+    /// User name or uid for chown
+    pub owner: Option<String>,
+    /// Group name or gid for chown
+    pub group: Option<String>,
+    /// Apply chmod/chown to everything below a directory
+    #[serde(default)]
+    pub recursive: bool,
+    /// Time for touch (RFC 3339 or unix seconds); defaults to now
+    pub mtime: Option<String>,
+    /// What a new symlink points to
+    pub target: Option<String>,
+    /// Descend into symlinked directories in tree/find/search
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Inline template for render
+    pub template: Option<String>,
+    /// Template language for render: handlebars or tera
+    pub engine: Option<String>,
+    /// Variables for render
+    pub variables: Option<Value>,
     /// Include hidden files
     #[serde(default)]
     pub include_hidden: bool,
@@ -96,38 +244,44 @@ pub struct FsToolArgs {
     /// Case insensitive
     #[serde(default)]
     pub ignore_case: bool,
+    /// Report what write/edit/patch would change without touching files
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Flush written files and their directory to disk before returning
+    #[serde(default)]
+    pub fsync: bool,
+    /// Reject write/edit/edit_lines unless the file's current sha256 matches
+    pub expected_hash: Option<String>,
+    /// Reject write/edit/edit_lines if the file was modified after this time
+    /// (RFC 3339 or unix seconds)
+    pub if_unchanged_since: Option<String>,
+    /// MCP session making the call, set by the server rather than the client
+    #[serde(skip)]
+    pub session_id: Option<String>,
 }
 
-/// Patch operation type
-#[derive(Debug, Clone, PartialEq)]
-pub enum PatchOp {
-    Add,
-    Update,
-    Delete,
+/// File system tool
+pub struct FsTool {
+    journal: Journal,
+    config: FsConfig,
 }
 
-/// Parsed patch file
-#[derive(Debug, Clone)]
-pub struct PatchFile {
-    pub op: PatchOp,
-    pub path: String,
-    pub hunks: Vec<PatchHunk>,
-}
+impl FsTool {
+    pub fn new() -> Self {
+        Self::with_journal(JournalConfig::default())
+    }
 
-/// Patch hunk
-#[derive(Debug, Clone)]
-pub struct PatchHunk {
-    pub context: Option<String>,
-    pub old_lines: Vec<String>,
-    pub new_lines: Vec<String>,
-}
+    pub fn with_journal(config: JournalConfig) -> Self {
+        Self::with_config(config, FsConfig::default())
+    }
 
-/// File system tool
-pub struct FsTool;
+    pub fn with_config(journal: JournalConfig, config: FsConfig) -> Self {
+        Self { journal: Journal::new(journal), config }
+    }
 
-impl FsTool {
-    pub fn new() -> Self {
-        Self
+    /// Drop the undo journal of a session that has disconnected
+    pub fn end_session(&self, session_id: &str) {
+        self.journal.end_session(session_id);
     }
 
     pub async fn execute(&self, args: FsToolArgs) -> Result<String> {
@@ -139,13 +293,37 @@ impl FsTool {
 
         let result = match action {
             FsAction::Read => self.read(args).await?,
+            FsAction::ReadLines => self.read_lines(args).await?,
             FsAction::Write => self.write(args).await?,
             FsAction::Edit => self.edit(args).await?,
+            FsAction::EditLines => self.edit_lines(args).await?,
             FsAction::Patch => self.patch(args).await?,
             FsAction::Tree => self.tree(args).await?,
             FsAction::Find => self.find(args).await?,
             FsAction::Search => self.search(args).await?,
+            FsAction::Replace => self.replace(args).await?,
+            FsAction::Dedupe => self.dedupe(args).await?,
+            FsAction::Outline => self.outline(args).await?,
             FsAction::Info => self.info(args).await?,
+            FsAction::Undo => {
+                let path = args.file_path.or(args.path).map(|p| shellexpand::tilde(&p).to_string());
+                self.journal.undo(args.session_id.as_deref(), path.as_deref()).await?
+            }
+            FsAction::Redo => {
+                let path = args.file_path.or(args.path).map(|p| shellexpand::tilde(&p).to_string());
+                self.journal.redo(args.session_id.as_deref(), path.as_deref()).await?
+            }
+            FsAction::History => {
+                let path = args.file_path.or(args.path).map(|p| shellexpand::tilde(&p).to_string());
+                self.journal.history(args.session_id.as_deref(), path.as_deref())
+            }
+            FsAction::ArchiveCreate | FsAction::ArchiveExtract | FsAction::ArchiveList => {
+                self.archive(action, args).await?
+            }
+            FsAction::Hash => self.hash(args).await?,
+            FsAction::Chmod | FsAction::Chown | FsAction::Touch => self.attributes(action, args).await?,
+            FsAction::Symlink | FsAction::Readlink | FsAction::Realpath => self.links(action, args)?,
+            FsAction::Render => self.render(args).await?,
             FsAction::Help => self.help()?,
         };
 
@@ -154,10 +332,22 @@ impl FsTool {
 
     async fn read(&self, args: FsToolArgs) -> Result<Value> {
         let path = args.file_path.or(args.path)
-            .ok_or_else(|| anyhow!("path required"))?;
+            .ok_or_else(|| ToolError::invalid("path required"))?;
         let path = shellexpand::tilde(&path).to_string();
 
-        let content = tokio::fs::read_to_string(&path).await?;
+        if let Some(byte_offset) = args.byte_offset {
+            return self.read_bytes(&path, byte_offset, args.length).await;
+        }
+        match args.mode.as_deref() {
+            None | Some("") | Some("full") => {}
+            Some("head") => return self.head(&path, args.limit.unwrap_or(100)).await,
+            Some("tail") => return self.tail(&path, args.limit.unwrap_or(100)).await,
+            Some(other) => {
+                return Err(ToolError::invalid(format!("Unknown read mode: {} (use head or tail)", other)).into());
+            }
+        }
+
+        let (bytes, content, format) = self.load_text(&path, args.encoding.as_deref()).await?;
         let lines: Vec<&str> = content.lines().collect();
         let total_lines = lines.len();
 
@@ -179,70 +369,273 @@ impl FsTool {
             "lines": lines.len(),
             "total_lines": total_lines,
             "offset": offset,
-            "truncated": total_lines > offset + limit
+            "truncated": total_lines > offset + limit,
+            "encoding": format.encoding.name(),
+            "line_endings": format.line_endings(),
+            "sha256": hash::sha256_hex(&bytes)
         }))
     }
 
-    async fn write(&self, args: FsToolArgs) -> Result<Value> {
+    /// Read `path` as text in `encoding`, or the detected one; returns the
+    /// raw bytes, the text with LF line endings and how to encode it back
+    async fn load_text(&self, path: &str, encoding: Option<&str>) -> Result<(Vec<u8>, String, TextFormat)> {
+        self.check_size(path).await?;
+        let bytes = tokio::fs::read(path).await?;
+        let forced = encoding.map(fs_encoding::for_label).transpose()?;
+        let (text, format) = fs_encoding::decode(path, &bytes, forced)?;
+        Ok((bytes, text, format))
+    }
+
+    /// Refuse to load files over `max_read_bytes` whole
+    async fn check_size(&self, path: &str) -> Result<()> {
+        let size = tokio::fs::metadata(path).await?.len();
+        if size > self.config.max_read_bytes {
+            return Err(ToolError::invalid(format!(
+                "{} is {} bytes, over the {} byte read limit; use mode=head/tail or byte_offset/length to read it in parts",
+                path, size, self.config.max_read_bytes
+            )).into());
+        }
+        Ok(())
+    }
+
+    /// First `count` lines, streamed so only what is returned is read
+    async fn head(&self, path: &str, count: usize) -> Result<Value> {
+        let file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let mut reader = tokio::io::BufReader::new(file).take(self.config.max_read_bytes);
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        let mut bytes_read = 0;
+        while lines.len() < count {
+            line.clear();
+            let n = reader.read_until(b'\n', &mut line).await?;
+            if n == 0 {
+                break;
+            }
+            bytes_read += n as u64;
+            lines.push(String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r']).to_string());
+        }
+
+        Ok(json!({
+            "path": path,
+            "mode": "head",
+            "content": lines.join("\n"),
+            "lines": lines.len(),
+            "size": size,
+            "truncated": bytes_read < size
+        }))
+    }
+
+    /// Last `count` lines, read backwards from the end of the file
+    async fn tail(&self, path: &str, count: usize) -> Result<Value> {
+        let mut file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let mut start = size;
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut newlines = 0;
+        // A trailing newline ends the last line rather than starting one
+        while start > 0 && newlines <= count && (buffer.len() as u64) < self.config.max_read_bytes {
+            let step = CHUNK_BYTES.min(start);
+            start -= step;
+            file.seek(SeekFrom::Start(start)).await?;
+            let mut chunk = vec![0; step as usize];
+            file.read_exact(&mut chunk).await?;
+            newlines += chunk.iter().filter(|b| **b == b'\n').count();
+            if start + step == size && chunk.ends_with(b"\n") {
+                newlines -= 1;
+            }
+            chunk.extend_from_slice(&buffer);
+            buffer = chunk;
+        }
+
+        let text = String::from_utf8_lossy(&buffer);
+        let all: Vec<&str> = text.lines().collect();
+        // Unless the whole file was read, the first line may be partial
+        let skip = if start > 0 { all.len().saturating_sub(count).max(1) } else { all.len().saturating_sub(count) };
+        let lines: Vec<&str> = all.into_iter().skip(skip).map(|l| l.trim_end_matches('\r')).collect();
+
+        Ok(json!({
+            "path": path,
+            "mode": "tail",
+            "content": lines.join("\n"),
+            "lines": lines.len(),
+            "size": size,
+            "truncated": start > 0 || skip > 0
+        }))
+    }
+
+    /// Up to `length` bytes from `offset`; text when the range is UTF-8,
+    /// base64 otherwise. A multi-byte character cut at the end of the range
+    /// is left for the next read.
+    async fn read_bytes(&self, path: &str, offset: u64, length: Option<u64>) -> Result<Value> {
+        let length = length.unwrap_or(CHUNK_BYTES).min(self.config.max_read_bytes);
+        let mut file = tokio::fs::File::open(path).await?;
+        let size = file.metadata().await?.len();
+        file.seek(SeekFrom::Start(offset.min(size))).await?;
+        let mut buffer = Vec::with_capacity(length.min(size.saturating_sub(offset)) as usize);
+        file.take(length).read_to_end(&mut buffer).await?;
+
+        let mut result = json!({ "path": path, "byte_offset": offset, "size": size });
+        match std::str::from_utf8(&buffer) {
+            Ok(text) => result["content"] = json!(text),
+            Err(e) if e.error_len().is_none() && offset + (buffer.len() as u64) < size => {
+                buffer.truncate(e.valid_up_to());
+                result["content"] = json!(std::str::from_utf8(&buffer).expect("valid prefix"));
+            }
+            Err(_) => {
+                use base64::Engine;
+                result["content_base64"] = json!(base64::engine::general_purpose::STANDARD.encode(&buffer));
+            }
+        }
+        let next = offset + buffer.len() as u64;
+        result["length"] = json!(buffer.len());
+        result["next_offset"] = json!(next);
+        result["eof"] = json!(next >= size);
+        Ok(result)
+    }
+
+    async fn read_lines(&self, args: FsToolArgs) -> Result<Value> {
         let path = args.file_path.or(args.path)
-            .ok_or_else(|| anyhow!("path required"))?;
+            .ok_or_else(|| ToolError::invalid("path required"))?;
         let path = shellexpand::tilde(&path).to_string();
-        let content = args.content.ok_or_else(|| anyhow!("content required"))?;
+        let start = args.start.ok_or_else(|| ToolError::invalid("start required"))?;
 
-        // Ensure parent directory exists
-        if let Some(parent) = Path::new(&path).parent() {
-            tokio::fs::create_dir_all(parent).await?;
+        let (bytes, content, format) = self.load_text(&path, args.encoding.as_deref()).await?;
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let total_lines = lines.len();
+        if start == 0 || start > total_lines {
+            return Err(ToolError::invalid(format!(
+                "start {} is outside {} ({} lines)", start, path, total_lines
+            )).into());
+        }
+        let end = args.end.unwrap_or(total_lines).min(total_lines);
+        if end < start {
+            return Err(ToolError::invalid(format!("end {} is before start {}", end, start)).into());
         }
-
-        tokio::fs::write(&path, &content).await?;
 
         Ok(json!({
             "path": path,
-            "bytes": content.len(),
+            "start": start,
+            "end": end,
+            "content": lines[start - 1..end].concat(),
+            "total_lines": total_lines,
+            "encoding": format.encoding.name(),
+            "line_endings": format.line_endings(),
+            "sha256": hash::sha256_hex(&bytes)
+        }))
+    }
+
+    async fn write(&self, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path)
+            .ok_or_else(|| ToolError::invalid("path required"))?;
+        let path = shellexpand::tilde(&path).to_string();
+        let content = args.content.ok_or_else(|| ToolError::invalid("content required"))?;
+
+        let before = fs_journal::snapshot(&path).await?;
+        check_unchanged(&path, before.as_deref(), args.expected_hash.as_deref(), args.if_unchanged_since.as_deref()).await?;
+        let (old, format) = target_format(&path, before.as_deref(), args.encoding.as_deref())?;
+
+        if args.dry_run {
+            return Ok(preview(&path, old.as_deref(), Some(&content)));
+        }
+
+        let bytes = format.encode(&content)?;
+        write_atomic(&path, &bytes, args.fsync).await?;
+        let result = json!({
+            "path": path,
+            "bytes": bytes.len(),
             "lines": content.lines().count(),
+            "encoding": format.encoding.name(),
+            "line_endings": format.line_endings(),
+            "sha256": hash::sha256_hex(&bytes),
             "success": true
-        }))
+        });
+        self.journal.record(args.session_id.as_deref(), &path, "write", before, Some(bytes));
+
+        Ok(result)
+    }
+
+    /// Render a template (inline or from `source`) and write it to path, or
+    /// just return it when there is no path
+    async fn render(&self, mut args: FsToolArgs) -> Result<Value> {
+        let template_path = args.source.take().map(|s| shellexpand::tilde(&s).to_string());
+        let template = match (args.template.take(), &template_path) {
+            (Some(template), None) => template,
+            (None, Some(template_path)) => tokio::fs::read_to_string(template_path).await?,
+            (Some(_), Some(_)) => return Err(ToolError::invalid("Give either template or source, not both").into()),
+            (None, None) => return Err(ToolError::invalid("template or source (a template file) required").into()),
+        };
+        let engine = fs_template::Engine::detect(args.engine.as_deref(), template_path.as_deref())?;
+        let content = fs_template::render(engine, &template, args.variables.as_ref().unwrap_or(&Value::Null))?;
+
+        let Some(path) = args.file_path.as_ref().or(args.path.as_ref()) else {
+            return Ok(json!({ "engine": engine.name(), "content": content }));
+        };
+        let path = shellexpand::tilde(path).to_string();
+        if !args.overwrite && !args.dry_run && tokio::fs::try_exists(&path).await? {
+            return Err(ToolError::conflict(format!("{} already exists (set overwrite to replace it)", path)).into());
+        }
+
+        args.content = Some(content);
+        let mut result = self.write(args).await?;
+        result["engine"] = json!(engine.name());
+        Ok(result)
     }
 
     async fn edit(&self, args: FsToolArgs) -> Result<Value> {
         let path = args.file_path.or(args.path)
-            .ok_or_else(|| anyhow!("path required"))?;
+            .ok_or_else(|| ToolError::invalid("path required"))?;
         let path = shellexpand::tilde(&path).to_string();
 
         let old_string = args.old_string.or(args.old_text)
-            .ok_or_else(|| anyhow!("old_string required"))?;
+            .ok_or_else(|| ToolError::invalid("old_string required"))?;
         let new_string = args.new_string.or(args.new_text)
-            .ok_or_else(|| anyhow!("new_string required"))?;
+            .ok_or_else(|| ToolError::invalid("new_string required"))?;
 
         // Handle creating new file
         if old_string.is_empty() {
-            // Create new file
-            if let Some(parent) = Path::new(&path).parent() {
-                tokio::fs::create_dir_all(parent).await?;
+            let before = fs_journal::snapshot(&path).await?;
+            check_unchanged(&path, before.as_deref(), args.expected_hash.as_deref(), args.if_unchanged_since.as_deref()).await?;
+            let (old, format) = target_format(&path, before.as_deref(), args.encoding.as_deref())?;
+            if args.dry_run {
+                return Ok(preview(&path, old.as_deref(), Some(&new_string)));
             }
-            tokio::fs::write(&path, &new_string).await?;
-            return Ok(json!({
+            // Create new file
+            let bytes = format.encode(&new_string)?;
+            write_atomic(&path, &bytes, args.fsync).await?;
+            let result = json!({
                 "path": path,
                 "created": true,
-                "bytes": new_string.len()
-            }));
+                "bytes": bytes.len(),
+                "sha256": hash::sha256_hex(&bytes)
+            });
+            self.journal.record(args.session_id.as_deref(), &path, "edit", before, Some(bytes));
+            return Ok(result);
         }
 
         // Read existing file
-        let content = tokio::fs::read_to_string(&path).await?;
+        let (original, content, format) = self.load_text(&path, args.encoding.as_deref()).await?;
+        check_unchanged(&path, Some(&original), args.expected_hash.as_deref(), args.if_unchanged_since.as_deref()).await?;
+
+        // The text is held with LF line endings
+        let (old_string, new_string) = if format.crlf {
+            (old_string.replace("\r\n", "\n"), new_string.replace("\r\n", "\n"))
+        } else {
+            (old_string, new_string)
+        };
 
         // Count occurrences
         let count = content.matches(&old_string).count();
 
         if count == 0 {
-            return Err(anyhow!("old_string not found in file"));
+            return Err(ToolError::invalid("old_string not found in file").into());
         }
 
         if count > 1 && !args.replace_all {
-            return Err(anyhow!(
+            return Err(ToolError::invalid(format!(
                 "old_string matches {} locations. Use replace_all=true or provide more context.",
                 count
-            ));
+            )).into());
         }
 
         // Replace
@@ -252,198 +645,241 @@ impl FsTool {
             content.replacen(&old_string, &new_string, 1)
         };
 
-        tokio::fs::write(&path, &new_content).await?;
+        if args.dry_run {
+            let mut result = preview(&path, Some(&content), Some(&new_content));
+            result["replacements"] = json!(if args.replace_all { count } else { 1 });
+            return Ok(result);
+        }
 
-        Ok(json!({
+        let bytes = format.encode(&new_content)?;
+        write_atomic(&path, &bytes, args.fsync).await?;
+        let result = json!({
             "path": path,
             "replacements": if args.replace_all { count } else { 1 },
-            "bytes": new_content.len(),
+            "bytes": bytes.len(),
+            "sha256": hash::sha256_hex(&bytes),
             "success": true
-        }))
+        });
+        self.journal.record(args.session_id.as_deref(), &path, "edit", Some(original), Some(bytes));
+
+        Ok(result)
     }
 
-    async fn patch(&self, args: FsToolArgs) -> Result<Value> {
-        let patch_text = args.patch.or(args.content)
-            .ok_or_else(|| anyhow!("patch required"))?;
+    /// Replace lines `start..=end` with `new_content`. `end = start - 1`
+    /// inserts before `start` without removing anything; empty content
+    /// deletes the range.
+    async fn edit_lines(&self, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path)
+            .ok_or_else(|| ToolError::invalid("path required"))?;
+        let path = shellexpand::tilde(&path).to_string();
+        let start = args.start.ok_or_else(|| ToolError::invalid("start required"))?;
+        let end = args.end.unwrap_or(start);
+        let new_content = args.new_content.or(args.content)
+            .ok_or_else(|| ToolError::invalid("new_content required"))?;
 
-        let patches = self.parse_patch(&patch_text)?;
-        let mut results = Vec::new();
+        let (original, content, format) = self.load_text(&path, args.encoding.as_deref()).await?;
+        check_unchanged(&path, Some(&original), args.expected_hash.as_deref(), args.if_unchanged_since.as_deref()).await?;
+
+        let lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let total_lines = lines.len();
+        if start == 0 || start > total_lines + 1 {
+            return Err(ToolError::invalid(format!(
+                "start {} is outside {} ({} lines)", start, path, total_lines
+            )).into());
+        }
+        if end + 1 < start || end > total_lines {
+            return Err(ToolError::invalid(format!(
+                "end {} must be between {} and {}", end, start - 1, total_lines
+            )).into());
+        }
 
-        for patch_file in patches {
-            let path = shellexpand::tilde(&patch_file.path).to_string();
+        // End inserted text on a line break unless it replaces the
+        // unterminated last line; CRLF files get theirs back on encode
+        let mut replacement = new_content.replace("\r\n", "\n");
+        let at_eof_without_newline = end == total_lines && !content.ends_with('\n');
+        if !replacement.is_empty() && !replacement.ends_with('\n') && !at_eof_without_newline {
+            replacement.push('\n');
+        }
+        if start > total_lines && !content.is_empty() && !content.ends_with('\n') {
+            replacement.insert(0, '\n');
+        }
 
-            match patch_file.op {
-                PatchOp::Add => {
-                    // Create new file
-                    if let Some(parent) = Path::new(&path).parent() {
-                        tokio::fs::create_dir_all(parent).await?;
-                    }
-                    let content: String = patch_file.hunks
-                        .iter()
-                        .flat_map(|h| &h.new_lines)
-                        .cloned()
-                        .collect::<Vec<_>>()
-                        .join("\n");
-                    tokio::fs::write(&path, &content).await?;
-                    results.push(json!({
-                        "path": path,
-                        "op": "add",
-                        "success": true
-                    }));
-                }
-                PatchOp::Delete => {
-                    tokio::fs::remove_file(&path).await?;
-                    results.push(json!({
-                        "path": path,
-                        "op": "delete",
-                        "success": true
-                    }));
-                }
-                PatchOp::Update => {
-                    let mut content = tokio::fs::read_to_string(&path).await?;
+        let new_text = [
+            lines[..start - 1].concat(),
+            replacement.clone(),
+            lines[end..].concat(),
+        ].concat();
 
-                    for hunk in &patch_file.hunks {
-                        let old_text = hunk.old_lines.join("\n");
-                        let new_text = hunk.new_lines.join("\n");
+        if args.dry_run {
+            return Ok(preview(&path, Some(&content), Some(&new_text)));
+        }
 
-                        if !content.contains(&old_text) {
-                            return Err(anyhow!("Hunk not found in {}", path));
-                        }
+        let bytes = format.encode(&new_text)?;
+        write_atomic(&path, &bytes, args.fsync).await?;
+        let result = json!({
+            "path": path,
+            "start": start,
+            "end": end,
+            "removed": end + 1 - start,
+            "inserted": replacement.lines().count(),
+            "bytes": bytes.len(),
+            "sha256": hash::sha256_hex(&bytes),
+            "success": true
+        });
+        self.journal.record(args.session_id.as_deref(), &path, "edit_lines", Some(original), Some(bytes));
 
-                        content = content.replacen(&old_text, &new_text, 1);
-                    }
+        Ok(result)
+    }
 
-                    tokio::fs::write(&path, &content).await?;
-                    results.push(json!({
-                        "path": path,
-                        "op": "update",
-                        "hunks": patch_file.hunks.len(),
-                        "success": true
-                    }));
-                }
-            }
+    /// Apply a unified diff or `*** Begin Patch` block. Every file's new
+    /// contents are worked out first, so a hunk that fails leaves all files
+    /// untouched; with `check` nothing is written and the per-hunk report is
+    /// returned instead.
+    async fn patch(&self, args: FsToolArgs) -> Result<Value> {
+        struct Planned {
+            path: String,
+            moved_from: Option<(String, Vec<u8>)>,
+            before: Option<Vec<u8>>,
+            old: Option<String>,
+            new: Option<String>,
+            format: TextFormat,
+            report: Value,
         }
 
-        Ok(json!({
-            "applied": results.len(),
-            "results": results
-        }))
-    }
+        let patch_text = args.patch.or(args.content)
+            .ok_or_else(|| ToolError::invalid("patch required"))?;
+        let base = args.file_path.or(args.path).map(|p| PathBuf::from(shellexpand::tilde(&p).as_ref()));
+        let resolve = |path: &str| {
+            let path = PathBuf::from(shellexpand::tilde(path).as_ref());
+            match &base {
+                Some(base) if path.is_relative() => base.join(path),
+                _ => path,
+            }.to_string_lossy().to_string()
+        };
+        let fuzz = args.fuzz.unwrap_or(fs_patch::DEFAULT_FUZZ);
 
-    fn parse_patch(&self, text: &str) -> Result<Vec<PatchFile>> {
-        let mut patches = Vec::new();
-        let mut current: Option<PatchFile> = None;
-        let mut current_hunk: Option<PatchHunk> = None;
+        let mut files = fs_patch::parse(&patch_text, args.strip)?;
+        if args.reverse {
+            files.iter_mut().for_each(fs_patch::FilePatch::reverse);
+        }
 
-        for line in text.lines() {
-            if line.starts_with("*** Begin Patch") {
-                continue;
-            }
-            if line.starts_with("*** End Patch") {
-                if let Some(mut hunk) = current_hunk.take() {
-                    if let Some(ref mut patch) = current {
-                        patch.hunks.push(hunk);
+        let mut planned = Vec::new();
+        let mut failures = Vec::new();
+        for file in &files {
+            let old_path = file.old_path.as_deref().map(resolve);
+            let new_path = file.new_path.as_deref().map(resolve);
+            // Plain `diff -u a.txt b.txt` names two files; patch whichever exists
+            let source = match (&old_path, &new_path) {
+                (Some(old), Some(new)) if !file.rename && !Path::new(old).exists() => Some(new.clone()),
+                (Some(old), _) => Some(old.clone()),
+                (None, _) => None,
+            };
+            let path = if file.rename { new_path.clone() } else { source.clone().or(new_path.clone()) }
+                .expect("parse drops files without paths");
+            let mut report = json!({ "path": path, "op": file.op() });
+
+            let loaded = match &source {
+                Some(source) => match self.load_text(source, None).await {
+                    Ok(loaded) => Some(loaded),
+                    Err(e) => {
+                        report["status"] = json!("failed");
+                        report["reason"] = json!(e.to_string());
+                        failures.push(format!("{}: {}", path, e));
+                        planned.push(Planned { path, moved_from: None, before: None, old: None, new: None, format: TextFormat::default(), report });
+                        continue;
                     }
-                }
-                if let Some(patch) = current.take() {
-                    patches.push(patch);
-                }
+                },
+                None => None,
+            };
+            let exists = tokio::fs::try_exists(&path).await?;
+            if (source.is_none() || file.rename) && exists {
+                report["status"] = json!("failed");
+                report["reason"] = json!("already exists");
+                failures.push(format!("{}: already exists", path));
+                planned.push(Planned { path, moved_from: None, before: None, old: None, new: None, format: TextFormat::default(), report });
                 continue;
             }
-            if line.starts_with("*** Add File:") {
-                if let Some(mut hunk) = current_hunk.take() {
-                    if let Some(ref mut patch) = current {
-                        patch.hunks.push(hunk);
-                    }
-                }
-                if let Some(patch) = current.take() {
-                    patches.push(patch);
-                }
-                let path = line.trim_start_matches("*** Add File:").trim().to_string();
-                current = Some(PatchFile {
-                    op: PatchOp::Add,
-                    path,
-                    hunks: Vec::new(),
-                });
-                current_hunk = Some(PatchHunk {
-                    context: None,
-                    old_lines: Vec::new(),
-                    new_lines: Vec::new(),
-                });
-                continue;
-            }
-            if line.starts_with("*** Update File:") {
-                if let Some(mut hunk) = current_hunk.take() {
-                    if let Some(ref mut patch) = current {
-                        patch.hunks.push(hunk);
-                    }
-                }
-                if let Some(patch) = current.take() {
-                    patches.push(patch);
-                }
-                let path = line.trim_start_matches("*** Update File:").trim().to_string();
-                current = Some(PatchFile {
-                    op: PatchOp::Update,
-                    path,
-                    hunks: Vec::new(),
-                });
-                continue;
-            }
-            if line.starts_with("*** Delete File:") {
-                if let Some(mut hunk) = current_hunk.take() {
-                    if let Some(ref mut patch) = current {
-                        patch.hunks.push(hunk);
-                    }
-                }
-                if let Some(patch) = current.take() {
-                    patches.push(patch);
-                }
-                let path = line.trim_start_matches("*** Delete File:").trim().to_string();
-                current = Some(PatchFile {
-                    op: PatchOp::Delete,
-                    path,
-                    hunks: Vec::new(),
-                });
-                continue;
+
+            let (before, old, format) = match loaded {
+                Some((bytes, text, format)) => (Some(bytes), Some(text), format),
+                None => (None, None, TextFormat::default()),
+            };
+            let applied = fs_patch::apply(old.as_deref(), &file.hunks, fuzz);
+            for hunk in applied.hunks.iter().filter(|h| h["status"] == "failed") {
+                failures.push(format!("{} hunk {} ({}): {}", path, hunk["hunk"], hunk["header"].as_str().unwrap_or(""), hunk["reason"].as_str().unwrap_or("")));
             }
-            if line.starts_with("@@") {
-                if let Some(hunk) = current_hunk.take() {
-                    if let Some(ref mut patch) = current {
-                        patch.hunks.push(hunk);
-                    }
+            let new = if new_path.is_none() {
+                if applied.failed == 0 && !file.hunks.is_empty() && !applied.text.is_empty() {
+                    failures.push(format!("{}: contents differ from the patch, not deleting", path));
                 }
-                current_hunk = Some(PatchHunk {
-                    context: Some(line.to_string()),
-                    old_lines: Vec::new(),
-                    new_lines: Vec::new(),
-                });
-                continue;
-            }
+                None
+            } else {
+                Some(applied.text)
+            };
+            report["status"] = json!(if applied.failed == 0 { "ok" } else { "failed" });
+            report["hunks"] = json!(applied.hunks);
+
+            let (before, moved_from) = match (file.rename, source, before) {
+                (true, Some(source), Some(bytes)) => (None, Some((source, bytes))),
+                (_, _, before) => (before, None),
+            };
+            planned.push(Planned { path, moved_from, before, old, new, format, report });
+        }
 
-            if let Some(ref mut hunk) = current_hunk {
-                if line.starts_with('+') {
-                    hunk.new_lines.push(line[1..].to_string());
-                } else if line.starts_with('-') {
-                    hunk.old_lines.push(line[1..].to_string());
-                } else if line.starts_with(' ') {
-                    // Context line - add to both
-                    hunk.old_lines.push(line[1..].to_string());
-                    hunk.new_lines.push(line[1..].to_string());
-                }
-            }
+        if args.check {
+            return Ok(json!({
+                "check": true,
+                "applies": failures.is_empty(),
+                "failures": failures,
+                "results": planned.into_iter().map(|p| p.report).collect::<Vec<_>>()
+            }));
+        }
+        if !failures.is_empty() {
+            return Err(ToolError::conflict(format!(
+                "Patch does not apply, nothing was written:\n  {}",
+                failures.join("\n  ")
+            )).into());
+        }
+        if args.dry_run {
+            let results: Vec<Value> = planned.iter().map(|p| {
+                let mut preview = preview(&p.path, p.old.as_deref(), p.new.as_deref());
+                preview["hunks"] = p.report["hunks"].clone();
+                preview
+            }).collect();
+            return Ok(json!({
+                "dry_run": true,
+                "files": results.len(),
+                "results": results
+            }));
         }
 
-        // Flush remaining
-        if let Some(mut hunk) = current_hunk.take() {
-            if let Some(ref mut patch) = current {
-                patch.hunks.push(hunk);
+        let session = args.session_id.as_deref();
+        let mut results = Vec::new();
+        for planned in planned {
+            match &planned.new {
+                Some(new) => {
+                    let bytes = planned.format.encode(new)?;
+                    write_atomic(&planned.path, &bytes, args.fsync).await?;
+                    self.journal.record(session, &planned.path, "patch", planned.before, Some(bytes));
+                }
+                None => {
+                    tokio::fs::remove_file(&planned.path).await?;
+                    self.journal.record(session, &planned.path, "patch", planned.before, None);
+                }
             }
-        }
-        if let Some(patch) = current.take() {
-            patches.push(patch);
+            if let Some((from, bytes)) = planned.moved_from {
+                tokio::fs::remove_file(&from).await?;
+                self.journal.record(session, &from, "patch", Some(bytes), None);
+            }
+            let mut result = planned.report;
+            result["success"] = json!(true);
+            results.push(result);
         }
 
-        Ok(patches)
+        Ok(json!({
+            "applied": results.len(),
+            "results": results
+        }))
     }
 
     async fn tree(&self, args: FsToolArgs) -> Result<Value> {
@@ -451,31 +887,75 @@ impl FsTool {
         let path = shellexpand::tilde(&path).to_string();
         let depth = args.depth.unwrap_or(3);
         let include_hidden = args.include_hidden;
+        let sort: fs_tree::Sort = args.sort.as_deref().map(str::parse).transpose()?.unwrap_or_default();
+        let limit = args.limit.unwrap_or(TREE_ENTRIES);
+
+        match args.format.as_deref() {
+            None | Some("text") => {}
+            Some("json") => {
+                let options = fs_tree::Options {
+                    depth,
+                    include_hidden,
+                    follow_symlinks: args.follow_symlinks,
+                    sort,
+                    max_entries: limit,
+                };
+                let root = PathBuf::from(&path);
+                let mut result = tokio::task::spawn_blocking(move || fs_tree::build(&root, &options)).await??;
+                result["path"] = json!(path);
+                return Ok(result);
+            }
+            Some(other) => {
+                return Err(ToolError::invalid(format!("Unknown tree format: {} (use text or json)", other)).into());
+            }
+        }
 
         let mut entries = Vec::new();
         let mut dirs = 0;
         let mut files = 0;
+        let mut cycles = Vec::new();
 
         for entry in WalkDir::new(&path)
             .max_depth(depth)
+            .follow_links(args.follow_symlinks)
+            .sort_by(move |a, b| match (a.metadata(), b.metadata()) {
+                (Ok(ma), Ok(mb)) => sort.compare(
+                    (&a.file_name().to_string_lossy(), &ma),
+                    (&b.file_name().to_string_lossy(), &mb),
+                ),
+                _ => a.file_name().cmp(b.file_name()),
+            })
             .into_iter()
             .filter_entry(|e| {
                 include_hidden || !e.file_name().to_string_lossy().starts_with('.')
             })
         {
-            if let Ok(entry) = entry {
-                let relative = entry.path().strip_prefix(&path).unwrap_or(entry.path());
-                let depth = relative.components().count();
-                let prefix = "  ".repeat(depth);
-                let name = entry.file_name().to_string_lossy();
-
-                if entry.file_type().is_dir() {
-                    dirs += 1;
-                    entries.push(format!("{}\u{251c}\u{2500} {}/", prefix, name));
-                } else {
-                    files += 1;
-                    entries.push(format!("{}\u{251c}\u{2500} {}", prefix, name));
+            if entries.len() >= limit {
+                break;
+            }
+            match entry {
+                Ok(entry) => {
+                    let relative = entry.path().strip_prefix(&path).unwrap_or(entry.path());
+                    let depth = relative.components().count();
+                    let prefix = "  ".repeat(depth);
+                    let name = entry.file_name().to_string_lossy();
+                    let link = if entry.path_is_symlink() {
+                        std::fs::read_link(entry.path())
+                            .map(|t| format!(" -> {}", t.display()))
+                            .unwrap_or_default()
+                    } else {
+                        String::new()
+                    };
+
+                    if entry.file_type().is_dir() {
+                        dirs += 1;
+                        entries.push(format!("{}\u{251c}\u{2500} {}/{}", prefix, name, link));
+                    } else {
+                        files += 1;
+                        entries.push(format!("{}\u{251c}\u{2500} {}{}", prefix, name, link));
+                    }
                 }
+                Err(err) => cycles.extend(fs_links::cycle(&err)),
             }
         }
 
@@ -483,51 +963,75 @@ impl FsTool {
             "path": path,
             "tree": entries.join("\n"),
             "directories": dirs,
-            "files": files
+            "files": files,
+            "truncated": entries.len() >= limit,
+            "symlink_cycles": cycles
         }))
     }
 
     async fn find(&self, args: FsToolArgs) -> Result<Value> {
         let path = args.path.unwrap_or_else(|| ".".to_string());
         let path = shellexpand::tilde(&path).to_string();
-        let pattern = args.pattern.ok_or_else(|| anyhow!("pattern required"))?;
+        let patterns: Vec<String> = args.pattern.iter().chain(args.include.iter().flatten()).cloned().collect();
+        if patterns.is_empty() {
+            return Err(ToolError::invalid("pattern (or include) required").into());
+        }
         let limit = args.limit.unwrap_or(100);
         let include_hidden = args.include_hidden;
+        let file_type = args.file_type.as_deref().map(str::to_lowercase);
+        let keep_type: fn(&walkdir::DirEntry) -> bool = match file_type.as_deref() {
+            None => |_| true,
+            Some("file" | "f") => |e| e.file_type().is_file(),
+            Some("dir" | "directory" | "d") => |e| e.file_type().is_dir(),
+            Some("symlink" | "link" | "l") => |e| e.path_is_symlink(),
+            Some(other) => {
+                return Err(ToolError::invalid(format!("Unknown type: {} (use file, dir or symlink)", other)).into());
+            }
+        };
 
-        let glob = glob::Pattern::new(&pattern)?;
+        let globs = PathGlobs::new(&patterns)?;
+        let excludes = PathGlobs::new(args.exclude.as_deref().unwrap_or_default())?;
+        let relative = |e: &walkdir::DirEntry| e.path().strip_prefix(&path).unwrap_or(e.path()).to_path_buf();
         let mut matches = Vec::new();
+        let mut cycles = Vec::new();
 
         for entry in WalkDir::new(&path)
+            .min_depth(1)
+            .follow_links(args.follow_symlinks)
             .into_iter()
             .filter_entry(|e| {
-                include_hidden || !e.file_name().to_string_lossy().starts_with('.')
+                (include_hidden || !e.file_name().to_string_lossy().starts_with('.'))
+                    && !excludes.is_match(&relative(e))
             })
         {
             if matches.len() >= limit {
                 break;
             }
 
-            if let Ok(entry) = entry {
-                let name = entry.file_name().to_string_lossy();
-                if glob.matches(&name) {
-                    matches.push(entry.path().to_string_lossy().to_string());
+            match entry {
+                Ok(entry) => {
+                    if keep_type(&entry) && globs.is_match(&relative(&entry)) {
+                        matches.push(entry.path().to_string_lossy().to_string());
+                    }
                 }
+                Err(err) => cycles.extend(fs_links::cycle(&err)),
             }
         }
 
         Ok(json!({
             "path": path,
-            "pattern": pattern,
+            "pattern": patterns.join(", "),
             "matches": matches,
             "count": matches.len(),
-            "truncated": matches.len() >= limit
+            "truncated": matches.len() >= limit,
+            "symlink_cycles": cycles
         }))
     }
 
     async fn search(&self, args: FsToolArgs) -> Result<Value> {
         let path = args.path.unwrap_or_else(|| ".".to_string());
         let path = shellexpand::tilde(&path).to_string();
-        let pattern = args.pattern.ok_or_else(|| anyhow!("pattern required"))?;
+        let pattern = args.pattern.ok_or_else(|| ToolError::invalid("pattern required"))?;
         let limit = args.limit.unwrap_or(50);
         let context = args.context.unwrap_or(2);
         let ignore_case = args.ignore_case;
@@ -542,8 +1046,10 @@ impl FsTool {
         };
 
         let mut results = Vec::new();
+        let mut cycles = Vec::new();
 
         for entry in WalkDir::new(&path)
+            .follow_links(args.follow_symlinks)
             .into_iter()
             .filter_entry(|e| include_hidden || !e.file_name().to_string_lossy().starts_with('.'))
         {
@@ -551,40 +1057,50 @@ impl FsTool {
                 break;
             }
 
-            if let Ok(entry) = entry {
-                if !entry.file_type().is_file() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    cycles.extend(fs_links::cycle(&err));
                     continue;
                 }
+            };
 
-                // Skip binary files
-                let path_str = entry.path().to_string_lossy();
-                if path_str.ends_with(".exe") || path_str.ends_with(".bin") ||
-                   path_str.ends_with(".so") || path_str.ends_with(".dylib") {
-                    continue;
-                }
+            if !entry.file_type().is_file() {
+                continue;
+            }
 
-                if let Ok(content) = tokio::fs::read_to_string(entry.path()).await {
-                    let lines: Vec<&str> = content.lines().collect();
-                    for (i, line) in lines.iter().enumerate() {
-                        if regex.is_match(line) {
-                            let start = i.saturating_sub(context);
-                            let end = (i + context + 1).min(lines.len());
-                            let context_lines: Vec<String> = lines[start..end]
-                                .iter()
-                                .enumerate()
-                                .map(|(j, l)| format!("{:>4}:{}", start + j + 1, l))
-                                .collect();
-
-                            results.push(json!({
-                                "file": path_str,
-                                "line": i + 1,
-                                "match": line,
-                                "context": context_lines.join("\n")
-                            }));
-
-                            if results.len() >= limit {
-                                break;
-                            }
+            // Skip binary files
+            let path_str = entry.path().to_string_lossy();
+            if path_str.ends_with(".exe") || path_str.ends_with(".bin") ||
+               path_str.ends_with(".so") || path_str.ends_with(".dylib") {
+                continue;
+            }
+
+            if entry.metadata().is_ok_and(|m| m.len() > self.config.max_read_bytes) {
+                continue;
+            }
+
+            if let Ok(content) = tokio::fs::read_to_string(entry.path()).await {
+                let lines: Vec<&str> = content.lines().collect();
+                for (i, line) in lines.iter().enumerate() {
+                    if regex.is_match(line) {
+                        let start = i.saturating_sub(context);
+                        let end = (i + context + 1).min(lines.len());
+                        let context_lines: Vec<String> = lines[start..end]
+                            .iter()
+                            .enumerate()
+                            .map(|(j, l)| format!("{:>4}:{}", start + j + 1, l))
+                            .collect();
+
+                        results.push(json!({
+                            "file": path_str,
+                            "line": i + 1,
+                            "match": line,
+                            "context": context_lines.join("\n")
+                        }));
+
+                        if results.len() >= limit {
+                            break;
                         }
                     }
                 }
@@ -596,35 +1112,320 @@ impl FsTool {
             "path": path,
             "results": results,
             "count": results.len(),
-            "truncated": results.len() >= limit
+            "truncated": results.len() >= limit,
+            "symlink_cycles": cycles
+        }))
+    }
+
+    /// Regex search-and-replace across every file under path that .gitignore
+    /// and the include/exclude globs let through. All files are rewritten
+    /// only after every replacement is known, and not at all if there would
+    /// be more than `max_replacements`.
+    async fn replace(&self, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path).unwrap_or_else(|| ".".to_string());
+        let path = shellexpand::tilde(&path).to_string();
+        let pattern = args.pattern.ok_or_else(|| ToolError::invalid("pattern required"))?;
+        let replacement = args.replacement.ok_or_else(|| ToolError::invalid("replacement required"))?;
+        let max = args.max_replacements.unwrap_or(MAX_REPLACEMENTS);
+        let regex = regex::RegexBuilder::new(&pattern)
+            .case_insensitive(args.ignore_case)
+            .build()
+            .map_err(|e| ToolError::invalid(format!("Invalid regex: {}", e)))?;
+        let includes = PathGlobs::new(args.include.as_deref().unwrap_or_default())?;
+        let excludes = PathGlobs::new(args.exclude.as_deref().unwrap_or_default())?;
+
+        let root = PathBuf::from(&path);
+        let mut candidates = Vec::new();
+        for entry in ignore::WalkBuilder::new(&root)
+            .hidden(!args.include_hidden)
+            .follow_links(args.follow_symlinks)
+            .require_git(false)
+            .build()
+            .flatten()
+        {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            let relative = if relative.as_os_str().is_empty() { Path::new(entry.file_name()) } else { relative };
+            if (!includes.is_empty() && !includes.is_match(relative)) || excludes.is_match(relative) {
+                continue;
+            }
+            if entry.metadata().is_ok_and(|m| m.len() > self.config.max_read_bytes) {
+                continue;
+            }
+            candidates.push(entry.path().to_string_lossy().to_string());
+        }
+
+        let mut planned = Vec::new();
+        let mut total = 0;
+        let mut skipped = 0;
+        for file in candidates {
+            // Binary and undecodable files are left alone
+            let Ok((before, text, format)) = self.load_text(&file, None).await else {
+                skipped += 1;
+                continue;
+            };
+            let count = regex.find_iter(&text).count();
+            if count == 0 {
+                continue;
+            }
+            total += count;
+            if total > max {
+                return Err(ToolError::invalid(format!(
+                    "Replacing would change more than {} matches ({} so far, in {} files); narrow the pattern or paths, or raise max_replacements",
+                    max, total, planned.len() + 1
+                )).into());
+            }
+            let new = regex.replace_all(&text, replacement.as_str()).into_owned();
+            planned.push((file, before, text, new, format, count));
+        }
+
+        let mut results = Vec::new();
+        for (file, before, old, new, format, count) in planned {
+            if args.dry_run {
+                let mut preview = preview(&file, Some(&old), Some(&new));
+                preview["replacements"] = json!(count);
+                results.push(preview);
+                continue;
+            }
+            let bytes = format.encode(&new)?;
+            write_atomic(&file, &bytes, args.fsync).await?;
+            self.journal.record(args.session_id.as_deref(), &file, "replace", Some(before), Some(bytes));
+            results.push(json!({ "path": file, "replacements": count }));
+        }
+
+        Ok(json!({
+            "pattern": pattern,
+            "replacement": replacement,
+            "path": path,
+            "dry_run": args.dry_run,
+            "files": results.len(),
+            "replacements": total,
+            "skipped": skipped,
+            "results": results
+        }))
+    }
+
+    async fn dedupe(&self, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path).unwrap_or_else(|| ".".to_string());
+        let root = PathBuf::from(shellexpand::tilde(&path).as_ref());
+        let similarity = args.similarity.unwrap_or(0.8);
+        if !(0.0..=1.0).contains(&similarity) {
+            return Err(ToolError::invalid("similarity must be between 0 and 1").into());
+        }
+        let options = fs_dedupe::Options {
+            include_hidden: args.include_hidden,
+            include: PathGlobs::new(args.include.as_deref().unwrap_or_default())?,
+            exclude: PathGlobs::new(args.exclude.as_deref().unwrap_or_default())?,
+            similarity,
+            limit: args.limit.unwrap_or(50),
+        };
+        tokio::task::spawn_blocking(move || fs_dedupe::scan(&root, &options)).await?
+    }
+
+    async fn outline(&self, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path)
+            .ok_or_else(|| ToolError::invalid("path required"))?;
+        let path = shellexpand::tilde(&path).to_string();
+        let language = match args.language {
+            Some(language) => language.to_lowercase(),
+            None => ast_search::language_for(Path::new(&path))
+                .ok_or_else(|| ToolError::unsupported(format!("No outline for {}; set language (rust, python, javascript, typescript, go, java, c, cpp)", path)))?
+                .to_string(),
+        };
+        let (_, text, _) = self.load_text(&path, args.encoding.as_deref()).await?;
+        let symbols = tokio::task::spawn_blocking({
+            let language = language.clone();
+            move || ast_search::outline(&text, &language).map(|symbols| (symbols, text.lines().count()))
+        }).await?;
+        let (symbols, lines) = symbols
+            .ok_or_else(|| ToolError::unsupported(format!("Unsupported outline language: {}", language)))?;
+
+        fn count(symbols: &[ast_search::OutlineSymbol]) -> usize {
+            symbols.iter().map(|s| 1 + count(&s.children)).sum()
+        }
+        Ok(json!({
+            "path": path,
+            "language": language,
+            "lines": lines,
+            "count": count(&symbols),
+            "symbols": symbols
         }))
     }
 
     async fn info(&self, args: FsToolArgs) -> Result<Value> {
         let path = args.file_path.or(args.path)
-            .ok_or_else(|| anyhow!("path required"))?;
+            .ok_or_else(|| ToolError::invalid("path required"))?;
         let path = shellexpand::tilde(&path).to_string();
 
-        let metadata = tokio::fs::metadata(&path).await?;
-        let file_type = if metadata.is_dir() {
+        // Links report their own details plus those of whatever they resolve to
+        let link = fs_links::describe(Path::new(&path));
+        let metadata = match tokio::fs::metadata(&path).await {
+            Err(_) if link.is_some() => tokio::fs::symlink_metadata(&path).await?,
+            metadata => metadata?,
+        };
+        let file_type = if link.is_some() {
+            "symlink"
+        } else if metadata.is_dir() {
             "directory"
         } else if metadata.is_file() {
             "file"
-        } else if metadata.is_symlink() {
-            "symlink"
         } else {
             "unknown"
         };
 
-        Ok(json!({
+        let (sha256, encoding) = if !metadata.is_file() {
+            (None, None)
+        } else if metadata.len() <= self.config.max_read_bytes {
+            let bytes = tokio::fs::read(&path).await?;
+            let format = fs_encoding::decode(&path, &bytes, None).ok().map(|(_, f)| f);
+            (Some(hash::sha256_hex(&bytes)), format.map(|f| f.encoding.name()))
+        } else {
+            let file = PathBuf::from(&path);
+            let digest = tokio::task::spawn_blocking(move || hash::file_hex(hash::Algorithm::Sha256, &file)).await??;
+            (Some(digest), None)
+        };
+
+        let mut info = json!({
             "path": path,
             "type": file_type,
+            "sha256": sha256,
+            "encoding": encoding,
             "size": metadata.len(),
             "readonly": metadata.permissions().readonly(),
             "modified": metadata.modified().ok().map(|t| {
                 chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()
             })
-        }))
+        });
+        if let Some(link) = link {
+            info["target_type"] = json!(if metadata.is_symlink() {
+                None
+            } else if metadata.is_dir() {
+                Some("directory")
+            } else {
+                Some("file")
+            });
+            info["link"] = link;
+        }
+        let resolved = std::fs::canonicalize(&path).unwrap_or_else(|_| PathBuf::from(&path));
+        if let (Some(info), Value::Object(unix)) = (info.as_object_mut(), fs_perms::describe(&resolved)) {
+            info.extend(unix);
+        }
+        Ok(info)
+    }
+
+    fn links(&self, action: FsAction, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path)
+            .ok_or_else(|| ToolError::invalid("path required"))?;
+        let path = PathBuf::from(shellexpand::tilde(&path).as_ref());
+
+        match action {
+            FsAction::Symlink => {
+                let target = args.target.ok_or_else(|| ToolError::invalid("target required"))?;
+                fs_links::symlink(&path, Path::new(shellexpand::tilde(&target).as_ref()), args.overwrite)
+            }
+            FsAction::Readlink => fs_links::readlink(&path),
+            _ => fs_links::realpath(&path),
+        }
+    }
+
+    async fn attributes(&self, action: FsAction, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path)
+            .ok_or_else(|| ToolError::invalid("path required"))?;
+        let path = PathBuf::from(shellexpand::tilde(&path).as_ref());
+        let time = args.mtime.as_deref().map(parse_time).transpose()?;
+
+        tokio::task::spawn_blocking(move || match action {
+            FsAction::Chmod => {
+                let spec = args.permissions.ok_or_else(|| ToolError::invalid("permissions required"))?;
+                fs_perms::chmod(&path, &spec, args.recursive)
+            }
+            FsAction::Chown => fs_perms::chown(&path, args.owner.as_deref(), args.group.as_deref(), args.recursive),
+            _ => fs_perms::touch(&path, time),
+        }).await?
+    }
+
+    async fn archive(&self, action: FsAction, args: FsToolArgs) -> Result<Value> {
+        let archive = args.file_path.or(args.path)
+            .ok_or_else(|| ToolError::invalid("path (the archive) required"))?;
+        let archive = shellexpand::tilde(&archive).to_string();
+        let format = fs_archive::Format::detect(&archive, args.format.as_deref())?;
+        let filter = fs_archive::Filter::new(
+            args.include.as_deref().unwrap_or_default(),
+            args.exclude.as_deref().unwrap_or_default(),
+        )?;
+        let limit = args.limit.unwrap_or(1000);
+        let overwrite = args.overwrite;
+        let source = args.source.map(|s| shellexpand::tilde(&s).to_string());
+        let dest = args.dest.map(|d| PathBuf::from(shellexpand::tilde(&d).as_ref()))
+            .unwrap_or_else(|| fs_archive::default_dest(&archive));
+
+        // zip and tar do blocking I/O
+        tokio::task::spawn_blocking(move || {
+            let archive = Path::new(&archive);
+            match action {
+                FsAction::ArchiveCreate => {
+                    let source = source.ok_or_else(|| ToolError::invalid("source required"))?;
+                    fs_archive::create(archive, Path::new(&source), format, &filter, overwrite)
+                }
+                FsAction::ArchiveExtract => fs_archive::extract(archive, &dest, format, &filter, overwrite),
+                _ => fs_archive::list(archive, format, &filter, limit),
+            }
+        }).await?
+    }
+
+    /// Digest of a file, or merkle digest of a directory tree, optionally
+    /// checked against `expected_hash`
+    async fn hash(&self, args: FsToolArgs) -> Result<Value> {
+        let path = args.file_path.or(args.path)
+            .ok_or_else(|| ToolError::invalid("path required"))?;
+        let path = shellexpand::tilde(&path).to_string();
+        // "sha1:abc..." names its algorithm
+        let (prefix, expected) = match args.expected_hash.as_deref().map(str::trim) {
+            Some(value) => match value.split_once(':') {
+                Some((algorithm, digest)) => (Some(algorithm.to_string()), Some(digest.to_lowercase())),
+                None => (None, Some(value.to_lowercase())),
+            },
+            None => (None, None),
+        };
+        let algorithm: hash::Algorithm = match args.algorithm.or(prefix) {
+            Some(name) => name.parse()?,
+            None => hash::Algorithm::default(),
+        };
+        let filter = fs_archive::Filter::new(&[], args.exclude.as_deref().unwrap_or_default())?;
+        let limit = args.limit.unwrap_or(1000);
+
+        let target = PathBuf::from(&path);
+        let mut result = tokio::task::spawn_blocking(move || -> Result<Value> {
+            if !std::fs::metadata(&target)?.is_dir() {
+                let digest = hash::file_hex(algorithm, &target)?;
+                return Ok(json!({ "type": "file", "hash": digest }));
+            }
+            let mut files = Vec::new();
+            let mut count = 0;
+            let digest = hash::tree_hex(algorithm, &target, &|p| !filter.excludes(p), &mut |p, d| {
+                count += 1;
+                if files.len() < limit {
+                    files.push(json!({ "path": p, "hash": d }));
+                }
+            })?;
+            Ok(json!({
+                "type": "tree",
+                "hash": digest,
+                "file_count": count,
+                "files": files,
+                "truncated": count > limit
+            }))
+        }).await??;
+
+        result["path"] = json!(path);
+        result["algorithm"] = json!(algorithm.name());
+        if let Some(expected) = expected {
+            result["verified"] = json!(result["hash"] == expected.as_str());
+            result["expected"] = json!(expected);
+        }
+        Ok(result)
     }
 
     fn help(&self) -> Result<Value> {
@@ -633,25 +1434,134 @@ impl FsTool {
             "version": "0.12.0",
             "description": "Unified filesystem tool (HIP-0300)",
             "actions": {
-                "read": "Read file contents",
+                "read": "Read file contents (mode=head/tail or byte_offset/length for large files)",
+                "read_lines": "Read lines start..=end verbatim (no line-number prefixes)",
                 "write": "Write file contents",
                 "edit": "Edit file with old/new replacement",
-                "patch": "Apply Rust-style patch format",
-                "tree": "Display directory tree",
-                "find": "Find files by pattern",
+                "edit_lines": "Replace lines start..=end with new_content (end = start - 1 inserts)",
+                "patch": "Apply a unified diff (git diff, diff -u) or *** Begin Patch block; all-or-nothing, with offsets, fuzz, reverse and check",
+                "tree": "Display directory tree; format=json for nested entries with size, mtime and counts, sort by name, size or mtime",
+                "find": "Find entries by glob: name patterns (*.rs) or relative-path patterns (src/**/*.rs), with include/exclude and file_type",
                 "search": "Search file contents",
-                "info": "Get file info"
+                "outline": "List the functions, classes, impl blocks and other symbols of a source file with line ranges and signatures, nested by container (tree-sitter; language from the extension or language)",
+                "dedupe": "Find exact duplicate files (by hash) and near-duplicate text files (by token shingles) under path, as clusters with sizes",
+                "replace": "Regex replace across files under path (respects .gitignore; include/exclude globs; $1 capture groups; dry_run previews diffs)",
+                "info": "Get file info",
+                "undo": "Revert the session's latest write/edit/patch (or latest to path)",
+                "redo": "Reapply the latest undone change",
+                "history": "List changes that undo and redo would apply",
+                "archive_create": "Pack source into the zip/tar/tar.gz archive at path",
+                "archive_extract": "Unpack the archive at path into dest, refusing paths that escape it",
+                "archive_list": "List archive entries without extracting",
+                "hash": "md5/sha1/sha256/blake3 of a file or merkle hash of a directory; verify with expected_hash",
+                "chmod": "Set permissions (octal or symbolic), optionally recursive (Unix)",
+                "chown": "Set owner and/or group by name or id, optionally recursive (Unix)",
+                "touch": "Create a file or set its timestamps to mtime or now",
+                "symlink": "Create a symlink at path pointing to target (relative targets resolve from the link's directory)",
+                "readlink": "Show a symlink's target, resolved path and whether it is broken",
+                "realpath": "Resolve path to an absolute path with every symlink followed",
+                "render": "Render a Handlebars or Tera template (inline template or source file) with variables and write it to path"
             }
         }))
     }
 }
 
+/// Format for replacing `path` whose current contents are `before`: its
+/// existing encoding and line endings, or UTF-8/LF for new files, unless
+/// `encoding` asks otherwise. Also returns the current text for previews.
+fn target_format(path: &str, before: Option<&[u8]>, encoding: Option<&str>) -> Result<(Option<String>, TextFormat)> {
+    let existing = before.and_then(|bytes| fs_encoding::decode(path, bytes, None).ok());
+    let old = existing.as_ref().map(|(text, _)| text.clone())
+        .or_else(|| before.map(|bytes| String::from_utf8_lossy(bytes).into_owned()));
+    let format = match (encoding, existing) {
+        (Some(label), Some((_, format))) => {
+            let encoding = fs_encoding::for_label(label)?;
+            if encoding == format.encoding {
+                format
+            } else {
+                TextFormat { crlf: format.crlf, ..TextFormat::with_encoding(encoding) }
+            }
+        }
+        (Some(label), None) => TextFormat::with_encoding(fs_encoding::for_label(label)?),
+        (None, Some((_, format))) => format,
+        (None, None) => TextFormat::default(),
+    };
+    Ok((old, format))
+}
+
+/// Enforce the `expected_hash` / `if_unchanged_since` preconditions against
+/// the file's `current` contents, so changes made by someone else since the
+/// caller read the file are rejected rather than overwritten
+async fn check_unchanged(
+    path: &str,
+    current: Option<&[u8]>,
+    expected_hash: Option<&str>,
+    if_unchanged_since: Option<&str>,
+) -> Result<()> {
+    if let Some(expected) = expected_hash {
+        let expected = expected.trim().trim_start_matches("sha256:");
+        let Some(bytes) = current else {
+            return Err(ToolError::conflict(format!(
+                "{} no longer exists; expected sha256 {}", path, expected
+            )).into());
+        };
+        let actual = hash::sha256_hex(bytes);
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Err(ToolError::conflict(format!(
+                "{} was modified: sha256 is {}, expected {}", path, actual, expected
+            )).into());
+        }
+    }
+    if let Some(since) = if_unchanged_since {
+        let since = parse_time(since)?;
+        if let Ok(modified) = tokio::fs::metadata(path).await.and_then(|m| m.modified()) {
+            let modified = chrono::DateTime::<chrono::Utc>::from(modified);
+            if modified > since {
+                return Err(ToolError::conflict(format!(
+                    "{} was modified at {}, after {}", path, modified.to_rfc3339(), since.to_rfc3339()
+                )).into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// RFC 3339 timestamp or unix seconds
+fn parse_time(value: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.with_timezone(&chrono::Utc));
+    }
+    value.parse::<f64>().ok()
+        .and_then(|secs| chrono::DateTime::from_timestamp_millis((secs * 1000.0) as i64))
+        .ok_or_else(|| ToolError::invalid(format!(
+            "if_unchanged_since must be RFC 3339 or unix seconds, got '{}'", value
+        )).into())
+}
+
+/// What replacing `old` with `new` at `path` would do, for dry runs;
+/// `None` means the file is absent before or after
+fn preview(path: &str, old: Option<&str>, new: Option<&str>) -> Value {
+    let op = match (old, new) {
+        (None, _) => "create",
+        (_, None) => "delete",
+        _ => "update",
+    };
+    json!({
+        "path": path,
+        "dry_run": true,
+        "op": op,
+        "bytes": new.map_or(0, str::len),
+        "diff": diff::unified(path, old, new)
+    })
+}
+
 /// MCP Tool Definition
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FsToolDefinition {
     pub name: String,
     pub description: String,
     pub input_schema: Value,
+    pub output_schema: Value,
 }
 
 impl FsToolDefinition {
@@ -662,35 +1572,104 @@ impl FsToolDefinition {
 
 Actions:
 - read: Read file contents
+- read_lines: Read lines start..=end verbatim
 - write: Write file contents
 - edit: Edit file with old/new replacement
-- patch: Apply Rust-style patch format
-- tree: Display directory tree
-- find: Find files by pattern
+- edit_lines: Replace lines start..=end with new_content
+- patch: Apply a unified diff or *** Begin Patch block (check, reverse, fuzz, strip; path is the base directory)
+- tree: Display directory tree (format: text or json; sort: name, size or mtime; limit caps entries)
+- find: Find files by glob; patterns with / match the relative path (src/**/*.rs), others the name
 - search: Search file contents
-- info: Get file info"#.to_string(),
+- replace: Regex search-and-replace across files (pattern, replacement with $1 groups, dry_run, max_replacements)
+- dedupe: Exact and near-duplicate file clusters (similarity threshold, include/exclude)
+- outline: Symbols of a source file (functions, classes, impls) with line ranges and signatures
+- info: Get file info
+- undo/redo: Revert or reapply this session's write/edit/patch changes
+- history: List journaled changes (optionally for one path)
+- archive_create/archive_extract/archive_list: zip, tar and tar.gz archives at path
+- hash: Checksum a file or directory tree (md5/sha1/sha256/blake3), optionally verifying expected_hash
+- chmod/chown/touch: Permissions, ownership and timestamps (info reports mode, owner, group and xattrs)
+- symlink/readlink/realpath: Create and resolve symlinks; tree/find/search take follow_symlinks
+- render: Render a Handlebars/Tera template with JSON variables into path (or return it without path)"#.to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["read", "write", "edit", "patch", "tree", "find", "search", "info", "help"],
+                        "enum": ["read", "read_lines", "write", "edit", "edit_lines", "patch", "tree", "find", "search", "replace", "dedupe", "outline", "info", "undo", "redo", "history", "archive_create", "archive_extract", "archive_list", "hash", "chmod", "chown", "touch", "symlink", "readlink", "realpath", "render", "help"],
                         "default": "help"
                     },
                     "path": {"type": "string", "description": "File or directory path"},
                     "file_path": {"type": "string", "description": "Alias for path"},
                     "content": {"type": "string", "description": "Content for write"},
                     "old_string": {"type": "string", "description": "Text to replace"},
+                    "old_text": {"type": "string", "description": "Alias for old_string"},
                     "new_string": {"type": "string", "description": "Replacement text"},
+                    "new_text": {"type": "string", "description": "Alias for new_string"},
                     "replace_all": {"type": "boolean", "description": "Replace all occurrences", "default": false},
-                    "patch": {"type": "string", "description": "Patch text"},
-                    "pattern": {"type": "string", "description": "Pattern for find/search"},
+                    "patch": {"type": "string", "description": "Patch text: a unified diff (git diff, diff -u) or a *** Begin Patch block. Relative paths resolve against path when given"},
+                    "check": {"type": "boolean", "description": "For patch: report which hunks would fail without writing anything", "default": false},
+                    "reverse": {"type": "boolean", "description": "For patch: apply in reverse, undoing the patch", "default": false},
+                    "fuzz": {"type": "integer", "minimum": 0, "description": "For patch: context lines a hunk may ignore at each end when it does not match exactly (default 2)"},
+                    "strip": {"type": "integer", "minimum": 0, "description": "For patch: leading path components to drop, like patch -p (default: git's a/ and b/)"},
+                    "pattern": {"type": "string", "description": "Pattern for find (glob; with / it matches the path relative to path, e.g. src/**/*.rs) or search/replace (regex)"},
                     "depth": {"type": "integer", "description": "Max depth for tree"},
-                    "limit": {"type": "integer", "description": "Limit results"},
+                    "limit": {"type": "integer", "description": "Limit results (for tree: max entries, default 1000)"},
                     "offset": {"type": "integer", "description": "Offset for pagination"},
+                    "start": {"type": "integer", "minimum": 1, "description": "First line (1-based) for read_lines/edit_lines"},
+                    "end": {"type": "integer", "minimum": 0, "description": "Last line, inclusive; read_lines defaults to end of file, edit_lines to start"},
+                    "new_content": {"type": "string", "description": "Replacement text for edit_lines (alias: content); empty deletes the range"},
+                    "mode": {"type": "string", "enum": ["full", "head", "tail"], "description": "For read: stream the first or last `limit` lines (default 100) instead of loading the file"},
+                    "byte_offset": {"type": "integer", "minimum": 0, "description": "For read: return raw bytes from this offset (use next_offset to continue)"},
+                    "length": {"type": "integer", "minimum": 1, "description": "For read with byte_offset: bytes to return (default 65536)"},
+                    "source": {"type": "string", "description": "For archive_create: directory (its contents) or file to pack. For render: template file"},
+                    "dest": {"type": "string", "description": "For archive_extract: destination directory (default: archive path without extension)"},
+                    "include": {"type": "array", "items": {"type": "string"}, "description": "For archives: globs of entries to include, matched against the path inside the archive or the file name. For find: more patterns, like pattern. For replace and dedupe: only files matching these"},
+                    "exclude": {"type": "array", "items": {"type": "string"}, "description": "For archives, directory hashes, find, replace and dedupe: globs of entries to leave out (e.g. target, *.log); excluded directories are not descended"},
+                    "file_type": {"type": "string", "enum": ["file", "dir", "symlink"], "description": "For find: only entries of this type"},
+                    "format": {"type": "string", "enum": ["zip", "tar", "tar.gz", "tgz", "text", "json"], "description": "Archive format (zip, tar, tar.gz, tgz), defaulting from the archive extension. For tree: text (default) or json, a nested structure with type, size, mtime and per-directory counts"},
+                    "sort": {"type": "string", "enum": ["name", "size", "mtime"], "description": "For tree: entry order within each directory (name puts directories first; size and mtime put largest and newest first)"},
+                    "overwrite": {"type": "boolean", "description": "For archives: replace an existing archive or extracted files. For symlink: replace an existing link. For render: replace an existing file", "default": false},
+                    "algorithm": {"type": "string", "enum": ["md5", "sha1", "sha256", "blake3"], "description": "For hash: digest algorithm (default sha256)"},
+                    "permissions": {"type": "string", "description": "For chmod: octal (755) or symbolic (u+x,go-w)"},
+                    "owner": {"type": "string", "description": "For chown: user name or uid"},
+                    "group": {"type": "string", "description": "For chown: group name or gid"},
+                    "recursive": {"type": "boolean", "description": "For chmod/chown: also change everything below a directory (symlinks are not followed)", "default": false},
+                    "mtime": {"type": "string", "description": "For touch: timestamp to set (RFC 3339 or unix seconds); defaults to now"},
+                    "target": {"type": "string", "description": "For symlink: what the new link points to"},
+                    "follow_symlinks": {"type": "boolean", "description": "For tree/find/search: descend into symlinked directories; cycles are skipped and listed in symlink_cycles", "default": false},
+                    "template": {"type": "string", "description": "For render: inline template text"},
+                    "engine": {"type": "string", "enum": ["handlebars", "tera"], "description": "For render: template language; defaults from the source extension (.j2/.tera are tera), else handlebars"},
+                    "variables": {"type": "object", "description": "For render: values available to the template; a variable the template uses but is missing here is an error"},
+                    "encoding": {"type": "string", "description": "Text encoding (e.g. utf-8, utf-16le, shift_jis, latin1); read/edit detect it and write keeps the file's own unless set"},
                     "include_hidden": {"type": "boolean", "description": "Include hidden files", "default": false},
                     "context": {"type": "integer", "description": "Context lines for search"},
-                    "ignore_case": {"type": "boolean", "description": "Case insensitive search", "default": false}
+                    "ignore_case": {"type": "boolean", "description": "Case insensitive search", "default": false},
+                    "dry_run": {"type": "boolean", "description": "For write/edit/patch/replace: return the diff without writing", "default": false},
+                    "replacement": {"type": "string", "description": "For replace: replacement text; $1 or ${name} insert capture groups, $$ is a literal $"},
+                    "language": {"type": "string", "enum": ["rust", "python", "javascript", "typescript", "go", "java", "c", "cpp"], "description": "For outline: source language, when the file extension doesn't identify it"},
+                    "similarity": {"type": "number", "minimum": 0, "maximum": 1, "description": "For dedupe: minimum similarity of near-duplicate files (default 0.8)"},
+                    "max_replacements": {"type": "integer", "minimum": 1, "description": "For replace: refuse (writing nothing) if more matches than this would change (default 1000)"},
+                    "fsync": {"type": "boolean", "description": "For write/edit/patch: flush to disk before returning", "default": false},
+                    "expected_hash": {"type": "string", "description": "For write/edit/edit_lines: fail with a conflict unless the file's sha256 (from read/info) still matches. For hash: digest to verify against, optionally prefixed with its algorithm (sha1:...)"},
+                    "if_unchanged_since": {"type": "string", "description": "For write/edit/edit_lines: fail with a conflict if the file was modified after this time (RFC 3339 or unix seconds)"},
+                    "max_bytes": {"type": "integer", "minimum": 1, "description": "Cap on the result size; longer output is cut (head and tail of logs, leading results of searches) with a cursor for the rest"},
+                    "max_output_tokens": {"type": "integer", "minimum": 1, "description": "Like max_bytes, counting about 4 bytes per token"}
+                },
+                "additionalProperties": false
+            }),
+            output_schema: json!({
+                "type": "object",
+                "description": "Fields depend on the action",
+                "properties": {
+                    "path": {"type": "string"},
+                    "content": {"type": "string", "description": "For read: the lines, each after its number"},
+                    "total_lines": {"type": "integer"},
+                    "offset": {"type": "integer"},
+                    "sha256": {"type": "string", "description": "Pass back as expected_hash to guard a write"},
+                    "results": {"type": "array", "items": {"type": "object"}, "description": "For find and search: the matches"},
+                    "count": {"type": "integer"},
+                    "truncated": {"type": "boolean"}
                 }
             }),
         }
@@ -764,6 +1743,359 @@ mod tests {
         assert_eq!(content, "hello rust");
     }
 
+    #[tokio::test]
+    async fn test_dry_run() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("dry.txt");
+        std::fs::write(&file_path, "hello world\n").unwrap();
+        let path = file_path.to_string_lossy().to_string();
+
+        let tool = FsTool::new();
+        let args = FsToolArgs {
+            action: "edit".to_string(),
+            path: Some(path.clone()),
+            old_string: Some("world".to_string()),
+            new_string: Some("rust".to_string()),
+            dry_run: true,
+            ..Default::default()
+        };
+        let result: Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+        assert_eq!(result["op"], "update");
+        assert!(result["diff"].as_str().unwrap().contains("-hello world\n+hello rust\n"));
+
+        let args = FsToolArgs {
+            action: "patch".to_string(),
+            patch: Some(format!("*** Begin Patch\n*** Delete File: {}\n*** End Patch", path)),
+            dry_run: true,
+            ..Default::default()
+        };
+        let result: Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+        assert_eq!(result["results"][0]["op"], "delete");
+
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "hello world\n");
+    }
+
+    #[tokio::test]
+    async fn test_undo_edit() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("undo.txt").to_string_lossy().to_string();
+        let tool = FsTool::new();
+        let run = |args: FsToolArgs| async { tool.execute(args).await.unwrap() };
+
+        run(FsToolArgs { action: "write".into(), path: Some(path.clone()), content: Some("a".into()), ..Default::default() }).await;
+        run(FsToolArgs {
+            action: "edit".into(),
+            path: Some(path.clone()),
+            old_string: Some("a".into()),
+            new_string: Some("b".into()),
+            ..Default::default()
+        }).await;
+        let history: Value = serde_json::from_str(&run(FsToolArgs { action: "history".into(), ..Default::default() }).await).unwrap();
+        assert_eq!(history["undo"][0]["action"], "edit");
+
+        run(FsToolArgs { action: "undo".into(), ..Default::default() }).await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a");
+        run(FsToolArgs { action: "redo".into(), path: Some(path.clone()), ..Default::default() }).await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "b");
+    }
+
+    #[tokio::test]
+    async fn test_line_ranges() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lines.rs").to_string_lossy().to_string();
+        std::fs::write(&path, "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+        let tool = FsTool::new();
+        let lines = |start: usize, end: Option<usize>, new_content: Option<&str>| FsToolArgs {
+            action: if new_content.is_some() { "edit_lines" } else { "read_lines" }.into(),
+            path: Some(path.clone()),
+            start: Some(start),
+            end,
+            new_content: new_content.map(String::from),
+            ..Default::default()
+        };
+
+        let read: Value = serde_json::from_str(&tool.execute(lines(2, None, None)).await.unwrap()).unwrap();
+        assert_eq!(read["content"], "fn b() {}\nfn c() {}\n");
+        assert_eq!(read["end"], 3);
+
+        tool.execute(lines(2, Some(2), Some("fn b2() {}\nfn b3() {}"))).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fn a() {}\nfn b2() {}\nfn b3() {}\nfn c() {}\n");
+
+        // end = start - 1 inserts, empty content deletes
+        tool.execute(lines(1, Some(0), Some("// header"))).await.unwrap();
+        tool.execute(lines(3, Some(4), Some(""))).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "// header\nfn a() {}\nfn c() {}\n");
+
+        assert!(tool.execute(lines(9, None, None)).await.is_err());
+        assert!(tool.execute(lines(2, Some(7), Some("x"))).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_large_file_reads() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("app.log").to_string_lossy().to_string();
+        let log: String = (1..=50).map(|i| format!("line {}\n", i)).collect();
+        std::fs::write(&path, &log).unwrap();
+        let tool = FsTool::with_config(JournalConfig::default(), FsConfig { max_read_bytes: 100 });
+        let read = |mode: Option<&str>, limit: Option<usize>, byte_offset: Option<u64>| {
+            let args = FsToolArgs {
+                action: "read".into(),
+                path: Some(path.clone()),
+                mode: mode.map(String::from),
+                limit,
+                byte_offset,
+                length: Some(10),
+                ..Default::default()
+            };
+            async { serde_json::from_str::<Value>(&tool.execute(args).await?).map_err(anyhow::Error::from) }
+        };
+
+        let err = read(None, None, None).await.unwrap_err();
+        assert!(err.to_string().contains("read limit"));
+
+        let head = read(Some("head"), Some(2), None).await.unwrap();
+        assert_eq!(head["content"], "line 1\nline 2");
+        assert_eq!(head["truncated"], true);
+
+        let tail = read(Some("tail"), Some(3), None).await.unwrap();
+        assert_eq!(tail["content"], "line 48\nline 49\nline 50");
+
+        let chunk = read(None, None, Some(7)).await.unwrap();
+        assert_eq!(chunk["content"], "line 2\nlin");
+        assert_eq!(chunk["next_offset"], 17);
+        assert_eq!(chunk["eof"], false);
+        let last = read(None, None, Some(log.len() as u64 - 3)).await.unwrap();
+        assert_eq!(last["eof"], true);
+    }
+
+    #[tokio::test]
+    async fn test_hash() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "abc").unwrap();
+        std::fs::create_dir(dir.path().join("logs")).unwrap();
+        std::fs::write(dir.path().join("logs/x.log"), "noise").unwrap();
+        let tool = FsTool::new();
+        let hash = |path: PathBuf, expected_hash: Option<&str>| {
+            let args = FsToolArgs {
+                action: "hash".into(),
+                path: Some(path.to_string_lossy().to_string()),
+                expected_hash: expected_hash.map(String::from),
+                exclude: Some(vec!["*.log".into()]),
+                ..Default::default()
+            };
+            async { serde_json::from_str::<Value>(&tool.execute(args).await.unwrap()).unwrap() }
+        };
+
+        let file = hash(dir.path().join("a.txt"), Some("md5:900150983cd24fb0d6963f7d28e17f72")).await;
+        assert_eq!(file["algorithm"], "md5");
+        assert_eq!(file["verified"], true);
+
+        let tree = hash(dir.path().to_path_buf(), Some("00")).await;
+        assert_eq!(tree["type"], "tree");
+        assert_eq!(tree["file_count"], 1);
+        assert_eq!(tree["verified"], false);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_chmod_and_info() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("new.sh").to_string_lossy().to_string();
+        let tool = FsTool::new();
+        let run = |action: &str, permissions: Option<&str>| {
+            let args = FsToolArgs {
+                action: action.into(),
+                path: Some(path.clone()),
+                permissions: permissions.map(String::from),
+                ..Default::default()
+            };
+            async { serde_json::from_str::<Value>(&tool.execute(args).await.unwrap()).unwrap() }
+        };
+
+        assert_eq!(run("touch", None).await["created"], true);
+        run("chmod", Some("750")).await;
+        let info = run("info", None).await;
+        assert_eq!(info["mode"], "0750");
+        assert_eq!(info["permissions"], "-rwxr-x---");
+        assert!(info["uid"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_unified_patch() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(dir.path().join("b.txt"), "left\n").unwrap();
+        let diff = "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n\
+                    diff --git a/b.txt b/b.txt\n--- a/b.txt\n+++ b/b.txt\n@@ -1 +1 @@\n-right\n+RIGHT\n";
+        let tool = FsTool::new();
+        let patch = |check: bool, reverse: bool| {
+            let args = FsToolArgs {
+                action: "patch".into(),
+                path: Some(dir.path().to_string_lossy().to_string()),
+                patch: Some(diff.to_string()),
+                check,
+                reverse,
+                ..Default::default()
+            };
+            tool.execute(args)
+        };
+
+        // b.txt does not match, so neither file changes
+        let report: Value = serde_json::from_str(&patch(true, false).await.unwrap()).unwrap();
+        assert_eq!(report["applies"], false);
+        assert_eq!(report["results"][0]["status"], "ok");
+        assert_eq!(report["results"][1]["hunks"][0]["status"], "failed");
+        assert!(patch(false, false).await.unwrap_err().to_string().contains("b.txt hunk 1"));
+        assert_eq!(std::fs::read_to_string(dir.path().join("a.txt")).unwrap(), "one\ntwo\nthree\n");
+
+        std::fs::write(dir.path().join("b.txt"), "right\n").unwrap();
+        patch(false, false).await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join("a.txt")).unwrap(), "one\nTWO\nthree\n");
+        patch(false, true).await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.path().join("b.txt")).unwrap(), "right\n");
+    }
+
+    #[tokio::test]
+    async fn test_render() {
+        let dir = TempDir::new().unwrap();
+        let template = dir.path().join("module.rs.j2");
+        std::fs::write(&template, "pub mod {{ name }};\n").unwrap();
+        let out = dir.path().join("src/lib.rs").to_string_lossy().to_string();
+        let tool = FsTool::new();
+        let render = |source: Option<&Path>, path: Option<&str>| {
+            let args = FsToolArgs {
+                action: "render".into(),
+                source: source.map(|s| s.to_string_lossy().to_string()),
+                template: source.is_none().then(|| "# {{title}}".to_string()),
+                variables: Some(json!({"name": "parser", "title": "Notes"})),
+                path: path.map(String::from),
+                ..Default::default()
+            };
+            tool.execute(args)
+        };
+
+        let inline: Value = serde_json::from_str(&render(None, None).await.unwrap()).unwrap();
+        assert_eq!(inline["content"], "# Notes");
+        let written: Value = serde_json::from_str(&render(Some(&template), Some(&out)).await.unwrap()).unwrap();
+        assert_eq!(written["engine"], "tera");
+        assert_eq!(std::fs::read_to_string(&out).unwrap(), "pub mod parser;\n");
+        assert!(render(Some(&template), Some(&out)).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_follow_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(dir.path().join("shared")).unwrap();
+        std::fs::write(dir.path().join("shared/util.rs"), "fn util() {}").unwrap();
+        std::fs::create_dir(&repo).unwrap();
+        let tool = FsTool::new();
+        let run = |action: &str, path: &Path, target: Option<&str>, follow_symlinks: bool| {
+            let args = FsToolArgs {
+                action: action.into(),
+                path: Some(path.to_string_lossy().to_string()),
+                target: target.map(String::from),
+                pattern: Some("*.rs".into()),
+                follow_symlinks,
+                ..Default::default()
+            };
+            async { serde_json::from_str::<Value>(&tool.execute(args).await.unwrap()).unwrap() }
+        };
+
+        run("symlink", &repo.join("shared"), Some("../shared"), false).await;
+        run("symlink", &repo.join("self"), Some("."), false).await;
+        let info = run("info", &repo.join("shared"), None, false).await;
+        assert_eq!(info["type"], "symlink");
+        assert_eq!(info["target_type"], "directory");
+        assert_eq!(info["link"]["target"], "../shared");
+
+        assert_eq!(run("find", &repo, None, false).await["count"], 0);
+        let found = run("find", &repo, None, true).await;
+        assert_eq!(found["count"], 1);
+        assert_eq!(found["symlink_cycles"].as_array().unwrap().len(), 1);
+        assert!(run("tree", &repo, None, false).await["tree"].as_str().unwrap().contains("shared -> ../shared"));
+    }
+
+    #[tokio::test]
+    async fn test_preserves_encoding() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("legacy.txt").to_string_lossy().to_string();
+        std::fs::write(&path, b"caf\xe9\r\nbar\r\n").unwrap();
+        let tool = FsTool::new();
+
+        let read: Value = serde_json::from_str(&tool.execute(FsToolArgs {
+            action: "read".into(),
+            path: Some(path.clone()),
+            ..Default::default()
+        }).await.unwrap()).unwrap();
+        assert_eq!(read["encoding"], "windows-1252");
+        assert_eq!(read["line_endings"], "crlf");
+        assert!(read["content"].as_str().unwrap().contains("caf\u{e9}"));
+
+        tool.execute(FsToolArgs {
+            action: "edit".into(),
+            path: Some(path.clone()),
+            old_string: Some("bar\n".into()),
+            new_string: Some("na\u{ef}ve\nbaz\n".into()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"caf\xe9\r\nna\xefve\r\nbaz\r\n");
+
+        // Writing a new file in UTF-16 adds a BOM
+        let utf16 = dir.path().join("wide.txt").to_string_lossy().to_string();
+        tool.execute(FsToolArgs {
+            action: "write".into(),
+            path: Some(utf16.clone()),
+            content: Some("hi".into()),
+            encoding: Some("utf-16le".into()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(std::fs::read(&utf16).unwrap(), [0xFF, 0xFE, b'h', 0, b'i', 0]);
+    }
+
+    #[tokio::test]
+    async fn test_write_preconditions() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("shared.txt").to_string_lossy().to_string();
+        std::fs::write(&path, "v1").unwrap();
+        let tool = FsTool::new();
+
+        let read: Value = serde_json::from_str(&tool.execute(FsToolArgs {
+            action: "read".into(),
+            path: Some(path.clone()),
+            ..Default::default()
+        }).await.unwrap()).unwrap();
+        let hash = read["sha256"].as_str().unwrap().to_string();
+
+        // Someone else changes the file after it was read
+        std::fs::write(&path, "v2").unwrap();
+        let write = |expected_hash: String| FsToolArgs {
+            action: "write".into(),
+            path: Some(path.clone()),
+            content: Some("mine".into()),
+            expected_hash: Some(expected_hash),
+            fsync: true,
+            ..Default::default()
+        };
+        let err = tool.execute(write(hash)).await.unwrap_err();
+        assert_eq!(ToolError::classify(&err).code(), "conflict");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v2");
+
+        tool.execute(write(hash::sha256_hex(b"v2"))).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "mine");
+
+        let err = tool.execute(FsToolArgs {
+            action: "edit".into(),
+            path: Some(path.clone()),
+            old_string: Some("mine".into()),
+            new_string: Some("x".into()),
+            if_unchanged_since: Some("2000-01-01T00:00:00Z".into()),
+            ..Default::default()
+        }).await.unwrap_err();
+        assert_eq!(ToolError::classify(&err).code(), "conflict");
+    }
+
     #[tokio::test]
     async fn test_tree() {
         let dir = TempDir::new().unwrap();
@@ -785,6 +2117,115 @@ mod tests {
         assert!(output.contains("file.txt"), "Missing file.txt in: {}", output);
     }
 
+    #[tokio::test]
+    async fn test_find_paths() {
+        let dir = TempDir::new().unwrap();
+        for file in ["src/main.rs", "src/tools/fs.rs", "target/debug/build.rs", "tests/it.rs", "Cargo.toml"] {
+            let path = dir.path().join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let tool = FsTool::new();
+        let find = |pattern: &str, include: &[&str], file_type: Option<&str>| {
+            let args = FsToolArgs {
+                action: "find".into(),
+                path: Some(dir.path().to_string_lossy().to_string()),
+                pattern: Some(pattern.into()),
+                include: Some(include.iter().map(|s| s.to_string()).collect()),
+                exclude: Some(vec!["target".into()]),
+                file_type: file_type.map(String::from),
+                ..Default::default()
+            };
+            async { serde_json::from_str::<Value>(&tool.execute(args).await.unwrap()).unwrap()["count"].clone() }
+        };
+
+        assert_eq!(find("src/**/*.rs", &[], None).await, 2);
+        assert_eq!(find("*.rs", &[], None).await, 3);
+        assert_eq!(find("src/*.rs", &["*.toml"], None).await, 2);
+        assert_eq!(find("*", &[], Some("dir")).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_replace() {
+        let dir = TempDir::new().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("build")).unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "build/\n").unwrap();
+        std::fs::write(dir.path().join("src/a.rs"), "old_name(1);\nold_name(2);\n").unwrap();
+        std::fs::write(dir.path().join("src/b.rs"), "let x = old_name(3);\n").unwrap();
+        std::fs::write(dir.path().join("build/gen.rs"), "old_name(4);\n").unwrap();
+        let tool = FsTool::new();
+        let replace = |dry_run: bool, max_replacements: Option<usize>| {
+            let args = FsToolArgs {
+                action: "replace".into(),
+                path: Some(dir.path().to_string_lossy().to_string()),
+                pattern: Some(r"old_name\((\d)\)".into()),
+                replacement: Some("new_name($1)".into()),
+                dry_run,
+                max_replacements,
+                ..Default::default()
+            };
+            tool.execute(args)
+        };
+
+        assert!(replace(false, Some(2)).await.is_err());
+        let preview: Value = serde_json::from_str(&replace(true, None).await.unwrap()).unwrap();
+        assert_eq!(preview["replacements"], 3);
+        assert!(preview["results"][0]["diff"].as_str().unwrap().contains("+new_name("));
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/b.rs")).unwrap(), "let x = old_name(3);\n");
+
+        let done: Value = serde_json::from_str(&replace(false, None).await.unwrap()).unwrap();
+        assert_eq!(done["files"], 2);
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/a.rs")).unwrap(), "new_name(1);\nnew_name(2);\n");
+        assert_eq!(std::fs::read_to_string(dir.path().join("build/gen.rs")).unwrap(), "old_name(4);\n");
+    }
+
+    #[tokio::test]
+    async fn test_outline() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("shapes.py");
+        std::fs::write(&file, "class Shape:\n    def area(self) -> float:\n        return 0.0\n\ndef main():\n    pass\n").unwrap();
+        let tool = FsTool::new();
+        let args = FsToolArgs {
+            action: "outline".into(),
+            path: Some(file.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let result: Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+        assert_eq!(result["language"], "python");
+        assert_eq!(result["count"], 3);
+        assert_eq!(result["symbols"][0]["children"][0]["signature"], "def area(self) -> float");
+        assert_eq!(result["symbols"][1]["start_line"], 5);
+
+        std::fs::write(dir.path().join("notes.txt"), "hello").unwrap();
+        let args = FsToolArgs {
+            action: "outline".into(),
+            path: Some(dir.path().join("notes.txt").to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        assert!(tool.execute(args).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tree_json() {
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("small.txt"), "a").unwrap();
+        std::fs::write(dir.path().join("large.txt"), "abc").unwrap();
+
+        let tool = FsTool::new();
+        let args = FsToolArgs {
+            action: "tree".to_string(),
+            path: Some(dir.path().to_string_lossy().to_string()),
+            format: Some("json".into()),
+            sort: Some("size".into()),
+            ..Default::default()
+        };
+        let result: Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+        assert_eq!(result["tree"]["files"], 2);
+        assert_eq!(result["tree"]["children"][0]["name"], "large.txt");
+        assert!(result["tree"]["children"][0]["modified"].is_string());
+    }
+
     #[tokio::test]
     async fn test_help() {
         let tool = FsTool::new();
@@ -0,0 +1,207 @@
+/// Directory trees for fs
+///
+/// The nested JSON form of `tree`: every entry carries its type, size and
+/// modification time, and every directory the file, directory and byte
+/// totals below it. Entries are sorted per directory, and an entry budget
+/// keeps huge directories from producing unbounded output.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::cmp::Ordering;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+
+/// Order of entries within a directory
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sort {
+    /// Directories first, then by name
+    #[default]
+    Name,
+    /// Largest first
+    Size,
+    /// Most recently modified first
+    Mtime,
+}
+
+impl std::str::FromStr for Sort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(Self::Name),
+            "size" => Ok(Self::Size),
+            "mtime" | "modified" | "time" => Ok(Self::Mtime),
+            _ => Err(ToolError::invalid(format!("Unknown sort: {} (use name, size or mtime)", s)).into()),
+        }
+    }
+}
+
+impl Sort {
+    /// Compare two entries by name and metadata
+    pub fn compare(self, a: (&str, &Metadata), b: (&str, &Metadata)) -> Ordering {
+        let by_name = || b.1.is_dir().cmp(&a.1.is_dir()).then_with(|| a.0.cmp(b.0));
+        match self {
+            Self::Name => by_name(),
+            Self::Size => b.1.len().cmp(&a.1.len()).then_with(by_name),
+            Self::Mtime => b.1.modified().ok().cmp(&a.1.modified().ok()).then_with(by_name),
+        }
+    }
+}
+
+pub struct Options {
+    pub depth: usize,
+    pub include_hidden: bool,
+    pub follow_symlinks: bool,
+    pub sort: Sort,
+    pub max_entries: usize,
+}
+
+/// Nested tree of `root`
+pub fn build(root: &Path, options: &Options) -> Result<Value> {
+    let metadata = std::fs::metadata(root)
+        .map_err(|e| ToolError::not_found(format!("{}: {}", root.display(), e)))?;
+    if !metadata.is_dir() {
+        return Err(ToolError::invalid(format!("{} is not a directory", root.display())).into());
+    }
+    let mut walker = Walker {
+        options,
+        budget: options.max_entries,
+        truncated: false,
+        stack: Vec::new(),
+        cycles: Vec::new(),
+    };
+    let name = root.file_name().map_or_else(|| root.to_string_lossy(), |n| n.to_string_lossy()).into_owned();
+    let tree = walker.node(root, name, &metadata, None, 0);
+    Ok(json!({
+        "tree": tree,
+        "entries": options.max_entries - walker.budget,
+        "truncated": walker.truncated,
+        "symlink_cycles": walker.cycles
+    }))
+}
+
+struct Walker<'a> {
+    options: &'a Options,
+    budget: usize,
+    truncated: bool,
+    /// Canonical paths of the directories being walked, for cycle detection
+    stack: Vec<PathBuf>,
+    cycles: Vec<Value>,
+}
+
+impl Walker<'_> {
+    fn node(&mut self, path: &Path, name: String, metadata: &Metadata, target: Option<PathBuf>, depth: usize) -> Value {
+        let modified = metadata.modified().ok().map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+        let mut node = json!({ "name": name, "modified": modified });
+        if let Some(target) = &target {
+            node["target"] = json!(target.to_string_lossy());
+        }
+
+        if !metadata.is_dir() {
+            node["type"] = json!(if target.is_some() { "symlink" } else { "file" });
+            node["size"] = json!(metadata.len());
+            return node;
+        }
+        node["type"] = json!("directory");
+
+        let canonical = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if self.stack.contains(&canonical) {
+            self.cycles.push(json!({ "link": path.to_string_lossy(), "ancestor": canonical.to_string_lossy() }));
+            node["cycle"] = json!(true);
+            return node;
+        }
+        if depth >= self.options.depth {
+            return node;
+        }
+
+        let mut entries: Vec<(String, PathBuf, Metadata, Option<PathBuf>)> = std::fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if !self.options.include_hidden && name.starts_with('.') {
+                    return None;
+                }
+                let path = entry.path();
+                let lstat = entry.metadata().ok()?;
+                if !lstat.file_type().is_symlink() {
+                    return Some((name, path, lstat, None));
+                }
+                let target = std::fs::read_link(&path).ok();
+                let metadata = match std::fs::metadata(&path) {
+                    Ok(m) if self.options.follow_symlinks && m.is_dir() => m,
+                    _ => lstat,
+                };
+                Some((name, path, metadata, target))
+            })
+            .collect();
+        entries.sort_by(|a, b| self.options.sort.compare((&a.0, &a.2), (&b.0, &b.2)));
+
+        self.stack.push(canonical);
+        let (mut files, mut directories, mut size) = (0u64, 0u64, 0u64);
+        let mut children = Vec::new();
+        for (name, path, metadata, target) in entries {
+            if self.budget == 0 {
+                self.truncated = true;
+                node["truncated"] = json!(true);
+                break;
+            }
+            self.budget -= 1;
+            let child = self.node(&path, name, &metadata, target, depth + 1);
+            if child["type"] == "directory" {
+                directories += 1 + child["directories"].as_u64().unwrap_or(0);
+                files += child["files"].as_u64().unwrap_or(0);
+            } else {
+                files += 1;
+            }
+            size += child["size"].as_u64().unwrap_or(0);
+            children.push(child);
+        }
+        self.stack.pop();
+
+        // Directory sizes are only known once their children are
+        if self.options.sort == Sort::Size {
+            children.sort_by(|a, b| b["size"].as_u64().cmp(&a["size"].as_u64()));
+        }
+        node["files"] = json!(files);
+        node["directories"] = json!(directories);
+        node["size"] = json!(size);
+        node["children"] = json!(children);
+        node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "x".repeat(10)).unwrap();
+        std::fs::write(dir.path().join("src/bin/main.rs"), "x".repeat(100)).unwrap();
+        std::fs::write(dir.path().join("README"), "x").unwrap();
+        std::fs::write(dir.path().join(".env"), "x").unwrap();
+        let options = |sort, max_entries| Options { depth: 5, include_hidden: false, follow_symlinks: false, sort, max_entries };
+
+        let result = build(dir.path(), &options(Sort::Name, 100)).unwrap();
+        let tree = &result["tree"];
+        assert_eq!(tree["files"], 3);
+        assert_eq!(tree["directories"], 2);
+        assert_eq!(tree["size"], 111);
+        assert_eq!(tree["children"][0]["name"], "src");
+        assert_eq!(tree["children"][0]["children"][0]["name"], "bin");
+        assert_eq!(tree["children"][1]["name"], "README");
+
+        let by_size = build(dir.path(), &options(Sort::Size, 100)).unwrap();
+        assert_eq!(by_size["tree"]["children"][0]["children"][0]["size"], 100);
+
+        let capped = build(dir.path(), &options(Sort::Name, 2)).unwrap();
+        assert_eq!(capped["entries"], 2);
+        assert_eq!(capped["truncated"], true);
+        assert!("bytes".parse::<Sort>().is_err());
+    }
+}
@@ -9,7 +9,8 @@
 /// - checkout: Switch branches
 /// - log: Commit history
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use crate::error::ToolError;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tokio::process::Command;
@@ -65,7 +66,7 @@ impl std::str::FromStr for VcsAction {
             "describe" => Ok(Self::Describe),
             "bisect" => Ok(Self::Bisect),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -87,6 +88,9 @@ pub struct GitToolArgs {
     pub value: Option<String>,
     pub args: Option<Vec<String>>,
     pub force: Option<bool>,
+    /// Report what commit would record without committing
+    #[serde(default)]
+    pub dry_run: bool,
 }
 
 pub struct GitToolDefinition;
@@ -116,7 +120,8 @@ impl GitToolDefinition {
                     "url": { "type": "string", "description": "URL for clone/remote" },
                     "key": { "type": "string", "description": "Config key" },
                     "value": { "type": "string", "description": "Config value" },
-                    "force": { "type": "boolean", "description": "Force operation" }
+                    "force": { "type": "boolean", "description": "Force operation" },
+                    "dry_run": { "type": "boolean", "description": "For commit: list the staged changes without committing" }
                 },
                 "required": ["action"]
             }
@@ -179,6 +184,7 @@ impl GitTool {
         let output = Command::new("git")
             .args(args)
             .current_dir(cwd)
+            .kill_on_drop(true)
             .output()
             .await?;
 
@@ -186,7 +192,7 @@ impl GitTool {
             Ok(String::from_utf8_lossy(&output.stdout).to_string())
         } else {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow!("git error: {}", stderr.trim()))
+            Err(ToolError::external(format!("git error: {}", stderr.trim())).into())
         }
     }
 
@@ -229,7 +235,7 @@ impl GitTool {
 
     async fn apply(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
         let patch = args.patch.as_deref()
-            .ok_or_else(|| anyhow!("patch content required"))?;
+            .ok_or_else(|| ToolError::invalid("patch content required"))?;
 
         // Write patch to temp file and apply
         let tmp = format!("{}/.__vcs_patch_tmp", cwd);
@@ -255,7 +261,28 @@ impl GitTool {
 
     async fn commit(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
         let message = args.message.as_deref()
-            .ok_or_else(|| anyhow!("message required"))?;
+            .ok_or_else(|| ToolError::invalid("message required"))?;
+
+        if args.dry_run {
+            let staged = self.git(cwd, &["diff", "--cached", "--name-status"]).await?;
+            let files: Vec<Value> = staged.lines()
+                .filter_map(|l| l.split_once('\t'))
+                .map(|(status, file)| json!({ "status": status, "file": file }))
+                .collect();
+            let stat = self.git(cwd, &["diff", "--cached", "--stat"]).await?;
+            return Ok(json!({
+                "ok": true,
+                "data": {
+                    "dry_run": true,
+                    "command": ["git", "commit", "-m", message],
+                    "message": message,
+                    "files": files,
+                    "stat": stat.trim()
+                },
+                "error": null,
+                "meta": { "tool": "git", "action": "commit" }
+            }));
+        }
 
         let out = self.git(cwd, &["commit", "-m", message]).await?;
 
@@ -290,7 +317,7 @@ impl GitTool {
 
     async fn checkout(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
         let branch = args.branch.as_deref()
-            .ok_or_else(|| anyhow!("branch required"))?;
+            .ok_or_else(|| ToolError::invalid("branch required"))?;
 
         let out = self.git(cwd, &["checkout", branch]).await?;
 
@@ -326,7 +353,7 @@ impl GitTool {
     }
 
     async fn blame(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
-        let file = args.file.as_deref().or(args.target.as_deref()).ok_or_else(|| anyhow!("file required"))?;
+        let file = args.file.as_deref().or(args.target.as_deref()).ok_or_else(|| ToolError::invalid("file required"))?;
         let out = self.git(cwd, &["blame", "--porcelain", file]).await?;
         Ok(json!({"ok": true, "data": {"output": out}, "meta": {"tool": "git", "action": "blame"}}))
     }
@@ -363,26 +390,26 @@ impl GitTool {
         let sub = args.target.as_deref().unwrap_or("list");
         match sub {
             "list" => { let out = self.git(cwd, &["remote", "-v"]).await?; Ok(json!({"ok": true, "data": {"output": out.trim()}, "meta": {"tool": "git", "action": "remote"}})) }
-            "add" => { let name = args.remote.as_deref().ok_or_else(|| anyhow!("remote name required"))?; let url = args.url.as_deref().ok_or_else(|| anyhow!("url required"))?; let out = self.git(cwd, &["remote", "add", name, url]).await?; Ok(json!({"ok": true, "data": {"added": name, "output": out.trim()}, "meta": {"tool": "git", "action": "remote"}})) }
-            "remove" => { let name = args.remote.as_deref().ok_or_else(|| anyhow!("remote name required"))?; let out = self.git(cwd, &["remote", "remove", name]).await?; Ok(json!({"ok": true, "data": {"removed": name, "output": out.trim()}, "meta": {"tool": "git", "action": "remote"}})) }
+            "add" => { let name = args.remote.as_deref().ok_or_else(|| ToolError::invalid("remote name required"))?; let url = args.url.as_deref().ok_or_else(|| ToolError::invalid("url required"))?; let out = self.git(cwd, &["remote", "add", name, url]).await?; Ok(json!({"ok": true, "data": {"added": name, "output": out.trim()}, "meta": {"tool": "git", "action": "remote"}})) }
+            "remove" => { let name = args.remote.as_deref().ok_or_else(|| ToolError::invalid("remote name required"))?; let out = self.git(cwd, &["remote", "remove", name]).await?; Ok(json!({"ok": true, "data": {"removed": name, "output": out.trim()}, "meta": {"tool": "git", "action": "remote"}})) }
             _ => { let out = self.git(cwd, &["remote", sub]).await?; Ok(json!({"ok": true, "data": {"output": out.trim()}, "meta": {"tool": "git", "action": "remote"}})) }
         }
     }
 
     async fn merge(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
-        let branch = args.branch.as_deref().or(args.target.as_deref()).ok_or_else(|| anyhow!("branch required"))?;
+        let branch = args.branch.as_deref().or(args.target.as_deref()).ok_or_else(|| ToolError::invalid("branch required"))?;
         let out = self.git(cwd, &["merge", branch]).await?;
         Ok(json!({"ok": true, "data": {"output": out.trim()}, "meta": {"tool": "git", "action": "merge"}}))
     }
 
     async fn rebase(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
-        let target = args.target.as_deref().or(args.branch.as_deref()).ok_or_else(|| anyhow!("target required"))?;
+        let target = args.target.as_deref().or(args.branch.as_deref()).ok_or_else(|| ToolError::invalid("target required"))?;
         let out = self.git(cwd, &["rebase", target]).await?;
         Ok(json!({"ok": true, "data": {"output": out.trim()}, "meta": {"tool": "git", "action": "rebase"}}))
     }
 
     async fn cherry_pick(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
-        let commit = args.target.as_deref().ok_or_else(|| anyhow!("commit hash required"))?;
+        let commit = args.target.as_deref().ok_or_else(|| ToolError::invalid("commit hash required"))?;
         let out = self.git(cwd, &["cherry-pick", commit]).await?;
         Ok(json!({"ok": true, "data": {"output": out.trim()}, "meta": {"tool": "git", "action": "cherry_pick"}}))
     }
@@ -407,7 +434,7 @@ impl GitTool {
     }
 
     async fn clone_repo(&self, cwd: &str, args: &GitToolArgs) -> Result<Value> {
-        let url = args.url.as_deref().or(args.target.as_deref()).ok_or_else(|| anyhow!("url required"))?;
+        let url = args.url.as_deref().or(args.target.as_deref()).ok_or_else(|| ToolError::invalid("url required"))?;
         let mut cmd = vec!["clone", url];
         if let Some(path) = args.file.as_deref() { cmd.push(path); }
         let out = self.git(cwd, &cmd).await?;
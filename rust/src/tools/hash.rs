@@ -0,0 +1,212 @@
+/// Content hashing for fs
+///
+/// md5, sha1, sha256 and blake3 digests of byte strings, files and
+/// directory trees. A tree digest is merkle-style: each directory hashes
+/// the sorted list of its children's kinds, digests and names, so equal
+/// trees hash equal wherever they live and any change below a directory
+/// changes every digest up to the root.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Digest algorithms offered by `fs hash`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl std::str::FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "").as_str() {
+            "md5" => Ok(Self::Md5),
+            "sha1" => Ok(Self::Sha1),
+            "sha256" => Ok(Self::Sha256),
+            "blake3" | "b3" => Ok(Self::Blake3),
+            _ => Err(ToolError::invalid(format!(
+                "Unknown hash algorithm: {} (use md5, sha1, sha256 or blake3)", s
+            )).into()),
+        }
+    }
+}
+
+impl Algorithm {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Md5 => "md5",
+            Self::Sha1 => "sha1",
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+        }
+    }
+}
+
+/// Incremental hasher for any `Algorithm`
+pub enum Hasher {
+    Md5(Md5),
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Md5 => Self::Md5(Md5::new()),
+            Algorithm::Sha1 => Self::Sha1(Sha1::new()),
+            Algorithm::Sha256 => Self::Sha256(Sha256::new()),
+            Algorithm::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Md5(h) => h.update(data),
+            Self::Sha1(h) => h.update(data),
+            Self::Sha256(h) => h.update(data),
+            Self::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        match self {
+            Self::Md5(h) => h.finalize().to_vec(),
+            Self::Sha1(h) => h.finalize().to_vec(),
+            Self::Sha256(h) => h.finalize().to_vec(),
+            Self::Blake3(h) => h.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Lowercase hex encoding
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Hex digest of `data`
+pub fn digest_hex(algorithm: Algorithm, data: &[u8]) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(data);
+    hex(&hasher.finish())
+}
+
+/// Hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    digest_hex(Algorithm::Sha256, data)
+}
+
+/// Hex digest of a file, streamed
+pub fn file_hex(algorithm: Algorithm, path: &Path) -> Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex(&hasher.finish()))
+}
+
+/// Merkle digest of the tree at `path`, calling `visit(relative path,
+/// digest)` for every file and symlink hashed. Symlinks hash their target
+/// path rather than being followed. Entries for which `keep` returns false
+/// are left out.
+pub fn tree_hex(
+    algorithm: Algorithm,
+    path: &Path,
+    keep: &dyn Fn(&str) -> bool,
+    visit: &mut dyn FnMut(&str, &str),
+) -> Result<String> {
+    fn walk(
+        algorithm: Algorithm,
+        dir: &Path,
+        prefix: &str,
+        keep: &dyn Fn(&str) -> bool,
+        visit: &mut dyn FnMut(&str, &str),
+    ) -> Result<String> {
+        let mut children: Vec<_> = std::fs::read_dir(dir)?.collect::<std::io::Result<_>>()?;
+        children.sort_by_key(|e| e.file_name());
+        let mut listing = Hasher::new(algorithm);
+        for child in children {
+            let name = child.file_name().to_string_lossy().into_owned();
+            let relative = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+            if !keep(&relative) {
+                continue;
+            }
+            let file_type = child.file_type()?;
+            let (kind, digest) = if file_type.is_symlink() {
+                let target = std::fs::read_link(child.path())?;
+                let digest = digest_hex(algorithm, target.to_string_lossy().as_bytes());
+                visit(&relative, &digest);
+                ("l", digest)
+            } else if file_type.is_dir() {
+                ("d", walk(algorithm, &child.path(), &relative, keep, visit)?)
+            } else {
+                let digest = file_hex(algorithm, &child.path())?;
+                visit(&relative, &digest);
+                ("f", digest)
+            };
+            listing.update(format!("{} {} {}\n", kind, digest, name).as_bytes());
+        }
+        Ok(hex(&listing.finish()))
+    }
+
+    walk(algorithm, path, "", keep, visit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        assert_eq!(sha256_hex(b""), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        assert_eq!(sha256_hex(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(digest_hex(Algorithm::Md5, b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(digest_hex(Algorithm::Sha1, b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(digest_hex(Algorithm::Blake3, b"abc"), "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+        assert_eq!("SHA-256".parse::<Algorithm>().unwrap(), Algorithm::Sha256);
+        assert!("crc32".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn test_tree() {
+        let make = || {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::create_dir_all(dir.path().join("src")).unwrap();
+            std::fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+            std::fs::write(dir.path().join("README"), "hi").unwrap();
+            dir
+        };
+        let (a, b) = (make(), make());
+        let mut files = Vec::new();
+        let root = tree_hex(Algorithm::Sha256, a.path(), &|_| true, &mut |p, _| files.push(p.to_string())).unwrap();
+        assert_eq!(files, ["README", "src/main.rs"]);
+        assert_eq!(root, tree_hex(Algorithm::Sha256, b.path(), &|_| true, &mut |_, _| {}).unwrap());
+
+        std::fs::write(b.path().join("src/main.rs"), "fn main() { }").unwrap();
+        assert_ne!(root, tree_hex(Algorithm::Sha256, b.path(), &|_| true, &mut |_, _| {}).unwrap());
+        // Leaving the changed file out restores equality with the same filter
+        let skip_src = |p: &str| p != "src";
+        assert_eq!(
+            tree_hex(Algorithm::Sha256, a.path(), &skip_src, &mut |_, _| {}).unwrap(),
+            tree_hex(Algorithm::Sha256, b.path(), &skip_src, &mut |_, _| {}).unwrap()
+        );
+    }
+}
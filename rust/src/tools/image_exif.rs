@@ -0,0 +1,212 @@
+/// EXIF metadata for the image tool
+///
+/// Reads the TIFF structure the image decoders hand back as the raw Exif
+/// chunk: IFD0, the Exif sub-IFD and the GPS sub-IFD. Only the tags people
+/// ask about (camera, capture time, exposure, orientation, location) are
+/// named; the rest are counted but not decoded.
+
+use serde_json::{json, Map, Value};
+
+/// Tags reported by name, from IFD0 and the Exif IFD
+const TAGS: &[(u16, &str)] = &[
+    (0x010E, "description"),
+    (0x010F, "make"),
+    (0x0110, "model"),
+    (0x0112, "orientation"),
+    (0x011A, "x_resolution"),
+    (0x011B, "y_resolution"),
+    (0x0131, "software"),
+    (0x0132, "modified"),
+    (0x013B, "artist"),
+    (0x8298, "copyright"),
+    (0x829A, "exposure_time"),
+    (0x829D, "f_number"),
+    (0x8827, "iso"),
+    (0x9003, "taken"),
+    (0x9011, "offset_time"),
+    (0x9209, "flash"),
+    (0x920A, "focal_length"),
+    (0xA002, "pixel_width"),
+    (0xA003, "pixel_height"),
+    (0xA405, "focal_length_35mm"),
+    (0xA433, "lens_make"),
+    (0xA434, "lens_model"),
+];
+
+const EXIF_IFD: u16 = 0x8769;
+const GPS_IFD: u16 = 0x8825;
+
+/// Most entries read from one IFD, against corrupt counts
+const MAX_ENTRIES: usize = 1024;
+
+struct Tiff<'a> {
+    data: &'a [u8],
+    little: bool,
+}
+
+impl Tiff<'_> {
+    fn u16(&self, at: usize) -> Option<u16> {
+        let b: [u8; 2] = self.data.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.little { u16::from_le_bytes(b) } else { u16::from_be_bytes(b) })
+    }
+
+    fn u32(&self, at: usize) -> Option<u32> {
+        let b: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.little { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) })
+    }
+
+    /// Entries of the IFD at `offset`: (tag, value)
+    fn ifd(&self, offset: usize) -> Vec<(u16, Value)> {
+        let Some(count) = self.u16(offset) else { return Vec::new() };
+        (0..(count as usize).min(MAX_ENTRIES))
+            .filter_map(|i| {
+                let entry = offset + 2 + i * 12;
+                let tag = self.u16(entry)?;
+                Some((tag, self.value(entry)?))
+            })
+            .collect()
+    }
+
+    fn value(&self, entry: usize) -> Option<Value> {
+        let kind = self.u16(entry + 2)?;
+        let count = self.u32(entry + 4)? as usize;
+        let size: usize = match kind {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 => 4,
+            5 | 10 => 8,
+            _ => return None,
+        };
+        let total = size.checked_mul(count)?;
+        // Values of four bytes or less sit in the entry itself
+        let at = if total <= 4 { entry + 8 } else { self.u32(entry + 8)? as usize };
+        let bytes = self.data.get(at..at.checked_add(total)?)?;
+        let values: Vec<Value> = match kind {
+            2 => {
+                let text = String::from_utf8_lossy(bytes);
+                return Some(json!(text.trim_end_matches('\0').trim()));
+            }
+            1 | 7 => return Some(if count <= 4 { json!(bytes) } else { json!(format!("<{} bytes>", count)) }),
+            6 => bytes.iter().map(|b| json!(*b as i8)).collect(),
+            3 => (0..count).filter_map(|i| self.u16(at + i * 2)).map(|v| json!(v)).collect(),
+            8 => (0..count).filter_map(|i| self.u16(at + i * 2)).map(|v| json!(v as i16)).collect(),
+            4 => (0..count).filter_map(|i| self.u32(at + i * 4)).map(|v| json!(v)).collect(),
+            9 => (0..count).filter_map(|i| self.u32(at + i * 4)).map(|v| json!(v as i32)).collect(),
+            5 | 10 => (0..count).filter_map(|i| {
+                let (n, d) = (self.u32(at + i * 8)?, self.u32(at + i * 8 + 4)?);
+                let (n, d) = if kind == 10 { (n as i32 as f64, d as i32 as f64) } else { (n as f64, d as f64) };
+                (d != 0.0).then(|| json!(n / d))
+            }).collect(),
+            _ => return None,
+        };
+        Some(if values.len() == 1 { values.into_iter().next()? } else { Value::Array(values) })
+    }
+}
+
+/// Named tags from a raw Exif chunk, `None` when it is not TIFF data
+pub fn parse(chunk: &[u8]) -> Option<Value> {
+    let data = chunk.strip_prefix(b"Exif\0\0").unwrap_or(chunk);
+    let little = match data.get(0..2)? {
+        b"II" => true,
+        b"MM" => false,
+        _ => return None,
+    };
+    let tiff = Tiff { data, little };
+    if tiff.u16(2)? != 42 {
+        return None;
+    }
+    let mut tags = Map::new();
+    let mut other = 0;
+    let mut gps = Vec::new();
+    let mut pending = vec![tiff.u32(4)? as usize];
+    let mut seen = Vec::new();
+    while let Some(offset) = pending.pop() {
+        if seen.contains(&offset) {
+            continue;
+        }
+        seen.push(offset);
+        for (tag, value) in tiff.ifd(offset) {
+            match tag {
+                EXIF_IFD => pending.extend(value.as_u64().map(|v| v as usize)),
+                GPS_IFD => gps = value.as_u64().map(|v| tiff.ifd(v as usize)).unwrap_or_default(),
+                _ => match TAGS.iter().find(|(t, _)| *t == tag) {
+                    Some((_, name)) => {
+                        tags.insert(name.to_string(), value);
+                    }
+                    None => other += 1,
+                },
+            }
+        }
+    }
+    if let Some(location) = location(&gps) {
+        tags.insert("gps".to_string(), location);
+    }
+    tags.insert("other_tags".to_string(), json!(other));
+    Some(Value::Object(tags))
+}
+
+/// Decimal degrees from the GPS IFD
+fn location(gps: &[(u16, Value)]) -> Option<Value> {
+    let get = |tag: u16| gps.iter().find(|(t, _)| *t == tag).map(|(_, v)| v);
+    let degrees = |value: &Value| -> Option<f64> {
+        let parts: Vec<f64> = value.as_array()?.iter().filter_map(Value::as_f64).collect();
+        Some(parts.first()? + parts.get(1).unwrap_or(&0.0) / 60.0 + parts.get(2).unwrap_or(&0.0) / 3600.0)
+    };
+    let sign = |tag: u16, negative: &str| if get(tag).and_then(Value::as_str) == Some(negative) { -1.0 } else { 1.0 };
+    let latitude = get(2).and_then(degrees).map(|d| d * sign(1, "S"));
+    let longitude = get(4).and_then(degrees).map(|d| d * sign(3, "W"));
+    if latitude.is_none() && longitude.is_none() {
+        return None;
+    }
+    let mut location = json!({ "latitude": latitude, "longitude": longitude });
+    if let Some(altitude) = get(6).and_then(Value::as_f64) {
+        // Reference 1 means below sea level
+        let below = get(5).and_then(Value::as_array).and_then(|b| b.first()).and_then(Value::as_u64) == Some(1);
+        location["altitude"] = json!(if below { -altitude } else { altitude });
+    }
+    Some(location)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Big-endian TIFF with Make, an Exif IFD holding ISO, and GPS
+    fn sample() -> Vec<u8> {
+        let mut d = b"MM\0\x2a\0\0\0\x08".to_vec();
+        // IFD0 at 8: three entries
+        d.extend([0, 3]);
+        d.extend([0x01, 0x0F, 0, 2, 0, 0, 0, 4]);
+        d.extend(b"Sny\0");
+        d.extend([0x87, 0x69, 0, 4, 0, 0, 0, 1, 0, 0, 0, 50]);
+        d.extend([0x88, 0x25, 0, 4, 0, 0, 0, 1, 0, 0, 0, 68]);
+        d.extend([0, 0, 0, 0]);
+        // Exif IFD at 50: ISO 400
+        d.extend([0, 1]);
+        d.extend([0x88, 0x27, 0, 3, 0, 0, 0, 1, 0x01, 0x90, 0, 0]);
+        d.extend([0, 0, 0, 0]);
+        // GPS IFD at 68: S, 33/1 52/1 30/1
+        d.extend([0, 2]);
+        d.extend([0, 1, 0, 2, 0, 0, 0, 2]);
+        d.extend(b"S\0\0\0");
+        d.extend([0, 2, 0, 5, 0, 0, 0, 3, 0, 0, 0, 98]);
+        d.extend([0, 0, 0, 0]);
+        for v in [33u32, 1, 52, 1, 30, 1] {
+            d.extend(v.to_be_bytes());
+        }
+        d
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut chunk = b"Exif\0\0".to_vec();
+        chunk.extend(sample());
+        let tags = parse(&chunk).unwrap();
+        assert_eq!(tags["make"], "Sny");
+        assert_eq!(tags["iso"], 400);
+        let latitude = tags["gps"]["latitude"].as_f64().unwrap();
+        assert!((latitude + 33.875).abs() < 1e-9);
+        assert!(tags["gps"]["longitude"].is_null());
+        assert!(parse(b"not tiff").is_none());
+    }
+}
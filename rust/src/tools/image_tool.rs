@@ -0,0 +1,743 @@
+/// Image tool
+///
+/// Actions: info, resize, crop, convert, annotate, diff, exif, help
+///
+/// Works on image files (png, jpeg, webp), or on base64 data passed in, so
+/// screenshots from `ui` can be cut down, marked up and compared without
+/// leaving the server. Results are written to `output` when given and
+/// returned as an image block otherwise. EXIF orientation is applied on
+/// load, so photos come out the way up they are shown.
+///
+/// `annotate` draws boxes and text labels with a built-in 5x7 pixel font
+/// (ASCII, lower case drawn as upper case). `diff` scores how alike two
+/// images are and finds the region that changed, for checking that a UI
+/// step did what it should.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use crate::tools::computer_tool::{encode_image, CaptureFormat, CaptureOptions, RawImage};
+use crate::tools::image_exif;
+use base64::Engine as _;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageReader, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Cursor;
+use std::path::Path;
+
+/// Per-channel difference below which diff counts pixels as unchanged
+const DEFAULT_TOLERANCE: u8 = 16;
+
+/// Pixels per font dot unless a text says otherwise
+const DEFAULT_TEXT_SIZE: u32 = 2;
+
+/// Box outline width unless a box says otherwise
+const DEFAULT_THICKNESS: u32 = 3;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageAction {
+    #[default]
+    Info,
+    Resize,
+    Crop,
+    Convert,
+    Annotate,
+    Diff,
+    Exif,
+    Help,
+}
+
+impl std::str::FromStr for ImageAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "info" | "" => Ok(Self::Info),
+            "resize" | "scale" | "thumbnail" => Ok(Self::Resize),
+            "crop" => Ok(Self::Crop),
+            "convert" | "encode" => Ok(Self::Convert),
+            "annotate" | "draw" | "markup" => Ok(Self::Annotate),
+            "diff" | "compare" => Ok(Self::Diff),
+            "exif" | "metadata" => Ok(Self::Exif),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl ImageAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Resize => "resize",
+            Self::Crop => "crop",
+            Self::Convert => "convert",
+            Self::Annotate => "annotate",
+            Self::Diff => "diff",
+            Self::Exif => "exif",
+            Self::Help => "help",
+        }
+    }
+}
+
+/// How resize fits an image to both width and height
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Fit {
+    /// Inside the box, keeping the aspect ratio
+    #[default]
+    Contain,
+    /// Covering the box, keeping the aspect ratio, then cropped to it
+    Cover,
+    /// Exactly the box, stretching if need be
+    Fill,
+}
+
+impl std::str::FromStr for Fit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "contain" | "inside" => Ok(Self::Contain),
+            "cover" | "crop" => Ok(Self::Cover),
+            "fill" | "exact" | "stretch" => Ok(Self::Fill),
+            _ => Err(ToolError::invalid(format!("Unknown fit: {} (use contain, cover or fill)", s)).into()),
+        }
+    }
+}
+
+/// A rectangle outline, with an optional label above it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BoxMark {
+    pub x: i64,
+    pub y: i64,
+    pub width: u32,
+    pub height: u32,
+    pub color: Option<String>,
+    pub label: Option<String>,
+    pub thickness: Option<u32>,
+}
+
+/// Text on a tag of its own color
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextMark {
+    pub x: i64,
+    pub y: i64,
+    pub text: String,
+    pub color: Option<String>,
+    /// Pixels per font dot
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImageToolArgs {
+    pub action: Option<String>,
+    /// Image file to read
+    pub path: Option<String>,
+    /// Image data instead of a file; a data: URL prefix is allowed
+    pub base64: Option<String>,
+    /// Where to write the result; returned inline when left out
+    pub output: Option<String>,
+    /// png, jpeg or webp; from output's extension, then the input's format
+    pub format: Option<String>,
+    /// JPEG quality, 1-100
+    pub quality: Option<u8>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// For resize: factor instead of width and height
+    pub scale: Option<f64>,
+    /// For resize with both width and height: contain, cover or fill
+    pub fit: Option<String>,
+    /// For crop: left edge
+    pub x: Option<u32>,
+    /// For crop: top edge
+    pub y: Option<u32>,
+    pub boxes: Option<Vec<BoxMark>>,
+    pub texts: Option<Vec<TextMark>>,
+    /// For diff: the image to compare against
+    pub other: Option<String>,
+    /// For diff: per-channel difference still counted as unchanged (0-255)
+    pub tolerance: Option<u8>,
+}
+
+pub struct ImageToolDefinition;
+
+impl ImageToolDefinition {
+    pub fn schema() -> Value {
+        let mark = |extra: Value| {
+            let mut properties = json!({
+                "x": { "type": "integer" },
+                "y": { "type": "integer" },
+                "color": { "type": "string", "description": "Name (red, green, blue, yellow, orange, magenta, cyan, white, black) or #rrggbb" }
+            });
+            if let (Some(p), Some(e)) = (properties.as_object_mut(), extra.as_object()) {
+                p.extend(e.clone());
+            }
+            properties
+        };
+        json!({
+            "name": "image",
+            "description": "Image processing for png/jpeg/webp files or base64 data: info, resize, crop, convert, annotate (boxes and labels), diff (similarity score and changed region), exif, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["info", "resize", "crop", "convert", "annotate", "diff", "exif", "help"],
+                        "default": "info"
+                    },
+                    "path": { "type": "string", "description": "Image file" },
+                    "base64": { "type": "string", "description": "Image data instead of path" },
+                    "output": { "type": "string", "description": "File to write (default: return the image inline)" },
+                    "format": { "type": "string", "enum": ["png", "jpeg", "webp"] },
+                    "quality": { "type": "integer", "minimum": 1, "maximum": 100, "default": 85 },
+                    "width": { "type": "integer", "description": "resize/crop width" },
+                    "height": { "type": "integer", "description": "resize/crop height" },
+                    "scale": { "type": "number", "description": "resize factor" },
+                    "fit": { "type": "string", "enum": ["contain", "cover", "fill"], "default": "contain" },
+                    "x": { "type": "integer", "description": "crop left edge" },
+                    "y": { "type": "integer", "description": "crop top edge" },
+                    "boxes": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": mark(json!({
+                                "width": { "type": "integer" },
+                                "height": { "type": "integer" },
+                                "label": { "type": "string" },
+                                "thickness": { "type": "integer", "default": 3 }
+                            })),
+                            "required": ["x", "y", "width", "height"]
+                        }
+                    },
+                    "texts": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": mark(json!({
+                                "text": { "type": "string" },
+                                "size": { "type": "integer", "default": 2 }
+                            })),
+                            "required": ["x", "y", "text"]
+                        }
+                    },
+                    "other": { "type": "string", "description": "diff: image file to compare with" },
+                    "tolerance": { "type": "integer", "default": 16, "description": "diff: per-channel difference still counted as unchanged" }
+                }
+            }
+        })
+    }
+}
+
+/// A decoded input and where it came from
+struct Loaded {
+    image: DynamicImage,
+    format: Option<image::ImageFormat>,
+    exif: Option<Vec<u8>>,
+    bytes: usize,
+}
+
+pub struct ImageTool;
+
+impl ImageTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub async fn execute(&self, args: ImageToolArgs) -> Result<Value> {
+        let action: ImageAction = args.action.as_deref().unwrap_or("info").parse()?;
+        if action == ImageAction::Help {
+            return Ok(self.help());
+        }
+        // Decoding and encoding are CPU-bound
+        let data = tokio::task::spawn_blocking(move || run(&action, &args).map(|data| (action, data))).await??;
+        Ok(json!({
+            "ok": true,
+            "data": data.1,
+            "error": null,
+            "meta": { "tool": "image", "action": data.0.name() }
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "image",
+                "actions": {
+                    "info": "Dimensions, format and color type",
+                    "resize": "Resize to width and/or height (fit: contain, cover, fill) or by scale",
+                    "crop": "Cut out x, y, width, height",
+                    "convert": "Re-encode as format (png, jpeg, webp) at quality",
+                    "annotate": "Draw boxes [{x,y,width,height,color,label}] and texts [{x,y,text,color,size}]",
+                    "diff": "Compare path with other: similarity 0-1, changed pixels and the region that changed; output writes a highlight image",
+                    "exif": "Camera, capture time, exposure, orientation and GPS metadata",
+                    "help": "Show tool help"
+                },
+                "input": "path, or base64; results go to output, or come back inline"
+            },
+            "error": null,
+            "meta": { "tool": "image", "action": "help" }
+        })
+    }
+}
+
+impl Default for ImageTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn run(action: &ImageAction, args: &ImageToolArgs) -> Result<Value> {
+    let input = load(args)?;
+    match action {
+        ImageAction::Info => Ok(info(&input)),
+        ImageAction::Exif => Ok(json!({
+            "exif": input.exif.as_deref().and_then(image_exif::parse),
+            "present": input.exif.is_some()
+        })),
+        ImageAction::Resize => {
+            let resized = resize(&input.image, args)?;
+            emit(resized, &input, args)
+        }
+        ImageAction::Crop => {
+            let (x, y) = (args.x.unwrap_or(0), args.y.unwrap_or(0));
+            let (width, height) = (input.image.width(), input.image.height());
+            if x >= width || y >= height {
+                return Err(ToolError::invalid(format!("Crop origin {},{} is outside the {}x{} image", x, y, width, height)).into());
+            }
+            let w = args.width.unwrap_or(width - x).min(width - x);
+            let h = args.height.unwrap_or(height - y).min(height - y);
+            if w == 0 || h == 0 {
+                return Err(ToolError::invalid("Crop width and height must be positive").into());
+            }
+            emit(input.image.crop_imm(x, y, w, h), &input, args)
+        }
+        ImageAction::Convert => emit(input.image.clone(), &input, args),
+        ImageAction::Annotate => {
+            let mut canvas = input.image.to_rgba8();
+            let boxes = args.boxes.as_deref().unwrap_or_default();
+            let texts = args.texts.as_deref().unwrap_or_default();
+            if boxes.is_empty() && texts.is_empty() {
+                return Err(ToolError::invalid("boxes or texts required").into());
+            }
+            for mark in boxes {
+                let color = parse_color(mark.color.as_deref().unwrap_or("red"))?;
+                draw_box(&mut canvas, mark.x, mark.y, mark.width, mark.height, mark.thickness.unwrap_or(DEFAULT_THICKNESS), color);
+                if let Some(label) = mark.label.as_deref().filter(|l| !l.is_empty()) {
+                    let tag_height = (GLYPH_HEIGHT + 2) * DEFAULT_TEXT_SIZE;
+                    // Above the box, or just inside it at the top edge
+                    let y = if mark.y >= tag_height as i64 { mark.y - tag_height as i64 } else { mark.y };
+                    draw_tag(&mut canvas, mark.x, y, label, DEFAULT_TEXT_SIZE, color);
+                }
+            }
+            for mark in texts {
+                let color = parse_color(mark.color.as_deref().unwrap_or("red"))?;
+                draw_tag(&mut canvas, mark.x, mark.y, &mark.text, mark.size.unwrap_or(DEFAULT_TEXT_SIZE).clamp(1, 16), color);
+            }
+            emit(DynamicImage::ImageRgba8(canvas), &input, args)
+        }
+        ImageAction::Diff => {
+            let other_path = args.other.as_deref().ok_or_else(|| ToolError::invalid("other required"))?;
+            let other = decode(std::fs::read(expand(other_path)).map_err(|e| read_error(other_path, e))?)?;
+            let diff = compare(&input.image.to_rgba8(), &other.image.to_rgba8(), args.tolerance.unwrap_or(DEFAULT_TOLERANCE));
+            let mut data = diff.summary;
+            if args.output.is_some() {
+                let written = emit(DynamicImage::ImageRgba8(diff.highlight), &input, args)?;
+                data["highlight"] = written;
+            }
+            Ok(data)
+        }
+        ImageAction::Help => unreachable!("help returns early"),
+    }
+}
+
+fn expand(path: &str) -> String {
+    shellexpand::tilde(path).into_owned()
+}
+
+fn read_error(path: &str, e: std::io::Error) -> anyhow::Error {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        ToolError::not_found(format!("No image at {}", path)).into()
+    } else {
+        e.into()
+    }
+}
+
+fn load(args: &ImageToolArgs) -> Result<Loaded> {
+    let bytes = match (&args.path, &args.base64) {
+        (Some(path), _) => std::fs::read(expand(path)).map_err(|e| read_error(path, e))?,
+        (None, Some(data)) => {
+            // Accept data: URLs as copied from a browser or an image block
+            let data = data.split_once(";base64,").map_or(data.as_str(), |(_, d)| d);
+            base64::engine::general_purpose::STANDARD.decode(data.trim())
+                .map_err(|e| ToolError::invalid(format!("base64 is not valid: {}", e)))?
+        }
+        (None, None) => return Err(ToolError::invalid("path or base64 required").into()),
+    };
+    decode(bytes)
+}
+
+fn decode(bytes: Vec<u8>) -> Result<Loaded> {
+    let size = bytes.len();
+    let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let format = reader.format();
+    if format.is_none() {
+        return Err(ToolError::unsupported("Not a png, jpeg or webp image").into());
+    }
+    let mut decoder = reader.into_decoder()
+        .map_err(|e| ToolError::unsupported(format!("Cannot decode image: {}", e)))?;
+    let exif = decoder.exif_metadata().ok().flatten();
+    let orientation = decoder.orientation().unwrap_or(image::metadata::Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)
+        .map_err(|e| ToolError::invalid(format!("Cannot decode image: {}", e)))?;
+    image.apply_orientation(orientation);
+    Ok(Loaded { image, format, exif, bytes: size })
+}
+
+fn info(input: &Loaded) -> Value {
+    json!({
+        "width": input.image.width(),
+        "height": input.image.height(),
+        "format": input.format.map(|f| f.extensions_str()[0]),
+        "color": format!("{:?}", input.image.color()),
+        "has_alpha": input.image.color().has_alpha(),
+        "bytes": input.bytes,
+        "exif": input.exif.is_some()
+    })
+}
+
+fn resize(image: &DynamicImage, args: &ImageToolArgs) -> Result<DynamicImage> {
+    let (width, height) = (image.width(), image.height());
+    let filter = FilterType::CatmullRom;
+    if let Some(scale) = args.scale {
+        if !(scale > 0.0 && scale <= 16.0) {
+            return Err(ToolError::invalid("scale must be above 0 and at most 16").into());
+        }
+        let w = ((width as f64 * scale).round() as u32).max(1);
+        let h = ((height as f64 * scale).round() as u32).max(1);
+        return Ok(image.resize_exact(w, h, filter));
+    }
+    let fit: Fit = args.fit.as_deref().unwrap_or("contain").parse()?;
+    Ok(match (args.width, args.height) {
+        (Some(w), Some(h)) if w > 0 && h > 0 => match fit {
+            Fit::Contain => image.resize(w, h, filter),
+            Fit::Cover => image.resize_to_fill(w, h, filter),
+            Fit::Fill => image.resize_exact(w, h, filter),
+        },
+        // One side given: keep the aspect ratio
+        (Some(w), None) if w > 0 => image.resize_exact(w, ((height as f64 * w as f64 / width as f64).round() as u32).max(1), filter),
+        (None, Some(h)) if h > 0 => image.resize_exact(((width as f64 * h as f64 / height as f64).round() as u32).max(1), h, filter),
+        _ => return Err(ToolError::invalid("width, height or scale required").into()),
+    })
+}
+
+/// Encode a result and write it to output, or return it inline
+fn emit(image: DynamicImage, input: &Loaded, args: &ImageToolArgs) -> Result<Value> {
+    let from_output = args.output.as_deref()
+        .and_then(|p| Path::new(p).extension())
+        .and_then(|e| e.to_str())
+        .and_then(|e| e.parse::<CaptureFormat>().ok());
+    let format = match &args.format {
+        Some(name) => name.parse()?,
+        None => from_output.unwrap_or(match input.format {
+            Some(image::ImageFormat::Jpeg) => CaptureFormat::Jpeg,
+            Some(image::ImageFormat::WebP) => CaptureFormat::Webp,
+            _ => CaptureFormat::Png,
+        }),
+    };
+    let rgba = image.into_rgba8();
+    let (width, height) = rgba.dimensions();
+    let raw = RawImage { width, height, rgba: rgba.into_raw() };
+    let options = CaptureOptions { format, quality: args.quality.unwrap_or(85).clamp(1, 100), scale: 1.0 };
+    let (data, width, height) = encode_image(raw, options)?;
+    let mut result = json!({
+        "width": width,
+        "height": height,
+        "format": format.extension(),
+        "bytes": data.len()
+    });
+    match args.output.as_deref() {
+        Some(output) => {
+            let path = expand(output);
+            if let Some(parent) = Path::new(&path).parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, &data)?;
+            result["path"] = json!(path);
+        }
+        None => result["base64"] = json!(base64::engine::general_purpose::STANDARD.encode(&data)),
+    }
+    Ok(result)
+}
+
+struct Diff {
+    summary: Value,
+    /// The first image faded, with changed pixels in red
+    highlight: RgbaImage,
+}
+
+/// Compare two images pixel by pixel; a different-sized second image is
+/// scaled to the first's size first
+fn compare(a: &RgbaImage, b: &RgbaImage, tolerance: u8) -> Diff {
+    let same_size = a.dimensions() == b.dimensions();
+    let scaled;
+    let b = if same_size {
+        b
+    } else {
+        scaled = image::imageops::resize(b, a.width(), a.height(), FilterType::Triangle);
+        &scaled
+    };
+    let (mut total, mut changed) = (0u64, 0u64);
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (u32::MAX, u32::MAX, 0, 0);
+    let mut highlight = RgbaImage::new(a.width(), a.height());
+    for ((x, y, pa), pb) in a.enumerate_pixels().zip(b.pixels()) {
+        let deltas: Vec<u8> = pa.0.iter().zip(pb.0.iter()).map(|(p, q)| p.abs_diff(*q)).collect();
+        total += deltas.iter().map(|d| *d as u64).sum::<u64>();
+        if deltas.iter().any(|d| *d > tolerance) {
+            changed += 1;
+            (min_x, min_y, max_x, max_y) = (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y));
+            highlight.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            let [r, g, b, _] = pa.0;
+            let gray = ((r as u32 * 3 + g as u32 * 6 + b as u32) / 10 / 3 + 170) as u8;
+            highlight.put_pixel(x, y, Rgba([gray, gray, gray, 255]));
+        }
+    }
+    let pixels = a.width() as u64 * a.height() as u64;
+    let similarity = if pixels == 0 { 1.0 } else { 1.0 - total as f64 / (pixels * 4 * 255) as f64 };
+    let region = (changed > 0).then(|| json!({ "x": min_x, "y": min_y, "width": max_x - min_x + 1, "height": max_y - min_y + 1 }));
+    Diff {
+        summary: json!({
+            "similarity": (similarity * 10000.0).round() / 10000.0,
+            "identical": total == 0 && same_size,
+            "changed_pixels": changed,
+            "changed_ratio": if pixels == 0 { 0.0 } else { (changed as f64 / pixels as f64 * 10000.0).round() / 10000.0 },
+            "changed_region": region,
+            "same_size": same_size,
+            "size": [a.width(), a.height()],
+            "other_size": if same_size { Value::Null } else { json!([b.width(), b.height()]) },
+            "tolerance": tolerance
+        }),
+        highlight,
+    }
+}
+
+fn parse_color(value: &str) -> Result<Rgba<u8>> {
+    let rgb = match value.to_lowercase().as_str() {
+        "red" => [230, 30, 30],
+        "green" => [20, 170, 60],
+        "blue" => [30, 100, 230],
+        "yellow" => [250, 210, 0],
+        "orange" => [250, 140, 0],
+        "magenta" | "pink" => [220, 30, 200],
+        "cyan" => [0, 200, 220],
+        "white" => [255, 255, 255],
+        "black" => [0, 0, 0],
+        hex => {
+            let hex = hex.trim_start_matches('#');
+            let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+            match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Some(r), Some(g), Some(b)) => [r, g, b],
+                _ => return Err(ToolError::invalid(format!("Unknown color: {} (use a name or #rrggbb)", value)).into()),
+            }
+        }
+    };
+    Ok(Rgba([rgb[0], rgb[1], rgb[2], 255]))
+}
+
+/// Fill a rectangle, clipped to the canvas
+fn fill(canvas: &mut RgbaImage, x: i64, y: i64, width: i64, height: i64, color: Rgba<u8>) {
+    let x0 = x.clamp(0, canvas.width() as i64) as u32;
+    let y0 = y.clamp(0, canvas.height() as i64) as u32;
+    let x1 = (x + width).clamp(0, canvas.width() as i64) as u32;
+    let y1 = (y + height).clamp(0, canvas.height() as i64) as u32;
+    for py in y0..y1 {
+        for px in x0..x1 {
+            canvas.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn draw_box(canvas: &mut RgbaImage, x: i64, y: i64, width: u32, height: u32, thickness: u32, color: Rgba<u8>) {
+    let (w, h, t) = (width as i64, height as i64, thickness.max(1) as i64);
+    fill(canvas, x, y, w, t, color);
+    fill(canvas, x, y + h - t, w, t, color);
+    fill(canvas, x, y, t, h, color);
+    fill(canvas, x + w - t, y, t, h, color);
+}
+
+/// Text in white or black, whichever reads better, on a tag of `color`
+fn draw_tag(canvas: &mut RgbaImage, x: i64, y: i64, text: &str, size: u32, color: Rgba<u8>) {
+    let s = size as i64;
+    let width = text.chars().count() as i64 * (GLYPH_WIDTH as i64 + 1) * s + s;
+    fill(canvas, x, y, width, (GLYPH_HEIGHT as i64 + 2) * s, color);
+    let [r, g, b, _] = color.0;
+    let luminance = 299 * r as u32 + 587 * g as u32 + 114 * b as u32;
+    let ink = if luminance > 150_000 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) };
+    for (i, c) in text.chars().enumerate() {
+        let left = x + s + i as i64 * (GLYPH_WIDTH as i64 + 1) * s;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    fill(canvas, left + col as i64 * s, y + s + row as i64 * s, s, s, ink);
+                }
+            }
+        }
+    }
+}
+
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+
+/// Rows of a 5x7 glyph, high bit on the left; lower case is drawn as upper
+/// case and anything else outside the table as `?`
+fn glyph(c: char) -> [u8; 7] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0, 0, 0, 0, 0, 0, 0],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        'A' => [0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '.' => [0, 0, 0, 0, 0, 0x0C, 0x0C],
+        ',' => [0, 0, 0, 0, 0x0C, 0x04, 0x08],
+        ':' => [0, 0x0C, 0x0C, 0, 0x0C, 0x0C, 0],
+        ';' => [0, 0x0C, 0x0C, 0, 0x0C, 0x04, 0x08],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0, 0, 0x04],
+        '-' => [0, 0, 0, 0x1F, 0, 0, 0],
+        '_' => [0, 0, 0, 0, 0, 0, 0x1F],
+        '+' => [0, 0x04, 0x04, 0x1F, 0x04, 0x04, 0],
+        '=' => [0, 0, 0x1F, 0, 0x1F, 0, 0],
+        '/' => [0, 0x01, 0x02, 0x04, 0x08, 0x10, 0],
+        '\\' => [0, 0x10, 0x08, 0x04, 0x02, 0x01, 0],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '[' => [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E],
+        ']' => [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E],
+        '<' => [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02],
+        '>' => [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        '*' => [0, 0x04, 0x15, 0x0E, 0x15, 0x04, 0],
+        '@' => [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E],
+        '$' => [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04],
+        '\'' => [0x0C, 0x04, 0x08, 0, 0, 0, 0],
+        '"' => [0x0A, 0x0A, 0x0A, 0, 0, 0, 0],
+        '|' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0, 0x04],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png(image: &RgbaImage) -> String {
+        let mut data = Vec::new();
+        DynamicImage::ImageRgba8(image.clone()).write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png).unwrap();
+        base64::engine::general_purpose::STANDARD.encode(data)
+    }
+
+    #[tokio::test]
+    async fn test_transforms() {
+        let tool = ImageTool::new();
+        let source = png(&RgbaImage::from_pixel(40, 20, Rgba([255, 255, 255, 255])));
+        let args = |action: &str| ImageToolArgs { action: Some(action.into()), base64: Some(source.clone()), ..Default::default() };
+
+        let info = tool.execute(args("info")).await.unwrap();
+        assert_eq!(info["data"]["width"], 40);
+        assert_eq!(info["data"]["format"], "png");
+
+        let resized = tool.execute(ImageToolArgs { width: Some(10), ..args("resize") }).await.unwrap();
+        assert_eq!((resized["data"]["width"].as_u64(), resized["data"]["height"].as_u64()), (Some(10), Some(5)));
+        let covered = tool.execute(ImageToolArgs { width: Some(10), height: Some(10), fit: Some("cover".into()), ..args("resize") }).await.unwrap();
+        assert_eq!(covered["data"]["height"], 10);
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("crop.jpg").to_string_lossy().to_string();
+        let cropped = tool.execute(ImageToolArgs { x: Some(30), width: Some(50), output: Some(output.clone()), ..args("crop") }).await.unwrap();
+        assert_eq!(cropped["data"]["width"], 10);
+        assert_eq!(cropped["data"]["format"], "jpg");
+        assert!(cropped["data"]["base64"].is_null());
+        assert!(image::open(&output).is_ok());
+
+        let webp = tool.execute(ImageToolArgs { format: Some("webp".into()), ..args("convert") }).await.unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD.decode(webp["data"]["base64"].as_str().unwrap()).unwrap();
+        assert_eq!(image::guess_format(&bytes).unwrap(), image::ImageFormat::WebP);
+        assert!(tool.execute(ImageToolArgs { x: Some(40), ..args("crop") }).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_annotate_and_diff() {
+        let tool = ImageTool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let before = dir.path().join("before.png");
+        RgbaImage::from_pixel(100, 60, Rgba([255, 255, 255, 255])).save(&before).unwrap();
+        let after = dir.path().join("after.png");
+
+        let marked = tool.execute(ImageToolArgs {
+            action: Some("annotate".into()),
+            path: Some(before.to_string_lossy().into()),
+            output: Some(after.to_string_lossy().into()),
+            boxes: Some(vec![BoxMark { x: 40, y: 30, width: 20, height: 20, label: Some("ok".into()), ..Default::default() }]),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(marked["data"]["width"], 100);
+        let canvas = image::open(&after).unwrap().to_rgba8();
+        assert_eq!(canvas.get_pixel(40, 30), &parse_color("red").unwrap());
+        assert_eq!(canvas.get_pixel(50, 40), &Rgba([255, 255, 255, 255]));
+
+        let diff = tool.execute(ImageToolArgs {
+            action: Some("diff".into()),
+            path: Some(before.to_string_lossy().into()),
+            other: Some(after.to_string_lossy().into()),
+            ..Default::default()
+        }).await.unwrap();
+        let data = &diff["data"];
+        assert_eq!(data["identical"], false);
+        assert!(data["similarity"].as_f64().unwrap() > 0.8);
+        // The label tag sits above the box, so the change starts there
+        assert_eq!(data["changed_region"]["x"], 40);
+        assert_eq!(data["changed_region"]["y"], 30 - 18);
+        assert_eq!(data["changed_region"]["height"], 20 + 18);
+
+        let same = compare(&canvas, &canvas, 0);
+        assert_eq!(same.summary["similarity"], 1.0);
+        assert!(same.summary["changed_region"].is_null());
+        assert!(parse_color("#12ab").is_err());
+        assert_eq!(parse_color("#12AB34").unwrap(), Rgba([0x12, 0xab, 0x34, 255]));
+    }
+}
@@ -0,0 +1,186 @@
+/// Control of the background indexer
+///
+/// Actions: status, rebuild, pause, resume, help
+///
+/// status shows what the last pass over each root found and how long it
+/// took; rebuild drops a root's tables (every root's without a path) and
+/// indexes again at once; pause and resume stop and restart the passes,
+/// say during a large checkout. Indexing runs only when `index.enabled`
+/// is set in the config.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use crate::search::indexer::Indexer;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexAction {
+    #[default]
+    Status,
+    Rebuild,
+    Pause,
+    Resume,
+    Help,
+}
+
+impl std::str::FromStr for IndexAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "status" | "info" => Ok(Self::Status),
+            "rebuild" | "reindex" | "refresh" => Ok(Self::Rebuild),
+            "pause" | "stop" => Ok(Self::Pause),
+            "resume" | "start" => Ok(Self::Resume),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl IndexAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Status => "status",
+            Self::Rebuild => "rebuild",
+            Self::Pause => "pause",
+            Self::Resume => "resume",
+            Self::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexToolArgs {
+    pub action: Option<String>,
+    /// rebuild: the root to index again (default: every root)
+    pub path: Option<String>,
+}
+
+pub struct IndexToolDefinition;
+
+impl IndexToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "index",
+            "description": "Background search indexer: status (per-root files, symbols, last pass), rebuild (drop and re-index a root or all), pause, resume, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["status", "rebuild", "pause", "resume", "help"],
+                        "default": "status"
+                    },
+                    "path": { "type": "string", "description": "rebuild: the root to index again (default: every root)" }
+                }
+            }
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct IndexTool {
+    /// None while indexing is off
+    indexer: Option<Arc<Indexer>>,
+}
+
+impl IndexTool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_indexer(indexer: Arc<Indexer>) -> Self {
+        Self { indexer: Some(indexer) }
+    }
+
+    pub async fn execute(&self, args: IndexToolArgs) -> Result<Value> {
+        let action: IndexAction = match args.action.as_deref() {
+            Some(action) if !action.is_empty() => action.parse()?,
+            _ => IndexAction::Status,
+        };
+        let data = match (&action, &self.indexer) {
+            (IndexAction::Help, _) => return Ok(self.help()),
+            (IndexAction::Status, None) => json!({ "enabled": false, "hint": "Set index.enabled in the config to keep search indexes warm" }),
+            (_, None) => return Err(ToolError::unsupported("Background indexing is off (index.enabled)").into()),
+            (IndexAction::Status, Some(indexer)) => indexer.status(),
+            (IndexAction::Rebuild, Some(indexer)) => {
+                let root = args.path.as_deref()
+                    .map(|path| PathBuf::from(shellexpand::tilde(path).as_ref()).canonicalize()
+                        .map_err(|_| ToolError::not_found(format!("Path not found: {}", path))))
+                    .transpose()?;
+                json!({ "rebuilding": root.as_ref().map_or_else(|| json!("all"), |root| json!(root)), "dropped": indexer.rebuild(root.as_deref()) })
+            }
+            (IndexAction::Pause, Some(indexer)) => {
+                indexer.pause(true);
+                json!({ "paused": true })
+            }
+            (IndexAction::Resume, Some(indexer)) => {
+                indexer.pause(false);
+                json!({ "paused": false })
+            }
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "index", "action": action.name() }
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "name": "index",
+                "enabled": self.indexer.is_some(),
+                "actions": {
+                    "status": "Per root: files and symbols indexed, files re-parsed and removed on the last pass, definitions embedded, how long it took and when",
+                    "rebuild": "Drop the tables of path (every root without one) and index again now",
+                    "pause": "Stop background passes; searches still refresh what they read",
+                    "resume": "Restart background passes, beginning with one right away",
+                    "help": "This help"
+                },
+                "config": "[index] enabled = true, interval_secs = 60, vector = false (embed definitions into the vector collection named by collection)"
+            },
+            "error": null,
+            "meta": { "tool": "index", "action": "help" }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::IndexConfig;
+
+    fn args(value: Value) -> IndexToolArgs {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_index_tool() {
+        let off = IndexTool::new();
+        assert_eq!(off.execute(args(json!({}))).await.unwrap()["data"]["enabled"], false);
+        assert!(off.execute(args(json!({ "action": "rebuild" }))).await.is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join("lib.rs"), "fn answer() {}\n").unwrap();
+        let indexer = Arc::new(Indexer::new(IndexConfig { enabled: true, ..Default::default() }, Default::default()));
+        indexer.pass(std::slice::from_ref(&root), None).await;
+        let on = IndexTool::with_indexer(indexer);
+        let status = on.execute(args(json!({ "action": "status" }))).await.unwrap();
+        assert_eq!((status["data"]["passes"].as_u64(), status["data"]["roots"][0]["symbols"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(on.execute(args(json!({ "action": "pause" }))).await.unwrap()["data"]["paused"], true);
+        let rebuilt = on.execute(args(json!({ "action": "reindex", "path": root }))).await.unwrap();
+        assert_eq!(rebuilt["data"]["dropped"], json!([root]));
+        assert!(on.execute(args(json!({ "action": "rebuild", "path": "/no/such/root" }))).await.is_err());
+        assert_eq!(on.execute(args(json!({ "action": "resume" }))).await.unwrap()["meta"]["action"], "resume");
+        assert!(on.execute(args(json!({ "action": "shred" }))).await.is_err());
+    }
+}
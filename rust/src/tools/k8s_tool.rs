@@ -0,0 +1,898 @@
+/// Kubernetes tool
+///
+/// Actions: contexts, pods, deployments, get, logs, exec, apply, rollout, help
+///
+/// Talks to the API server through kube-rs with the kubeconfig kubectl
+/// would use (or the in-cluster service account), so results are the API
+/// objects themselves rather than kubectl's tables. pods and deployments
+/// summarize each object (phase, readiness, restarts, images); get returns
+/// one object whole. apply server-side applies a manifest inline or from a
+/// file and can run as a client or server dry run. rollout polls the
+/// workload until its rollout finishes, the way `kubectl rollout status`
+/// does.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use k8s_openapi::api::apps::v1::Deployment;
+use k8s_openapi::api::core::v1::Pod;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::Status;
+use kube::api::{Api, AttachParams, DynamicObject, ListParams, LogParams, Patch, PatchParams};
+use kube::config::{KubeConfigOptions, Kubeconfig};
+use kube::discovery::{self, ApiCapabilities, ApiResource, Discovery, Scope};
+use kube::core::GroupVersionKind;
+use kube::{Client, Config, Resource};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Longest any API call other than rollout may take
+const CALL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How often rollout checks the workload
+const ROLLOUT_POLL: Duration = Duration::from_secs(2);
+
+/// Field manager recorded on what apply writes
+const FIELD_MANAGER: &str = "hanzo-mcp";
+
+/// kubectl's short names for the built-in kinds
+const SHORT_NAMES: &[(&str, &str)] = &[
+    ("po", "pods"),
+    ("svc", "services"),
+    ("deploy", "deployments"),
+    ("ds", "daemonsets"),
+    ("sts", "statefulsets"),
+    ("rs", "replicasets"),
+    ("cm", "configmaps"),
+    ("ns", "namespaces"),
+    ("no", "nodes"),
+    ("pv", "persistentvolumes"),
+    ("pvc", "persistentvolumeclaims"),
+    ("sa", "serviceaccounts"),
+    ("ing", "ingresses"),
+    ("cj", "cronjobs"),
+    ("hpa", "horizontalpodautoscalers"),
+    ("ep", "endpoints"),
+    ("netpol", "networkpolicies"),
+    ("sc", "storageclasses"),
+    ("crd", "customresourcedefinitions"),
+];
+
+/// How long rollout waits when the caller gives no timeout
+const ROLLOUT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Log lines returned when the caller gives no tail
+const DEFAULT_TAIL: usize = 200;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum K8sAction {
+    Contexts,
+    #[default]
+    Pods,
+    Deployments,
+    Get,
+    Logs,
+    Exec,
+    Apply,
+    Rollout,
+    Help,
+}
+
+impl std::str::FromStr for K8sAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "contexts" | "context" | "clusters" => Ok(Self::Contexts),
+            "pods" | "pod" | "po" | "" => Ok(Self::Pods),
+            "deployments" | "deployment" | "deploy" => Ok(Self::Deployments),
+            "get" | "describe" => Ok(Self::Get),
+            "logs" | "log" => Ok(Self::Logs),
+            "exec" | "run" => Ok(Self::Exec),
+            "apply" => Ok(Self::Apply),
+            "rollout" | "rollout_status" | "status" => Ok(Self::Rollout),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl K8sAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Contexts => "contexts",
+            Self::Pods => "pods",
+            Self::Deployments => "deployments",
+            Self::Get => "get",
+            Self::Logs => "logs",
+            Self::Exec => "exec",
+            Self::Apply => "apply",
+            Self::Rollout => "rollout",
+            Self::Help => "help",
+        }
+    }
+}
+
+/// Dry-run strategies apply accepts: client only parses and resolves the
+/// manifest, server has the API server validate it without persisting
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DryRun {
+    #[default]
+    None,
+    Client,
+    Server,
+}
+
+impl std::str::FromStr for DryRun {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" | "false" | "" => Ok(Self::None),
+            "client" => Ok(Self::Client),
+            "server" | "true" => Ok(Self::Server),
+            _ => Err(ToolError::invalid(format!("Unknown dry_run: {} (use none, client or server)", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct K8sToolArgs {
+    pub action: Option<String>,
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub all_namespaces: bool,
+    /// kubeconfig context to use instead of the current one
+    pub context: Option<String>,
+    pub kubeconfig: Option<String>,
+    /// Object name; for logs and rollout also `kind/name`
+    pub name: Option<String>,
+    /// For get and rollout: resource kind (default deployment for rollout)
+    pub kind: Option<String>,
+    /// Label selector, e.g. app=web
+    pub selector: Option<String>,
+    pub container: Option<String>,
+    /// For exec: the command and its arguments
+    pub command: Option<Vec<String>>,
+    /// For apply: YAML or JSON manifest text
+    pub manifest: Option<String>,
+    /// For apply: manifest file or directory
+    pub file: Option<String>,
+    /// For apply: none, client or server
+    pub dry_run: Option<String>,
+    /// For logs: lines from the end
+    pub tail: Option<usize>,
+    /// For logs: only newer than this duration, e.g. 10m
+    pub since: Option<String>,
+    /// For logs: the previous container instance's logs
+    #[serde(default)]
+    pub previous: bool,
+    /// For rollout and exec: seconds to wait
+    pub timeout_secs: Option<u64>,
+}
+
+pub struct K8sToolDefinition;
+
+impl K8sToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "k8s",
+            "description": "Kubernetes API objects as JSON: contexts, pods, deployments, get (one object), logs, exec (command in a pod), apply (manifest, with client or server dry run), rollout (wait for a rollout to finish), help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["contexts", "pods", "deployments", "get", "logs", "exec", "apply", "rollout", "help"],
+                        "default": "pods"
+                    },
+                    "namespace": { "type": "string", "description": "Namespace (default: the context's)" },
+                    "all_namespaces": { "type": "boolean", "description": "For pods and deployments: every namespace" },
+                    "context": { "type": "string", "description": "kubeconfig context" },
+                    "kubeconfig": { "type": "string", "description": "kubeconfig file" },
+                    "name": { "type": "string", "description": "Object name; for logs and rollout also kind/name" },
+                    "kind": { "type": "string", "description": "For get and rollout: resource kind", "default": "deployment" },
+                    "selector": { "type": "string", "description": "Label selector, e.g. app=web" },
+                    "container": { "type": "string", "description": "For logs and exec: container in the pod" },
+                    "command": { "type": "array", "items": { "type": "string" }, "description": "For exec: command and arguments" },
+                    "manifest": { "type": "string", "description": "For apply: YAML or JSON manifest" },
+                    "file": { "type": "string", "description": "For apply: manifest file or directory" },
+                    "dry_run": { "type": "string", "enum": ["none", "client", "server"], "description": "For apply: validate without persisting", "default": "none" },
+                    "tail": { "type": "integer", "description": "For logs: lines from the end", "default": DEFAULT_TAIL },
+                    "since": { "type": "string", "description": "For logs: only newer than this, e.g. 10m" },
+                    "previous": { "type": "boolean", "description": "For logs: the previous container instance" },
+                    "timeout_secs": { "type": "integer", "description": "For rollout and exec: seconds to wait" }
+                }
+            }
+        })
+    }
+}
+
+pub struct K8sTool;
+
+impl K8sTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub async fn execute(&self, args: K8sToolArgs) -> Result<Value> {
+        let action: K8sAction = args.action.as_deref().unwrap_or("pods").parse()?;
+        let data = match action {
+            K8sAction::Contexts => self.contexts(&args).await?,
+            K8sAction::Pods => self.pods(&args).await?,
+            K8sAction::Deployments => self.deployments(&args).await?,
+            K8sAction::Get => self.get(&args).await?,
+            K8sAction::Logs => self.logs(&args).await?,
+            K8sAction::Exec => self.exec(&args).await?,
+            K8sAction::Apply => self.apply(&args).await?,
+            K8sAction::Rollout => self.rollout(&args).await?,
+            K8sAction::Help => return Ok(self.help()),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "k8s", "action": action.name() }
+        }))
+    }
+
+    async fn contexts(&self, args: &K8sToolArgs) -> Result<Value> {
+        let config = kubeconfig(args)?;
+        let contexts: Vec<Value> = config.contexts.iter()
+            .map(|c| {
+                let context = c.context.as_ref();
+                json!({
+                    "name": c.name,
+                    "cluster": context.map(|c| &c.cluster),
+                    "user": context.and_then(|c| c.user.as_ref()),
+                    "namespace": context.and_then(|c| c.namespace.as_deref()).unwrap_or("default")
+                })
+            })
+            .collect();
+        Ok(json!({ "current": config.current_context, "contexts": contexts }))
+    }
+
+    async fn pods(&self, args: &K8sToolArgs) -> Result<Value> {
+        let pods = list(api::<Pod>(client(args).await?, args, true), args).await?;
+        let pods: Vec<Value> = pods.iter().map(pod_summary).collect();
+        Ok(json!({ "pods": pods, "count": pods.len() }))
+    }
+
+    async fn deployments(&self, args: &K8sToolArgs) -> Result<Value> {
+        let deployments = list(api::<Deployment>(client(args).await?, args, true), args).await?;
+        let deployments: Vec<Value> = deployments.iter().map(deployment_summary).collect();
+        Ok(json!({ "deployments": deployments, "count": deployments.len() }))
+    }
+
+    async fn get(&self, args: &K8sToolArgs) -> Result<Value> {
+        let kind = args.kind.as_deref().ok_or_else(|| ToolError::invalid("kind required"))?;
+        let client = client(args).await?;
+        let (resource, capabilities) = resource(&client, kind).await?;
+        let api = dynamic_api(client, args, &resource, &capabilities, true);
+        let object = match args.name.as_deref() {
+            Some(name) => serde_json::to_value(quick(api.get(name)).await?)?,
+            None => serde_json::to_value(quick(api.list(&list_params(args))).await?)?,
+        };
+        Ok(json!({ "object": object }))
+    }
+
+    async fn logs(&self, args: &K8sToolArgs) -> Result<Value> {
+        let client = client(args).await?;
+        let pods = api::<Pod>(client.clone(), args, false);
+        let targets = match (args.name.as_deref(), args.selector.as_deref()) {
+            (Some(name), _) => vec![log_pod(&client, args, name).await?],
+            (None, Some(selector)) => quick(pods.list(&ListParams::default().labels(selector))).await?.items,
+            (None, None) => return Err(ToolError::invalid("name or selector required").into()),
+        };
+        let since_seconds = args.since.as_deref().map(crate::schedule::parse_interval).transpose()?;
+        let prefix = args.name.is_none();
+        let mut lines = Vec::new();
+        for pod in &targets {
+            let name = pod.metadata.name.clone().unwrap_or_default();
+            let container = args.container.clone().or_else(|| default_container(pod));
+            let params = LogParams {
+                container: container.clone(),
+                tail_lines: Some(args.tail.unwrap_or(DEFAULT_TAIL) as i64),
+                since_seconds,
+                previous: args.previous,
+                ..Default::default()
+            };
+            let text = quick(pods.logs(&name, &params)).await?;
+            // Lines from a selector's pods say where they came from, as kubectl --prefix does
+            lines.extend(text.lines().map(|line| match &container {
+                Some(container) if prefix => format!("[pod/{}/{}] {}", name, container, line),
+                _ => line.to_string(),
+            }));
+        }
+        Ok(json!({ "lines": lines, "count": lines.len() }))
+    }
+
+    async fn exec(&self, args: &K8sToolArgs) -> Result<Value> {
+        let name = args.name.as_deref().ok_or_else(|| ToolError::invalid("name (pod) required"))?;
+        let command = args.command.clone()
+            .filter(|c| !c.is_empty())
+            .ok_or_else(|| ToolError::invalid("command required"))?;
+        let pods = api::<Pod>(client(args).await?, args, false);
+        let mut params = AttachParams::default();
+        if let Some(container) = &args.container {
+            params = params.container(container);
+        }
+        let timeout = args.timeout_secs.map_or(CALL_TIMEOUT, Duration::from_secs);
+        let run = async {
+            let mut attached = pods.exec(name, command, &params).await.map_err(api_error)?;
+            let (stdout, stderr) = tokio::join!(read_all(attached.stdout()), read_all(attached.stderr()));
+            let status = match attached.take_status() {
+                Some(status) => status.await,
+                None => None,
+            };
+            Ok::<_, anyhow::Error>((stdout, stderr, exit_code(status.as_ref())))
+        };
+        let (stdout, stderr, exit_code) = tokio::time::timeout(timeout, run)
+            .await
+            .map_err(|_| ToolError::timeout(format!("exec in {} did not finish within {}s", name, timeout.as_secs())))??;
+        Ok(json!({ "exit_code": exit_code, "stdout": stdout, "stderr": stderr }))
+    }
+
+    async fn apply(&self, args: &K8sToolArgs) -> Result<Value> {
+        let dry_run: DryRun = args.dry_run.as_deref().unwrap_or("none").parse()?;
+        let texts = match (&args.manifest, &args.file) {
+            (Some(manifest), None) => vec![manifest.clone()],
+            (None, Some(file)) => manifest_files(Path::new(shellexpand::tilde(file).as_ref()))?,
+            _ => return Err(ToolError::invalid("exactly one of manifest or file required").into()),
+        };
+        let objects = texts.iter().map(|text| parse_manifests(text)).collect::<Result<Vec<_>>>()?.concat();
+
+        let client = client(args).await?;
+        let mut params = PatchParams::apply(FIELD_MANAGER).force();
+        params.dry_run = dry_run == DryRun::Server;
+        let mut resources = Vec::new();
+        for mut object in objects {
+            let types = object.types.clone().unwrap_or_default();
+            let (group, version) = types.api_version.split_once('/').unwrap_or(("", &types.api_version));
+            let kind = GroupVersionKind::gvk(group, version, &types.kind);
+            let (resource, capabilities) = quick(discovery::pinned_kind(&client, &kind)).await?;
+            if capabilities.scope == Scope::Namespaced && object.metadata.namespace.is_none() {
+                object.metadata.namespace = Some(args.namespace.clone().unwrap_or_else(|| client.default_namespace().to_string()));
+            }
+            let name = object.metadata.name.clone().unwrap_or_default();
+            let applied = match dry_run {
+                DryRun::Client => object,
+                _ => {
+                    let api = match object.metadata.namespace.as_deref() {
+                        Some(namespace) if capabilities.scope == Scope::Namespaced => {
+                            Api::<DynamicObject>::namespaced_with(client.clone(), namespace, &resource)
+                        }
+                        _ => Api::<DynamicObject>::all_with(client.clone(), &resource),
+                    };
+                    quick(api.patch(&name, &params, &Patch::Apply(&object))).await?
+                }
+            };
+            resources.push(json!({ "kind": resource.kind, "name": name, "namespace": applied.metadata.namespace }));
+        }
+        Ok(json!({
+            "dry_run": dry_run != DryRun::None,
+            "resources": resources,
+            "count": resources.len()
+        }))
+    }
+
+    async fn rollout(&self, args: &K8sToolArgs) -> Result<Value> {
+        let name = args.name.as_deref().ok_or_else(|| ToolError::invalid("name required"))?;
+        let (kind, name) = name.split_once('/').unwrap_or((args.kind.as_deref().unwrap_or("deployment"), name));
+        let client = client(args).await?;
+        let (resource, capabilities) = resource(&client, kind).await?;
+        let target = format!("{}/{}", resource.kind.to_lowercase(), name);
+        let api = dynamic_api(client, args, &resource, &capabilities, false);
+
+        let timeout = args.timeout_secs.map_or(ROLLOUT_TIMEOUT, Duration::from_secs);
+        let started = Instant::now();
+        let mut messages: Vec<String> = Vec::new();
+        let (object, outcome) = loop {
+            let object = serde_json::to_value(quick(api.get(name)).await?)?;
+            let progress = rollout_status(&object);
+            if let Rollout::Waiting(message) | Rollout::Done(message) = &progress {
+                if messages.last() != Some(message) {
+                    messages.push(message.clone());
+                }
+            }
+            match progress {
+                Rollout::Waiting(_) if started.elapsed() >= timeout => {
+                    break (object, Some(format!("timed out waiting for the rollout of {} after {}s", target, timeout.as_secs())));
+                }
+                Rollout::Waiting(_) => tokio::time::sleep(ROLLOUT_POLL).await,
+                Rollout::Done(_) => break (object, None),
+                Rollout::Failed(error) => break (object, Some(error)),
+            }
+        };
+        let summary = match object["kind"].as_str() {
+            Some("Deployment") => deployment_summary(&object),
+            _ => json!({ "kind": object["kind"], "name": object["metadata"]["name"], "status": object["status"] }),
+        };
+        Ok(json!({
+            "complete": outcome.is_none(),
+            "target": target,
+            "messages": messages,
+            "error": outcome,
+            "workload": summary
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "k8s",
+                "actions": {
+                    "contexts": "kubeconfig contexts and the current one",
+                    "pods": "Pods with phase, readiness, restarts and node (namespace, all_namespaces, selector, name)",
+                    "deployments": "Deployments with replica counts, images and conditions",
+                    "get": "One object or list as the API returns it (requires kind)",
+                    "logs": "Container logs as lines (name or selector; container, tail, since, previous)",
+                    "exec": "Run a command in a pod (requires name, command)",
+                    "apply": "Apply a manifest or file, optionally as a client or server dry run",
+                    "rollout": "Wait for a rollout to finish and report the workload (requires name)",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "k8s", "action": "help" }
+        })
+    }
+}
+
+impl Default for K8sTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The kubeconfig named by the call, or the one kubectl would read
+fn kubeconfig(args: &K8sToolArgs) -> Result<Kubeconfig> {
+    let config = match &args.kubeconfig {
+        Some(path) => Kubeconfig::read_from(shellexpand::tilde(path).as_ref()),
+        None => Kubeconfig::read(),
+    };
+    config.map_err(|e| ToolError::not_found(format!("kubeconfig: {}", e)).into())
+}
+
+/// A client for the call's kubeconfig and context; with neither, whatever
+/// kubectl would use, falling back to the in-cluster service account
+async fn client(args: &K8sToolArgs) -> Result<Client> {
+    let options = KubeConfigOptions { context: args.context.clone(), ..Default::default() };
+    let config = match (&args.kubeconfig, &args.context) {
+        (None, None) => Config::infer().await.map_err(|e| e.to_string()),
+        (None, Some(_)) => Config::from_kubeconfig(&options).await.map_err(|e| e.to_string()),
+        (Some(_), _) => Config::from_custom_kubeconfig(kubeconfig(args)?, &options).await.map_err(|e| e.to_string()),
+    };
+    let config = config.map_err(|e| ToolError::unsupported(format!("No usable Kubernetes configuration: {}", e)))?;
+    Client::try_from(config).map_err(api_error)
+}
+
+/// The call's namespace, or every namespace when `all` and all_namespaces allow it
+fn api<K>(client: Client, args: &K8sToolArgs, all: bool) -> Api<K>
+where
+    K: Resource<Scope = k8s_openapi::NamespaceResourceScope>,
+    <K as Resource>::DynamicType: Default,
+{
+    match &args.namespace {
+        _ if all && args.all_namespaces => Api::all(client),
+        Some(namespace) => Api::namespaced(client, namespace),
+        None => Api::default_namespaced(client),
+    }
+}
+
+/// `api` for a resource found through discovery
+fn dynamic_api(client: Client, args: &K8sToolArgs, resource: &ApiResource, capabilities: &ApiCapabilities, all: bool) -> Api<DynamicObject> {
+    match &args.namespace {
+        _ if capabilities.scope == Scope::Cluster || (all && args.all_namespaces) => Api::all_with(client, resource),
+        Some(namespace) => Api::namespaced_with(client, namespace, resource),
+        None => Api::default_namespaced_with(client, resource),
+    }
+}
+
+fn list_params(args: &K8sToolArgs) -> ListParams {
+    match args.selector.as_deref() {
+        Some(selector) => ListParams::default().labels(selector),
+        None => ListParams::default(),
+    }
+}
+
+/// The named object, or the objects the selector matches, as JSON
+async fn list<K>(api: Api<K>, args: &K8sToolArgs) -> Result<Vec<Value>>
+where
+    K: Resource + Clone + std::fmt::Debug + Serialize + serde::de::DeserializeOwned,
+{
+    let objects = match args.name.as_deref() {
+        Some(name) => vec![quick(api.get(name)).await?],
+        None => quick(api.list(&list_params(args))).await?.items,
+    };
+    objects.iter().map(|object| serde_json::to_value(object).map_err(Into::into)).collect()
+}
+
+/// An API call that should answer within CALL_TIMEOUT
+async fn quick<T>(call: impl Future<Output = kube::Result<T>>) -> Result<T> {
+    tokio::time::timeout(CALL_TIMEOUT, call)
+        .await
+        .map_err(|_| ToolError::timeout(format!("The API server did not answer within {}s", CALL_TIMEOUT.as_secs())))?
+        .map_err(api_error)
+}
+
+fn api_error(error: kube::Error) -> anyhow::Error {
+    match &error {
+        kube::Error::Api(response) => {
+            let message = format!("Kubernetes error: {}", response.message);
+            match response.code {
+                404 => ToolError::not_found(message),
+                401 | 403 => ToolError::permission_denied(message),
+                _ => ToolError::external(message),
+            }
+        }
+        kube::Error::Auth(_) => ToolError::permission_denied(format!("Kubernetes error: {}", error)),
+        kube::Error::HyperError(_) | kube::Error::Service(_) => ToolError::unsupported(format!("Cannot reach the cluster: {}", error)),
+        _ => ToolError::external(format!("Kubernetes error: {}", error)),
+    }
+    .into()
+}
+
+/// A kind as the user wrote it (plural, kind, short name, optionally
+/// `plural.group`) as a lowercase name and group
+fn kind_name(kind: &str) -> (String, Option<String>) {
+    let kind = kind.to_lowercase();
+    let (name, group) = match kind.split_once('.') {
+        Some((name, group)) => (name, Some(group.to_string())),
+        None => (kind.as_str(), None),
+    };
+    let name = SHORT_NAMES.iter().find(|(short, _)| *short == name).map_or(name, |(_, plural)| plural);
+    (name.to_string(), group)
+}
+
+/// The API resource a kind names, found through discovery
+async fn resource(client: &Client, kind: &str) -> Result<(ApiResource, ApiCapabilities)> {
+    let (name, group) = kind_name(kind);
+    let mut discovery = Discovery::new(client.clone());
+    if let Some(group) = group.as_deref() {
+        discovery = discovery.filter(&[group]);
+    }
+    let discovery = quick(discovery.run()).await?;
+    let found = discovery.groups()
+        .flat_map(|group| group.recommended_resources())
+        .find(|(resource, _)| resource.plural == name || resource.kind.to_lowercase() == name);
+    found.ok_or_else(|| ToolError::not_found(format!("No resource type {:?} on this cluster", kind)).into())
+}
+
+/// The pod `kind/name` logs come from: the pod itself, or the first pod
+/// of a workload, preferring a running one as kubectl does
+async fn log_pod(client: &Client, args: &K8sToolArgs, name: &str) -> Result<Pod> {
+    let pods = api::<Pod>(client.clone(), args, false);
+    let (kind, workload) = match name.split_once('/') {
+        Some((kind, workload)) if !matches!(kind_name(kind).0.as_str(), "pod" | "pods") => (kind, workload),
+        Some((_, pod)) => return quick(pods.get(pod)).await,
+        None => return quick(pods.get(name)).await,
+    };
+    let (resource, capabilities) = resource(client, kind).await?;
+    let object = serde_json::to_value(quick(dynamic_api(client.clone(), args, &resource, &capabilities, false).get(workload)).await?)?;
+    let selector = object["spec"]["selector"]["matchLabels"].as_object()
+        .filter(|labels| !labels.is_empty())
+        .map(|labels| labels.iter().map(|(k, v)| format!("{}={}", k, v.as_str().unwrap_or_default())).collect::<Vec<_>>().join(","))
+        .ok_or_else(|| ToolError::invalid(format!("{} has no label selector to find its pods", name)))?;
+    let mut matching = quick(pods.list(&ListParams::default().labels(&selector))).await?.items;
+    matching.sort_by_key(|pod| pod.status.as_ref().and_then(|s| s.phase.as_deref()) != Some("Running"));
+    matching.into_iter().next().ok_or_else(|| ToolError::not_found(format!("No pods for {}", name)).into())
+}
+
+/// The container logs read when none is named: the one the
+/// default-container annotation names, else the first
+fn default_container(pod: &Pod) -> Option<String> {
+    pod.metadata.annotations.as_ref()
+        .and_then(|a| a.get("kubectl.kubernetes.io/default-container").cloned())
+        .or_else(|| pod.spec.as_ref()?.containers.first().map(|c| c.name.clone()))
+}
+
+async fn read_all(reader: Option<impl AsyncRead + Unpin>) -> String {
+    let mut text = String::new();
+    if let Some(mut reader) = reader {
+        let mut bytes = Vec::new();
+        let _ = reader.read_to_end(&mut bytes).await;
+        text = String::from_utf8_lossy(&bytes).to_string();
+    }
+    text
+}
+
+/// The exit code a finished exec reports: 0 on Success, else its ExitCode cause
+fn exit_code(status: Option<&Status>) -> Option<i32> {
+    let status = status?;
+    if status.status.as_deref() == Some("Success") {
+        return Some(0);
+    }
+    status.details.as_ref()?.causes.as_ref()?.iter()
+        .find(|cause| cause.reason.as_deref() == Some("ExitCode"))
+        .and_then(|cause| cause.message.as_deref()?.parse().ok())
+}
+
+/// The YAML or JSON files of a manifest directory, or the one file
+fn manifest_files(path: &Path) -> Result<Vec<String>> {
+    if !path.is_dir() {
+        return std::fs::read_to_string(path)
+            .map(|text| vec![text])
+            .map_err(|e| ToolError::not_found(format!("{}: {}", path.display(), e)).into());
+    }
+    let mut files: Vec<_> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|file| matches!(file.extension().and_then(|e| e.to_str()), Some("yaml" | "yml" | "json")))
+        .collect();
+    files.sort();
+    files.iter().map(|file| std::fs::read_to_string(file).map_err(Into::into)).collect()
+}
+
+/// The objects in a manifest: every YAML document, with Lists expanded
+fn parse_manifests(text: &str) -> Result<Vec<DynamicObject>> {
+    let mut objects = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(text) {
+        let value = serde_json::to_value(serde_yaml::Value::deserialize(document)
+            .map_err(|e| ToolError::invalid(format!("Bad manifest: {}", e)))?)?;
+        let values = match value["kind"].as_str() {
+            _ if value.is_null() => continue,
+            Some("List") => value["items"].as_array().cloned().unwrap_or_default(),
+            _ => vec![value],
+        };
+        for value in values {
+            let object: DynamicObject = serde_json::from_value(value)
+                .map_err(|e| ToolError::invalid(format!("Bad manifest object: {}", e)))?;
+            if object.types.is_none() || object.metadata.name.is_none() {
+                return Err(ToolError::invalid("Every manifest object needs apiVersion, kind and metadata.name").into());
+            }
+            objects.push(object);
+        }
+    }
+    Ok(objects)
+}
+
+/// Where a rollout stands
+#[derive(Debug, PartialEq)]
+enum Rollout {
+    Waiting(String),
+    Done(String),
+    Failed(String),
+}
+
+/// How `kubectl rollout status` reads a deployment, statefulset or daemonset
+fn rollout_status(object: &Value) -> Rollout {
+    let name = object["metadata"]["name"].as_str().unwrap_or_default();
+    let spec = &object["spec"];
+    let status = &object["status"];
+    let count = |field: &str| status[field].as_i64().unwrap_or(0);
+    let observed = object["metadata"]["generation"].as_i64().unwrap_or(0) <= count("observedGeneration");
+    let rolling = spec["updateStrategy"]["type"].as_str().unwrap_or("RollingUpdate") == "RollingUpdate";
+    match object["kind"].as_str().unwrap_or_default() {
+        "Deployment" => {
+            if !observed {
+                return Rollout::Waiting("Waiting for deployment spec update to be observed...".into());
+            }
+            let stalled = status["conditions"].as_array().into_iter().flatten()
+                .any(|c| c["type"] == "Progressing" && c["reason"] == "ProgressDeadlineExceeded");
+            if stalled {
+                return Rollout::Failed(format!("deployment {:?} exceeded its progress deadline", name));
+            }
+            let replicas = spec["replicas"].as_i64().unwrap_or(1);
+            let (updated, available) = (count("updatedReplicas"), count("availableReplicas"));
+            let waiting = |detail: String| Rollout::Waiting(format!("Waiting for deployment {:?} rollout to finish: {}", name, detail));
+            if updated < replicas {
+                waiting(format!("{} out of {} new replicas have been updated...", updated, replicas))
+            } else if count("replicas") > updated {
+                waiting(format!("{} old replicas are pending termination...", count("replicas") - updated))
+            } else if available < updated {
+                waiting(format!("{} of {} updated replicas are available...", available, updated))
+            } else {
+                Rollout::Done(format!("deployment {:?} successfully rolled out", name))
+            }
+        }
+        "StatefulSet" => {
+            if !rolling {
+                return Rollout::Failed("rollout status is only available for RollingUpdate strategy type".into());
+            }
+            if count("observedGeneration") == 0 || !observed {
+                return Rollout::Waiting("Waiting for statefulset spec update to be observed...".into());
+            }
+            let replicas = spec["replicas"].as_i64().unwrap_or(1);
+            let partition = spec["updateStrategy"]["rollingUpdate"]["partition"].as_i64().unwrap_or(0);
+            let revision = status["updateRevision"].as_str().unwrap_or_default();
+            if count("readyReplicas") < replicas {
+                Rollout::Waiting(format!("Waiting for {} pods to be ready...", replicas - count("readyReplicas")))
+            } else if partition > 0 && count("updatedReplicas") < replicas - partition {
+                Rollout::Waiting(format!(
+                    "Waiting for partitioned roll out to finish: {} out of {} new pods have been updated...",
+                    count("updatedReplicas"), replicas - partition
+                ))
+            } else if partition > 0 {
+                Rollout::Done(format!("partitioned roll out complete: {} new pods have been updated...", count("updatedReplicas")))
+            } else if status["currentRevision"].as_str() != Some(revision) {
+                Rollout::Waiting(format!(
+                    "waiting for statefulset rolling update to complete {} pods at revision {}...",
+                    count("updatedReplicas"), revision
+                ))
+            } else {
+                Rollout::Done(format!("statefulset rolling update complete {} pods at revision {}...", count("currentReplicas"), revision))
+            }
+        }
+        "DaemonSet" => {
+            if !rolling {
+                return Rollout::Failed("rollout status is only available for RollingUpdate strategy type".into());
+            }
+            if !observed {
+                return Rollout::Waiting("Waiting for daemon set spec update to be observed...".into());
+            }
+            let desired = count("desiredNumberScheduled");
+            let waiting = |detail: String| Rollout::Waiting(format!("Waiting for daemon set {:?} rollout to finish: {}", name, detail));
+            if count("updatedNumberScheduled") < desired {
+                waiting(format!("{} out of {} new pods have been updated...", count("updatedNumberScheduled"), desired))
+            } else if count("numberAvailable") < desired {
+                waiting(format!("{} of {} updated pods are available...", count("numberAvailable"), desired))
+            } else {
+                Rollout::Done(format!("daemon set {:?} successfully rolled out", name))
+            }
+        }
+        kind => Rollout::Failed(format!("no rollout status for {}; use a deployment, statefulset or daemonset", kind)),
+    }
+}
+
+/// What `kubectl get pods` shows, as fields
+fn pod_summary(pod: &Value) -> Value {
+    let statuses = pod["status"]["containerStatuses"].as_array().cloned().unwrap_or_default();
+    let total = pod["spec"]["containers"].as_array().map_or(statuses.len(), Vec::len);
+    let ready = statuses.iter().filter(|s| s["ready"].as_bool() == Some(true)).count();
+    let restarts: u64 = statuses.iter().filter_map(|s| s["restartCount"].as_u64()).sum();
+    // A waiting or terminated container's reason (CrashLoopBackOff,
+    // ImagePullBackOff, OOMKilled) says more than the pod's phase
+    let reason = statuses.iter()
+        .find_map(|s| s["state"]["waiting"]["reason"].as_str().or(s["state"]["terminated"]["reason"].as_str()))
+        .or(pod["status"]["reason"].as_str());
+    let containers: Vec<Value> = statuses.iter()
+        .map(|s| json!({ "name": s["name"], "image": s["image"], "ready": s["ready"], "restarts": s["restartCount"] }))
+        .collect();
+    json!({
+        "name": pod["metadata"]["name"],
+        "namespace": pod["metadata"]["namespace"],
+        "phase": pod["status"]["phase"],
+        "reason": reason,
+        "ready": format!("{}/{}", ready, total),
+        "restarts": restarts,
+        "node": pod["spec"]["nodeName"],
+        "ip": pod["status"]["podIP"],
+        "started": pod["status"]["startTime"],
+        "containers": containers
+    })
+}
+
+/// What `kubectl get deployments` shows, plus images and conditions
+fn deployment_summary(deployment: &Value) -> Value {
+    let status = &deployment["status"];
+    let images: Vec<&Value> = deployment["spec"]["template"]["spec"]["containers"].as_array().into_iter().flatten()
+        .map(|c| &c["image"])
+        .collect();
+    let conditions: Vec<Value> = status["conditions"].as_array().into_iter().flatten()
+        .map(|c| json!({ "type": c["type"], "status": c["status"], "reason": c["reason"], "message": c["message"] }))
+        .collect();
+    let count = |field: &str| status[field].as_u64().unwrap_or(0);
+    json!({
+        "name": deployment["metadata"]["name"],
+        "namespace": deployment["metadata"]["namespace"],
+        "replicas": deployment["spec"]["replicas"].as_u64().unwrap_or(1),
+        "ready": count("readyReplicas"),
+        "updated": count("updatedReplicas"),
+        "available": count("availableReplicas"),
+        "generation": deployment["metadata"]["generation"],
+        "observed_generation": status["observedGeneration"],
+        "images": images,
+        "conditions": conditions
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{StatusCause, StatusDetails};
+
+    #[test]
+    fn test_summaries() {
+        let pods = json!({ "items": [{
+            "metadata": { "name": "web-1", "namespace": "prod" },
+            "spec": { "nodeName": "node-a", "containers": [{ "name": "app" }, { "name": "sidecar" }] },
+            "status": {
+                "phase": "Running",
+                "podIP": "10.0.0.5",
+                "containerStatuses": [
+                    { "name": "app", "image": "web:2", "ready": false, "restartCount": 4,
+                      "state": { "waiting": { "reason": "CrashLoopBackOff" } } },
+                    { "name": "sidecar", "image": "proxy:1", "ready": true, "restartCount": 0, "state": { "running": {} } }
+                ]
+            }
+        }]});
+        let pod = pod_summary(&pods["items"][0]);
+        assert_eq!(pod["ready"], "1/2");
+        assert_eq!(pod["restarts"], 4);
+        assert_eq!(pod["reason"], "CrashLoopBackOff");
+        assert_eq!(pod["containers"][1]["image"], "proxy:1");
+
+        let deployment = json!({
+            "kind": "Deployment",
+            "metadata": { "name": "web", "namespace": "prod", "generation": 3 },
+            "spec": { "replicas": 3, "template": { "spec": { "containers": [{ "image": "web:2" }] } } },
+            "status": { "readyReplicas": 2, "updatedReplicas": 3, "observedGeneration": 3,
+                        "conditions": [{ "type": "Available", "status": "False", "reason": "MinimumReplicasUnavailable" }] }
+        });
+        let summary = deployment_summary(&deployment);
+        assert_eq!(summary["ready"], 2);
+        assert_eq!(summary["available"], 0);
+        assert_eq!(summary["images"], json!(["web:2"]));
+        assert_eq!(summary["conditions"][0]["reason"], "MinimumReplicasUnavailable");
+    }
+
+    #[test]
+    fn test_rollout_status() {
+        let mut deployment = json!({
+            "kind": "Deployment",
+            "metadata": { "name": "web", "generation": 4 },
+            "spec": { "replicas": 3 },
+            "status": { "observedGeneration": 3 }
+        });
+        assert_eq!(rollout_status(&deployment), Rollout::Waiting("Waiting for deployment spec update to be observed...".into()));
+        deployment["status"] = json!({ "observedGeneration": 4, "replicas": 4, "updatedReplicas": 3, "availableReplicas": 3 });
+        assert_eq!(
+            rollout_status(&deployment),
+            Rollout::Waiting("Waiting for deployment \"web\" rollout to finish: 1 old replicas are pending termination...".into())
+        );
+        deployment["status"]["replicas"] = json!(3);
+        assert_eq!(rollout_status(&deployment), Rollout::Done("deployment \"web\" successfully rolled out".into()));
+        deployment["status"]["conditions"] = json!([{ "type": "Progressing", "reason": "ProgressDeadlineExceeded" }]);
+        assert!(matches!(rollout_status(&deployment), Rollout::Failed(_)));
+
+        let daemonset = json!({
+            "kind": "DaemonSet",
+            "metadata": { "name": "agent", "generation": 1 },
+            "status": { "observedGeneration": 1, "desiredNumberScheduled": 2, "updatedNumberScheduled": 2, "numberAvailable": 1 }
+        });
+        assert!(matches!(rollout_status(&daemonset), Rollout::Waiting(m) if m.ends_with("1 of 2 updated pods are available...")));
+        assert!(matches!(rollout_status(&json!({ "kind": "Pod" })), Rollout::Failed(_)));
+    }
+
+    #[test]
+    fn test_manifests() {
+        let objects = parse_manifests(concat!(
+            "apiVersion: v1\nkind: ConfigMap\nmetadata:\n  name: settings\n",
+            "---\n",
+            "apiVersion: v1\nkind: List\nitems:\n",
+            "- { apiVersion: apps/v1, kind: Deployment, metadata: { name: web, namespace: prod } }\n",
+            "---\n"
+        )).unwrap();
+        assert_eq!(objects.len(), 2);
+        assert_eq!(objects[1].types.as_ref().unwrap().api_version, "apps/v1");
+        assert_eq!(objects[1].metadata.namespace.as_deref(), Some("prod"));
+        assert!(parse_manifests("kind: ConfigMap\nmetadata: { name: x }\n").is_err());
+
+        assert_eq!(kind_name("deploy"), ("deployments".to_string(), None));
+        assert_eq!(kind_name("Ingresses.networking.k8s.io"), ("ingresses".to_string(), Some("networking.k8s.io".to_string())));
+
+        let exited = Status {
+            status: Some("Failure".into()),
+            details: Some(StatusDetails {
+                causes: Some(vec![StatusCause { reason: Some("ExitCode".into()), message: Some("3".into()), ..Default::default() }]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(exit_code(Some(&exited)), Some(3));
+        assert_eq!(exit_code(Some(&Status { status: Some("Success".into()), ..Default::default() })), Some(0));
+        assert_eq!(exit_code(None), None);
+    }
+
+    #[test]
+    fn test_action_parse() {
+        assert_eq!("po".parse::<K8sAction>().unwrap(), K8sAction::Pods);
+        assert_eq!("deploy".parse::<K8sAction>().unwrap(), K8sAction::Deployments);
+        assert_eq!("true".parse::<DryRun>().unwrap(), DryRun::Server);
+        assert!("maybe".parse::<DryRun>().is_err());
+    }
+}
@@ -0,0 +1,281 @@
+/// Language server client for the lsp tool
+///
+/// Speaks JSON-RPC over a server's stdio with `Content-Length` framing. A
+/// reader task routes responses to their waiting requests, keeps the latest
+/// `textDocument/publishDiagnostics` per document, and answers the requests
+/// servers make of their client (configuration, capability registration,
+/// progress tokens) so they never stall waiting on us.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{oneshot, Notify};
+
+type Pending = Arc<Mutex<HashMap<i64, oneshot::Sender<Result<Value, String>>>>>;
+
+pub struct LspClient {
+    pub root: PathBuf,
+    pub command: Vec<String>,
+    writer: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Pending,
+    diagnostics: Arc<Mutex<HashMap<String, Vec<Value>>>>,
+    published: Arc<Notify>,
+    /// Open documents: uri -> (version, text sent)
+    documents: tokio::sync::Mutex<HashMap<String, (i64, String)>>,
+    next_id: AtomicI64,
+    child: tokio::sync::Mutex<Child>,
+    request_timeout: Duration,
+}
+
+impl LspClient {
+    /// Spawn `command` and run the initialize handshake for `root`
+    pub async fn start(command: &[String], root: &Path, request_timeout: Duration) -> Result<Self> {
+        let mut child = Command::new(&command[0])
+            .args(&command[1..])
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => ToolError::unsupported(format!(
+                    "Language server '{}' is not installed; install it or configure code.language_servers", command[0]
+                )),
+                _ => ToolError::external(format!("Cannot start {}: {}", command[0], e)),
+            })?;
+        let writer = Arc::new(tokio::sync::Mutex::new(child.stdin.take().expect("stdin is piped")));
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+        let client = Self {
+            root: root.to_path_buf(),
+            command: command.to_vec(),
+            writer: writer.clone(),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            diagnostics: Arc::new(Mutex::new(HashMap::new())),
+            published: Arc::new(Notify::new()),
+            documents: tokio::sync::Mutex::new(HashMap::new()),
+            next_id: AtomicI64::new(1),
+            child: tokio::sync::Mutex::new(child),
+            request_timeout,
+        };
+        tokio::spawn(read_loop(stdout, writer, client.pending.clone(), client.diagnostics.clone(), client.published.clone()));
+
+        let root_uri = uri_for(root)?;
+        client.request("initialize", json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri,
+            "workspaceFolders": [{ "uri": root_uri, "name": root.file_name().map(|n| n.to_string_lossy()).unwrap_or_default() }],
+            "capabilities": {
+                "textDocument": {
+                    "hover": { "contentFormat": ["markdown", "plaintext"] },
+                    "definition": { "linkSupport": false },
+                    "references": {},
+                    "rename": { "prepareSupport": false },
+                    "publishDiagnostics": { "relatedInformation": false },
+                    "synchronization": { "didSave": false }
+                },
+                "workspace": { "workspaceFolders": true, "configuration": true }
+            }
+        })).await?;
+        client.notify("initialized", json!({})).await?;
+        Ok(client)
+    }
+
+    /// Send a request and wait for its result
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if let Err(e) = write_message(&mut *self.writer.lock().await, &message).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(ToolError::external(format!("{} is not running: {}", self.command[0], e)).into());
+        }
+        match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(ToolError::external(format!("{} {}: {}", self.command[0], method, error)).into()),
+            Ok(Err(_)) => Err(ToolError::external(format!("{} exited during {}", self.command[0], method)).into()),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(ToolError::timeout(format!(
+                    "{} did not answer {} within {}s; it may still be indexing",
+                    self.command[0], method, self.request_timeout.as_secs()
+                )).into())
+            }
+        }
+    }
+
+    pub async fn notify(&self, method: &str, params: Value) -> Result<()> {
+        let message = json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        write_message(&mut *self.writer.lock().await, &message).await
+            .map_err(|e| ToolError::external(format!("{} is not running: {}", self.command[0], e)).into())
+    }
+
+    /// Open `path` in the server, or resend it if it changed on disk since;
+    /// returns its uri and text
+    pub async fn sync(&self, path: &Path, language_id: &str) -> Result<(String, String)> {
+        let uri = uri_for(path)?;
+        let text = tokio::fs::read_to_string(path).await?;
+        let mut documents = self.documents.lock().await;
+        match documents.get_mut(&uri) {
+            None => {
+                self.notify("textDocument/didOpen", json!({
+                    "textDocument": { "uri": uri, "languageId": language_id, "version": 1, "text": text }
+                })).await?;
+                documents.insert(uri.clone(), (1, text.clone()));
+            }
+            Some((version, sent)) if *sent != text => {
+                *version += 1;
+                self.notify("textDocument/didChange", json!({
+                    "textDocument": { "uri": uri, "version": *version },
+                    "contentChanges": [{ "text": text }]
+                })).await?;
+                *sent = text.clone();
+            }
+            Some(_) => {}
+        }
+        Ok((uri, text))
+    }
+
+    /// Diagnostics last published for `uri`, waiting up to `wait` for the
+    /// server's first report on it
+    pub async fn diagnostics(&self, uri: &str, wait: Duration) -> Option<Vec<Value>> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let published = self.published.notified();
+            if let Some(found) = self.diagnostics.lock().unwrap().get(uri) {
+                return Some(found.clone());
+            }
+            if tokio::time::timeout_at(deadline, published).await.is_err() {
+                return None;
+            }
+        }
+    }
+
+    /// Ask the server to shut down, then make sure it is gone
+    pub async fn stop(&self) {
+        let _ = tokio::time::timeout(Duration::from_secs(2), self.request("shutdown", Value::Null)).await;
+        let _ = self.notify("exit", Value::Null).await;
+        let mut child = self.child.lock().await;
+        if tokio::time::timeout(Duration::from_secs(2), child.wait()).await.is_err() {
+            let _ = child.kill().await;
+        }
+    }
+
+    /// Whether the server process is still running
+    pub async fn alive(&self) -> bool {
+        matches!(self.child.lock().await.try_wait(), Ok(None))
+    }
+}
+
+pub fn uri_for(path: &Path) -> Result<String> {
+    url::Url::from_file_path(path)
+        .map(String::from)
+        .map_err(|_| ToolError::invalid(format!("{} is not an absolute path", path.display())).into())
+}
+
+pub fn path_for(uri: &str) -> Option<PathBuf> {
+    url::Url::parse(uri).ok()?.to_file_path().ok()
+}
+
+async fn write_message(writer: &mut (impl AsyncWrite + Unpin), message: &Value) -> std::io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    writer.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await?;
+    writer.write_all(&body).await?;
+    writer.flush().await
+}
+
+/// Next framed message, or None at end of stream
+async fn read_message(reader: &mut (impl AsyncBufRead + Unpin)) -> std::io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+async fn read_loop(
+    mut reader: impl AsyncBufRead + Unpin,
+    writer: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: Pending,
+    diagnostics: Arc<Mutex<HashMap<String, Vec<Value>>>>,
+    published: Arc<Notify>,
+) {
+    while let Ok(Some(message)) = read_message(&mut reader).await {
+        match (message.get("id"), message["method"].as_str()) {
+            // Response to one of our requests
+            (Some(id), None) => {
+                let Some(id) = id.as_i64() else { continue };
+                if let Some(tx) = pending.lock().unwrap().remove(&id) {
+                    let result = match message.get("error") {
+                        Some(error) => Err(error["message"].as_str().unwrap_or("request failed").to_string()),
+                        None => Ok(message["result"].clone()),
+                    };
+                    let _ = tx.send(result);
+                }
+            }
+            // Request from the server: empty answers are valid for all we get
+            (Some(id), Some(method)) => {
+                let result = match method {
+                    "workspace/configuration" => {
+                        let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                        json!(vec![Value::Null; items])
+                    }
+                    _ => Value::Null,
+                };
+                let reply = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                let _ = write_message(&mut *writer.lock().await, &reply).await;
+            }
+            (None, Some("textDocument/publishDiagnostics")) => {
+                let params = &message["params"];
+                if let Some(uri) = params["uri"].as_str() {
+                    let items = params["diagnostics"].as_array().cloned().unwrap_or_default();
+                    diagnostics.lock().unwrap().insert(uri.to_string(), items);
+                    published.notify_waiters();
+                }
+            }
+            _ => {}
+        }
+    }
+    // The server is gone: fail whatever is still waiting
+    pending.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_framing() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({ "id": 1, "result": "héllo" })).await.unwrap();
+        assert!(buffer.starts_with(b"Content-Length: 26\r\n\r\n"));
+        let mut reader = BufReader::new(buffer.as_slice());
+        assert_eq!(read_message(&mut reader).await.unwrap().unwrap()["result"], "héllo");
+        assert!(read_message(&mut reader).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,668 @@
+/// Language server bridge tool
+///
+/// Actions: hover, definition, references, rename, diagnostics, servers,
+/// stop, help
+///
+/// Starts rust-analyzer, pyright, typescript-language-server or gopls on
+/// first use for a project root and keeps it running, so later calls get
+/// the server's already-built index. Positions are 1-based lines and
+/// character columns; `symbol` finds the column (and line) when only a name
+/// is known.
+
+use anyhow::Result;
+use crate::config::CodeConfig;
+use crate::error::ToolError;
+use super::lsp_client::{self, LspClient};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// Seconds a single request may take; first requests wait on indexing
+const REQUEST_SECS: u64 = 60;
+
+/// Default wait for a file's first diagnostics
+const DIAGNOSTICS_WAIT_MS: u64 = 5000;
+
+/// Default cap on references returned
+const MAX_RESULTS: usize = 100;
+
+/// Server family, language id and project markers per extension
+const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("rs", "rust", "rust"),
+    ("py", "python", "python"),
+    ("pyi", "python", "python"),
+    ("ts", "typescript", "typescript"),
+    ("mts", "typescript", "typescript"),
+    ("cts", "typescript", "typescript"),
+    ("tsx", "typescript", "typescriptreact"),
+    ("js", "typescript", "javascript"),
+    ("mjs", "typescript", "javascript"),
+    ("cjs", "typescript", "javascript"),
+    ("jsx", "typescript", "javascriptreact"),
+    ("go", "go", "go"),
+];
+
+/// Files marking a project root, per server family
+fn root_markers(family: &str) -> &'static [&'static str] {
+    match family {
+        "rust" => &["Cargo.toml"],
+        "python" => &["pyproject.toml", "setup.py", "setup.cfg", "pyrightconfig.json"],
+        "typescript" => &["tsconfig.json", "jsconfig.json", "package.json"],
+        "go" => &["go.mod"],
+        _ => &[],
+    }
+}
+
+/// Language servers used unless configured otherwise
+pub fn default_servers() -> HashMap<String, Vec<String>> {
+    HashMap::from([
+        ("rust".to_string(), vec!["rust-analyzer".to_string()]),
+        ("python".to_string(), vec!["pyright-langserver".to_string(), "--stdio".to_string()]),
+        ("typescript".to_string(), vec!["typescript-language-server".to_string(), "--stdio".to_string()]),
+        ("go".to_string(), vec!["gopls".to_string()]),
+    ])
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LspAction {
+    Hover,
+    Definition,
+    References,
+    Rename,
+    Diagnostics,
+    Servers,
+    Stop,
+    #[default]
+    Help,
+}
+
+impl std::str::FromStr for LspAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "hover" | "info" => Ok(Self::Hover),
+            "definition" | "goto" | "goto_definition" => Ok(Self::Definition),
+            "references" | "refs" => Ok(Self::References),
+            "rename" => Ok(Self::Rename),
+            "diagnostics" | "check" => Ok(Self::Diagnostics),
+            "servers" | "status" => Ok(Self::Servers),
+            "stop" | "shutdown" => Ok(Self::Stop),
+            "help" | "" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LspToolArgs {
+    pub action: Option<String>,
+    /// File the position is in
+    pub path: Option<String>,
+    /// 1-based line
+    pub line: Option<usize>,
+    /// 1-based character column
+    pub column: Option<usize>,
+    /// Name at the position; finds line and column when they are missing
+    pub symbol: Option<String>,
+    /// New name for rename
+    pub new_name: Option<String>,
+    /// Write rename's edits to the files instead of only returning them
+    #[serde(default)]
+    pub apply: bool,
+    /// Server family (rust, python, typescript, go) when the extension
+    /// doesn't tell
+    pub language: Option<String>,
+    /// Project root; found from the file's nearest manifest by default
+    pub root: Option<String>,
+    /// Include the declaration among references (default true)
+    pub include_declaration: Option<bool>,
+    /// Milliseconds to wait for a file's diagnostics
+    pub wait_ms: Option<u64>,
+    pub max_results: Option<usize>,
+}
+
+pub struct LspToolDefinition;
+
+impl LspToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "lsp",
+            "description": "Language server bridge (rust-analyzer, pyright, typescript-language-server, gopls): hover, definition, references, rename, diagnostics, servers, stop, help. Servers start on first use per project and stay warm",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["hover", "definition", "references", "rename", "diagnostics", "servers", "stop", "help"],
+                        "description": "LSP action"
+                    },
+                    "path": { "type": "string", "description": "Source file" },
+                    "line": { "type": "integer", "minimum": 1, "description": "1-based line of the position" },
+                    "column": { "type": "integer", "minimum": 1, "description": "1-based character column of the position" },
+                    "symbol": { "type": "string", "description": "Name at the position; locates column on line, or its first use in the file without line" },
+                    "new_name": { "type": "string", "description": "For rename: the new name" },
+                    "apply": { "type": "boolean", "description": "For rename: write the edits to disk", "default": false },
+                    "language": { "type": "string", "enum": ["rust", "python", "typescript", "go"], "description": "Language server to use when the extension doesn't identify it" },
+                    "root": { "type": "string", "description": "Project root; defaults to the nearest directory with a manifest (Cargo.toml, pyproject.toml, package.json, go.mod)" },
+                    "include_declaration": { "type": "boolean", "description": "For references: include the declaration", "default": true },
+                    "wait_ms": { "type": "integer", "description": "For diagnostics: how long to wait for the server's report", "default": DIAGNOSTICS_WAIT_MS },
+                    "max_results": { "type": "integer", "default": MAX_RESULTS }
+                },
+                "required": ["action"]
+            }
+        })
+    }
+}
+
+/// A resolved file position
+struct Position {
+    path: PathBuf,
+    uri: String,
+    text: String,
+    /// 0-based line and UTF-16 character, as LSP counts them
+    lsp: Value,
+}
+
+pub struct LspTool {
+    config: CodeConfig,
+    servers: Mutex<HashMap<(String, PathBuf), Arc<LspClient>>>,
+}
+
+impl Default for LspTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LspTool {
+    pub fn new() -> Self {
+        Self::with_config(CodeConfig::default())
+    }
+
+    pub fn with_config(config: CodeConfig) -> Self {
+        Self { config, servers: Mutex::new(HashMap::new()) }
+    }
+
+    pub async fn execute(&self, args: LspToolArgs) -> Result<Value> {
+        let action: LspAction = args.action.as_deref().unwrap_or("help").parse()?;
+
+        let data = match action {
+            LspAction::Hover => self.hover(&args).await?,
+            LspAction::Definition => self.definition(&args).await?,
+            LspAction::References => self.references(&args).await?,
+            LspAction::Rename => self.rename(&args).await?,
+            LspAction::Diagnostics => self.diagnostics(&args).await?,
+            LspAction::Servers => self.list_servers().await,
+            LspAction::Stop => self.stop(&args).await,
+            LspAction::Help => return Ok(self.help()),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "lsp", "action": format!("{:?}", action).to_lowercase() }
+        }))
+    }
+
+    /// Server family and language id for `path`
+    fn language(args: &LspToolArgs, path: &Path) -> Result<(String, &'static str)> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        let detected = LANGUAGES.iter().find(|(ext, _, _)| *ext == extension);
+        match (&args.language, detected) {
+            (Some(language), _) => {
+                let language = language.to_lowercase();
+                let id = LANGUAGES.iter().find(|(_, family, _)| *family == language).map_or("plaintext", |(_, _, id)| *id);
+                Ok((language, detected.map_or(id, |(_, _, id)| *id)))
+            }
+            (None, Some((_, family, id))) => Ok((family.to_string(), *id)),
+            (None, None) => Err(ToolError::invalid(format!(
+                "No language server for {}; set language (rust, python, typescript, go)", path.display()
+            )).into()),
+        }
+    }
+
+    fn root(args: &LspToolArgs, family: &str, path: &Path) -> Result<PathBuf> {
+        if let Some(root) = &args.root {
+            let root = shellexpand::tilde(root).to_string();
+            return std::fs::canonicalize(&root).map_err(|e| ToolError::not_found(format!("{}: {}", root, e)).into());
+        }
+        let start = path.parent().unwrap_or(path);
+        let markers = root_markers(family);
+        let nearest = |markers: &[&str]| start.ancestors().find(|dir| markers.iter().any(|m| dir.join(m).exists())).map(Path::to_path_buf);
+        Ok(nearest(markers).or_else(|| nearest(&[".git"])).unwrap_or_else(|| start.to_path_buf()))
+    }
+
+    /// Running server for `family` at `root`, started if needed
+    async fn server(&self, family: &str, root: &Path) -> Result<Arc<LspClient>> {
+        let mut servers = self.servers.lock().await;
+        let key = (family.to_string(), root.to_path_buf());
+        if let Some(client) = servers.get(&key) {
+            if client.alive().await {
+                return Ok(client.clone());
+            }
+        }
+        let command = self.config.language_servers.get(family)
+            .filter(|command| !command.is_empty())
+            .ok_or_else(|| ToolError::unsupported(format!("No language server configured for {}", family)))?;
+        let client = Arc::new(LspClient::start(command, root, Duration::from_secs(REQUEST_SECS)).await?);
+        servers.insert(key, client.clone());
+        Ok(client)
+    }
+
+    fn file(args: &LspToolArgs) -> Result<PathBuf> {
+        let path = args.path.as_deref().ok_or_else(|| ToolError::invalid("path required"))?;
+        let path = shellexpand::tilde(path).to_string();
+        std::fs::canonicalize(&path).map_err(|e| ToolError::not_found(format!("{}: {}", path, e)).into())
+    }
+
+    /// Open the file in its server and resolve the requested position
+    async fn position(&self, args: &LspToolArgs) -> Result<(Arc<LspClient>, Position)> {
+        let path = Self::file(args)?;
+        let (family, language_id) = Self::language(args, &path)?;
+        let root = Self::root(args, &family, &path)?;
+        let client = self.server(&family, &root).await?;
+        let (uri, text) = client.sync(&path, language_id).await?;
+
+        let (line, column) = locate(&text, args.line, args.column, args.symbol.as_deref())?;
+        let line_text = text.lines().nth(line - 1).unwrap_or("");
+        let character: usize = line_text.chars().take(column - 1).map(char::len_utf16).sum();
+        let lsp = json!({ "line": line - 1, "character": character });
+        Ok((client, Position { path, uri, text, lsp }))
+    }
+
+    async fn hover(&self, args: &LspToolArgs) -> Result<Value> {
+        let (client, position) = self.position(args).await?;
+        let result = client.request("textDocument/hover", json!({
+            "textDocument": { "uri": position.uri },
+            "position": position.lsp
+        })).await?;
+        Ok(json!({
+            "path": position.path,
+            "contents": hover_text(&result["contents"]),
+            "found": !result.is_null()
+        }))
+    }
+
+    async fn definition(&self, args: &LspToolArgs) -> Result<Value> {
+        let (client, position) = self.position(args).await?;
+        let result = client.request("textDocument/definition", json!({
+            "textDocument": { "uri": position.uri },
+            "position": position.lsp
+        })).await?;
+        let locations = locations(&result, &position);
+        Ok(json!({ "path": position.path, "definitions": locations, "count": locations.len() }))
+    }
+
+    async fn references(&self, args: &LspToolArgs) -> Result<Value> {
+        let (client, position) = self.position(args).await?;
+        let result = client.request("textDocument/references", json!({
+            "textDocument": { "uri": position.uri },
+            "position": position.lsp,
+            "context": { "includeDeclaration": args.include_declaration.unwrap_or(true) }
+        })).await?;
+        let mut locations = locations(&result, &position);
+        let total = locations.len();
+        let limit = args.max_results.unwrap_or(MAX_RESULTS);
+        locations.truncate(limit);
+        Ok(json!({
+            "path": position.path,
+            "references": locations,
+            "count": total,
+            "truncated": total > limit
+        }))
+    }
+
+    async fn rename(&self, args: &LspToolArgs) -> Result<Value> {
+        let new_name = args.new_name.as_deref().ok_or_else(|| ToolError::invalid("new_name required"))?;
+        let (client, position) = self.position(args).await?;
+        let result = client.request("textDocument/rename", json!({
+            "textDocument": { "uri": position.uri },
+            "position": position.lsp,
+            "newName": new_name
+        })).await?;
+        if result.is_null() {
+            return Err(ToolError::invalid("Nothing to rename at this position").into());
+        }
+
+        let mut by_file: Vec<(String, Vec<Value>)> = Vec::new();
+        for (uri, edits) in result["changes"].as_object().into_iter().flatten() {
+            by_file.push((uri.clone(), edits.as_array().cloned().unwrap_or_default()));
+        }
+        for change in result["documentChanges"].as_array().into_iter().flatten() {
+            if let Some(uri) = change["textDocument"]["uri"].as_str() {
+                by_file.push((uri.to_string(), change["edits"].as_array().cloned().unwrap_or_default()));
+            }
+        }
+
+        let mut files = Vec::new();
+        let mut total = 0;
+        for (uri, edits) in &by_file {
+            let path = lsp_client::path_for(uri).ok_or_else(|| ToolError::external(format!("Edit for non-file uri {}", uri)))?;
+            let text = tokio::fs::read_to_string(&path).await?;
+            let mut spans = Vec::new();
+            for edit in edits {
+                let start = byte_offset(&text, &edit["range"]["start"]);
+                let end = byte_offset(&text, &edit["range"]["end"]);
+                spans.push((start, end, edit["newText"].as_str().unwrap_or("").to_string()));
+            }
+            spans.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+            let lines: Vec<usize> = edits.iter().map(|e| e["range"]["start"]["line"].as_u64().unwrap_or(0) as usize + 1).collect();
+            if args.apply {
+                let mut updated = text.clone();
+                for (start, end, new_text) in &spans {
+                    updated.replace_range(*start..*end, new_text);
+                }
+                super::fs_atomic::write_atomic(&path.to_string_lossy(), updated.as_bytes(), false).await?;
+            }
+            total += edits.len();
+            files.push(json!({ "path": path, "edits": edits.len(), "lines": lines }));
+        }
+
+        Ok(json!({
+            "path": position.path,
+            "new_name": new_name,
+            "files": files,
+            "edits": total,
+            "applied": args.apply
+        }))
+    }
+
+    async fn diagnostics(&self, args: &LspToolArgs) -> Result<Value> {
+        let path = Self::file(args)?;
+        let (family, language_id) = Self::language(args, &path)?;
+        let root = Self::root(args, &family, &path)?;
+        let client = self.server(&family, &root).await?;
+        let (uri, _) = client.sync(&path, language_id).await?;
+        let wait = Duration::from_millis(args.wait_ms.unwrap_or(DIAGNOSTICS_WAIT_MS));
+
+        let Some(found) = client.diagnostics(&uri, wait).await else {
+            return Ok(json!({
+                "path": path,
+                "diagnostics": [],
+                "count": 0,
+                "complete": false,
+                "message": format!("{} published nothing for this file within {}ms; it may still be indexing", client.command[0], wait.as_millis())
+            }));
+        };
+        let text = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+        let diagnostics: Vec<Value> = found.iter().map(|d| {
+            let (line, column) = line_column(&text, &d["range"]["start"]);
+            json!({
+                "file": path.strip_prefix(&root).unwrap_or(&path),
+                "line": line,
+                "column": column,
+                "severity": match d["severity"].as_u64() {
+                    Some(1) => "error",
+                    Some(2) => "warning",
+                    Some(3) => "info",
+                    Some(4) => "hint",
+                    _ => "error",
+                },
+                "code": match &d["code"] {
+                    Value::Null => Value::Null,
+                    Value::String(code) => json!(code),
+                    other => json!(other.to_string()),
+                },
+                "message": d["message"],
+                "source": d["source"].as_str().unwrap_or(family.as_str())
+            })
+        }).collect();
+        Ok(json!({ "path": path, "diagnostics": diagnostics, "count": diagnostics.len(), "complete": true }))
+    }
+
+    async fn list_servers(&self) -> Value {
+        let servers = self.servers.lock().await;
+        let mut list = Vec::new();
+        for ((language, root), client) in servers.iter() {
+            list.push(json!({
+                "language": language,
+                "root": root,
+                "command": client.command.join(" "),
+                "running": client.alive().await
+            }));
+        }
+        json!({ "servers": list, "count": list.len() })
+    }
+
+    async fn stop(&self, args: &LspToolArgs) -> Value {
+        let root = args.root.as_deref().map(|r| std::fs::canonicalize(shellexpand::tilde(r).as_ref()).unwrap_or_else(|_| PathBuf::from(r)));
+        let mut servers = self.servers.lock().await;
+        let keys: Vec<(String, PathBuf)> = servers.keys()
+            .filter(|(language, server_root)| {
+                args.language.as_ref().is_none_or(|l| l.eq_ignore_ascii_case(language))
+                    && root.as_ref().is_none_or(|r| r == server_root)
+            })
+            .cloned()
+            .collect();
+        let mut stopped = Vec::new();
+        for key in keys {
+            if let Some(client) = servers.remove(&key) {
+                client.stop().await;
+                stopped.push(json!({ "language": key.0, "root": key.1 }));
+            }
+        }
+        json!({ "stopped": stopped, "count": stopped.len() })
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "lsp",
+                "actions": {
+                    "hover": "Type and docs at path:line:column (or symbol)",
+                    "definition": "Where the symbol at the position is defined",
+                    "references": "Every use of the symbol at the position",
+                    "rename": "Rename the symbol at the position across the project (new_name; apply writes the edits)",
+                    "diagnostics": "The server's errors and warnings for path",
+                    "servers": "List running language servers",
+                    "stop": "Stop language servers (optionally by language or root)"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "lsp", "action": "help" }
+        })
+    }
+}
+
+/// 1-based line and column from explicit values or a symbol's position
+fn locate(text: &str, line: Option<usize>, column: Option<usize>, symbol: Option<&str>) -> Result<(usize, usize)> {
+    let find = |line_text: &str, symbol: &str| {
+        let word = regex::Regex::new(&format!(r"\b{}\b", regex::escape(symbol))).ok()?;
+        word.find(line_text).map(|m| line_text[..m.start()].chars().count() + 1)
+    };
+    match (line, column, symbol) {
+        (Some(line), Some(column), _) => Ok((line, column)),
+        (Some(line), None, Some(symbol)) => {
+            let line_text = text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+            let column = find(line_text, symbol)
+                .ok_or_else(|| ToolError::not_found(format!("'{}' is not on line {}", symbol, line)))?;
+            Ok((line, column))
+        }
+        (None, _, Some(symbol)) => text.lines().enumerate()
+            .find_map(|(i, line_text)| find(line_text, symbol).map(|column| (i + 1, column)))
+            .ok_or_else(|| ToolError::not_found(format!("'{}' does not occur in the file", symbol)).into()),
+        _ => Err(ToolError::invalid("line and column (or symbol) required").into()),
+    }
+}
+
+/// 1-based line and character column of an LSP position in `text`
+fn line_column(text: &str, position: &Value) -> (usize, usize) {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let units = position["character"].as_u64().unwrap_or(0) as usize;
+    let line_text = text.lines().nth(line).unwrap_or("");
+    let mut seen = 0;
+    let column = line_text.chars().take_while(|c| {
+        seen += c.len_utf16();
+        seen <= units
+    }).count();
+    (line + 1, column + 1)
+}
+
+/// Byte offset of an LSP position in `text`
+fn byte_offset(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0) as usize;
+    let units = position["character"].as_u64().unwrap_or(0) as usize;
+    let start: usize = text.split_inclusive('\n').take(line).map(str::len).sum();
+    let line_text = text[start.min(text.len())..].split('\n').next().unwrap_or("");
+    let mut seen = 0;
+    let within: usize = line_text.chars().take_while(|c| {
+        seen += c.len_utf16();
+        seen <= units
+    }).map(char::len_utf8).sum();
+    (start + within).min(text.len())
+}
+
+/// Hover contents as plain text, whichever of the LSP shapes it came in
+fn hover_text(contents: &Value) -> String {
+    match contents {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(hover_text).filter(|t| !t.is_empty()).collect::<Vec<_>>().join("\n\n"),
+        Value::Object(part) => match (part.get("language").and_then(Value::as_str), part.get("value").and_then(Value::as_str)) {
+            (Some(language), Some(value)) => format!("```{}\n{}\n```", language, value),
+            (None, Some(value)) => value.to_string(),
+            _ => String::new(),
+        },
+        _ => String::new(),
+    }
+}
+
+/// Location, Location[] or LocationLink[] as file positions with the line's
+/// text
+fn locations(result: &Value, origin: &Position) -> Vec<Value> {
+    let items = match result {
+        Value::Array(items) => items.clone(),
+        Value::Null => Vec::new(),
+        single => vec![single.clone()],
+    };
+    let mut texts: HashMap<String, String> = HashMap::from([(origin.uri.clone(), origin.text.clone())]);
+    items.iter().filter_map(|item| {
+        let uri = item["uri"].as_str().or(item["targetUri"].as_str())?;
+        let range = if item["targetSelectionRange"].is_object() { &item["targetSelectionRange"] } else { &item["range"] };
+        let path = lsp_client::path_for(uri)?;
+        let text = texts.entry(uri.to_string())
+            .or_insert_with(|| std::fs::read_to_string(&path).unwrap_or_default());
+        let (line, column) = line_column(text, &range["start"]);
+        let (end_line, end_column) = line_column(text, &range["end"]);
+        Some(json!({
+            "path": path,
+            "line": line,
+            "column": column,
+            "end_line": end_line,
+            "end_column": end_column,
+            "text": text.lines().nth(line - 1).unwrap_or("").trim()
+        }))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers just enough LSP to exercise the bridge
+    const FAKE_SERVER: &str = r#"
+import json, sys
+def read():
+    length = 0
+    while True:
+        line = sys.stdin.buffer.readline()
+        if not line:
+            sys.exit(0)
+        if line in (b"\r\n", b"\n"):
+            break
+        if line.lower().startswith(b"content-length:"):
+            length = int(line.split(b":")[1])
+    return json.loads(sys.stdin.buffer.read(length))
+def send(message):
+    body = json.dumps(message).encode()
+    sys.stdout.buffer.write(b"Content-Length: %d\r\n\r\n" % len(body) + body)
+    sys.stdout.buffer.flush()
+def span(line, start, end):
+    return {"start": {"line": line, "character": start}, "end": {"line": line, "character": end}}
+while True:
+    msg = read()
+    method, params = msg.get("method"), msg.get("params") or {}
+    if method == "initialize":
+        send({"jsonrpc": "2.0", "id": 99, "method": "workspace/configuration", "params": {"items": [{}]}})
+        send({"jsonrpc": "2.0", "id": msg["id"], "result": {"capabilities": {}}})
+    elif method == "textDocument/didOpen":
+        uri = params["textDocument"]["uri"]
+        send({"jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": {"uri": uri, "diagnostics": [
+            {"range": span(1, 4, 5), "severity": 2, "code": "unused", "message": "unused variable", "source": "fake"}]}})
+    elif method == "textDocument/hover":
+        send({"jsonrpc": "2.0", "id": msg["id"], "result": {"contents": {"kind": "markdown", "value": "fn greet() at %d:%d" % (params["position"]["line"], params["position"]["character"])}}})
+    elif method == "textDocument/definition":
+        uri = params["textDocument"]["uri"]
+        send({"jsonrpc": "2.0", "id": msg["id"], "result": [{"uri": uri, "range": span(0, 3, 8)}]})
+    elif method == "textDocument/rename":
+        uri = params["textDocument"]["uri"]
+        edits = [{"range": span(0, 3, 8), "newText": params["newName"]}, {"range": span(2, 4, 9), "newText": params["newName"]}]
+        send({"jsonrpc": "2.0", "id": msg["id"], "result": {"changes": {uri: edits}}})
+    elif method == "shutdown":
+        send({"jsonrpc": "2.0", "id": msg["id"], "result": None})
+    elif method == "exit":
+        sys.exit(0)
+"#;
+
+    #[tokio::test]
+    async fn test_lsp_bridge() {
+        if which::which("python3").is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let server = dir.path().join("server.py");
+        std::fs::write(&server, FAKE_SERVER).unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        let file = dir.path().join("main.rs");
+        std::fs::write(&file, "fn greet() {}\nfn main() {\n    greet();\n}\n").unwrap();
+
+        let mut config = CodeConfig::default();
+        config.language_servers.insert("rust".into(), vec!["python3".into(), server.to_string_lossy().to_string()]);
+        let tool = LspTool::with_config(config);
+        let args = |action: &str| LspToolArgs {
+            action: Some(action.into()),
+            path: Some(file.to_string_lossy().to_string()),
+            line: Some(3),
+            symbol: Some("greet".into()),
+            ..Default::default()
+        };
+
+        let hover = tool.execute(args("hover")).await.unwrap();
+        assert_eq!(hover["data"]["contents"], "fn greet() at 2:4");
+        let definition = tool.execute(args("definition")).await.unwrap();
+        assert_eq!(definition["data"]["definitions"][0]["line"], 1);
+        assert_eq!(definition["data"]["definitions"][0]["column"], 4);
+        assert_eq!(definition["data"]["definitions"][0]["text"], "fn greet() {}");
+
+        let diagnostics = tool.execute(args("diagnostics")).await.unwrap();
+        assert_eq!(diagnostics["data"]["diagnostics"][0]["severity"], "warning");
+        assert_eq!(diagnostics["data"]["diagnostics"][0]["line"], 2);
+
+        let mut rename = args("rename");
+        rename.new_name = Some("welcome".into());
+        rename.apply = true;
+        let renamed = tool.execute(rename).await.unwrap();
+        assert_eq!(renamed["data"]["edits"], 2);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn welcome() {}\nfn main() {\n    welcome();\n}\n");
+
+        let servers = tool.execute(args("servers")).await.unwrap();
+        assert_eq!(servers["data"]["count"], 1);
+        let stopped = tool.execute(args("stop")).await.unwrap();
+        assert_eq!(stopped["data"]["count"], 1);
+    }
+
+    #[test]
+    fn test_positions() {
+        let text = "let é = 1;\nlet 😀x = 2;\n";
+        assert_eq!(locate(text, None, None, Some("x")).unwrap(), (2, 6));
+        assert_eq!(line_column(text, &json!({ "line": 1, "character": 6 })), (2, 6));
+        assert_eq!(&text[byte_offset(text, &json!({ "line": 1, "character": 6 }))..], "x = 2;\n");
+        assert!(locate(text, None, None, None).is_err());
+    }
+}
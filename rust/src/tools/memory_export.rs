@@ -0,0 +1,346 @@
+/// Memory export/import formats.
+///
+/// A snapshot of memories and knowledge bases can be written as a single
+/// JSON document, as JSONL (one tagged record per line) or as Markdown meant
+/// for people to read and edit. All three formats load back losslessly,
+/// apart from Markdown content lines that look like headings.
+
+use super::memory_tool::{Fact, KnowledgeBase, Memory, MemoryScope, DEFAULT_NAMESPACE};
+use anyhow::Result;
+use crate::error::ToolError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Serialization format for export/import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Jsonl,
+    Markdown,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            "markdown" | "md" => Ok(Self::Markdown),
+            _ => Err(ToolError::invalid(format!("Unknown format: {} (json, jsonl, markdown)", s)).into()),
+        }
+    }
+}
+
+impl ExportFormat {
+    /// Guess the format of exported data
+    pub fn detect(data: &str) -> Self {
+        let trimmed = data.trim_start();
+        if trimmed.starts_with('#') {
+            Self::Markdown
+        } else if serde_json::from_str::<Value>(data).is_ok() {
+            Self::Json
+        } else {
+            Self::Jsonl
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+            Self::Markdown => "markdown",
+        }
+    }
+}
+
+/// Memories and knowledge bases to export or import
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    #[serde(default)]
+    pub memories: Vec<Memory>,
+    #[serde(default)]
+    pub knowledge_bases: Vec<KnowledgeBase>,
+}
+
+fn scope_name(scope: &MemoryScope) -> String {
+    format!("{:?}", scope).to_lowercase()
+}
+
+impl Snapshot {
+    pub fn render(&self, format: ExportFormat) -> Result<String> {
+        match format {
+            ExportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            ExportFormat::Jsonl => self.to_jsonl(),
+            ExportFormat::Markdown => Ok(self.to_markdown()),
+        }
+    }
+
+    pub fn parse(data: &str, format: ExportFormat) -> Result<Self> {
+        match format {
+            ExportFormat::Json => Ok(serde_json::from_str(data)?),
+            ExportFormat::Jsonl => Self::from_jsonl(data),
+            ExportFormat::Markdown => Self::from_markdown(data),
+        }
+    }
+
+    fn to_jsonl(&self) -> Result<String> {
+        let mut out = String::new();
+        let mut push = |kind: &str, value: Value| -> Result<()> {
+            let mut record = value;
+            record["type"] = json!(kind);
+            out.push_str(&serde_json::to_string(&record)?);
+            out.push('\n');
+            Ok(())
+        };
+        for memory in &self.memories {
+            push("memory", serde_json::to_value(memory)?)?;
+        }
+        for kb in &self.knowledge_bases {
+            push("kb", json!({
+                "name": kb.name,
+                "description": kb.description,
+                "scope": kb.scope,
+                "created_at": kb.created_at,
+            }))?;
+            for fact in &kb.facts {
+                push("fact", serde_json::to_value(fact)?)?;
+            }
+        }
+        Ok(out)
+    }
+
+    fn from_jsonl(data: &str) -> Result<Self> {
+        let mut snapshot = Self::default();
+        let mut kbs: Vec<KnowledgeBase> = Vec::new();
+        for (i, line) in data.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let record: Value = serde_json::from_str(line)
+                .map_err(|e| ToolError::invalid(format!("line {}: {}", i + 1, e)))?;
+            match record["type"].as_str() {
+                Some("memory") => snapshot.memories.push(serde_json::from_value(record)?),
+                Some("kb") => kbs.push(serde_json::from_value(record)?),
+                Some("fact") => {
+                    let fact: Fact = serde_json::from_value(record)?;
+                    let kb = match kbs.iter().position(|kb| kb.name == fact.kb_name) {
+                        Some(pos) => &mut kbs[pos],
+                        None => {
+                            kbs.push(KnowledgeBase {
+                                name: fact.kb_name.clone(),
+                                scope: fact.scope.clone(),
+                                created_at: fact.created_at.clone(),
+                                ..Default::default()
+                            });
+                            kbs.last_mut().expect("just pushed")
+                        }
+                    };
+                    kb.facts.push(fact);
+                }
+                other => return Err(ToolError::invalid(format!("line {}: unknown record type {:?}", i + 1, other)).into()),
+            }
+        }
+        snapshot.knowledge_bases = kbs;
+        Ok(snapshot)
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut out = String::from("# Hanzo MCP Memory Export\n\n");
+        if !self.memories.is_empty() {
+            out.push_str("## Memories\n");
+            for m in &self.memories {
+                out.push_str(&format!("\n### {}\n\n", m.id));
+                out.push_str(&format!("- scope: {}\n", scope_name(&m.scope)));
+                out.push_str(&format!("- namespace: {}\n", m.namespace));
+                if !m.tags.is_empty() {
+                    out.push_str(&format!("- tags: {}\n", m.tags.join(", ")));
+                }
+                out.push_str(&format!("- created_at: {}\n", m.created_at));
+                out.push_str(&format!("- updated_at: {}\n", m.updated_at));
+                if let Some(expires_at) = &m.expires_at {
+                    out.push_str(&format!("- expires_at: {}\n", expires_at));
+                }
+                if let Some(session_id) = &m.session_id {
+                    out.push_str(&format!("- session_id: {}\n", session_id));
+                }
+                if let Some(project) = &m.project {
+                    out.push_str(&format!("- project: {}\n", project));
+                }
+                if !m.metadata.is_empty() {
+                    out.push_str(&format!("- metadata: {}\n", json!(m.metadata)));
+                }
+                out.push_str(&format!("\n{}\n", m.content.trim_end()));
+            }
+        }
+        for kb in &self.knowledge_bases {
+            out.push_str(&format!("\n## Knowledge Base: {}\n\n", kb.name));
+            out.push_str(&format!("- scope: {}\n", scope_name(&kb.scope)));
+            out.push_str(&format!("- created_at: {}\n", kb.created_at));
+            if let Some(description) = &kb.description {
+                out.push_str(&format!("- description: {}\n", description));
+            }
+            out.push('\n');
+            for fact in &kb.facts {
+                out.push_str(&format!("- [{}] {}\n", fact.id, fact.content.replace('\n', " ")));
+            }
+        }
+        out
+    }
+
+    fn from_markdown(data: &str) -> Result<Self> {
+        enum Section {
+            None,
+            Memories,
+            Memory { fields: HashMap<String, String>, body: Vec<String> },
+            Kb,
+        }
+
+        fn finish_memory(section: Section, memories: &mut Vec<Memory>) -> Result<()> {
+            let Section::Memory { fields, body } = section else { return Ok(()) };
+            let field = |key: &str| fields.get(key).cloned();
+            let now = chrono::Utc::now().to_rfc3339();
+            memories.push(Memory {
+                id: field("id").unwrap_or_default(),
+                content: body.join("\n").trim().to_string(),
+                scope: field("scope").unwrap_or_default().parse()?,
+                created_at: field("created_at").unwrap_or_else(|| now.clone()),
+                updated_at: field("updated_at").unwrap_or(now),
+                metadata: match field("metadata") {
+                    Some(raw) => serde_json::from_str(&raw)?,
+                    None => HashMap::new(),
+                },
+                namespace: field("namespace").unwrap_or_else(|| DEFAULT_NAMESPACE.to_string()),
+                tags: field("tags")
+                    .map(|t| t.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect())
+                    .unwrap_or_default(),
+                expires_at: field("expires_at"),
+                superseded_by: None,
+                session_id: field("session_id"),
+                project: field("project"),
+            });
+            Ok(())
+        }
+
+        let mut snapshot = Self::default();
+        let mut section = Section::None;
+        for line in data.lines() {
+            if let Some(name) = line.strip_prefix("## Knowledge Base: ") {
+                finish_memory(std::mem::replace(&mut section, Section::Kb), &mut snapshot.memories)?;
+                snapshot.knowledge_bases.push(KnowledgeBase {
+                    name: name.trim().to_string(),
+                    ..Default::default()
+                });
+                continue;
+            }
+            if line.trim() == "## Memories" {
+                finish_memory(std::mem::replace(&mut section, Section::Memories), &mut snapshot.memories)?;
+                continue;
+            }
+            if let Some(id) = line.strip_prefix("### ") {
+                if matches!(section, Section::Memories | Section::Memory { .. }) {
+                    finish_memory(std::mem::replace(&mut section, Section::Memories), &mut snapshot.memories)?;
+                    let mut fields = HashMap::new();
+                    fields.insert("id".to_string(), id.trim().to_string());
+                    section = Section::Memory { fields, body: Vec::new() };
+                    continue;
+                }
+            }
+            match &mut section {
+                Section::Memory { fields, body } => {
+                    // Field list comes first; everything after it is content
+                    let field = line.strip_prefix("- ").and_then(|l| l.split_once(": "));
+                    match field {
+                        Some((key, value)) if body.is_empty() => {
+                            fields.insert(key.trim().to_string(), value.trim().to_string());
+                        }
+                        _ if body.is_empty() && line.trim().is_empty() => {}
+                        _ => body.push(line.to_string()),
+                    }
+                }
+                Section::Kb => {
+                    let kb = snapshot.knowledge_bases.last_mut().expect("kb section has a kb");
+                    let Some(item) = line.strip_prefix("- ") else { continue };
+                    if let Some(rest) = item.strip_prefix('[') {
+                        let (id, content) = rest.split_once("] ").unwrap_or(("", rest));
+                        kb.facts.push(Fact {
+                            id: id.to_string(),
+                            content: content.trim().to_string(),
+                            kb_name: kb.name.clone(),
+                            scope: kb.scope.clone(),
+                            created_at: kb.created_at.clone(),
+                            superseded_by: None,
+                        });
+                    } else if let Some((key, value)) = item.split_once(": ") {
+                        match key {
+                            "scope" => kb.scope = value.trim().parse()?,
+                            "created_at" => kb.created_at = value.trim().to_string(),
+                            "description" => kb.description = Some(value.trim().to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+                Section::None | Section::Memories => {}
+            }
+        }
+        finish_memory(section, &mut snapshot.memories)?;
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Snapshot {
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), json!("review"));
+        Snapshot {
+            memories: vec![Memory {
+                id: "mem_1".to_string(),
+                content: "Prefers tabs\nover spaces".to_string(),
+                scope: MemoryScope::Global,
+                created_at: "2024-01-01T00:00:00+00:00".to_string(),
+                updated_at: "2024-01-02T00:00:00+00:00".to_string(),
+                metadata,
+                namespace: "style".to_string(),
+                tags: vec!["editor".to_string()],
+                expires_at: None,
+                superseded_by: None,
+                session_id: None,
+                project: None,
+            }],
+            knowledge_bases: vec![KnowledgeBase {
+                name: "coding".to_string(),
+                description: Some("Team conventions".to_string()),
+                scope: MemoryScope::Project,
+                facts: vec![Fact {
+                    id: "fact_1".to_string(),
+                    content: "Use uv for Python".to_string(),
+                    kb_name: "coding".to_string(),
+                    scope: MemoryScope::Project,
+                    created_at: "2024-01-01T00:00:00+00:00".to_string(),
+                    superseded_by: None,
+                }],
+                created_at: "2024-01-01T00:00:00+00:00".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_round_trip_all_formats() {
+        for format in [ExportFormat::Json, ExportFormat::Jsonl, ExportFormat::Markdown] {
+            let data = sample().render(format).unwrap();
+            assert_eq!(ExportFormat::detect(&data), format);
+            let parsed = Snapshot::parse(&data, format).unwrap();
+            let memory = &parsed.memories[0];
+            assert_eq!(memory.content, "Prefers tabs\nover spaces", "{:?}", format);
+            assert_eq!(memory.scope, MemoryScope::Global);
+            assert_eq!(memory.namespace, "style");
+            assert_eq!(memory.tags, vec!["editor".to_string()]);
+            assert_eq!(memory.metadata["source"], "review");
+            let kb = &parsed.knowledge_bases[0];
+            assert_eq!(kb.description.as_deref(), Some("Team conventions"));
+            assert_eq!(kb.facts[0].content, "Use uv for Python");
+            assert_eq!(kb.facts[0].id, "fact_1");
+        }
+    }
+}
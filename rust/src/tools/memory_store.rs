@@ -0,0 +1,88 @@
+/// Shared store for global-scope memories.
+///
+/// Each hanzo-mcp process (typically one per editor window) keeps its own
+/// in-memory map, but global memories and knowledge bases also live in a
+/// single JSON file in the user data dir. An operation takes an exclusive
+/// lock on a sidecar lock file, reloads the shared entries, runs, and writes
+/// them back before releasing the lock, so every process sees a consistent,
+/// serialized view. The file also carries the id counter, keeping ids unique
+/// across processes.
+
+use super::memory_export::Snapshot;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Contents of the shared store file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SharedState {
+    /// Highest id counter handed out by any process
+    #[serde(default)]
+    pub counter: u64,
+    #[serde(flatten)]
+    pub snapshot: Snapshot,
+}
+
+/// Location of the shared global store
+#[derive(Debug, Clone)]
+pub struct GlobalStore {
+    path: PathBuf,
+}
+
+/// Exclusive hold on the store; released when dropped
+pub struct StoreLock {
+    _file: File,
+    path: PathBuf,
+}
+
+impl GlobalStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Wait for exclusive access to the store
+    pub async fn lock(&self) -> Result<StoreLock> {
+        let path = self.path.clone();
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(path.with_extension("lock"))?;
+            file.lock()?;
+            Ok(StoreLock { _file: file, path })
+        })
+        .await
+        .map_err(|e| anyhow!("Global store lock task failed: {}", e))?
+    }
+}
+
+impl StoreLock {
+    /// Read the shared state; a missing file is an empty store
+    pub fn load(&self) -> Result<SharedState> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(data) if data.trim().is_empty() => Ok(SharedState::default()),
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| anyhow!("Corrupt global memory store {}: {}", self.path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(SharedState::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Replace the shared state, writing through a temp file so readers never
+    /// see a partial store
+    pub fn save(&self, state: &SharedState) -> Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(state)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
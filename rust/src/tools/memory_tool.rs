@@ -7,15 +7,36 @@
 /// - delete: Remove memories
 /// - facts: Manage knowledge base facts
 /// - summarize: Summarize and store information
-
-use anyhow::{anyhow, Result};
+///
+/// Memories carry a namespace and tags; recall and list filter on them, on
+/// metadata and on creation date. Memories may expire (TTL) or be superseded;
+/// a background task prunes both, and `compact` merges near-duplicates.
+///
+/// Session-scoped memories belong to the MCP session that created them and
+/// are only visible to it; they are dropped or archived when it ends.
+///
+/// With a global store attached, global-scope memories and knowledge bases
+/// are shared with every other hanzo-mcp process through a locked file.
+
+use super::memory_export::{ExportFormat, Snapshot};
+use super::memory_store::{GlobalStore, SharedState, StoreLock};
+use anyhow::Result;
+use crate::error::ToolError;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+/// How often the background task prunes expired and superseded memories
+const COMPACTION_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Default word-overlap similarity for `compact` to treat memories as duplicates
+const DEFAULT_SIMILARITY: f64 = 0.8;
+
 /// Memory scope
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -63,6 +84,8 @@ pub enum MemoryAction {
     Merge,
     Tag,
     Untag,
+    Retag,
+    Compact,
     Namespaces,
     History,
     Help,
@@ -94,37 +117,327 @@ impl std::str::FromStr for MemoryAction {
             "merge" => Ok(Self::Merge),
             "tag" => Ok(Self::Tag),
             "untag" => Ok(Self::Untag),
+            "retag" | "rename_tag" => Ok(Self::Retag),
+            "compact" | "prune" => Ok(Self::Compact),
             "namespaces" => Ok(Self::Namespaces),
             "history" => Ok(Self::History),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+/// How import treats a memory whose content already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    Skip,
+    Overwrite,
+    Newer,
+    Duplicate,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "skip" | "keep" => Ok(Self::Skip),
+            "overwrite" | "replace" => Ok(Self::Overwrite),
+            "newer" | "latest" => Ok(Self::Newer),
+            "duplicate" | "keep_both" => Ok(Self::Duplicate),
+            _ => Err(ToolError::invalid(format!("Unknown conflict policy: {} (skip, overwrite, newer, duplicate)", s)).into()),
         }
     }
 }
 
+/// Namespace for memories created without one
+pub const DEFAULT_NAMESPACE: &str = "default";
+
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
 /// A stored memory
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Memory {
+    #[serde(default)]
     pub id: String,
     pub content: String,
+    #[serde(default)]
     pub scope: MemoryScope,
+    #[serde(default)]
     pub created_at: String,
+    #[serde(default)]
     pub updated_at: String,
+    #[serde(default)]
     pub metadata: HashMap<String, Value>,
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Id of the memory that replaced this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
+    /// Owning MCP session for session-scoped memories
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Project root of a project-scoped memory made while a workspace root
+    /// was active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+}
+
+/// Who is looking at memories: the calling session and its project root
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Viewer<'a> {
+    pub session: Option<&'a str>,
+    pub project: Option<&'a str>,
+}
+
+impl<'a> Viewer<'a> {
+    pub fn new(session: &'a Option<String>, project: &'a Option<String>) -> Self {
+        Self { session: session.as_deref(), project: project.as_deref() }
+    }
+}
+
+impl Memory {
+    /// Session memories are private to their session and project memories
+    /// to their project; memories from before projects were tracked, and
+    /// callers without one, see every project's
+    pub fn visible_to(&self, viewer: Viewer) -> bool {
+        match self.scope {
+            MemoryScope::Session => self.session_id.as_deref() == viewer.session,
+            MemoryScope::Project => match (self.project.as_deref(), viewer.project) {
+                (Some(mine), Some(theirs)) => mine == theirs,
+                _ => true,
+            },
+            MemoryScope::Global => true,
+        }
+    }
+
+    /// Not expired and not superseded
+    pub fn is_live(&self, now: DateTime<Utc>) -> bool {
+        self.superseded_by.is_none() && !self.is_expired(now)
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at
+            .as_deref()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .is_some_and(|ts| ts.with_timezone(&Utc) <= now)
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "content": self.content,
+            "scope": format!("{:?}", self.scope).to_lowercase(),
+            "namespace": self.namespace,
+            "tags": self.tags,
+            "created_at": self.created_at,
+            "expires_at": self.expires_at
+        })
+    }
+}
+
+/// Look up a memory the caller may modify
+fn visible_mut<'a>(
+    memories: &'a mut HashMap<String, Memory>,
+    id: &str,
+    viewer: Viewer,
+) -> Option<&'a mut Memory> {
+    memories.get_mut(id).filter(|m| m.visible_to(viewer))
+}
+
+/// Owning session for a memory created in `scope`
+fn owner(scope: &MemoryScope, session: &Option<String>) -> Option<String> {
+    if *scope == MemoryScope::Session { session.clone() } else { None }
+}
+
+/// Owning project for a memory created in `scope`
+fn owner_project(scope: &MemoryScope, project: &Option<String>) -> Option<String> {
+    if *scope == MemoryScope::Project { project.clone() } else { None }
+}
+
+/// Remove expired and superseded memories, returning their ids
+fn prune(memories: &mut HashMap<String, Memory>, now: DateTime<Utc>) -> (Vec<String>, Vec<String>) {
+    let mut expired = Vec::new();
+    let mut superseded = Vec::new();
+    memories.retain(|id, m| {
+        if m.superseded_by.is_some() {
+            superseded.push(id.clone());
+            false
+        } else if m.is_expired(now) {
+            expired.push(id.clone());
+            false
+        } else {
+            true
+        }
+    });
+    expired.sort();
+    superseded.sort();
+    (expired, superseded)
+}
+
+fn word_set(text: &str) -> std::collections::HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+static FACT_PATTERN: once_cell::sync::Lazy<regex::Regex> = once_cell::sync::Lazy::new(|| {
+    regex::Regex::new(
+        r"(?i)^(?P<subject>.+?)\s*(?P<verb>\b(?:is not|isn't|is|are not|aren't|are|equals|uses|defaults to|should be|must be)\b|=|:)\s*(?P<value>.+)$",
+    )
+    .expect("valid fact pattern")
+});
+
+/// Split a fact into a (subject + relation) key and its value, e.g.
+/// "The default port is 8080" -> ("the default port is", "8080").
+/// Negated relations share the key of the positive form with a "not " value.
+fn fact_claim(text: &str) -> Option<(String, String)> {
+    let caps = FACT_PATTERN.captures(text.trim())?;
+    let normalize = |s: &str| {
+        s.split_whitespace().collect::<Vec<_>>().join(" ").trim_end_matches('.').to_lowercase()
+    };
+    let verb = caps["verb"].to_lowercase();
+    let (relation, negated) = match verb.as_str() {
+        "is not" | "isn't" | "are not" | "aren't" => ("is", true),
+        "is" | "are" | "equals" | "=" | ":" => ("is", false),
+        "should be" | "must be" => ("should be", false),
+        other => (other, false),
+    };
+    let value = normalize(&caps["value"]);
+    if value.is_empty() {
+        return None;
+    }
+    let value = if negated { format!("not {}", value) } else { value };
+    Some((format!("{} {}", normalize(&caps["subject"]), relation), value))
+}
+
+/// Jaccard similarity of the two texts' word sets
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (word_set(a), word_set(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+/// Lowercase, trim and deduplicate tags
+fn normalize_tags(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut out: Vec<String> = tags
+        .into_iter()
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    out.sort();
+    out.dedup();
+    out
+}
+
+/// Parse an RFC 3339 timestamp or a YYYY-MM-DD date. Dates resolve to the
+/// start of the day, or its last second when `end_of_day` is set.
+fn parse_date(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(value) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| ToolError::invalid(format!("Invalid date (expected RFC 3339 or YYYY-MM-DD): {}", value)))?;
+    let time = if end_of_day { date.and_hms_opt(23, 59, 59) } else { date.and_hms_opt(0, 0, 0) };
+    Ok(time.expect("valid time of day").and_utc())
+}
+
+/// Filters shared by recall and list
+#[derive(Debug, Default)]
+struct MemoryFilter {
+    namespace: Option<String>,
+    tags: Vec<String>,
+    any_tag: bool,
+    metadata: HashMap<String, Value>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl MemoryFilter {
+    fn from_args(args: &MemoryToolArgs) -> Result<Self> {
+        let any_tag = match args.tag_match.as_deref() {
+            None | Some("all") => false,
+            Some("any") => true,
+            Some(other) => return Err(ToolError::invalid(format!("tag_match must be 'all' or 'any', got {}", other)).into()),
+        };
+        Ok(Self {
+            namespace: args.namespace.clone(),
+            tags: normalize_tags(args.tags.clone().unwrap_or_default()),
+            any_tag,
+            metadata: args.where_metadata.clone().unwrap_or_default(),
+            since: args.since.as_deref().map(|s| parse_date(s, false)).transpose()?,
+            until: args.until.as_deref().map(|s| parse_date(s, true)).transpose()?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.namespace.is_none()
+            && self.tags.is_empty()
+            && self.metadata.is_empty()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+
+    fn matches(&self, memory: &Memory) -> bool {
+        if self.namespace.as_ref().is_some_and(|ns| *ns != memory.namespace) {
+            return false;
+        }
+        if !self.tags.is_empty() {
+            let has = |t: &String| memory.tags.contains(t);
+            let ok = if self.any_tag { self.tags.iter().any(has) } else { self.tags.iter().all(has) };
+            if !ok {
+                return false;
+            }
+        }
+        // A matcher equals the stored value, or is an element of a stored array
+        let metadata_ok = self.metadata.iter().all(|(key, expected)| match memory.metadata.get(key) {
+            Some(Value::Array(items)) if !expected.is_array() => items.contains(expected),
+            Some(actual) => actual == expected,
+            None => false,
+        });
+        if !metadata_ok {
+            return false;
+        }
+        if self.since.is_some() || self.until.is_some() {
+            let Ok(created) = DateTime::parse_from_rfc3339(&memory.created_at) else { return false };
+            let created = created.with_timezone(&Utc);
+            if self.since.is_some_and(|since| created < since) || self.until.is_some_and(|until| created > until) {
+                return false;
+            }
+        }
+        true
+    }
 }
 
 /// A fact in a knowledge base
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fact {
+    #[serde(default)]
     pub id: String,
     pub content: String,
+    #[serde(default)]
     pub kb_name: String,
+    #[serde(default)]
     pub scope: MemoryScope,
+    #[serde(default)]
     pub created_at: String,
+    /// Id of the fact that replaced this one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub superseded_by: Option<String>,
 }
 
 /// Knowledge base
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct KnowledgeBase {
     pub name: String,
     pub description: Option<String>,
@@ -170,12 +483,52 @@ pub struct MemoryToolArgs {
     pub creations: Option<Vec<String>>,
     /// Deletions for manage
     pub deletions: Option<Vec<String>>,
-    /// Tag name for tag/untag
+    /// Tag name for tag/untag, or the tag to rename for retag
     pub tag: Option<String>,
+    /// Tags to attach on create, add/remove in bulk, or filter by
+    pub tags: Option<Vec<String>>,
+    /// Whether tag filters require all tags (default) or any
+    pub tag_match: Option<String>,
+    /// New tag name for retag
+    pub new_tag: Option<String>,
+    /// Namespace to store into or filter by
+    pub namespace: Option<String>,
+    /// Metadata matchers for recall/list
+    pub where_metadata: Option<HashMap<String, Value>>,
+    /// Only memories created at or after this date
+    pub since: Option<String>,
+    /// Only memories created at or before this date
+    pub until: Option<String>,
+    /// Seconds until created memories expire
+    pub ttl_seconds: Option<u64>,
+    /// Absolute expiry for created memories (RFC 3339 or YYYY-MM-DD)
+    pub expires_at: Option<String>,
+    /// Memory ids replaced by the created memory
+    pub supersedes: Option<Vec<String>>,
+    /// Similarity threshold (0-1) for compact and fact deduplication
+    pub threshold: Option<f64>,
+    /// Let new facts supersede duplicate or contradicting older facts
+    pub auto_supersede: Option<bool>,
+    /// Export/import format: json, jsonl or markdown
+    pub format: Option<String>,
+    /// File to export to or import from
+    pub path: Option<String>,
+    /// Remap scopes on import, e.g. {"session": "project"}
+    pub scope_map: Option<HashMap<String, String>>,
+    /// Import conflict policy: skip, overwrite, newer or duplicate
+    pub on_conflict: Option<String>,
+    /// MCP session making the call, set by the server rather than the client
+    #[serde(skip)]
+    pub session_id: Option<String>,
+    /// Active workspace root of the caller, set by the server
+    #[serde(skip)]
+    pub project: Option<String>,
     /// JSON data for import
     pub data: Option<String>,
 }
 
+
+
 /// Memory tool
 pub struct MemoryTool {
     memories: Arc<RwLock<HashMap<String, Memory>>>,
@@ -183,6 +536,7 @@ pub struct MemoryTool {
     counter: Arc<RwLock<u64>>,
     history: Arc<RwLock<Vec<String>>>,
     storage_path: PathBuf,
+    global_store: Option<GlobalStore>,
 }
 
 impl MemoryTool {
@@ -192,13 +546,83 @@ impl MemoryTool {
             .join("hanzo-mcp")
             .join("memory");
 
-        Self {
+        let tool = Self {
             memories: Arc::new(RwLock::new(HashMap::new())),
             knowledge_bases: Arc::new(RwLock::new(HashMap::new())),
             counter: Arc::new(RwLock::new(0)),
             history: Arc::new(RwLock::new(Vec::new())),
             storage_path,
+            global_store: None,
+        };
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(compaction_loop(Arc::downgrade(&tool.memories), COMPACTION_INTERVAL));
         }
+        tool
+    }
+
+    /// Memory tool sharing global memories through the store in the user data dir
+    pub fn shared() -> Self {
+        let tool = Self::new();
+        let path = tool.storage_path.join("global.json");
+        tool.with_global_store(path)
+    }
+
+    /// Share global-scope memories with other processes through `path`
+    pub fn with_global_store(mut self, path: impl Into<PathBuf>) -> Self {
+        self.global_store = Some(GlobalStore::new(path));
+        self
+    }
+
+    /// Replace local copies of global memories and knowledge bases with the
+    /// shared ones, returning what was loaded so changes can be detected
+    async fn pull_global(&self, lock: &StoreLock) -> Result<Value> {
+        let state = lock.load()?;
+        let loaded = serde_json::to_value(&state)?;
+        let mut counter = self.counter.write().await;
+        *counter = (*counter).max(state.counter);
+        let mut memories = self.memories.write().await;
+        memories.retain(|_, m| m.scope != MemoryScope::Global);
+        memories.extend(state.snapshot.memories.into_iter().map(|m| (m.id.clone(), m)));
+        let mut kbs = self.knowledge_bases.write().await;
+        kbs.retain(|_, kb| kb.scope != MemoryScope::Global);
+        kbs.extend(state.snapshot.knowledge_bases.into_iter().map(|kb| (kb.name.clone(), kb)));
+        Ok(loaded)
+    }
+
+    /// Write global memories and knowledge bases back if they changed
+    async fn push_global(&self, lock: &StoreLock, loaded: Value) -> Result<()> {
+        let mut snapshot = Snapshot {
+            memories: self.memories.read().await.values()
+                .filter(|m| m.scope == MemoryScope::Global)
+                .cloned()
+                .collect(),
+            knowledge_bases: self.knowledge_bases.read().await.values()
+                .filter(|kb| kb.scope == MemoryScope::Global)
+                .cloned()
+                .collect(),
+        };
+        snapshot.memories.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        snapshot.knowledge_bases.sort_by(|a, b| a.name.cmp(&b.name));
+        // The counter is saved even when only local memories were created,
+        // so no other process can reuse their ids for a global memory
+        let state = SharedState { counter: *self.counter.read().await, snapshot };
+        if serde_json::to_value(&state)? != loaded {
+            lock.save(&state)?;
+        }
+        Ok(())
+    }
+
+    /// Expiry for memories created with these args
+    fn expiry(args: &MemoryToolArgs) -> Result<Option<String>> {
+        if let Some(at) = &args.expires_at {
+            return Ok(Some(parse_date(at, false)?.to_rfc3339()));
+        }
+        let Some(ttl) = args.ttl_seconds else { return Ok(None) };
+        let expires = i64::try_from(ttl).ok()
+            .and_then(chrono::Duration::try_seconds)
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .ok_or_else(|| ToolError::invalid(format!("ttl_seconds is too large: {}", ttl)))?;
+        Ok(Some(expires.to_rfc3339()))
     }
 
     async fn next_id(&self, prefix: &str) -> String {
@@ -207,6 +631,15 @@ impl MemoryTool {
         format!("{}_{}", prefix, *counter)
     }
 
+    /// Ids of the live memories `viewer` can see
+    pub async fn ids(&self, viewer: Viewer<'_>) -> Vec<String> {
+        let now = Utc::now();
+        self.memories.read().await.values()
+            .filter(|m| m.is_live(now) && m.visible_to(viewer))
+            .map(|m| m.id.clone())
+            .collect()
+    }
+
     pub async fn execute(&self, args: MemoryToolArgs) -> Result<String> {
         let action: MemoryAction = if args.action.is_empty() {
             MemoryAction::Help
@@ -214,6 +647,15 @@ impl MemoryTool {
             args.action.parse()?
         };
 
+        let global = match &self.global_store {
+            Some(store) => {
+                let lock = store.lock().await?;
+                let loaded = self.pull_global(&lock).await?;
+                Some((lock, loaded))
+            }
+            None => None,
+        };
+
         let result = match action {
             MemoryAction::Recall => self.recall(args).await?,
             MemoryAction::Create => self.create(args).await?,
@@ -225,45 +667,58 @@ impl MemoryTool {
             MemoryAction::List => self.list(args).await?,
             MemoryAction::Stats => self.stats(args).await?,
             MemoryAction::Clear => self.clear(args).await?,
-            MemoryAction::Export => self.export_memories().await?,
+            MemoryAction::Export => self.export_memories(args).await?,
             MemoryAction::Import => self.import_memories(args).await?,
             MemoryAction::Merge => self.merge_memories().await?,
             MemoryAction::Tag => self.tag_memory(args).await?,
             MemoryAction::Untag => self.untag_memory(args).await?,
+            MemoryAction::Retag => self.retag(args).await?,
+            MemoryAction::Compact => self.compact(args).await?,
             MemoryAction::Namespaces => self.namespaces().await?,
             MemoryAction::History => self.history_log().await?,
             MemoryAction::Help => self.help()?,
         };
 
+        if let Some((lock, loaded)) = global {
+            self.push_global(&lock, loaded).await?;
+        }
+
         Ok(serde_json::to_string(&result)?)
     }
 
     async fn recall(&self, args: MemoryToolArgs) -> Result<Value> {
-        let queries = args.queries.or_else(|| args.query.map(|q| vec![q]))
-            .ok_or_else(|| anyhow!("queries required"))?;
+        let filter = MemoryFilter::from_args(&args)?;
+        let queries = match args.queries.or_else(|| args.query.map(|q| vec![q])) {
+            Some(queries) => queries,
+            // Filters alone are enough to recall; match every content
+            None if !filter.is_empty() => vec![String::new()],
+            None => return Err(ToolError::invalid("queries required").into()),
+        };
         let scope: MemoryScope = args.scope.as_deref().unwrap_or("project").parse()?;
         let limit = args.limit.unwrap_or(10);
+        let now = Utc::now();
+        let viewer = Viewer::new(&args.session_id, &args.project);
 
         let memories = self.memories.read().await;
         let mut results = Vec::new();
 
         for query in &queries {
             let query_lower = query.to_lowercase();
-            let matches: Vec<&Memory> = memories.values()
+            let mut matches: Vec<&Memory> = memories.values()
                 .filter(|m| {
-                    m.scope == scope && m.content.to_lowercase().contains(&query_lower)
+                    m.scope == scope
+                        && m.visible_to(viewer)
+                        && m.is_live(now)
+                        && m.content.to_lowercase().contains(&query_lower)
+                        && filter.matches(m)
                 })
-                .take(limit)
                 .collect();
+            matches.sort_by(|a, b| b.created_at.cmp(&a.created_at));
 
-            for m in matches {
-                results.push(json!({
-                    "id": m.id,
-                    "content": m.content,
-                    "scope": format!("{:?}", m.scope).to_lowercase(),
-                    "created_at": m.created_at,
-                    "relevance": 1.0 // Simplified - would use vector similarity in real impl
-                }));
+            for m in matches.into_iter().take(limit) {
+                let mut item = m.to_json();
+                item["relevance"] = json!(1.0); // Simplified - would use vector similarity in real impl
+                results.push(item);
             }
         }
 
@@ -276,13 +731,21 @@ impl MemoryTool {
     }
 
     async fn create(&self, args: MemoryToolArgs) -> Result<Value> {
+        let expires_at = Self::expiry(&args)?;
         let statements = args.statements.or_else(|| args.statement.map(|s| vec![s]))
-            .ok_or_else(|| anyhow!("statements required"))?;
+            .ok_or_else(|| ToolError::invalid("statements required"))?;
         let scope: MemoryScope = args.scope.as_deref().unwrap_or("project").parse()?;
+        let namespace = args.namespace.clone().unwrap_or_else(default_namespace);
+        let tags = normalize_tags(args.tags.clone().unwrap_or_default());
         let now = chrono::Utc::now().to_rfc3339();
 
         let mut created_ids = Vec::new();
         let mut memories = self.memories.write().await;
+        let supersedes = args.supersedes.clone().unwrap_or_default();
+        let viewer = Viewer::new(&args.session_id, &args.project);
+        if let Some(missing) = supersedes.iter().find(|id| !memories.get(*id).is_some_and(|m| m.visible_to(viewer))) {
+            return Err(ToolError::not_found(format!("Memory not found: {}", missing)).into());
+        }
 
         for statement in statements {
             let id = self.next_id("mem").await;
@@ -293,20 +756,38 @@ impl MemoryTool {
                 created_at: now.clone(),
                 updated_at: now.clone(),
                 metadata: args.metadata.clone().unwrap_or_default(),
+                namespace: namespace.clone(),
+                tags: tags.clone(),
+                expires_at: expires_at.clone(),
+                superseded_by: None,
+                session_id: owner(&scope, &args.session_id),
+                project: owner_project(&scope, &args.project),
             };
             memories.insert(id.clone(), memory);
             created_ids.push(id);
         }
 
+        // Superseded memories drop out of recall and are pruned by compaction
+        if let Some(newest) = created_ids.last() {
+            for id in &supersedes {
+                if let Some(old) = memories.get_mut(id) {
+                    old.superseded_by = Some(newest.clone());
+                }
+            }
+        }
+
         Ok(json!({
             "created": created_ids.len(),
             "ids": created_ids,
-            "scope": format!("{:?}", scope).to_lowercase()
+            "superseded": supersedes,
+            "expires_at": expires_at,
+            "scope": format!("{:?}", scope).to_lowercase(),
+            "namespace": namespace
         }))
     }
 
     async fn update(&self, args: MemoryToolArgs) -> Result<Value> {
-        let updates = args.updates.ok_or_else(|| anyhow!("updates required"))?;
+        let updates = args.updates.ok_or_else(|| ToolError::invalid("updates required"))?;
         let now = chrono::Utc::now().to_rfc3339();
 
         let mut updated_ids = Vec::new();
@@ -318,7 +799,7 @@ impl MemoryTool {
                     obj.get("id").and_then(|v| v.as_str()),
                     obj.get("statement").and_then(|v| v.as_str())
                 ) {
-                    if let Some(memory) = memories.get_mut(id) {
+                    if let Some(memory) = visible_mut(&mut memories, id, Viewer::new(&args.session_id, &args.project)) {
                         memory.content = statement.to_string();
                         memory.updated_at = now.clone();
                         updated_ids.push(id.to_string());
@@ -335,13 +816,14 @@ impl MemoryTool {
 
     async fn delete(&self, args: MemoryToolArgs) -> Result<Value> {
         let ids = args.ids.or_else(|| args.id.map(|id| vec![id]))
-            .ok_or_else(|| anyhow!("ids required"))?;
+            .ok_or_else(|| ToolError::invalid("ids required"))?;
 
         let mut deleted_ids = Vec::new();
         let mut memories = self.memories.write().await;
 
         for id in ids {
-            if memories.remove(&id).is_some() {
+            if visible_mut(&mut memories, &id, Viewer::new(&args.session_id, &args.project)).is_some() {
+                memories.remove(&id);
                 deleted_ids.push(id);
             }
         }
@@ -353,6 +835,7 @@ impl MemoryTool {
     }
 
     async fn manage(&self, args: MemoryToolArgs) -> Result<Value> {
+        let expires_at = Self::expiry(&args)?;
         let scope: MemoryScope = args.scope.as_deref().unwrap_or("project").parse()?;
         let now = chrono::Utc::now().to_rfc3339();
 
@@ -372,6 +855,12 @@ impl MemoryTool {
                     created_at: now.clone(),
                     updated_at: now.clone(),
                     metadata: HashMap::new(),
+                    namespace: args.namespace.clone().unwrap_or_else(default_namespace),
+                    tags: normalize_tags(args.tags.clone().unwrap_or_default()),
+                    expires_at: expires_at.clone(),
+                    superseded_by: None,
+                    session_id: owner(&scope, &args.session_id),
+                project: owner_project(&scope, &args.project),
                 };
                 memories.insert(id.clone(), memory);
                 created_ids.push(id);
@@ -387,7 +876,7 @@ impl MemoryTool {
                         obj.get("id").and_then(|v| v.as_str()),
                         obj.get("statement").and_then(|v| v.as_str())
                     ) {
-                        if let Some(memory) = memories.get_mut(id) {
+                        if let Some(memory) = visible_mut(&mut memories, id, Viewer::new(&args.session_id, &args.project)) {
                             memory.content = statement.to_string();
                             memory.updated_at = now.clone();
                             updated_ids.push(id.to_string());
@@ -401,7 +890,8 @@ impl MemoryTool {
         if let Some(deletions) = args.deletions {
             let mut memories = self.memories.write().await;
             for id in deletions {
-                if memories.remove(&id).is_some() {
+                if visible_mut(&mut memories, &id, Viewer::new(&args.session_id, &args.project)).is_some() {
+                    memories.remove(&id);
                     deleted_ids.push(id);
                 }
             }
@@ -431,24 +921,77 @@ impl MemoryTool {
                 created_at: now.clone(),
             });
 
+            let threshold = args.threshold.unwrap_or(DEFAULT_SIMILARITY);
+            let supersede = args.auto_supersede.unwrap_or(false);
             let mut created_ids = Vec::new();
+            let mut duplicates = Vec::new();
+            let mut contradictions = Vec::new();
+            let mut superseded = Vec::new();
             for fact_content in new_facts {
+                let claim = fact_claim(&fact_content);
+                let live = kb.facts.iter().filter(|f| f.superseded_by.is_none());
+
+                // Near-duplicates are not stored again unless they replace the old fact
+                let duplicate = live.clone()
+                    .map(|f| (f, similarity(&f.content, &fact_content)))
+                    .filter(|(_, score)| *score >= threshold)
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((existing, score)) = duplicate {
+                    duplicates.push(json!({
+                        "fact": fact_content,
+                        "existing_id": existing.id,
+                        "existing": existing.content,
+                        "similarity": (score * 100.0).round() / 100.0
+                    }));
+                    if !supersede {
+                        continue;
+                    }
+                }
+
                 let id = self.next_id("fact").await;
-                let fact = Fact {
+                let mut replaced: Vec<String> = duplicate.iter().map(|(f, _)| f.id.clone()).collect();
+                if let Some((key, value)) = &claim {
+                    let mut conflicting = Vec::new();
+                    for existing in live.filter(|f| !replaced.contains(&f.id)) {
+                        let Some((other_key, other_value)) = fact_claim(&existing.content) else { continue };
+                        if other_key == *key && other_value != *value {
+                            contradictions.push(json!({
+                                "fact": fact_content,
+                                "id": id,
+                                "existing_id": existing.id,
+                                "existing": existing.content,
+                                "subject": key
+                            }));
+                            conflicting.push(existing.id.clone());
+                        }
+                    }
+                    replaced.extend(conflicting);
+                }
+                if supersede {
+                    for fact in kb.facts.iter_mut().filter(|f| replaced.contains(&f.id)) {
+                        fact.superseded_by = Some(id.clone());
+                        superseded.push(fact.id.clone());
+                    }
+                }
+
+                kb.facts.push(Fact {
                     id: id.clone(),
                     content: fact_content,
                     kb_name: kb_name.clone(),
                     scope: scope.clone(),
                     created_at: now.clone(),
-                };
-                kb.facts.push(fact);
+                    superseded_by: None,
+                });
                 created_ids.push(id);
             }
 
             return Ok(json!({
                 "stored": created_ids.len(),
                 "ids": created_ids,
-                "kb_name": kb_name
+                "kb_name": kb_name,
+                "duplicates": duplicates,
+                "contradictions": contradictions,
+                "superseded": superseded
             }));
         }
 
@@ -462,6 +1005,7 @@ impl MemoryTool {
                 for query in &queries {
                     let query_lower = query.to_lowercase();
                     let matches: Vec<&Fact> = kb.facts.iter()
+                        .filter(|f| f.superseded_by.is_none())
                         .filter(|f| f.content.to_lowercase().contains(&query_lower))
                         .take(limit)
                         .collect();
@@ -490,7 +1034,7 @@ impl MemoryTool {
             .map(|kb| json!({
                 "name": kb.name,
                 "description": kb.description,
-                "fact_count": kb.facts.len(),
+                "fact_count": kb.facts.iter().filter(|f| f.superseded_by.is_none()).count(),
                 "scope": format!("{:?}", kb.scope).to_lowercase()
             }))
             .collect();
@@ -502,8 +1046,9 @@ impl MemoryTool {
     }
 
     async fn summarize(&self, args: MemoryToolArgs) -> Result<Value> {
-        let content = args.content.ok_or_else(|| anyhow!("content required"))?;
-        let topic = args.topic.ok_or_else(|| anyhow!("topic required"))?;
+        let expires_at = Self::expiry(&args)?;
+        let content = args.content.ok_or_else(|| ToolError::invalid("content required"))?;
+        let topic = args.topic.ok_or_else(|| ToolError::invalid("topic required"))?;
         let scope: MemoryScope = args.scope.as_deref().unwrap_or("project").parse()?;
         let now = chrono::Utc::now().to_rfc3339();
 
@@ -513,6 +1058,8 @@ impl MemoryTool {
         let memory = Memory {
             id: id.clone(),
             content: summary.clone(),
+            session_id: owner(&scope, &args.session_id),
+            project: owner_project(&scope, &args.project),
             scope,
             created_at: now.clone(),
             updated_at: now,
@@ -522,6 +1069,10 @@ impl MemoryTool {
                 m.insert("type".to_string(), json!("summary"));
                 m
             },
+            expires_at,
+            namespace: args.namespace.unwrap_or_else(default_namespace),
+            tags: normalize_tags(args.tags.unwrap_or_default()),
+            superseded_by: None,
         };
 
         self.memories.write().await.insert(id.clone(), memory);
@@ -543,23 +1094,27 @@ impl MemoryTool {
 
     async fn list(&self, args: MemoryToolArgs) -> Result<Value> {
         let scope: Option<MemoryScope> = args.scope.as_deref().map(|s| s.parse().ok()).flatten();
+        let filter = MemoryFilter::from_args(&args)?;
         let limit = args.limit.unwrap_or(50);
 
+        let now = Utc::now();
         let memories = self.memories.read().await;
-        let results: Vec<Value> = memories.values()
+        let mut matching: Vec<&Memory> = memories.values()
+            .filter(|m| m.is_live(now) && m.visible_to(Viewer::new(&args.session_id, &args.project)))
             .filter(|m| scope.as_ref().map_or(true, |s| m.scope == *s))
+            .filter(|m| filter.matches(m))
+            .collect();
+        matching.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        let matched = matching.len();
+        let results: Vec<Value> = matching.into_iter()
             .take(limit)
-            .map(|m| json!({
-                "id": m.id,
-                "content": m.content,
-                "scope": format!("{:?}", m.scope).to_lowercase(),
-                "created_at": m.created_at
-            }))
+            .map(Memory::to_json)
             .collect();
 
         Ok(json!({
             "memories": results,
             "count": results.len(),
+            "matched": matched,
             "total": memories.len()
         }))
     }
@@ -574,12 +1129,14 @@ impl MemoryTool {
         let memories = self.memories.read().await;
         let kbs = self.knowledge_bases.read().await;
         let mut by_scope: HashMap<String, usize> = HashMap::new();
-        for m in memories.values() {
+        let mut total = 0;
+        for m in memories.values().filter(|m| m.visible_to(Viewer::new(&args.session_id, &args.project))) {
             let scope_key = format!("{:?}", m.scope).to_lowercase();
             *by_scope.entry(scope_key).or_insert(0) += 1;
+            total += 1;
         }
         Ok(json!({
-            "total_memories": memories.len(),
+            "total_memories": total,
             "by_scope": by_scope,
             "knowledge_bases": kbs.len(),
             "total_facts": kbs.values().map(|kb| kb.facts.len()).sum::<usize>()
@@ -590,54 +1147,176 @@ impl MemoryTool {
         let scope: Option<MemoryScope> = args.scope.as_deref().map(|s| s.parse().ok()).flatten();
         let mut memories = self.memories.write().await;
         let before = memories.len();
-        if let Some(scope) = scope {
-            memories.retain(|_, m| m.scope != scope);
-        } else {
-            memories.clear();
+        let viewer = Viewer::new(&args.session_id, &args.project);
+        match scope {
+            Some(scope) => memories.retain(|_, m| m.scope != scope || !m.visible_to(viewer)),
+            None => memories.retain(|_, m| !m.visible_to(viewer)),
         }
         let cleared = before - memories.len();
         self.record_history(&format!("clear: removed {} memories", cleared)).await;
         Ok(json!({ "cleared": cleared, "remaining": memories.len() }))
     }
 
-    async fn export_memories(&self) -> Result<Value> {
-        let memories = self.memories.read().await;
-        let kbs = self.knowledge_bases.read().await;
-        let mem_list: Vec<Value> = memories.values().map(|m| json!({
-            "id": m.id, "content": m.content,
-            "scope": format!("{:?}", m.scope).to_lowercase(),
-            "created_at": m.created_at, "updated_at": m.updated_at,
-            "metadata": m.metadata
-        })).collect();
-        let kb_list: Vec<Value> = kbs.values().map(|kb| json!({
-            "name": kb.name, "fact_count": kb.facts.len(),
-            "scope": format!("{:?}", kb.scope).to_lowercase()
-        })).collect();
-        Ok(json!({ "memories": mem_list, "knowledge_bases": kb_list, "count": mem_list.len() }))
+    async fn export_memories(&self, args: MemoryToolArgs) -> Result<Value> {
+        let format: ExportFormat = args.format.as_deref().unwrap_or("json").parse()?;
+        let scope: Option<MemoryScope> = args.scope.as_deref().map(|s| s.parse()).transpose()?;
+        let in_scope = |s: &MemoryScope| scope.as_ref().is_none_or(|want| want == s);
+        let now = Utc::now();
+
+        let mut snapshot = Snapshot::default();
+        {
+            let memories = self.memories.read().await;
+            snapshot.memories = memories.values()
+                .filter(|m| m.is_live(now) && in_scope(&m.scope) && m.visible_to(Viewer::new(&args.session_id, &args.project)))
+                .cloned()
+                .collect();
+            snapshot.memories.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+            let kbs = self.knowledge_bases.read().await;
+            snapshot.knowledge_bases = kbs.values().filter(|kb| in_scope(&kb.scope)).cloned().collect();
+            for kb in &mut snapshot.knowledge_bases {
+                kb.facts.retain(|f| f.superseded_by.is_none());
+            }
+            snapshot.knowledge_bases.sort_by(|a, b| a.name.cmp(&b.name));
+        }
+        let count = snapshot.memories.len();
+        let kb_count = snapshot.knowledge_bases.len();
+
+        if let Some(path) = &args.path {
+            let path = PathBuf::from(shellexpand::tilde(path).as_ref());
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::write(&path, snapshot.render(format)?).await?;
+            self.record_history(&format!("export: {} memories to {}", count, path.display())).await;
+            return Ok(json!({
+                "format": format.as_str(),
+                "path": path,
+                "count": count,
+                "knowledge_bases": kb_count
+            }));
+        }
+        match format {
+            ExportFormat::Json => {
+                let mut out = serde_json::to_value(&snapshot)?;
+                out["format"] = json!(format.as_str());
+                out["count"] = json!(count);
+                Ok(out)
+            }
+            _ => Ok(json!({
+                "format": format.as_str(),
+                "data": snapshot.render(format)?,
+                "count": count,
+                "knowledge_bases": kb_count
+            })),
+        }
     }
 
     async fn import_memories(&self, args: MemoryToolArgs) -> Result<Value> {
-        let data = args.data.ok_or_else(|| anyhow!("data (JSON string) required"))?;
-        let parsed: Value = serde_json::from_str(&data)?;
-        let now = chrono::Utc::now().to_rfc3339();
-        let mut imported = 0;
-        let mut memories = self.memories.write().await;
-        if let Some(items) = parsed.get("memories").and_then(|v| v.as_array()) {
-            for item in items {
-                let id = self.next_id("mem").await;
-                let content = item.get("content").and_then(|v| v.as_str()).unwrap_or("").to_string();
-                let scope: MemoryScope = item.get("scope").and_then(|v| v.as_str()).unwrap_or("project").parse()?;
-                let memory = Memory {
-                    id: id.clone(), content, scope,
-                    created_at: now.clone(), updated_at: now.clone(),
-                    metadata: HashMap::new(),
-                };
-                memories.insert(id, memory);
-                imported += 1;
+        let data = match (&args.data, &args.path) {
+            (Some(data), _) => data.clone(),
+            (None, Some(path)) => tokio::fs::read_to_string(shellexpand::tilde(path).as_ref()).await?,
+            (None, None) => return Err(ToolError::invalid("data or path required").into()),
+        };
+        let format = match args.format.as_deref() {
+            Some(f) => f.parse()?,
+            None => ExportFormat::detect(&data),
+        };
+        let conflict: ConflictPolicy = args.on_conflict.as_deref().unwrap_or("skip").parse()?;
+        let scope_map: HashMap<String, MemoryScope> = args.scope_map.clone().unwrap_or_default()
+            .into_iter()
+            .map(|(from, to)| Ok((from.to_lowercase(), to.parse()?)))
+            .collect::<Result<_>>()?;
+        let map_scope = |scope: MemoryScope| {
+            scope_map.get(&format!("{:?}", scope).to_lowercase()).cloned().unwrap_or(scope)
+        };
+        let snapshot = Snapshot::parse(&data, format)?;
+        let now = Utc::now().to_rfc3339();
+
+        let (mut imported, mut updated, mut skipped) = (0, 0, 0);
+        {
+            let mut memories = self.memories.write().await;
+            for mut incoming in snapshot.memories {
+                incoming.scope = map_scope(incoming.scope);
+                incoming.tags = normalize_tags(std::mem::take(&mut incoming.tags));
+                incoming.superseded_by = None;
+                incoming.session_id = owner(&incoming.scope, &args.session_id);
+                incoming.project = owner_project(&incoming.scope, &args.project);
+                if incoming.created_at.is_empty() {
+                    incoming.created_at = now.clone();
+                }
+                if incoming.updated_at.is_empty() {
+                    incoming.updated_at = incoming.created_at.clone();
+                }
+                // Ids are local to each store, so conflicts are matched on content
+                let existing = memories.values_mut().find(|m| {
+                    m.superseded_by.is_none()
+                        && m.session_id == incoming.session_id
+                        && m.project == incoming.project
+                        && m.content == incoming.content
+                        && m.scope == incoming.scope
+                        && m.namespace == incoming.namespace
+                });
+                match (existing, conflict) {
+                    (Some(_), ConflictPolicy::Skip) => skipped += 1,
+                    (Some(m), ConflictPolicy::Newer) if m.updated_at >= incoming.updated_at => skipped += 1,
+                    (Some(m), ConflictPolicy::Overwrite | ConflictPolicy::Newer) => {
+                        m.tags = incoming.tags;
+                        m.metadata = incoming.metadata;
+                        m.expires_at = incoming.expires_at;
+                        m.updated_at = incoming.updated_at;
+                        updated += 1;
+                    }
+                    (None, _) | (Some(_), ConflictPolicy::Duplicate) => {
+                        incoming.id = self.next_id("mem").await;
+                        memories.insert(incoming.id.clone(), incoming);
+                        imported += 1;
+                    }
+                }
+            }
+        }
+
+        let (mut facts_imported, mut facts_skipped) = (0, 0);
+        {
+            let mut kbs = self.knowledge_bases.write().await;
+            for incoming in snapshot.knowledge_bases {
+                let scope = map_scope(incoming.scope);
+                let kb = kbs.entry(incoming.name.clone()).or_insert_with(|| KnowledgeBase {
+                    name: incoming.name.clone(),
+                    description: incoming.description.clone(),
+                    scope: scope.clone(),
+                    facts: Vec::new(),
+                    created_at: if incoming.created_at.is_empty() { now.clone() } else { incoming.created_at.clone() },
+                });
+                for mut fact in incoming.facts {
+                    if conflict != ConflictPolicy::Duplicate && kb.facts.iter().any(|f| f.content == fact.content) {
+                        facts_skipped += 1;
+                        continue;
+                    }
+                    fact.id = self.next_id("fact").await;
+                    fact.kb_name = kb.name.clone();
+                    fact.scope = kb.scope.clone();
+                    if fact.created_at.is_empty() {
+                        fact.created_at = now.clone();
+                    }
+                    fact.superseded_by = None;
+                    kb.facts.push(fact);
+                    facts_imported += 1;
+                }
             }
         }
-        self.record_history(&format!("import: {} memories", imported)).await;
-        Ok(json!({ "imported": imported }))
+
+        self.record_history(&format!(
+            "import: {} memories ({} updated, {} skipped), {} facts",
+            imported, updated, skipped, facts_imported
+        )).await;
+        Ok(json!({
+            "format": format.as_str(),
+            "imported": imported,
+            "updated": updated,
+            "skipped": skipped,
+            "facts_imported": facts_imported,
+            "facts_skipped": facts_skipped
+        }))
     }
 
     async fn merge_memories(&self) -> Result<Value> {
@@ -663,30 +1342,155 @@ impl MemoryTool {
         Ok(json!({ "merged": merged, "removed_ids": to_remove }))
     }
 
+    /// Ids and tags for tag/untag; both accept a single value or a list
+    fn tag_targets(args: &MemoryToolArgs) -> Result<(Vec<String>, Vec<String>)> {
+        let ids = args.ids.clone().or_else(|| args.id.clone().map(|id| vec![id]))
+            .ok_or_else(|| ToolError::invalid("id or ids required"))?;
+        let tags = normalize_tags(args.tags.clone().unwrap_or_default().into_iter().chain(args.tag.clone()));
+        if tags.is_empty() {
+            return Err(ToolError::invalid("tag or tags required").into());
+        }
+        Ok((ids, tags))
+    }
+
     async fn tag_memory(&self, args: MemoryToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
-        let tag = args.tag.ok_or_else(|| anyhow!("tag required"))?;
+        let (ids, tags) = Self::tag_targets(&args)?;
         let mut memories = self.memories.write().await;
-        let memory = memories.get_mut(&id).ok_or_else(|| anyhow!("Memory not found: {}", id))?;
-        memory.metadata.insert(format!("tag:{}", tag), json!(true));
-        self.record_history(&format!("tag: {} += {}", id, tag)).await;
-        Ok(json!({ "id": id, "tag": tag, "tagged": true }))
+        let mut tagged = Vec::new();
+        for id in &ids {
+            let memory = visible_mut(&mut memories, id, Viewer::new(&args.session_id, &args.project))
+                .ok_or_else(|| ToolError::not_found(format!("Memory not found: {}", id)))?;
+            memory.tags = normalize_tags(memory.tags.drain(..).chain(tags.iter().cloned()));
+            tagged.push(id.clone());
+        }
+        self.record_history(&format!("tag: {} += {}", ids.join(","), tags.join(","))).await;
+        Ok(json!({ "ids": tagged, "tags": tags, "tagged": true }))
     }
 
     async fn untag_memory(&self, args: MemoryToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
-        let tag = args.tag.ok_or_else(|| anyhow!("tag required"))?;
+        let (ids, tags) = Self::tag_targets(&args)?;
+        let mut memories = self.memories.write().await;
+        let mut untagged = Vec::new();
+        for id in &ids {
+            let memory = visible_mut(&mut memories, id, Viewer::new(&args.session_id, &args.project))
+                .ok_or_else(|| ToolError::not_found(format!("Memory not found: {}", id)))?;
+            memory.tags.retain(|t| !tags.contains(t));
+            untagged.push(id.clone());
+        }
+        self.record_history(&format!("untag: {} -= {}", ids.join(","), tags.join(","))).await;
+        Ok(json!({ "ids": untagged, "tags": tags, "untagged": true }))
+    }
+
+    /// Rename a tag on every memory matching the filters
+    async fn retag(&self, args: MemoryToolArgs) -> Result<Value> {
+        let from = args.tag.as_deref().map(|t| t.trim().to_lowercase())
+            .ok_or_else(|| ToolError::invalid("tag required"))?;
+        let to = args.new_tag.as_deref().map(|t| t.trim().to_lowercase())
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| ToolError::invalid("new_tag required"))?;
+        let filter = MemoryFilter::from_args(&args)?;
         let mut memories = self.memories.write().await;
-        let memory = memories.get_mut(&id).ok_or_else(|| anyhow!("Memory not found: {}", id))?;
-        memory.metadata.remove(&format!("tag:{}", tag));
-        self.record_history(&format!("untag: {} -= {}", id, tag)).await;
-        Ok(json!({ "id": id, "tag": tag, "untagged": true }))
+        let mut retagged = Vec::new();
+        for memory in memories.values_mut() {
+            if memory.tags.contains(&from) && memory.visible_to(Viewer::new(&args.session_id, &args.project)) && filter.matches(memory) {
+                memory.tags = normalize_tags(
+                    memory.tags.drain(..).map(|t| if t == from { to.clone() } else { t }),
+                );
+                retagged.push(memory.id.clone());
+            }
+        }
+        retagged.sort();
+        self.record_history(&format!("retag: {} -> {} on {} memories", from, to, retagged.len())).await;
+        Ok(json!({ "from": from, "to": to, "retagged": retagged.len(), "ids": retagged }))
+    }
+
+    /// Merge near-duplicate memories, then prune expired and superseded ones
+    async fn compact(&self, args: MemoryToolArgs) -> Result<Value> {
+        let threshold = args.threshold.unwrap_or(DEFAULT_SIMILARITY);
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(ToolError::invalid("threshold must be between 0 and 1").into());
+        }
+        let now = Utc::now();
+        let mut memories = self.memories.write().await;
+
+        // Newest first, so each group consolidates into its most recent memory
+        let mut live: Vec<Memory> = memories.values().filter(|m| m.is_live(now)).cloned().collect();
+        live.sort_by(|a, b| b.created_at.cmp(&a.created_at).then(a.id.cmp(&b.id)));
+
+        let mut merged = Vec::new();
+        let mut absorbed = std::collections::HashSet::new();
+        for (i, keep) in live.iter().enumerate() {
+            if absorbed.contains(&keep.id) {
+                continue;
+            }
+            let group: Vec<&Memory> = live[i + 1..]
+                .iter()
+                .filter(|m| !absorbed.contains(&m.id))
+                .filter(|m| m.scope == keep.scope && m.namespace == keep.namespace && m.session_id == keep.session_id)
+                .filter(|m| similarity(&keep.content, &m.content) >= threshold)
+                .collect();
+            if group.is_empty() {
+                continue;
+            }
+            let ids: Vec<String> = group.iter().map(|m| m.id.clone()).collect();
+            let mut tags = keep.tags.clone();
+            let mut metadata = HashMap::new();
+            for old in group.iter().rev() {
+                tags.extend(old.tags.iter().cloned());
+                metadata.extend(old.metadata.clone());
+            }
+            metadata.extend(keep.metadata.clone());
+            metadata.insert("merged_from".to_string(), json!(ids));
+            if let Some(survivor) = memories.get_mut(&keep.id) {
+                survivor.tags = normalize_tags(tags);
+                survivor.metadata = metadata;
+                survivor.updated_at = now.to_rfc3339();
+            }
+            for id in &ids {
+                if let Some(old) = memories.get_mut(id) {
+                    old.superseded_by = Some(keep.id.clone());
+                }
+                absorbed.insert(id.clone());
+            }
+            merged.push(json!({ "id": keep.id, "merged_from": ids }));
+        }
+
+        let (expired, superseded) = prune(&mut memories, now);
+        self.record_history(&format!(
+            "compact: {} merged groups, {} expired, {} superseded",
+            merged.len(), expired.len(), superseded.len()
+        )).await;
+        Ok(json!({
+            "threshold": threshold,
+            "merged": merged,
+            "expired": expired,
+            "removed": superseded.len() + expired.len(),
+            "remaining": memories.len()
+        }))
     }
 
     async fn namespaces(&self) -> Result<Value> {
-        let kbs = self.knowledge_bases.read().await;
-        let names: Vec<String> = kbs.keys().cloned().collect();
-        Ok(json!({ "namespaces": names, "count": names.len() }))
+        let memories = self.memories.read().await;
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        let mut tag_counts: HashMap<&str, usize> = HashMap::new();
+        for m in memories.values() {
+            *counts.entry(m.namespace.as_str()).or_insert(0) += 1;
+            for tag in &m.tags {
+                *tag_counts.entry(tag.as_str()).or_insert(0) += 1;
+            }
+        }
+        let mut namespaces: Vec<Value> = counts.iter()
+            .map(|(name, count)| json!({ "name": name, "memories": count }))
+            .collect();
+        namespaces.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        let mut kbs: Vec<String> = self.knowledge_bases.read().await.keys().cloned().collect();
+        kbs.sort();
+        Ok(json!({
+            "namespaces": namespaces,
+            "count": namespaces.len(),
+            "tags": tag_counts,
+            "knowledge_bases": kbs
+        }))
     }
 
     async fn history_log(&self) -> Result<Value> {
@@ -695,6 +1499,40 @@ impl MemoryTool {
         Ok(json!({ "history": entries, "count": entries.len() }))
     }
 
+    /// Drop the memories owned by an ended MCP session, optionally archiving
+    /// them as JSONL under the storage directory first
+    pub async fn end_session(&self, session_id: &str, archive: bool) -> Result<Value> {
+        let mut memories = self.memories.write().await;
+        let mut owned: Vec<Memory> = memories.values()
+            .filter(|m| m.session_id.as_deref() == Some(session_id))
+            .cloned()
+            .collect();
+        owned.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+
+        let mut archived = None;
+        if archive && !owned.is_empty() {
+            let name: String = session_id.chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+                .collect();
+            let path = self.storage_path.join("sessions").join(format!("{}.jsonl", name));
+            let snapshot = Snapshot { memories: owned.clone(), ..Default::default() };
+            tokio::fs::create_dir_all(self.storage_path.join("sessions")).await?;
+            tokio::fs::write(&path, snapshot.render(ExportFormat::Jsonl)?).await?;
+            archived = Some(path.display().to_string());
+        }
+        for m in &owned {
+            memories.remove(&m.id);
+        }
+        drop(memories);
+
+        self.record_history(&format!("end_session: {} dropped {} memories", session_id, owned.len())).await;
+        Ok(json!({
+            "session_id": session_id,
+            "dropped": owned.len(),
+            "archived": archived
+        }))
+    }
+
     fn help(&self) -> Result<Value> {
         Ok(json!({
             "name": "memory",
@@ -706,30 +1544,51 @@ impl MemoryTool {
                 "update": "Update existing memories",
                 "delete": "Remove memories",
                 "manage": "Atomic create/update/delete",
-                "facts": "Manage knowledge base facts",
+                "facts": "Manage knowledge base facts (flags duplicates and contradictions; auto_supersede replaces them)",
                 "summarize": "Summarize and store information",
                 "list": "List all memories",
                 "stats": "Memory statistics by scope",
                 "clear": "Clear memories (optional scope filter)",
-                "export": "Export all memories as JSON",
-                "import": "Import memories from JSON data",
+                "export": "Export memories and knowledge bases (format: json, jsonl, markdown; optional path)",
+                "import": "Import from data or path (scope_map, on_conflict: skip/overwrite/newer/duplicate)",
                 "merge": "Merge duplicate memories",
-                "tag": "Add tag to memory metadata",
-                "untag": "Remove tag from memory metadata",
-                "namespaces": "List knowledge base names",
+                "tag": "Add tags to one or more memories",
+                "untag": "Remove tags from one or more memories",
+                "retag": "Rename a tag across matching memories",
+                "compact": "Merge near-duplicates (threshold) and prune expired/superseded memories",
+                "namespaces": "List memory namespaces, tag counts and knowledge bases",
                 "history": "Recent operation history"
             },
-            "scopes": ["session", "project", "global"]
+            "scopes": ["session", "project", "global"],
+            "filters": {
+                "namespace": "Only memories in this namespace",
+                "tags": "Tags to require (tag_match='all') or accept (tag_match='any')",
+                "where_metadata": "Metadata key/value matchers",
+                "since/until": "Creation date range (RFC 3339 or YYYY-MM-DD)"
+            }
         }))
     }
 }
 
+/// Periodically prune expired and superseded memories until the tool is dropped
+async fn compaction_loop(memories: Weak<RwLock<HashMap<String, Memory>>>, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        let Some(memories) = memories.upgrade() else { break };
+        let (expired, superseded) = prune(&mut *memories.write().await, Utc::now());
+        if !expired.is_empty() || !superseded.is_empty() {
+            log::debug!("memory compaction: {} expired, {} superseded", expired.len(), superseded.len());
+        }
+    }
+}
+
 /// MCP Tool Definition
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MemoryToolDefinition {
     pub name: String,
     pub description: String,
     pub input_schema: Value,
+    pub output_schema: Value,
 }
 
 impl MemoryToolDefinition {
@@ -747,6 +1606,13 @@ Actions:
 - facts: Manage knowledge base facts
 - summarize: Summarize and store information
 - list: List all memories
+- tag / untag / retag: Bulk tag management
+- compact: Merge near-duplicate memories and prune expired ones
+- export / import: JSON, JSONL or Markdown, inline or via path
+
+create accepts ttl_seconds or expires_at, and supersedes to replace older memories.
+
+recall and list filter by namespace, tags, where_metadata and since/until.
 
 Scopes: session, project, global"#.to_string(),
             input_schema: json!({
@@ -754,7 +1620,7 @@ Scopes: session, project, global"#.to_string(),
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["recall", "create", "update", "delete", "manage", "facts", "summarize", "list", "stats", "clear", "export", "import", "merge", "tag", "untag", "namespaces", "history", "help"]
+                        "enum": ["recall", "create", "update", "delete", "manage", "facts", "summarize", "list", "stats", "clear", "export", "import", "merge", "tag", "untag", "retag", "compact", "namespaces", "history", "help"]
                     },
                     "queries": {"type": "array", "items": {"type": "string"}},
                     "query": {"type": "string"},
@@ -771,8 +1637,34 @@ Scopes: session, project, global"#.to_string(),
                     "topic": {"type": "string"},
                     "creations": {"type": "array", "items": {"type": "string"}},
                     "deletions": {"type": "array", "items": {"type": "string"}},
-                    "tag": {"type": "string", "description": "Tag name for tag/untag"},
-                    "data": {"type": "string", "description": "JSON data for import"}
+                    "tag": {"type": "string", "description": "Tag name for tag/untag, or the tag to rename for retag"},
+                    "tags": {"type": "array", "items": {"type": "string"}, "description": "Tags to attach, add/remove, or filter by"},
+                    "tag_match": {"type": "string", "enum": ["all", "any"], "description": "Tag filter mode"},
+                    "new_tag": {"type": "string", "description": "New tag name for retag"},
+                    "namespace": {"type": "string", "description": "Namespace to store into or filter by"},
+                    "where_metadata": {"type": "object", "description": "Metadata key/value matchers for recall/list"},
+                    "since": {"type": "string", "description": "Created at or after (RFC 3339 or YYYY-MM-DD)"},
+                    "until": {"type": "string", "description": "Created at or before (RFC 3339 or YYYY-MM-DD)"},
+                    "ttl_seconds": {"type": "integer", "description": "Seconds until created memories expire"},
+                    "expires_at": {"type": "string", "description": "Expiry for created memories (RFC 3339 or YYYY-MM-DD)"},
+                    "supersedes": {"type": "array", "items": {"type": "string"}, "description": "Memory ids replaced by the created memory"},
+                    "threshold": {"type": "number", "description": "Similarity threshold (0-1) for compact and fact deduplication"},
+                    "auto_supersede": {"type": "boolean", "description": "New facts supersede duplicate or contradicting older facts"},
+                    "data": {"type": "string", "description": "Exported data to import (format detected if omitted)"},
+                    "format": {"type": "string", "enum": ["json", "jsonl", "markdown"], "description": "Export/import format"},
+                    "path": {"type": "string", "description": "File to export to or import from"},
+                    "scope_map": {"type": "object", "additionalProperties": {"type": "string"}, "description": "Remap scopes on import"},
+                    "on_conflict": {"type": "string", "enum": ["skip", "overwrite", "newer", "duplicate"], "description": "Import conflict policy"}
+                }
+            }),
+            output_schema: json!({
+                "type": "object",
+                "description": "Fields depend on the action",
+                "properties": {
+                    "id": {"type": "string"},
+                    "message": {"type": "string"},
+                    "results": {"type": "array", "items": {"type": "object"}, "description": "For recall and list: the memories found"},
+                    "count": {"type": "integer"}
                 }
             }),
         }
@@ -855,4 +1747,12 @@ mod tests {
         let output = result.unwrap();
         assert!(output.contains("API Design"));
     }
+
+    #[test]
+    fn test_fact_claim() {
+        assert_eq!(fact_claim("The default port is 8080."), Some(("the default port is".into(), "8080".into())));
+        assert_eq!(fact_claim("timeout: 30s"), Some(("timeout is".into(), "30s".into())));
+        assert_eq!(fact_claim("Tabs aren't allowed"), Some(("tabs is".into(), "not allowed".into())));
+        assert_eq!(fact_claim("Deploys happen weekly"), None);
+    }
 }
@@ -2,39 +2,103 @@
 ///
 /// 13 unified tools matching TypeScript and Python implementations.
 /// All tools follow the action-routed pattern with unified envelope.
+/// `ToolWrapper` makes any of them an `MCPTool` for embedding (see `builtin`).
 
 pub mod personality;
+pub mod rubric;
+pub mod report;
 pub mod mode_tool;
 pub mod computer_tool;
 pub mod exec_tool;
+pub mod exec_env;
+pub mod exec_ports;
+pub mod exec_service;
+pub mod diff;
+pub mod hash;
+pub mod fs_archive;
+pub mod fs_dedupe;
+pub mod fs_atomic;
+pub mod fs_encoding;
+pub mod fs_glob;
+pub mod fs_journal;
+pub mod fs_links;
+pub mod fs_patch;
+pub mod fs_perms;
+pub mod fs_template;
+pub mod fs_tree;
 pub mod fs_tool;
 pub mod plan_tool;
 pub mod think_tool;
 pub mod memory_tool;
+pub mod memory_export;
+pub mod memory_store;
 pub mod browser_tool;
+pub mod browser_session;
+pub mod calendar_ics;
+pub mod calendar_tool;
 pub mod code_tool;
+pub mod code_format;
+pub mod diagnostics_tool;
+pub mod docker_tool;
+pub mod test_tool;
+pub mod task_tool;
+pub mod lsp_client;
+pub mod lsp_tool;
+pub mod repl_tool;
+pub mod scratch_tool;
+pub mod index_tool;
+pub mod search_repos;
+pub mod search_tool;
 pub mod git_tool;
 pub mod fetch_tool;
+pub mod k8s_tool;
+pub mod notify_tool;
 pub mod workspace_tool;
+pub mod workspace_roots;
+pub mod storage_s3;
+pub mod storage_tool;
+pub mod sysinfo_tool;
 pub mod tasks_tool;
 pub mod hanzo_tool;
+pub mod image_exif;
+pub mod image_tool;
+pub mod vector_tool;
+pub mod builtin;
 
 // Re-export tools — HIP-0300 canonical names
 pub use fs_tool::{FsTool, FsToolArgs, FsToolDefinition};
 pub use exec_tool::{ExecTool, ExecToolArgs, ExecToolDefinition};
 pub use code_tool::{CodeTool, CodeToolArgs, CodeToolDefinition};
+pub use diagnostics_tool::{DiagnosticsTool, DiagnosticsToolArgs, DiagnosticsToolDefinition};
+pub use docker_tool::{DockerTool, DockerToolArgs, DockerToolDefinition};
+pub use test_tool::{TestTool, TestToolArgs, TestToolDefinition};
+pub use task_tool::{TaskTool, TaskToolArgs, TaskToolDefinition};
+pub use lsp_tool::{LspTool, LspToolArgs, LspToolDefinition};
+pub use repl_tool::{ReplTool, ReplToolArgs, ReplToolDefinition};
+pub use scratch_tool::{ScratchTool, ScratchToolArgs, ScratchToolDefinition};
+pub use search_tool::{SearchTool, SearchToolArgs, SearchToolDefinition};
+pub use index_tool::{IndexTool, IndexToolArgs, IndexToolDefinition};
 pub use git_tool::{GitTool, GitToolArgs, GitToolDefinition};
 pub use fetch_tool::{FetchTool, FetchToolArgs, FetchToolDefinition};
+pub use k8s_tool::{K8sTool, K8sToolArgs, K8sToolDefinition};
+pub use notify_tool::{NotifyTool, NotifyToolArgs, NotifyToolDefinition};
 pub use workspace_tool::{WorkspaceTool, WorkspaceToolArgs, WorkspaceToolDefinition};
+pub use workspace_roots::{Root, RootSource, Roots};
 pub use computer_tool::{ComputerTool, ComputerToolArgs, ComputerToolDefinition};
 pub use think_tool::{ThinkTool, ThinkToolArgs, ThinkToolDefinition};
 pub use memory_tool::{MemoryTool, MemoryToolArgs, MemoryToolDefinition};
 pub use hanzo_tool::{HanzoTool, HanzoToolArgs, HanzoToolDefinition};
+pub use image_tool::{ImageTool, ImageToolArgs, ImageToolDefinition};
 pub use plan_tool::{PlanTool, PlanToolArgs, PlanToolDefinition};
+pub use storage_tool::{StorageTool, StorageToolArgs, StorageToolDefinition};
+pub use sysinfo_tool::{SysinfoTool, SysinfoToolArgs, SysinfoToolDefinition};
 pub use tasks_tool::{TasksTool, TasksToolArgs, TasksToolDefinition};
 pub use mode_tool::{ModeTool, ModeToolArgs, ModeToolDefinition};
 pub use browser_tool::{BrowserTool, BrowserToolArgs, BrowserToolDefinition};
+pub use calendar_tool::{CalendarTool, CalendarToolArgs, CalendarToolDefinition};
+pub use vector_tool::{VectorStoreTool, VectorStoreToolArgs, VectorStoreToolDefinition};
 pub use personality::{ToolPersonality, PersonalityRegistry};
+pub use builtin::{BuiltinTool, ComputerControlTool, FileSystemTool, ShellTool};
 
 /// Tool category for organization
 #[derive(Debug, Clone, PartialEq)]
@@ -4,6 +4,7 @@ use crate::tools::personality::{self, ToolPersonality};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use anyhow::Result;
+use crate::error::ToolError;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ModeToolArgs {
@@ -73,7 +74,7 @@ impl ModeTool {
     }
 
     fn activate_mode(&self, name: Option<String>) -> Result<String> {
-        let name = name.ok_or_else(|| anyhow::anyhow!("Mode name required for activate action"))?;
+        let name = name.ok_or_else(|| ToolError::invalid("Mode name required for activate action"))?;
         
         personality::api::set_active(&name)
             .map_err(|e| anyhow::anyhow!("{}", e))?;
@@ -123,10 +124,10 @@ impl ModeTool {
     }
 
     fn show_mode(&self, name: Option<String>) -> Result<String> {
-        let name = name.ok_or_else(|| anyhow::anyhow!("Mode name required for show action"))?;
+        let name = name.ok_or_else(|| ToolError::invalid("Mode name required for show action"))?;
         
         let mode = personality::api::get(&name)
-            .ok_or_else(|| anyhow::anyhow!("Mode '{}' not found", name))?;
+            .ok_or_else(|| ToolError::not_found(format!("Mode '{}' not found", name)))?;
         
         let mut output = vec![format!("Mode: {}", mode.name)];
         output.push(format!("Programmer: {}", mode.programmer));
@@ -190,7 +191,7 @@ impl ModeTool {
     }
 
     fn select_preset(&self, name: Option<String>) -> Result<String> {
-        let name = name.ok_or_else(|| anyhow::anyhow!("Preset name required"))?;
+        let name = name.ok_or_else(|| ToolError::invalid("Preset name required"))?;
         let preset_names = vec![
             "fullstack", "minimal", "data_scientist", "devops", "security",
             "academic", "startup", "enterprise", "creative", "hanzo",
@@ -295,9 +296,9 @@ impl ModeToolDefinition {
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["list", "activate", "show", "current"],
+                        "enum": ["list", "activate", "show", "current", "list_presets", "select_preset"],
                         "default": "list",
-                        "description": "Action to perform"
+                        "description": "Action to perform (switch is an alias of activate)"
                     },
                     "name": {
                         "type": "string",
@@ -0,0 +1,554 @@
+/// Notification tool
+///
+/// Actions: send, watch, channels, help
+///
+/// Lets a long-running agent job tell a human how it went, through the
+/// channels under `[notify.channels]` in the config (see `NotifyConfig`):
+/// SMTP email (sent with curl's SMTP support), Slack incoming webhooks,
+/// generic JSON webhooks and desktop notifications (osascript on macOS,
+/// notify-send on Linux). `watch` waits in the background for an exec
+/// process to exit and sends its outcome then, so a build started with
+/// exec can report back without the agent polling it.
+
+use anyhow::Result;
+use crate::config::{self, NotifyChannel, NotifyConfig};
+use crate::error::ToolError;
+use crate::events;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Longest one delivery may take
+const SEND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest watch waits for a process to exit
+const MAX_WATCH: Duration = Duration::from_secs(24 * 3600);
+
+/// Name of the channel used when none are configured
+const DESKTOP: &str = "desktop";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotifyAction {
+    #[default]
+    Send,
+    Watch,
+    Channels,
+    Help,
+}
+
+impl std::str::FromStr for NotifyAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "send" | "notify" | "alert" | "" => Ok(Self::Send),
+            "watch" | "on_exit" | "when_done" => Ok(Self::Watch),
+            "channels" | "list" => Ok(Self::Channels),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl NotifyAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Send => "send",
+            Self::Watch => "watch",
+            Self::Channels => "channels",
+            Self::Help => "help",
+        }
+    }
+}
+
+/// How a message is marked in channels that show it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    #[default]
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl std::str::FromStr for Level {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "info" | "" => Ok(Self::Info),
+            "success" | "ok" | "done" => Ok(Self::Success),
+            "warning" | "warn" => Ok(Self::Warning),
+            "error" | "failure" | "failed" => Ok(Self::Error),
+            _ => Err(ToolError::invalid(format!("Unknown level: {} (use info, success, warning or error)", s)).into()),
+        }
+    }
+}
+
+impl Level {
+    fn emoji(self) -> &'static str {
+        match self {
+            Self::Info => ":information_source:",
+            Self::Success => ":white_check_mark:",
+            Self::Warning => ":warning:",
+            Self::Error => ":x:",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NotifyToolArgs {
+    pub action: Option<String>,
+    /// Short headline; the email subject
+    pub title: Option<String>,
+    pub message: Option<String>,
+    /// info, success, warning or error
+    pub level: Option<String>,
+    /// Channels to use; the configured defaults when left out
+    pub channels: Option<Vec<String>>,
+    /// For watch: exec process to report on
+    pub proc_id: Option<String>,
+}
+
+/// A message ready to deliver
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub title: String,
+    pub body: String,
+    pub level: Level,
+}
+
+pub struct NotifyToolDefinition;
+
+impl NotifyToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "notify",
+            "description": "Alert a human through configured channels (SMTP email, Slack, webhook, desktop notification): send, watch (notify when an exec proc_id exits), channels, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["send", "watch", "channels", "help"],
+                        "default": "send"
+                    },
+                    "title": { "type": "string", "description": "Headline; the email subject" },
+                    "message": { "type": "string", "description": "Message body" },
+                    "level": { "type": "string", "enum": ["info", "success", "warning", "error"], "default": "info" },
+                    "channels": { "type": "array", "items": { "type": "string" }, "description": "Channel names (default: the configured defaults)" },
+                    "proc_id": { "type": "string", "description": "For watch: exec process whose exit to report" }
+                }
+            }
+        })
+    }
+}
+
+pub struct NotifyTool {
+    config: Arc<NotifyConfig>,
+    http: reqwest::Client,
+}
+
+impl NotifyTool {
+    pub fn new() -> Self {
+        Self::with_config(NotifyConfig::default())
+    }
+
+    pub fn with_config(config: NotifyConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            http: reqwest::Client::builder().timeout(SEND_TIMEOUT).build().unwrap_or_default(),
+        }
+    }
+
+    pub async fn execute(&self, args: NotifyToolArgs) -> Result<Value> {
+        let action: NotifyAction = args.action.as_deref().unwrap_or("send").parse()?;
+        let data = match action {
+            NotifyAction::Send => self.send(&args).await?,
+            NotifyAction::Watch => self.watch(&args)?,
+            NotifyAction::Channels => self.channels(),
+            NotifyAction::Help => return Ok(self.help()),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "notify", "action": action.name() }
+        }))
+    }
+
+    /// Channels a call goes to, by name
+    fn targets(&self, requested: Option<&[String]>) -> Result<Vec<(String, NotifyChannel)>> {
+        if self.config.channels.is_empty() {
+            return match requested {
+                None => Ok(vec![(DESKTOP.to_string(), NotifyChannel::Desktop)]),
+                Some(names) if names.iter().all(|n| n == DESKTOP) => Ok(vec![(DESKTOP.to_string(), NotifyChannel::Desktop)]),
+                Some(_) => Err(ToolError::not_found("No notify channels are configured; only desktop is available").into()),
+            };
+        }
+        let mut names: Vec<String> = match requested {
+            Some(names) => names.to_vec(),
+            None if !self.config.default_channels.is_empty() => self.config.default_channels.clone(),
+            None => self.config.channels.keys().cloned().collect(),
+        };
+        names.sort();
+        names.dedup();
+        names.into_iter()
+            .map(|name| match self.config.channels.get(&name) {
+                Some(channel) => Ok((name, channel.clone())),
+                None => Err(ToolError::not_found(format!("No notify channel {}; notify(action=\"channels\") lists them", name)).into()),
+            })
+            .collect()
+    }
+
+    fn message(args: &NotifyToolArgs) -> Result<Message> {
+        let body = args.message.clone().ok_or_else(|| ToolError::invalid("message required"))?;
+        Ok(Message {
+            title: args.title.clone().unwrap_or_else(|| "hanzo-mcp".to_string()),
+            body,
+            level: args.level.as_deref().unwrap_or("info").parse()?,
+        })
+    }
+
+    async fn send(&self, args: &NotifyToolArgs) -> Result<Value> {
+        let message = Self::message(args)?;
+        let targets = self.targets(args.channels.as_deref())?;
+        let results = deliver_all(&self.http, &targets, &message).await;
+        let failed = results.iter().filter(|r| r["sent"] == false).count();
+        if failed == results.len() {
+            let errors: Vec<String> = results.iter().map(|r| format!("{}: {}", r["channel"].as_str().unwrap_or(""), r["error"].as_str().unwrap_or(""))).collect();
+            return Err(ToolError::external(format!("No notification was delivered: {}", errors.join("; "))).into());
+        }
+        Ok(json!({ "results": results, "sent": results.len() - failed, "failed": failed }))
+    }
+
+    /// Send the outcome of an exec process once it exits
+    fn watch(&self, args: &NotifyToolArgs) -> Result<Value> {
+        let proc_id = args.proc_id.clone().ok_or_else(|| ToolError::invalid("proc_id required"))?;
+        let targets = self.targets(args.channels.as_deref())?;
+        let title = args.title.clone();
+        let note = args.message.clone();
+        let http = self.http.clone();
+        let watched = proc_id.clone();
+        tokio::spawn(async move {
+            // Since id 0, so a process that already exited is reported at once
+            let filter = json!({ "proc_id": watched });
+            let Some(event) = events::bus().wait(Some(events::PROCESS_EXITED), &filter, 0, MAX_WATCH).await else {
+                log::warn!("notify: {} did not exit within {}s", watched, MAX_WATCH.as_secs());
+                return;
+            };
+            let message = exit_message(&event.data, title, note);
+            for result in deliver_all(&http, &targets, &message).await {
+                if result["sent"] == false {
+                    log::warn!("notify: {} for {}: {}", result["channel"], watched, result["error"]);
+                }
+            }
+        });
+        Ok(json!({
+            "watching": proc_id,
+            "channels": self.targets(args.channels.as_deref())?.into_iter().map(|(name, _)| name).collect::<Vec<_>>()
+        }))
+    }
+
+    /// Configured channels, without their secrets
+    fn channels(&self) -> Value {
+        let mut channels: Vec<Value> = self.config.channels.iter().map(|(name, channel)| {
+            let detail = match channel {
+                NotifyChannel::Smtp { url, to, .. } => json!({ "type": "smtp", "server": host(url), "to": to }),
+                NotifyChannel::Slack { webhook_url, webhook_url_env } => {
+                    json!({ "type": "slack", "configured": config::secret(webhook_url, webhook_url_env).is_some() })
+                }
+                NotifyChannel::Webhook { url, url_env, .. } => {
+                    json!({ "type": "webhook", "host": config::secret(url, url_env).as_deref().map(host) })
+                }
+                NotifyChannel::Desktop => json!({ "type": "desktop" }),
+            };
+            let mut entry = json!({ "name": name, "default": self.config.default_channels.contains(name) });
+            if let (Some(entry), Some(detail)) = (entry.as_object_mut(), detail.as_object()) {
+                entry.extend(detail.clone());
+            }
+            entry
+        }).collect();
+        if channels.is_empty() {
+            channels.push(json!({ "name": DESKTOP, "type": "desktop", "default": true }));
+        }
+        channels.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        json!({ "channels": channels, "count": channels.len() })
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "notify",
+                "actions": {
+                    "send": "Send title and message at a level to channels (default: the configured defaults)",
+                    "watch": "Notify when the exec process proc_id exits, with its exit code",
+                    "channels": "Configured channels (secrets are never shown)",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "notify", "action": "help" }
+        })
+    }
+}
+
+impl Default for NotifyTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Message for a `process.exited` event
+fn exit_message(data: &Value, title: Option<String>, note: Option<String>) -> Message {
+    let command = data["command"].as_str().unwrap_or("process");
+    let exit_code = data["exit_code"].as_i64();
+    let level = if exit_code == Some(0) { Level::Success } else { Level::Error };
+    let outcome = match exit_code {
+        Some(0) => "finished".to_string(),
+        Some(code) => format!("failed with exit code {}", code),
+        None => "ended".to_string(),
+    };
+    let mut body = format!("{} ({}) {}", command, data["proc_id"].as_str().unwrap_or(""), outcome);
+    if let Some(note) = note {
+        body = format!("{}\n\n{}", note, body);
+    }
+    Message {
+        title: title.unwrap_or_else(|| format!("Job {}", outcome)),
+        body,
+        level,
+    }
+}
+
+async fn deliver_all(http: &reqwest::Client, targets: &[(String, NotifyChannel)], message: &Message) -> Vec<Value> {
+    let deliveries = targets.iter().map(|(name, channel)| async move {
+        match tokio::time::timeout(SEND_TIMEOUT, deliver(http, channel, message)).await {
+            Ok(Ok(())) => json!({ "channel": name, "sent": true }),
+            Ok(Err(e)) => json!({ "channel": name, "sent": false, "error": e.to_string() }),
+            Err(_) => json!({ "channel": name, "sent": false, "error": format!("timed out after {}s", SEND_TIMEOUT.as_secs()) }),
+        }
+    });
+    futures::future::join_all(deliveries).await
+}
+
+async fn deliver(http: &reqwest::Client, channel: &NotifyChannel, message: &Message) -> Result<()> {
+    match channel {
+        NotifyChannel::Smtp { url, from, to, username, password, password_env, tls } => {
+            let password = config::secret(password, password_env);
+            send_email(url, from, to, username.as_deref(), password.as_deref(), *tls, message).await
+        }
+        NotifyChannel::Slack { webhook_url, webhook_url_env } => {
+            let url = config::secret(webhook_url, webhook_url_env)
+                .ok_or_else(|| ToolError::invalid("Slack channel has no webhook_url"))?;
+            let text = format!("{} *{}*\n{}", message.level.emoji(), message.title, message.body);
+            post(http, &url, &json!({ "text": text }), None).await
+        }
+        NotifyChannel::Webhook { url, url_env, headers } => {
+            let url = config::secret(url, url_env).ok_or_else(|| ToolError::invalid("Webhook channel has no url"))?;
+            let body = json!({
+                "title": message.title,
+                "message": message.body,
+                "level": message.level,
+                "ts": chrono::Utc::now().to_rfc3339(),
+                "source": "hanzo-mcp"
+            });
+            post(http, &url, &body, Some(headers)).await
+        }
+        NotifyChannel::Desktop => desktop(message).await,
+    }
+}
+
+async fn post(http: &reqwest::Client, url: &str, body: &Value, headers: Option<&std::collections::HashMap<String, String>>) -> Result<()> {
+    let mut request = http.post(url).json(body);
+    for (name, value) in headers.into_iter().flatten() {
+        request = request.header(name, value);
+    }
+    // Errors name only the host: webhook URLs carry their own credentials
+    let response = request.send().await
+        .map_err(|e| ToolError::external(format!("POST to {} failed: {}", host(url), e.without_url())))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        return Err(ToolError::external(format!("{} answered {}: {}", host(url), status, text.trim())).into());
+    }
+    Ok(())
+}
+
+/// Host of a URL, for messages that must not show the rest of it
+fn host(url: &str) -> String {
+    url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)).unwrap_or_else(|| "?".to_string())
+}
+
+/// Email through curl, which handles SMTP, TLS and authentication; the
+/// credentials go in a private config file rather than on the command line
+async fn send_email(url: &str, from: &str, to: &[String], username: Option<&str>, password: Option<&str>, tls: bool, message: &Message) -> Result<()> {
+    if to.is_empty() {
+        return Err(ToolError::invalid("SMTP channel has no recipients").into());
+    }
+    let curl = which::which("curl").map_err(|_| ToolError::unsupported("curl is needed to send email and is not on PATH"))?;
+    let mut command = Command::new(curl);
+    command.args(["--silent", "--show-error", "--max-time", &SEND_TIMEOUT.as_secs().to_string(), "--url", url, "--mail-from", from]);
+    for recipient in to {
+        command.args(["--mail-rcpt", recipient]);
+    }
+    if tls && url.starts_with("smtp://") {
+        command.arg("--ssl-reqd");
+    }
+    let credentials = match username {
+        Some(user) => {
+            let path = std::env::temp_dir().join(format!("hanzo-mcp-smtp-{}-{}", std::process::id(), unique_suffix()));
+            let line = format!("user = \"{}\"\n", curl_quote(&format!("{}:{}", user, password.unwrap_or(""))));
+            write_private(&path, &line)?;
+            command.args(["--config", &path.to_string_lossy()]);
+            Some(path)
+        }
+        None => None,
+    };
+    command.args(["--upload-file", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let result = async {
+        let mut child = command.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(email(from, to, message).as_bytes()).await?;
+        }
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(ToolError::external(format!("SMTP via {} failed: {}", host(url), stderr.trim())).into());
+        }
+        Ok(())
+    }.await;
+    if let Some(path) = credentials {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+fn unique_suffix() -> String {
+    format!("{:x}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default())
+}
+
+fn write_private(path: &std::path::Path, content: &str) -> Result<()> {
+    use std::io::Write;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(content.as_bytes())?;
+    Ok(())
+}
+
+/// A value inside double quotes in a curl config file
+fn curl_quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// RFC 5322 message with a UTF-8 plain text body
+fn email(from: &str, to: &[String], message: &Message) -> String {
+    let single_line = |s: &str| s.replace(['\r', '\n'], " ");
+    let subject = single_line(&message.title);
+    let subject = if subject.is_ascii() {
+        subject
+    } else {
+        use base64::Engine as _;
+        format!("=?UTF-8?B?{}?=", base64::engine::general_purpose::STANDARD.encode(subject))
+    };
+    // Lines starting with a dot are doubled only by the SMTP client; curl does it
+    let body = message.body.replace("\r\n", "\n").replace('\n', "\r\n");
+    format!(
+        "From: {}\r\nTo: {}\r\nSubject: {}\r\nDate: {}\r\nMIME-Version: 1.0\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Transfer-Encoding: 8bit\r\nX-Priority: {}\r\n\r\n{}\r\n",
+        single_line(from),
+        to.iter().map(|t| single_line(t)).collect::<Vec<_>>().join(", "),
+        subject,
+        chrono::Utc::now().to_rfc2822(),
+        if message.level == Level::Error { 1 } else { 3 },
+        body
+    )
+}
+
+/// Desktop notification through the platform's own notifier
+async fn desktop(message: &Message) -> Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        let quote = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut command = Command::new("osascript");
+        command.args(["-e", &format!("display notification \"{}\" with title \"{}\"", quote(&message.body), quote(&message.title))]);
+        command
+    } else {
+        let notify_send = which::which("notify-send")
+            .map_err(|_| ToolError::unsupported("Desktop notifications need notify-send (libnotify) on this system"))?;
+        let urgency = match message.level {
+            Level::Error => "critical",
+            Level::Warning => "normal",
+            _ => "low",
+        };
+        let mut command = Command::new(notify_send);
+        command.args(["--app-name", "hanzo-mcp", "--urgency", urgency, &message.title, &message.body]);
+        command
+    };
+    let output = command.kill_on_drop(true).output().await?;
+    if !output.status.success() {
+        return Err(ToolError::external(format!("Desktop notification failed: {}", String::from_utf8_lossy(&output.stderr).trim())).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_email() {
+        let message = Message { title: "Build ✓\r\nBcc: evil@example.com".into(), body: "line 1\n.line 2".into(), level: Level::Error };
+        let text = email("agent@example.com", &["me@example.com".into()], &message);
+        assert!(text.starts_with("From: agent@example.com\r\nTo: me@example.com\r\nSubject: =?UTF-8?B?"));
+        assert!(!text.contains("\r\nBcc:"));
+        assert!(text.contains("X-Priority: 1\r\n"));
+        assert!(text.ends_with("\r\n\r\nline 1\r\n.line 2\r\n"));
+        assert_eq!(curl_quote(r#"u:p"a\b"#), r#"u:p\"a\\b"#);
+    }
+
+    #[tokio::test]
+    async fn test_channels() {
+        let tool = NotifyTool::new();
+        assert!(tool.targets(Some(&["team".to_string()])).is_err());
+        assert_eq!(tool.targets(None).unwrap()[0].0, DESKTOP);
+
+        let mut config = NotifyConfig::default();
+        config.channels.insert("hook".into(), NotifyChannel::Webhook {
+            url: Some("https://hooks.example.com/secret-token".into()),
+            url_env: None,
+            headers: HashMap::new(),
+        });
+        config.channels.insert("team".into(), NotifyChannel::Slack { webhook_url: None, webhook_url_env: None });
+        config.default_channels = vec!["hook".into()];
+        let tool = NotifyTool::with_config(config);
+        assert_eq!(tool.targets(None).unwrap().len(), 1);
+        let listed = tool.channels();
+        assert_eq!(listed["channels"][0]["host"], "hooks.example.com");
+        assert_eq!(listed["channels"][1]["configured"], false);
+        assert!(!listed.to_string().contains("secret-token"));
+
+        let args = NotifyToolArgs { message: Some("done".into()), channels: Some(vec!["team".into()]), ..Default::default() };
+        let err = tool.execute(args).await.unwrap_err();
+        assert!(err.to_string().contains("no webhook_url"));
+
+        let message = exit_message(&json!({ "proc_id": "proc_3", "command": "cargo build", "exit_code": 101 }), None, None);
+        assert_eq!(message.level, Level::Error);
+        assert_eq!(message.body, "cargo build (proc_3) failed with exit code 101");
+    }
+}
@@ -3,13 +3,34 @@
 /// Manages execution plans with step tracking:
 /// - update: Update plan and step status
 /// - get: Get current plan
+/// - critical_path: Longest chain of remaining work
+/// - create/switch/archive/list: Manage named plans
+/// - export: Markdown report of the plan
 /// - clear: Clear plan
+///
+/// Steps may nest (subtasks) and depend on other steps. Status changes are
+/// validated: a step cannot start or complete while its dependencies are
+/// unfinished, and a parent cannot complete before its subtasks.
+///
+/// Plans are persisted per project and exposed as `plan://<name>` resources;
+/// every change is announced on a notification channel.
 
-use anyhow::{anyhow, Result};
+use super::report::Report;
+use super::think_tool::project_key;
+use anyhow::{bail, Result};
+use crate::error::ToolError;
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
+
+/// Name given to a plan created without one
+const DEFAULT_PLAN: &str = "default";
+
+/// URI scheme for plan resources
+const PLAN_URI_PREFIX: &str = "plan://";
 
 /// Step status
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -17,6 +38,7 @@ use tokio::sync::RwLock;
 pub enum StepStatus {
     Pending,
     InProgress,
+    Blocked,
     Completed,
     Failed,
     Skipped,
@@ -35,22 +57,76 @@ impl std::str::FromStr for StepStatus {
         match s.to_lowercase().as_str() {
             "pending" => Ok(Self::Pending),
             "in_progress" | "inprogress" => Ok(Self::InProgress),
+            "blocked" => Ok(Self::Blocked),
             "completed" | "done" => Ok(Self::Completed),
             "failed" | "error" => Ok(Self::Failed),
             "skipped" | "skip" => Ok(Self::Skipped),
-            _ => Err(anyhow!("Unknown status: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown status: {}", s)).into()),
         }
     }
 }
 
+impl StepStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::InProgress => "in_progress",
+            Self::Blocked => "blocked",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+
+    /// Whether dependents may proceed once a step reaches this status
+    pub fn is_done(&self) -> bool {
+        matches!(self, Self::Completed | Self::Skipped)
+    }
+
+    /// Whether a step may move from this status to `to`
+    pub fn can_transition(&self, to: &StepStatus) -> bool {
+        use StepStatus::*;
+        self == to
+            || matches!(
+                (self, to),
+                (Pending, InProgress | Blocked | Completed | Failed | Skipped)
+                    | (Blocked, Pending | InProgress | Skipped)
+                    | (InProgress, Completed | Failed | Blocked | Pending)
+                    | (Failed, Pending | InProgress | Skipped)
+                    | (Completed | Skipped, Pending)
+            )
+    }
+}
+
+/// Steps completed in the serialized plan `new` that were not in `old`
+fn newly_completed<'a>(old: Option<&Value>, new: Option<&'a Value>) -> Vec<&'a Value> {
+    let completed = |plan: Option<&Value>, id: &Value| plan
+        .and_then(|p| p["steps"].as_array())
+        .is_some_and(|steps| steps.iter().any(|s| s["id"] == *id && s["status"] == "completed"));
+    new.and_then(|p| p["steps"].as_array())
+        .into_iter()
+        .flatten()
+        .filter(|step| step["status"] == "completed" && !completed(old, &step["id"]))
+        .collect()
+}
+
 /// A tracked step
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TrackedStep {
     pub id: usize,
     pub description: String,
     pub status: StepStatus,
     pub output: Option<String>,
     pub error: Option<String>,
+    /// Parent step id when this is a subtask
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+    /// Step ids that must be done before this step can start
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<usize>,
+    /// Relative effort used by critical_path (defaults to 1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<f64>,
 }
 
 /// A tracked plan
@@ -60,6 +136,240 @@ pub struct TrackedPlan {
     pub steps: Vec<TrackedStep>,
     pub created_at: Option<String>,
     pub updated_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
+}
+
+impl TrackedPlan {
+    pub fn position(&self, id: usize) -> Option<usize> {
+        self.steps.iter().position(|s| s.id == id)
+    }
+
+    pub fn step(&self, id: usize) -> Option<&TrackedStep> {
+        self.steps.iter().find(|s| s.id == id)
+    }
+
+    fn next_id(&self) -> usize {
+        self.steps.iter().map(|s| s.id).max().unwrap_or(0) + 1
+    }
+
+    /// Resolve a step by stable id, or by 1-based position
+    fn resolve(&self, step_id: Option<usize>, step_index: Option<usize>) -> Result<Option<usize>> {
+        if let Some(id) = step_id {
+            let position = self.position(id).ok_or_else(|| ToolError::not_found(format!("Invalid step id: {}", id)))?;
+            return Ok(Some(position));
+        }
+        match step_index {
+            Some(idx) if idx > 0 && idx <= self.steps.len() => Ok(Some(idx - 1)),
+            Some(idx) => Err(ToolError::invalid(format!("Invalid step index: {}", idx)).into()),
+            None => Ok(None),
+        }
+    }
+
+    fn children(&self, id: usize) -> impl Iterator<Item = &TrackedStep> {
+        self.steps.iter().filter(move |s| s.parent == Some(id))
+    }
+
+    fn descendants(&self, id: usize) -> Vec<usize> {
+        let mut out = Vec::new();
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            for child in self.children(current) {
+                out.push(child.id);
+                stack.push(child.id);
+            }
+        }
+        out
+    }
+
+    fn depth(&self, step: &TrackedStep) -> usize {
+        let mut depth = 0;
+        let mut parent = step.parent;
+        while let Some(id) = parent {
+            depth += 1;
+            parent = self.step(id).and_then(|p| p.parent);
+            if depth > self.steps.len() {
+                break;
+            }
+        }
+        depth
+    }
+
+    /// Dependencies of the step and of its ancestors; a subtask cannot start
+    /// before its parent could
+    fn dependencies(&self, step: &TrackedStep) -> Vec<usize> {
+        let mut ids = step.depends_on.clone();
+        let mut parent = step.parent;
+        let mut hops = 0;
+        while let Some(p) = parent.and_then(|id| self.step(id)) {
+            ids.extend(p.depends_on.iter().copied());
+            parent = p.parent;
+            hops += 1;
+            if hops > self.steps.len() {
+                break;
+            }
+        }
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Steps that must finish before `step` can: its dependencies and its subtasks
+    fn prerequisites(&self, step: &TrackedStep) -> Vec<usize> {
+        let mut ids = self.dependencies(step);
+        ids.extend(self.children(step.id).map(|c| c.id));
+        ids
+    }
+
+    fn unmet_dependencies(&self, step: &TrackedStep) -> Vec<usize> {
+        self.dependencies(step)
+            .into_iter()
+            .filter(|d| self.step(*d).is_none_or(|s| !s.status.is_done()))
+            .collect()
+    }
+
+    /// Pending leaf steps whose dependencies are all done
+    pub fn ready(&self) -> Vec<&TrackedStep> {
+        self.steps
+            .iter()
+            .filter(|s| s.status == StepStatus::Pending)
+            .filter(|s| self.unmet_dependencies(s).is_empty())
+            .filter(|s| self.children(s.id).all(|c| c.status.is_done()))
+            .collect()
+    }
+
+    /// Check ids are unique, references resolve and the graph is acyclic
+    pub fn validate(&self) -> Result<()> {
+        let mut seen = HashSet::new();
+        for step in &self.steps {
+            if !seen.insert(step.id) {
+                bail!("Duplicate step id: {}", step.id);
+            }
+        }
+        for step in &self.steps {
+            if let Some(parent) = step.parent {
+                if parent == step.id || !seen.contains(&parent) {
+                    bail!("Step {} has invalid parent {}", step.id, parent);
+                }
+            }
+            for dep in &step.depends_on {
+                if *dep == step.id || !seen.contains(dep) {
+                    bail!("Step {} has invalid dependency {}", step.id, dep);
+                }
+            }
+        }
+
+        // 1 = on the current DFS path, 2 = fully explored
+        fn visit(plan: &TrackedPlan, id: usize, state: &mut HashMap<usize, u8>) -> Result<()> {
+            match state.get(&id) {
+                Some(1) => bail!("Dependency cycle through step {}", id),
+                Some(_) => return Ok(()),
+                None => {}
+            }
+            state.insert(id, 1);
+            if let Some(step) = plan.step(id) {
+                for pre in plan.prerequisites(step) {
+                    visit(plan, pre, state)?;
+                }
+            }
+            state.insert(id, 2);
+            Ok(())
+        }
+        let mut state = HashMap::new();
+        for step in &self.steps {
+            visit(self, step.id, &mut state)?;
+        }
+        Ok(())
+    }
+
+    /// Move the step at `pos` to `to`, enforcing legal transitions unless
+    /// `force` is set. Returns ids of blocked dependents that became pending.
+    fn set_status(&mut self, pos: usize, to: StepStatus, force: bool) -> Result<Vec<usize>> {
+        let step = &self.steps[pos];
+        let id = step.id;
+        if !force {
+            if !step.status.can_transition(&to) {
+                bail!("Illegal transition for step {}: {} -> {}", id, step.status.as_str(), to.as_str());
+            }
+            if matches!(to, StepStatus::InProgress | StepStatus::Completed) {
+                let unmet = self.unmet_dependencies(step);
+                if !unmet.is_empty() {
+                    bail!("Step {} is blocked by unfinished dependencies: {:?}", id, unmet);
+                }
+            }
+            if to == StepStatus::Completed {
+                let open: Vec<usize> = self.children(id).filter(|c| !c.status.is_done()).map(|c| c.id).collect();
+                if !open.is_empty() {
+                    bail!("Step {} has unfinished subtasks: {:?}", id, open);
+                }
+            }
+        }
+        self.steps[pos].status = to;
+
+        let mut unblocked = Vec::new();
+        if self.steps[pos].status.is_done() {
+            let waiting: Vec<usize> = self
+                .steps
+                .iter()
+                .filter(|s| s.status == StepStatus::Blocked && self.dependencies(s).contains(&id))
+                .filter(|s| self.unmet_dependencies(s).is_empty())
+                .map(|s| s.id)
+                .collect();
+            for dep_id in waiting {
+                if let Some(p) = self.position(dep_id) {
+                    self.steps[p].status = StepStatus::Pending;
+                    unblocked.push(dep_id);
+                }
+            }
+        }
+        Ok(unblocked)
+    }
+
+    /// Longest chain of remaining work through dependencies and subtasks.
+    /// Done steps cost nothing; parents cost nothing beyond their subtasks.
+    pub fn critical_path(&self) -> (Vec<usize>, f64) {
+        fn finish(plan: &TrackedPlan, id: usize, memo: &mut HashMap<usize, (f64, Option<usize>)>) -> f64 {
+            if let Some((f, _)) = memo.get(&id) {
+                return *f;
+            }
+            let Some(step) = plan.step(id) else { return 0.0 };
+            let duration = if step.status.is_done() || plan.children(id).next().is_some() {
+                0.0
+            } else {
+                step.estimate.unwrap_or(1.0)
+            };
+            let mut best: (f64, Option<usize>) = (0.0, None);
+            for pre in plan.prerequisites(step) {
+                let f = finish(plan, pre, memo);
+                if f > best.0 {
+                    best = (f, Some(pre));
+                }
+            }
+            let total = duration + best.0;
+            memo.insert(id, (total, best.1));
+            total
+        }
+
+        let mut memo = HashMap::new();
+        let mut end: Option<(usize, f64)> = None;
+        for step in &self.steps {
+            let f = finish(self, step.id, &mut memo);
+            if f > 0.0 && end.is_none_or(|(_, best)| f > best) {
+                end = Some((step.id, f));
+            }
+        }
+        let Some((end_id, length)) = end else { return (Vec::new(), 0.0) };
+
+        let mut path = vec![end_id];
+        let mut current = end_id;
+        while let Some((_, Some(pre))) = memo.get(&current) {
+            path.push(*pre);
+            current = *pre;
+        }
+        path.reverse();
+        path.retain(|id| self.step(*id).is_some_and(|s| !s.status.is_done()));
+        (path, length)
+    }
 }
 
 /// Plan actions
@@ -78,9 +388,12 @@ pub enum PlanAction {
     Estimate,
     Visualize,
     Clone,
+    Switch,
     Cancel,
     Notes,
     Progress,
+    CriticalPath,
+    Export,
     Clear,
     Help,
 }
@@ -108,12 +421,15 @@ impl std::str::FromStr for PlanAction {
             "estimate" => Ok(Self::Estimate),
             "visualize" | "viz" => Ok(Self::Visualize),
             "clone" | "copy" => Ok(Self::Clone),
+            "switch" | "use" | "checkout" => Ok(Self::Switch),
             "cancel" => Ok(Self::Cancel),
             "notes" | "note" => Ok(Self::Notes),
             "progress" => Ok(Self::Progress),
+            "critical_path" | "criticalpath" | "critical" => Ok(Self::CriticalPath),
+            "export" | "report" => Ok(Self::Export),
             "clear" | "reset" => Ok(Self::Clear),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -133,7 +449,7 @@ pub struct PlanToolArgs {
     pub step: Option<String>,
     /// Step index to update
     pub step_index: Option<usize>,
-    /// Step ID to update
+    /// Stable step id to update
     pub step_id: Option<usize>,
     /// Position for add_step
     pub position: Option<usize>,
@@ -145,26 +461,128 @@ pub struct PlanToolArgs {
     pub error: Option<String>,
     /// Note text
     pub note: Option<String>,
+    /// Parent step id for add_step
+    pub parent: Option<usize>,
+    /// Step ids the step depends on
+    pub depends_on: Option<Vec<usize>>,
+    /// Relative effort for the step
+    pub estimate: Option<f64>,
+    /// Skip transition and dependency checks
+    pub force: Option<bool>,
+    /// File to write an export to
+    pub path: Option<String>,
+    /// Heading for an export
+    pub title: Option<String>,
 }
 
 /// Plan tool
 pub struct PlanTool {
     plan: Arc<RwLock<TrackedPlan>>,
-    plans: Arc<RwLock<std::collections::HashMap<String, TrackedPlan>>>,
+    plans: Arc<RwLock<HashMap<String, TrackedPlan>>>,
     notes: Arc<RwLock<Vec<String>>>,
     counter: Arc<RwLock<usize>>,
+    store_path: Option<PathBuf>,
+    events: broadcast::Sender<Value>,
+}
+
+/// On-disk form of a project's plans
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PlanStore {
+    active: Option<String>,
+    plans: HashMap<String, TrackedPlan>,
 }
 
 impl PlanTool {
     pub fn new() -> Self {
+        let project = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        Self::for_project(&project)
+    }
+
+    /// Plans persisted for the project rooted at `root`
+    pub fn for_project(root: &Path) -> Self {
+        let path = dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("hanzo-mcp")
+            .join("plans")
+            .join(format!("{}.json", project_key(root)));
+        Self::with_store(Some(path))
+    }
+
+    /// Emit notifications on `events`, shared with other emitters
+    pub fn with_notifier(mut self, events: broadcast::Sender<Value>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Plans backed by the given JSON file, or in-memory only when `None`
+    pub fn with_store(path: Option<PathBuf>) -> Self {
+        let store = path.as_deref().map(load_store).unwrap_or_default();
+        let active = store
+            .active
+            .as_ref()
+            .and_then(|name| store.plans.get(name))
+            .cloned()
+            .unwrap_or_default();
+        let (events, _) = broadcast::channel(64);
+
         Self {
-            plan: Arc::new(RwLock::new(TrackedPlan::default())),
-            plans: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            plan: Arc::new(RwLock::new(active)),
+            counter: Arc::new(RwLock::new(store.plans.len())),
+            plans: Arc::new(RwLock::new(store.plans)),
             notes: Arc::new(RwLock::new(Vec::new())),
-            counter: Arc::new(RwLock::new(0)),
+            store_path: path,
+            events,
         }
     }
 
+    /// Receive MCP notifications (`notifications/resources/updated`,
+    /// `notifications/resources/list_changed`) emitted when plans change
+    pub fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.events.subscribe()
+    }
+
+    /// Sender for the notification channel, shareable with other emitters
+    pub fn notifier(&self) -> broadcast::Sender<Value> {
+        self.events.clone()
+    }
+
+    /// Plans as MCP resources
+    pub async fn resources(&self) -> Vec<Value> {
+        let plans = self.plans.read().await;
+        let mut names: Vec<&String> = plans.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let plan = &plans[name];
+                let done = plan.steps.iter().filter(|s| s.status.is_done()).count();
+                let state = if plan.archived_at.is_some() { ", archived" } else { "" };
+                json!({
+                    "uri": format!("{}{}", PLAN_URI_PREFIX, name),
+                    "name": format!("Plan: {}", name),
+                    "description": format!("{}/{} steps done{}", done, plan.steps.len(), state),
+                    "mimeType": "application/json"
+                })
+            })
+            .collect()
+    }
+
+    /// Read a `plan://<name>` resource
+    pub async fn read_resource(&self, uri: &str) -> Result<Value> {
+        let name = uri
+            .strip_prefix(PLAN_URI_PREFIX)
+            .ok_or_else(|| ToolError::invalid(format!("Not a plan resource: {}", uri)))?;
+        let plans = self.plans.read().await;
+        let plan = plans.get(name).ok_or_else(|| ToolError::not_found(format!("Plan not found: {}", name)))?;
+        Ok(json!({
+            "contents": [{
+                "uri": uri,
+                "mimeType": "application/json",
+                "text": serde_json::to_string_pretty(plan)?
+            }]
+        }))
+    }
+
     pub async fn execute(&self, args: PlanToolArgs) -> Result<String> {
         let action: PlanAction = if args.action.is_empty() {
             PlanAction::Help
@@ -172,6 +590,7 @@ impl PlanTool {
             args.action.parse()?
         };
 
+        let before = self.snapshot().await;
         let result = match action {
             PlanAction::Create => self.create(args).await?,
             PlanAction::Show => self.get().await?,
@@ -185,19 +604,92 @@ impl PlanTool {
             PlanAction::Estimate => self.estimate().await?,
             PlanAction::Visualize => self.visualize().await?,
             PlanAction::Clone => self.clone_plan(args).await?,
+            PlanAction::Switch => self.switch(args).await?,
             PlanAction::Cancel => self.cancel(args).await?,
             PlanAction::Notes => self.manage_notes(args).await?,
             PlanAction::Progress => self.progress().await?,
+            PlanAction::CriticalPath => self.critical_path().await?,
+            PlanAction::Export => self.export(args).await?,
             PlanAction::Clear => self.clear().await?,
             PlanAction::Help => self.help()?,
         };
+        self.sync(before).await?;
 
         Ok(serde_json::to_string(&result)?)
     }
 
+    /// Active plan name and every plan serialized, with the active plan
+    /// taking precedence over its stored copy
+    async fn snapshot(&self) -> (Option<String>, HashMap<String, Value>) {
+        let active = self.plan.read().await;
+        let mut plans: HashMap<String, Value> = self
+            .plans
+            .read()
+            .await
+            .iter()
+            .map(|(name, plan)| (name.clone(), serde_json::to_value(plan).unwrap_or_default()))
+            .collect();
+        if let Some(name) = &active.name {
+            plans.insert(name.clone(), serde_json::to_value(&*active).unwrap_or_default());
+        }
+        (active.name.clone(), plans)
+    }
+
+    /// Store the active plan, persist and notify if anything changed since `before`
+    async fn sync(&self, before: (Option<String>, HashMap<String, Value>)) -> Result<()> {
+        {
+            let mut active = self.plan.write().await;
+            if active.name.is_none() && !active.steps.is_empty() {
+                active.name = Some(DEFAULT_PLAN.to_string());
+            }
+            if let Some(name) = active.name.clone() {
+                self.plans.write().await.insert(name, active.clone());
+            }
+        }
+        let after = self.snapshot().await;
+        if after == before {
+            return Ok(());
+        }
+
+        if let Some(path) = &self.store_path {
+            let store = PlanStore { active: after.0.clone(), plans: self.plans.read().await.clone() };
+            save_store(path, &store).await?;
+        }
+
+        let (old, new) = (&before.1, &after.1);
+        let mut changed: Vec<&String> = old.keys().chain(new.keys()).filter(|n| old.get(*n) != new.get(*n)).collect();
+        changed.sort();
+        changed.dedup();
+        for name in changed {
+            let _ = self.events.send(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": {"uri": format!("{}{}", PLAN_URI_PREFIX, name)}
+            }));
+            for step in newly_completed(old.get(name), new.get(name)) {
+                crate::events::publish(crate::events::PLAN_STEP_COMPLETED, json!({
+                    "plan": name,
+                    "step": step["id"],
+                    "description": step["description"],
+                    "output": step["output"]
+                }));
+            }
+        }
+        if old.len() != new.len() || old.keys().any(|n| !new.contains_key(n)) {
+            let _ = self.events.send(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/list_changed"
+            }));
+        }
+        Ok(())
+    }
+
     async fn update(&self, args: PlanToolArgs) -> Result<Value> {
-        let mut plan = self.plan.write().await;
+        let mut guard = self.plan.write().await;
+        // Work on a copy so a rejected change leaves the plan untouched
+        let mut plan = guard.clone();
         let now = chrono::Utc::now().to_rfc3339();
+        let mut unblocked = Vec::new();
 
         // Update plan name
         if let Some(name) = args.name {
@@ -211,31 +703,37 @@ impl PlanTool {
         }
 
         // Update specific step
-        if let Some(idx) = args.step_index.or(args.step_id) {
-            if idx > 0 && idx <= plan.steps.len() {
-                let step = &mut plan.steps[idx - 1];
-
-                if let Some(status_str) = args.status {
-                    step.status = status_str.parse()?;
-                }
-                if let Some(output) = args.output {
-                    step.output = Some(output);
-                }
-                if let Some(error) = args.error {
-                    step.error = Some(error);
-                    step.status = StepStatus::Failed;
-                }
-            } else {
-                return Err(anyhow!("Invalid step index: {}", idx));
+        if let Some(pos) = plan.resolve(args.step_id, args.step_index)? {
+            if let Some(deps) = args.depends_on {
+                plan.steps[pos].depends_on = deps;
+                plan.validate()?;
+            }
+            if let Some(estimate) = args.estimate {
+                plan.steps[pos].estimate = Some(estimate);
+            }
+            let force = args.force.unwrap_or(false);
+            if let Some(status_str) = args.status {
+                unblocked = plan.set_status(pos, status_str.parse()?, force)?;
+            }
+            if let Some(output) = args.output {
+                plan.steps[pos].output = Some(output);
+            }
+            // An error fails the step, by the same rules as status failed
+            if let Some(error) = args.error {
+                plan.steps[pos].error = Some(error);
+                unblocked.extend(plan.set_status(pos, StepStatus::Failed, force)?);
             }
         }
 
         plan.updated_at = Some(now);
+        *guard = plan;
+        let plan = &*guard;
 
         // Calculate progress
         let total = plan.steps.len();
         let completed = plan.steps.iter().filter(|s| s.status == StepStatus::Completed).count();
         let in_progress = plan.steps.iter().filter(|s| s.status == StepStatus::InProgress).count();
+        let blocked = plan.steps.iter().filter(|s| s.status == StepStatus::Blocked).count();
         let failed = plan.steps.iter().filter(|s| s.status == StepStatus::Failed).count();
 
         Ok(json!({
@@ -243,142 +741,192 @@ impl PlanTool {
             "total_steps": total,
             "completed": completed,
             "in_progress": in_progress,
+            "blocked": blocked,
             "failed": failed,
             "progress": if total > 0 { (completed as f64 / total as f64) * 100.0 } else { 0.0 },
             "steps": plan.steps,
+            "unblocked": unblocked,
             "updated_at": plan.updated_at
         }))
     }
 
     fn parse_steps(&self, value: Value) -> Result<Vec<TrackedStep>> {
-        match value {
+        let steps = match value {
             Value::String(text) => {
-                // Parse numbered list
-                let steps: Vec<TrackedStep> = text
-                    .lines()
-                    .filter_map(|line| {
-                        let line = line.trim();
-                        if line.is_empty() {
-                            return None;
-                        }
-                        // Remove numbering
-                        let desc = line
-                            .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ')' || c == '-')
-                            .trim();
-                        if desc.is_empty() {
-                            return None;
-                        }
-                        Some(desc.to_string())
-                    })
-                    .enumerate()
-                    .map(|(i, desc)| TrackedStep {
-                        id: i + 1,
-                        description: desc,
-                        status: StepStatus::Pending,
-                        output: None,
-                        error: None,
-                    })
-                    .collect();
-                Ok(steps)
+                // Parse numbered list; deeper indentation makes a subtask
+                let mut steps: Vec<TrackedStep> = Vec::new();
+                let mut stack: Vec<(usize, usize)> = Vec::new();
+                for line in text.lines() {
+                    let indent: usize = line
+                        .chars()
+                        .take_while(|c| c.is_whitespace())
+                        .map(|c| if c == '\t' { 4 } else { 1 })
+                        .sum();
+                    let line = line.trim();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    // Remove numbering
+                    let desc = line
+                        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ')' || c == '-')
+                        .trim();
+                    if desc.is_empty() {
+                        continue;
+                    }
+                    while stack.last().is_some_and(|(i, _)| *i >= indent) {
+                        stack.pop();
+                    }
+                    let id = steps.len() + 1;
+                    steps.push(TrackedStep {
+                        id,
+                        description: desc.to_string(),
+                        parent: stack.last().map(|(_, id)| *id),
+                        ..Default::default()
+                    });
+                    stack.push((indent, id));
+                }
+                steps
             }
             Value::Array(arr) => {
-                let steps: Vec<TrackedStep> = arr
-                    .into_iter()
-                    .enumerate()
-                    .filter_map(|(i, v)| {
-                        let desc = match v {
-                            Value::String(s) => s,
-                            Value::Object(obj) => {
-                                obj.get("description")
-                                    .or(obj.get("desc"))
-                                    .or(obj.get("step"))
-                                    .and_then(|v| v.as_str())
-                                    .map(|s| s.to_string())?
-                            }
-                            _ => return None,
-                        };
-                        Some(TrackedStep {
-                            id: i + 1,
-                            description: desc,
-                            status: StepStatus::Pending,
-                            output: None,
-                            error: None,
-                        })
-                    })
-                    .collect();
-                Ok(steps)
+                let mut used = HashSet::new();
+                collect_explicit_ids(&arr, &mut used);
+                let mut steps = Vec::new();
+                flatten_steps(arr, None, &mut used, &mut steps)?;
+                steps
             }
-            _ => Err(anyhow!("steps must be string or array")),
-        }
+            _ => return Err(ToolError::invalid("steps must be string or array").into()),
+        };
+        let plan = TrackedPlan { steps, ..Default::default() };
+        plan.validate()?;
+        Ok(plan.steps)
     }
 
     async fn create(&self, args: PlanToolArgs) -> Result<Value> {
         let mut counter = self.counter.write().await;
         *counter += 1;
         let name = args.name.unwrap_or_else(|| format!("plan-{}", *counter));
+        if self.plans.read().await.contains_key(&name) {
+            return Err(ToolError::invalid(format!("Plan '{}' already exists; use switch to activate it", name)).into());
+        }
         let now = chrono::Utc::now().to_rfc3339();
         let steps = if let Some(sv) = args.steps { self.parse_steps(sv)? } else { Vec::new() };
-        let plan = TrackedPlan { name: Some(name.clone()), steps, created_at: Some(now.clone()), updated_at: Some(now) };
+        let plan = TrackedPlan { name: Some(name.clone()), steps, created_at: Some(now.clone()), updated_at: Some(now), archived_at: None };
         self.plans.write().await.insert(name.clone(), plan.clone());
         *self.plan.write().await = plan;
         Ok(json!({ "message": format!("Created plan '{}'", name), "name": name }))
     }
 
     async fn list_plans(&self) -> Result<Value> {
+        let active = self.plan.read().await.name.clone();
         let plans = self.plans.read().await;
-        let items: Vec<Value> = plans.iter().map(|(n, p)| {
+        let mut names: Vec<&String> = plans.keys().collect();
+        names.sort();
+        let items: Vec<Value> = names.into_iter().map(|n| {
+            let p = &plans[n];
             let done = p.steps.iter().filter(|s| s.status == StepStatus::Completed).count();
-            json!({"name": n, "steps": p.steps.len(), "completed": done})
+            json!({
+                "name": n,
+                "steps": p.steps.len(),
+                "completed": done,
+                "active": active.as_ref() == Some(n),
+                "archived": p.archived_at.is_some(),
+                "uri": format!("{}{}", PLAN_URI_PREFIX, n)
+            })
         }).collect();
-        Ok(json!({ "plans": items, "total": items.len() }))
+        Ok(json!({ "plans": items, "total": items.len(), "active": active }))
     }
 
     async fn next_step(&self) -> Result<Value> {
         let plan = self.plan.read().await;
-        for (i, s) in plan.steps.iter().enumerate() {
-            if s.status == StepStatus::Pending {
-                return Ok(json!({"index": i+1, "step": s.description, "status": "pending"}));
-            }
+        let ready = plan.ready();
+        if let Some(s) = ready.first() {
+            let index = plan.position(s.id).unwrap_or_default() + 1;
+            let ready_ids: Vec<usize> = ready.iter().map(|s| s.id).collect();
+            return Ok(json!({"index": index, "id": s.id, "step": s.description, "status": "pending", "ready": ready_ids}));
+        }
+        let waiting: Vec<usize> = plan.steps.iter().filter(|s| !s.status.is_done()).map(|s| s.id).collect();
+        if !waiting.is_empty() {
+            return Ok(json!({"message": "No step is ready to start", "unfinished": waiting}));
         }
         Ok(json!({"message": "All steps completed"}))
     }
 
     async fn archive(&self, args: PlanToolArgs) -> Result<Value> {
-        let name = args.name.or_else(|| {
-            let p = self.plan.try_read().ok();
-            p.and_then(|p| p.name.clone())
-        });
-        if let Some(n) = &name {
-            self.plans.write().await.remove(n);
+        let mut active = self.plan.write().await;
+        let name = args
+            .name
+            .or_else(|| active.name.clone())
+            .ok_or_else(|| ToolError::not_found("No active plan to archive"))?;
+        let mut plans = self.plans.write().await;
+        if active.name.as_deref() == Some(name.as_str()) {
+            plans.insert(name.clone(), active.clone());
+            *active = TrackedPlan::default();
         }
-        *self.plan.write().await = TrackedPlan::default();
-        Ok(json!({"message": format!("Archived plan '{}'", name.unwrap_or_default())}))
+        let plan = plans.get_mut(&name).ok_or_else(|| ToolError::not_found(format!("Plan not found: {}", name)))?;
+        plan.archived_at = Some(chrono::Utc::now().to_rfc3339());
+        Ok(json!({"message": format!("Archived plan '{}'", name)}))
+    }
+
+    async fn switch(&self, args: PlanToolArgs) -> Result<Value> {
+        let name = args.name.ok_or_else(|| ToolError::invalid("name required"))?;
+        let mut active = self.plan.write().await;
+        let mut plans = self.plans.write().await;
+        let mut plan = plans.get(&name).cloned().ok_or_else(|| ToolError::not_found(format!("Plan not found: {}", name)))?;
+        if let Some(current) = active.name.clone() {
+            plans.insert(current, active.clone());
+        }
+        // Switching to an archived plan brings it back
+        let restored = plan.archived_at.take().is_some();
+        plans.insert(name.clone(), plan.clone());
+        *active = plan;
+        Ok(json!({"message": format!("Switched to plan '{}'", name), "name": name, "restored": restored}))
     }
 
     async fn add_step(&self, args: PlanToolArgs) -> Result<Value> {
-        let step_text = args.step.ok_or_else(|| anyhow!("step text required"))?;
+        let step_text = args.step.ok_or_else(|| ToolError::invalid("step text required"))?;
         let mut plan = self.plan.write().await;
-        let id = plan.steps.len() + 1;
-        let new_step = TrackedStep { id, description: step_text.clone(), status: StepStatus::Pending, output: None, error: None };
+        let id = plan.next_id();
+        let new_step = TrackedStep {
+            id,
+            description: step_text,
+            parent: args.parent,
+            depends_on: args.depends_on.unwrap_or_default(),
+            estimate: args.estimate,
+            ..Default::default()
+        };
+        let mut next = plan.clone();
         if let Some(pos) = args.position {
-            let pos = pos.min(plan.steps.len());
-            plan.steps.insert(pos, new_step);
+            let pos = pos.min(next.steps.len());
+            next.steps.insert(pos, new_step);
+        } else if let Some(parent) = args.parent {
+            // Place subtasks after the parent's existing subtree
+            let last = next.descendants(parent).iter().chain([&parent]).filter_map(|id| next.position(*id)).max();
+            let pos = last.map(|p| p + 1).unwrap_or(next.steps.len());
+            next.steps.insert(pos, new_step);
         } else {
-            plan.steps.push(new_step);
+            next.steps.push(new_step);
         }
-        plan.updated_at = Some(chrono::Utc::now().to_rfc3339());
-        Ok(json!({"message": "Step added", "total_steps": plan.steps.len()}))
+        next.validate()?;
+        next.updated_at = Some(chrono::Utc::now().to_rfc3339());
+        *plan = next;
+        Ok(json!({"message": "Step added", "id": id, "total_steps": plan.steps.len()}))
     }
 
     async fn remove_step(&self, args: PlanToolArgs) -> Result<Value> {
-        let idx = args.step_index.or(args.step_id).ok_or_else(|| anyhow!("step_index required"))?;
         let mut plan = self.plan.write().await;
-        if idx == 0 || idx > plan.steps.len() {
-            return Err(anyhow!("Invalid step index: {}", idx));
+        let pos = plan
+            .resolve(args.step_id, args.step_index)?
+            .ok_or_else(|| ToolError::invalid("step_index required"))?;
+        let removed = plan.steps[pos].clone();
+        // Subtasks go with their parent; dependencies on removed steps are dropped
+        let mut ids = plan.descendants(removed.id);
+        ids.push(removed.id);
+        plan.steps.retain(|s| !ids.contains(&s.id));
+        for step in plan.steps.iter_mut() {
+            step.depends_on.retain(|d| !ids.contains(d));
         }
-        let removed = plan.steps.remove(idx - 1);
         plan.updated_at = Some(chrono::Utc::now().to_rfc3339());
-        Ok(json!({"message": format!("Removed step: {}", removed.description), "total_steps": plan.steps.len()}))
+        Ok(json!({"message": format!("Removed step: {}", removed.description), "removed_ids": ids, "total_steps": plan.steps.len()}))
     }
 
     async fn estimate(&self) -> Result<Value> {
@@ -395,14 +943,23 @@ impl PlanTool {
         let plan = self.plan.read().await;
         let name = plan.name.as_deref().unwrap_or("(unnamed)");
         let mut lines = vec![format!("Plan: {}", name)];
-        for (i, s) in plan.steps.iter().enumerate() {
+        for s in &plan.steps {
             let icon = match s.status {
                 StepStatus::Completed => "[x]",
                 StepStatus::InProgress => "[~]",
+                StepStatus::Blocked => "[#]",
                 StepStatus::Failed => "[!]",
+                StepStatus::Skipped => "[-]",
                 _ => "[ ]",
             };
-            lines.push(format!("  {}. {} {}", i + 1, icon, s.description));
+            let indent = "  ".repeat(plan.depth(s) + 1);
+            let deps = if s.depends_on.is_empty() {
+                String::new()
+            } else {
+                let ids: Vec<String> = s.depends_on.iter().map(|d| d.to_string()).collect();
+                format!(" (after {})", ids.join(", "))
+            };
+            lines.push(format!("{}{}. {} {}{}", indent, s.id, icon, s.description, deps));
         }
         let total = plan.steps.len();
         let done = plan.steps.iter().filter(|s| s.status == StepStatus::Completed).count();
@@ -417,15 +974,15 @@ impl PlanTool {
 
     async fn clone_plan(&self, args: PlanToolArgs) -> Result<Value> {
         let plan = self.plan.read().await;
-        if plan.steps.is_empty() { return Err(anyhow!("No active plan to clone")); }
+        if plan.steps.is_empty() { return Err(ToolError::not_found("No active plan to clone").into()); }
         let mut counter = self.counter.write().await;
         *counter += 1;
         let new_name = args.new_name.unwrap_or_else(|| format!("{}-copy-{}", plan.name.as_deref().unwrap_or("plan"), *counter));
         let now = chrono::Utc::now().to_rfc3339();
-        let new_steps: Vec<TrackedStep> = plan.steps.iter().enumerate().map(|(i, s)| TrackedStep {
-            id: i + 1, description: s.description.clone(), status: StepStatus::Pending, output: None, error: None
+        let new_steps: Vec<TrackedStep> = plan.steps.iter().map(|s| TrackedStep {
+            status: StepStatus::Pending, output: None, error: None, ..s.clone()
         }).collect();
-        let new_plan = TrackedPlan { name: Some(new_name.clone()), steps: new_steps, created_at: Some(now.clone()), updated_at: Some(now) };
+        let new_plan = TrackedPlan { name: Some(new_name.clone()), steps: new_steps, created_at: Some(now.clone()), updated_at: Some(now), archived_at: None };
         self.plans.write().await.insert(new_name.clone(), new_plan);
         Ok(json!({"message": format!("Cloned to '{}'", new_name)}))
     }
@@ -465,24 +1022,56 @@ impl PlanTool {
         let total = plan.steps.len();
         let completed = plan.steps.iter().filter(|s| s.status == StepStatus::Completed).count();
         let in_progress = plan.steps.iter().filter(|s| s.status == StepStatus::InProgress).count();
+        let blocked = plan.steps.iter().filter(|s| s.status == StepStatus::Blocked).count();
         let failed = plan.steps.iter().filter(|s| s.status == StepStatus::Failed).count();
+        let ready: Vec<usize> = plan.ready().iter().map(|s| s.id).collect();
 
         Ok(json!({
             "name": plan.name,
             "total_steps": total,
             "completed": completed,
             "in_progress": in_progress,
+            "blocked": blocked,
             "failed": failed,
             "progress": if total > 0 { (completed as f64 / total as f64) * 100.0 } else { 0.0 },
             "steps": plan.steps,
+            "ready": ready,
             "created_at": plan.created_at,
             "updated_at": plan.updated_at
         }))
     }
 
+    async fn critical_path(&self) -> Result<Value> {
+        let plan = self.plan.read().await;
+        let (path, length) = plan.critical_path();
+        let steps: Vec<Value> = path
+            .iter()
+            .filter_map(|id| plan.step(*id))
+            .map(|s| json!({"id": s.id, "step": s.description, "status": s.status, "estimate": s.estimate.unwrap_or(1.0)}))
+            .collect();
+        Ok(json!({"critical_path": steps, "length": length}))
+    }
+
+    /// The active plan as a Markdown report, written to `path` when given
+    async fn export(&self, args: PlanToolArgs) -> Result<Value> {
+        let plan = self.get().await?;
+        let report = Report { title: args.title.as_deref(), plan: Some(&plan), ..Default::default() }.render();
+        match args.path {
+            Some(path) => {
+                let path = shellexpand::tilde(&path).to_string();
+                tokio::fs::write(&path, &report).await?;
+                Ok(json!({ "path": path, "bytes": report.len() }))
+            }
+            None => Ok(json!({ "format": "markdown", "report": report })),
+        }
+    }
+
     async fn clear(&self) -> Result<Value> {
         let mut plan = self.plan.write().await;
         let had_plan = !plan.steps.is_empty();
+        if let Some(name) = &plan.name {
+            self.plans.write().await.remove(name);
+        }
         *plan = TrackedPlan::default();
 
         Ok(json!({
@@ -499,22 +1088,127 @@ impl PlanTool {
             "actions": {
                 "update": "Update plan and step status",
                 "get": "Get current plan",
+                "add_step": "Add a step (parent for subtasks, depends_on for ordering)",
+                "next": "Next step whose dependencies are done",
+                "critical_path": "Longest chain of remaining work",
+                "create": "Create a named plan and make it active",
+                "switch": "Activate another plan (restores archived plans)",
+                "archive": "Archive a plan (default: the active one)",
+                "list": "List plans for this project",
+                "export": "Markdown report of the plan, reasoning and this session's tool calls (optional path, title)",
                 "clear": "Clear plan"
             },
             "example": {
                 "create": "plan(action='update', steps='1. First step\\n2. Second step')",
-                "update_step": "plan(action='update', step_index=1, status='completed')"
+                "update_step": "plan(action='update', step_index=1, status='completed')",
+                "subtask": "plan(action='add_step', step='Write tests', parent=1, depends_on=[2])"
             }
         }))
     }
 }
 
+/// Load stored plans, starting empty when the file is missing or unreadable
+fn load_store(path: &Path) -> PlanStore {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+async fn save_store(path: &Path, store: &PlanStore) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    // Write then rename so a crash never leaves a truncated store
+    let tmp = path.with_extension("json.tmp");
+    tokio::fs::write(&tmp, serde_json::to_vec_pretty(store)?).await?;
+    tokio::fs::rename(&tmp, path).await?;
+    Ok(())
+}
+
+fn subtasks(obj: &Map<String, Value>) -> Option<&Vec<Value>> {
+    obj.get("subtasks").or(obj.get("children")).and_then(|v| v.as_array())
+}
+
+/// Gather ids given explicitly in structured steps so generated ids avoid them
+fn collect_explicit_ids(values: &[Value], used: &mut HashSet<usize>) {
+    for obj in values.iter().filter_map(|v| v.as_object()) {
+        if let Some(id) = obj.get("id").and_then(|v| v.as_u64()) {
+            used.insert(id as usize);
+        }
+        if let Some(children) = subtasks(obj) {
+            collect_explicit_ids(children, used);
+        }
+    }
+}
+
+/// Flatten structured steps depth-first, linking subtasks to their parent
+fn flatten_steps(
+    values: Vec<Value>,
+    parent: Option<usize>,
+    used: &mut HashSet<usize>,
+    out: &mut Vec<TrackedStep>,
+) -> Result<()> {
+    for value in values {
+        let obj = match value {
+            Value::String(description) => {
+                let id = (1..).find(|n| !used.contains(n)).unwrap_or_default();
+                used.insert(id);
+                out.push(TrackedStep { id, description, parent, ..Default::default() });
+                continue;
+            }
+            Value::Object(obj) => obj,
+            _ => continue,
+        };
+        let Some(description) = obj
+            .get("description")
+            .or(obj.get("desc"))
+            .or(obj.get("step"))
+            .and_then(|v| v.as_str())
+        else {
+            continue;
+        };
+        let id = match obj.get("id").and_then(|v| v.as_u64()) {
+            Some(id) => id as usize,
+            None => {
+                let id = (1..).find(|n| !used.contains(n)).unwrap_or_default();
+                used.insert(id);
+                id
+            }
+        };
+        let depends_on = obj
+            .get("depends_on")
+            .or(obj.get("deps"))
+            .and_then(|v| v.as_array())
+            .map(|deps| deps.iter().filter_map(|d| d.as_u64()).map(|d| d as usize).collect())
+            .unwrap_or_default();
+        let status = match obj.get("status").and_then(|v| v.as_str()) {
+            Some(status) => status.parse()?,
+            None => StepStatus::Pending,
+        };
+        out.push(TrackedStep {
+            id,
+            description: description.to_string(),
+            status,
+            parent,
+            depends_on,
+            estimate: obj.get("estimate").and_then(|v| v.as_f64()),
+            ..Default::default()
+        });
+        if let Some(children) = subtasks(&obj) {
+            flatten_steps(children.clone(), Some(id), used, out)?;
+        }
+    }
+    Ok(())
+}
+
 /// MCP Tool Definition
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PlanToolDefinition {
     pub name: String,
     pub description: String,
     pub input_schema: Value,
+    pub output_schema: Value,
 }
 
 impl PlanToolDefinition {
@@ -526,34 +1220,75 @@ impl PlanToolDefinition {
 Actions:
 - update: Update plan and step status
 - get: Get current plan
+- add_step / remove_step: Edit steps (parent for subtasks, depends_on for ordering)
+- next: Next step whose dependencies are done
+- critical_path: Longest chain of remaining work
+- create / switch / archive / list: Manage named plans, persisted per project
+- export: Markdown report of the plan, think journal and this session's
+  tool calls, for a PR description or run artifact (path to write a file)
 - clear: Clear plan
 
-Step statuses: pending, in_progress, completed, failed, skipped"#.to_string(),
+Plans are readable as plan://<name> resources.
+
+Steps may be nested (indent text lines, or use subtasks in objects) and
+depend on other steps by id. Status changes are validated unless force=true.
+
+Step statuses: pending, in_progress, blocked, completed, failed, skipped"#.to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["update", "get", "clear", "help"],
+                        "enum": ["create", "show", "update", "get", "list", "next", "archive", "add_step",
+                                 "remove_step", "estimate", "visualize", "clone", "switch", "cancel", "notes",
+                                 "progress", "critical_path", "export", "clear", "help"],
                         "default": "help"
                     },
                     "name": {"type": "string", "description": "Plan name"},
                     "steps": {
                         "oneOf": [
                             {"type": "string"},
-                            {"type": "array", "items": {"type": "string"}}
+                            {"type": "array", "items": {"oneOf": [
+                                {"type": "string"},
+                                {"type": "object", "properties": {
+                                    "id": {"type": "integer"},
+                                    "description": {"type": "string"},
+                                    "depends_on": {"type": "array", "items": {"type": "integer"}},
+                                    "estimate": {"type": "number"},
+                                    "subtasks": {"type": "array"}
+                                }}
+                            ]}}
                         ],
                         "description": "Plan steps"
                     },
+                    "step": {"type": "string", "description": "Step text for add_step"},
                     "step_index": {"type": "integer", "description": "Step index to update (1-based)"},
-                    "step_id": {"type": "integer", "description": "Alias for step_index"},
+                    "step_id": {"type": "integer", "description": "Stable step id (takes precedence over step_index)"},
+                    "parent": {"type": "integer", "description": "Parent step id for add_step"},
+                    "depends_on": {"type": "array", "items": {"type": "integer"}, "description": "Step ids this step depends on"},
+                    "estimate": {"type": "number", "description": "Relative effort used by critical_path"},
+                    "force": {"type": "boolean", "description": "Skip transition and dependency checks"},
                     "status": {
                         "type": "string",
-                        "enum": ["pending", "in_progress", "completed", "failed", "skipped"],
+                        "enum": ["pending", "in_progress", "blocked", "completed", "failed", "skipped"],
                         "description": "New status for step"
                     },
                     "output": {"type": "string", "description": "Output for step"},
-                    "error": {"type": "string", "description": "Error for step"}
+                    "error": {"type": "string", "description": "Error for step"},
+                    "path": {"type": "string", "description": "File to write the export to"},
+                    "title": {"type": "string", "description": "Heading for the export"}
+                }
+            }),
+            output_schema: json!({
+                "type": "object",
+                "description": "Fields depend on the action",
+                "properties": {
+                    "name": {"type": "string"},
+                    "message": {"type": "string"},
+                    "steps": {"type": "array", "items": {"type": "object"}},
+                    "total_steps": {"type": "integer"},
+                    "completed": {"type": "integer"},
+                    "progress": {"type": "number"}
                 }
             }),
         }
@@ -566,7 +1301,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_create_plan() {
-        let tool = PlanTool::new();
+        let tool = PlanTool::with_store(None);
         let args = PlanToolArgs {
             action: "update".to_string(),
             name: Some("Test Plan".to_string()),
@@ -583,7 +1318,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_update_step() {
-        let tool = PlanTool::new();
+        let tool = PlanTool::with_store(None);
 
         // Create plan
         let args = PlanToolArgs {
@@ -592,6 +1327,7 @@ mod tests {
             ..Default::default()
         };
         tool.execute(args).await.unwrap();
+        let since = crate::events::bus().last_id();
 
         // Update step
         let args = PlanToolArgs {
@@ -606,11 +1342,16 @@ mod tests {
         assert!(result.is_ok());
         let output = result.unwrap();
         assert!(output.contains("completed"));
+
+        let filter = json!({ "output": "Done!" });
+        let completed = crate::events::bus().recent(Some(crate::events::PLAN_STEP_COMPLETED), &filter, since, 10);
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].data["description"], "First step");
     }
 
     #[tokio::test]
     async fn test_get_plan() {
-        let tool = PlanTool::new();
+        let tool = PlanTool::with_store(None);
 
         // Create plan
         let args = PlanToolArgs {
@@ -638,7 +1379,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_clear_plan() {
-        let tool = PlanTool::new();
+        let tool = PlanTool::with_store(None);
 
         // Create plan
         let args = PlanToolArgs {
@@ -659,4 +1400,146 @@ mod tests {
         let output = result.unwrap();
         assert!(output.contains("cleared"));
     }
+
+    #[tokio::test]
+    async fn test_dependencies_and_transitions() {
+        let tool = PlanTool::with_store(None);
+        let args = PlanToolArgs {
+            action: "update".to_string(),
+            steps: Some(json!([
+                {"id": 1, "description": "Design"},
+                {"id": 2, "description": "Build", "depends_on": [1], "subtasks": [
+                    {"description": "Backend", "estimate": 3.0},
+                    {"description": "Frontend"}
+                ]},
+                {"id": 5, "description": "Ship", "depends_on": [2]}
+            ])),
+            ..Default::default()
+        };
+        tool.execute(args).await.unwrap();
+
+        {
+            let plan = tool.plan.read().await;
+            assert_eq!(plan.step(3).unwrap().parent, Some(2));
+            assert_eq!(plan.step(4).unwrap().parent, Some(2));
+            // Design -> Backend (3) -> Build -> Ship
+            let (path, length) = plan.critical_path();
+            assert_eq!(path, vec![1, 3, 2, 5]);
+            assert_eq!(length, 5.0);
+        }
+
+        // Ship cannot start before Build is done
+        let start_ship = PlanToolArgs {
+            action: "update".to_string(),
+            step_id: Some(5),
+            status: Some("in_progress".to_string()),
+            ..Default::default()
+        };
+        let err = tool.execute(start_ship.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("unfinished dependencies"));
+
+        // Build cannot complete while subtasks are open
+        let complete = |id| PlanToolArgs {
+            action: "update".to_string(),
+            step_id: Some(id),
+            status: Some("completed".to_string()),
+            ..Default::default()
+        };
+        tool.execute(complete(1)).await.unwrap();
+        let err = tool.execute(complete(2)).await.unwrap_err();
+        assert!(err.to_string().contains("unfinished subtasks"));
+
+        // Blocked Ship is released once Build completes
+        tool.execute(PlanToolArgs { status: Some("blocked".to_string()), ..start_ship.clone() }).await.unwrap();
+        tool.execute(complete(3)).await.unwrap();
+        tool.execute(complete(4)).await.unwrap();
+        let output = tool.execute(complete(2)).await.unwrap();
+        let value: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["unblocked"], json!([5]));
+
+        // Completed -> failed is not a legal transition without force
+        let fail = PlanToolArgs { status: Some("failed".to_string()), ..complete(1) };
+        assert!(tool.execute(fail.clone()).await.is_err());
+        // Nor is it when reporting an error
+        let error = PlanToolArgs { action: "update".to_string(), step_id: Some(2), error: Some("broke".to_string()), ..Default::default() };
+        let err = tool.execute(error).await.unwrap_err();
+        assert!(err.to_string().contains("Illegal transition"));
+        assert_eq!(tool.plan.read().await.step(2).unwrap().status, StepStatus::Completed);
+        tool.execute(PlanToolArgs { force: Some(true), ..fail }).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_cycles_and_nests_text() {
+        let tool = PlanTool::with_store(None);
+        let cyclic = PlanToolArgs {
+            action: "update".to_string(),
+            steps: Some(json!([
+                {"id": 1, "description": "A", "depends_on": [2]},
+                {"id": 2, "description": "B", "depends_on": [1]}
+            ])),
+            ..Default::default()
+        };
+        assert!(tool.execute(cyclic).await.unwrap_err().to_string().contains("cycle"));
+
+        let args = PlanToolArgs {
+            action: "update".to_string(),
+            steps: Some(Value::String("1. Release\n  - Tag\n  - Publish\n2. Announce".to_string())),
+            ..Default::default()
+        };
+        tool.execute(args).await.unwrap();
+        let plan = tool.plan.read().await;
+        let parents: Vec<Option<usize>> = plan.steps.iter().map(|s| s.parent).collect();
+        assert_eq!(parents, vec![None, Some(1), Some(1), None]);
+        let ready: Vec<usize> = plan.ready().iter().map(|s| s.id).collect();
+        assert_eq!(ready, vec![2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_plans_persist_and_switch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plans.json");
+        let tool = PlanTool::with_store(Some(path.clone()));
+        let mut events = tool.subscribe();
+
+        let create = |name: &str| PlanToolArgs {
+            action: "create".to_string(),
+            name: Some(name.to_string()),
+            steps: Some(Value::String("1. One\n2. Two".to_string())),
+            ..Default::default()
+        };
+        tool.execute(create("alpha")).await.unwrap();
+        tool.execute(create("beta")).await.unwrap();
+        assert!(tool.execute(create("alpha")).await.is_err());
+
+        let first = events.recv().await.unwrap();
+        assert_eq!(first["method"], "notifications/resources/updated");
+        assert_eq!(first["params"]["uri"], "plan://alpha");
+        assert_eq!(events.recv().await.unwrap()["method"], "notifications/resources/list_changed");
+
+        let switch = PlanToolArgs { action: "switch".to_string(), name: Some("alpha".to_string()), ..Default::default() };
+        tool.execute(switch.clone()).await.unwrap();
+        let complete = PlanToolArgs {
+            action: "update".to_string(),
+            step_id: Some(1),
+            status: Some("completed".to_string()),
+            ..Default::default()
+        };
+        tool.execute(complete).await.unwrap();
+        tool.execute(PlanToolArgs { action: "archive".to_string(), name: Some("beta".to_string()), ..Default::default() }).await.unwrap();
+
+        // A fresh instance reloads plans and the active selection
+        let reloaded = PlanTool::with_store(Some(path));
+        let plan = reloaded.plan.read().await;
+        assert_eq!(plan.name.as_deref(), Some("alpha"));
+        assert_eq!(plan.steps[0].status, StepStatus::Completed);
+        drop(plan);
+
+        let list: Value = serde_json::from_str(&reloaded.execute(PlanToolArgs { action: "list".to_string(), ..Default::default() }).await.unwrap()).unwrap();
+        assert_eq!(list["active"], "alpha");
+        assert_eq!(list["plans"][1]["name"], "beta");
+        assert_eq!(list["plans"][1]["archived"], true);
+
+        let resource = reloaded.read_resource("plan://alpha").await.unwrap();
+        assert!(resource["contents"][0]["text"].as_str().unwrap().contains("One"));
+    }
 }
@@ -0,0 +1,652 @@
+/// Persistent interpreter sessions
+///
+/// Actions: start, eval, vars, reset, sessions, stop, help
+///
+/// Each session is a python, node or deno process running a small driver
+/// that evaluates code in one long-lived namespace, so variables, imports
+/// and functions from one `eval` are there for the next. Like a notebook
+/// cell, the value of a trailing expression is returned along with what
+/// the code printed.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::{mpsc, Mutex};
+
+/// Default seconds an eval may run before it is interrupted
+const EVAL_SECS: u64 = 30;
+
+/// Characters of output or result kept per eval
+const MAX_OUTPUT: usize = 50_000;
+
+/// Reads requests as JSON lines and answers each with one marked JSON line;
+/// anything else on stdout came from outside the redirected streams
+const PYTHON_DRIVER: &str = r#"
+import ast, contextlib, io, json, os, sys, traceback
+
+MARK = os.environ["HANZO_REPL_MARK"]
+namespace = {"__name__": "__main__", "__builtins__": __builtins__}
+protocol = sys.stdout
+
+def run(code):
+    tree = ast.parse(code, "<repl>", "exec")
+    last = None
+    if tree.body and isinstance(tree.body[-1], ast.Expr):
+        last = ast.Expression(tree.body.pop().value)
+    exec(compile(tree, "<repl>", "exec"), namespace)
+    if last is not None:
+        value = eval(compile(last, "<repl>", "eval"), namespace)
+        if value is not None:
+            namespace["_"] = value
+            return repr(value)
+    return None
+
+def variables():
+    found = []
+    for name, value in namespace.items():
+        if name.startswith("_") or type(value).__name__ == "module":
+            continue
+        text = repr(value)
+        found.append({"name": name, "type": type(value).__name__, "value": text[:200]})
+    return found
+
+while True:
+    line = sys.stdin.readline()
+    if not line:
+        break
+    request = json.loads(line)
+    out, err = io.StringIO(), io.StringIO()
+    response = {"id": request["id"]}
+    try:
+        with contextlib.redirect_stdout(out), contextlib.redirect_stderr(err):
+            if request["op"] == "eval":
+                response["result"] = run(request["code"])
+            else:
+                response["vars"] = variables()
+    except BaseException as error:
+        tb = traceback.format_exception(type(error), error, error.__traceback__.tb_next)
+        response["error"] = {"type": type(error).__name__, "message": str(error), "traceback": "".join(tb)}
+    response["stdout"] = out.getvalue()
+    response["stderr"] = err.getvalue()
+    protocol.write(MARK + json.dumps(response) + "\n")
+    protocol.flush()
+"#;
+
+/// Same protocol for node and deno, evaluating in one `vm` context
+const JS_DRIVER: &str = r#"
+import vm from "node:vm";
+import readline from "node:readline";
+import util from "node:util";
+import process from "node:process";
+import { Buffer } from "node:buffer";
+import { createRequire } from "node:module";
+
+const MARK = process.env.HANZO_REPL_MARK;
+const out = [];
+const err = [];
+const capture = (buffer) => (...args) => { buffer.push(util.format(...args)); };
+const console = {
+  log: capture(out), info: capture(out), debug: capture(out), dir: capture(out), table: capture(out),
+  error: capture(err), warn: capture(err), trace: capture(err),
+};
+const context = vm.createContext({
+  console, process, Buffer, URL, URLSearchParams, TextEncoder, TextDecoder, fetch: globalThis.fetch,
+  setTimeout, clearTimeout, setInterval, clearInterval, queueMicrotask, structuredClone,
+  require: createRequire(process.cwd() + "/"),
+});
+const builtins = new Set(Object.keys(context));
+const kind = (value) => value === null ? "null" : Array.isArray(value) ? "array" : value?.constructor?.name ?? typeof value;
+
+for await (const line of readline.createInterface({ input: process.stdin })) {
+  const request = JSON.parse(line);
+  out.length = 0;
+  err.length = 0;
+  const response = { id: request.id, result: null };
+  try {
+    if (request.op === "eval") {
+      let value = vm.runInContext(request.code, context, { filename: "<repl>", timeout: request.timeout_ms });
+      if (value && typeof value.then === "function") value = await value;
+      if (value !== undefined) {
+        context._ = value;
+        response.result = util.inspect(value, { depth: 4 });
+      }
+    } else {
+      response.vars = Object.keys(context)
+        .filter((name) => !builtins.has(name) && !name.startsWith("_"))
+        .map((name) => ({ name, type: kind(context[name]), value: util.inspect(context[name], { depth: 1 }).slice(0, 200) }));
+    }
+  } catch (error) {
+    response.error = { type: error?.name ?? "Error", message: error?.message ?? String(error), traceback: error?.stack ?? "" };
+  }
+  response.stdout = out.length ? out.join("\n") + "\n" : "";
+  response.stderr = err.length ? err.join("\n") + "\n" : "";
+  process.stdout.write(MARK + JSON.stringify(response) + "\n");
+}
+"#;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    Python,
+    Node,
+    Deno,
+}
+
+impl std::str::FromStr for Language {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "python" | "python3" | "py" => Ok(Self::Python),
+            "node" | "nodejs" | "javascript" | "js" => Ok(Self::Node),
+            "deno" | "typescript" | "ts" => Ok(Self::Deno),
+            _ => Err(ToolError::invalid(format!("Unknown language: {} (python, node, deno)", s)).into()),
+        }
+    }
+}
+
+impl Language {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Python => "python",
+            Self::Node => "node",
+            Self::Deno => "deno",
+        }
+    }
+
+    fn driver(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Python => ("driver.py", PYTHON_DRIVER),
+            Self::Node | Self::Deno => ("driver.mjs", JS_DRIVER),
+        }
+    }
+
+    fn command(self, driver: &std::path::Path) -> Command {
+        let mut cmd = match self {
+            Self::Python => {
+                let mut cmd = Command::new("python3");
+                cmd.arg("-u");
+                cmd
+            }
+            Self::Node => Command::new("node"),
+            Self::Deno => {
+                let mut cmd = Command::new("deno");
+                cmd.args(["run", "-A", "--quiet"]);
+                cmd
+            }
+        };
+        cmd.arg(driver);
+        cmd
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplAction {
+    Start,
+    Eval,
+    Vars,
+    Reset,
+    Sessions,
+    Stop,
+    #[default]
+    Help,
+}
+
+impl std::str::FromStr for ReplAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "start" | "new" | "open" => Ok(Self::Start),
+            "eval" | "run" | "exec" => Ok(Self::Eval),
+            "vars" | "variables" | "inspect" => Ok(Self::Vars),
+            "reset" | "restart" => Ok(Self::Reset),
+            "sessions" | "list" => Ok(Self::Sessions),
+            "stop" | "close" | "kill" => Ok(Self::Stop),
+            "help" | "" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplToolArgs {
+    pub action: Option<String>,
+    /// Session id; defaults to the language's own session
+    pub session: Option<String>,
+    /// python, node or deno
+    pub language: Option<String>,
+    /// Code to evaluate
+    pub code: Option<String>,
+    /// Working directory for a new session
+    pub cwd: Option<String>,
+    /// Seconds an eval may run before it is interrupted
+    pub timeout: Option<u64>,
+}
+
+pub struct ReplToolDefinition;
+
+impl ReplToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "repl",
+            "description": "Persistent python/node/deno sessions: start, eval (state is kept between calls; a trailing expression's value is returned), vars, reset, sessions, stop, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["start", "eval", "vars", "reset", "sessions", "stop", "help"],
+                        "description": "REPL action"
+                    },
+                    "session": { "type": "string", "description": "Session id; eval without one uses (and starts) the language's default session" },
+                    "language": { "type": "string", "enum": ["python", "node", "deno"], "description": "Interpreter for a new session" },
+                    "code": { "type": "string", "description": "For eval: code to run" },
+                    "cwd": { "type": "string", "description": "For start: working directory" },
+                    "timeout": { "type": "integer", "description": "For eval: seconds before the code is interrupted", "default": EVAL_SECS }
+                },
+                "required": ["action"]
+            }
+        })
+    }
+}
+
+struct Session {
+    language: Language,
+    cwd: PathBuf,
+    mark: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::UnboundedReceiver<String>,
+    stderr: Arc<std::sync::Mutex<String>>,
+    evals: u64,
+    next_id: u64,
+    started: chrono::DateTime<chrono::Utc>,
+}
+
+impl Session {
+    async fn spawn(language: Language, cwd: PathBuf) -> Result<Self> {
+        let dir = std::env::temp_dir().join("hanzo-mcp-repl");
+        tokio::fs::create_dir_all(&dir).await?;
+        let (file, source) = language.driver();
+        let driver = dir.join(file);
+        tokio::fs::write(&driver, source).await?;
+
+        let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let mark = format!("\u{1e}hanzo-repl:{:x}:", nanos);
+        let mut child = language.command(&driver)
+            .current_dir(&cwd)
+            .env("HANZO_REPL_MARK", &mark)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => ToolError::unsupported(format!("{} is not installed", language.name())),
+                _ => ToolError::external(format!("Cannot start {}: {}", language.name(), e)),
+            })?;
+
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+        let (tx, lines) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok(Some(line)) = stdout.next_line().await {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let stderr = Arc::new(std::sync::Mutex::new(String::new()));
+        let mut pipe = child.stderr.take().expect("stderr is piped");
+        let sink = stderr.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = pipe.read(&mut buf).await {
+                if n == 0 {
+                    break;
+                }
+                sink.lock().unwrap().push_str(&String::from_utf8_lossy(&buf[..n]));
+            }
+        });
+
+        Ok(Self {
+            language,
+            cwd,
+            mark,
+            child,
+            stdin,
+            lines,
+            stderr,
+            evals: 0,
+            next_id: 1,
+            started: chrono::Utc::now(),
+        })
+    }
+
+    /// Send one request and wait for its answer; None if `limit` passed
+    /// or the interpreter exited first
+    async fn call(&mut self, mut request: Value, limit: Duration) -> Result<Option<(Value, String)>> {
+        let id = self.next_id;
+        self.next_id += 1;
+        request["id"] = json!(id);
+        request["timeout_ms"] = json!(limit.as_millis() as u64);
+        let line = format!("{}\n", request);
+        self.stdin.write_all(line.as_bytes()).await
+            .map_err(|e| ToolError::external(format!("{} session has exited: {}", self.language.name(), e)))?;
+        self.stdin.flush().await?;
+
+        let deadline = tokio::time::Instant::now() + limit;
+        let mut stray = String::new();
+        loop {
+            match tokio::time::timeout_at(deadline, self.lines.recv()).await {
+                Ok(Some(line)) => match line.strip_prefix(self.mark.as_str()) {
+                    Some(message) => {
+                        let response: Value = serde_json::from_str(message)?;
+                        if response["id"] == json!(id) {
+                            return Ok(Some((response, stray)));
+                        }
+                    }
+                    None => {
+                        stray.push_str(&line);
+                        stray.push('\n');
+                    }
+                },
+                Ok(None) | Err(_) => return Ok(None),
+            }
+        }
+    }
+
+    /// Output the interpreter wrote to its own stderr since the last call
+    fn take_stderr(&self) -> String {
+        std::mem::take(&mut *self.stderr.lock().unwrap())
+    }
+
+    fn interrupt(&self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.id() {
+            use nix::sys::signal::{kill, Signal};
+            use nix::unistd::Pid;
+            let _ = kill(Pid::from_raw(pid as i32), Signal::SIGINT);
+        }
+    }
+
+    fn describe(&mut self, id: &str) -> Value {
+        json!({
+            "session": id,
+            "language": self.language.name(),
+            "cwd": self.cwd,
+            "pid": self.child.id(),
+            "running": matches!(self.child.try_wait(), Ok(None)),
+            "evals": self.evals,
+            "started": self.started.to_rfc3339()
+        })
+    }
+}
+
+pub struct ReplTool {
+    sessions: Mutex<HashMap<String, Arc<Mutex<Session>>>>,
+}
+
+impl Default for ReplTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReplTool {
+    pub fn new() -> Self {
+        Self { sessions: Mutex::new(HashMap::new()) }
+    }
+
+    pub async fn execute(&self, args: ReplToolArgs) -> Result<Value> {
+        let action: ReplAction = args.action.as_deref().unwrap_or("help").parse()?;
+
+        let data = match action {
+            ReplAction::Start => self.start(&args).await?,
+            ReplAction::Eval => self.eval(&args).await?,
+            ReplAction::Vars => self.vars(&args).await?,
+            ReplAction::Reset => self.reset(&args).await?,
+            ReplAction::Sessions => self.list().await,
+            ReplAction::Stop => self.stop(&args).await?,
+            ReplAction::Help => return Ok(self.help()),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "repl", "action": format!("{:?}", action).to_lowercase() }
+        }))
+    }
+
+    fn language(args: &ReplToolArgs) -> Result<Option<Language>> {
+        args.language.as_deref().map(str::parse).transpose()
+    }
+
+    /// Session id an action addresses: explicit, else the language's own
+    fn session_id(args: &ReplToolArgs) -> Result<String> {
+        match (&args.session, Self::language(args)?) {
+            (Some(session), _) => Ok(session.clone()),
+            (None, Some(language)) => Ok(language.name().to_string()),
+            (None, None) => Err(ToolError::invalid("session or language required").into()),
+        }
+    }
+
+    async fn session(&self, id: &str) -> Result<Arc<Mutex<Session>>> {
+        self.sessions.lock().await.get(id).cloned()
+            .ok_or_else(|| ToolError::not_found(format!("No REPL session '{}'; start one first", id)).into())
+    }
+
+    async fn start(&self, args: &ReplToolArgs) -> Result<Value> {
+        let language = Self::language(args)?
+            .or_else(|| args.session.as_deref().and_then(|s| s.parse().ok()))
+            .ok_or_else(|| ToolError::invalid("language required (python, node, deno)"))?;
+        let id = args.session.clone().unwrap_or_else(|| language.name().to_string());
+        let mut sessions = self.sessions.lock().await;
+        if sessions.contains_key(&id) {
+            return Err(ToolError::conflict(format!("REPL session '{}' already exists", id)).into());
+        }
+        let cwd = match &args.cwd {
+            Some(cwd) => PathBuf::from(shellexpand::tilde(cwd).to_string()),
+            None => std::env::current_dir()?,
+        };
+        let mut session = Session::spawn(language, cwd).await?;
+        let info = session.describe(&id);
+        sessions.insert(id, Arc::new(Mutex::new(session)));
+        Ok(info)
+    }
+
+    async fn eval(&self, args: &ReplToolArgs) -> Result<Value> {
+        let code = args.code.as_deref().ok_or_else(|| ToolError::invalid("code required"))?;
+        let id = Self::session_id(args)?;
+        let existing = self.sessions.lock().await.get(&id).cloned();
+        let session = match existing {
+            Some(session) => session,
+            None => {
+                self.start(&ReplToolArgs { session: Some(id.clone()), ..args.clone() }).await?;
+                self.session(&id).await?
+            }
+        };
+        let mut session = session.lock().await;
+        let limit = Duration::from_secs(args.timeout.unwrap_or(EVAL_SECS).max(1));
+        let started = Instant::now();
+        let request = json!({ "op": "eval", "code": code });
+
+        let (response, stray) = match session.call(request, limit).await? {
+            Some(answer) => answer,
+            None => {
+                // Python turns SIGINT into KeyboardInterrupt and keeps its
+                // namespace; node's own vm timeout has already fired
+                session.interrupt();
+                match session.call(json!({ "op": "vars" }), Duration::from_secs(2)).await? {
+                    Some(_) => {
+                        return Err(ToolError::timeout(format!(
+                            "Eval interrupted after {}s; session '{}' kept its state", limit.as_secs(), id
+                        )).into());
+                    }
+                    None => {
+                        let _ = session.child.kill().await;
+                        drop(session);
+                        self.sessions.lock().await.remove(&id);
+                        return Err(ToolError::timeout(format!(
+                            "Eval did not finish within {}s; session '{}' was stopped and its state lost", limit.as_secs(), id
+                        )).into());
+                    }
+                }
+            }
+        };
+        session.evals += 1;
+        let stdout = format!("{}{}", stray, response["stdout"].as_str().unwrap_or(""));
+        let stderr = format!("{}{}", session.take_stderr(), response["stderr"].as_str().unwrap_or(""));
+        Ok(json!({
+            "session": id,
+            "language": session.language.name(),
+            "result": response["result"].as_str().map(truncate),
+            "stdout": truncate(&stdout),
+            "stderr": truncate(&stderr),
+            "error": response["error"],
+            "success": response["error"].is_null(),
+            "execution_count": session.evals,
+            "duration_ms": started.elapsed().as_millis() as u64
+        }))
+    }
+
+    async fn vars(&self, args: &ReplToolArgs) -> Result<Value> {
+        let id = Self::session_id(args)?;
+        let session = self.session(&id).await?;
+        let mut session = session.lock().await;
+        let (response, _) = session.call(json!({ "op": "vars" }), Duration::from_secs(10)).await?
+            .ok_or_else(|| ToolError::timeout(format!("Session '{}' did not answer", id)))?;
+        let vars = response["vars"].as_array().cloned().unwrap_or_default();
+        Ok(json!({ "session": id, "language": session.language.name(), "vars": vars, "count": vars.len() }))
+    }
+
+    /// Replace the interpreter with a fresh one, keeping id and directory
+    async fn reset(&self, args: &ReplToolArgs) -> Result<Value> {
+        let id = Self::session_id(args)?;
+        let session = self.session(&id).await?;
+        let mut session = session.lock().await;
+        let _ = session.child.kill().await;
+        *session = Session::spawn(session.language, session.cwd.clone()).await?;
+        Ok(json!({ "reset": true, "session": session.describe(&id) }))
+    }
+
+    async fn list(&self) -> Value {
+        let sessions = self.sessions.lock().await;
+        let mut list = Vec::new();
+        for (id, session) in sessions.iter() {
+            list.push(session.lock().await.describe(id));
+        }
+        list.sort_by(|a, b| a["session"].as_str().cmp(&b["session"].as_str()));
+        json!({ "sessions": list, "count": list.len() })
+    }
+
+    async fn stop(&self, args: &ReplToolArgs) -> Result<Value> {
+        let id = Self::session_id(args)?;
+        let session = self.sessions.lock().await.remove(&id)
+            .ok_or_else(|| ToolError::not_found(format!("No REPL session '{}'", id)))?;
+        let _ = session.lock().await.child.kill().await;
+        Ok(json!({ "session": id, "stopped": true }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "repl",
+                "languages": ["python", "node", "deno"],
+                "actions": {
+                    "start": "Start a session (language; session names it, cwd sets its directory)",
+                    "eval": "Run code in a session, starting the language's default session if needed",
+                    "vars": "List the session's variables with type and value",
+                    "reset": "Restart the session's interpreter, clearing its state",
+                    "sessions": "List sessions",
+                    "stop": "End a session"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "repl", "action": "help" }
+        })
+    }
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_OUTPUT) {
+        Some((end, _)) => format!("{}\n... [truncated]", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(language: &str, code: &str) -> ReplToolArgs {
+        ReplToolArgs {
+            action: Some("eval".into()),
+            language: Some(language.into()),
+            code: Some(code.into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_python_session() {
+        if which::which("python3").is_err() {
+            return;
+        }
+        let tool = ReplTool::new();
+        let first = tool.execute(eval("python", "import math\nx = 21\nprint('hi')\nx * 2")).await.unwrap();
+        assert_eq!(first["data"]["result"], "42");
+        assert_eq!(first["data"]["stdout"], "hi\n");
+
+        let second = tool.execute(eval("python", "math.sqrt(x + 4)")).await.unwrap();
+        assert_eq!(second["data"]["result"], "5.0");
+        assert_eq!(second["data"]["execution_count"], 2);
+
+        let failed = tool.execute(eval("python", "1 / 0")).await.unwrap();
+        assert_eq!(failed["data"]["success"], false);
+        assert_eq!(failed["data"]["error"]["type"], "ZeroDivisionError");
+
+        let vars = tool.execute(ReplToolArgs { action: Some("vars".into()), language: Some("python".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(vars["data"]["vars"][0]["name"], "x");
+        assert_eq!(vars["data"]["count"], 1);
+
+        let mut slow = eval("python", "import time\ntime.sleep(30)");
+        slow.timeout = Some(1);
+        assert!(tool.execute(slow).await.is_err());
+        assert_eq!(tool.execute(eval("python", "x")).await.unwrap()["data"]["result"], "21");
+
+        tool.execute(ReplToolArgs { action: Some("reset".into()), language: Some("python".into()), ..Default::default() }).await.unwrap();
+        let gone = tool.execute(eval("python", "x")).await.unwrap();
+        assert_eq!(gone["data"]["error"]["type"], "NameError");
+
+        let stopped = tool.execute(ReplToolArgs { action: Some("stop".into()), session: Some("python".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(stopped["data"]["stopped"], true);
+        assert_eq!(tool.list().await["count"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_node_session() {
+        if which::which("node").is_err() {
+            return;
+        }
+        let tool = ReplTool::new();
+        let first = tool.execute(eval("node", "var items = [1, 2, 3]; console.log('n', items.length); items.map(i => i * 2)")).await.unwrap();
+        assert_eq!(first["data"]["result"], "[ 2, 4, 6 ]");
+        assert_eq!(first["data"]["stdout"], "n 3\n");
+        let second = tool.execute(eval("node", "await_ = Promise.resolve(items.length)")).await.unwrap();
+        assert_eq!(second["data"]["result"], "3");
+        let vars = tool.execute(ReplToolArgs { action: Some("vars".into()), language: Some("node".into()), ..Default::default() }).await.unwrap();
+        assert_eq!(vars["data"]["vars"][0]["name"], "items");
+    }
+}
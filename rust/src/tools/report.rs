@@ -0,0 +1,224 @@
+/// Markdown run reports
+///
+/// Combines the active plan, the think journal and the session's recent
+/// tool calls into one Markdown document that reads well pasted into a PR
+/// description or kept as a run artifact. Inputs are the JSON the plan
+/// tool, think tool and audit log already produce.
+
+use serde_json::Value;
+use std::fmt::Write;
+
+/// Audit entries a report lists unless told otherwise
+pub const DEFAULT_CALLS: usize = 50;
+
+/// Longest thought or error quoted before it is cut
+const MAX_TEXT_CHARS: usize = 400;
+
+/// What goes into a report; sections left `None` are omitted
+#[derive(Debug, Default)]
+pub struct Report<'a> {
+    pub title: Option<&'a str>,
+    pub session: Option<&'a str>,
+    /// Output of `plan get`
+    pub plan: Option<&'a Value>,
+    /// Think journal entries, oldest first
+    pub thoughts: Option<&'a [Value]>,
+    /// Audit log entries, oldest first
+    pub calls: Option<&'a [Value]>,
+}
+
+impl Report<'_> {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let plan_name = self.plan.and_then(|p| p["name"].as_str());
+        let title = self.title.or(plan_name).unwrap_or("Run report");
+        let _ = writeln!(out, "# {}\n", title);
+        let mut meta = format!("Generated {}", chrono::Utc::now().format("%Y-%m-%d %H:%M UTC"));
+        if let Some(session) = self.session {
+            let _ = write!(meta, " for session `{}`", session);
+        }
+        let _ = writeln!(out, "_{}_\n", meta);
+        if let Some(plan) = self.plan {
+            plan_section(&mut out, plan);
+        }
+        if let Some(thoughts) = self.thoughts {
+            thought_section(&mut out, thoughts);
+        }
+        if let Some(calls) = self.calls {
+            call_section(&mut out, calls);
+        }
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        out
+    }
+}
+
+fn plan_section(out: &mut String, plan: &Value) {
+    out.push_str("## Plan\n\n");
+    let Some(steps) = plan["steps"].as_array().filter(|s| !s.is_empty()) else {
+        out.push_str("No plan.\n\n");
+        return;
+    };
+    let count = |key: &str| plan[key].as_u64().unwrap_or(0);
+    let _ = write!(out, "{}/{} steps done", count("completed"), steps.len());
+    for key in ["in_progress", "blocked", "failed"] {
+        if count(key) > 0 {
+            let _ = write!(out, ", {} {}", count(key), key.replace('_', " "));
+        }
+    }
+    out.push_str("\n\n");
+    // Subtasks follow their parent in the plan, so depth is the parent's plus one
+    let mut depths: Vec<(u64, usize)> = Vec::new();
+    for step in steps {
+        let depth = step["parent"].as_u64()
+            .and_then(|parent| depths.iter().find(|(id, _)| *id == parent))
+            .map_or(0, |(_, depth)| depth + 1);
+        depths.extend(step["id"].as_u64().map(|id| (id, depth)));
+        let status = step["status"].as_str().unwrap_or("pending");
+        let mark = if status == "completed" { 'x' } else { ' ' };
+        let _ = write!(out, "{}- [{}] {}", "  ".repeat(depth), mark, one_line(step["description"].as_str().unwrap_or("")));
+        if !matches!(status, "pending" | "completed") {
+            let _ = write!(out, " _({})_", status.replace('_', " "));
+        }
+        if let Some(error) = step["error"].as_str() {
+            let _ = write!(out, ": {}", one_line(error));
+        }
+        out.push('\n');
+    }
+    out.push('\n');
+}
+
+fn thought_section(out: &mut String, thoughts: &[Value]) {
+    out.push_str("## Reasoning\n\n");
+    if thoughts.is_empty() {
+        out.push_str("No recorded thoughts.\n\n");
+        return;
+    }
+    let mut branches: Vec<&str> = Vec::new();
+    for entry in thoughts {
+        let branch = entry["branch"].as_str().unwrap_or("main");
+        if !branches.contains(&branch) {
+            branches.push(branch);
+        }
+    }
+    for branch in branches {
+        if thoughts.iter().any(|e| e["branch"].as_str().unwrap_or("main") != branch) {
+            let _ = writeln!(out, "### Branch `{}`\n", branch);
+        }
+        for entry in thoughts.iter().filter(|e| e["branch"].as_str().unwrap_or("main") == branch) {
+            let _ = write!(out, "- **#{}** ", entry["id"]);
+            if let (Some(relation), Some(parent)) = (entry["relation"].as_str(), entry["parent"].as_u64()) {
+                let _ = write!(out, "_{} #{}_ ", relation, parent);
+            }
+            if entry["action"] != "think" {
+                let _ = write!(out, "({}) ", entry["action"].as_str().unwrap_or(""));
+            }
+            let _ = writeln!(out, "{}", indent(&cut(entry["thought"].as_str().unwrap_or(""))));
+            if let Some(context) = entry["context"].as_str().filter(|c| !c.is_empty()) {
+                let _ = writeln!(out, "  > {}", one_line(context));
+            }
+        }
+        out.push('\n');
+    }
+}
+
+fn call_section(out: &mut String, calls: &[Value]) {
+    out.push_str("## Tool calls\n\n");
+    if calls.is_empty() {
+        out.push_str("No recorded calls.\n\n");
+        return;
+    }
+    let failed = calls.iter().filter(|c| c["success"] == false).count();
+    let plural = if calls.len() == 1 { "" } else { "s" };
+    let _ = writeln!(out, "{} call{}, {} failed\n", calls.len(), plural, failed);
+    out.push_str("| Time | Call | Duration | Result |\n|---|---|---|---|\n");
+    for call in calls {
+        let time = call["ts"].as_str()
+            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&chrono::Utc).format("%H:%M:%S").to_string())
+            .unwrap_or_default();
+        let name = match call["action"].as_str() {
+            Some(action) => format!("{}.{}", call["tool"].as_str().unwrap_or(""), action),
+            None => call["tool"].as_str().unwrap_or("").to_string(),
+        };
+        let result = if call["success"] == false {
+            match call["error"].as_str() {
+                Some(error) => format!("failed: {}", one_line(error)),
+                None => "failed".to_string(),
+            }
+        } else {
+            "ok".to_string()
+        };
+        let _ = writeln!(out, "| {} | `{}` | {} ms | {} |", time, name, call["duration_ms"].as_u64().unwrap_or(0), cell(&result));
+    }
+    out.push('\n');
+}
+
+fn cut(text: &str) -> String {
+    match text.char_indices().nth(MAX_TEXT_CHARS) {
+        Some((at, _)) => format!("{}…", text[..at].trim_end()),
+        None => text.trim().to_string(),
+    }
+}
+
+fn one_line(text: &str) -> String {
+    cut(&text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Continuation lines indented to stay inside their list item
+fn indent(text: &str) -> String {
+    text.lines().collect::<Vec<_>>().join("\n  ")
+}
+
+/// Text safe inside a table cell
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let plan = json!({
+            "name": "ship it",
+            "completed": 1,
+            "failed": 1,
+            "steps": [
+                { "id": 1, "description": "Write code", "status": "completed" },
+                { "id": 2, "description": "Test", "status": "failed", "error": "2 failures" },
+                { "id": 3, "description": "Unit tests", "status": "pending", "parent": 2 }
+            ]
+        });
+        let thoughts = [
+            json!({ "id": 1, "action": "think", "thought": "Start with the parser\nthen the CLI", "branch": "main" }),
+            json!({ "id": 2, "action": "think", "thought": "Or a rewrite", "branch": "alt", "parent": 1, "relation": "contradicts" }),
+        ];
+        let calls = [
+            json!({ "ts": "2026-01-02T03:04:05Z", "tool": "fs", "action": "read", "duration_ms": 3, "success": true }),
+            json!({ "ts": "2026-01-02T03:04:06Z", "tool": "exec", "duration_ms": 900, "success": false, "error": "exit 1 | boom" }),
+        ];
+        let report = Report { session: Some("s1"), plan: Some(&plan), thoughts: Some(&thoughts), calls: Some(&calls), ..Default::default() }.render();
+
+        assert!(report.starts_with("# ship it\n"));
+        assert!(report.contains("for session `s1`"));
+        assert!(report.contains("1/3 steps done, 1 failed"));
+        assert!(report.contains("- [x] Write code\n- [ ] Test _(failed)_: 2 failures\n  - [ ] Unit tests\n"));
+        assert!(report.contains("### Branch `alt`"));
+        assert!(report.contains("- **#1** Start with the parser\n  then the CLI"));
+        assert!(report.contains("- **#2** _contradicts #1_ Or a rewrite"));
+        assert!(report.contains("2 calls, 1 failed"));
+        assert!(report.contains("| 03:04:05 | `fs.read` | 3 ms | ok |"));
+        assert!(report.contains("| `exec` | 900 ms | failed: exit 1 \\| boom |"));
+
+        let empty = Report { title: Some("Nightly"), ..Default::default() }.render();
+        assert!(empty.starts_with("# Nightly\n"));
+        assert!(!empty.contains("## Plan"));
+        let no_steps = Report { plan: Some(&json!({ "message": "No plan set" })), thoughts: Some(&[]), ..Default::default() }.render();
+        assert!(no_steps.starts_with("# Run report\n"));
+        assert!(no_steps.contains("No plan."));
+        assert!(no_steps.contains("No recorded thoughts."));
+    }
+}
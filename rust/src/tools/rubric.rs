@@ -0,0 +1,333 @@
+/// Rubric scoring for think critic/review actions.
+///
+/// Scores code, diffs or plan text against weighted criteria using static
+/// heuristics. Each criterion starts at 10 and loses points per finding by
+/// severity. Scores from an LLM pass over MCP sampling can be blended in.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+
+/// Finding severity
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+impl Severity {
+    fn penalty(&self) -> f64 {
+        match self {
+            Self::Low => 0.5,
+            Self::Medium => 1.5,
+            Self::High => 3.0,
+        }
+    }
+}
+
+/// Built-in rubric criterion
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Criterion {
+    Correctness,
+    Security,
+    Style,
+}
+
+impl Criterion {
+    pub const ALL: [Criterion; 3] = [Self::Correctness, Self::Security, Self::Style];
+}
+
+impl std::str::FromStr for Criterion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "correctness" | "bugs" => Ok(Self::Correctness),
+            "security" | "safety" => Ok(Self::Security),
+            "style" | "readability" => Ok(Self::Style),
+            _ => Err(ToolError::invalid(format!("Unknown rubric criterion: {} (correctness, security, style)", s)).into()),
+        }
+    }
+}
+
+/// A line-level pattern check
+struct Rule {
+    id: &'static str,
+    criterion: Criterion,
+    severity: Severity,
+    pattern: Regex,
+    message: &'static str,
+}
+
+fn rule(id: &'static str, criterion: Criterion, severity: Severity, pattern: &str, message: &'static str) -> Rule {
+    Rule {
+        id,
+        criterion,
+        severity,
+        pattern: Regex::new(pattern).expect("valid rubric pattern"),
+        message,
+    }
+}
+
+static RULES: Lazy<Vec<Rule>> = Lazy::new(|| {
+    use Criterion::*;
+    use Severity::*;
+    vec![
+        // Security
+        rule("hardcoded-secret", Security, High,
+            r#"(?i)(password|passwd|secret|api[_-]?key|token)\s*[:=]\s*["'][^"']{4,}["']"#,
+            "Possible hardcoded credential"),
+        rule("eval", Security, High, r"\beval\s*\(", "Dynamic code evaluation"),
+        rule("shell-injection", Security, High,
+            r"shell\s*=\s*True|os\.system\s*\(|child_process\.exec\s*\(",
+            "Command executed through a shell"),
+        rule("sql-concat", Security, High,
+            r#"(?i)["'](select|insert|update|delete)\s[^"']*["']\s*(\+|%|\.format)"#,
+            "SQL built by string concatenation"),
+        rule("tls-disabled", Security, High,
+            r"verify\s*=\s*False|rejectUnauthorized\s*:\s*false|danger_accept_invalid_certs\(true\)",
+            "TLS certificate verification disabled"),
+        rule("weak-hash", Security, Medium, r"(?i)\b(md5|sha1)\s*\(", "Weak hash function"),
+        rule("unsafe-block", Security, Medium, r"\bunsafe\s*\{", "unsafe block needs a safety justification"),
+        rule("world-writable", Security, Medium, r"chmod\s+(0?777|a\+w)", "World-writable permissions"),
+        rule("plain-http", Security, Low, r#"["']http://[^"'\s]*["']"#, "Plain HTTP URL"),
+        // Correctness
+        rule("unwrap", Correctness, Medium, r"\.unwrap\(\)", "unwrap() panics on error"),
+        rule("todo-macro", Correctness, High, r"\b(todo|unimplemented)!\s*\(", "Unimplemented code path"),
+        rule("bare-except", Correctness, Medium, r"^\s*except\s*:", "Bare except swallows all errors"),
+        rule("empty-catch", Correctness, Medium, r"catch\s*(\([^)]*\))?\s*\{\s*\}", "Empty catch block"),
+        rule("none-equality", Correctness, Low, r"[!=]=\s*None\b", "Compare to None with `is`"),
+        rule("loose-equality", Correctness, Low, r"[^=!<>]==[^=]\s*(null|undefined)\b", "Loose equality against null"),
+        rule("todo-comment", Correctness, Low, r"\b(TODO|FIXME|XXX|HACK)\b", "Unresolved TODO/FIXME"),
+        // Style
+        rule("debug-print", Style, Low, r"console\.log\(|\bdbg!\(|pdb\.set_trace\(|\bbreakpoint\(\)", "Leftover debug output"),
+        rule("trailing-whitespace", Style, Low, r"[ \t]+$", "Trailing whitespace"),
+    ]
+});
+
+/// Lines longer than this are reported under style
+const MAX_LINE_LENGTH: usize = 120;
+
+/// A single rubric finding
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub line: usize,
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub message: &'static str,
+    pub excerpt: String,
+}
+
+/// Score for one criterion
+#[derive(Debug, Clone, Serialize)]
+pub struct CriterionScore {
+    pub criterion: Criterion,
+    pub weight: f64,
+    pub score: f64,
+    pub findings: Vec<Finding>,
+}
+
+/// Full rubric evaluation
+#[derive(Debug, Clone, Serialize)]
+pub struct Evaluation {
+    pub input_kind: &'static str,
+    pub lines_evaluated: usize,
+    pub overall_score: f64,
+    pub verdict: &'static str,
+    pub criteria: Vec<CriterionScore>,
+}
+
+/// Parse a rubric spec: a list of criterion names, or an object of
+/// criterion -> weight. `None` selects `defaults` with weight 1.
+pub fn parse_rubric(spec: Option<&Value>, defaults: &[Criterion]) -> Result<Vec<(Criterion, f64)>> {
+    match spec {
+        None | Some(Value::Null) => Ok(defaults.iter().map(|c| (*c, 1.0)).collect()),
+        Some(Value::String(s)) => s.split(',').map(|name| Ok((name.trim().parse()?, 1.0))).collect(),
+        Some(Value::Array(names)) => names
+            .iter()
+            .map(|v| {
+                let name = v.as_str().ok_or_else(|| ToolError::invalid("rubric entries must be strings"))?;
+                Ok((name.parse()?, 1.0))
+            })
+            .collect(),
+        Some(Value::Object(weights)) => weights
+            .iter()
+            .map(|(name, w)| {
+                let weight = w.as_f64().filter(|w| *w > 0.0)
+                    .ok_or_else(|| ToolError::invalid(format!("rubric weight for {} must be a positive number", name)))?;
+                Ok((name.parse()?, weight))
+            })
+            .collect(),
+        Some(_) => Err(ToolError::invalid("rubric must be a list of criteria or an object of weights").into()),
+    }
+}
+
+/// Collect (line number, text) pairs to evaluate. For unified diffs only
+/// added lines are considered, numbered by their position in the new file.
+fn evaluated_lines(input: &str) -> (&'static str, Vec<(usize, &str)>) {
+    let is_diff = input.starts_with("diff --git")
+        || input.starts_with("--- ")
+        || input.lines().any(|l| l.starts_with("@@ "));
+    if !is_diff {
+        return ("text", input.lines().enumerate().map(|(i, l)| (i + 1, l)).collect());
+    }
+
+    let hunk = Regex::new(r"^@@ -\d+(?:,\d+)? \+(\d+)").expect("valid hunk pattern");
+    let mut lines = Vec::new();
+    let mut new_line = 0;
+    for line in input.lines() {
+        if let Some(caps) = hunk.captures(line) {
+            new_line = caps[1].parse().unwrap_or(1);
+        } else if line.starts_with("+++") || line.starts_with("---") {
+            continue;
+        } else if let Some(added) = line.strip_prefix('+') {
+            lines.push((new_line, added));
+            new_line += 1;
+        } else if !line.starts_with('-') {
+            new_line += 1;
+        }
+    }
+    ("diff", lines)
+}
+
+/// Evaluate input text against the rubric
+pub fn evaluate(input: &str, rubric: &[(Criterion, f64)]) -> Evaluation {
+    let (input_kind, lines) = evaluated_lines(input);
+
+    let criteria: Vec<CriterionScore> = rubric
+        .iter()
+        .map(|(criterion, weight)| {
+            let mut findings = Vec::new();
+            for (line_no, text) in &lines {
+                for rule in RULES.iter().filter(|r| r.criterion == *criterion) {
+                    if rule.pattern.is_match(text) {
+                        findings.push(Finding {
+                            line: *line_no,
+                            rule: rule.id,
+                            severity: rule.severity,
+                            message: rule.message,
+                            excerpt: text.trim().chars().take(160).collect(),
+                        });
+                    }
+                }
+                if *criterion == Criterion::Style && text.chars().count() > MAX_LINE_LENGTH {
+                    findings.push(Finding {
+                        line: *line_no,
+                        rule: "long-line",
+                        severity: Severity::Low,
+                        message: "Line exceeds 120 characters",
+                        excerpt: text.chars().take(80).collect(),
+                    });
+                }
+            }
+            let penalty: f64 = findings.iter().map(|f| f.severity.penalty()).sum();
+            CriterionScore {
+                criterion: *criterion,
+                weight: *weight,
+                score: round1((10.0 - penalty).max(0.0)),
+                findings,
+            }
+        })
+        .collect();
+
+    let (overall_score, verdict) = summary(&criteria);
+    Evaluation {
+        input_kind,
+        lines_evaluated: lines.len(),
+        overall_score,
+        verdict,
+        criteria,
+    }
+}
+
+impl Evaluation {
+    /// Blend an LLM's 0-10 criterion scores into the heuristic ones: each
+    /// criterion it scored takes the mean of both, then the overall score
+    /// and verdict are worked out again
+    pub fn blend(&mut self, scores: &[(Criterion, f64)]) {
+        for criterion in &mut self.criteria {
+            if let Some((_, score)) = scores.iter().find(|(c, _)| *c == criterion.criterion) {
+                criterion.score = round1((criterion.score + score.clamp(0.0, 10.0)) / 2.0);
+            }
+        }
+        (self.overall_score, self.verdict) = summary(&self.criteria);
+    }
+}
+
+/// Weighted overall score and verdict; any high-severity finding fails
+fn summary(criteria: &[CriterionScore]) -> (f64, &'static str) {
+    let total_weight: f64 = criteria.iter().map(|c| c.weight).sum();
+    let overall_score = if total_weight > 0.0 {
+        round1(criteria.iter().map(|c| c.score * c.weight).sum::<f64>() / total_weight)
+    } else {
+        10.0
+    };
+    let has_high = criteria.iter().flat_map(|c| &c.findings).any(|f| f.severity == Severity::High);
+    let verdict = if has_high || overall_score < 5.0 {
+        "fail"
+    } else if overall_score < 8.0 {
+        "needs_work"
+    } else {
+        "pass"
+    };
+    (overall_score, verdict)
+}
+
+fn round1(v: f64) -> f64 {
+    (v * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_rubric() {
+        let defaults = [Criterion::Correctness];
+        assert_eq!(parse_rubric(None, &defaults).unwrap(), vec![(Criterion::Correctness, 1.0)]);
+        assert_eq!(
+            parse_rubric(Some(&json!({"security": 2.0})), &defaults).unwrap(),
+            vec![(Criterion::Security, 2.0)]
+        );
+        assert!(parse_rubric(Some(&json!(["nonsense"])), &defaults).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_flags_security_issue() {
+        let code = "let password = \"hunter22\";\nlet x = y.unwrap();\n";
+        let rubric = parse_rubric(None, &Criterion::ALL).unwrap();
+        let eval = evaluate(code, &rubric);
+        assert_eq!(eval.verdict, "fail");
+        let security = eval.criteria.iter().find(|c| c.criterion == Criterion::Security).unwrap();
+        assert_eq!(security.findings[0].rule, "hardcoded-secret");
+        assert_eq!(security.findings[0].line, 1);
+    }
+
+    #[test]
+    fn test_evaluate_diff_only_added_lines() {
+        let diff = "--- a/x.py\n+++ b/x.py\n@@ -10,2 +10,3 @@\n context\n-eval(old)\n+value = eval(data)\n";
+        let eval = evaluate(diff, &[(Criterion::Security, 1.0)]);
+        assert_eq!(eval.input_kind, "diff");
+        assert_eq!(eval.lines_evaluated, 1);
+        assert_eq!(eval.criteria[0].findings.len(), 1);
+        assert_eq!(eval.criteria[0].findings[0].line, 11);
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut eval = evaluate("let x = y.unwrap();\n", &[(Criterion::Correctness, 1.0), (Criterion::Style, 1.0)]);
+        assert_eq!(eval.verdict, "pass");
+        eval.blend(&[(Criterion::Correctness, 3.0), (Criterion::Security, 0.0)]);
+        assert_eq!(eval.criteria[0].score, 5.8);
+        assert_eq!(eval.criteria[1].score, 10.0);
+        assert_eq!(eval.overall_score, 7.9);
+        assert_eq!(eval.verdict, "needs_work");
+    }
+}
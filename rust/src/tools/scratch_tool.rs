@@ -0,0 +1,396 @@
+/// Scratchpad for passing large text between tool calls by handle
+///
+/// Actions: store, get, append, delete, list, help
+///
+/// Entries live in memory until their TTL passes and are also readable as
+/// `scratch://<handle>` resources. Content can come from and go to files
+/// directly (`path`, `to_path`) so a big diff or log never has to pass
+/// through the conversation to move from one tool to another.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::RwLock;
+
+pub const SCRATCH_URI_PREFIX: &str = "scratch://";
+
+/// Seconds an entry lives unless `ttl` says otherwise
+const DEFAULT_TTL_SECS: i64 = 3600;
+
+/// Bytes held across all entries
+const MAX_TOTAL_BYTES: usize = 256 * 1024 * 1024;
+
+/// Characters `get` returns per call unless `limit` says otherwise
+const DEFAULT_LIMIT: usize = 100_000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScratchAction {
+    Store,
+    Get,
+    Append,
+    Delete,
+    List,
+    #[default]
+    Help,
+}
+
+impl std::str::FromStr for ScratchAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "store" | "put" | "set" | "save" => Ok(Self::Store),
+            "get" | "read" | "load" => Ok(Self::Get),
+            "append" | "add" => Ok(Self::Append),
+            "delete" | "remove" | "rm" => Ok(Self::Delete),
+            "list" | "ls" => Ok(Self::List),
+            "help" | "" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScratchToolArgs {
+    pub action: Option<String>,
+    /// Entry handle; store picks one when omitted
+    pub handle: Option<String>,
+    /// Text to store or append
+    pub content: Option<String>,
+    /// File to take the content from instead of `content`
+    pub path: Option<String>,
+    /// For get: file to write the entry to instead of returning it
+    pub to_path: Option<String>,
+    /// Seconds until the entry expires (0 keeps it until deleted)
+    pub ttl: Option<i64>,
+    /// Free-form note shown by list
+    pub description: Option<String>,
+    /// For get: character offset to start at
+    pub offset: Option<usize>,
+    /// For get: characters to return
+    pub limit: Option<usize>,
+}
+
+pub struct ScratchToolDefinition;
+
+impl ScratchToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "scratch",
+            "description": "Scratchpad for large text passed between tool calls by handle: store, get, append, delete, list, help. Entries expire after ttl and are readable as scratch://<handle> resources",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["store", "get", "append", "delete", "list", "help"],
+                        "description": "Scratchpad action"
+                    },
+                    "handle": { "type": "string", "description": "Entry handle (store generates one if omitted)" },
+                    "content": { "type": "string", "description": "For store/append: text" },
+                    "path": { "type": "string", "description": "For store/append: read the text from this file" },
+                    "to_path": { "type": "string", "description": "For get: write the entry to this file instead of returning it" },
+                    "ttl": { "type": "integer", "description": "Seconds until the entry expires; 0 never expires", "default": DEFAULT_TTL_SECS },
+                    "description": { "type": "string", "description": "Note about what the entry holds" },
+                    "offset": { "type": "integer", "description": "For get: character offset", "default": 0 },
+                    "limit": { "type": "integer", "description": "For get: characters to return", "default": DEFAULT_LIMIT }
+                },
+                "required": ["action"]
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    content: String,
+    description: Option<String>,
+    created: DateTime<Utc>,
+    updated: DateTime<Utc>,
+    expires: Option<DateTime<Utc>>,
+}
+
+impl Entry {
+    fn expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires.is_some_and(|at| at <= now)
+    }
+
+    fn summary(&self, handle: &str) -> Value {
+        json!({
+            "handle": handle,
+            "uri": format!("{}{}", SCRATCH_URI_PREFIX, handle),
+            "bytes": self.content.len(),
+            "lines": self.content.lines().count(),
+            "description": self.description,
+            "created": self.created.to_rfc3339(),
+            "updated": self.updated.to_rfc3339(),
+            "expires": self.expires.map(|at| at.to_rfc3339())
+        })
+    }
+}
+
+pub struct ScratchTool {
+    entries: RwLock<HashMap<String, Entry>>,
+    next: AtomicU64,
+}
+
+impl Default for ScratchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScratchTool {
+    pub fn new() -> Self {
+        Self { entries: RwLock::new(HashMap::new()), next: AtomicU64::new(1) }
+    }
+
+    pub async fn execute(&self, args: ScratchToolArgs) -> Result<Value> {
+        let action: ScratchAction = args.action.as_deref().unwrap_or("help").parse()?;
+        self.purge().await;
+
+        let data = match action {
+            ScratchAction::Store => self.store(&args).await?,
+            ScratchAction::Get => self.get(&args).await?,
+            ScratchAction::Append => self.append(&args).await?,
+            ScratchAction::Delete => self.delete(&args).await?,
+            ScratchAction::List => self.list().await,
+            ScratchAction::Help => return Ok(self.help()),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "scratch", "action": format!("{:?}", action).to_lowercase() }
+        }))
+    }
+
+    /// Entries as MCP resources
+    pub async fn resources(&self) -> Vec<Value> {
+        self.purge().await;
+        let entries = self.entries.read().await;
+        let mut handles: Vec<&String> = entries.keys().collect();
+        handles.sort();
+        handles
+            .into_iter()
+            .map(|handle| {
+                let entry = &entries[handle];
+                json!({
+                    "uri": format!("{}{}", SCRATCH_URI_PREFIX, handle),
+                    "name": format!("Scratch: {}", handle),
+                    "description": entry.description.clone().unwrap_or_else(|| format!("{} bytes", entry.content.len())),
+                    "mimeType": "text/plain"
+                })
+            })
+            .collect()
+    }
+
+    /// Read a `scratch://<handle>` resource
+    pub async fn read_resource(&self, uri: &str) -> Result<Value> {
+        let handle = uri
+            .strip_prefix(SCRATCH_URI_PREFIX)
+            .ok_or_else(|| ToolError::invalid(format!("Not a scratch resource: {}", uri)))?;
+        self.purge().await;
+        let entries = self.entries.read().await;
+        let entry = entries.get(handle).ok_or_else(|| ToolError::not_found(format!("Scratch entry not found: {}", handle)))?;
+        Ok(json!({
+            "contents": [{ "uri": uri, "mimeType": "text/plain", "text": entry.content }]
+        }))
+    }
+
+    async fn purge(&self) {
+        let now = Utc::now();
+        self.entries.write().await.retain(|_, entry| !entry.expired(now));
+    }
+
+    /// Text from `content` or the file at `path`
+    async fn input(args: &ScratchToolArgs) -> Result<String> {
+        match (&args.content, &args.path) {
+            (Some(content), None) => Ok(content.clone()),
+            (None, Some(path)) => {
+                let path = shellexpand::tilde(path).to_string();
+                let bytes = tokio::fs::read(&path).await.map_err(|e| ToolError::not_found(format!("{}: {}", path, e)))?;
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            (Some(_), Some(_)) => Err(ToolError::invalid("Pass content or path, not both").into()),
+            (None, None) => Err(ToolError::invalid("content or path required").into()),
+        }
+    }
+
+    fn expiry(args: &ScratchToolArgs, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match args.ttl.unwrap_or(DEFAULT_TTL_SECS) {
+            ttl if ttl <= 0 => None,
+            ttl => Some(now + Duration::seconds(ttl)),
+        }
+    }
+
+    fn check_budget(entries: &HashMap<String, Entry>, replacing: Option<&str>, adding: usize) -> Result<()> {
+        let held: usize = entries.iter()
+            .filter(|(handle, _)| Some(handle.as_str()) != replacing)
+            .map(|(_, entry)| entry.content.len())
+            .sum();
+        if held + adding > MAX_TOTAL_BYTES {
+            return Err(ToolError::invalid(format!(
+                "Scratchpad is full ({} of {} bytes held); delete entries first", held, MAX_TOTAL_BYTES
+            )).into());
+        }
+        Ok(())
+    }
+
+    async fn store(&self, args: &ScratchToolArgs) -> Result<Value> {
+        let content = Self::input(args).await?;
+        let mut entries = self.entries.write().await;
+        let handle = match &args.handle {
+            Some(handle) if handle.is_empty() || handle.contains('/') => {
+                return Err(ToolError::invalid(format!("Invalid handle: '{}'", handle)).into());
+            }
+            Some(handle) => handle.clone(),
+            None => loop {
+                let handle = format!("s{}", self.next.fetch_add(1, Ordering::SeqCst));
+                if !entries.contains_key(&handle) {
+                    break handle;
+                }
+            },
+        };
+        Self::check_budget(&entries, Some(&handle), content.len())?;
+        let now = Utc::now();
+        let created = entries.get(&handle).map_or(now, |old| old.created);
+        let entry = Entry {
+            content,
+            description: args.description.clone(),
+            created,
+            updated: now,
+            expires: Self::expiry(args, now),
+        };
+        let summary = entry.summary(&handle);
+        entries.insert(handle, entry);
+        Ok(summary)
+    }
+
+    async fn append(&self, args: &ScratchToolArgs) -> Result<Value> {
+        let handle = args.handle.as_deref().ok_or_else(|| ToolError::invalid("handle required"))?;
+        let content = Self::input(args).await?;
+        let mut entries = self.entries.write().await;
+        if !entries.contains_key(handle) {
+            return Err(ToolError::not_found(format!("Scratch entry not found: {}", handle)).into());
+        }
+        Self::check_budget(&entries, None, content.len())?;
+        let now = Utc::now();
+        let entry = entries.get_mut(handle).expect("checked above");
+        entry.content.push_str(&content);
+        entry.updated = now;
+        if args.ttl.is_some() {
+            entry.expires = Self::expiry(args, now);
+        }
+        if args.description.is_some() {
+            entry.description = args.description.clone();
+        }
+        Ok(json!({ "appended": content.len(), "entry": entry.summary(handle) }))
+    }
+
+    async fn get(&self, args: &ScratchToolArgs) -> Result<Value> {
+        let handle = args.handle.as_deref().ok_or_else(|| ToolError::invalid("handle required"))?;
+        let entries = self.entries.read().await;
+        let entry = entries.get(handle).ok_or_else(|| ToolError::not_found(format!("Scratch entry not found: {}", handle)))?;
+
+        if let Some(to_path) = &args.to_path {
+            let to_path = shellexpand::tilde(to_path).to_string();
+            super::fs_atomic::write_atomic(&to_path, entry.content.as_bytes(), false).await?;
+            return Ok(json!({ "handle": handle, "written": to_path, "bytes": entry.content.len() }));
+        }
+
+        let offset = args.offset.unwrap_or(0);
+        let limit = args.limit.unwrap_or(DEFAULT_LIMIT);
+        let start = entry.content.char_indices().nth(offset).map_or(entry.content.len(), |(i, _)| i);
+        let rest = &entry.content[start..];
+        let end = rest.char_indices().nth(limit).map_or(rest.len(), |(i, _)| i);
+        let more = end < rest.len();
+        Ok(json!({
+            "handle": handle,
+            "content": &rest[..end],
+            "offset": offset,
+            "bytes": entry.content.len(),
+            "truncated": more,
+            "next_offset": if more { Some(offset + limit) } else { None }
+        }))
+    }
+
+    async fn delete(&self, args: &ScratchToolArgs) -> Result<Value> {
+        let handle = args.handle.as_deref().ok_or_else(|| ToolError::invalid("handle required"))?;
+        let removed = self.entries.write().await.remove(handle);
+        Ok(json!({ "handle": handle, "deleted": removed.is_some() }))
+    }
+
+    async fn list(&self) -> Value {
+        let entries = self.entries.read().await;
+        let mut list: Vec<Value> = entries.iter().map(|(handle, entry)| entry.summary(handle)).collect();
+        list.sort_by(|a, b| a["handle"].as_str().cmp(&b["handle"].as_str()));
+        let bytes: usize = entries.values().map(|entry| entry.content.len()).sum();
+        json!({ "entries": list, "count": list.len(), "bytes": bytes })
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "scratch",
+                "actions": {
+                    "store": "Save content (or a file's text via path) under a handle; ttl in seconds, 0 never expires",
+                    "get": "Return an entry in pages (offset, limit) or write it to to_path",
+                    "append": "Add content (or a file's text) to an entry",
+                    "delete": "Remove an entry",
+                    "list": "List entries with size and expiry"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "scratch", "action": "help" }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(action: &str) -> ScratchToolArgs {
+        ScratchToolArgs { action: Some(action.into()), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_scratch() {
+        let tool = ScratchTool::new();
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("build.log");
+        std::fs::write(&log, "line 1\n").unwrap();
+
+        let stored = tool.execute(ScratchToolArgs { path: Some(log.to_string_lossy().into()), ..args("store") }).await.unwrap();
+        let handle = stored["data"]["handle"].as_str().unwrap().to_string();
+        assert_eq!(handle, "s1");
+        tool.execute(ScratchToolArgs { handle: Some(handle.clone()), content: Some("line 2\n".into()), ..args("append") }).await.unwrap();
+
+        let page = tool.execute(ScratchToolArgs { handle: Some(handle.clone()), offset: Some(2), limit: Some(5), ..args("get") }).await.unwrap();
+        assert_eq!(page["data"]["content"], "ne 1\n");
+        assert_eq!(page["data"]["next_offset"], 7);
+
+        let copy = dir.path().join("copy.log");
+        tool.execute(ScratchToolArgs { handle: Some(handle.clone()), to_path: Some(copy.to_string_lossy().into()), ..args("get") }).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&copy).unwrap(), "line 1\nline 2\n");
+
+        let resource = tool.read_resource("scratch://s1").await.unwrap();
+        assert_eq!(resource["contents"][0]["text"], "line 1\nline 2\n");
+
+        tool.execute(ScratchToolArgs { handle: Some("old".into()), content: Some("x".into()), ttl: Some(1), ..args("store") }).await.unwrap();
+        tool.entries.write().await.get_mut("old").unwrap().expires = Some(Utc::now() - Duration::seconds(1));
+        let list = tool.execute(args("list")).await.unwrap();
+        assert_eq!(list["data"]["count"], 1);
+
+        let deleted = tool.execute(ScratchToolArgs { handle: Some(handle), ..args("delete") }).await.unwrap();
+        assert_eq!(deleted["data"]["deleted"], true);
+        assert!(tool.resources().await.is_empty());
+    }
+}
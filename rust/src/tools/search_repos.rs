@@ -0,0 +1,161 @@
+/// Remote git repositories for search, kept in a local cache
+///
+/// A repository named by URL is cloned shallow (one commit of one branch)
+/// into the user cache directory on first use, under a name derived from
+/// the URL and ref, and fetched again once the copy is older than
+/// `REFRESH_AFTER`. A fetch that fails leaves the last copy in place, so
+/// searches keep working offline.
+
+use crate::error::ToolError;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tokio::process::Command;
+
+/// How old a cached copy may get before it is fetched again
+const REFRESH_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// Longest a clone or fetch may take
+const GIT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// A repository checked out for searching
+#[derive(Debug, Clone)]
+pub struct Checkout {
+    pub path: PathBuf,
+    /// Commit the copy is at
+    pub commit: String,
+    /// Whether this call cloned or fetched it
+    pub updated: bool,
+}
+
+pub fn cache_dir() -> PathBuf {
+    dirs::cache_dir()
+        .or_else(dirs::data_dir)
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("hanzo-mcp")
+        .join("repos")
+}
+
+/// `url` at `reference` (its default branch when `None`), cloned into or
+/// refreshed in `cache`
+pub async fn checkout(cache: &Path, url: &str, reference: Option<&str>) -> Result<Checkout> {
+    validate(url, reference)?;
+    let path = cache.join(dir_name(url, reference));
+    let cloned = path.join(".git").exists();
+    let mut updated = false;
+    if !cloned {
+        tokio::fs::create_dir_all(cache).await?;
+        let partial = path.with_extension("partial");
+        let _ = tokio::fs::remove_dir_all(&partial).await;
+        let mut args = vec!["clone", "--quiet", "--depth", "1", "--single-branch", "--no-tags"];
+        if let Some(reference) = reference {
+            args.extend(["--branch", reference]);
+        }
+        args.extend(["--", url]);
+        let target = partial.to_string_lossy().to_string();
+        args.push(&target);
+        git(None, &args).await
+            .map_err(|e| ToolError::external(format!("Cannot clone {}: {}", url, e)))?;
+        tokio::fs::rename(&partial, &path).await?;
+        updated = true;
+    } else if stale(&path) {
+        let fetch = git(Some(&path), &["fetch", "--quiet", "--depth", "1", "--no-tags", "origin", reference.unwrap_or("HEAD")]).await;
+        match fetch {
+            Ok(_) => {
+                git(Some(&path), &["reset", "--quiet", "--hard", "FETCH_HEAD"]).await?;
+                updated = true;
+            }
+            Err(e) => log::warn!("Cannot refresh {}, searching the cached copy: {}", url, e),
+        }
+        // Checked again after REFRESH_AFTER whether or not the fetch worked
+        let _ = std::fs::OpenOptions::new().write(true).open(path.join(".git").join("HEAD")).and_then(|f| f.set_modified(SystemTime::now()));
+    }
+    let commit = git(Some(&path), &["rev-parse", "HEAD"]).await?.trim().to_string();
+    Ok(Checkout { path, commit, updated })
+}
+
+/// Refuse what git would read as an option or a local command
+fn validate(url: &str, reference: Option<&str>) -> Result<()> {
+    let scp = url.split_once(':').is_some_and(|(host, _)| host.contains('@') && !host.contains('/'));
+    let scheme = ["https://", "http://", "ssh://", "git://", "file://"].iter().any(|s| url.starts_with(s));
+    if !(scheme || scp) || url.starts_with('-') || url.contains(char::is_whitespace) {
+        return Err(ToolError::invalid(format!("Not a git URL: {} (https://, ssh://, git://, file:// or user@host:path)", url)).into());
+    }
+    if reference.is_some_and(|r| r.is_empty() || r.starts_with('-') || r.contains(char::is_whitespace)) {
+        return Err(ToolError::invalid(format!("Not a git ref: {}", reference.unwrap_or_default())).into());
+    }
+    Ok(())
+}
+
+/// `<repo name>-<hash of url and ref>`, readable and unique per checkout
+fn dir_name(url: &str, reference: Option<&str>) -> String {
+    let name = url.trim_end_matches('/').trim_end_matches(".git")
+        .rsplit(['/', ':'])
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or("repo");
+    let name: String = name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+    let digest = Sha256::digest(format!("{}#{}", url, reference.unwrap_or("")).as_bytes());
+    let hash: String = digest.iter().take(6).map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}", name, hash)
+}
+
+fn stale(path: &Path) -> bool {
+    std::fs::metadata(path.join(".git").join("HEAD"))
+        .and_then(|m| m.modified())
+        .map_or(true, |modified| modified.elapsed().unwrap_or_default() > REFRESH_AFTER)
+}
+
+async fn git(cwd: Option<&Path>, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("git");
+    command.args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true);
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    let output = tokio::time::timeout(GIT_TIMEOUT, command.output()).await
+        .map_err(|_| ToolError::timeout(format!("git {} took longer than {}s", args[0], GIT_TIMEOUT.as_secs())))??;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(ToolError::external(format!("git error: {}", String::from_utf8_lossy(&output.stderr).trim())).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checkout() {
+        let origin = tempfile::tempdir().unwrap();
+        let run = |args: &[&str]| {
+            let status = std::process::Command::new("git").args(args).current_dir(origin.path()).output().unwrap();
+            assert!(status.status.success(), "{:?}", status);
+        };
+        run(&["init", "--quiet", "-b", "main"]);
+        std::fs::write(origin.path().join("lib.rs"), "fn answer() -> u32 { 42 }\n").unwrap();
+        run(&["add", "."]);
+        run(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "--quiet", "-m", "one"]);
+
+        let cache = tempfile::tempdir().unwrap();
+        let url = format!("file://{}", origin.path().display());
+        let first = checkout(cache.path(), &url, None).await.unwrap();
+        assert!(first.updated);
+        assert!(first.path.join("lib.rs").exists());
+        assert_eq!(first.commit.len(), 40);
+        let again = checkout(cache.path(), &url, Some("main")).await.unwrap();
+        assert_ne!(again.path, first.path);
+        assert!(!checkout(cache.path(), &url, None).await.unwrap().updated);
+
+        assert!(checkout(cache.path(), "--upload-pack=touch /tmp/x", None).await.is_err());
+        assert!(checkout(cache.path(), "/etc", None).await.is_err());
+        assert!(checkout(cache.path(), &url, Some("-x")).await.is_err());
+        assert!(checkout(cache.path(), "file:///nonexistent/repo", None).await.is_err());
+        assert!(dir_name("git@github.com:hanzoai/mcp.git", None).starts_with("mcp-"));
+        assert!(dir_name("https://github.com/hanzoai/mcp", None).starts_with("mcp-"));
+    }
+}
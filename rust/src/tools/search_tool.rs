@@ -0,0 +1,974 @@
+/// Code search tool backed by the search module
+///
+/// Actions: text, ast, rewrite, symbols, history, unified, help
+///
+/// text is a ripgrep-style search: regex or literal patterns, whole words,
+/// multiline, case modes, file types (`rust`, `py`), include/exclude globs
+/// and a cap on matches per file. It honours .gitignore and skips hidden
+/// and binary files unless asked not to. Results come as a flat list of
+/// matches, grouped by file (optionally merging nearby matches into one
+/// snippet), or as counts per file alone with summary_only.
+///
+/// ast finds code by shape: a pattern in the target language with
+/// metavariables (`if $COND { return $X }`, `foo($$$ARGS)`), reporting
+/// what each metavariable matched. rewrite replaces those matches with a
+/// template over the same metavariables (`foo($B, $A)`), previewing diffs
+/// with dry_run and refusing to write anything that no longer parses.
+///
+/// symbols looks up definitions by fuzzy name (`usrSrv` finds
+/// `UserService`) and kind, returning jump targets with signatures. The
+/// symbol table of each root searched is kept and refreshed incrementally.
+///
+/// history searches git history for the commits that added or removed a
+/// pattern, with the hunks that did it.
+///
+/// unified runs several modalities for one query, chosen by its syntax
+/// (`sym:UserService lang:rust path:src/ -path:tests`) or, for free text,
+/// by heuristics, and ranks what they find together.
+///
+/// roots and repos run any of these across several trees at once: workspace
+/// roots by name (`*` for all of them) and git URLs, cloned shallow into a
+/// cache on first use, with results reported per root.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use super::diff;
+use super::fs_atomic::write_atomic;
+use super::search_repos;
+use super::workspace_roots::Roots;
+use crate::search::ast_search::{self, AstSearcher};
+use crate::search::structural::{self, StructuralMatch};
+use crate::search::history::{self, HistoryOptions};
+use crate::search::symbol_index::{self, SymbolTables};
+use crate::search::text::{self, CaseMode, TextOptions};
+use crate::search::unified_search::UnifiedSearch;
+use crate::search::{query, SearchConfig, SearchResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Matches returned unless `limit` says otherwise
+const DEFAULT_LIMIT: usize = 50;
+
+/// Lines of context on each side of a match unless `context` says otherwise
+const DEFAULT_CONTEXT: usize = 2;
+
+/// Matches a rewrite may change unless `max_replacements` says otherwise
+const MAX_REPLACEMENTS: usize = 1000;
+
+/// Matches counted for summary_only unless `limit` says otherwise; no
+/// content comes back, so counting more is cheap
+const SUMMARY_LIMIT: usize = 10_000;
+
+/// Commits a history search returns unless `limit` says otherwise
+const HISTORY_LIMIT: usize = 20;
+
+/// How matches are laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Output {
+    /// One entry per match
+    #[default]
+    Matches,
+    /// One entry per file holding its matches
+    Grouped,
+}
+
+impl std::str::FromStr for Output {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "matches" | "flat" => Ok(Self::Matches),
+            "grouped" | "files" | "by_file" => Ok(Self::Grouped),
+            _ => Err(ToolError::invalid(format!("Unknown output: {} (matches, grouped)", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchAction {
+    Text,
+    Ast,
+    Rewrite,
+    Symbols,
+    History,
+    Unified,
+    #[default]
+    Help,
+}
+
+impl std::str::FromStr for SearchAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "text" | "grep" | "search" | "rg" => Ok(Self::Text),
+            "ast" | "structural" | "sg" => Ok(Self::Ast),
+            "rewrite" | "codemod" => Ok(Self::Rewrite),
+            "symbols" | "symbol" | "sym" => Ok(Self::Symbols),
+            "history" | "history_search" | "pickaxe" => Ok(Self::History),
+            "unified" | "all" | "auto" => Ok(Self::Unified),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl SearchAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Ast => "ast",
+            Self::Rewrite => "rewrite",
+            Self::Symbols => "symbols",
+            Self::History => "history",
+            Self::Unified => "unified",
+            Self::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchToolArgs {
+    /// Defaults to text when a pattern is given, help otherwise
+    pub action: Option<String>,
+    #[serde(alias = "query")]
+    pub pattern: Option<String>,
+    /// Directory or file to search
+    pub path: Option<String>,
+    /// Match the pattern as a fixed string
+    #[serde(default)]
+    pub literal: bool,
+    /// Match whole words only
+    #[serde(default)]
+    pub word: bool,
+    /// Let matches span lines
+    #[serde(default)]
+    pub multiline: bool,
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// true or false overrides smart case
+    pub case_sensitive: Option<bool>,
+    /// File types, comma separated: `rust`, `py,ts`
+    #[serde(alias = "type")]
+    pub file_type: Option<String>,
+    pub include: Option<Vec<String>>,
+    pub exclude: Option<Vec<String>>,
+    pub max_per_file: Option<usize>,
+    pub limit: Option<usize>,
+    pub context: Option<usize>,
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Search files .gitignore leaves out
+    #[serde(default)]
+    pub no_ignore: bool,
+    /// ast: the pattern's language; every supported one it parses as when unset
+    #[serde(alias = "lang")]
+    pub language: Option<String>,
+    /// rewrite: what matches become, over the pattern's metavariables
+    #[serde(alias = "template")]
+    pub rewrite: Option<String>,
+    /// rewrite: return the diffs without writing
+    #[serde(default)]
+    pub dry_run: bool,
+    pub max_replacements: Option<usize>,
+    /// symbols: kinds to keep, comma separated: `fn,struct`
+    pub kind: Option<String>,
+    /// matches (default) or grouped by file
+    pub output: Option<String>,
+    /// Grouped: merge matches whose context overlaps into one snippet
+    #[serde(default)]
+    pub merge: bool,
+    /// Counts per file without any content
+    #[serde(default)]
+    pub summary_only: bool,
+    /// Workspace roots to search instead of path, by name or path; `*` for all
+    pub roots: Option<Vec<String>>,
+    /// Git URLs to search, cloned shallow into the cache
+    pub repos: Option<Vec<String>>,
+    /// repos: branch or tag to check out (default: the remote's default
+    /// branch). history: where to start (default HEAD)
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    /// history: only commits after this date (`2024-01-01`, `2 weeks ago`)
+    pub since: Option<String>,
+    /// history: only commits before this date
+    pub until: Option<String>,
+    /// history: only commits by authors matching this
+    pub author: Option<String>,
+    /// Set by the registry: whose roots `*` names
+    #[serde(skip)]
+    pub session_id: Option<String>,
+}
+
+impl SearchToolArgs {
+    fn options(&self) -> TextOptions {
+        let case = match (self.case_sensitive, self.ignore_case) {
+            (Some(true), _) => CaseMode::Sensitive,
+            (Some(false), _) | (None, true) => CaseMode::Insensitive,
+            (None, false) => CaseMode::Smart,
+        };
+        TextOptions {
+            literal: self.literal,
+            word: self.word,
+            multiline: self.multiline,
+            case,
+            types: self.file_type.iter()
+                .flat_map(|types| types.split(','))
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty())
+                .collect(),
+            include: self.include.clone().unwrap_or_default(),
+            exclude: self.exclude.clone().unwrap_or_default(),
+            max_per_file: self.max_per_file,
+            hidden: self.include_hidden,
+            no_ignore: self.no_ignore,
+        }
+    }
+}
+
+pub struct SearchToolDefinition;
+
+impl SearchToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "search",
+            "description": "Search code: text (regex or literal, whole words, multiline, case, file types, include/exclude globs, max matches per file; respects .gitignore), ast (structural patterns with $METAVARS, reporting captures), rewrite (structural find-and-replace with diff previews), symbols (fuzzy definition lookup by name and kind), history (commits that added or removed a pattern, with their hunks), unified (several modalities at once, picked by query syntax like sym:Name lang:rust path:src/ -path:tests), help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["text", "ast", "rewrite", "symbols", "history", "unified", "help"],
+                        "description": "Search action; text when a pattern is given"
+                    },
+                    "pattern": { "type": "string", "description": "text: a regex, or a fixed string with literal. ast: code with metavariables, e.g. if $COND { return $X }" },
+                    "language": { "type": "string", "description": "ast, rewrite: rust, python, typescript, javascript, go, java, c, cpp (default: every language the pattern parses as)" },
+                    "rewrite": { "type": "string", "description": "rewrite: the template each match becomes, using the pattern's metavariables, e.g. foo($B, $A)" },
+                    "dry_run": { "type": "boolean", "description": "rewrite: return the diffs without writing", "default": false },
+                    "kind": { "type": "string", "description": "symbols: kinds to keep, comma separated: function, method, struct, enum, trait, impl, class, interface, type, const, module, macro" },
+                    "max_replacements": { "type": "integer", "minimum": 1, "description": "rewrite: refuse to change more matches than this", "default": MAX_REPLACEMENTS },
+                    "path": { "type": "string", "description": "Directory or file to search (default: project root)" },
+                    "literal": { "type": "boolean", "description": "Match the pattern as a fixed string", "default": false },
+                    "word": { "type": "boolean", "description": "Match whole words only", "default": false },
+                    "multiline": { "type": "boolean", "description": "Let matches span lines; . also matches newlines", "default": false },
+                    "ignore_case": { "type": "boolean", "description": "Case-insensitive", "default": false },
+                    "case_sensitive": { "type": "boolean", "description": "Force case sensitivity on or off; smart case (sensitive only with an uppercase letter) when unset" },
+                    "file_type": { "type": "string", "description": "ripgrep file types, comma separated: rust, py, ts, go, md, ..." },
+                    "include": { "type": "array", "items": { "type": "string" }, "description": "Globs files must match" },
+                    "exclude": { "type": "array", "items": { "type": "string" }, "description": "Globs that exclude files" },
+                    "max_per_file": { "type": "integer", "minimum": 1, "description": "Matches reported per file" },
+                    "limit": { "type": "integer", "description": "Matches returned", "default": DEFAULT_LIMIT },
+                    "context": { "type": "integer", "description": "Lines of context around each match", "default": DEFAULT_CONTEXT },
+                    "include_hidden": { "type": "boolean", "description": "Search hidden files", "default": false },
+                    "no_ignore": { "type": "boolean", "description": "Search files .gitignore excludes", "default": false },
+                    "output": { "type": "string", "enum": ["matches", "grouped"], "description": "matches: one entry per match. grouped: one entry per file with its count", "default": "matches" },
+                    "merge": { "type": "boolean", "description": "grouped: merge matches whose context overlaps into one numbered snippet", "default": false },
+                    "summary_only": { "type": "boolean", "description": "Return match counts per file without content", "default": false },
+                    "roots": { "type": "array", "items": { "type": "string" }, "description": "Search these workspace roots instead of path, by name or path; [\"*\"] for every root. Results come per root" },
+                    "repos": { "type": "array", "items": { "type": "string" }, "description": "Also search these git repositories (https, ssh or file URLs), cloned shallow into a local cache and refreshed every 15 minutes" },
+                    "ref": { "type": "string", "description": "repos: branch or tag to search (default: the default branch). history: commit to start from (default HEAD)" },
+                    "since": { "type": "string", "description": "history: only commits after this date, e.g. 2024-01-01 or 2 weeks ago" },
+                    "until": { "type": "string", "description": "history: only commits before this date" },
+                    "author": { "type": "string", "description": "history: only commits whose author matches this" },
+                    "max_bytes": {"type": "integer", "minimum": 1, "description": "Cap on the result size; longer output is cut (head and tail of logs, leading results of searches) with a cursor for the rest"},
+                    "max_output_tokens": {"type": "integer", "minimum": 1, "description": "Like max_bytes, counting about 4 bytes per token"}
+                }
+            },
+            "outputSchema": {
+                "type": "object",
+                "properties": {
+                    "ok": { "type": "boolean" },
+                    "data": { "description": "The action's results" },
+                    "error": { "type": ["object", "null"] },
+                    "meta": { "type": "object", "description": "Timing, counts and truncation" }
+                }
+            }
+        })
+    }
+}
+
+pub struct SearchTool {
+    /// Symbol tables by root, kept current between calls
+    symbols: SymbolTables,
+    /// Workspace roots `roots` names; directories stand in for them without
+    roots: Option<Arc<Roots>>,
+    /// Where repos are cloned
+    repo_cache: PathBuf,
+}
+
+impl Default for SearchTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchTool {
+    pub fn new() -> Self {
+        Self { symbols: Arc::default(), roots: None, repo_cache: search_repos::cache_dir() }
+    }
+
+    /// A search tool that resolves `roots` against the registry's roots
+    pub fn with_roots(roots: Arc<Roots>) -> Self {
+        Self { roots: Some(roots), ..Self::new() }
+    }
+
+    /// Look symbols up in tables someone else, such as the indexer, keeps warm
+    pub fn sharing(mut self, symbols: SymbolTables) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    pub async fn execute(&self, args: SearchToolArgs) -> Result<Value> {
+        let action: SearchAction = match args.action.as_deref() {
+            Some(action) if !action.is_empty() => action.parse()?,
+            _ if args.pattern.is_some() => SearchAction::Text,
+            _ => SearchAction::Help,
+        };
+        let data = match action {
+            SearchAction::Help => return Ok(self.help()),
+            _ if args.roots.is_some() || args.repos.is_some() => self.across(&action, args).await?,
+            _ => self.run(&action, args).await?,
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "search", "action": action.name() }
+        }))
+    }
+
+    async fn run(&self, action: &SearchAction, args: SearchToolArgs) -> Result<Value> {
+        match action {
+            SearchAction::Text => self.text(args).await,
+            SearchAction::Ast => self.ast(args).await,
+            SearchAction::Rewrite => self.rewrite(args).await,
+            SearchAction::Symbols => self.symbols(args).await,
+            SearchAction::History => self.history(args).await,
+            SearchAction::Unified => self.unified(args).await,
+            SearchAction::Help => Ok(self.help()),
+        }
+    }
+
+    /// `action` in every root and repo the call names, one entry each. A
+    /// root that fails reports its error without failing the rest
+    async fn across(&self, action: &SearchAction, args: SearchToolArgs) -> Result<Value> {
+        let repos = args.repos.clone().unwrap_or_default();
+        if *action == SearchAction::Rewrite && !repos.is_empty() {
+            return Err(ToolError::invalid("rewrite changes local files; search repos with text, ast or symbols").into());
+        }
+        let mut targets: Vec<(Value, Result<PathBuf>)> = self.roots(&args)?.into_iter()
+            .map(|(name, path)| (json!({ "root": name }), Ok(path)))
+            .collect();
+        for url in &repos {
+            let checkout = search_repos::checkout(&self.repo_cache, url, args.git_ref.as_deref()).await;
+            let mut entry = json!({ "root": url, "repo": url, "ref": args.git_ref });
+            if let Ok(checkout) = &checkout {
+                entry["commit"] = json!(checkout.commit);
+                entry["updated"] = json!(checkout.updated);
+            }
+            targets.push((entry, checkout.map(|c| c.path)));
+        }
+        if targets.is_empty() {
+            return Err(ToolError::invalid("roots and repos name nothing to search").into());
+        }
+
+        let mut results = Vec::new();
+        let mut count = 0;
+        for (mut entry, path) in targets {
+            let path = match path {
+                Ok(path) => path,
+                Err(e) => {
+                    entry["error"] = json!(e.to_string());
+                    results.push(entry);
+                    continue;
+                }
+            };
+            let one = SearchToolArgs {
+                path: Some(path.display().to_string()),
+                roots: None,
+                repos: None,
+                ..args.clone()
+            };
+            match self.run(action, one).await {
+                Ok(Value::Object(data)) => {
+                    count += data.get("replacements").or(data.get("count")).and_then(Value::as_u64).unwrap_or(0);
+                    for (key, value) in data {
+                        if !matches!(key.as_str(), "pattern" | "query" | "rewrite") {
+                            entry[key] = value;
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => entry["error"] = json!(e.to_string()),
+            }
+            results.push(entry);
+        }
+        Ok(json!({
+            "pattern": args.pattern,
+            "roots": results,
+            "count": count
+        }))
+    }
+
+    /// Names and directories of the roots `args.roots` names, each once
+    fn roots(&self, args: &SearchToolArgs) -> Result<Vec<(String, PathBuf)>> {
+        let session = args.session_id.as_deref();
+        let mut found: Vec<(String, PathBuf)> = Vec::new();
+        for name in args.roots.iter().flatten() {
+            let named = match (&self.roots, name.as_str()) {
+                (Some(roots), "*" | "all") => roots.visible(session).into_iter().map(|r| (r.name, r.path)).collect(),
+                (Some(roots), name) => {
+                    let root = roots.find(name, session)?;
+                    vec![(root.name, root.path)]
+                }
+                (None, "*" | "all") => {
+                    return Err(ToolError::invalid("No workspace roots to expand *; name directories instead").into());
+                }
+                (None, name) => {
+                    let path = PathBuf::from(shellexpand::tilde(name).as_ref());
+                    let path = path.canonicalize().map_err(|_| ToolError::not_found(format!("Path not found: {}", name)))?;
+                    vec![(name.to_string(), path)]
+                }
+            };
+            for (name, path) in named {
+                if !found.iter().any(|(_, seen)| *seen == path) {
+                    found.push((name, path));
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    async fn text(&self, args: SearchToolArgs) -> Result<Value> {
+        let (pattern, path) = target(&args)?;
+        let options = args.options();
+        let output: Output = args.output.as_deref().map_or(Ok(Output::default()), str::parse)?;
+        let limit = args.limit.unwrap_or(if args.summary_only { SUMMARY_LIMIT } else { DEFAULT_LIMIT });
+        let context = if args.summary_only { 0 } else { args.context.unwrap_or(DEFAULT_CONTEXT) };
+        let outcome = {
+            let (path, pattern) = (path.clone(), pattern.clone());
+            tokio::task::spawn_blocking(move || text::search(&path, &pattern, &options, limit, context)).await??
+        };
+
+        let mut data = json!({
+            "pattern": pattern,
+            "path": path.display().to_string(),
+            "count": outcome.results.len(),
+            "files_searched": outcome.files_searched,
+            "files_matched": outcome.files_matched,
+            "truncated": outcome.truncated
+        });
+        let by_file = group(&outcome.results);
+        if args.summary_only {
+            data["files"] = by_file.iter()
+                .map(|(file, hits)| json!({ "file": file, "count": hits.len() }))
+                .collect();
+        } else if output == Output::Grouped {
+            data["files"] = by_file.iter()
+                .map(|(file, hits)| {
+                    let mut entry = json!({ "file": file, "count": hits.len() });
+                    if args.merge {
+                        entry["snippets"] = json!(snippets(hits));
+                    } else {
+                        entry["matches"] = hits.iter().map(|r| found(r, false)).collect();
+                    }
+                    entry
+                })
+                .collect();
+        } else {
+            data["matches"] = outcome.results.iter().map(|r| found(r, true)).collect();
+        }
+        Ok(data)
+    }
+
+    async fn ast(&self, args: SearchToolArgs) -> Result<Value> {
+        let (pattern, path) = target(&args)?;
+        let options = args.options();
+        let limit = args.limit.unwrap_or(DEFAULT_LIMIT);
+        let found = {
+            let (path, pattern, language) = (path.clone(), pattern.clone(), args.language.clone());
+            tokio::task::spawn_blocking(move || {
+                AstSearcher::new().search_structural(&pattern, &path, language.as_deref(), &options, limit)
+            }).await??
+        };
+        let matches: Vec<Value> = found.iter()
+            .map(|m| json!({
+                "file": m.file.display().to_string(),
+                "line": m.line,
+                "column": m.column,
+                "end_line": m.end_line,
+                "node_type": m.node_type,
+                "text": m.text,
+                "captures": m.captures
+            }))
+            .collect();
+        Ok(json!({
+            "pattern": pattern,
+            "path": path.display().to_string(),
+            "language": args.language,
+            "matches": matches,
+            "count": matches.len(),
+            "truncated": matches.len() >= limit
+        }))
+    }
+
+    async fn rewrite(&self, args: SearchToolArgs) -> Result<Value> {
+        let (pattern, path) = target(&args)?;
+        let template = args.rewrite.clone()
+            .ok_or_else(|| ToolError::invalid("rewrite required: the template matches become, e.g. foo($B, $A)"))?;
+        let max = args.max_replacements.unwrap_or(MAX_REPLACEMENTS);
+        let options = args.options();
+        let found = {
+            let (path, pattern, language) = (path.clone(), pattern.clone(), args.language.clone());
+            tokio::task::spawn_blocking(move || {
+                AstSearcher::new().search_structural(&pattern, &path, language.as_deref(), &options, max + 1)
+            }).await??
+        };
+        if found.len() > max {
+            return Err(ToolError::invalid(format!(
+                "Rewriting would change more than {} matches; narrow the pattern or paths, or raise max_replacements", max
+            )).into());
+        }
+
+        // Plan every file before writing any, so a bad template changes nothing
+        let mut by_file: Vec<(PathBuf, Vec<StructuralMatch>)> = Vec::new();
+        for found in found {
+            match by_file.last_mut() {
+                Some((file, matches)) if *file == found.file => matches.push(found),
+                _ => by_file.push((found.file.clone(), vec![found])),
+            }
+        }
+        let mut planned = Vec::new();
+        let mut broken = Vec::new();
+        for (file, matches) in by_file {
+            let old = tokio::fs::read_to_string(&file).await?;
+            let (new, count) = structural::rewrite(&old, &matches, &template)?;
+            let language = ast_search::detect_language(&file);
+            let parses = structural::parses(&new, language) || !structural::parses(&old, language);
+            if !parses {
+                broken.push(file.display().to_string());
+            }
+            planned.push((file.display().to_string(), old, new, count, parses));
+        }
+        if !broken.is_empty() && !args.dry_run {
+            return Err(ToolError::invalid(format!(
+                "The rewrite would leave {} with syntax errors; nothing was written (dry_run shows the diffs)", broken.join(", ")
+            )).into());
+        }
+
+        let mut results = Vec::new();
+        let mut total = 0;
+        for (file, old, new, count, parses) in planned {
+            total += count;
+            if args.dry_run {
+                results.push(json!({ "path": file, "replacements": count, "parses": parses, "diff": diff::unified(&file, Some(&old), Some(&new)) }));
+                continue;
+            }
+            write_atomic(&file, new.as_bytes(), false).await?;
+            results.push(json!({ "path": file, "replacements": count }));
+        }
+        Ok(json!({
+            "pattern": pattern,
+            "rewrite": template,
+            "path": path.display().to_string(),
+            "dry_run": args.dry_run,
+            "files": results.len(),
+            "replacements": total,
+            "results": results
+        }))
+    }
+
+    async fn symbols(&self, args: SearchToolArgs) -> Result<Value> {
+        let query = args.pattern.clone().unwrap_or_default();
+        let path = PathBuf::from(shellexpand::tilde(args.path.as_deref().unwrap_or(".")).as_ref());
+        let root = path.canonicalize().map_err(|_| ToolError::not_found(format!("Path not found: {}", path.display())))?;
+        let kinds = args.kind.iter()
+            .flat_map(|kinds| kinds.split(','))
+            .filter(|kind| !kind.trim().is_empty())
+            .map(symbol_index::kind)
+            .collect::<Result<Vec<_>>>()?;
+        let limit = args.limit.unwrap_or(DEFAULT_LIMIT);
+        let tables = self.symbols.clone();
+        let (symbols, refresh) = tokio::task::spawn_blocking(move || -> Result<_> {
+            let mut tables = tables.lock().unwrap_or_else(|e| e.into_inner());
+            let index = tables.entry(root.clone()).or_default();
+            let refresh = index.refresh(&root)?;
+            let symbols: Vec<Value> = index.lookup(&query, &kinds, limit).into_iter()
+                .map(|(score, symbol)| {
+                    let mut found = json!(symbol);
+                    found["score"] = json!(score);
+                    found
+                })
+                .collect();
+            Ok((symbols, refresh))
+        }).await??;
+        Ok(json!({
+            "query": args.pattern,
+            "path": path.display().to_string(),
+            "kind": args.kind,
+            "symbols": symbols,
+            "count": symbols.len(),
+            "index": refresh
+        }))
+    }
+
+    async fn history(&self, args: SearchToolArgs) -> Result<Value> {
+        let (pattern, path) = target(&args)?;
+        let path = path.canonicalize()?;
+        let (dir, paths) = match (path.is_file(), path.parent(), path.file_name()) {
+            (true, Some(parent), Some(name)) => (parent.to_path_buf(), vec![name.to_string_lossy().to_string()]),
+            _ => (path.clone(), Vec::new()),
+        };
+        let options = HistoryOptions {
+            reference: args.git_ref.clone(),
+            since: args.since.clone(),
+            until: args.until.clone(),
+            author: args.author.clone(),
+            paths,
+            include: args.include.clone().unwrap_or_default(),
+            exclude: args.exclude.clone().unwrap_or_default(),
+            context: args.context.unwrap_or(DEFAULT_CONTEXT),
+            limit: args.limit.unwrap_or(HISTORY_LIMIT),
+        };
+        let text = args.options();
+        let limit = options.limit;
+        let commits = {
+            let pattern = pattern.clone();
+            tokio::task::spawn_blocking(move || history::search(&dir, &pattern, &text, &options)).await??
+        };
+        Ok(json!({
+            "pattern": pattern,
+            "path": path.display().to_string(),
+            "mode": if args.literal && !args.word { "pickaxe" } else { "regex" },
+            "commits": commits,
+            "count": commits.len(),
+            "truncated": commits.len() >= limit
+        }))
+    }
+
+    async fn unified(&self, args: SearchToolArgs) -> Result<Value> {
+        let (pattern, path) = target(&args)?;
+        let parsed = query::parse(&pattern)?;
+        let config = SearchConfig {
+            query: pattern.clone(),
+            path: Some(path.clone()),
+            max_results: args.limit.unwrap_or(DEFAULT_LIMIT),
+            context_lines: args.context.unwrap_or(DEFAULT_CONTEXT),
+            language: args.language.clone(),
+            text: args.options(),
+            ..Default::default()
+        };
+        let results = UnifiedSearch::new(config).execute().await?;
+        let matches: Vec<Value> = results.iter()
+            .map(|r| {
+                let mut entry = found(r, true);
+                entry["modality"] = json!(r.match_type.to_string());
+                entry
+            })
+            .collect();
+        Ok(json!({
+            "pattern": pattern,
+            "path": path.display().to_string(),
+            "searches": parsed.searches().into_iter().map(|(modality, term)| json!({ "modality": modality, "term": term })).collect::<Vec<_>>(),
+            "matches": matches,
+            "count": matches.len()
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "name": "search",
+                "actions": {
+                    "text": "Find pattern under path: regex by default, literal for fixed strings, word for whole words, multiline to span lines",
+                    "ast": "Find code by shape: pattern is code with metavariables, $X for one node, $$$ARGS for a run of siblings, $_ to match without capturing; a metavariable used twice must match the same text",
+                    "symbols": "Definitions whose names fuzzily match pattern (usrSrv finds UserService; empty lists all), filtered by kind, with file, line and signature; the symbol table is cached and refreshed by modification time",
+                    "history": "Commits that added or removed pattern, newest first, with author, date and the matching hunks: literal counts occurrences like git log -S, a regex matches changed lines like git log -G; path, include, exclude, ref, since, until and author narrow it",
+                    "rewrite": "Replace ast matches with the rewrite template, e.g. pattern foo($A, $B) with rewrite foo($B, $A); dry_run returns diffs; nothing is written if any file would stop parsing",
+                    "unified": "Run several modalities for one pattern and rank their matches together. Syntax: text:, ast:, sym:, file: pick a modality for the term after them (quote terms with spaces); lang:rust, ext:rs, path:src/, -path:tests, -ext:md and case:yes|no|smart filter files, all of them applying at once; free text gets the modalities that suit it",
+                    "help": "This help"
+                },
+                "filters": {
+                    "file_type": "ripgrep type names, comma separated (rust, py, ts, js, go, java, md, json, ...)",
+                    "include": "Globs files must match, e.g. [\"src/**\"]",
+                    "exclude": "Globs that rule files out, e.g. [\"**/tests/**\"]",
+                    "max_per_file": "Cap matches per file so one noisy file cannot fill the result"
+                },
+                "output": {
+                    "matches": "One entry per match with its context (default)",
+                    "grouped": "One entry per file with its count and matches; merge joins matches whose context overlaps into numbered snippets",
+                    "summary_only": "Counts per file, no content; counts up to 10000 matches unless limit says otherwise"
+                },
+                "roots": {
+                    "roots": "Workspace root names or paths to search instead of path; [\"*\"] searches every root",
+                    "repos": "Git URLs searched as extra roots: cloned shallow into the cache on first use (ref picks a branch or tag) and fetched again after 15 minutes",
+                    "results": "One entry per root with its name, path, the action's usual fields or an error, and for repos the commit searched; count sums them"
+                },
+                "case": "Smart by default: insensitive unless the pattern has an uppercase letter; ignore_case or case_sensitive override it"
+            },
+            "error": null,
+            "meta": { "tool": "search", "action": "help" }
+        })
+    }
+}
+
+/// The pattern and the path to search, which must exist
+fn target(args: &SearchToolArgs) -> Result<(String, PathBuf)> {
+    let pattern = args.pattern.clone().ok_or_else(|| ToolError::invalid("pattern required"))?;
+    let path = PathBuf::from(shellexpand::tilde(args.path.as_deref().unwrap_or(".")).as_ref());
+    if !path.exists() {
+        return Err(ToolError::not_found(format!("Path not found: {}", path.display())).into());
+    }
+    Ok((pattern, path))
+}
+
+/// A match as returned, optionally naming its file
+fn found(result: &SearchResult, with_file: bool) -> Value {
+    let mut found = json!({
+        "line": result.line_number,
+        "column": result.column,
+        "text": result.match_text
+    });
+    if with_file {
+        found["file"] = json!(result.file_path.display().to_string());
+    }
+    if !result.context_before.is_empty() {
+        found["before"] = json!(result.context_before);
+    }
+    if !result.context_after.is_empty() {
+        found["after"] = json!(result.context_after);
+    }
+    found
+}
+
+/// Matches by file, files in the order they were found
+fn group(results: &[SearchResult]) -> Vec<(String, Vec<&SearchResult>)> {
+    let mut groups: Vec<(String, Vec<&SearchResult>)> = Vec::new();
+    for result in results {
+        let file = result.file_path.display().to_string();
+        match groups.last_mut() {
+            Some((last, hits)) if *last == file => hits.push(result),
+            _ => groups.push((file, vec![result])),
+        }
+    }
+    groups
+}
+
+/// One file's matches with their context joined into runs of adjacent
+/// lines, numbered the way grep does: `12:` on a match, `12-` on context
+fn snippets(hits: &[&SearchResult]) -> Vec<Value> {
+    // Line number to (text, is a match line)
+    let mut lines: BTreeMap<usize, (&str, bool)> = BTreeMap::new();
+    for hit in hits {
+        let first = hit.line_number;
+        let before = first - hit.context_before.len();
+        for (i, text) in hit.context_before.iter().enumerate() {
+            lines.entry(before + i).or_insert((text, false));
+        }
+        let matched: Vec<&str> = hit.match_text.split('\n').collect();
+        for (i, text) in matched.iter().enumerate() {
+            lines.insert(first + i, (text, true));
+        }
+        let after = first + matched.len();
+        for (i, text) in hit.context_after.iter().enumerate() {
+            lines.entry(after + i).or_insert((text, false));
+        }
+    }
+
+    let mut snippets = Vec::new();
+    let mut run: Vec<(usize, &str, bool)> = Vec::new();
+    for (number, (text, matched)) in lines {
+        if run.last().is_some_and(|&(last, _, _)| last + 1 != number) {
+            snippets.push(snippet(&run));
+            run.clear();
+        }
+        run.push((number, text, matched));
+    }
+    if !run.is_empty() {
+        snippets.push(snippet(&run));
+    }
+    snippets
+}
+
+fn snippet(run: &[(usize, &str, bool)]) -> Value {
+    let width = run.last().map_or(1, |&(last, _, _)| last.to_string().len());
+    let text: Vec<String> = run.iter()
+        .map(|&(number, text, matched)| format!("{:>width$}{}{}", number, if matched { ':' } else { '-' }, text))
+        .collect();
+    json!({
+        "start": run[0].0,
+        "end": run[run.len() - 1].0,
+        "matches": run.iter().filter(|(_, _, matched)| *matched).count(),
+        "text": text.join("\n")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::RootSource;
+
+    #[tokio::test]
+    async fn test_search_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {\n    let user_service = UserService::new();\n}\n").unwrap();
+        std::fs::write(dir.path().join("main.py"), "user_service = UserService()\n").unwrap();
+        let tool = SearchTool::new();
+        let run = |params: Value| {
+            let mut params = params;
+            params["path"] = json!(dir.path());
+            let args: SearchToolArgs = serde_json::from_value(params).unwrap();
+            async { tool.execute(args).await }
+        };
+
+        let found = run(json!({ "pattern": "UserService::new", "literal": true, "type": "rust", "context": 1 })).await.unwrap();
+        assert_eq!(found["meta"]["action"], "text");
+        let data = &found["data"];
+        assert_eq!(data["count"], 1);
+        assert_eq!(data["matches"][0]["line"], 2);
+        assert_eq!(data["matches"][0]["before"], json!(["fn main() {"]));
+
+        let both = run(json!({ "action": "grep", "query": "userservice", "case_sensitive": false })).await.unwrap();
+        assert_eq!(both["data"]["count"], 2);
+        let exact = run(json!({ "action": "text", "pattern": "userservice" })).await.unwrap();
+        assert_eq!(exact["data"]["count"], 2);
+        let sensitive = run(json!({ "action": "text", "pattern": "userservice", "case_sensitive": true })).await.unwrap();
+        assert_eq!(sensitive["data"]["count"], 0);
+
+        assert!(run(json!({ "action": "text" })).await.is_err());
+
+        let body: String = (1..=20).map(|i| if [3, 5, 15].contains(&i) { format!("hit {}\n", i) } else { format!("line {}\n", i) }).collect();
+        std::fs::write(dir.path().join("notes.txt"), body).unwrap();
+        let grouped = run(json!({ "pattern": "hit", "output": "grouped", "merge": true, "context": 1 })).await.unwrap();
+        let files = grouped["data"]["files"].as_array().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0]["count"], 3);
+        let merged = files[0]["snippets"].as_array().unwrap();
+        assert_eq!(merged.len(), 2);
+        assert_eq!((merged[0]["start"].as_u64(), merged[0]["end"].as_u64(), merged[0]["matches"].as_u64()), (Some(2), Some(6), Some(2)));
+        assert_eq!(merged[0]["text"], "2-line 2\n3:hit 3\n4-line 4\n5:hit 5\n6-line 6");
+        let unmerged = run(json!({ "pattern": "hit", "output": "files" })).await.unwrap();
+        assert_eq!(unmerged["data"]["files"][0]["matches"][2]["line"], 15);
+        assert!(unmerged["data"]["matches"].is_null());
+
+        let summary = run(json!({ "pattern": "e", "summary_only": true })).await.unwrap();
+        let counts: Vec<(&str, u64)> = summary["data"]["files"].as_array().unwrap().iter()
+            .map(|f| (f["file"].as_str().unwrap().rsplit('/').next().unwrap(), f["count"].as_u64().unwrap()))
+            .collect();
+        assert_eq!(counts, [("main.py", 1), ("main.rs", 1), ("notes.txt", 17)]);
+        assert!(summary["data"]["files"][0]["matches"].is_null());
+        assert!(run(json!({ "pattern": "e", "output": "tree" })).await.is_err());
+
+        std::fs::write(dir.path().join("lib.rs"), "fn check(v: i32) -> i32 {\n    if v > 10 { return 10 }\n    v\n}\n").unwrap();
+        let ast = run(json!({ "action": "ast", "pattern": "if $COND { return $X }", "lang": "rust" })).await.unwrap();
+        assert_eq!(ast["meta"]["action"], "ast");
+        assert_eq!(ast["data"]["count"], 1);
+        let hit = &ast["data"]["matches"][0];
+        assert_eq!((hit["line"].as_u64(), hit["node_type"].as_str()), (Some(2), Some("if_expression")));
+        assert_eq!(hit["captures"], json!({ "COND": "v > 10", "X": "10" }));
+        let calls = run(json!({ "action": "sg", "pattern": "UserService::new($$$)" })).await.unwrap();
+        assert_eq!(calls["data"]["matches"][0]["text"], "UserService::new()");
+        assert!(run(json!({ "action": "ast", "pattern": "if $C {", "language": "rust" })).await.is_err());
+        assert!(run(json!({ "action": "ast", "pattern": "$X", "language": "cobol" })).await.is_err());
+
+        let lib = dir.path().join("lib.rs");
+        std::fs::write(&lib, "fn f() {\r\n    if ready(a) { go(a, 1); }\r\n    go(b, 2);\r\n}\r\n").unwrap();
+        let swap = json!({ "action": "rewrite", "pattern": "go($A, $B)", "rewrite": "go($B, $A)", "language": "rust", "dry_run": true });
+        let preview = run(swap.clone()).await.unwrap();
+        assert_eq!(preview["data"]["replacements"], 2);
+        assert!(preview["data"]["results"][0]["diff"].as_str().unwrap().contains("+    go(2, b);"));
+        assert!(std::fs::read_to_string(&lib).unwrap().contains("go(b, 2)"));
+        let mut apply = swap.clone();
+        apply["dry_run"] = json!(false);
+        run(apply).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&lib).unwrap(), "fn f() {\r\n    if ready(a) { go(1, a); }\r\n    go(2, b);\r\n}\r\n");
+
+        let unwrap = json!({ "action": "rewrite", "pattern": "if ready($X) { $$$BODY }", "rewrite": "if !ready($X) {\n    return;\n}\n$BODY", "language": "rust" });
+        run(unwrap).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&lib).unwrap(), "fn f() {\r\n    if !ready(a) {\r\n        return;\r\n    }\r\n    go(1, a);\r\n    go(2, b);\r\n}\r\n");
+
+        let broken = json!({ "action": "rewrite", "pattern": "go($A, $B)", "rewrite": "go($A,", "language": "rust" });
+        assert!(run(broken).await.is_err());
+        assert!(std::fs::read_to_string(&lib).unwrap().contains("go(2, b);"));
+        assert!(run(json!({ "action": "rewrite", "pattern": "go($A, $B)", "rewrite": "go($C)", "language": "rust" })).await.is_err());
+        assert!(run(json!({ "action": "rewrite", "pattern": "go($A, $B)", "rewrite": "go()", "max_replacements": 1 })).await.is_err());
+
+        let symbols = run(json!({ "action": "symbols", "query": "mn", "kind": "fn" })).await.unwrap();
+        let hit = &symbols["data"]["symbols"][0];
+        assert_eq!((hit["name"].as_str(), hit["kind"].as_str(), hit["line"].as_u64()), (Some("main"), Some("function"), Some(1)));
+        assert_eq!(hit["signature"], "fn main()");
+        assert!(hit["file"].as_str().unwrap().ends_with("main.rs"));
+        assert!(symbols["data"]["index"]["parsed"].as_u64().unwrap() >= 1);
+        let again = run(json!({ "action": "symbols", "query": "mn" })).await.unwrap();
+        assert_eq!(again["data"]["index"]["parsed"], 0);
+        assert!(run(json!({ "action": "symbols", "query": "f", "kind": "gadget" })).await.is_err());
+        assert_eq!(run(json!({})).await.unwrap()["meta"]["action"], "help");
+        assert!("nope".parse::<SearchAction>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_roots() {
+        let (one, two) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        std::fs::write(one.path().join("a.rs"), "fn parse_config() {}\n").unwrap();
+        std::fs::write(two.path().join("b.rs"), "fn parse_config() {}\nfn parse_args() {}\n").unwrap();
+        let origin = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git").args(args).current_dir(origin.path()).output().unwrap();
+            assert!(output.status.success(), "{:?}", output);
+        };
+        git(&["init", "--quiet", "-b", "main"]);
+        std::fs::write(origin.path().join("c.py"), "def parse_config():\n    pass\n").unwrap();
+        git(&["add", "."]);
+        git(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "--quiet", "-m", "one"]);
+
+        let mut tool = SearchTool::new();
+        let cache = tempfile::tempdir().unwrap();
+        tool.repo_cache = cache.path().to_path_buf();
+        let run = |params: Value| tool.execute(serde_json::from_value(params).unwrap());
+
+        let url = format!("file://{}", origin.path().display());
+        let roots = json!([one.path(), two.path()]);
+        let found = run(json!({ "pattern": "parse_", "roots": roots, "repos": [url, "file:///nonexistent/repo"] })).await.unwrap();
+        let data = &found["data"];
+        assert_eq!(data["count"], 4);
+        assert_eq!(data["roots"][0]["count"], 1);
+        assert_eq!(data["roots"][1]["count"], 2);
+        assert_eq!(data["roots"][2]["repo"], url.as_str());
+        assert_eq!(data["roots"][2]["commit"].as_str().unwrap().len(), 40);
+        assert!(data["roots"][2]["matches"][0]["file"].as_str().unwrap().ends_with("c.py"));
+        assert!(data["roots"][3]["error"].as_str().unwrap().contains("Cannot clone"));
+
+        let symbols = run(json!({ "action": "symbols", "query": "prsCfg", "repos": [url] })).await.unwrap();
+        assert_eq!(symbols["data"]["roots"][0]["symbols"][0]["name"], "parse_config");
+        assert!(run(json!({ "action": "rewrite", "pattern": "f()", "rewrite": "g()", "repos": [url] })).await.is_err());
+        assert!(run(json!({ "pattern": "x", "roots": ["*"] })).await.is_err());
+
+        let history = run(json!({ "action": "history", "pattern": "parse_config", "literal": true, "path": origin.path(), "include": ["*.py"] })).await.unwrap();
+        let commit = &history["data"]["commits"][0];
+        assert_eq!((history["data"]["count"].as_u64(), history["data"]["mode"].as_str()), (Some(1), Some("pickaxe")));
+        assert_eq!((commit["subject"].as_str(), commit["author"].as_str()), (Some("one"), Some("t")));
+        assert_eq!(commit["files"][0]["hunks"][0]["lines"], "+def parse_config():\n+    pass");
+        let excluded = run(json!({ "action": "history", "pattern": "parse_config", "path": origin.path(), "exclude": ["*.py"] })).await.unwrap();
+        assert_eq!(excluded["data"]["count"], 0);
+        assert!(run(json!({ "action": "history", "pattern": "x", "path": one.path() })).await.is_err());
+
+        let unified = run(json!({ "action": "unified", "pattern": "text:parse_ lang:rust", "path": two.path() })).await.unwrap();
+        assert_eq!(unified["data"]["searches"], json!([{ "modality": "text", "term": "parse_" }]));
+        assert_eq!((unified["data"]["count"].as_u64(), unified["data"]["matches"][0]["modality"].as_str()), (Some(2), Some("text")));
+        assert_eq!(run(json!({ "action": "unified", "pattern": "text:parse_ ext:py", "path": two.path() })).await.unwrap()["data"]["count"], 0);
+        assert!(run(json!({ "action": "unified", "pattern": "case:maybe", "path": two.path() })).await.is_err());
+
+        let registry_roots = Arc::new(Roots::new());
+        registry_roots.add(one.path().to_str().unwrap(), Some("one".into()), RootSource::Config).unwrap();
+        let tool = SearchTool::with_roots(registry_roots);
+        let all = tool.execute(serde_json::from_value(json!({ "pattern": "parse_config", "roots": ["*"] })).unwrap()).await.unwrap();
+        let names: Vec<&str> = all["data"]["roots"].as_array().unwrap().iter().filter_map(|r| r["root"].as_str()).collect();
+        assert!(names.contains(&"one"));
+        assert!(tool.execute(serde_json::from_value(json!({ "pattern": "x", "roots": ["missing"] })).unwrap()).await.is_err());
+    }
+}
@@ -0,0 +1,605 @@
+/// Object storage tool (S3, GCS, MinIO)
+///
+/// Actions: profiles, buckets, list, get, put, head, delete, presign, sync, help
+///
+/// Works against any S3-compatible service through the accounts under
+/// `[storage.profiles]` in the config (see `StorageConfig`). get streams
+/// an object to a local file or returns small ones inline; put uploads a
+/// file, in parts when it is large, or inline content. presign hands out a
+/// time-limited URL for a GET or PUT. sync makes a bucket prefix match a
+/// local directory (up) or the directory match the prefix (down), copying
+/// only what differs by size or MD5.
+
+use anyhow::Result;
+use crate::config::{StorageConfig, StorageProfile};
+use crate::error::ToolError;
+use super::hash::{self, Algorithm};
+use super::storage_s3::{Object, S3Client, MAX_PRESIGN_SECS};
+use base64::Engine as _;
+use futures::StreamExt;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+/// Largest object get returns inline unless max_bytes says otherwise
+const MAX_INLINE_BYTES: u64 = 1024 * 1024;
+
+/// Objects list returns per page unless max_keys says otherwise
+const DEFAULT_MAX_KEYS: usize = 100;
+
+/// Lifetime of a presigned URL unless expires_secs says otherwise
+const DEFAULT_PRESIGN_SECS: u64 = 3600;
+
+/// Transfers sync runs at once
+const SYNC_CONCURRENCY: usize = 4;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageAction {
+    Profiles,
+    Buckets,
+    #[default]
+    List,
+    Get,
+    Put,
+    Head,
+    Delete,
+    Presign,
+    Sync,
+    Help,
+}
+
+impl std::str::FromStr for StorageAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "profiles" | "accounts" => Ok(Self::Profiles),
+            "buckets" | "list_buckets" => Ok(Self::Buckets),
+            "list" | "ls" | "objects" | "" => Ok(Self::List),
+            "get" | "download" | "read" => Ok(Self::Get),
+            "put" | "upload" | "write" => Ok(Self::Put),
+            "head" | "stat" | "info" => Ok(Self::Head),
+            "delete" | "rm" | "remove" => Ok(Self::Delete),
+            "presign" | "presigned_url" | "share" => Ok(Self::Presign),
+            "sync" => Ok(Self::Sync),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl StorageAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Profiles => "profiles",
+            Self::Buckets => "buckets",
+            Self::List => "list",
+            Self::Get => "get",
+            Self::Put => "put",
+            Self::Head => "head",
+            Self::Delete => "delete",
+            Self::Presign => "presign",
+            Self::Sync => "sync",
+            Self::Help => "help",
+        }
+    }
+}
+
+/// Which side sync changes
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum SyncDirection {
+    /// Local directory to bucket
+    #[default]
+    Up,
+    /// Bucket to local directory
+    Down,
+}
+
+impl std::str::FromStr for SyncDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "up" | "upload" | "push" | "" => Ok(Self::Up),
+            "down" | "download" | "pull" => Ok(Self::Down),
+            _ => Err(ToolError::invalid(format!("Unknown direction: {} (use up or down)", s)).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StorageToolArgs {
+    pub action: Option<String>,
+    /// Configured profile; the default one when left out
+    pub profile: Option<String>,
+    /// Bucket; the profile's bucket when left out
+    pub bucket: Option<String>,
+    pub key: Option<String>,
+    /// For list and sync: key prefix
+    pub prefix: Option<String>,
+    /// For list: roll keys up to this character, e.g. "/"
+    pub delimiter: Option<String>,
+    pub max_keys: Option<usize>,
+    /// For list: continuation token from the previous page
+    pub token: Option<String>,
+    /// Local file (get, put) or directory (sync)
+    pub path: Option<String>,
+    /// For put: text to upload instead of a file
+    pub content: Option<String>,
+    /// For put: base64 bytes to upload instead of a file
+    pub content_base64: Option<String>,
+    /// For get without path: largest object returned inline
+    pub max_bytes: Option<u64>,
+    /// For presign: GET or PUT
+    pub method: Option<String>,
+    pub expires_secs: Option<u64>,
+    /// For sync: up (local to bucket) or down (bucket to local)
+    pub direction: Option<String>,
+    /// For sync: remove what the source side does not have
+    #[serde(default)]
+    pub delete: bool,
+    /// For sync: report the plan without copying
+    #[serde(default)]
+    pub dry_run: bool,
+    /// For sync: glob patterns of relative paths to leave out
+    pub exclude: Option<Vec<String>>,
+}
+
+pub struct StorageToolDefinition;
+
+impl StorageToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "storage",
+            "description": "S3-compatible object storage (AWS S3, GCS, MinIO, R2) through configured profiles: profiles, buckets, list, get (to a file or inline), put (file or content), head, delete, presign (time-limited GET/PUT URL), sync (local directory up to a bucket prefix or down from it), help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["profiles", "buckets", "list", "get", "put", "head", "delete", "presign", "sync", "help"],
+                        "default": "list"
+                    },
+                    "profile": { "type": "string", "description": "Configured storage profile" },
+                    "bucket": { "type": "string", "description": "Bucket (default: the profile's)" },
+                    "key": { "type": "string", "description": "Object key" },
+                    "prefix": { "type": "string", "description": "For list and sync: key prefix" },
+                    "delimiter": { "type": "string", "description": "For list: group keys up to this, e.g. /" },
+                    "max_keys": { "type": "integer", "description": "For list: objects per page", "default": DEFAULT_MAX_KEYS },
+                    "token": { "type": "string", "description": "For list: next_token of the previous page" },
+                    "path": { "type": "string", "description": "Local file for get and put, directory for sync" },
+                    "content": { "type": "string", "description": "For put: text to upload" },
+                    "content_base64": { "type": "string", "description": "For put: base64 bytes to upload" },
+                    "max_bytes": { "type": "integer", "description": "For get without path: largest object returned inline", "default": MAX_INLINE_BYTES },
+                    "method": { "type": "string", "enum": ["GET", "PUT"], "description": "For presign", "default": "GET" },
+                    "expires_secs": { "type": "integer", "description": "For presign: URL lifetime", "default": DEFAULT_PRESIGN_SECS, "maximum": MAX_PRESIGN_SECS },
+                    "direction": { "type": "string", "enum": ["up", "down"], "description": "For sync: up copies path to the bucket, down the bucket to path", "default": "up" },
+                    "delete": { "type": "boolean", "description": "For sync: remove what the source lacks" },
+                    "dry_run": { "type": "boolean", "description": "For sync: report without copying" },
+                    "exclude": { "type": "array", "items": { "type": "string" }, "description": "For sync: globs of relative paths to skip" }
+                }
+            }
+        })
+    }
+}
+
+pub struct StorageTool {
+    config: StorageConfig,
+}
+
+impl StorageTool {
+    pub fn new() -> Self {
+        Self::with_config(StorageConfig::default())
+    }
+
+    pub fn with_config(config: StorageConfig) -> Self {
+        Self { config }
+    }
+
+    pub async fn execute(&self, args: StorageToolArgs) -> Result<Value> {
+        let action: StorageAction = args.action.as_deref().unwrap_or("list").parse()?;
+        let data = match action {
+            StorageAction::Profiles => self.profiles(),
+            StorageAction::Help => return Ok(self.help()),
+            _ => {
+                let (name, profile) = self.config.profile(args.profile.as_deref())
+                    .ok_or_else(|| ToolError::not_found(match &args.profile {
+                        Some(name) => format!("No storage profile {}; storage(action=\"profiles\") lists them", name),
+                        None => "Several storage profiles and no default_profile; pass profile".to_string(),
+                    }))?;
+                let client = S3Client::new(&profile)?;
+                let mut data = match action {
+                    StorageAction::Buckets => self.buckets(&client).await?,
+                    StorageAction::List => self.list(&client, &profile, &args).await?,
+                    StorageAction::Get => self.get(&client, &profile, &args).await?,
+                    StorageAction::Put => self.put(&client, &profile, &args).await?,
+                    StorageAction::Head => self.head(&client, &profile, &args).await?,
+                    StorageAction::Delete => self.delete(&client, &profile, &args).await?,
+                    StorageAction::Presign => self.presign(&client, &profile, &args)?,
+                    StorageAction::Sync => self.sync(&client, &profile, &args).await?,
+                    StorageAction::Profiles | StorageAction::Help => unreachable!(),
+                };
+                data["profile"] = json!(name);
+                data
+            }
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "storage", "action": action.name() }
+        }))
+    }
+
+    /// Configured profiles without their keys
+    fn profiles(&self) -> Value {
+        let mut names: Vec<&String> = self.config.profiles.keys().collect();
+        names.sort();
+        let profiles: Vec<Value> = names.into_iter().map(|name| {
+            let profile = &self.config.profiles[name];
+            json!({
+                "name": name,
+                "endpoint": profile.endpoint(),
+                "region": profile.region(),
+                "bucket": profile.bucket,
+                "has_credentials": profile.credentials().is_some()
+            })
+        }).collect();
+        json!({
+            "profiles": profiles,
+            "default": self.config.profile(None).map(|(name, _)| name),
+            "from_environment": self.config.profiles.is_empty()
+        })
+    }
+
+    async fn buckets(&self, client: &S3Client) -> Result<Value> {
+        let buckets: Vec<Value> = client.list_buckets().await?.into_iter()
+            .map(|b| json!({ "name": b.name, "created": b.created }))
+            .collect();
+        Ok(json!({ "buckets": buckets, "count": buckets.len() }))
+    }
+
+    async fn list(&self, client: &S3Client, profile: &StorageProfile, args: &StorageToolArgs) -> Result<Value> {
+        let bucket = bucket(profile, args)?;
+        let page = client.list(
+            &bucket,
+            args.prefix.as_deref(),
+            args.delimiter.as_deref(),
+            args.max_keys.unwrap_or(DEFAULT_MAX_KEYS),
+            args.token.as_deref(),
+        ).await?;
+        let objects: Vec<Value> = page.objects.iter().map(object_json).collect();
+        Ok(json!({
+            "bucket": bucket,
+            "objects": objects,
+            "count": objects.len(),
+            "prefixes": page.prefixes,
+            "next_token": page.next_token
+        }))
+    }
+
+    async fn get(&self, client: &S3Client, profile: &StorageProfile, args: &StorageToolArgs) -> Result<Value> {
+        let bucket = bucket(profile, args)?;
+        let key = args.key.as_deref().ok_or_else(|| ToolError::invalid("key required"))?;
+        if let Some(path) = &args.path {
+            let path = PathBuf::from(shellexpand::tilde(path).to_string());
+            let (size, meta) = client.download(&bucket, key, &path).await?;
+            crate::events::publish(crate::events::DOWNLOAD_FINISHED, json!({
+                "url": format!("s3://{}/{}", bucket, key),
+                "output": path,
+                "size": size
+            }));
+            return Ok(json!({ "bucket": bucket, "key": key, "path": path, "size": size, "etag": meta.etag, "content_type": meta.content_type }));
+        }
+        let (bytes, meta) = client.get_bytes(&bucket, key, args.max_bytes.unwrap_or(MAX_INLINE_BYTES)).await?;
+        let mut data = json!({ "bucket": bucket, "key": key, "size": bytes.len(), "etag": meta.etag, "content_type": meta.content_type });
+        match String::from_utf8(bytes) {
+            Ok(text) => data["content"] = json!(text),
+            Err(e) => data["content_base64"] = json!(base64::engine::general_purpose::STANDARD.encode(e.into_bytes())),
+        }
+        Ok(data)
+    }
+
+    async fn put(&self, client: &S3Client, profile: &StorageProfile, args: &StorageToolArgs) -> Result<Value> {
+        let bucket = bucket(profile, args)?;
+        let key = args.key.as_deref().ok_or_else(|| ToolError::invalid("key required"))?;
+        let (size, etag) = match (&args.path, &args.content, &args.content_base64) {
+            (Some(path), None, None) => client.upload(&bucket, key, Path::new(&shellexpand::tilde(path).to_string())).await?,
+            (None, Some(content), None) => (content.len() as u64, client.put_bytes(&bucket, key, content.clone().into_bytes()).await?),
+            (None, None, Some(encoded)) => {
+                let bytes = base64::engine::general_purpose::STANDARD.decode(encoded)
+                    .map_err(|e| ToolError::invalid(format!("content_base64: {}", e)))?;
+                (bytes.len() as u64, client.put_bytes(&bucket, key, bytes).await?)
+            }
+            _ => return Err(ToolError::invalid("exactly one of path, content or content_base64 required").into()),
+        };
+        Ok(json!({ "bucket": bucket, "key": key, "size": size, "etag": etag }))
+    }
+
+    async fn head(&self, client: &S3Client, profile: &StorageProfile, args: &StorageToolArgs) -> Result<Value> {
+        let bucket = bucket(profile, args)?;
+        let key = args.key.as_deref().ok_or_else(|| ToolError::invalid("key required"))?;
+        let meta = client.head(&bucket, key).await?;
+        Ok(json!({
+            "bucket": bucket,
+            "key": key,
+            "size": meta.size,
+            "etag": meta.etag,
+            "content_type": meta.content_type,
+            "last_modified": meta.last_modified
+        }))
+    }
+
+    async fn delete(&self, client: &S3Client, profile: &StorageProfile, args: &StorageToolArgs) -> Result<Value> {
+        let bucket = bucket(profile, args)?;
+        let key = args.key.as_deref().ok_or_else(|| ToolError::invalid("key required"))?;
+        client.delete(&bucket, key).await?;
+        Ok(json!({ "bucket": bucket, "key": key, "deleted": true }))
+    }
+
+    fn presign(&self, client: &S3Client, profile: &StorageProfile, args: &StorageToolArgs) -> Result<Value> {
+        let bucket = bucket(profile, args)?;
+        let key = args.key.as_deref().ok_or_else(|| ToolError::invalid("key required"))?;
+        let method = match args.method.as_deref().unwrap_or("GET").to_uppercase().as_str() {
+            "GET" => Method::GET,
+            "PUT" => Method::PUT,
+            other => return Err(ToolError::invalid(format!("Cannot presign {} (use GET or PUT)", other)).into()),
+        };
+        let expires = args.expires_secs.unwrap_or(DEFAULT_PRESIGN_SECS).clamp(1, MAX_PRESIGN_SECS);
+        let now = chrono::Utc::now();
+        Ok(json!({
+            "bucket": bucket,
+            "key": key,
+            "method": method.as_str(),
+            "url": client.presign(&method, &bucket, key, expires, now),
+            "expires_at": (now + chrono::Duration::seconds(expires as i64)).to_rfc3339()
+        }))
+    }
+
+    async fn sync(&self, client: &S3Client, profile: &StorageProfile, args: &StorageToolArgs) -> Result<Value> {
+        let bucket = bucket(profile, args)?;
+        let direction: SyncDirection = args.direction.as_deref().unwrap_or("up").parse()?;
+        let root = PathBuf::from(shellexpand::tilde(args.path.as_deref().ok_or_else(|| ToolError::invalid("path required"))?).to_string());
+        let prefix = match args.prefix.as_deref().map(|p| p.trim_matches('/')) {
+            Some(p) if !p.is_empty() => format!("{}/", p),
+            _ => String::new(),
+        };
+        let exclude = globs(args.exclude.as_deref().unwrap_or_default())?;
+
+        let remote: HashMap<String, Object> = client.list_all(&bucket, Some(&prefix).filter(|p| !p.is_empty()).map(String::as_str)).await?
+            .into_iter()
+            .filter_map(|object| {
+                let relative = object.key.strip_prefix(&prefix)?.to_string();
+                (!relative.is_empty() && !relative.ends_with('/') && !exclude.is_match(&relative)).then_some((relative, object))
+            })
+            .collect();
+        let local = match direction {
+            SyncDirection::Down if !root.exists() => BTreeMap::new(),
+            _ => local_files(&root, &exclude)?,
+        };
+
+        let (sources, targets): (Vec<&String>, Vec<&String>) = match direction {
+            SyncDirection::Up => (local.keys().collect(), remote.keys().collect()),
+            SyncDirection::Down => (remote.keys().collect(), local.keys().collect()),
+        };
+        let mut copy = Vec::new();
+        let mut unchanged = 0usize;
+        for relative in sources {
+            if direction == SyncDirection::Down && !safe_relative(relative) {
+                continue;
+            }
+            let same = match (local.get(relative), remote.get(relative)) {
+                (Some(file), Some(object)) => same_content(file, object),
+                _ => false,
+            };
+            if same {
+                unchanged += 1;
+            } else {
+                copy.push(relative.clone());
+            }
+        }
+        let mut delete: Vec<String> = Vec::new();
+        if args.delete {
+            let source: Vec<&String> = match direction {
+                SyncDirection::Up => local.keys().collect(),
+                SyncDirection::Down => remote.keys().collect(),
+            };
+            delete = targets.into_iter().filter(|t| !source.contains(t)).cloned().collect();
+            delete.sort();
+        }
+        copy.sort();
+
+        let mut failed = Vec::new();
+        if !args.dry_run {
+            // Collected first: a lazily mapped stream of borrowing futures
+            // trips the Send check on the registry's futures
+            let transfers: Vec<_> = copy.iter().map(|relative| {
+                let key = format!("{}{}", prefix, relative);
+                let file = root.join(relative);
+                let bucket = bucket.as_str();
+                async move {
+                    let result = match direction {
+                        SyncDirection::Up => client.upload(bucket, &key, &file).await.map(|_| ()),
+                        SyncDirection::Down => client.download(bucket, &key, &file).await.map(|_| ()),
+                    };
+                    (relative.clone(), result)
+                }
+            }).collect();
+            let transfers = futures::stream::iter(transfers)
+                .buffer_unordered(SYNC_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+            for (relative, result) in transfers {
+                if let Err(e) = result {
+                    failed.push(json!({ "path": relative, "error": e.to_string() }));
+                }
+            }
+            for relative in &delete {
+                let result = match direction {
+                    SyncDirection::Up => client.delete(&bucket, &format!("{}{}", prefix, relative)).await,
+                    SyncDirection::Down => std::fs::remove_file(root.join(relative)).map_err(Into::into),
+                };
+                if let Err(e) = result {
+                    failed.push(json!({ "path": relative, "error": e.to_string() }));
+                }
+            }
+        }
+
+        Ok(json!({
+            "bucket": bucket,
+            "prefix": prefix,
+            "path": root,
+            "direction": if direction == SyncDirection::Up { "up" } else { "down" },
+            "dry_run": args.dry_run,
+            "copied": copy,
+            "deleted": delete,
+            "unchanged": unchanged,
+            "failed": failed
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "storage",
+                "actions": {
+                    "profiles": "Configured storage profiles (keys are never shown)",
+                    "buckets": "Buckets the profile can see",
+                    "list": "Objects in a bucket (prefix, delimiter, max_keys, token for the next page)",
+                    "get": "Download key to path, or return it inline up to max_bytes",
+                    "put": "Upload path, content or content_base64 to key; large files go up in parts",
+                    "head": "Size, ETag, type and modification time of key",
+                    "delete": "Delete key",
+                    "presign": "Time-limited URL to GET or PUT key without credentials",
+                    "sync": "Copy what differs between path and the bucket prefix (direction up or down, delete, dry_run, exclude)",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "storage", "action": "help" }
+        })
+    }
+}
+
+impl Default for StorageTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn bucket(profile: &StorageProfile, args: &StorageToolArgs) -> Result<String> {
+    args.bucket.clone()
+        .or_else(|| profile.bucket.clone())
+        .ok_or_else(|| ToolError::invalid("bucket required (or set bucket on the profile)").into())
+}
+
+fn object_json(object: &Object) -> Value {
+    json!({
+        "key": object.key,
+        "size": object.size,
+        "last_modified": object.last_modified,
+        "etag": object.etag
+    })
+}
+
+fn globs(patterns: &[String]) -> Result<globset::GlobSet> {
+    let mut set = globset::GlobSetBuilder::new();
+    for pattern in patterns {
+        set.add(globset::Glob::new(pattern).map_err(|e| ToolError::invalid(format!("exclude {}: {}", pattern, e)))?);
+    }
+    Ok(set.build()?)
+}
+
+/// Files under `root` by `/`-separated relative path
+fn local_files(root: &Path, exclude: &globset::GlobSet) -> Result<BTreeMap<String, PathBuf>> {
+    if !root.is_dir() {
+        return Err(ToolError::not_found(format!("{} is not a directory", root.display())).into());
+    }
+    Ok(walkdir::WalkDir::new(root).into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(root).ok()?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            (!exclude.is_match(&relative)).then(|| (relative, entry.into_path()))
+        })
+        .collect())
+}
+
+/// Whether the local file already holds the object: equal sizes and, when
+/// the ETag is a plain MD5 (not a multipart one), equal digests
+fn same_content(file: &Path, object: &Object) -> bool {
+    let Ok(metadata) = std::fs::metadata(file) else { return false };
+    if metadata.len() != object.size {
+        return false;
+    }
+    match object.etag.as_deref().map(|e| e.trim_matches('"')) {
+        Some(etag) if etag.len() == 32 && !etag.contains('-') => {
+            hash::file_hex(Algorithm::Md5, file).is_ok_and(|md5| md5 == etag)
+        }
+        _ => true,
+    }
+}
+
+/// Keys sync down may write: relative, without `..`
+fn safe_relative(key: &str) -> bool {
+    Path::new(key).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_helpers() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("a.txt"), "hello").unwrap();
+        std::fs::write(dir.path().join("sub/b.log"), "x").unwrap();
+        let files = local_files(dir.path(), &globs(&["*.log".to_string()]).unwrap()).unwrap();
+        assert_eq!(files.keys().collect::<Vec<_>>(), vec!["a.txt"]);
+        let files = local_files(dir.path(), &globs(&[]).unwrap()).unwrap();
+        assert!(files.contains_key("sub/b.log"));
+
+        let object = |size, etag: &str| Object { key: "a.txt".into(), size, last_modified: None, etag: Some(etag.into()) };
+        let file = dir.path().join("a.txt");
+        assert!(same_content(&file, &object(5, "\"5d41402abc4b2a76b9719d911017c592\"")));
+        assert!(!same_content(&file, &object(5, "\"00000000000000000000000000000000\"")));
+        assert!(same_content(&file, &object(5, "\"abc-2\"")));
+        assert!(!same_content(&file, &object(6, "\"abc-2\"")));
+
+        assert!(safe_relative("docs/a.txt"));
+        assert!(!safe_relative("../etc/passwd"));
+        assert!(!safe_relative("/etc/passwd"));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let mut config = StorageConfig::default();
+        assert_eq!(config.profile(None).unwrap().0, "default");
+        assert!(config.profile(Some("other")).is_none());
+        config.profiles.insert("minio".into(), StorageProfile {
+            endpoint: Some("http://localhost:9000".into()),
+            access_key_id: Some("key".into()),
+            secret_access_key: Some("secret".into()),
+            ..Default::default()
+        });
+        assert_eq!(config.profile(None).unwrap().0, "minio");
+        config.profiles.insert("gcs".into(), StorageProfile::default());
+        assert!(config.profile(None).is_none());
+        config.default_profile = Some("gcs".into());
+        assert_eq!(config.profile(None).unwrap().0, "gcs");
+
+        let listed = StorageTool::with_config(config).profiles();
+        assert_eq!(listed["profiles"][1]["name"], "minio");
+        assert_eq!(listed["profiles"][1]["has_credentials"], true);
+        assert!(!listed.to_string().contains("secret"));
+    }
+}
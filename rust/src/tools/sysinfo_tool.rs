@@ -0,0 +1,561 @@
+/// Host system information tool
+///
+/// Actions: overview, os, cpu, memory, disk, network, runtimes, env, power, help
+///
+/// Reports what an agent may want to adapt to: the OS and kernel, CPU load,
+/// memory and disk headroom, network interfaces, which language runtimes
+/// are installed and at what version, the environment (secret-named
+/// variables masked) and battery and thermal state. Figures come from
+/// /proc and /sys on Linux and from sysctl, vm_stat, pmset and ifconfig on
+/// macOS; sections a platform cannot report are null.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use crate::redaction::{self, REDACTED};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Longest a probe command may run
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runtimes probed by `runtimes`: name, binary and version flag
+const RUNTIMES: &[(&str, &str, &str)] = &[
+    ("node", "node", "--version"),
+    ("deno", "deno", "--version"),
+    ("bun", "bun", "--version"),
+    ("python", "python3", "--version"),
+    ("uv", "uv", "--version"),
+    ("rustc", "rustc", "--version"),
+    ("cargo", "cargo", "--version"),
+    ("go", "go", "version"),
+    ("java", "java", "-version"),
+    ("ruby", "ruby", "--version"),
+    ("docker", "docker", "--version"),
+    ("git", "git", "--version"),
+];
+
+/// Filesystems `disk` leaves out: memory-backed and virtual ones
+const PSEUDO_FILESYSTEMS: &[&str] = &["tmpfs", "devtmpfs", "devfs", "overlay", "shm", "map", "none", "udev"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SysAction {
+    #[default]
+    Overview,
+    Os,
+    Cpu,
+    Memory,
+    Disk,
+    Network,
+    Runtimes,
+    Env,
+    Power,
+    Help,
+}
+
+impl std::str::FromStr for SysAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "overview" | "all" | "" => Ok(Self::Overview),
+            "os" | "system" => Ok(Self::Os),
+            "cpu" | "load" => Ok(Self::Cpu),
+            "memory" | "mem" => Ok(Self::Memory),
+            "disk" | "disks" => Ok(Self::Disk),
+            "network" | "net" | "interfaces" => Ok(Self::Network),
+            "runtimes" | "versions" => Ok(Self::Runtimes),
+            "env" | "environment" => Ok(Self::Env),
+            "power" | "battery" | "thermal" => Ok(Self::Power),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl SysAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Overview => "overview",
+            Self::Os => "os",
+            Self::Cpu => "cpu",
+            Self::Memory => "memory",
+            Self::Disk => "disk",
+            Self::Network => "network",
+            Self::Runtimes => "runtimes",
+            Self::Env => "env",
+            Self::Power => "power",
+            Self::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SysinfoToolArgs {
+    pub action: Option<String>,
+    /// For env: only variables whose names start with this, case-insensitively
+    pub prefix: Option<String>,
+    /// For env: list names without values
+    #[serde(default)]
+    pub names_only: bool,
+}
+
+pub struct SysinfoToolDefinition;
+
+impl SysinfoToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "sysinfo",
+            "description": "Host machine information: overview, os, cpu (load, usage), memory, disk, network (interfaces, addresses), runtimes (node, python, rustc, ... versions), env (secret values masked), power (battery, thermal), help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["overview", "os", "cpu", "memory", "disk", "network", "runtimes", "env", "power", "help"],
+                        "description": "Section to report; overview reports os, cpu, memory, disk and power",
+                        "default": "overview"
+                    },
+                    "prefix": { "type": "string", "description": "For env: only variables whose names start with this" },
+                    "names_only": { "type": "boolean", "description": "For env: list names without values", "default": false }
+                }
+            }
+        })
+    }
+}
+
+pub struct SysinfoTool;
+
+impl SysinfoTool {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub async fn execute(&self, args: SysinfoToolArgs) -> Result<Value> {
+        let action: SysAction = args.action.as_deref().unwrap_or("overview").parse()?;
+        let data = match action {
+            SysAction::Overview => json!({
+                "os": os().await,
+                "cpu": cpu().await,
+                "memory": memory().await,
+                "disk": disk().await,
+                "power": power().await
+            }),
+            SysAction::Os => os().await,
+            SysAction::Cpu => cpu().await,
+            SysAction::Memory => memory().await,
+            SysAction::Disk => disk().await,
+            SysAction::Network => network().await,
+            SysAction::Runtimes => runtimes().await,
+            SysAction::Env => env(&args),
+            SysAction::Power => power().await,
+            SysAction::Help => return Ok(self.help()),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "sysinfo", "action": action.name() }
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "sysinfo",
+                "actions": {
+                    "overview": "OS, CPU, memory, disk and power in one report",
+                    "os": "OS name and version, kernel, architecture, hostname, uptime",
+                    "cpu": "CPU model, cores, load averages and current usage",
+                    "memory": "Total, available and used memory and swap",
+                    "disk": "Size, used and available space of each mounted filesystem",
+                    "network": "Network interfaces with their addresses and traffic",
+                    "runtimes": "Installed language runtimes and tools with their versions",
+                    "env": "Environment variables, secret-named values masked (prefix, names_only)",
+                    "power": "Battery charge and state, thermal zone temperatures",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "sysinfo", "action": "help" }
+        })
+    }
+}
+
+impl Default for SysinfoTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Trimmed stdout of a command, or stderr when it writes only there (as
+/// `java -version` does); None if it cannot run
+async fn probe(program: &str, args: &[&str]) -> Option<String> {
+    let output = tokio::time::timeout(PROBE_TIMEOUT, Command::new(program).args(args).kill_on_drop(true).output())
+        .await
+        .ok()?
+        .ok()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !stdout.is_empty() {
+        return Some(stdout);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    (output.status.success() || !stderr.is_empty()).then_some(stderr)
+}
+
+fn read(path: impl AsRef<Path>) -> Option<String> {
+    std::fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+async fn os() -> Value {
+    let (name, version) = if cfg!(target_os = "macos") {
+        (Some("macOS".to_string()), probe("sw_vers", &["-productVersion"]).await)
+    } else {
+        let release = read("/etc/os-release").unwrap_or_default();
+        let field = |key: &str| release.lines()
+            .find_map(|l| l.strip_prefix(&format!("{}=", key)))
+            .map(|v| v.trim_matches('"').to_string());
+        (field("PRETTY_NAME").or_else(|| field("NAME")), field("VERSION_ID"))
+    };
+    let hostname = match read("/proc/sys/kernel/hostname") {
+        Some(name) => Some(name),
+        None => probe("hostname", &[]).await,
+    };
+    let uptime_secs = match read("/proc/uptime") {
+        Some(uptime) => uptime.split_whitespace().next().and_then(|s| s.parse::<f64>().ok()).map(|s| s as u64),
+        None => probe("sysctl", &["-n", "kern.boottime"]).await
+            .and_then(|boot| parse_boottime(&boot))
+            .map(|boot| (chrono::Utc::now().timestamp() as u64).saturating_sub(boot)),
+    };
+    json!({
+        "family": std::env::consts::OS,
+        "name": name,
+        "version": version,
+        "kernel": probe("uname", &["-r"]).await,
+        "arch": std::env::consts::ARCH,
+        "hostname": hostname,
+        "uptime_secs": uptime_secs
+    })
+}
+
+/// Seconds since the epoch from macOS `kern.boottime` (`{ sec = 1700000000, usec = 0 } ...`)
+fn parse_boottime(text: &str) -> Option<u64> {
+    text.split("sec =").nth(1)?.split(',').next()?.trim().parse().ok()
+}
+
+async fn cpu() -> Value {
+    let cores = std::thread::available_parallelism().map(|n| n.get()).ok();
+    let model = match read("/proc/cpuinfo") {
+        Some(info) => info.lines()
+            .find(|l| l.starts_with("model name") || l.starts_with("Model"))
+            .and_then(|l| l.split_once(':'))
+            .map(|(_, v)| v.trim().to_string()),
+        None => probe("sysctl", &["-n", "machdep.cpu.brand_string"]).await,
+    };
+    let load = match read("/proc/loadavg") {
+        Some(load) => Some(load),
+        None => probe("sysctl", &["-n", "vm.loadavg"]).await.map(|l| l.trim_matches(|c| c == '{' || c == '}').to_string()),
+    };
+    let load: Vec<f64> = load.unwrap_or_default().split_whitespace().take(3).filter_map(|v| v.parse().ok()).collect();
+    json!({
+        "model": model,
+        "cores": cores,
+        "load_average": if load.len() == 3 { json!({ "1m": load[0], "5m": load[1], "15m": load[2] }) } else { Value::Null },
+        "usage_percent": usage().await
+    })
+}
+
+/// Busy share of all CPUs over a short sample, from /proc/stat
+async fn usage() -> Option<f64> {
+    fn sample() -> Option<(u64, u64)> {
+        let stat = read("/proc/stat")?;
+        let fields: Vec<u64> = stat.lines().next()?.split_whitespace().skip(1).filter_map(|v| v.parse().ok()).collect();
+        let idle = fields.get(3)? + fields.get(4).unwrap_or(&0);
+        Some((fields.iter().sum(), idle))
+    }
+    let (total1, idle1) = sample()?;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let (total2, idle2) = sample()?;
+    let total = total2.saturating_sub(total1);
+    if total == 0 {
+        return None;
+    }
+    let busy = total.saturating_sub(idle2.saturating_sub(idle1));
+    Some((busy as f64 * 1000.0 / total as f64).round() / 10.0)
+}
+
+async fn memory() -> Value {
+    if let Some(meminfo) = read("/proc/meminfo") {
+        let kb = |key: &str| meminfo.lines()
+            .find_map(|l| l.strip_prefix(&format!("{}:", key)))
+            .and_then(|v| v.split_whitespace().next()?.parse::<u64>().ok())
+            .map(|kb| kb * 1024);
+        let (total, available) = (kb("MemTotal"), kb("MemAvailable").or_else(|| kb("MemFree")));
+        let (swap_total, swap_free) = (kb("SwapTotal"), kb("SwapFree"));
+        return usage_json(total, available, swap_total.zip(swap_free).map(|(t, f)| (t, t.saturating_sub(f))));
+    }
+    let total = probe("sysctl", &["-n", "hw.memsize"]).await.and_then(|t| t.parse().ok());
+    let available = probe("vm_stat", &[]).await.and_then(|stat| parse_vm_stat(&stat));
+    usage_json(total, available, None)
+}
+
+/// Free, inactive and speculative bytes from macOS `vm_stat`
+fn parse_vm_stat(stat: &str) -> Option<u64> {
+    let page_size: u64 = stat.split("page size of ").nth(1)?.split_whitespace().next()?.parse().ok()?;
+    let pages = |key: &str| stat.lines()
+        .find_map(|l| l.strip_prefix(key))
+        .and_then(|v| v.trim().trim_end_matches('.').parse::<u64>().ok())
+        .unwrap_or(0);
+    Some((pages("Pages free:") + pages("Pages inactive:") + pages("Pages speculative:")) * page_size)
+}
+
+fn usage_json(total: Option<u64>, available: Option<u64>, swap: Option<(u64, u64)>) -> Value {
+    let used = total.zip(available).map(|(t, a)| t.saturating_sub(a));
+    json!({
+        "total_bytes": total,
+        "available_bytes": available,
+        "used_bytes": used,
+        "used_percent": used.zip(total).filter(|(_, t)| *t > 0).map(|(u, t)| percent(u, t)),
+        "swap_total_bytes": swap.map(|s| s.0),
+        "swap_used_bytes": swap.map(|s| s.1)
+    })
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    (part as f64 * 1000.0 / whole as f64).round() / 10.0
+}
+
+async fn disk() -> Value {
+    match probe("df", &["-kP"]).await {
+        Some(df) => json!(parse_df(&df)),
+        None => Value::Null,
+    }
+}
+
+/// Real filesystems in POSIX `df -kP` output
+fn parse_df(df: &str) -> Vec<Value> {
+    df.lines().skip(1).filter_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 6 || PSEUDO_FILESYSTEMS.contains(&fields[0]) {
+            return None;
+        }
+        let kb = |i: usize| fields[i].parse::<u64>().ok().map(|kb| kb * 1024);
+        let (size, used, available) = (kb(1)?, kb(2)?, kb(3)?);
+        if size == 0 {
+            return None;
+        }
+        Some(json!({
+            "filesystem": fields[0],
+            "mount": fields[5..].join(" "),
+            "size_bytes": size,
+            "used_bytes": used,
+            "available_bytes": available,
+            "used_percent": percent(used, used + available)
+        }))
+    }).collect()
+}
+
+async fn network() -> Value {
+    let mut interfaces = match probe("ip", &["-o", "addr", "show"]).await {
+        Some(ip) => parse_ip_addr(&ip),
+        None => probe("ifconfig", &[]).await.map(|out| parse_ifconfig(&out)).unwrap_or_default(),
+    };
+    for (name, interface) in interfaces.iter_mut() {
+        let sys = Path::new("/sys/class/net").join(name);
+        if sys.exists() {
+            interface["state"] = json!(read(sys.join("operstate")));
+            interface["mac"] = json!(read(sys.join("address")));
+            interface["rx_bytes"] = json!(read(sys.join("statistics/rx_bytes")).and_then(|v| v.parse::<u64>().ok()));
+            interface["tx_bytes"] = json!(read(sys.join("statistics/tx_bytes")).and_then(|v| v.parse::<u64>().ok()));
+        }
+    }
+    let list: Vec<Value> = interfaces.into_iter().map(|(name, mut interface)| {
+        interface["name"] = json!(name);
+        interface
+    }).collect();
+    json!({ "interfaces": list, "count": list.len() })
+}
+
+/// Interfaces and addresses in Linux `ip -o addr show` output
+fn parse_ip_addr(output: &str) -> Map<String, Value> {
+    let mut interfaces = Map::new();
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 4 {
+            continue;
+        }
+        let name = fields[1].trim_end_matches(':').to_string();
+        let interface = interfaces.entry(name).or_insert_with(|| json!({ "addresses": [] }));
+        if let Some(addresses) = interface["addresses"].as_array_mut() {
+            addresses.push(json!({ "family": fields[2], "address": fields[3] }));
+        }
+    }
+    interfaces
+}
+
+/// Interfaces and addresses in BSD/macOS `ifconfig` output
+fn parse_ifconfig(output: &str) -> Map<String, Value> {
+    let mut interfaces = Map::new();
+    let mut current: Option<String> = None;
+    for line in output.lines() {
+        if !line.starts_with(['\t', ' ']) {
+            current = line.split(':').next().filter(|n| !n.is_empty()).map(str::to_string);
+            if let Some(name) = &current {
+                interfaces.insert(name.clone(), json!({ "addresses": [] }));
+            }
+            continue;
+        }
+        let Some(interface) = current.as_ref().and_then(|name| interfaces.get_mut(name)) else { continue };
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        match fields.as_slice() {
+            ["inet" | "inet6", address, ..] => {
+                let family = fields[0];
+                if let Some(addresses) = interface["addresses"].as_array_mut() {
+                    addresses.push(json!({ "family": family, "address": address.split('%').next() }));
+                }
+            }
+            ["ether", mac, ..] => interface["mac"] = json!(mac),
+            ["status:", state, ..] => interface["state"] = json!(state),
+            _ => {}
+        }
+    }
+    interfaces
+}
+
+async fn runtimes() -> Value {
+    let probes = RUNTIMES.iter().map(|(name, program, flag)| async move {
+        let path = which::which(program).ok();
+        let version = match &path {
+            Some(_) => probe(program, &[flag]).await.and_then(|out| out.lines().next().map(str::to_string)),
+            None => None,
+        };
+        (name.to_string(), json!({ "installed": path.is_some(), "path": path, "version": version }))
+    });
+    let found: Map<String, Value> = futures::future::join_all(probes).await.into_iter().collect();
+    Value::Object(found)
+}
+
+fn env(args: &SysinfoToolArgs) -> Value {
+    let prefix = args.prefix.as_deref().unwrap_or("").to_lowercase();
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.to_lowercase().starts_with(&prefix))
+        .collect();
+    vars.sort();
+    let count = vars.len();
+    if args.names_only {
+        let names: Vec<String> = vars.into_iter().map(|(name, _)| name).collect();
+        return json!({ "names": names, "count": count });
+    }
+    let vars: Map<String, Value> = vars.into_iter()
+        .map(|(name, value)| {
+            let value = if redaction::secret_name(&name) { REDACTED.to_string() } else { value };
+            (name, json!(value))
+        })
+        .collect();
+    json!({ "vars": vars, "count": count })
+}
+
+async fn power() -> Value {
+    if cfg!(target_os = "macos") {
+        return json!({
+            "battery": probe("pmset", &["-g", "batt"]).await.and_then(|out| parse_pmset(&out)),
+            "thermal": probe("pmset", &["-g", "therm"]).await
+        });
+    }
+    let entries = |dir: &str| -> Vec<std::path::PathBuf> {
+        let mut paths: Vec<_> = std::fs::read_dir(dir).into_iter().flatten().flatten().map(|e| e.path()).collect();
+        paths.sort();
+        paths
+    };
+    let batteries: Vec<Value> = entries("/sys/class/power_supply").into_iter()
+        .filter(|p| read(p.join("type")).as_deref() == Some("Battery"))
+        .map(|p| json!({
+            "name": p.file_name().map(|n| n.to_string_lossy().to_string()),
+            "percent": read(p.join("capacity")).and_then(|v| v.parse::<u64>().ok()),
+            "status": read(p.join("status"))
+        }))
+        .collect();
+    let on_ac = entries("/sys/class/power_supply").into_iter()
+        .filter(|p| read(p.join("type")).as_deref() == Some("Mains"))
+        .map(|p| read(p.join("online")).as_deref() == Some("1"))
+        .reduce(|a, b| a || b);
+    let thermal: Vec<Value> = entries("/sys/class/thermal").into_iter()
+        .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().starts_with("thermal_zone")))
+        .filter_map(|p| {
+            let millis: f64 = read(p.join("temp"))?.parse().ok()?;
+            Some(json!({ "zone": read(p.join("type")), "celsius": millis / 1000.0 }))
+        })
+        .collect();
+    json!({
+        "battery": batteries.first(),
+        "batteries": batteries,
+        "on_ac_power": on_ac,
+        "thermal": thermal
+    })
+}
+
+/// Charge and state from macOS `pmset -g batt`
+fn parse_pmset(output: &str) -> Option<Value> {
+    let line = output.lines().find(|l| l.contains('%'))?;
+    let percent: u64 = line.split('%').next()?.rsplit(|c: char| !c.is_ascii_digit()).next()?.parse().ok()?;
+    let status = line.split(';').nth(1).map(|s| s.trim().to_string());
+    Some(json!({
+        "percent": percent,
+        "status": status,
+        "on_ac_power": output.contains("AC Power")
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parsers() {
+        let df = "Filesystem 1024-blocks Used Available Capacity Mounted on\ntmpfs 100 0 100 0% /dev/shm\n/dev/vda 1000 400 600 40% /\n/dev/sdb1 2000 1000 1000 50% /mnt/my disk\n";
+        let disks = parse_df(df);
+        assert_eq!(disks.len(), 2);
+        assert_eq!(disks[0]["used_percent"], 40.0);
+        assert_eq!(disks[1]["mount"], "/mnt/my disk");
+
+        let ip = "1: lo    inet 127.0.0.1/8 scope host lo\\       valid_lft forever\n4: eth0    inet 192.0.2.2/24 brd 192.0.2.255 scope global eth0\n4: eth0    inet6 fe80::1/64 scope link\n";
+        let interfaces = parse_ip_addr(ip);
+        assert_eq!(interfaces["eth0"]["addresses"][1]["family"], "inet6");
+        assert_eq!(interfaces["lo"]["addresses"][0]["address"], "127.0.0.1/8");
+
+        let ifconfig = "lo0: flags=8049<UP,LOOPBACK> mtu 16384\n\tinet 127.0.0.1 netmask 0xff000000\nen0: flags=8863<UP> mtu 1500\n\tether a4:83:e7:00:00:01\n\tinet6 fe80::1%en0 prefixlen 64\n\tinet 192.168.1.5 netmask 0xffffff00\n\tstatus: active\n";
+        let interfaces = parse_ifconfig(ifconfig);
+        assert_eq!(interfaces["en0"]["mac"], "a4:83:e7:00:00:01");
+        assert_eq!(interfaces["en0"]["addresses"][0]["address"], "fe80::1");
+        assert_eq!(interfaces["en0"]["state"], "active");
+
+        let stat = "Mach Virtual Memory Statistics: (page size of 16384 bytes)\nPages free:                               10.\nPages active:                            99.\nPages inactive:                           5.\nPages speculative:                        1.\n";
+        assert_eq!(parse_vm_stat(stat), Some(16 * 16384));
+        assert_eq!(parse_boottime("{ sec = 1700000000, usec = 5 } Tue Nov 14"), Some(1700000000));
+
+        let batt = "Now drawing from 'Battery Power'\n -InternalBattery-0 (id=1)\t87%; discharging; 5:01 remaining present: true\n";
+        let battery = parse_pmset(batt).unwrap();
+        assert_eq!(battery["percent"], 87);
+        assert_eq!(battery["status"], "discharging");
+        assert_eq!(battery["on_ac_power"], false);
+    }
+
+    #[tokio::test]
+    async fn test_env_masks_secrets() {
+        std::env::set_var("HANZO_SYSINFO_TEST_API_KEY", "sk-123");
+        std::env::set_var("HANZO_SYSINFO_TEST_MODE", "fast");
+        let tool = SysinfoTool::new();
+        let args = SysinfoToolArgs { action: Some("env".into()), prefix: Some("hanzo_sysinfo_test".into()), ..Default::default() };
+        let result = tool.execute(args).await.unwrap();
+        assert_eq!(result["data"]["vars"]["HANZO_SYSINFO_TEST_API_KEY"], REDACTED);
+        assert_eq!(result["data"]["vars"]["HANZO_SYSINFO_TEST_MODE"], "fast");
+        assert_eq!(result["data"]["count"], 2);
+
+        let overview = tool.execute(SysinfoToolArgs::default()).await.unwrap();
+        assert_eq!(overview["data"]["os"]["family"], std::env::consts::OS);
+    }
+}
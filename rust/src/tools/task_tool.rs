@@ -0,0 +1,396 @@
+/// Project task runner tool
+///
+/// Actions: list, run, help
+///
+/// Finds the commands a project defines for itself: cargo subcommands and
+/// `[alias]` entries, package.json scripts, Makefile targets and justfile
+/// recipes. Runs go through the exec tool's ProcessManager with their
+/// output logged as it arrives, so a long build keeps going in the
+/// background and exec(action="logs") follows it.
+
+use anyhow::Result;
+use crate::error::ToolError;
+use super::exec_tool::ProcessManager;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+/// Seconds run waits before leaving a task in the background
+const WAIT_SECS: u64 = 45;
+
+/// Log lines returned with a finished task
+const TAIL_LINES: usize = 200;
+
+/// Cargo subcommands offered for every Cargo project
+const CARGO_TASKS: &[(&str, &str)] = &[
+    ("build", "Compile the package"),
+    ("check", "Type-check without producing binaries"),
+    ("test", "Run the tests"),
+    ("run", "Run the main binary"),
+    ("clippy", "Lint with clippy"),
+    ("fmt", "Format the sources"),
+    ("doc", "Build the documentation"),
+    ("bench", "Run the benchmarks"),
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskAction {
+    #[default]
+    List,
+    Run,
+    Help,
+}
+
+impl std::str::FromStr for TaskAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "list" | "ls" | "" => Ok(Self::List),
+            "run" => Ok(Self::Run),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+/// One runnable project command
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Task {
+    pub name: String,
+    /// cargo, npm/pnpm/yarn/bun, make or just
+    pub source: String,
+    /// Program and arguments that run it
+    pub command: Vec<String>,
+    pub description: Option<String>,
+}
+
+impl Task {
+    fn new(source: &str, name: &str, command: &[&str], description: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            source: source.to_string(),
+            command: command.iter().map(|s| s.to_string()).collect(),
+            description: description.filter(|d| !d.is_empty()),
+        }
+    }
+
+    /// `source:name`, unique within a project
+    pub fn id(&self) -> String {
+        format!("{}:{}", self.source, self.name)
+    }
+}
+
+/// Every task defined in the project at `root`
+pub fn discover(root: &Path) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    if root.join("Cargo.toml").exists() {
+        tasks.extend(CARGO_TASKS.iter().map(|(name, description)| {
+            Task::new("cargo", name, &["cargo", name], Some(description.to_string()))
+        }));
+        tasks.extend(cargo_aliases(root));
+    }
+    if let Ok(package) = std::fs::read_to_string(root.join("package.json")) {
+        tasks.extend(npm_scripts(root, &package));
+    }
+    if let Some(makefile) = ["GNUmakefile", "makefile", "Makefile"].iter().map(|f| root.join(f)).find(|f| f.exists()) {
+        tasks.extend(make_targets(&std::fs::read_to_string(makefile).unwrap_or_default()));
+    }
+    if let Some(justfile) = ["justfile", "Justfile", ".justfile"].iter().map(|f| root.join(f)).find(|f| f.exists()) {
+        tasks.extend(just_recipes(&std::fs::read_to_string(justfile).unwrap_or_default()));
+    }
+    tasks
+}
+
+/// `[alias]` entries in .cargo/config.toml
+fn cargo_aliases(root: &Path) -> Vec<Task> {
+    let config = [".cargo/config.toml", ".cargo/config"].iter()
+        .find_map(|f| std::fs::read_to_string(root.join(f)).ok())
+        .and_then(|text| text.parse::<toml::Table>().ok());
+    let Some(aliases) = config.as_ref().and_then(|c| c.get("alias")).and_then(|a| a.as_table()) else {
+        return Vec::new();
+    };
+    aliases.iter().map(|(name, expansion)| {
+        let expansion = match expansion {
+            toml::Value::Array(words) => words.iter().filter_map(|w| w.as_str()).collect::<Vec<_>>().join(" "),
+            other => other.as_str().unwrap_or("").to_string(),
+        };
+        Task::new("cargo", name, &["cargo", name], Some(format!("cargo {}", expansion)))
+    }).collect()
+}
+
+/// package.json scripts, run with the package manager the lockfile names
+fn npm_scripts(root: &Path, package: &str) -> Vec<Task> {
+    let manager = [("pnpm-lock.yaml", "pnpm"), ("yarn.lock", "yarn"), ("bun.lockb", "bun"), ("bun.lock", "bun")]
+        .iter()
+        .find(|(lockfile, _)| root.join(lockfile).exists())
+        .map_or("npm", |(_, manager)| *manager);
+    let Ok(package) = serde_json::from_str::<Value>(package) else { return Vec::new() };
+    package["scripts"].as_object().into_iter().flatten().map(|(name, script)| {
+        Task::new(manager, name, &[manager, "run", name], script.as_str().map(String::from))
+    }).collect()
+}
+
+/// Makefile targets, described by a trailing `## text` or the comment
+/// line above
+fn make_targets(makefile: &str) -> Vec<Task> {
+    static TARGET: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([A-Za-z0-9][A-Za-z0-9_./-]*)\s*:([^=]|$)").unwrap());
+    let mut tasks: Vec<Task> = Vec::new();
+    let mut comment: Option<String> = None;
+    for line in makefile.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim_start_matches('#').trim().to_string());
+            continue;
+        }
+        if let Some(caps) = TARGET.captures(line) {
+            let name = &caps[1];
+            if !tasks.iter().any(|t| t.name == name) {
+                let description = line.split_once("##").map(|(_, d)| d.trim().to_string()).or(comment.take());
+                tasks.push(Task::new("make", name, &["make", name], description));
+            }
+        }
+        comment = None;
+    }
+    tasks
+}
+
+/// Public justfile recipes, described by the comment line above
+fn just_recipes(justfile: &str) -> Vec<Task> {
+    static RECIPE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^@?([A-Za-z][A-Za-z0-9_-]*)(\s+[^:]*)?:([^=]|$)").unwrap());
+    let mut tasks = Vec::new();
+    let mut comment: Option<String> = None;
+    for line in justfile.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim().to_string());
+            continue;
+        }
+        let keyword = ["set ", "alias ", "export ", "import ", "mod "].iter().any(|k| line.starts_with(k));
+        if let Some(caps) = RECIPE.captures(line).filter(|_| !keyword) {
+            tasks.push(Task::new("just", &caps[1], &["just", &caps[1]], comment.take()));
+        }
+        comment = None;
+    }
+    tasks
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskToolArgs {
+    pub action: Option<String>,
+    /// Project root
+    pub path: Option<String>,
+    /// Task to run: `name`, or `source:name` when several sources define it
+    pub task: Option<String>,
+    /// Extra arguments passed to the task
+    pub args: Option<Vec<String>>,
+    /// Seconds to wait before leaving the task running in the background
+    pub timeout: Option<u64>,
+    /// Log lines returned when the task finishes in time
+    pub tail: Option<usize>,
+}
+
+pub struct TaskToolDefinition;
+
+impl TaskToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "task",
+            "description": "Project tasks: list the commands a project defines (cargo, package.json scripts, Makefile targets, justfile recipes) and run them with logged output: list, run, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["list", "run", "help"],
+                        "description": "Task action",
+                        "default": "list"
+                    },
+                    "path": { "type": "string", "description": "Project root", "default": "." },
+                    "task": { "type": "string", "description": "Task to run, as listed: name, or source:name (npm:build, make:test) when several sources define it" },
+                    "args": { "type": "array", "items": { "type": "string" }, "description": "Extra arguments for the task" },
+                    "timeout": { "type": "integer", "description": "Seconds to wait before leaving the task running; exec(action=\"logs\") follows it", "default": WAIT_SECS },
+                    "tail": { "type": "integer", "description": "Log lines returned when the task finishes", "default": TAIL_LINES }
+                }
+            }
+        })
+    }
+}
+
+pub struct TaskTool {
+    manager: Arc<ProcessManager>,
+}
+
+impl TaskTool {
+    pub fn new(manager: Arc<ProcessManager>) -> Self {
+        Self { manager }
+    }
+
+    pub async fn execute(&self, args: TaskToolArgs) -> Result<Value> {
+        let action: TaskAction = args.action.as_deref().unwrap_or("list").parse()?;
+
+        match action {
+            TaskAction::List => self.list(&args),
+            TaskAction::Run => self.run(&args).await,
+            TaskAction::Help => Ok(self.help()),
+        }
+    }
+
+    fn root(args: &TaskToolArgs) -> Result<PathBuf> {
+        let root = shellexpand::tilde(args.path.as_deref().unwrap_or(".")).to_string();
+        std::fs::canonicalize(&root)
+            .map_err(|e| ToolError::not_found(format!("{}: {}", root, e)).into())
+    }
+
+    fn list(&self, args: &TaskToolArgs) -> Result<Value> {
+        let root = Self::root(args)?;
+        let tasks: Vec<Value> = discover(&root).iter().map(|task| json!({
+            "id": task.id(),
+            "name": task.name,
+            "source": task.source,
+            "command": task.command.join(" "),
+            "description": task.description
+        })).collect();
+
+        Ok(json!({
+            "ok": true,
+            "data": { "root": root.to_string_lossy(), "tasks": tasks, "count": tasks.len() },
+            "error": null,
+            "meta": { "tool": "task", "action": "list" }
+        }))
+    }
+
+    async fn run(&self, args: &TaskToolArgs) -> Result<Value> {
+        let root = Self::root(args)?;
+        let wanted = args.task.as_deref().ok_or_else(|| ToolError::invalid("task required"))?;
+        let tasks = discover(&root);
+        let matches: Vec<&Task> = tasks.iter().filter(|t| t.id() == wanted).collect();
+        let matches = if matches.is_empty() { tasks.iter().filter(|t| t.name == wanted).collect() } else { matches };
+        let task = match matches.as_slice() {
+            [task] => *task,
+            [] => return Err(ToolError::not_found(format!("No task '{}' in {}; task(action=\"list\") shows them", wanted, root.display())).into()),
+            several => return Err(ToolError::invalid(format!(
+                "'{}' is defined by several sources; use one of: {}",
+                wanted, several.iter().map(|t| t.id()).collect::<Vec<_>>().join(", ")
+            )).into()),
+        };
+
+        let mut argv = task.command.clone();
+        if let Some(extra) = &args.args {
+            // npm-style runners need `--` before arguments meant for the script
+            if matches!(task.source.as_str(), "npm" | "pnpm" | "bun") {
+                argv.push("--".into());
+            }
+            argv.extend(extra.iter().cloned());
+        }
+        let mut cmd = Command::new(&argv[0]);
+        cmd.args(&argv[1..]).current_dir(&root);
+        let info = self.manager.spawn_logged(cmd, argv.join(" ")).await?;
+
+        let deadline = Duration::from_secs(args.timeout.unwrap_or(WAIT_SECS));
+        let started = Instant::now();
+        let mut current = info.clone();
+        while current.running && started.elapsed() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            current = self.manager.get(&info.proc_id).await.unwrap_or(current);
+        }
+
+        let log = match &info.log_file {
+            Some(file) => tokio::fs::read_to_string(file).await.unwrap_or_default(),
+            None => String::new(),
+        };
+        let lines: Vec<&str> = log.lines().collect();
+        let tail = args.tail.unwrap_or(TAIL_LINES);
+        let mut data = json!({
+            "task": task.id(),
+            "command": argv.join(" "),
+            "proc_id": info.proc_id,
+            "log_file": info.log_file,
+            "output": lines[lines.len().saturating_sub(tail)..].join("\n"),
+            "total_lines": lines.len()
+        });
+        if current.running {
+            data["status"] = json!("running");
+            data["message"] = json!(format!(
+                "Still running after {}s; exec(action=\"logs\", proc_id=\"{}\") shows its output so far and exec(action=\"wait\") waits for it",
+                deadline.as_secs(), info.proc_id
+            ));
+        } else {
+            data["status"] = json!(if current.exit_code == Some(0) { "success" } else { "failed" });
+            data["exit_code"] = json!(current.exit_code);
+            data["duration_ms"] = json!(started.elapsed().as_millis() as u64);
+        }
+
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "task", "action": "run" }
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "task",
+                "actions": {
+                    "list": "List the project's tasks: cargo commands and aliases, package.json scripts, Makefile targets, justfile recipes",
+                    "run": "Run a task (task, optional args); returns its output, or its proc_id if it outlives timeout",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "task", "action": "help" }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"scripts": {"build": "tsc", "test": "jest"}}"#).unwrap();
+        std::fs::write(dir.path().join("yarn.lock"), "").unwrap();
+        std::fs::write(dir.path().join("Makefile"), ".PHONY: test\nCC := gcc\n# Run the test suite\ntest: build\n\tcargo test\n\ndeploy: ## Ship it\n\t./deploy.sh\n%.o: %.c\n\tcc $<\n").unwrap();
+        std::fs::write(dir.path().join("justfile"), "set shell := [\"bash\", \"-c\"]\nversion := \"1\"\n\n# Lint everything\nlint target=\"all\":\n    ruff check\n\n_private:\n    true\n").unwrap();
+
+        let tasks = discover(dir.path());
+        let ids: Vec<String> = tasks.iter().map(Task::id).collect();
+        assert_eq!(ids, ["yarn:build", "yarn:test", "make:test", "make:deploy", "just:lint"]);
+        assert_eq!(tasks[0].command, ["yarn", "run", "build"]);
+        assert_eq!(tasks[2].description.as_deref(), Some("Run the test suite"));
+        assert_eq!(tasks[3].description.as_deref(), Some("Ship it"));
+        assert_eq!(tasks[4].description.as_deref(), Some("Lint everything"));
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        if which::which("make").is_err() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Makefile"), "hello:\n\t@echo hello from make\n").unwrap();
+        let manager = Arc::new(ProcessManager::new());
+        let tool = TaskTool::new(manager.clone());
+        let args = TaskToolArgs {
+            action: Some("run".into()),
+            path: Some(dir.path().to_string_lossy().to_string()),
+            task: Some("hello".into()),
+            ..Default::default()
+        };
+
+        let result = tool.execute(args).await.unwrap();
+        assert_eq!(result["data"]["status"], "success");
+        assert_eq!(result["data"]["output"], "hello from make");
+        let proc_id = result["data"]["proc_id"].as_str().unwrap();
+        assert_eq!(manager.get(proc_id).await.unwrap().exit_code, Some(0));
+    }
+}
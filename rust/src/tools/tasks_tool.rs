@@ -2,7 +2,8 @@
 ///
 /// Actions: list, add, update, remove, clear
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use crate::error::ToolError;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::sync::Arc;
@@ -58,7 +59,7 @@ impl std::str::FromStr for TodoAction {
             "export" => Ok(Self::Export),
             "import" => Ok(Self::Import),
             "help" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -167,7 +168,7 @@ impl TasksTool {
     }
 
     async fn add(&self, args: &TasksToolArgs) -> Result<Value> {
-        let text = args.text.as_deref().ok_or_else(|| anyhow!("text required"))?;
+        let text = args.text.as_deref().ok_or_else(|| ToolError::invalid("text required"))?;
 
         let mut counter = self.counter.write().await;
         *counter += 1;
@@ -194,7 +195,7 @@ impl TasksTool {
     }
 
     async fn update(&self, args: &TasksToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
+        let id = args.id.ok_or_else(|| ToolError::invalid("id required"))?;
         let mut items = self.items.write().await;
 
         if let Some(item) = items.iter_mut().find(|i| i.id == id) {
@@ -219,7 +220,7 @@ impl TasksTool {
     }
 
     async fn remove(&self, args: &TasksToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
+        let id = args.id.ok_or_else(|| ToolError::invalid("id required"))?;
         let mut items = self.items.write().await;
         let len_before = items.len();
         items.retain(|i| i.id != id);
@@ -266,7 +267,7 @@ impl TasksTool {
     }
 
     async fn search(&self, args: &TasksToolArgs) -> Result<Value> {
-        let query = args.query.as_deref().or(args.text.as_deref()).ok_or_else(|| anyhow!("query or text required"))?;
+        let query = args.query.as_deref().or(args.text.as_deref()).ok_or_else(|| ToolError::invalid("query or text required"))?;
         let items = self.items.read().await;
         let q = query.to_lowercase();
         let matches: Vec<&TodoItem> = items.iter().filter(|i| i.text.to_lowercase().contains(&q)).collect();
@@ -278,8 +279,8 @@ impl TasksTool {
     }
 
     async fn batch(&self, args: &TasksToolArgs) -> Result<Value> {
-        let from = args.from_status.as_deref().ok_or_else(|| anyhow!("from_status required"))?;
-        let to = args.to_status.as_deref().or(args.status.as_deref()).ok_or_else(|| anyhow!("to_status or status required"))?;
+        let from = args.from_status.as_deref().ok_or_else(|| ToolError::invalid("from_status required"))?;
+        let to = args.to_status.as_deref().or(args.status.as_deref()).ok_or_else(|| ToolError::invalid("to_status or status required"))?;
         let mut items = self.items.write().await;
         let mut count = 0;
         for item in items.iter_mut() {
@@ -305,10 +306,10 @@ impl TasksTool {
     }
 
     async fn move_item(&self, args: &TasksToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
+        let id = args.id.ok_or_else(|| ToolError::invalid("id required"))?;
         let pos = args.position.unwrap_or(0);
         let mut items = self.items.write().await;
-        let idx = items.iter().position(|i| i.id == id).ok_or_else(|| anyhow!("Todo {} not found", id))?;
+        let idx = items.iter().position(|i| i.id == id).ok_or_else(|| ToolError::not_found(format!("Todo {} not found", id)))?;
         let item = items.remove(idx);
         let pos = pos.min(items.len());
         items.insert(pos, item);
@@ -316,8 +317,8 @@ impl TasksTool {
     }
 
     async fn prioritize(&self, args: &TasksToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
-        let priority = args.priority.as_deref().ok_or_else(|| anyhow!("priority required"))?;
+        let id = args.id.ok_or_else(|| ToolError::invalid("id required"))?;
+        let priority = args.priority.as_deref().ok_or_else(|| ToolError::invalid("priority required"))?;
         let mut items = self.items.write().await;
         if let Some(item) = items.iter_mut().find(|i| i.id == id) {
             item.priority = Some(priority.to_string());
@@ -328,7 +329,7 @@ impl TasksTool {
     }
 
     async fn assign(&self, args: &TasksToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
+        let id = args.id.ok_or_else(|| ToolError::invalid("id required"))?;
         let assignee = args.assignee.as_deref().unwrap_or("");
         let mut items = self.items.write().await;
         if let Some(item) = items.iter_mut().find(|i| i.id == id) {
@@ -340,7 +341,7 @@ impl TasksTool {
     }
 
     async fn subtasks(&self, args: &TasksToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
+        let id = args.id.ok_or_else(|| ToolError::invalid("id required"))?;
         let items = self.items.read().await;
         if let Some(item) = items.iter().find(|i| i.id == id) {
             let subs = item.subtasks.as_deref().unwrap_or(&[]);
@@ -351,7 +352,7 @@ impl TasksTool {
     }
 
     async fn notes(&self, args: &TasksToolArgs) -> Result<Value> {
-        let id = args.id.ok_or_else(|| anyhow!("id required"))?;
+        let id = args.id.ok_or_else(|| ToolError::invalid("id required"))?;
         let items = self.items.read().await;
         if let Some(item) = items.iter().find(|i| i.id == id) {
             let notes = item.notes.as_deref().unwrap_or(&[]);
@@ -368,9 +369,9 @@ impl TasksTool {
     }
 
     async fn import_items(&self, args: &TasksToolArgs) -> Result<Value> {
-        let content = args.content.as_deref().ok_or_else(|| anyhow!("content (JSON) required"))?;
-        let data: Value = serde_json::from_str(content).map_err(|e| anyhow!("Invalid JSON: {}", e))?;
-        let arr = data.as_array().or_else(|| data.get("items").and_then(|v| v.as_array())).ok_or_else(|| anyhow!("Expected array or {{items:[...]}}"))?;
+        let content = args.content.as_deref().ok_or_else(|| ToolError::invalid("content (JSON) required"))?;
+        let data: Value = serde_json::from_str(content).map_err(|e| ToolError::invalid(format!("Invalid JSON: {}", e)))?;
+        let arr = data.as_array().or_else(|| data.get("items").and_then(|v| v.as_array())).ok_or_else(|| ToolError::invalid("Expected array or {items:[...]}"))?;
         let mut counter = self.counter.write().await;
         let mut items = self.items.write().await;
         let mut count = 0;
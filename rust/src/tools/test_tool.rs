@@ -0,0 +1,518 @@
+/// Test runner tool with structured results
+///
+/// Actions: run, rerun, frameworks, help
+///
+/// Runs cargo test, pytest or jest, detected from the project's manifests,
+/// and parses pass/fail/skip counts and each failure's message into JSON.
+/// The failures of the latest run in each project are remembered so
+/// `rerun` can run just those again.
+
+use anyhow::Result;
+use crate::config::CodeConfig;
+use crate::error::ToolError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+use which::which;
+
+/// Longest failure message returned, in characters
+const MAX_MESSAGE: usize = 4000;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TestAction {
+    #[default]
+    Run,
+    Rerun,
+    Frameworks,
+    Help,
+}
+
+impl std::str::FromStr for TestAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "run" | "test" | "" => Ok(Self::Run),
+            "rerun" | "rerun_failed" | "failed" => Ok(Self::Rerun),
+            "frameworks" | "detect" => Ok(Self::Frameworks),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+/// Test frameworks whose output can be parsed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framework {
+    Cargo,
+    Pytest,
+    Jest,
+}
+
+impl std::str::FromStr for Framework {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "cargo" | "cargo_test" | "rust" => Ok(Self::Cargo),
+            "pytest" | "python" => Ok(Self::Pytest),
+            "jest" => Ok(Self::Jest),
+            _ => Err(ToolError::invalid(format!("Unknown framework: {} (use cargo, pytest or jest)", s)).into()),
+        }
+    }
+}
+
+impl Framework {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cargo => "cargo",
+            Self::Pytest => "pytest",
+            Self::Jest => "jest",
+        }
+    }
+
+    fn default_command(self) -> &'static [&'static str] {
+        match self {
+            Self::Cargo => &["cargo", "test", "--no-fail-fast"],
+            Self::Pytest => &["pytest", "-rA", "--tb=short", "-q"],
+            Self::Jest => &["npx", "--no-install", "jest", "--json"],
+        }
+    }
+
+    /// Frameworks that apply to the project at `root`
+    pub fn detect(root: &Path) -> Vec<Self> {
+        let has = |name: &str| root.join(name).exists();
+        let mut frameworks = Vec::new();
+        if has("Cargo.toml") {
+            frameworks.push(Self::Cargo);
+        }
+        let pyproject = std::fs::read_to_string(root.join("pyproject.toml")).unwrap_or_default();
+        if has("pytest.ini") || has("conftest.py") || pyproject.contains("pytest") || has("tests/conftest.py") {
+            frameworks.push(Self::Pytest);
+        }
+        let package = std::fs::read_to_string(root.join("package.json")).unwrap_or_default();
+        if ["jest.config.js", "jest.config.ts", "jest.config.mjs", "jest.config.cjs"].iter().any(|f| has(f))
+            || package.contains("\"jest\"")
+        {
+            frameworks.push(Self::Jest);
+        }
+        frameworks
+    }
+
+    /// Arguments selecting tests whose names contain `filter`
+    fn filter_args(self, filter: &str) -> Vec<String> {
+        match self {
+            Self::Cargo => vec!["--".into(), filter.into()],
+            Self::Pytest => vec!["-k".into(), filter.into()],
+            Self::Jest => vec!["-t".into(), filter.into()],
+        }
+    }
+
+    /// Arguments selecting exactly the tests in `names`, as this framework
+    /// reported them
+    fn only_args(self, names: &[String]) -> Vec<String> {
+        match self {
+            Self::Cargo => ["--".to_string(), "--exact".to_string()].into_iter().chain(names.iter().cloned()).collect(),
+            Self::Pytest => names.to_vec(),
+            Self::Jest => {
+                let names: Vec<String> = names.iter().map(|n| regex::escape(n)).collect();
+                vec!["-t".into(), format!("^({})$", names.join("|"))]
+            }
+        }
+    }
+
+    /// Results in the framework's output
+    pub fn parse(self, stdout: &str, root: &Path) -> Report {
+        match self {
+            Self::Cargo => parse_cargo(stdout),
+            Self::Pytest => parse_pytest(stdout),
+            Self::Jest => parse_jest(stdout, root),
+        }
+    }
+}
+
+/// Outcome of one test run
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct Report {
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub failures: Vec<Failure>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Failure {
+    pub name: String,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Failure {
+    fn new(name: &str, message: &str) -> Self {
+        static LOCATION: Lazy<Regex> = Lazy::new(|| Regex::new(r"([\w./-]+\.\w+):(\d+)").unwrap());
+        let location = LOCATION.captures(message);
+        let mut message = message.trim().to_string();
+        if message.chars().count() > MAX_MESSAGE {
+            message = message.chars().take(MAX_MESSAGE).collect::<String>() + "...";
+        }
+        Self {
+            name: name.to_string(),
+            file: location.as_ref().map(|c| c[1].to_string()),
+            line: location.and_then(|c| c[2].parse().ok()),
+            message,
+        }
+    }
+}
+
+/// libtest's text output, summed over every test binary
+fn parse_cargo(stdout: &str) -> Report {
+    static RESULT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^test (.+) \.\.\. (ok|FAILED|ignored.*)$").unwrap());
+    let mut report = Report::default();
+    let mut failed = Vec::new();
+    let mut output: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+    for line in stdout.lines() {
+        if let Some(caps) = RESULT.captures(line) {
+            match &caps[2] {
+                "ok" => report.passed += 1,
+                "FAILED" => failed.push(caps[1].to_string()),
+                _ => report.skipped += 1,
+            }
+            continue;
+        }
+        if let Some(name) = line.strip_prefix("---- ").and_then(|l| l.strip_suffix(" stdout ----")) {
+            current = Some(name.to_string());
+        } else if line == "failures:" || line.starts_with("test result:") {
+            current = None;
+        } else if let Some(name) = &current {
+            let text = output.entry(name.clone()).or_default();
+            text.push_str(line);
+            text.push('\n');
+        }
+    }
+    report.failed = failed.len();
+    report.failures = failed.iter()
+        .map(|name| Failure::new(name, output.get(name).map_or("", String::as_str)))
+        .collect();
+    report
+}
+
+/// pytest with `-rA`: the short summary lists every outcome, and the last
+/// line has the counts
+fn parse_pytest(stdout: &str) -> Report {
+    static COUNT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+) (passed|failed|skipped|errors?|xfailed|xpassed)").unwrap());
+    let mut report = Report::default();
+    for line in stdout.lines() {
+        if let Some(rest) = line.strip_prefix("FAILED ").or_else(|| line.strip_prefix("ERROR ")) {
+            let (name, message) = rest.split_once(" - ").unwrap_or((rest, ""));
+            let mut failure = Failure::new(name, message);
+            if failure.file.is_none() {
+                failure.file = name.split("::").next().map(String::from);
+            }
+            report.failures.push(failure);
+        }
+    }
+    if let Some(summary) = stdout.lines().rev().find(|l| COUNT.is_match(l) && l.contains(" in ")) {
+        for caps in COUNT.captures_iter(summary) {
+            let count: usize = caps[1].parse().unwrap_or(0);
+            match &caps[2] {
+                "passed" | "xpassed" => report.passed += count,
+                "failed" | "error" | "errors" => report.failed += count,
+                _ => report.skipped += count,
+            }
+        }
+    }
+    report
+}
+
+/// `jest --json`
+fn parse_jest(stdout: &str, root: &Path) -> Report {
+    static ANSI: Lazy<Regex> = Lazy::new(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap());
+    let start = stdout.find('{').unwrap_or(0);
+    let Ok(result) = serde_json::from_str::<Value>(&stdout[start..]) else { return Report::default() };
+    let count = |key: &str| result[key].as_u64().unwrap_or(0) as usize;
+    let mut report = Report {
+        passed: count("numPassedTests"),
+        failed: count("numFailedTests"),
+        skipped: count("numPendingTests") + count("numTodoTests"),
+        failures: Vec::new(),
+    };
+    for suite in result["testResults"].as_array().into_iter().flatten() {
+        let file = suite["name"].as_str().map(|f| {
+            Path::new(f).strip_prefix(root).unwrap_or(Path::new(f)).to_string_lossy().into_owned()
+        });
+        let assertions = suite["assertionResults"].as_array().cloned().unwrap_or_default();
+        for test in assertions.iter().filter(|t| t["status"] == "failed") {
+            let messages: Vec<&str> = test["failureMessages"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+            let mut failure = Failure::new(test["fullName"].as_str().unwrap_or(""), &ANSI.replace_all(&messages.join("\n"), ""));
+            failure.file = file.clone();
+            report.failures.push(failure);
+        }
+        // A suite that fails to load has no assertions, only a message
+        if suite["status"] == "failed" && assertions.is_empty() {
+            let mut failure = Failure::new(file.as_deref().unwrap_or(""), &ANSI.replace_all(suite["message"].as_str().unwrap_or(""), ""));
+            failure.file = file.clone();
+            report.failed += 1;
+            report.failures.push(failure);
+        }
+    }
+    report
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TestToolArgs {
+    pub action: Option<String>,
+    /// Project root
+    pub path: Option<String>,
+    /// cargo, pytest or jest; detected when omitted
+    pub framework: Option<String>,
+    /// Only run tests whose names match
+    pub filter: Option<String>,
+}
+
+pub struct TestToolDefinition;
+
+impl TestToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "test",
+            "description": "Run a project's tests (cargo test, pytest, jest) and return pass/fail/skip counts and each failure's message as JSON: run, rerun (only the last run's failures), frameworks, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["run", "rerun", "frameworks", "help"],
+                        "description": "Test action",
+                        "default": "run"
+                    },
+                    "path": { "type": "string", "description": "Project root", "default": "." },
+                    "framework": { "type": "string", "enum": ["cargo", "pytest", "jest"], "description": "Test framework; detected from the project when omitted" },
+                    "filter": { "type": "string", "description": "Only run tests whose names match (cargo test filter, pytest -k, jest -t)" }
+                }
+            }
+        })
+    }
+}
+
+pub struct TestTool {
+    config: CodeConfig,
+    /// Failed test names of the latest run, per project root
+    last_failed: Mutex<HashMap<PathBuf, (Framework, Vec<String>)>>,
+}
+
+impl Default for TestTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestTool {
+    pub fn new() -> Self {
+        Self::with_config(CodeConfig::default())
+    }
+
+    pub fn with_config(config: CodeConfig) -> Self {
+        Self { config, last_failed: Mutex::new(HashMap::new()) }
+    }
+
+    pub async fn execute(&self, args: TestToolArgs) -> Result<Value> {
+        let action: TestAction = args.action.as_deref().unwrap_or("run").parse()?;
+
+        match action {
+            TestAction::Run => self.run(&args).await,
+            TestAction::Rerun => self.rerun(&args).await,
+            TestAction::Frameworks => self.frameworks(&args),
+            TestAction::Help => Ok(self.help()),
+        }
+    }
+
+    fn root(args: &TestToolArgs) -> Result<PathBuf> {
+        let root = shellexpand::tilde(args.path.as_deref().unwrap_or(".")).to_string();
+        std::fs::canonicalize(&root)
+            .map_err(|e| ToolError::not_found(format!("{}: {}", root, e)).into())
+    }
+
+    fn framework(args: &TestToolArgs, root: &Path) -> Result<Framework> {
+        match &args.framework {
+            Some(name) => name.parse(),
+            None => Framework::detect(root).into_iter().next().ok_or_else(|| ToolError::unsupported(format!(
+                "No test framework detected in {}; set framework (cargo, pytest or jest)", root.display()
+            )).into()),
+        }
+    }
+
+    fn command(&self, framework: Framework) -> Vec<String> {
+        self.config.test_runners.get(framework.name())
+            .filter(|command| !command.is_empty())
+            .cloned()
+            .unwrap_or_else(|| framework.default_command().iter().map(|s| s.to_string()).collect())
+    }
+
+    fn frameworks(&self, args: &TestToolArgs) -> Result<Value> {
+        let root = Self::root(args)?;
+        let frameworks: Vec<Value> = Framework::detect(&root).into_iter().map(|framework| {
+            let command = self.command(framework);
+            json!({
+                "name": framework.name(),
+                "command": command.join(" "),
+                "installed": which(&command[0]).is_ok()
+            })
+        }).collect();
+
+        Ok(json!({
+            "ok": true,
+            "data": { "root": root.to_string_lossy(), "frameworks": frameworks },
+            "error": null,
+            "meta": { "tool": "test", "action": "frameworks" }
+        }))
+    }
+
+    async fn run(&self, args: &TestToolArgs) -> Result<Value> {
+        let root = Self::root(args)?;
+        let framework = Self::framework(args, &root)?;
+        let extra = args.filter.as_deref().map(|f| framework.filter_args(f)).unwrap_or_default();
+        self.run_tests(&root, framework, extra, "run").await
+    }
+
+    async fn rerun(&self, args: &TestToolArgs) -> Result<Value> {
+        let root = Self::root(args)?;
+        let (framework, names) = self.last_failed.lock().await.get(&root).cloned()
+            .ok_or_else(|| ToolError::not_found(format!("No earlier test run in {}", root.display())))?;
+        if names.is_empty() {
+            return Err(ToolError::invalid("The last run had no failures to rerun").into());
+        }
+        self.run_tests(&root, framework, framework.only_args(&names), "rerun").await
+    }
+
+    async fn run_tests(&self, root: &Path, framework: Framework, extra: Vec<String>, action: &str) -> Result<Value> {
+        let mut command = self.command(framework);
+        if which(&command[0]).is_err() {
+            return Err(ToolError::unsupported(format!("{} is not installed", command[0])).into());
+        }
+        command.extend(extra);
+
+        let started = Instant::now();
+        let output = Command::new(&command[0])
+            .args(&command[1..])
+            .current_dir(root)
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| ToolError::external(format!("Could not run {}: {}", command[0], e)))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let report = framework.parse(&stdout, root);
+
+        let names = report.failures.iter().map(|f| f.name.clone()).collect();
+        self.last_failed.lock().await.insert(root.to_path_buf(), (framework, names));
+
+        let mut data = json!({
+            "root": root.to_string_lossy(),
+            "framework": framework.name(),
+            "command": command.join(" "),
+            "exit_code": output.status.code(),
+            "success": output.status.success(),
+            "passed": report.passed,
+            "failed": report.failed,
+            "skipped": report.skipped,
+            "total": report.passed + report.failed + report.skipped,
+            "failures": report.failures,
+            "duration_ms": started.elapsed().as_millis() as u64
+        });
+        // Nothing ran: the build or collection failed before any test did
+        if !output.status.success() && report.passed + report.failed + report.skipped == 0 {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let text = if stderr.trim().is_empty() { stdout } else { stderr };
+            let tail: Vec<&str> = text.lines().rev().take(40).collect();
+            data["output"] = json!(tail.into_iter().rev().collect::<Vec<_>>().join("\n"));
+        }
+
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "test", "action": action }
+        }))
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "ok": true,
+            "data": {
+                "tool": "test",
+                "actions": {
+                    "run": "Run the project's tests (optionally framework, filter) and return counts and failures",
+                    "rerun": "Run only the tests that failed in the previous run of this project",
+                    "frameworks": "List detected test frameworks, their commands and whether they are installed",
+                    "help": "Show tool help"
+                }
+            },
+            "error": null,
+            "meta": { "tool": "test", "action": "help" }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cargo() {
+        let stdout = "\nrunning 3 tests\ntest tests::adds ... ok\ntest tests::slow ... ignored\ntest tests::divides ... FAILED\n\nfailures:\n\n---- tests::divides stdout ----\n\nthread 'tests::divides' panicked at src/lib.rs:12:9:\nassertion `left == right` failed\n\nfailures:\n    tests::divides\n\ntest result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out\n";
+        let report = Framework::Cargo.parse(stdout, Path::new("."));
+        assert_eq!((report.passed, report.failed, report.skipped), (1, 1, 1));
+        assert_eq!(report.failures[0].name, "tests::divides");
+        assert_eq!(report.failures[0].file.as_deref(), Some("src/lib.rs"));
+        assert_eq!(report.failures[0].line, Some(12));
+        assert!(report.failures[0].message.contains("assertion `left == right` failed"));
+        assert_eq!(Framework::Cargo.only_args(&report.failures.iter().map(|f| f.name.clone()).collect::<Vec<_>>()), ["--", "--exact", "tests::divides"]);
+    }
+
+    #[test]
+    fn test_parse_pytest_and_jest() {
+        let stdout = "..F\n=========================== short test summary info ============================\nPASSED tests/test_math.py::test_add\nFAILED tests/test_math.py::test_div - ZeroDivisionError: division by zero\n1 failed, 2 passed, 1 skipped in 0.05s\n";
+        let report = Framework::Pytest.parse(stdout, Path::new("."));
+        assert_eq!((report.passed, report.failed, report.skipped), (2, 1, 1));
+        assert_eq!(report.failures[0].name, "tests/test_math.py::test_div");
+        assert_eq!(report.failures[0].file.as_deref(), Some("tests/test_math.py"));
+
+        let stdout = r#"{"numPassedTests":1,"numFailedTests":1,"numPendingTests":0,"numTodoTests":0,"testResults":[{"name":"/app/src/sum.test.js","status":"failed","message":"","assertionResults":[{"fullName":"sum adds","status":"passed","failureMessages":[]},{"fullName":"sum subtracts","status":"failed","failureMessages":["Error: \u001b[31mexpected 1\u001b[39m"]}]}]}"#;
+        let report = Framework::Jest.parse(stdout, Path::new("/app"));
+        assert_eq!((report.passed, report.failed), (1, 1));
+        assert_eq!(report.failures[0].file.as_deref(), Some("src/sum.test.js"));
+        assert_eq!(report.failures[0].message, "Error: expected 1");
+        assert_eq!(Framework::Jest.only_args(&["sum subtracts".into()]), ["-t", "^(sum subtracts)$"]);
+    }
+
+    #[tokio::test]
+    async fn test_run_and_rerun() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "[package]\nname = \"sample\"\nversion = \"0.1.0\"\nedition = \"2021\"\n").unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "#[test]\nfn passes() {}\n#[test]\nfn fails() { assert_eq!(1, 2); }\n").unwrap();
+        let tool = TestTool::new();
+        let args = |action: &str| TestToolArgs {
+            action: Some(action.into()),
+            path: Some(dir.path().to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let result = tool.execute(args("run")).await.unwrap();
+        assert_eq!(result["data"]["framework"], "cargo");
+        assert_eq!((result["data"]["passed"].as_u64(), result["data"]["failed"].as_u64()), (Some(1), Some(1)));
+        assert_eq!(result["data"]["failures"][0]["name"], "fails");
+
+        let result = tool.execute(args("rerun")).await.unwrap();
+        assert_eq!(result["data"]["total"], 1);
+        assert_eq!(result["data"]["failed"], 1);
+    }
+}
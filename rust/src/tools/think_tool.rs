@@ -7,13 +7,22 @@
 /// - summarize: Compress text to summary
 /// - classify: Classify text
 /// - explain: Explain code/concepts
+/// - history/branch/export: Review the persisted reasoning journal
 ///
 /// Wraps the think/critic functionality with HIP-0300 naming.
-
-use anyhow::{anyhow, Result};
+/// Journal entries are appended to a per-project JSONL file so reasoning
+/// survives restarts.
+
+use super::report::Report;
+use super::rubric::{self, Criterion};
+use anyhow::Result;
+use crate::error::ToolError;
+use crate::sampling;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::RwLock;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -31,6 +40,9 @@ pub enum LlmAction {
     Compare,
     Chain,
     Embed,
+    History,
+    Branch,
+    Export,
     Help,
 }
 
@@ -57,8 +69,11 @@ impl std::str::FromStr for LlmAction {
             "compare" => Ok(Self::Compare),
             "chain" => Ok(Self::Chain),
             "embed" | "embedding" => Ok(Self::Embed),
+            "history" | "log" => Ok(Self::History),
+            "branch" | "branches" => Ok(Self::Branch),
+            "export" => Ok(Self::Export),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -82,8 +97,55 @@ pub struct ThinkToolArgs {
     pub steps: Option<String>,
     pub content: Option<String>,
     pub audience: Option<String>,
+    /// Journal branch (default: main)
+    pub branch: Option<String>,
+    /// Entry id this thought links to
+    pub parent: Option<usize>,
+    /// Link type to parent: extends, contradicts, resolves
+    pub relation: Option<String>,
+    /// Max entries for history
+    pub limit: Option<usize>,
+    /// File path for export
+    pub output: Option<String>,
+    /// Export format: json (default) or markdown
+    pub format: Option<String>,
+    /// Heading for a markdown export
+    pub title: Option<String>,
+    /// Unified diff for critic/review
+    pub diff: Option<String>,
+    /// Rubric criteria: ["correctness", "security", "style"] or {"security": 2.0, ...}
+    pub rubric: Option<Value>,
+    /// Score with the client's LLM over MCP sampling and blend it into the rubric
+    pub sample: Option<bool>,
+    /// MCP session making the call, set by the server rather than the client
+    #[serde(skip)]
+    pub session_id: Option<String>,
 }
 
+/// How a journal entry relates to the entry it links to
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThoughtRelation {
+    Extends,
+    Contradicts,
+    Resolves,
+}
+
+impl std::str::FromStr for ThoughtRelation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "extends" | "extend" => Ok(Self::Extends),
+            "contradicts" | "contradict" => Ok(Self::Contradicts),
+            "resolves" | "resolve" => Ok(Self::Resolves),
+            _ => Err(ToolError::invalid(format!("Unknown relation: {} (extends, contradicts, resolves)", s)).into()),
+        }
+    }
+}
+
+const MAIN_BRANCH: &str = "main";
+
 pub struct ThinkToolDefinition {
     pub description: String,
     pub input_schema: Value,
@@ -98,7 +160,7 @@ impl ThinkToolDefinition {
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["think", "critic", "review", "consensus", "agent", "summarize", "classify", "explain", "translate", "compare", "chain", "embed", "help"],
+                        "enum": ["think", "critic", "review", "consensus", "agent", "summarize", "classify", "explain", "translate", "compare", "chain", "embed", "history", "branch", "export", "help"],
                         "description": "LLM action"
                     },
                     "thought": { "type": "string", "description": "What to think about / critique" },
@@ -116,7 +178,23 @@ impl ThinkToolDefinition {
                     "criteria": { "type": "string", "description": "Criteria for compare" },
                     "steps": { "type": "string", "description": "Steps for chain-of-thought" },
                     "content": { "type": "string", "description": "Content for embed/translate" },
-                    "audience": { "type": "string", "description": "Target audience for explain" }
+                    "audience": { "type": "string", "description": "Target audience for explain" },
+                    "branch": { "type": "string", "description": "Journal branch (default: main); name for branch action" },
+                    "parent": { "type": "integer", "description": "Entry id this thought links to / branch point" },
+                    "relation": { "type": "string", "enum": ["extends", "contradicts", "resolves"], "description": "Link type to parent" },
+                    "limit": { "type": "integer", "description": "Max entries for history" },
+                    "output": { "type": "string", "description": "File path for export" },
+                    "format": { "type": "string", "enum": ["json", "markdown"], "description": "Export format (default: json); markdown adds the plan and this session's tool calls" },
+                    "title": { "type": "string", "description": "Heading for a markdown export" },
+                    "diff": { "type": "string", "description": "Unified diff to evaluate (critic/review)" },
+                    "rubric": {
+                        "description": "Rubric criteria (correctness, security, style) as a list, or an object of criterion weights",
+                        "oneOf": [
+                            { "type": "array", "items": { "type": "string" } },
+                            { "type": "object", "additionalProperties": { "type": "number" } }
+                        ]
+                    },
+                    "sample": { "type": "boolean", "description": "Also score with the client's LLM via MCP sampling, blended into the rubric scores" }
                 },
                 "required": ["action"]
             }),
@@ -125,25 +203,59 @@ impl ThinkToolDefinition {
 }
 
 /// Entry in thinking journal
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct ThinkEntry {
     id: usize,
     action: String,
     thought: String,
     context: Option<String>,
     timestamp: String,
+    #[serde(default = "default_branch")]
+    branch: String,
+    #[serde(default)]
+    parent: Option<usize>,
+    #[serde(default)]
+    relation: Option<ThoughtRelation>,
+}
+
+fn default_branch() -> String {
+    MAIN_BRANCH.to_string()
+}
+
+/// Branch and link placement for a new entry
+struct EntryLink {
+    branch: String,
+    parent: Option<usize>,
+    relation: Option<ThoughtRelation>,
 }
 
 pub struct ThinkTool {
     journal: Arc<RwLock<Vec<ThinkEntry>>>,
     counter: Arc<RwLock<usize>>,
+    journal_path: Option<PathBuf>,
 }
 
 impl ThinkTool {
+    /// Journal persisted under the data dir, keyed by the current project
     pub fn new() -> Self {
+        let project = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let path = dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("hanzo-mcp")
+            .join("think")
+            .join(format!("{}.jsonl", project_key(&project)));
+        Self::with_journal(Some(path))
+    }
+
+    /// Journal backed by the given JSONL file, or in-memory only when `None`
+    pub fn with_journal(path: Option<PathBuf>) -> Self {
+        let entries = path.as_deref().map(load_journal).unwrap_or_default();
+        let counter = entries.iter().map(|e| e.id).max().unwrap_or(0);
+
         Self {
-            journal: Arc::new(RwLock::new(Vec::new())),
-            counter: Arc::new(RwLock::new(0)),
+            journal: Arc::new(RwLock::new(entries)),
+            counter: Arc::new(RwLock::new(counter)),
+            journal_path: path,
         }
     }
 
@@ -163,11 +275,37 @@ impl ThinkTool {
             LlmAction::Compare => self.compare(&args).await,
             LlmAction::Chain => self.chain(&args).await,
             LlmAction::Embed => self.embed(&args).await,
+            LlmAction::History => self.history(&args).await,
+            LlmAction::Branch => self.branch(&args).await,
+            LlmAction::Export => self.export(&args).await,
             LlmAction::Help => Ok(self.help()),
         }
     }
 
-    async fn record(&self, action: &str, thought: &str, context: Option<&str>) -> usize {
+    /// Resolve branch/parent/relation args, defaulting the parent to the
+    /// branch head so consecutive thoughts form a chain
+    async fn link(&self, args: &ThinkToolArgs) -> Result<EntryLink> {
+        let branch = args.branch.clone().unwrap_or_else(default_branch);
+        let relation = args.relation.as_deref().map(str::parse).transpose()?;
+        let journal = self.journal.read().await;
+
+        let parent = match args.parent {
+            Some(id) => {
+                if !journal.iter().any(|e| e.id == id) {
+                    return Err(ToolError::invalid(format!("Unknown parent entry: {}", id)).into());
+                }
+                Some(id)
+            }
+            None if relation.is_some() => {
+                return Err(ToolError::invalid("parent required when relation is set").into());
+            }
+            None => journal.iter().rev().find(|e| e.branch == branch).map(|e| e.id),
+        };
+
+        Ok(EntryLink { branch, parent, relation })
+    }
+
+    async fn record(&self, action: &str, thought: &str, context: Option<&str>, link: EntryLink) -> Result<usize> {
         let mut counter = self.counter.write().await;
         *counter += 1;
         let id = *counter;
@@ -178,18 +316,25 @@ impl ThinkTool {
             thought: thought.to_string(),
             context: context.map(|s| s.to_string()),
             timestamp: chrono::Utc::now().to_rfc3339(),
+            branch: link.branch,
+            parent: link.parent,
+            relation: link.relation,
         };
 
+        if let Some(path) = &self.journal_path {
+            append_journal(path, &entry).await?;
+        }
         self.journal.write().await.push(entry);
-        id
+        Ok(id)
     }
 
     async fn think(&self, args: &ThinkToolArgs) -> Result<Value> {
         let thought = args.thought.as_deref()
             .or(args.question.as_deref())
-            .ok_or_else(|| anyhow!("thought or question required"))?;
+            .ok_or_else(|| ToolError::invalid("thought or question required"))?;
 
-        let id = self.record("think", thought, args.context.as_deref()).await;
+        let link = self.link(args).await?;
+        let id = self.record("think", thought, args.context.as_deref(), link).await?;
 
         Ok(json!({
             "ok": true,
@@ -205,41 +350,75 @@ impl ThinkTool {
     }
 
     async fn critic(&self, args: &ThinkToolArgs) -> Result<Value> {
-        let thought = args.thought.as_deref()
+        let input = args.diff.as_deref()
             .or(args.code.as_deref())
-            .ok_or_else(|| anyhow!("thought or code required"))?;
+            .or(args.thought.as_deref())
+            .ok_or_else(|| ToolError::invalid("thought, code, or diff required"))?;
+
+        let rubric = rubric::parse_rubric(args.rubric.as_ref(), &[Criterion::Correctness, Criterion::Security])?;
+        let mut evaluation = rubric::evaluate(input, &rubric);
+
+        let link = self.link(args).await?;
+        let id = self.record("critic", input, args.context.as_deref(), link).await?;
 
-        let id = self.record("critic", thought, args.context.as_deref()).await;
+        let sampling = match args.sample.unwrap_or(false) {
+            true => Some(sample("critic", input, args, &mut evaluation).await),
+            false => None,
+        };
+        let mut data = json!({
+            "id": id,
+            "recorded": true,
+            "evaluation": evaluation,
+            "hint": "Critical analysis: address high-severity findings first. Heuristic scores are a floor, not a sign-off."
+        });
+        if let Some(sampling) = sampling {
+            data["sampling"] = sampling;
+        }
 
         Ok(json!({
             "ok": true,
-            "data": {
-                "id": id,
-                "input": thought,
-                "recorded": true,
-                "hint": "Critical analysis recorded. Use this to challenge assumptions and find flaws."
-            },
+            "data": data,
             "error": null,
             "meta": { "tool": "think", "action": "critic" }
         }))
     }
 
     async fn review(&self, args: &ThinkToolArgs) -> Result<Value> {
-        let code = args.code.as_deref()
+        let input = args.diff.as_deref()
+            .or(args.code.as_deref())
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("code required"))?;
+            .ok_or_else(|| ToolError::invalid("code or diff required"))?;
+
+        let rubric = rubric::parse_rubric(args.rubric.as_ref(), &Criterion::ALL)?;
+        let mut evaluation = rubric::evaluate(input, &rubric);
+        let strengths: Vec<Criterion> = evaluation.criteria.iter()
+            .filter(|c| c.findings.is_empty())
+            .map(|c| c.criterion)
+            .collect();
+
+        let link = self.link(args).await?;
+        let id = self.record("review", input, args.language.as_deref(), link).await?;
 
-        let id = self.record("review", code, args.language.as_deref()).await;
+        let sampling = match args.sample.unwrap_or(false) {
+            true => Some(sample("review", input, args, &mut evaluation).await),
+            false => None,
+        };
+        let mut data = json!({
+            "id": id,
+            "code_length": input.len(),
+            "language": args.language,
+            "recorded": true,
+            "evaluation": evaluation,
+            "strengths": strengths,
+            "hint": "Balanced review: weigh findings against intent before requesting changes."
+        });
+        if let Some(sampling) = sampling {
+            data["sampling"] = sampling;
+        }
 
         Ok(json!({
             "ok": true,
-            "data": {
-                "id": id,
-                "code_length": code.len(),
-                "language": args.language,
-                "recorded": true,
-                "hint": "Code review recorded. Use this for balanced analysis of code quality."
-            },
+            "data": data,
             "error": null,
             "meta": { "tool": "think", "action": "review" }
         }))
@@ -248,7 +427,7 @@ impl ThinkTool {
     async fn summarize(&self, args: &ThinkToolArgs) -> Result<Value> {
         let text = args.text.as_deref()
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("text required"))?;
+            .ok_or_else(|| ToolError::invalid("text required"))?;
 
         let words = text.split_whitespace().count();
         let chars = text.len();
@@ -268,7 +447,7 @@ impl ThinkTool {
     async fn classify(&self, args: &ThinkToolArgs) -> Result<Value> {
         let text = args.text.as_deref()
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("text required"))?;
+            .ok_or_else(|| ToolError::invalid("text required"))?;
 
         Ok(json!({
             "ok": true,
@@ -286,7 +465,7 @@ impl ThinkTool {
         let text = args.text.as_deref()
             .or(args.code.as_deref())
             .or(args.question.as_deref())
-            .ok_or_else(|| anyhow!("text, code, or question required"))?;
+            .ok_or_else(|| ToolError::invalid("text, code, or question required"))?;
 
         Ok(json!({
             "ok": true,
@@ -303,9 +482,10 @@ impl ThinkTool {
     async fn consensus(&self, args: &ThinkToolArgs) -> Result<Value> {
         let topic = args.topic.as_deref()
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("topic or thought required"))?;
+            .ok_or_else(|| ToolError::invalid("topic or thought required"))?;
         let perspectives = args.perspectives.unwrap_or(3);
-        let id = self.record("consensus", topic, args.context.as_deref()).await;
+        let link = self.link(args).await?;
+        let id = self.record("consensus", topic, args.context.as_deref(), link).await?;
         Ok(json!({
             "ok": true,
             "data": { "id": id, "topic": topic, "perspectives": perspectives, "recorded": true,
@@ -318,8 +498,9 @@ impl ThinkTool {
     async fn agent(&self, args: &ThinkToolArgs) -> Result<Value> {
         let goal = args.goal.as_deref()
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("goal or thought required"))?;
-        let id = self.record("agent", goal, args.context.as_deref()).await;
+            .ok_or_else(|| ToolError::invalid("goal or thought required"))?;
+        let link = self.link(args).await?;
+        let id = self.record("agent", goal, args.context.as_deref(), link).await?;
         Ok(json!({
             "ok": true,
             "data": { "id": id, "goal": goal, "recorded": true,
@@ -333,7 +514,7 @@ impl ThinkTool {
         let content = args.content.as_deref()
             .or(args.text.as_deref())
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("content or text required"))?;
+            .ok_or_else(|| ToolError::invalid("content or text required"))?;
         let target = args.target.as_deref().unwrap_or("");
         Ok(json!({
             "ok": true,
@@ -347,7 +528,7 @@ impl ThinkTool {
     async fn compare(&self, args: &ThinkToolArgs) -> Result<Value> {
         let items = args.items.as_deref()
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("items or thought required"))?;
+            .ok_or_else(|| ToolError::invalid("items or thought required"))?;
         Ok(json!({
             "ok": true,
             "data": { "items": items, "criteria": args.criteria,
@@ -360,8 +541,9 @@ impl ThinkTool {
     async fn chain(&self, args: &ThinkToolArgs) -> Result<Value> {
         let steps = args.steps.as_deref()
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("steps or thought required"))?;
-        let id = self.record("chain", steps, args.context.as_deref()).await;
+            .ok_or_else(|| ToolError::invalid("steps or thought required"))?;
+        let link = self.link(args).await?;
+        let id = self.record("chain", steps, args.context.as_deref(), link).await?;
         Ok(json!({
             "ok": true,
             "data": { "id": id, "recorded": true,
@@ -375,7 +557,7 @@ impl ThinkTool {
         let content = args.content.as_deref()
             .or(args.text.as_deref())
             .or(args.thought.as_deref())
-            .ok_or_else(|| anyhow!("content or text required"))?;
+            .ok_or_else(|| ToolError::invalid("content or text required"))?;
         Ok(json!({
             "ok": true,
             "data": { "input_length": content.len(),
@@ -385,6 +567,115 @@ impl ThinkTool {
         }))
     }
 
+    async fn history(&self, args: &ThinkToolArgs) -> Result<Value> {
+        let limit = args.limit.unwrap_or(50);
+        let journal = self.journal.read().await;
+        let matching: Vec<&ThinkEntry> = journal.iter()
+            .filter(|e| args.branch.as_ref().is_none_or(|b| &e.branch == b))
+            .collect();
+        let entries: Vec<&ThinkEntry> = matching.iter().rev().take(limit).rev().copied().collect();
+
+        Ok(json!({
+            "ok": true,
+            "data": {
+                "entries": entries,
+                "count": entries.len(),
+                "total": matching.len(),
+                "branch": args.branch
+            },
+            "error": null,
+            "meta": { "tool": "think", "action": "history" }
+        }))
+    }
+
+    /// Fork a new branch from an entry, or list branches when no name is given
+    async fn branch(&self, args: &ThinkToolArgs) -> Result<Value> {
+        let Some(name) = args.branch.as_deref() else {
+            let journal = self.journal.read().await;
+            return Ok(json!({
+                "ok": true,
+                "data": { "branches": branch_summaries(&journal) },
+                "error": null,
+                "meta": { "tool": "think", "action": "branch" }
+            }));
+        };
+
+        {
+            let journal = self.journal.read().await;
+            if journal.iter().any(|e| e.branch == name) {
+                return Err(ToolError::invalid(format!("Branch already exists: {}", name)).into());
+            }
+        }
+
+        // Fork point: explicit parent, else the latest entry on main
+        let from = match args.parent {
+            Some(id) => Some(id),
+            None => {
+                let journal = self.journal.read().await;
+                journal.iter().rev().find(|e| e.branch == MAIN_BRANCH).map(|e| e.id)
+            }
+        };
+        let link = self.link(&ThinkToolArgs {
+            branch: Some(name.to_string()),
+            parent: from,
+            ..Default::default()
+        }).await?;
+
+        let note = args.thought.clone().unwrap_or_else(|| match from {
+            Some(id) => format!("Branch {} from #{}", name, id),
+            None => format!("Branch {}", name),
+        });
+        let id = self.record("branch", &note, args.context.as_deref(), link).await?;
+
+        Ok(json!({
+            "ok": true,
+            "data": { "id": id, "branch": name, "from": from },
+            "error": null,
+            "meta": { "tool": "think", "action": "branch" }
+        }))
+    }
+
+    async fn export(&self, args: &ThinkToolArgs) -> Result<Value> {
+        let markdown = match args.format.as_deref().unwrap_or("json").to_lowercase().as_str() {
+            "json" => false,
+            "markdown" | "md" => true,
+            other => return Err(ToolError::invalid(format!("Unknown format: {} (json, markdown)", other)).into()),
+        };
+        let journal = self.journal.read().await;
+        let entries: Vec<&ThinkEntry> = journal.iter()
+            .filter(|e| args.branch.as_ref().is_none_or(|b| &e.branch == b))
+            .collect();
+        let export = json!({
+            "journal": self.journal_path,
+            "exported_at": chrono::Utc::now().to_rfc3339(),
+            "branches": branch_summaries(&journal),
+            "entries": entries
+        });
+        let text = if markdown {
+            let thoughts = export["entries"].as_array().map(Vec::as_slice);
+            Report { title: args.title.as_deref(), thoughts, ..Default::default() }.render()
+        } else {
+            serde_json::to_string_pretty(&export)?
+        };
+
+        let data = match &args.output {
+            Some(output) => {
+                let path = shellexpand::tilde(output).to_string();
+                tokio::fs::write(&path, text).await?;
+                json!({ "path": path, "count": entries.len() })
+            }
+            None if markdown => json!({ "format": "markdown", "report": text, "count": entries.len() }),
+            None => export,
+        };
+
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "think", "action": "export" }
+        }))
+    }
+
     fn help(&self) -> Value {
         json!({
             "ok": true,
@@ -392,8 +683,8 @@ impl ThinkTool {
                 "tool": "think",
                 "actions": {
                     "think": "Record structured reasoning (requires thought)",
-                    "critic": "Critical analysis (requires thought or code)",
-                    "review": "Balanced code review (requires code)",
+                    "critic": "Critical analysis scored on correctness + security (requires thought, code, or diff; optional rubric, sample)",
+                    "review": "Balanced review scored on correctness, security, style (requires code or diff; optional rubric, sample)",
                     "consensus": "Multi-perspective reasoning (requires topic)",
                     "agent": "Agent-style reasoning (requires goal)",
                     "summarize": "Compress to summary (requires text)",
@@ -402,8 +693,12 @@ impl ThinkTool {
                     "translate": "Translate between formats (requires content, target)",
                     "compare": "Compare items (requires items, optional criteria)",
                     "chain": "Chain-of-thought reasoning (requires steps)",
-                    "embed": "Embedding placeholder (requires content)"
-                }
+                    "embed": "Embedding placeholder (requires content)",
+                    "history": "Journal entries (optional branch, limit)",
+                    "branch": "Fork a branch from an entry (branch, optional parent); lists branches without a name",
+                    "export": "Export journal as JSON, or as a Markdown report with format=markdown (optional branch, output path, title)"
+                },
+                "links": "Pass parent and relation (extends, contradicts, resolves) to link a thought to an earlier entry"
             },
             "error": null,
             "meta": { "tool": "think", "action": "help" }
@@ -411,6 +706,127 @@ impl ThinkTool {
     }
 }
 
+/// Have the session's client score the input over MCP sampling and blend
+/// its criterion scores into `evaluation`. The result carries the model, its
+/// verdict and findings; when the client cannot sample it carries the error
+/// and the request, for the caller to forward to an LLM itself.
+async fn sample(action: &str, input: &str, args: &ThinkToolArgs, evaluation: &mut rubric::Evaluation) -> Value {
+    let request = sampling_request(action, input, args, evaluation);
+    let result = match sampling::create_message(args.session_id.as_deref(), request["params"].clone()).await {
+        Ok(result) => result,
+        Err(e) => return json!({ "error": e.to_string(), "request": request }),
+    };
+    match sampling::text(&result).and_then(sampled_scores) {
+        Some((reply, scores)) => {
+            evaluation.blend(&scores);
+            json!({ "model": result["model"], "verdict": reply["verdict"], "criteria": reply["criteria"] })
+        }
+        None => json!({
+            "model": result["model"],
+            "error": "The client's reply was not rubric JSON",
+            "reply": sampling::text(&result)
+        }),
+    }
+}
+
+/// The rubric JSON an LLM replied with and its criterion scores, allowing
+/// prose or a code fence around the object
+fn sampled_scores(text: &str) -> Option<(Value, Vec<(Criterion, f64)>)> {
+    let json = text.get(text.find('{')?..=text.rfind('}')?)?;
+    let reply: Value = serde_json::from_str(json).ok()?;
+    let scores = reply["criteria"].as_array()?.iter()
+        .filter_map(|c| Some((c["criterion"].as_str()?.parse().ok()?, c["score"].as_f64()?)))
+        .collect();
+    Some((reply, scores))
+}
+
+/// MCP `sampling/createMessage` request asking the client's LLM to score
+/// the input against the same rubric, seeded with the heuristic findings
+fn sampling_request(action: &str, input: &str, args: &ThinkToolArgs, evaluation: &rubric::Evaluation) -> Value {
+    let criteria: Vec<Criterion> = evaluation.criteria.iter().map(|c| c.criterion).collect();
+    let prompt = format!(
+        "Perform a {} of the following {}{}.\n\nScore each criterion {} from 0-10 and list findings with line numbers.\n\
+         Respond with JSON: {{\"criteria\": [{{\"criterion\", \"score\", \"findings\": [{{\"line\", \"severity\", \"message\"}}]}}], \"overall_score\", \"verdict\"}}.\n\n\
+         Static analysis findings:\n{}\n\nInput:\n{}",
+        action,
+        evaluation.input_kind,
+        args.language.as_deref().map(|l| format!(" ({})", l)).unwrap_or_default(),
+        serde_json::to_string(&criteria).unwrap_or_default(),
+        serde_json::to_string(&evaluation.criteria).unwrap_or_default(),
+        input
+    );
+
+    json!({
+        "method": "sampling/createMessage",
+        "params": {
+            "messages": [{ "role": "user", "content": { "type": "text", "text": prompt } }],
+            "systemPrompt": "You are a rigorous code reviewer. Output only JSON.",
+            "includeContext": "none",
+            "maxTokens": 2048
+        }
+    })
+}
+
+/// File-name-safe key for a project directory
+pub(crate) fn project_key(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
+
+/// Load journal entries, skipping lines that fail to parse
+fn load_journal(path: &Path) -> Vec<ThinkEntry> {
+    std::fs::read_to_string(path)
+        .map(|content| {
+            content.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+async fn append_journal(path: &Path, entry: &ThinkEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    file.write_all(line.as_bytes()).await?;
+    // tokio completes writes in the background; flush so reloads see the entry
+    file.flush().await?;
+    Ok(())
+}
+
+/// Per-branch entry counts, heads and fork points
+fn branch_summaries(journal: &[ThinkEntry]) -> Vec<Value> {
+    let mut names: Vec<&str> = Vec::new();
+    for entry in journal {
+        if !names.contains(&entry.branch.as_str()) {
+            names.push(&entry.branch);
+        }
+    }
+
+    names.into_iter()
+        .map(|name| {
+            let entries: Vec<&ThinkEntry> = journal.iter().filter(|e| e.branch == name).collect();
+            json!({
+                "name": name,
+                "entries": entries.len(),
+                "head": entries.last().map(|e| e.id),
+                "forked_from": entries.first().and_then(|e| e.parent)
+            })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -424,7 +840,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_llm_think() {
-        let tool = ThinkTool::new();
+        let tool = ThinkTool::with_journal(None);
         let result = tool.execute(ThinkToolArgs {
             action: Some("think".to_string()),
             thought: Some("Testing reasoning".to_string()),
@@ -433,4 +849,106 @@ mod tests {
         assert_eq!(result["ok"], true);
         assert_eq!(result["data"]["recorded"], true);
     }
+
+    #[tokio::test]
+    async fn test_journal_persists_branches_and_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+
+        let tool = ThinkTool::with_journal(Some(path.clone()));
+        let first = tool.execute(ThinkToolArgs {
+            action: Some("think".to_string()),
+            thought: Some("Cache is stale".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+        let first_id = first["data"]["id"].as_u64().unwrap() as usize;
+
+        let branched = tool.execute(ThinkToolArgs {
+            action: Some("branch".to_string()),
+            branch: Some("alt".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(branched["data"]["from"], first_id);
+
+        tool.execute(ThinkToolArgs {
+            action: Some("think".to_string()),
+            thought: Some("Cache is fine; clock skew".to_string()),
+            branch: Some("alt".to_string()),
+            parent: Some(first_id),
+            relation: Some("contradicts".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+
+        // Reload from disk
+        let reloaded = ThinkTool::with_journal(Some(path));
+        let history = reloaded.execute(ThinkToolArgs {
+            action: Some("history".to_string()),
+            branch: Some("alt".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(history["data"]["count"], 2);
+        assert_eq!(history["data"]["entries"][1]["relation"], "contradicts");
+        assert_eq!(history["data"]["entries"][1]["parent"], first_id);
+
+        let next = reloaded.execute(ThinkToolArgs {
+            action: Some("think".to_string()),
+            thought: Some("Continue".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(next["data"]["id"], 4);
+    }
+
+    #[tokio::test]
+    async fn test_relation_requires_parent() {
+        let tool = ThinkTool::with_journal(None);
+        let result = tool.execute(ThinkToolArgs {
+            action: Some("think".to_string()),
+            thought: Some("Orphan".to_string()),
+            relation: Some("resolves".to_string()),
+            ..Default::default()
+        }).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_review_scores_rubric() {
+        let tool = ThinkTool::with_journal(None);
+        let result = tool.execute(ThinkToolArgs {
+            action: Some("review".to_string()),
+            code: Some("fn run(cmd: &str) { let v = parse(cmd).unwrap(); }".to_string()),
+            rubric: Some(json!({"correctness": 2.0, "style": 1.0})),
+            sample: Some(true),
+            ..Default::default()
+        }).await.unwrap();
+
+        let evaluation = &result["data"]["evaluation"];
+        assert_eq!(evaluation["criteria"].as_array().unwrap().len(), 2);
+        assert_eq!(evaluation["criteria"][0]["findings"][0]["rule"], "unwrap");
+        assert_eq!(result["data"]["strengths"][0], "style");
+        assert_eq!(result["data"]["sampling"]["request"]["method"], "sampling/createMessage");
+        assert!(result["data"]["sampling"]["error"].as_str().unwrap().contains("session"));
+    }
+
+    #[tokio::test]
+    async fn test_critic_blends_sampled_scores() {
+        sampling::attach_client(Arc::new(|_, params: Value| Box::pin(async move {
+            assert_eq!(params["maxTokens"], 2048);
+            let text = "```json\n{\"criteria\": [{\"criterion\": \"correctness\", \"score\": 3, \"findings\": []}], \"verdict\": \"fail\"}\n```";
+            Ok(json!({ "role": "assistant", "model": "m1", "content": { "type": "text", "text": text } }))
+        })));
+        let tool = ThinkTool::with_journal(None);
+        let result = tool.execute(ThinkToolArgs {
+            action: Some("critic".to_string()),
+            code: Some("let v = parse(cmd).unwrap();".to_string()),
+            rubric: Some(json!(["correctness"])),
+            sample: Some(true),
+            session_id: Some("s1".to_string()),
+            ..Default::default()
+        }).await.unwrap();
+
+        assert_eq!(result["data"]["sampling"]["model"], "m1");
+        assert_eq!(result["data"]["sampling"]["verdict"], "fail");
+        assert_eq!(result["data"]["evaluation"]["criteria"][0]["score"], 5.8);
+        assert_eq!(result["data"]["evaluation"]["verdict"], "needs_work");
+    }
 }
@@ -0,0 +1,334 @@
+/// Vector store tool
+///
+/// Actions: query, upsert, get, delete, create, collections, info, drop, help
+///
+/// Front end to the shared `VectorStore`: named collections of documents
+/// with embeddings and JSON metadata, ranked by similarity to a text or
+/// vector and narrowed by metadata filters. Documents may bring their own
+/// embeddings or have the store embed their content (see `VectorConfig`).
+/// Collections persist under the data directory and are created on first
+/// upsert unless created explicitly with a metric or dimension check.
+
+use anyhow::Result;
+use crate::config::VectorConfig;
+use crate::error::ToolError;
+use crate::search::vector_store::{self, Embedder, Metric, NewRecord, Query, VectorStore};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Matches returned by query unless told otherwise
+const DEFAULT_LIMIT: usize = 10;
+
+/// Records returned by get unless told otherwise
+const DEFAULT_GET_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorAction {
+    #[default]
+    Query,
+    Upsert,
+    Get,
+    Delete,
+    Create,
+    Collections,
+    Info,
+    Drop,
+    Help,
+}
+
+impl std::str::FromStr for VectorAction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "query" | "search" | "similar" | "" => Ok(Self::Query),
+            "upsert" | "add" | "insert" | "index" => Ok(Self::Upsert),
+            "get" | "fetch" => Ok(Self::Get),
+            "delete" | "remove" => Ok(Self::Delete),
+            "create" | "create_collection" => Ok(Self::Create),
+            "collections" | "list" => Ok(Self::Collections),
+            "info" | "stats" => Ok(Self::Info),
+            "drop" | "drop_collection" => Ok(Self::Drop),
+            "help" => Ok(Self::Help),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
+        }
+    }
+}
+
+impl VectorAction {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Query => "query",
+            Self::Upsert => "upsert",
+            Self::Get => "get",
+            Self::Delete => "delete",
+            Self::Create => "create",
+            Self::Collections => "collections",
+            Self::Info => "info",
+            Self::Drop => "drop",
+            Self::Help => "help",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorStoreToolArgs {
+    pub action: Option<String>,
+    pub collection: Option<String>,
+    /// Documents to upsert: {id, content, metadata, embedding}
+    pub documents: Option<Vec<Value>>,
+    /// One document to upsert, as an alternative to documents
+    pub id: Option<String>,
+    pub content: Option<String>,
+    pub metadata: Option<Value>,
+    pub embedding: Option<Vec<f32>>,
+    /// Query text, embedded by the store
+    pub text: Option<String>,
+    /// Query vector
+    pub vector: Option<Vec<f32>>,
+    /// Metadata filter, e.g. {"lang": "rust", "stars": {"$gte": 10}}
+    pub filter: Option<Value>,
+    /// Record ids for get and delete
+    pub ids: Option<Vec<String>>,
+    pub limit: Option<usize>,
+    /// Leave out matches scoring below this
+    pub min_score: Option<f32>,
+    /// Return embeddings along with matches
+    pub include_embeddings: Option<bool>,
+    /// For create: cosine (default), dot or euclidean
+    pub metric: Option<String>,
+    /// For create: reject vectors of any other length
+    pub dimensions: Option<usize>,
+}
+
+pub struct VectorStoreToolDefinition;
+
+impl VectorStoreToolDefinition {
+    pub fn schema() -> Value {
+        json!({
+            "name": "vector",
+            "description": "Vector store: upsert documents with embeddings (or text the store embeds), query by similarity with metadata filters, persisted collections. Actions: query, upsert, get, delete, create, collections, info, drop, help",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "action": {
+                        "type": "string",
+                        "enum": ["query", "upsert", "get", "delete", "create", "collections", "info", "drop", "help"],
+                        "default": "query"
+                    },
+                    "collection": { "type": "string", "description": "Collection name (letters, digits, '-', '_', '.')" },
+                    "documents": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "id": { "type": "string" },
+                                "content": { "type": "string" },
+                                "metadata": { "type": "object" },
+                                "embedding": { "type": "array", "items": { "type": "number" } }
+                            }
+                        },
+                        "description": "For upsert; id defaults to a hash of content, embedding to the store's embedding of content"
+                    },
+                    "id": { "type": "string", "description": "For upsert of a single document" },
+                    "content": { "type": "string", "description": "For upsert of a single document" },
+                    "metadata": { "type": "object", "description": "For upsert of a single document" },
+                    "embedding": { "type": "array", "items": { "type": "number" }, "description": "For upsert of a single document" },
+                    "text": { "type": "string", "description": "For query: text to find similar documents to" },
+                    "vector": { "type": "array", "items": { "type": "number" }, "description": "For query: embedding to find similar documents to" },
+                    "filter": { "type": "object", "description": "Metadata filter: {\"field\": value} or operators $eq $ne $gt $gte $lt $lte $in $nin $exists $contains, combined with $and $or $not" },
+                    "ids": { "type": "array", "items": { "type": "string" }, "description": "For get and delete" },
+                    "limit": { "type": "integer", "description": "Most results (query default 10, get default 100)" },
+                    "min_score": { "type": "number", "description": "For query: drop matches scoring lower" },
+                    "include_embeddings": { "type": "boolean", "default": false },
+                    "metric": { "type": "string", "enum": ["cosine", "dot", "euclidean"], "description": "For create (default cosine)" },
+                    "dimensions": { "type": "integer", "description": "For create: fix the vector length" }
+                }
+            }
+        })
+    }
+}
+
+pub struct VectorStoreTool {
+    store: Arc<VectorStore>,
+}
+
+impl VectorStoreTool {
+    pub fn new() -> Self {
+        Self::with_config(VectorConfig::default())
+    }
+
+    pub fn with_config(config: VectorConfig) -> Self {
+        let dir = config.path
+            .map(|p| PathBuf::from(shellexpand::tilde(&p.to_string_lossy()).into_owned()))
+            .unwrap_or_else(vector_store::default_vector_dir);
+        Self::with_store(Arc::new(VectorStore::open(dir, Embedder::from_config(config.embeddings.as_ref()))))
+    }
+
+    pub fn with_store(store: Arc<VectorStore>) -> Self {
+        Self { store }
+    }
+
+    /// The store behind the tool, for other tools to share
+    pub fn store(&self) -> Arc<VectorStore> {
+        self.store.clone()
+    }
+
+    pub async fn execute(&self, args: VectorStoreToolArgs) -> Result<Value> {
+        let action: VectorAction = args.action.as_deref().unwrap_or("query").parse()?;
+        let data = match action {
+            VectorAction::Query => self.query(args).await?,
+            VectorAction::Upsert => self.upsert(args).await?,
+            VectorAction::Get => self.get(&args).await?,
+            VectorAction::Delete => self.delete(&args).await?,
+            VectorAction::Create => self.create(&args).await?,
+            VectorAction::Collections => {
+                let collections = self.store.collections().await?;
+                json!({ "count": collections.len(), "collections": collections, "path": self.store.dir() })
+            }
+            VectorAction::Info => self.store.info(collection(&args)?).await?,
+            VectorAction::Drop => {
+                let name = collection(&args)?;
+                if !self.store.drop_collection(name).await? {
+                    return Err(ToolError::not_found(format!("No such collection: {}", name)).into());
+                }
+                json!({ "dropped": name })
+            }
+            VectorAction::Help => self.help(),
+        };
+        Ok(json!({
+            "ok": true,
+            "data": data,
+            "error": null,
+            "meta": { "tool": "vector", "action": action.name() }
+        }))
+    }
+
+    async fn query(&self, args: VectorStoreToolArgs) -> Result<Value> {
+        let name = collection(&args)?.to_string();
+        let limit = args.limit.unwrap_or(DEFAULT_LIMIT);
+        let query = Query {
+            vector: args.vector,
+            text: args.text.or(args.content),
+            filter: args.filter,
+            limit,
+            min_score: args.min_score,
+            include_embeddings: args.include_embeddings.unwrap_or(false),
+        };
+        let matches = self.store.query(&name, query).await?;
+        Ok(json!({ "collection": name, "count": matches.len(), "matches": matches }))
+    }
+
+    async fn upsert(&self, args: VectorStoreToolArgs) -> Result<Value> {
+        let name = collection(&args)?.to_string();
+        let mut records: Vec<NewRecord> = args.documents.unwrap_or_default().into_iter()
+            .map(|doc| serde_json::from_value(doc).map_err(|e| ToolError::invalid(format!("Invalid document: {}", e))))
+            .collect::<std::result::Result<_, _>>()?;
+        if args.content.is_some() || args.embedding.is_some() {
+            records.push(NewRecord { id: args.id, content: args.content, metadata: args.metadata, embedding: args.embedding });
+        }
+        if records.is_empty() {
+            return Err(ToolError::invalid("upsert needs documents, or content or an embedding").into());
+        }
+        self.store.upsert(&name, records).await
+    }
+
+    async fn get(&self, args: &VectorStoreToolArgs) -> Result<Value> {
+        let name = collection(args)?;
+        let limit = args.limit.unwrap_or(DEFAULT_GET_LIMIT);
+        let records = self.store.get(name, args.ids.as_deref(), args.filter.as_ref(), limit, args.include_embeddings.unwrap_or(false)).await?;
+        Ok(json!({ "collection": name, "count": records.len(), "documents": records }))
+    }
+
+    async fn delete(&self, args: &VectorStoreToolArgs) -> Result<Value> {
+        let name = collection(args)?;
+        let deleted = self.store.delete(name, args.ids.as_deref(), args.filter.as_ref()).await?;
+        Ok(json!({ "collection": name, "deleted": deleted }))
+    }
+
+    async fn create(&self, args: &VectorStoreToolArgs) -> Result<Value> {
+        let metric: Metric = args.metric.as_deref().unwrap_or("cosine").parse()?;
+        self.store.create(collection(args)?, args.dimensions, metric).await
+    }
+
+    fn help(&self) -> Value {
+        json!({
+            "tool": "vector",
+            "embedder": self.store.embedder().name(),
+            "path": self.store.dir(),
+            "actions": {
+                "query": "Documents most similar to text or vector (collection; optional filter, limit, min_score)",
+                "upsert": "Insert or replace documents (collection, documents or content/embedding/metadata/id)",
+                "get": "Documents by ids or filter",
+                "delete": "Remove documents by ids or filter",
+                "create": "Create a collection with a metric and optional fixed dimensions",
+                "collections": "List collections with counts",
+                "info": "One collection's size, dimensions, metric and embedder",
+                "drop": "Delete a collection"
+            },
+            "filters": "{\"lang\": \"rust\"}, {\"stars\": {\"$gte\": 10}}, {\"$or\": [{\"tag\": \"a\"}, {\"tag\": \"b\"}]}"
+        })
+    }
+}
+
+impl Default for VectorStoreTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn collection(args: &VectorStoreToolArgs) -> Result<&str> {
+    args.collection.as_deref().ok_or_else(|| ToolError::invalid("collection required").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(value: Value) -> VectorStoreToolArgs {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_vector_tool() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = VectorStoreTool::with_config(VectorConfig { path: Some(dir.path().to_path_buf()), embeddings: None });
+        let created = tool.execute(args(json!({ "action": "create", "collection": "pts", "metric": "l2", "dimensions": 2 }))).await.unwrap();
+        assert_eq!(created["data"]["metric"], "euclidean");
+
+        let upserted = tool.execute(args(json!({
+            "action": "upsert",
+            "collection": "pts",
+            "documents": [
+                { "id": "origin", "embedding": [0.0, 0.0], "metadata": { "kind": "a" } },
+                { "id": "far", "vector": [5.0, 5.0], "metadata": { "kind": "b" } }
+            ]
+        }))).await.unwrap();
+        assert_eq!(upserted["data"]["inserted"], 2);
+        let single = tool.execute(args(json!({ "action": "add", "collection": "pts", "id": "near", "embedding": [1.0, 0.0], "metadata": { "kind": "b" } }))).await.unwrap();
+        assert_eq!(single["data"]["count"], 3);
+        assert!(tool.execute(args(json!({ "action": "upsert", "collection": "pts", "embedding": [1.0] }))).await.is_err());
+
+        let hits = tool.execute(args(json!({ "collection": "pts", "vector": [0.9, 0.1], "limit": 2 }))).await.unwrap();
+        assert_eq!(hits["meta"]["action"], "query");
+        assert_eq!(hits["data"]["matches"][0]["id"], "near");
+        assert_eq!(hits["data"]["matches"][1]["id"], "origin");
+        let filtered = tool.execute(args(json!({ "collection": "pts", "vector": [0.0, 0.0], "filter": { "kind": "b" }, "min_score": 0.2 }))).await.unwrap();
+        assert_eq!(filtered["data"]["count"], 1);
+
+        let got = tool.execute(args(json!({ "action": "get", "collection": "pts", "ids": ["far", "nope"], "include_embeddings": true }))).await.unwrap();
+        assert_eq!(got["data"]["documents"][0]["embedding"], json!([5.0, 5.0]));
+        assert!(tool.execute(args(json!({ "action": "delete", "collection": "pts" }))).await.is_err());
+        let deleted = tool.execute(args(json!({ "action": "delete", "collection": "pts", "filter": { "kind": { "$in": ["b"] } } }))).await.unwrap();
+        assert_eq!(deleted["data"]["deleted"], 2);
+
+        let listed = tool.execute(args(json!({ "action": "collections" }))).await.unwrap();
+        assert_eq!(listed["data"]["collections"][0]["count"], 1);
+        tool.execute(args(json!({ "action": "drop", "collection": "pts" }))).await.unwrap();
+        assert!(tool.execute(args(json!({ "action": "info", "collection": "pts" }))).await.is_err());
+        assert!(tool.execute(args(json!({ "action": "query" }))).await.is_err());
+    }
+}
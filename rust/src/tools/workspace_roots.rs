@@ -0,0 +1,479 @@
+/// Project roots the server works in
+///
+/// Roots come from the directory the server started in, the `workspaces`
+/// config, the roots an MCP client reports (`roots/list`) and `workspace
+/// add`. Each session works in one active root: the one it switched to,
+/// else the first root its client reported, else the default root. Calls
+/// resolve relative paths against that root, and per-project state (plans,
+/// project-scoped memories) follows it. A root may carry its own allow and
+/// deny lists on top of the server-wide hooks.
+///
+/// A client that reports roots also bounds its session: paths outside its
+/// roots and the configured ones are refused.
+
+use crate::config::WorkspaceRoot;
+use crate::error::ToolError;
+use crate::hooks::ToolCall;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+
+/// Params naming a directory or file (or a list of files), per tool, and
+/// whether a call that leaves them out works in the root
+const PATH_PARAMS: &[(&str, &str, bool)] = &[
+    // exec defaults its own cwd, see ExecConfig::default_cwd
+    ("exec", "cwd", false),
+    ("exec", "workdir", false),
+    ("repl", "cwd", true),
+    ("fs", "path", false),
+    ("fs", "file_path", false),
+    ("fs", "source", false),
+    ("fs", "dest", false),
+    ("search", "path", true),
+    ("code", "path", false),
+    ("diagnostics", "path", true),
+    ("test", "path", true),
+    ("task", "path", true),
+    ("git", "path", true),
+    ("workspace", "path", true),
+    ("lsp", "path", false),
+    ("lsp", "root", true),
+    ("storage", "path", false),
+    ("image", "path", false),
+    ("image", "other", false),
+    ("image", "output", false),
+    ("scratch", "path", false),
+    ("scratch", "to_path", false),
+    ("browser", "files", false),
+    ("fetch", "output", false),
+    ("docker", "path", true),
+    ("docker", "file", false),
+    ("k8s", "file", false),
+    ("memory", "path", false),
+    ("plan", "path", false),
+    ("think", "output", false),
+];
+
+/// fs actions whose path is a directory to walk, `.` when left out
+const FS_DIR_ACTIONS: &[&str] = &["tree", "ls", "find", "glob", "search", "grep", "dedupe", "duplicates"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RootSource {
+    /// The directory the server started in
+    Startup,
+    Config,
+    /// Reported by the MCP client
+    Client,
+    /// Added with `workspace add`
+    Added,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Root {
+    pub name: String,
+    pub path: PathBuf,
+    pub source: RootSource,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    /// Session whose client reported the root; only it sees the root
+    #[serde(skip)]
+    pub session: Option<String>,
+}
+
+impl Root {
+    fn visible_to(&self, session: Option<&str>) -> bool {
+        self.session.is_none() || self.session.as_deref() == session
+    }
+}
+
+pub struct Roots {
+    roots: RwLock<Vec<Root>>,
+    /// Root sessions without a choice of their own work in
+    default: RwLock<String>,
+    /// Root each session switched to
+    active: RwLock<HashMap<String, String>>,
+}
+
+impl Roots {
+    pub fn new() -> Self {
+        let path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let path = path.canonicalize().unwrap_or(path);
+        let startup = Root {
+            name: base_name(&path),
+            path,
+            source: RootSource::Startup,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            session: None,
+        };
+        Self {
+            default: RwLock::new(startup.name.clone()),
+            roots: RwLock::new(vec![startup]),
+            active: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Add the configured roots; one at the startup directory gives it its
+    /// name and policies instead
+    pub fn configure(&self, configured: &[WorkspaceRoot]) -> Result<()> {
+        for entry in configured {
+            let path = canonical(&entry.path.to_string_lossy())?;
+            let mut roots = self.roots.write().unwrap();
+            if let Some(startup) = roots.iter_mut().find(|r| r.source == RootSource::Startup && r.path == path) {
+                if let Some(name) = &entry.name {
+                    let old = std::mem::replace(&mut startup.name, name.clone());
+                    let mut default = self.default.write().unwrap();
+                    if *default == old {
+                        *default = name.clone();
+                    }
+                }
+                startup.allow = entry.allow.clone();
+                startup.deny = entry.deny.clone();
+                continue;
+            }
+            drop(roots);
+            let mut root = self.add(&path.to_string_lossy(), entry.name.clone(), RootSource::Config)?;
+            root.allow = entry.allow.clone();
+            root.deny = entry.deny.clone();
+            self.replace(root);
+        }
+        Ok(())
+    }
+
+    fn replace(&self, root: Root) {
+        let mut roots = self.roots.write().unwrap();
+        if let Some(existing) = roots.iter_mut().find(|r| r.name == root.name) {
+            *existing = root;
+        }
+    }
+
+    /// Add a root, or return the one already at `path`
+    pub fn add(&self, path: &str, name: Option<String>, source: RootSource) -> Result<Root> {
+        self.add_for(path, name, source, None)
+    }
+
+    fn add_for(&self, path: &str, name: Option<String>, source: RootSource, session: Option<&str>) -> Result<Root> {
+        let path = canonical(path)?;
+        if !path.is_dir() {
+            return Err(ToolError::invalid(format!("Not a directory: {}", path.display())).into());
+        }
+        let mut roots = self.roots.write().unwrap();
+        if let Some(existing) = roots.iter().find(|r| r.path == path && r.visible_to(session)) {
+            return Ok(existing.clone());
+        }
+        let base = name.unwrap_or_else(|| base_name(&path));
+        let mut name = base.clone();
+        let mut n = 2;
+        while roots.iter().any(|r| r.name == name) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        let root = Root { name, path, source, allow: Vec::new(), deny: Vec::new(), session: session.map(str::to_string) };
+        roots.push(root.clone());
+        Ok(root)
+    }
+
+    /// Drop a root; sessions working in it go back to their default
+    pub fn remove(&self, name_or_path: &str, session: Option<&str>) -> Result<Root> {
+        let root = self.find(name_or_path, session)?;
+        if root.source == RootSource::Startup {
+            return Err(ToolError::invalid("The startup root cannot be removed").into());
+        }
+        self.drop_root(&root);
+        Ok(root)
+    }
+
+    fn drop_root(&self, root: &Root) {
+        self.roots.write().unwrap().retain(|r| r.name != root.name);
+        self.active.write().unwrap().retain(|_, name| *name != root.name);
+        let startup = self.startup();
+        let mut default = self.default.write().unwrap();
+        if *default == root.name {
+            *default = startup.name;
+        }
+    }
+
+    /// Replace the roots `session`'s client reported with `roots`, MCP
+    /// `Root` objects (`{uri: "file://...", name}`)
+    pub fn set_client_roots(&self, session: Option<&str>, roots: &[Value]) -> Result<Vec<Root>> {
+        for stale in self.client_roots(session) {
+            self.drop_root(&stale);
+        }
+        let mut added = Vec::new();
+        for root in roots {
+            let uri = root["uri"].as_str().ok_or_else(|| ToolError::invalid("Root without a uri"))?;
+            let path = url::Url::parse(uri).ok()
+                .filter(|u| u.scheme() == "file")
+                .and_then(|u| u.to_file_path().ok())
+                .ok_or_else(|| ToolError::invalid(format!("Roots must be file:// URIs: {}", uri)))?;
+            match self.add_for(&path.to_string_lossy(), root["name"].as_str().map(str::to_string), RootSource::Client, session) {
+                Ok(root) => added.push(root),
+                Err(e) => log::warn!("Skipping client root {}: {}", uri, e),
+            }
+        }
+        Ok(added)
+    }
+
+    fn client_roots(&self, session: Option<&str>) -> Vec<Root> {
+        self.roots.read().unwrap().iter()
+            .filter(|r| r.source == RootSource::Client && r.session.as_deref() == session)
+            .cloned()
+            .collect()
+    }
+
+    /// Make a root the active one for `session`, or the default for
+    /// sessions that have not chosen when there is no session
+    pub fn switch(&self, name_or_path: &str, session: Option<&str>) -> Result<Root> {
+        let root = self.find(name_or_path, session)?;
+        match session {
+            Some(session) => {
+                self.active.write().unwrap().insert(session.to_string(), root.name.clone());
+            }
+            None => *self.default.write().unwrap() = root.name.clone(),
+        }
+        Ok(root)
+    }
+
+    /// A root `session` can see, by name or path
+    pub fn find(&self, name_or_path: &str, session: Option<&str>) -> Result<Root> {
+        let roots = self.roots.read().unwrap();
+        let path = canonical(name_or_path).ok();
+        roots.iter()
+            .filter(|r| r.visible_to(session))
+            .find(|r| r.name == name_or_path || Some(&r.path) == path.as_ref())
+            .cloned()
+            .ok_or_else(|| ToolError::not_found(format!("No workspace root {}; list them with workspace roots", name_or_path)).into())
+    }
+
+    fn startup(&self) -> Root {
+        self.roots.read().unwrap().iter().find(|r| r.source == RootSource::Startup).cloned().expect("startup root")
+    }
+
+    /// Root `session` works in
+    pub fn active(&self, session: Option<&str>) -> Root {
+        if let Some(root) = session
+            .and_then(|s| self.active.read().unwrap().get(s).cloned())
+            .and_then(|name| self.find(&name, session).ok())
+        {
+            return root;
+        }
+        if let Some(root) = self.client_roots(session).into_iter().next() {
+            return root;
+        }
+        let name = self.default.read().unwrap().clone();
+        self.find(&name, session).unwrap_or_else(|_| self.startup())
+    }
+
+    /// Every root `session` can see
+    pub fn visible(&self, session: Option<&str>) -> Vec<Root> {
+        self.roots.read().unwrap().iter().filter(|r| r.visible_to(session)).cloned().collect()
+    }
+
+    pub fn list(&self, session: Option<&str>) -> Vec<Value> {
+        let active = self.active(session).name;
+        self.visible(session).iter().map(|root| {
+            let mut entry = json!(root);
+            entry["active"] = json!(root.name == active);
+            entry
+        }).collect()
+    }
+
+    /// Forget the root a session switched to and the roots its client
+    /// reported
+    pub fn end_session(&self, session: &str) {
+        self.active.write().unwrap().remove(session);
+        self.roots.write().unwrap().retain(|r| r.session.as_deref() != Some(session));
+    }
+
+    /// Why the active root's policies, or the session's roots, refuse
+    /// `call`, if they do
+    pub fn refuses(&self, call: &ToolCall) -> Option<String> {
+        let session = call.session.as_deref();
+        let root = self.active(session);
+        if call.matches(&root.deny) {
+            return Some(format!("{} is denied in workspace {}", call.key(), root.name));
+        }
+        if !root.allow.is_empty() && !call.matches(&root.allow) {
+            return Some(format!("{} is not allowed in workspace {}", call.key(), root.name));
+        }
+        self.outside(&call.tool, &call.params, session)
+    }
+
+    /// The first path of a call outside the roots its session's client
+    /// allows, for clients that reported roots
+    fn outside(&self, tool: &str, params: &Value, session: Option<&str>) -> Option<String> {
+        let client = self.client_roots(session);
+        if client.is_empty() {
+            return None;
+        }
+        let allowed: Vec<PathBuf> = self.roots.read().unwrap().iter()
+            .filter(|r| r.source == RootSource::Config)
+            .chain(client.iter())
+            .map(|r| r.path.clone())
+            .collect();
+        PATH_PARAMS.iter()
+            .filter(|(t, _, _)| *t == tool)
+            .flat_map(|(_, key, _)| paths(&params[*key]))
+            .find(|path| {
+                let path = existing_ancestor(Path::new(&shellexpand::tilde(path).to_string()));
+                !allowed.iter().any(|root| path.starts_with(root))
+            })
+            .map(|path| format!("{} is outside the client's roots", path))
+    }
+
+    /// Resolve a call's paths against the session's root. Calls in the
+    /// startup root are left alone, since relative paths already resolve
+    /// there.
+    pub fn resolve(&self, tool: &str, params: &mut Value, session: Option<&str>) {
+        let root = self.active(session);
+        if root.source == RootSource::Startup || !params.is_object() {
+            return;
+        }
+        let walks = tool == "fs" && params["action"].as_str().is_some_and(|a| FS_DIR_ACTIONS.contains(&a));
+        let under_root = |path: &str| {
+            let expanded = shellexpand::tilde(path).to_string();
+            match Path::new(&expanded).is_relative() {
+                true => json!(root.path.join(expanded)),
+                false => json!(path),
+            }
+        };
+        for (_, key, default) in PATH_PARAMS.iter().filter(|(t, _, _)| *t == tool) {
+            match &params[*key] {
+                Value::String(path) => params[*key] = under_root(path),
+                Value::Array(list) => params[*key] = list.iter()
+                    .map(|item| item.as_str().map_or_else(|| item.clone(), under_root))
+                    .collect(),
+                Value::Null if *default || walks => params[*key] = json!(root.path),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Default for Roots {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn canonical(path: &str) -> Result<PathBuf> {
+    let expanded = shellexpand::tilde(path).to_string();
+    std::fs::canonicalize(&expanded).map_err(|e| ToolError::not_found(format!("{}: {}", path, e)).into())
+}
+
+/// The path or paths a param's value names
+fn paths(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(path) => vec![path.as_str()],
+        Value::Array(list) => list.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// `path` canonicalized through its longest existing ancestor, so paths
+/// about to be created resolve symlinks and `..` like existing ones. Below
+/// that ancestor nothing exists to be a symlink, so `..` there goes up
+/// lexically, as creating the path would.
+fn existing_ancestor(path: &Path) -> PathBuf {
+    let mut rest = Vec::new();
+    let mut current = path;
+    loop {
+        if let Ok(found) = current.canonicalize() {
+            return rest.iter().rev().fold(found, |mut acc: PathBuf, part: &Component| {
+                match part {
+                    Component::ParentDir => {
+                        acc.pop();
+                    }
+                    part => acc.push(part),
+                }
+                acc
+            });
+        }
+        match (current.parent(), current.components().next_back()) {
+            (Some(parent), Some(part @ (Component::Normal(_) | Component::ParentDir))) => {
+                rest.push(part);
+                current = parent;
+            }
+            (Some(parent), Some(Component::CurDir)) => current = parent,
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+fn base_name(path: &Path) -> String {
+    path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "root".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roots() {
+        let a = tempfile::tempdir().unwrap();
+        let b = tempfile::tempdir().unwrap();
+        let roots = Roots::new();
+        let added = roots.add(&a.path().to_string_lossy(), Some("app".into()), RootSource::Added).unwrap();
+        assert_eq!(roots.add(&a.path().to_string_lossy(), None, RootSource::Added).unwrap().name, "app");
+        assert!(roots.add("/definitely/not/here", None, RootSource::Added).is_err());
+
+        roots.switch("app", Some("s1")).unwrap();
+        assert_eq!(roots.active(Some("s1")).path, added.path);
+        assert_eq!(roots.active(Some("s2")).source, RootSource::Startup);
+
+        let mut params = json!({ "action": "read", "path": "src/main.rs" });
+        roots.resolve("fs", &mut params, Some("s1"));
+        assert_eq!(params["path"], json!(added.path.join("src/main.rs")));
+        let mut params = json!({ "action": "tree" });
+        roots.resolve("fs", &mut params, Some("s1"));
+        assert_eq!(params["path"], json!(added.path));
+        let mut params = json!({ "command": "ls" });
+        roots.resolve("exec", &mut params, Some("s2"));
+        assert!(params["cwd"].is_null());
+
+        roots.remove("app", Some("s1")).unwrap();
+        assert_eq!(roots.active(Some("s1")).source, RootSource::Startup);
+        assert!(roots.remove(&roots.active(None).name, None).is_err());
+    }
+
+    #[test]
+    fn test_client_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().canonicalize().unwrap();
+        let roots = Roots::new();
+        let uri = url::Url::from_directory_path(&lib).unwrap();
+        let client = roots.set_client_roots(Some("s1"), &[json!({ "uri": uri.as_str(), "name": "lib" })]).unwrap();
+        assert_eq!(client[0].name, "lib");
+        assert!(roots.set_client_roots(Some("s1"), &[json!({ "uri": "https://example.com" })]).is_err());
+        roots.set_client_roots(Some("s1"), &[json!({ "uri": uri.as_str(), "name": "lib" })]).unwrap();
+
+        // The client's first root replaces the startup directory for its session only
+        assert_eq!(roots.active(Some("s1")).name, "lib");
+        assert_eq!(roots.active(Some("s2")).source, RootSource::Startup);
+        assert!(roots.find("lib", Some("s2")).is_err());
+
+        let inside = ToolCall::new("fs", json!({ "action": "write", "path": lib.join("new/file.txt") }), Some("s1"));
+        assert_eq!(roots.refuses(&inside), None);
+        let outside = ToolCall::new("fs", json!({ "action": "read", "path": "/etc/hostname" }), Some("s1"));
+        assert!(roots.refuses(&outside).unwrap().contains("outside"));
+        let escape = ToolCall::new("fs", json!({ "action": "read", "path": lib.join("../x") }), Some("s1"));
+        assert!(roots.refuses(&escape).is_some());
+        let missing = ToolCall::new("fs", json!({ "action": "write", "path": lib.join("missing/../../escaped/x.txt") }), Some("s1"));
+        assert!(roots.refuses(&missing).is_some());
+        let within = ToolCall::new("fs", json!({ "action": "write", "path": lib.join("missing/../kept/x.txt") }), Some("s1"));
+        assert_eq!(roots.refuses(&within), None);
+        let upload = ToolCall::new("browser", json!({ "action": "upload", "files": [lib.join("a.txt"), "/etc/hostname"] }), Some("s1"));
+        assert!(roots.refuses(&upload).unwrap().contains("/etc/hostname"));
+        let other = ToolCall::new("fs", json!({ "action": "read", "path": "/etc/hostname" }), Some("s2"));
+        assert_eq!(roots.refuses(&other), None);
+
+        roots.end_session("s1");
+        assert_eq!(roots.active(Some("s1")).source, RootSource::Startup);
+    }
+}
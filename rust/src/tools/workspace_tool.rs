@@ -1,11 +1,14 @@
 /// Workspace context tool (HIP-0300)
 ///
-/// Actions: detect, capabilities, help
+/// Actions: detect, capabilities, schema, roots, switch, add, remove, help
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
+use crate::error::ToolError;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use super::workspace_roots::{RootSource, Roots};
 use std::path::Path;
+use std::sync::Arc;
 use which::which;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -14,6 +17,10 @@ pub enum WsAction {
     Detect,
     Capabilities,
     Schema,
+    Roots,
+    Switch,
+    Add,
+    Remove,
     Help,
 }
 
@@ -31,8 +38,12 @@ impl std::str::FromStr for WsAction {
             "detect" | "scan" => Ok(Self::Detect),
             "capabilities" | "caps" => Ok(Self::Capabilities),
             "schema" => Ok(Self::Schema),
+            "roots" | "list" => Ok(Self::Roots),
+            "switch" | "use" => Ok(Self::Switch),
+            "add" => Ok(Self::Add),
+            "remove" | "rm" => Ok(Self::Remove),
             "help" | "" => Ok(Self::Help),
-            _ => Err(anyhow!("Unknown action: {}", s)),
+            _ => Err(ToolError::invalid(format!("Unknown action: {}", s)).into()),
         }
     }
 }
@@ -41,6 +52,11 @@ impl std::str::FromStr for WsAction {
 pub struct WorkspaceToolArgs {
     pub action: Option<String>,
     pub path: Option<String>,
+    /// Root name for switch, add and remove
+    pub name: Option<String>,
+    /// MCP session making the call, set by the server rather than the client
+    #[serde(skip)]
+    pub session_id: Option<String>,
 }
 
 pub struct WorkspaceToolDefinition;
@@ -49,16 +65,17 @@ impl WorkspaceToolDefinition {
     pub fn schema() -> Value {
         json!({
             "name": "workspace",
-            "description": "Workspace context: detect, capabilities, schema, help",
+            "description": "Workspace context and project roots: detect, capabilities, schema, roots (list), switch, add, remove, help. The active root is where relative paths resolve and per-project plans, memories and permissions apply",
             "inputSchema": {
                 "type": "object",
                 "properties": {
                     "action": {
                         "type": "string",
-                        "enum": ["detect", "capabilities", "schema", "help"],
+                        "enum": ["detect", "capabilities", "schema", "roots", "switch", "add", "remove", "help"],
                         "description": "Workspace action"
                     },
-                    "path": { "type": "string", "description": "Project root", "default": "." }
+                    "path": { "type": "string", "description": "Project root", "default": "." },
+                    "name": { "type": "string", "description": "Root name (switch, remove; optional for add)" }
                 },
                 "required": ["action"]
             }
@@ -66,11 +83,18 @@ impl WorkspaceToolDefinition {
     }
 }
 
-pub struct WorkspaceTool;
+pub struct WorkspaceTool {
+    roots: Arc<Roots>,
+}
 
 impl WorkspaceTool {
     pub fn new() -> Self {
-        Self
+        Self::with_roots(Arc::new(Roots::new()))
+    }
+
+    /// Workspace tool managing `roots`, shared with the registry
+    pub fn with_roots(roots: Arc<Roots>) -> Self {
+        Self { roots }
     }
 
     pub async fn execute(&self, args: WorkspaceToolArgs) -> Result<Value> {
@@ -80,10 +104,59 @@ impl WorkspaceTool {
             WsAction::Detect => self.detect(&args).await,
             WsAction::Capabilities => self.capabilities().await,
             WsAction::Schema => self.schema(&args).await,
+            WsAction::Roots => Ok(self.roots_list(&args)),
+            WsAction::Switch => self.switch(&args),
+            WsAction::Add => self.add(&args),
+            WsAction::Remove => self.remove(&args),
             WsAction::Help => Ok(self.help()),
         }
     }
 
+    fn roots_list(&self, args: &WorkspaceToolArgs) -> Value {
+        let roots = self.roots.list(args.session_id.as_deref());
+        json!({
+            "ok": true,
+            "data": { "roots": roots, "count": roots.len() },
+            "error": null,
+            "meta": { "tool": "workspace", "action": "roots" }
+        })
+    }
+
+    fn switch(&self, args: &WorkspaceToolArgs) -> Result<Value> {
+        let target = args.name.as_deref().or(args.path.as_deref())
+            .ok_or_else(|| ToolError::invalid("name or path required"))?;
+        let root = self.roots.switch(target, args.session_id.as_deref())?;
+        Ok(json!({
+            "ok": true,
+            "data": { "active": root },
+            "error": null,
+            "meta": { "tool": "workspace", "action": "switch" }
+        }))
+    }
+
+    fn add(&self, args: &WorkspaceToolArgs) -> Result<Value> {
+        let path = args.path.as_deref().ok_or_else(|| ToolError::invalid("path required"))?;
+        let root = self.roots.add(path, args.name.clone(), RootSource::Added)?;
+        Ok(json!({
+            "ok": true,
+            "data": { "root": root },
+            "error": null,
+            "meta": { "tool": "workspace", "action": "add" }
+        }))
+    }
+
+    fn remove(&self, args: &WorkspaceToolArgs) -> Result<Value> {
+        let target = args.name.as_deref().or(args.path.as_deref())
+            .ok_or_else(|| ToolError::invalid("name or path required"))?;
+        let root = self.roots.remove(target, args.session_id.as_deref())?;
+        Ok(json!({
+            "ok": true,
+            "data": { "removed": root },
+            "error": null,
+            "meta": { "tool": "workspace", "action": "remove" }
+        }))
+    }
+
     async fn detect(&self, args: &WorkspaceToolArgs) -> Result<Value> {
         let root = args.path.as_deref().unwrap_or(".");
         let root = Path::new(root);
@@ -225,6 +298,10 @@ impl WorkspaceTool {
                     "detect": "Detect project languages, build systems, VCS, test frameworks",
                     "capabilities": "List available system tools and runtimes",
                     "schema": "Extract project manifest schemas (package.json, Cargo.toml, etc.)",
+                    "roots": "List project roots and which one is active",
+                    "switch": "Make a root (by name or path) active for this session",
+                    "add": "Add a directory as a project root",
+                    "remove": "Remove a root added by config, client or add",
                     "help": "Show tool help"
                 }
             },
@@ -234,6 +311,12 @@ impl WorkspaceTool {
     }
 }
 
+impl Default for WorkspaceTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,188 @@
+/// Per-call output budgets
+///
+/// exec, fs, search and browser calls may pass `max_bytes` or
+/// `max_output_tokens`. A result over budget has its largest field cut the
+/// way that keeps the useful part: head and tail of command output, the
+/// leading (best ranked) items of a list, the start of other text. The cut
+/// is marked with `truncated: true` and a `truncation` record whose cursor
+/// pages through the full field with the `page` tool.
+
+use crate::pagination::{self, Pager};
+use serde_json::{json, Value};
+
+/// Tools whose calls take a budget
+pub const BUDGET_TOOLS: &[&str] = &["exec", "fs", "search", "browser"];
+
+/// Rough size of a token in bytes of JSON text
+const BYTES_PER_TOKEN: u64 = 4;
+
+/// Room the cut field keeps however large the rest of the result is
+const MIN_FIELD_BYTES: usize = 256;
+
+/// Bytes the truncation record itself adds to a result
+const RECORD_BYTES: usize = 320;
+
+/// Fields holding command output, which is cut in the middle
+const LOG_FIELDS: &[&str] = &["stdout", "stderr", "output", "logs", "log"];
+
+/// Byte budget a call asked for, taken out of its params
+pub fn take_budget(tool: &str, params: &mut Value) -> Option<usize> {
+    if !BUDGET_TOOLS.contains(&tool) {
+        return None;
+    }
+    let params = params.as_object_mut()?;
+    let bytes = params.remove("max_bytes").and_then(|v| v.as_u64());
+    let tokens = params.remove("max_output_tokens").and_then(|v| v.as_u64());
+    [bytes, tokens.map(|t| t.saturating_mul(BYTES_PER_TOKEN))]
+        .into_iter()
+        .flatten()
+        .min()
+        .map(|b| b as usize)
+}
+
+/// `content` cut to about `budget` bytes, keeping the rest behind a cursor
+/// for `session`
+pub fn fit(tool: &str, content: Value, budget: usize, pager: &Pager, session: Option<&str>) -> Value {
+    let size = content.to_string().len();
+    if size <= budget || !content.is_object() {
+        return content;
+    }
+    let Some((pointer, field_size)) = pagination::largest(&content, String::new()) else { return content };
+    let room = budget.saturating_sub(size - field_size + RECORD_BYTES).max(MIN_FIELD_BYTES);
+    let mut cut = content.clone();
+    let Some(field) = cut.pointer_mut(&pointer) else { return content };
+    let key = pointer.rsplit('/').next().unwrap_or("");
+
+    let (strategy, unit, total, kept, resume) = match field {
+        Value::Array(items) => {
+            let total = items.len();
+            let mut used = 0;
+            let count = items.iter().take_while(|item| {
+                used += item.to_string().len() + 1;
+                used <= room
+            }).count();
+            items.truncate(count);
+            ("top_n", "items", total, count, count)
+        }
+        Value::String(text) => {
+            let total = text.chars().count();
+            // Room is in serialized bytes; escapes make those outnumber raw ones
+            let room = room * text.len() / field_size.max(1);
+            let (shortened, kind) = if tool == "exec" || LOG_FIELDS.contains(&key) {
+                (head_tail(text, room), "head_tail")
+            } else {
+                (head(text, room), "head")
+            };
+            let Some((shortened, resume)) = shortened else { return content };
+            let kept = shortened.chars().count();
+            *text = shortened;
+            (kind, "chars", total, kept, resume)
+        }
+        _ => return content,
+    };
+
+    let id = pager.keep(tool, content, &pointer, session);
+    let cursor = Pager::cursor(id, resume);
+    cut["truncated"] = json!(true);
+    cut["truncation"] = json!({
+        "field": pointer,
+        "strategy": strategy,
+        "unit": unit,
+        "total": total,
+        "kept": kept,
+        "budget_bytes": budget,
+        "original_bytes": size,
+        "cursor": cursor,
+        "hint": format!("Call page with cursor {} for the {} from {} on", cursor, unit, resume)
+    });
+    cut
+}
+
+/// Largest char boundary at or below `index`
+fn floor_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Start of `text` within `room` bytes, ending at a line break when one
+/// is near; with the char offset where the cut begins
+fn head(text: &str, room: usize) -> Option<(String, usize)> {
+    if text.len() <= room {
+        return None;
+    }
+    let mut end = floor_boundary(text, room);
+    if let Some(newline) = text[..end].rfind('\n').filter(|&n| n >= end / 2) {
+        end = newline + 1;
+    }
+    let resume = text[..end].chars().count();
+    Some((format!("{}\n... [{} more bytes]", &text[..end], text.len() - end), resume))
+}
+
+/// First and last lines of `text` within `room` bytes, the middle replaced
+/// by a marker; with the char offset where the omitted part begins
+fn head_tail(text: &str, room: usize) -> Option<(String, usize)> {
+    if text.len() <= room {
+        return None;
+    }
+    let mut head_end = floor_boundary(text, room * 3 / 5);
+    if let Some(newline) = text[..head_end].rfind('\n').filter(|&n| n >= head_end / 2) {
+        head_end = newline + 1;
+    }
+    let mut tail_start = floor_boundary(text, text.len() - (room - room * 3 / 5));
+    if let Some(newline) = text[tail_start..].find('\n').filter(|&n| tail_start + n + 1 < text.len()) {
+        tail_start += newline + 1;
+    }
+    let tail_start = tail_start.max(head_end);
+    let resume = text[..head_end].chars().count();
+    Some((
+        format!("{}... [{} bytes omitted] ...\n{}", &text[..head_end], tail_start - head_end, &text[tail_start..]),
+        resume,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PaginationConfig;
+
+    #[test]
+    fn test_fit() {
+        let pager = Pager::new(PaginationConfig::default());
+        let log: String = (1..=2000).map(|i| format!("line {}\n", i)).collect();
+        let cut = fit("exec", json!({ "stdout": log, "exit_code": 0 }), 1000, &pager, None);
+        let stdout = cut["stdout"].as_str().unwrap();
+        assert!(stdout.starts_with("line 1\n"));
+        assert!(stdout.ends_with("line 2000\n"));
+        assert!(stdout.contains("bytes omitted"));
+        assert!(cut.to_string().len() <= 1000, "{}", cut.to_string().len());
+        assert_eq!(cut["truncated"], true);
+        assert_eq!(cut["truncation"]["strategy"], "head_tail");
+
+        // The cursor resumes right where the head stopped
+        let resume = cut["truncation"]["cursor"].as_str().unwrap();
+        let rest = pager.page(resume, None).unwrap();
+        let head_lines = stdout.split("... [").next().unwrap();
+        assert!(log[head_lines.len()..].starts_with(rest["stdout"].as_str().unwrap()));
+
+        let matches: Vec<Value> = (0..500).map(|i| json!({ "file": "a.rs", "line": i })).collect();
+        let cut = fit("search", json!({ "data": { "matches": matches } }), 2000, &pager, None);
+        assert_eq!(cut["truncation"]["strategy"], "top_n");
+        assert_eq!(cut["data"]["matches"][0]["line"], 0);
+        assert_eq!(cut["truncation"]["kept"], cut["data"]["matches"].as_array().unwrap().len());
+
+        let small = json!({ "content": "short" });
+        assert_eq!(fit("fs", small.clone(), 1000, &pager, None), small);
+    }
+
+    #[test]
+    fn test_take_budget() {
+        let mut params = json!({ "action": "read", "max_bytes": 9000, "max_output_tokens": 1000 });
+        assert_eq!(take_budget("fs", &mut params), Some(4000));
+        assert_eq!(params, json!({ "action": "read" }));
+        let mut params = json!({ "action": "show", "max_bytes": 10 });
+        assert_eq!(take_budget("plan", &mut params), None);
+    }
+}
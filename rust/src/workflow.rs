@@ -0,0 +1,201 @@
+/// Declarative multi-step workflows
+///
+/// A workflow is a named list of tool calls kept in the config (or passed
+/// inline) that the `workflow` tool runs in order within the caller's
+/// session. Step args are Handlebars templates over the caller's inputs
+/// and the outcomes of earlier steps, and a step's `when` template decides
+/// whether it runs at all. A failing step stops the workflow unless it is
+/// marked `continue_on_error`; the result lists every step's outcome.
+
+use crate::config::{Workflow, WorkflowStep};
+use crate::error::ToolError;
+use crate::tools::fs_template::{self, Engine};
+use anyhow::Result;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Steps one workflow may hold
+pub const MAX_STEPS: usize = 100;
+
+/// Tools a step may not call
+const FORBIDDEN_TOOLS: &[&str] = &["workflow"];
+
+/// Check a workflow before it is stored or run
+pub fn check(name: &str, workflow: &Workflow) -> Result<()> {
+    if workflow.steps.is_empty() {
+        return Err(ToolError::invalid(format!("Workflow {} has no steps", name)).into());
+    }
+    if workflow.steps.len() > MAX_STEPS {
+        return Err(ToolError::invalid(format!("Workflow {} has {} steps, at most {} are allowed", name, workflow.steps.len(), MAX_STEPS)).into());
+    }
+    let mut ids = Vec::new();
+    for (index, step) in workflow.steps.iter().enumerate() {
+        if step.tool.is_empty() || FORBIDDEN_TOOLS.contains(&step.tool.as_str()) {
+            return Err(ToolError::invalid(format!("Step {} of workflow {} cannot call {:?}", index, name, step.tool)).into());
+        }
+        if let Some(id) = &step.id {
+            if ids.contains(&id) {
+                return Err(ToolError::invalid(format!("Workflow {} has two steps with id {}", name, id)).into());
+            }
+            ids.push(id);
+        }
+    }
+    Ok(())
+}
+
+/// The caller's inputs with defaults filled in; a missing required input
+/// or one the workflow does not declare is an error
+pub fn inputs(name: &str, workflow: &Workflow, given: &Value) -> Result<Value> {
+    let given = match given {
+        Value::Null => Map::new(),
+        Value::Object(map) => map.clone(),
+        _ => return Err(ToolError::invalid("inputs must be an object").into()),
+    };
+    if let Some(unknown) = given.keys().find(|key| !workflow.inputs.contains_key(*key)) {
+        return Err(ToolError::invalid(format!("Workflow {} takes no input {}", name, unknown)).into());
+    }
+    let mut values = given;
+    for (key, input) in &workflow.inputs {
+        if values.contains_key(key) {
+            continue;
+        }
+        match &input.default {
+            Some(default) => {
+                values.insert(key.clone(), default.clone());
+            }
+            None => return Err(ToolError::invalid(format!("Workflow {} needs input {}", name, key)).into()),
+        }
+    }
+    Ok(Value::Object(values))
+}
+
+/// Variables templates see while a workflow runs
+pub struct Context {
+    inputs: Value,
+    steps: HashMap<String, Value>,
+    previous: Value,
+}
+
+impl Context {
+    pub fn new(inputs: Value) -> Self {
+        Self { inputs, steps: HashMap::new(), previous: Value::Null }
+    }
+
+    /// Record a step's outcome for the steps after it
+    pub fn record(&mut self, step: &WorkflowStep, outcome: Value) {
+        if let Some(id) = &step.id {
+            self.steps.insert(id.clone(), outcome.clone());
+        }
+        self.previous = outcome;
+    }
+
+    fn variables(&self) -> Value {
+        json!({ "inputs": self.inputs, "steps": self.steps, "previous": self.previous })
+    }
+
+    /// Whether the step's condition holds
+    pub fn admits(&self, step: &WorkflowStep) -> Result<bool> {
+        let Some(when) = &step.when else { return Ok(true) };
+        Ok(truthy(&render_string(when, &self.variables())?))
+    }
+
+    /// The step's args with every template rendered
+    pub fn args(&self, step: &WorkflowStep) -> Result<Value> {
+        render(&step.args, &self.variables())
+    }
+}
+
+fn render(value: &Value, variables: &Value) -> Result<Value> {
+    Ok(match value {
+        Value::String(template) => render_string(template, variables)?,
+        Value::Array(items) => Value::Array(items.iter().map(|item| render(item, variables)).collect::<Result<_>>()?),
+        Value::Object(map) => Value::Object(map.iter()
+            .map(|(key, item)| Ok((key.clone(), render(item, variables)?)))
+            .collect::<Result<_>>()?),
+        other => other.clone(),
+    })
+}
+
+/// A lone `{{path}}` yields the value it names, anything else the
+/// rendered text
+fn render_string(template: &str, variables: &Value) -> Result<Value> {
+    if let Some(path) = lone_variable(template) {
+        return lookup(variables, path)
+            .cloned()
+            .ok_or_else(|| ToolError::invalid(format!("Template error: {} is not defined", path)).into());
+    }
+    if !template.contains("{{") {
+        return Ok(json!(template));
+    }
+    fs_template::render(Engine::Handlebars, template, variables).map(Value::String)
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !matches!(s.trim().to_lowercase().as_str(), "" | "false" | "0" | "null"),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn lone_variable(template: &str) -> Option<&str> {
+    let path = template.trim().strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    let simple = !path.is_empty() && path.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '-');
+    simple.then_some(path)
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WorkflowInput;
+
+    fn step(id: Option<&str>, args: Value, when: Option<&str>) -> WorkflowStep {
+        WorkflowStep { id: id.map(str::to_string), tool: "think".into(), args, when: when.map(str::to_string), continue_on_error: false }
+    }
+
+    #[test]
+    fn test_templates() {
+        let workflow = Workflow {
+            inputs: HashMap::from([
+                ("branch".to_string(), WorkflowInput::default()),
+                ("limit".to_string(), WorkflowInput { default: Some(json!(5)), ..Default::default() }),
+            ]),
+            steps: vec![step(None, json!({}), None)],
+            ..Default::default()
+        };
+        assert!(inputs("w", &workflow, &json!({})).is_err());
+        assert!(inputs("w", &workflow, &json!({ "branch": "main", "other": 1 })).is_err());
+        let given = inputs("w", &workflow, &json!({ "branch": "main" })).unwrap();
+        assert_eq!(given["limit"], 5);
+
+        let mut context = Context::new(given);
+        let tests = step(Some("tests"), json!({}), None);
+        context.record(&tests, json!({ "success": true, "result": { "failed": 2, "names": ["a", "b"] } }));
+
+        let args = context.args(&step(None, json!({
+            "limit": "{{inputs.limit}}",
+            "names": ["{{ steps.tests.result.names }}"],
+            "thought": "{{steps.tests.result.failed}} failed on {{inputs.branch}}: {{#each steps.tests.result.names}}{{this}} {{/each}}",
+            "plain": "text"
+        }), None)).unwrap();
+        assert_eq!(args["limit"], 5);
+        assert_eq!(args["names"], json!([["a", "b"]]));
+        assert_eq!(args["thought"], "2 failed on main: a b ");
+        assert_eq!(args["plain"], "text");
+        assert!(context.args(&step(None, json!({ "x": "{{steps.lint.success}}" }), None)).is_err());
+
+        assert!(context.admits(&step(None, json!({}), Some("{{steps.tests.result.failed}}"))).unwrap());
+        assert!(!context.admits(&step(None, json!({}), Some("{{not previous.success}}"))).unwrap());
+        assert!(context.admits(&step(None, json!({}), None)).unwrap());
+    }
+}
@@ -627,3 +627,361 @@ async fn test_memory_scope_parsing() {
         assert!(result.is_ok());
     }
 }
+
+/// Test filtering recall and list by namespace, tags, metadata and dates
+#[tokio::test]
+async fn test_memory_filtered_recall() {
+    let tool = MemoryTool::new();
+
+    let mut metadata = std::collections::HashMap::new();
+    metadata.insert("source".to_string(), json!("review"));
+    let create = |statement: &str, namespace: &str, tags: &[&str]| MemoryToolArgs {
+        action: "create".to_string(),
+        statement: Some(statement.to_string()),
+        namespace: Some(namespace.to_string()),
+        tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+        ..Default::default()
+    };
+    tool.execute(create("Deploy with blue/green", "ops", &["deploy", "Infra"])).await.unwrap();
+    tool.execute(create("Deploy docs on merge", "docs", &["deploy"])).await.unwrap();
+    tool.execute(MemoryToolArgs {
+        metadata: Some(metadata.clone()),
+        ..create("Rollback needs approval", "ops", &["infra"])
+    }).await.unwrap();
+
+    // Namespace + tag filter narrows a keyword query
+    let args = MemoryToolArgs {
+        action: "recall".to_string(),
+        query: Some("deploy".to_string()),
+        namespace: Some("ops".to_string()),
+        tags: Some(vec!["infra".to_string()]),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["results"][0]["content"], "Deploy with blue/green");
+
+    // Filters alone are enough for recall
+    let args = MemoryToolArgs {
+        action: "recall".to_string(),
+        where_metadata: Some(metadata),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["results"][0]["content"], "Rollback needs approval");
+
+    // Date ranges exclude memories created outside them
+    let args = MemoryToolArgs {
+        action: "list".to_string(),
+        until: Some("2000-01-01".to_string()),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 0);
+
+    let args = MemoryToolArgs {
+        action: "list".to_string(),
+        tags: Some(vec!["deploy".to_string(), "infra".to_string()]),
+        tag_match: Some("any".to_string()),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 3);
+}
+
+/// Test bulk retagging
+#[tokio::test]
+async fn test_memory_retag() {
+    let tool = MemoryTool::new();
+    let args = MemoryToolArgs {
+        action: "create".to_string(),
+        statements: Some(vec!["First".to_string(), "Second".to_string()]),
+        tags: Some(vec!["todo".to_string()]),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    let ids: Vec<String> = json["ids"].as_array().unwrap().iter().map(|v| v.as_str().unwrap().to_string()).collect();
+
+    let args = MemoryToolArgs {
+        action: "retag".to_string(),
+        tag: Some("todo".to_string()),
+        new_tag: Some("backlog".to_string()),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["retagged"], 2);
+
+    let args = MemoryToolArgs {
+        action: "untag".to_string(),
+        ids: Some(ids),
+        tags: Some(vec!["backlog".to_string()]),
+        ..Default::default()
+    };
+    tool.execute(args).await.unwrap();
+
+    let args = MemoryToolArgs {
+        action: "list".to_string(),
+        tags: Some(vec!["backlog".to_string()]),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 0);
+}
+
+/// Test expired and superseded memories drop out of recall
+#[tokio::test]
+async fn test_memory_expiry_and_supersede() {
+    let tool = MemoryTool::new();
+    let create = |statement: &str| MemoryToolArgs {
+        action: "create".to_string(),
+        statement: Some(statement.to_string()),
+        ..Default::default()
+    };
+    tool.execute(MemoryToolArgs {
+        expires_at: Some("2001-01-01".to_string()),
+        ..create("Staging host is db-old")
+    }).await.unwrap();
+    tool.execute(MemoryToolArgs { ttl_seconds: Some(3600), ..create("Staging host is db-temp") }).await.unwrap();
+    assert!(tool.execute(MemoryToolArgs { ttl_seconds: Some(u64::MAX), ..create("Staging host is db-forever") }).await.is_err());
+    let json: serde_json::Value =
+        serde_json::from_str(&tool.execute(create("Staging host is db-1")).await.unwrap()).unwrap();
+    let old_id = json["ids"][0].as_str().unwrap().to_string();
+    tool.execute(MemoryToolArgs {
+        supersedes: Some(vec![old_id]),
+        ..create("Staging host is db-2")
+    }).await.unwrap();
+
+    let args = MemoryToolArgs {
+        action: "recall".to_string(),
+        query: Some("staging host".to_string()),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    let contents: Vec<&str> = json["results"].as_array().unwrap().iter().map(|r| r["content"].as_str().unwrap()).collect();
+    assert_eq!(contents.len(), 2);
+    assert!(contents.contains(&"Staging host is db-2"));
+    assert!(contents.contains(&"Staging host is db-temp"));
+}
+
+/// Test compact merges near-duplicates and prunes expired memories
+#[tokio::test]
+async fn test_memory_compact() {
+    let tool = MemoryTool::new();
+    for (statement, tag) in [
+        ("The API uses bearer tokens for auth", "api"),
+        ("The API uses bearer tokens for auth.", "security"),
+        ("Frontend is built with Svelte", "web"),
+    ] {
+        tool.execute(MemoryToolArgs {
+            action: "create".to_string(),
+            statement: Some(statement.to_string()),
+            tags: Some(vec![tag.to_string()]),
+            ..Default::default()
+        }).await.unwrap();
+    }
+    tool.execute(MemoryToolArgs {
+        action: "create".to_string(),
+        statement: Some("Temporary note".to_string()),
+        expires_at: Some("2001-01-01".to_string()),
+        ..Default::default()
+    }).await.unwrap();
+
+    let args = MemoryToolArgs { action: "compact".to_string(), ..Default::default() };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["merged"].as_array().unwrap().len(), 1);
+    assert_eq!(json["expired"].as_array().unwrap().len(), 1);
+    assert_eq!(json["remaining"], 2);
+
+    let args = MemoryToolArgs {
+        action: "list".to_string(),
+        tags: Some(vec!["api".to_string(), "security".to_string()]),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 1);
+}
+
+/// Test exporting to Markdown/JSONL and importing with scope mapping and conflicts
+#[tokio::test]
+async fn test_memory_export_import_round_trip() {
+    let source = MemoryTool::new();
+    source.execute(MemoryToolArgs {
+        action: "create".to_string(),
+        statements: Some(vec!["Release on Fridays".to_string(), "CI runs on push".to_string()]),
+        scope: Some("session".to_string()),
+        tags: Some(vec!["process".to_string()]),
+        ..Default::default()
+    }).await.unwrap();
+    source.execute(MemoryToolArgs {
+        action: "facts".to_string(),
+        kb_name: Some("coding".to_string()),
+        facts: Some(vec!["Use uv for Python".to_string()]),
+        ..Default::default()
+    }).await.unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("memory.md");
+    for format in ["markdown", "jsonl"] {
+        let args = MemoryToolArgs {
+            action: "export".to_string(),
+            format: Some(format.to_string()),
+            path: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let json: serde_json::Value = serde_json::from_str(&source.execute(args).await.unwrap()).unwrap();
+        assert_eq!(json["count"], 2);
+
+        let target = MemoryTool::new();
+        let mut scope_map = std::collections::HashMap::new();
+        scope_map.insert("session".to_string(), "project".to_string());
+        let import = MemoryToolArgs {
+            action: "import".to_string(),
+            path: Some(path.to_string_lossy().to_string()),
+            scope_map: Some(scope_map),
+            ..Default::default()
+        };
+        let json: serde_json::Value = serde_json::from_str(&target.execute(import.clone()).await.unwrap()).unwrap();
+        assert_eq!(json["format"], format);
+        assert_eq!(json["imported"], 2);
+        assert_eq!(json["facts_imported"], 1);
+
+        // Importing again skips everything by default
+        let json: serde_json::Value = serde_json::from_str(&target.execute(import).await.unwrap()).unwrap();
+        assert_eq!(json["imported"], 0);
+        assert_eq!(json["skipped"], 2);
+        assert_eq!(json["facts_skipped"], 1);
+
+        let args = MemoryToolArgs {
+            action: "list".to_string(),
+            scope: Some("project".to_string()),
+            tags: Some(vec!["process".to_string()]),
+            ..Default::default()
+        };
+        let json: serde_json::Value = serde_json::from_str(&target.execute(args).await.unwrap()).unwrap();
+        assert_eq!(json["count"], 2);
+    }
+}
+
+/// Test duplicate and contradicting facts are flagged and optionally superseded
+#[tokio::test]
+async fn test_facts_dedup_and_contradictions() {
+    let tool = MemoryTool::new();
+    let store = |facts: &[&str], auto_supersede: bool| MemoryToolArgs {
+        action: "facts".to_string(),
+        kb_name: Some("infra".to_string()),
+        facts: Some(facts.iter().map(|f| f.to_string()).collect()),
+        auto_supersede: Some(auto_supersede),
+        ..Default::default()
+    };
+    tool.execute(store(&["The default port is 8080", "Logs go to stdout"], false)).await.unwrap();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&tool.execute(store(&["Logs go to stdout.", "The default port is 9090"], false)).await.unwrap()).unwrap();
+    assert_eq!(json["stored"], 1);
+    assert_eq!(json["duplicates"][0]["existing"], "Logs go to stdout");
+    assert_eq!(json["contradictions"][0]["existing"], "The default port is 8080");
+    assert_eq!(json["superseded"].as_array().unwrap().len(), 0);
+
+    let json: serde_json::Value =
+        serde_json::from_str(&tool.execute(store(&["The default port is 7070"], true)).await.unwrap()).unwrap();
+    assert_eq!(json["contradictions"].as_array().unwrap().len(), 2);
+    assert_eq!(json["superseded"].as_array().unwrap().len(), 2);
+
+    let args = MemoryToolArgs {
+        action: "facts".to_string(),
+        kb_name: Some("infra".to_string()),
+        query: Some("default port".to_string()),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["results"][0]["content"], "The default port is 7070");
+}
+
+/// Test session memories are isolated per MCP session and dropped when it ends
+#[tokio::test]
+async fn test_session_memory_isolation() {
+    let tool = MemoryTool::new();
+    for (session, statement) in [("alpha", "Alpha is debugging the parser"), ("beta", "Beta is writing docs")] {
+        let args = MemoryToolArgs {
+            action: "create".to_string(),
+            statements: Some(vec![statement.to_string()]),
+            scope: Some("session".to_string()),
+            session_id: Some(session.to_string()),
+            ..Default::default()
+        };
+        tool.execute(args).await.unwrap();
+    }
+    let list = |session: Option<&str>| MemoryToolArgs {
+        action: "list".to_string(),
+        scope: Some("session".to_string()),
+        session_id: session.map(str::to_string),
+        ..Default::default()
+    };
+
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(list(Some("alpha"))).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["memories"][0]["content"], "Alpha is debugging the parser");
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(list(None)).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 0);
+
+    // Another session cannot delete what it cannot see
+    let args = MemoryToolArgs {
+        action: "delete".to_string(),
+        ids: Some(vec!["mem_1".to_string()]),
+        session_id: Some("beta".to_string()),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(args).await.unwrap()).unwrap();
+    assert_eq!(json["deleted"], 0);
+
+    let ended = tool.end_session("alpha", false).await.unwrap();
+    assert_eq!(ended["dropped"], 1);
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(list(Some("alpha"))).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 0);
+    let json: serde_json::Value = serde_json::from_str(&tool.execute(list(Some("beta"))).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 1);
+}
+
+/// Test global memories are shared between tools using the same store
+#[tokio::test]
+async fn test_global_store_shared_between_instances() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("global.json");
+    let first = MemoryTool::new().with_global_store(&path);
+    let second = MemoryTool::new().with_global_store(&path);
+
+    let create = |statement: &str, scope: &str| MemoryToolArgs {
+        action: "create".to_string(),
+        statement: Some(statement.to_string()),
+        scope: Some(scope.to_string()),
+        ..Default::default()
+    };
+    first.execute(create("Only this window", "project")).await.unwrap();
+    first.execute(create("Use rustfmt defaults", "global")).await.unwrap();
+    second.execute(create("Prefer small commits", "global")).await.unwrap();
+
+    let list = MemoryToolArgs {
+        action: "list".to_string(),
+        ..Default::default()
+    };
+    let json: serde_json::Value = serde_json::from_str(&first.execute(list.clone()).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 3);
+    let json: serde_json::Value = serde_json::from_str(&second.execute(list.clone()).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 2);
+    let ids: Vec<&str> = json["memories"].as_array().unwrap().iter().map(|m| m["id"].as_str().unwrap()).collect();
+    assert_eq!(ids.len(), 2);
+    assert_ne!(ids[0], ids[1]);
+
+    // Deletes propagate too
+    let args = MemoryToolArgs {
+        action: "delete".to_string(),
+        ids: Some(ids.iter().map(|id| id.to_string()).collect()),
+        ..Default::default()
+    };
+    second.execute(args).await.unwrap();
+    let json: serde_json::Value = serde_json::from_str(&first.execute(list).await.unwrap()).unwrap();
+    assert_eq!(json["count"], 1);
+    assert_eq!(json["memories"][0]["content"], "Only this window");
+}